@@ -0,0 +1,254 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::config::{RouteRule, Settings};
+
+/// 访问日志可记录的字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Method,
+    Path,
+    Query,
+    Status,
+    DurationMs,
+    ClientIp,
+    UserAgent,
+    Referer,
+    RequestId,
+    Route,
+    ResponseBytes,
+}
+
+impl Field {
+    pub const ALL: [Field; 11] = [
+        Field::Method,
+        Field::Path,
+        Field::Query,
+        Field::Status,
+        Field::DurationMs,
+        Field::ClientIp,
+        Field::UserAgent,
+        Field::Referer,
+        Field::RequestId,
+        Field::Route,
+        Field::ResponseBytes,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Field::Method => "method",
+            Field::Path => "path",
+            Field::Query => "query",
+            Field::Status => "status",
+            Field::DurationMs => "duration_ms",
+            Field::ClientIp => "client_ip",
+            Field::UserAgent => "user_agent",
+            Field::Referer => "referer",
+            Field::RequestId => "request_id",
+            Field::Route => "route",
+            Field::ResponseBytes => "response_bytes",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.as_str() == s)
+    }
+
+    fn bit(&self) -> u16 {
+        1 << (*self as u16)
+    }
+}
+
+/// 路由级访问日志配置，未配置的项沿用全局设置
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AccessLogPolicy {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    // 采样比例 0~1；5xx 响应始终记录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fields: Option<Vec<String>>,
+}
+
+impl AccessLogPolicy {
+    pub fn validation_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.sample_rate.is_some_and(|rate| !(0.0..=1.0).contains(&rate)) {
+            errors.push("access_log.sample_rate必须在0到1之间".to_string());
+        }
+        errors.extend(fields_errors("access_log.fields", self.fields.iter().flatten()));
+        errors
+    }
+}
+
+/// 校验字段名
+pub fn fields_errors<'a>(field: &str, names: impl IntoIterator<Item = &'a String>) -> Vec<String> {
+    names
+        .into_iter()
+        .filter(|name| Field::parse(name).is_none())
+        .map(|name| format!("{}包含未知字段: {}", field, name))
+        .collect()
+}
+
+/// 合并全局与路由配置后的访问日志策略
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sampling {
+    pub sample_rate: f64,
+    fields: u16,
+}
+
+impl Sampling {
+    /// 未启用访问日志时返回 None
+    pub fn resolve(settings: &Settings, rule: Option<&RouteRule>) -> Option<Self> {
+        let policy = rule.and_then(|r| r.access_log.as_ref());
+        let enabled = policy
+            .map(|p| p.enabled.unwrap_or(true))
+            .unwrap_or(settings.access_log.unwrap_or(false));
+        if !enabled {
+            return None;
+        }
+        let sample_rate = policy
+            .and_then(|p| p.sample_rate)
+            .or(settings.access_log_sample_rate)
+            .unwrap_or(1.0);
+        let names = policy.and_then(|p| p.fields.as_ref()).unwrap_or(&settings.access_log_fields);
+        let fields = if names.is_empty() {
+            Field::ALL.iter().fold(0, |acc, f| acc | f.bit())
+        } else {
+            names.iter().filter_map(|n| Field::parse(n)).fold(0, |acc, f| acc | f.bit())
+        };
+        Some(Self { sample_rate, fields })
+    }
+
+    pub fn includes(&self, field: Field) -> bool {
+        self.fields & field.bit() != 0
+    }
+
+    fn pick<'a>(&self, field: Field, value: Option<&'a str>) -> Option<&'a str> {
+        value.filter(|_| self.includes(field))
+    }
+
+    fn sampled(&self, status: u16) -> bool {
+        status >= 500 || self.sample_rate >= 1.0 || rand::random::<f64>() < self.sample_rate
+    }
+}
+
+/// 请求到达时记录的信息，响应返回后输出
+pub struct Pending {
+    sampling: Sampling,
+    start: Instant,
+    method: String,
+    path: String,
+    query: Option<String>,
+    client_ip: String,
+    user_agent: Option<String>,
+    referer: Option<String>,
+    request_id: Option<String>,
+    route: Option<String>,
+}
+
+impl Pending {
+    pub fn new(sampling: Sampling, req: &Request, route: Option<String>) -> Self {
+        let header = |name| req.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+        Self {
+            sampling,
+            start: Instant::now(),
+            method: req.method().to_string(),
+            path: req.uri().path().to_string(),
+            query: req.uri().query().map(str::to_string),
+            client_ip: crate::rate_limit::client_ip(req).to_string(),
+            user_agent: header(header::USER_AGENT),
+            referer: header(header::REFERER),
+            request_id: header(header::HeaderName::from_static(crate::problem::REQUEST_ID_HEADER)),
+            route,
+        }
+    }
+
+    pub fn finish(self, resp: &Response<Body>) {
+        let status = resp.status().as_u16();
+        if !self.sampling.sampled(status) {
+            return;
+        }
+        let duration: Duration = self.start.elapsed();
+        let bytes = resp
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok());
+        let s = &self.sampling;
+        tracing::info!(
+            target: "helios::access",
+            method = s.pick(Field::Method, Some(&self.method)),
+            path = s.pick(Field::Path, Some(&self.path)),
+            query = s.pick(Field::Query, self.query.as_deref()),
+            status = s.includes(Field::Status).then_some(status),
+            duration_ms = s.includes(Field::DurationMs).then_some(duration.as_millis() as u64),
+            client_ip = s.pick(Field::ClientIp, Some(&self.client_ip)),
+            user_agent = s.pick(Field::UserAgent, self.user_agent.as_deref()),
+            referer = s.pick(Field::Referer, self.referer.as_deref()),
+            request_id = s.pick(Field::RequestId, self.request_id.as_deref()),
+            route = s.pick(Field::Route, self.route.as_deref()),
+            response_bytes = bytes.filter(|_| s.includes(Field::ResponseBytes)),
+            "access"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> Settings {
+        let mut settings: Settings = serde_json::from_value(serde_json::json!({
+            "gateway_bind": "0.0.0.0:8080",
+            "jwt_decoding_key": "k",
+            "global_qps": 1,
+            "client_qps": 1,
+        }))
+        .unwrap();
+        settings.access_log = Some(true);
+        settings.access_log_sample_rate = Some(0.1);
+        settings
+    }
+
+    #[test]
+    fn test_resolve_route_overrides() {
+        let settings = settings();
+        let global = Sampling::resolve(&settings, None).unwrap();
+        assert_eq!(global.sample_rate, 0.1);
+        assert!(global.includes(Field::Query));
+
+        let private = RouteRule {
+            access_log: Some(AccessLogPolicy {
+                fields: Some(vec!["method".to_string(), "path".to_string(), "status".to_string()]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let sampling = Sampling::resolve(&settings, Some(&private)).unwrap();
+        assert!(sampling.includes(Field::Path));
+        assert!(!sampling.includes(Field::Query));
+        assert_eq!(sampling.sample_rate, 0.1);
+
+        let off = RouteRule { access_log: Some(AccessLogPolicy { enabled: Some(false), ..Default::default() }), ..Default::default() };
+        assert_eq!(Sampling::resolve(&settings, Some(&off)), None);
+
+        // 5xx 不受采样影响
+        let none = Sampling { sample_rate: 0.0, fields: 0 };
+        assert!(none.sampled(502));
+        assert!(!none.sampled(200));
+    }
+
+    #[test]
+    fn test_policy_validation() {
+        let policy = AccessLogPolicy { sample_rate: Some(1.5), fields: Some(vec!["password".to_string()]), ..Default::default() };
+        assert_eq!(policy.validation_errors().len(), 2);
+    }
+}
@@ -0,0 +1,15 @@
+use axum::{extract::Query, Json};
+use serde::Deserialize;
+
+use crate::audit::{AuditEntry, CAPACITY};
+
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    // 返回的条数，默认 100
+    pub limit: Option<usize>,
+}
+
+// ===== 最近的审计记录（新记录在前） =====
+pub async fn list_audit(Query(query): Query<AuditQuery>) -> Json<Vec<AuditEntry>> {
+    Json(crate::audit::recent(query.limit.unwrap_or(100).min(CAPACITY)))
+}
@@ -0,0 +1,9 @@
+use axum::{Extension, Json};
+
+use crate::config::RouteRule;
+use crate::load_balancer::traffic::{self, TrafficSnapshot};
+
+// ===== 各负载均衡器的上游、权重、健康状态与累计请求（一致性快照，只读） =====
+pub async fn balancer_traffic(Extension(route_rules): Extension<Vec<RouteRule>>) -> Json<TrafficSnapshot> {
+    Json(traffic::snapshot(&route_rules))
+}
@@ -0,0 +1,24 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use serde::Deserialize;
+use crate::ban::{self, BanView};
+use crate::problem::{Problem, ProblemType};
+
+#[derive(Debug, Deserialize)]
+pub struct BanTarget {
+    // 如 ip:10.0.0.1 或 token:<摘要>
+    pub source: String,
+}
+
+pub async fn list_bans() -> Json<Vec<BanView>> {
+    Json(ban::list())
+}
+
+// ===== 解除封禁：同时清空该来源的计数 =====
+pub async fn lift_ban(Json(target): Json<BanTarget>) -> impl IntoResponse {
+    if !ban::lift(&target.source) {
+        return Problem::new(StatusCode::NOT_FOUND, ProblemType::NotFound)
+            .detail(format!("Ban not found: {}", target.source))
+            .into_response();
+    }
+    Json(ban::list()).into_response()
+}
@@ -0,0 +1,111 @@
+use axum::{Extension, Json};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use crate::config::{RouteRule, Settings};
+use crate::load_balancer::BalancerSnapshot;
+use crate::namespace::AdminScope;
+use crate::path_matcher::RoutePattern;
+use crate::rate_limit::RateLimits;
+
+#[derive(Debug, Serialize)]
+pub struct CompiledPrefix {
+    pub pattern: String,
+    // 通配/变量模式编译后的正则；普通前缀为 None
+    pub regex: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RouteDump {
+    #[serde(flatten)]
+    pub rule: RouteRule,
+    pub compiled: Vec<CompiledPrefix>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BalancerDump {
+    pub key: String,
+    #[serde(flatten)]
+    pub snapshot: BalancerSnapshot,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LimiterDump {
+    pub global_qps: u32,
+    pub client_qps: u32,
+    pub tenant_qps: Option<u32>,
+    pub user_qps: Option<u32>,
+    // 按键限流器当前跟踪的键数量
+    pub tracked_keys: BTreeMap<&'static str, usize>,
+    pub exempt_ip_ranges: usize,
+    pub exempt_api_keys: usize,
+    pub exempt_subjects: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfigDump {
+    pub version: &'static str,
+    pub settings: serde_json::Value,
+    pub routes: Vec<RouteDump>,
+    // 负载均衡器在首次命中路由时才会创建
+    pub balancers: Vec<BalancerDump>,
+    pub rate_limits: LimiterDump,
+}
+
+// ===== 当前生效配置（已脱敏） =====
+pub async fn config_dump(
+    Extension(settings): Extension<Settings>,
+    Extension(route_rules): Extension<Vec<RouteRule>>,
+    Extension(rate_limits): Extension<Arc<RateLimits>>,
+    scope: Option<Extension<AdminScope>>,
+) -> Json<ConfigDump> {
+    // 静态路由在前，之后是当前运行时发现的路由；命名空间令牌只能看到本命名空间的路由与负载均衡器，不含全局设置
+    let discovered = match scope {
+        Some(_) => Vec::new(),
+        None => crate::discovery::routes().to_vec(),
+    };
+    let balancer_keys = scope.is_some().then(|| crate::proxy::balancer_keys(&route_rules));
+    let routes = route_rules
+        .into_iter()
+        .chain(discovered)
+        .map(|rule| {
+            let compiled = rule
+                .prefix
+                .iter()
+                .map(|p| CompiledPrefix {
+                    pattern: p.clone(),
+                    regex: if p.contains('{') || p.contains('*') || p.contains('?') {
+                        RoutePattern::from_pattern(p).ok().map(|rp| rp.regex_str().to_string())
+                    } else {
+                        None
+                    },
+                })
+                .collect();
+            RouteDump { rule, compiled }
+        })
+        .collect();
+
+    let balancers = crate::proxy::balancer_snapshots()
+        .into_iter()
+        .filter(|(key, _)| balancer_keys.as_ref().is_none_or(|keys| keys.contains(key)))
+        .map(|(key, snapshot)| BalancerDump { key, snapshot })
+        .collect();
+
+    let exemptions = &rate_limits.exemptions;
+    Json(ConfigDump {
+        version: env!("CARGO_PKG_VERSION"),
+        settings: if scope.is_some() { serde_json::Value::Null } else { settings.redacted() },
+        routes,
+        balancers,
+        rate_limits: LimiterDump {
+            global_qps: settings.global_qps,
+            client_qps: settings.client_qps,
+            tenant_qps: settings.tenant_qps,
+            user_qps: settings.user_qps,
+            tracked_keys: rate_limits.tracked_keys().into_iter().collect(),
+            exempt_ip_ranges: exemptions.ip_range_count(),
+            exempt_api_keys: exemptions.api_key_count(),
+            exempt_subjects: exemptions.subject_count(),
+        },
+    })
+}
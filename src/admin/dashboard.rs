@@ -0,0 +1,87 @@
+use axum::{response::Html, Extension, Json};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use crate::config::RouteRule;
+use crate::metrics::{counter_samples, HTTP_COUNTER, RATE_LIMITED_COUNTER, UPSTREAM_COUNTER};
+
+/// 内嵌的管理面板页面
+pub async fn page() -> Html<&'static str> {
+    Html(include_str!("dashboard.html"))
+}
+
+#[derive(Debug, Serialize)]
+pub struct RouteView {
+    pub prefix: Vec<String>,
+    pub upstream: Vec<String>,
+    pub strategy: String,
+    pub whitelist: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct UpstreamView {
+    pub url: String,
+    pub ok: u64,
+    pub server_error: u64,
+    pub failed: u64,
+    pub draining: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Overview {
+    pub routes: Vec<RouteView>,
+    pub upstreams: Vec<UpstreamView>,
+    // 按状态码累计的请求数，面板侧通过两次轮询的差值计算近期错误率
+    pub responses_by_status: BTreeMap<String, u64>,
+    pub rate_limited: BTreeMap<String, u64>,
+}
+
+// ===== 面板数据：与 /metrics 使用同一份计数器 =====
+pub async fn overview(Extension(route_rules): Extension<Vec<RouteRule>>) -> Json<Overview> {
+    let routes = route_rules
+        .iter()
+        .map(|r| RouteView {
+            prefix: r.prefix.clone(),
+            upstream: r.upstream.clone(),
+            strategy: r.strategy.clone(),
+            whitelist: r.whitelist.clone().unwrap_or_default(),
+        })
+        .collect();
+
+    let mut upstreams: BTreeMap<String, UpstreamView> = BTreeMap::new();
+    // 先列出所有已配置的上游，保证未产生流量的节点也可见
+    for url in route_rules.iter().flat_map(|r| r.upstream.iter()) {
+        upstreams.entry(url.clone()).or_insert_with(|| UpstreamView { url: url.clone(), ..Default::default() });
+    }
+    for (labels, value) in counter_samples(&UPSTREAM_COUNTER) {
+        let url = labels.get("upstream").cloned().unwrap_or_default();
+        let view = upstreams.entry(url.clone()).or_insert_with(|| UpstreamView { url, ..Default::default() });
+        match labels.get("outcome").map(String::as_str) {
+            Some("ok") => view.ok += value,
+            Some("5xx") => view.server_error += value,
+            _ => view.failed += value,
+        }
+    }
+
+    let mut responses_by_status = BTreeMap::new();
+    for (labels, value) in counter_samples(&HTTP_COUNTER) {
+        let status = labels.get("status").cloned().unwrap_or_default();
+        *responses_by_status.entry(status).or_insert(0) += value;
+    }
+
+    let mut rate_limited = BTreeMap::new();
+    for (labels, value) in counter_samples(&RATE_LIMITED_COUNTER) {
+        let scope = labels.get("scope").cloned().unwrap_or_default();
+        *rate_limited.entry(scope).or_insert(0) += value;
+    }
+
+    for view in upstreams.values_mut() {
+        view.draining = !crate::upstream::is_routable(&view.url);
+    }
+
+    Json(Overview {
+        routes,
+        upstreams: upstreams.into_values().collect(),
+        responses_by_status,
+        rate_limited,
+    })
+}
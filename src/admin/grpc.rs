@@ -0,0 +1,39 @@
+use axum::{Extension, Json};
+use serde::Serialize;
+
+use crate::config::RouteRule;
+use crate::grpc_reflection::{self, ServiceInfo};
+
+#[derive(Debug, Serialize)]
+pub struct GrpcRouteReport {
+    pub route: String,
+    // 查询反射的上游（路由的第一个上游）
+    pub upstream: String,
+    pub services: Vec<ServiceInfo>,
+    // 没有对应任何方法的路由前缀
+    pub unmatched_prefixes: Vec<String>,
+    pub error: Option<String>,
+}
+
+// ===== 开启了 grpc_reflection 的路由：上游服务与方法，以及路由前缀校验（实时查询上游） =====
+pub async fn grpc_services(Extension(route_rules): Extension<Vec<RouteRule>>) -> Json<Vec<GrpcRouteReport>> {
+    let mut reports = Vec::new();
+    for rule in route_rules.iter().filter(|r| r.grpc_reflection == Some(true)) {
+        let Some(upstream) = rule.upstream.first() else {
+            continue;
+        };
+        let (services, error) = match grpc_reflection::inspect(upstream).await {
+            Ok(services) => (services, None),
+            Err(err) => (Vec::new(), Some(err)),
+        };
+        let unmatched_prefixes = if error.is_none() { grpc_reflection::unmatched_prefixes(rule, &services) } else { Vec::new() };
+        reports.push(GrpcRouteReport {
+            route: crate::proxy::route_label(rule),
+            upstream: upstream.clone(),
+            services,
+            unmatched_prefixes,
+            error,
+        });
+    }
+    Json(reports)
+}
@@ -0,0 +1,43 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use crate::logging;
+use crate::problem::{Problem, ProblemType};
+
+#[derive(Debug, Serialize)]
+pub struct LogFilterView {
+    pub filter: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LogFilterUpdate {
+    // EnvFilter 语法，如 "info,helios::proxy=debug"
+    pub filter: String,
+    // 到期后恢复为之前的规则；不填则永久生效
+    pub ttl_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogFilterUpdated {
+    pub filter: String,
+    pub previous: String,
+    pub ttl_secs: Option<u64>,
+}
+
+pub async fn get_log_filter() -> Json<LogFilterView> {
+    Json(LogFilterView { filter: logging::current_filter() })
+}
+
+pub async fn set_log_filter(Json(update): Json<LogFilterUpdate>) -> impl IntoResponse {
+    match logging::set_filter(&update.filter, update.ttl_secs.map(Duration::from_secs)) {
+        Ok(previous) => Json(LogFilterUpdated {
+            filter: update.filter,
+            previous,
+            ttl_secs: update.ttl_secs,
+        })
+        .into_response(),
+        Err(err) => Problem::new(StatusCode::BAD_REQUEST, ProblemType::BadRequest)
+            .detail(format!("Invalid log filter: {}", err))
+            .into_response(),
+    }
+}
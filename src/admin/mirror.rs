@@ -0,0 +1,17 @@
+use axum::{extract::Query, Json};
+use serde::Deserialize;
+
+use crate::mirror::{MirrorDiff, CAPACITY};
+
+#[derive(Debug, Deserialize)]
+pub struct MirrorDiffQuery {
+    // 只返回该路由（id，未配置 id 时为前缀列表）的样本
+    pub route: Option<String>,
+    // 返回的条数，默认 50
+    pub limit: Option<usize>,
+}
+
+// ===== 最近的镜像对比不一致样本（新样本在前） =====
+pub async fn list_mirror_diffs(Query(query): Query<MirrorDiffQuery>) -> Json<Vec<MirrorDiff>> {
+    Json(crate::mirror::recent(query.route.as_deref(), query.limit.unwrap_or(50).min(CAPACITY)))
+}
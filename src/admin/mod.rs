@@ -0,0 +1,162 @@
+pub mod audit;
+pub mod balancers;
+pub mod bans;
+pub mod config_dump;
+pub mod dashboard;
+pub mod grpc;
+pub mod log_filter;
+pub mod mirror;
+pub mod route_test;
+pub mod runtime;
+pub mod slo;
+pub mod tenant_upstreams;
+pub mod upstreams;
+pub mod weights;
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderMap, Response, StatusCode},
+    middleware::{self, Next},
+    response::IntoResponse,
+    routing::{get, post},
+    Router,
+};
+use base64::Engine;
+use crate::config::{RouteRule, Settings};
+use crate::namespace::AdminScope;
+use crate::problem::{Problem, ProblemType};
+
+// ===== 管理端路由 =====
+pub fn router() -> Router {
+    Router::new()
+        .route("/admin", get(dashboard::page))
+        .route("/admin/api/overview", get(dashboard::overview))
+        .route("/admin/api/config", get(config_dump::config_dump))
+        .route("/admin/api/route-test", post(route_test::route_test))
+        .route("/admin/api/runtime", get(runtime::runtime_stats))
+        .route("/admin/api/slo", get(slo::slo_status))
+        .route("/admin/api/audit", get(audit::list_audit))
+        .route("/admin/api/bans", get(bans::list_bans))
+        .route("/admin/api/bans/lift", post(bans::lift_ban))
+        .route("/admin/api/log-filter", get(log_filter::get_log_filter).put(log_filter::set_log_filter))
+        .route("/admin/api/upstreams", get(upstreams::list_upstreams))
+        .route("/admin/api/upstreams/drain", post(upstreams::drain_upstream))
+        .route("/admin/api/upstreams/enable", post(upstreams::enable_upstream))
+        .route("/admin/api/balancers", get(balancers::balancer_traffic))
+        .route("/admin/api/grpc", get(grpc::grpc_services))
+        .route("/admin/api/weights", get(weights::get_weights).put(weights::set_weight))
+        .route("/admin/api/mirror-diffs", get(mirror::list_mirror_diffs))
+        .route(
+            "/admin/api/tenant-upstreams",
+            get(tenant_upstreams::list_tenant_upstreams)
+                .put(tenant_upstreams::set_tenant_upstream)
+                .delete(tenant_upstreams::reset_tenant_upstream),
+        )
+        .route_layer(middleware::from_fn(require_admin))
+}
+
+// ===== 管理端鉴权中间件 =====
+/// 支持 `Authorization: Bearer <token>`，以及浏览器使用的 Basic 认证（密码为 token，用户名任意）。
+/// 命名空间令牌只能访问 NAMESPACE_ENDPOINTS，且请求中的路由表替换为该命名空间的路由
+async fn require_admin(mut req: Request, next: Next) -> Response<Body> {
+    let (token, namespace_tokens) = req
+        .extensions()
+        .get::<Settings>()
+        .map(|s| (s.admin_token.clone().filter(|t| !t.is_empty()), s.admin_namespace_tokens.clone()))
+        .unwrap_or_default();
+
+    // 未配置任何管理令牌时不暴露管理端
+    if token.is_none() && namespace_tokens.is_empty() {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap();
+    }
+
+    if token.is_some_and(|token| is_authorized(req.headers(), &token)) {
+        return next.run(req).await;
+    }
+
+    let namespace = namespace_tokens
+        .into_iter()
+        .find(|(_, token)| !token.is_empty() && is_authorized(req.headers(), token))
+        .map(|(namespace, _)| namespace);
+    let Some(namespace) = namespace else {
+        return Problem::new(StatusCode::UNAUTHORIZED, ProblemType::Unauthorized)
+            .detail("Admin token required")
+            .header(header::WWW_AUTHENTICATE, "Basic realm=\"helios-admin\"")
+            .into_response();
+    };
+
+    if !NAMESPACE_ENDPOINTS.contains(&req.uri().path()) {
+        return Problem::new(StatusCode::FORBIDDEN, ProblemType::Forbidden)
+            .detail(format!("Namespace token for {} cannot access {}", namespace, req.uri().path()))
+            .into_response();
+    }
+    let routes = req.extensions().get::<Vec<RouteRule>>().cloned().unwrap_or_default();
+    let (scope, routes) = AdminScope::new(&namespace, &routes);
+    req.extensions_mut().insert(routes);
+    req.extensions_mut().insert(scope);
+    next.run(req).await
+}
+
+/// 命名空间令牌可访问的端点：只涉及路由与其上游的查看和调整
+const NAMESPACE_ENDPOINTS: [&str; 7] = [
+    "/admin/api/config",
+    "/admin/api/route-test",
+    "/admin/api/upstreams",
+    "/admin/api/upstreams/drain",
+    "/admin/api/upstreams/enable",
+    "/admin/api/weights",
+    "/admin/api/tenant-upstreams",
+];
+
+pub fn is_authorized(headers: &HeaderMap, token: &str) -> bool {
+    let Some(value) = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+
+    if let Some(bearer) = value.strip_prefix("Bearer ") {
+        return constant_time_eq(bearer.trim().as_bytes(), token.as_bytes());
+    }
+
+    if let Some(basic) = value.strip_prefix("Basic ")
+        && let Ok(decoded) = base64::engine::general_purpose::STANDARD.decode(basic.trim())
+        && let Ok(credentials) = String::from_utf8(decoded)
+        && let Some((_, password)) = credentials.split_once(':')
+    {
+        return constant_time_eq(password.as_bytes(), token.as_bytes());
+    }
+
+    false
+}
+
+// 避免按字节提前返回导致的时序侧信道
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admin_authorization() {
+        let mut headers = HeaderMap::new();
+        assert!(!is_authorized(&headers, "secret"));
+
+        headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        assert!(is_authorized(&headers, "secret"));
+
+        headers.insert(header::AUTHORIZATION, "Bearer wrong".parse().unwrap());
+        assert!(!is_authorized(&headers, "secret"));
+
+        // admin:secret
+        headers.insert(header::AUTHORIZATION, "Basic YWRtaW46c2VjcmV0".parse().unwrap());
+        assert!(is_authorized(&headers, "secret"));
+    }
+}
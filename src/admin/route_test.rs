@@ -0,0 +1,108 @@
+use axum::{http::{HeaderMap, HeaderName, HeaderValue}, Extension, Json};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use crate::config::{RouteRule, Settings};
+use crate::proxy::{find_best_match_scored, reconstruct_forward_path};
+use crate::rate_limit::RateLimits;
+
+#[derive(Debug, Deserialize)]
+pub struct RouteTestRequest {
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub host: Option<String>,
+    pub path: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    // 模拟的客户端 IP，用于判断限流豁免
+    #[serde(default)]
+    pub client_ip: Option<IpAddr>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+#[derive(Debug, Serialize)]
+pub struct MatchedRoute {
+    pub rule: RouteRule,
+    pub score: i32,
+    pub variables: HashMap<String, String>,
+    pub forward_path: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MiddlewareVerdict {
+    pub rate_limit_exempt: bool,
+    pub whitelisted: bool,
+    pub auth_required: bool,
+    // 命中路由的鉴权模式：required / shadow
+    pub auth_mode: Option<String>,
+    // 请求头中携带的 token 是否能通过校验（未携带为 None）
+    pub token_valid: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RouteTestResponse {
+    pub method: String,
+    pub host: Option<String>,
+    pub path: String,
+    pub matched: Option<MatchedRoute>,
+    pub middleware: MiddlewareVerdict,
+}
+
+// ===== 路由匹配试运行：不向上游发送任何请求 =====
+pub async fn route_test(
+    Extension(settings): Extension<Settings>,
+    Extension(route_rules): Extension<Vec<RouteRule>>,
+    Extension(rate_limits): Extension<Arc<RateLimits>>,
+    Json(input): Json<RouteTestRequest>,
+) -> Json<RouteTestResponse> {
+    // 与网关实际处理一致：先规范化再匹配
+    let path = crate::normalize::normalize_path(&input.path, &crate::normalize::NormalizePolicy::from_settings(&settings));
+    let match_path = path.strip_prefix("/proxy").unwrap_or(&path).to_string();
+
+    let headers: HeaderMap = input
+        .headers
+        .iter()
+        .filter_map(|(k, v)| {
+            Some((HeaderName::try_from(k.as_str()).ok()?, HeaderValue::from_str(v).ok()?))
+        })
+        .collect();
+
+    let best = find_best_match_scored(&route_rules, &match_path, &headers);
+    let method = axum::http::Method::from_bytes(input.method.to_ascii_uppercase().as_bytes()).unwrap_or(axum::http::Method::GET);
+    let whitelisted = best.is_some_and(|(rule, _)| crate::whitelist::hit(rule, &method, &match_path));
+
+    let auth_mode = best.map(|(rule, _)| crate::auth::AuthMode::for_route(rule));
+
+    let client_ip = input.client_ip.unwrap_or_else(|| "127.0.0.1".parse().unwrap());
+    let rate_limit_exempt = rate_limits.exemptions.is_exempt(&client_ip, &headers, Some(&settings));
+
+    let token_valid = headers
+        .contains_key(axum::http::header::AUTHORIZATION)
+        .then(|| crate::auth::decode_bearer(&headers, &settings.jwt_decoding_key).is_ok());
+
+    let matched = best.map(|(rule, score)| {
+        let variables = rule.extract_variables(&match_path);
+        let forward_path = reconstruct_forward_path(&match_path, &rule.prefix, &variables);
+        MatchedRoute { rule: rule.clone(), score, variables, forward_path }
+    });
+
+    Json(RouteTestResponse {
+        method: input.method,
+        host: input.host,
+        path: input.path,
+        middleware: MiddlewareVerdict {
+            rate_limit_exempt,
+            whitelisted,
+            auth_required: !whitelisted
+                && matches!(auth_mode, Some(crate::auth::AuthMode::Required | crate::auth::AuthMode::ApiKey)),
+            auth_mode: auth_mode.map(|m| m.as_str().to_string()),
+            token_valid,
+        },
+        matched,
+    })
+}
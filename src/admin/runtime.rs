@@ -0,0 +1,136 @@
+use axum::{Extension, Json};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::metrics::UPSTREAM_IN_FLIGHT;
+use crate::proxy::POOL_MAX_IDLE_PER_HOST;
+
+// 上次采样的时间与各 worker 累计繁忙时长，用于计算采样间隔内的利用率
+static LAST_SAMPLE: Lazy<Mutex<(Instant, Vec<Duration>)>> =
+    Lazy::new(|| Mutex::new((Instant::now(), Vec::new())));
+
+#[derive(Debug, Serialize)]
+pub struct WorkerStats {
+    pub worker: usize,
+    // 距上次调用本接口期间的繁忙占比（0~1）
+    pub utilization: f64,
+    pub park_count: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlockingPoolStats {
+    pub threads: usize,
+    pub idle_threads: usize,
+    pub queue_depth: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpstreamClientStats {
+    pub in_flight: i64,
+    // 连接池配置的每个上游主机空闲连接上限，不是当前连接数（reqwest 不暴露连接池状态）
+    pub pool_max_idle_per_host_limit: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QosStats {
+    pub max_concurrency: usize,
+    pub in_use: usize,
+    pub queued: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RuntimeStats {
+    pub workers: usize,
+    pub alive_tasks: usize,
+    pub global_queue_depth: usize,
+    pub sample_window_secs: f64,
+    pub worker_stats: Vec<WorkerStats>,
+    // 需以 --cfg tokio_unstable 编译才可用
+    pub blocking_pool: Option<BlockingPoolStats>,
+    pub upstream_client: UpstreamClientStats,
+    // 未启用 QoS 调度时为 None
+    pub qos: Option<QosStats>,
+}
+
+// ===== Tokio 运行时诊断 =====
+pub async fn runtime_stats(scheduler: Option<Extension<Arc<crate::qos::Scheduler>>>) -> Json<RuntimeStats> {
+    let metrics = tokio::runtime::Handle::current().metrics();
+    let workers = metrics.num_workers();
+
+    let busy: Vec<Duration> = (0..workers).map(|w| metrics.worker_total_busy_duration(w)).collect();
+    let (window, previous) = {
+        let mut last = LAST_SAMPLE.lock().unwrap();
+        let window = last.0.elapsed();
+        let previous = std::mem::replace(&mut last.1, busy.clone());
+        last.0 = Instant::now();
+        (window, previous)
+    };
+
+    let worker_stats = busy
+        .iter()
+        .enumerate()
+        .map(|(w, total)| {
+            let delta = total.saturating_sub(previous.get(w).copied().unwrap_or_default());
+            let utilization = if window.is_zero() {
+                0.0
+            } else {
+                (delta.as_secs_f64() / window.as_secs_f64()).min(1.0)
+            };
+            WorkerStats { worker: w, utilization, park_count: metrics.worker_park_count(w) }
+        })
+        .collect();
+
+    Json(RuntimeStats {
+        workers,
+        alive_tasks: metrics.num_alive_tasks(),
+        global_queue_depth: metrics.global_queue_depth(),
+        sample_window_secs: window.as_secs_f64(),
+        worker_stats,
+        blocking_pool: blocking_pool_stats(&metrics),
+        upstream_client: UpstreamClientStats {
+            in_flight: UPSTREAM_IN_FLIGHT.get(),
+            pool_max_idle_per_host_limit: POOL_MAX_IDLE_PER_HOST,
+        },
+        qos: scheduler.map(|Extension(scheduler)| {
+            let (in_use, queued) = scheduler.usage();
+            QosStats { max_concurrency: scheduler.max_concurrency(), in_use, queued }
+        }),
+    })
+}
+
+#[cfg(tokio_unstable)]
+fn blocking_pool_stats(metrics: &tokio::runtime::RuntimeMetrics) -> Option<BlockingPoolStats> {
+    Some(BlockingPoolStats {
+        threads: metrics.num_blocking_threads(),
+        idle_threads: metrics.num_idle_blocking_threads(),
+        queue_depth: metrics.blocking_queue_depth(),
+    })
+}
+
+#[cfg(not(tokio_unstable))]
+fn blocking_pool_stats(_metrics: &tokio::runtime::RuntimeMetrics) -> Option<BlockingPoolStats> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_runtime_stats_shape() {
+        let Json(stats) = runtime_stats(None).await;
+        let value = serde_json::to_value(stats).unwrap();
+        assert_eq!(value["workers"], 1);
+        assert!(value["alive_tasks"].is_u64());
+        assert!(value["global_queue_depth"].is_u64());
+        assert!(value["sample_window_secs"].is_f64());
+        assert_eq!(value["worker_stats"].as_array().unwrap().len(), 1);
+        assert!(value["worker_stats"][0]["utilization"].is_f64());
+        assert!(value["worker_stats"][0]["park_count"].is_u64());
+        assert!(value["upstream_client"]["in_flight"].is_i64());
+        assert_eq!(value["upstream_client"]["pool_max_idle_per_host_limit"], POOL_MAX_IDLE_PER_HOST);
+        assert!(value.get("blocking_pool").is_some());
+        assert!(value["qos"].is_null());
+    }
+}
@@ -0,0 +1,8 @@
+use axum::Json;
+
+use crate::slo::RouteSlo;
+
+// ===== 各路由 SLO 的达标率、消耗速度与剩余错误预算 =====
+pub async fn slo_status() -> Json<Vec<RouteSlo>> {
+    Json(crate::slo::snapshot())
+}
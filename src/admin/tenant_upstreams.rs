@@ -0,0 +1,87 @@
+use axum::{http::StatusCode, response::IntoResponse, Extension, Json};
+use serde::Deserialize;
+use crate::config::RouteRule;
+use crate::namespace::AdminScope;
+use crate::problem::{Problem, ProblemType};
+use crate::tenant_upstreams::{self, Override};
+
+#[derive(Debug, Deserialize)]
+pub struct TenantUpstreamUpdate {
+    // 路由标识（id，未配置 id 时为前缀列表）
+    pub route: String,
+    pub tenant: String,
+    // 空列表表示该租户回到路由的默认上游
+    pub upstream: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TenantUpstreamTarget {
+    pub route: String,
+    pub tenant: String,
+}
+
+pub async fn list_tenant_upstreams(Extension(route_rules): Extension<Vec<RouteRule>>) -> Json<Vec<Override>> {
+    Json(tenant_upstreams::list(&route_rules))
+}
+
+fn find_route<'a>(route_rules: &'a [RouteRule], route: &str) -> Option<&'a RouteRule> {
+    route_rules.iter().find(|r| crate::proxy::route_label(r) == route)
+}
+
+fn route_not_found(route: &str) -> axum::response::Response {
+    Problem::new(StatusCode::NOT_FOUND, ProblemType::NotFound).detail(format!("Route not found: {}", route)).into_response()
+}
+
+// ===== 运行时把租户固定到指定上游组，立即对新请求生效 =====
+pub async fn set_tenant_upstream(
+    Extension(route_rules): Extension<Vec<RouteRule>>,
+    scope: Option<Extension<AdminScope>>,
+    Json(update): Json<TenantUpstreamUpdate>,
+) -> impl IntoResponse {
+    let Some(rule) = find_route(&route_rules, &update.route) else {
+        return route_not_found(&update.route);
+    };
+    if update.tenant.trim().is_empty() {
+        return Problem::new(StatusCode::BAD_REQUEST, ProblemType::BadRequest).detail("Tenant must not be empty").into_response();
+    }
+    if let Some((url, err)) = update.upstream.iter().find_map(|u| crate::upstream_url::parse(u).err().map(|e| (u, e))) {
+        return Problem::new(StatusCode::BAD_REQUEST, ProblemType::BadRequest)
+            .detail(format!("Invalid upstream {}: {}", url, err))
+            .into_response();
+    }
+    // 命名空间令牌只能在本命名空间路由已配置的上游之间切换
+    if scope.is_some() {
+        let known: Vec<&String> = route_rules.iter().flat_map(|r| r.all_upstreams()).collect();
+        if let Some(url) = update.upstream.iter().find(|u| !known.contains(u)) {
+            return Problem::new(StatusCode::FORBIDDEN, ProblemType::Forbidden)
+                .detail(format!("Upstream {} is not configured in this namespace", url))
+                .into_response();
+        }
+    }
+
+    tenant_upstreams::set(&update.route, &update.tenant, Some(update.upstream.clone()));
+    let detail = if update.upstream.is_empty() {
+        "回到路由默认上游".to_string()
+    } else {
+        format!("固定到 {}", update.upstream.join(","))
+    };
+    crate::audit::record("admin", "tenant_upstream", &format!("{}/{}", update.route, update.tenant), detail);
+    Json(tenant_upstreams::list(std::slice::from_ref(rule))).into_response()
+}
+
+// ===== 删除运行时设置，恢复配置文件中的值 =====
+pub async fn reset_tenant_upstream(
+    Extension(route_rules): Extension<Vec<RouteRule>>,
+    Json(target): Json<TenantUpstreamTarget>,
+) -> impl IntoResponse {
+    let Some(rule) = find_route(&route_rules, &target.route) else {
+        return route_not_found(&target.route);
+    };
+    if tenant_upstreams::set(&target.route, &target.tenant, None).is_none() {
+        return Problem::new(StatusCode::NOT_FOUND, ProblemType::NotFound)
+            .detail(format!("No runtime override for tenant {} on route {}", target.tenant, target.route))
+            .into_response();
+    }
+    crate::audit::record("admin", "tenant_upstream_reset", &format!("{}/{}", target.route, target.tenant), "恢复配置".to_string());
+    Json(tenant_upstreams::list(std::slice::from_ref(rule))).into_response()
+}
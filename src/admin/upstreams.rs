@@ -0,0 +1,81 @@
+use axum::{http::StatusCode, response::IntoResponse, Extension, Json};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use crate::config::{RouteRule, Settings};
+use crate::namespace::AdminScope;
+use crate::problem::{Problem, ProblemType};
+use crate::upstream::{self, UpstreamStatus};
+
+#[derive(Debug, Deserialize)]
+pub struct UpstreamTarget {
+    pub upstream: String,
+}
+
+// 所有路由中配置的上游（去重、排序）
+fn configured_upstreams(route_rules: &[RouteRule]) -> BTreeSet<&str> {
+    route_rules.iter().flat_map(|r| r.upstream.iter().map(String::as_str)).collect()
+}
+
+pub async fn list_upstreams(Extension(route_rules): Extension<Vec<RouteRule>>) -> Json<Vec<UpstreamStatus>> {
+    Json(configured_upstreams(&route_rules).into_iter().map(upstream::status).collect())
+}
+
+pub async fn drain_upstream(
+    Extension(route_rules): Extension<Vec<RouteRule>>,
+    scope: Option<Extension<AdminScope>>,
+    Json(target): Json<UpstreamTarget>,
+) -> impl IntoResponse {
+    if let Some(response) = shared_with_others(&scope, &target.upstream) {
+        return response;
+    }
+    set_draining(&route_rules, &target.upstream, true)
+}
+
+pub async fn enable_upstream(
+    Extension(settings): Extension<Settings>,
+    Extension(route_rules): Extension<Vec<RouteRule>>,
+    scope: Option<Extension<AdminScope>>,
+    Json(target): Json<UpstreamTarget>,
+) -> impl IntoResponse {
+    if let Some(response) = shared_with_others(&scope, &target.upstream) {
+        return response;
+    }
+    let was_draining = !upstream::is_routable(&target.upstream);
+    let response = set_draining(&route_rules, &target.upstream, false);
+    // 重新加入轮转前按配置预热
+    if was_draining {
+        upstream::spawn_warm_up(&settings, [target.upstream]);
+    }
+    response
+}
+
+// 摘流对所有引用该上游的路由生效：命名空间令牌不能操作与其他路由共用的上游
+fn shared_with_others(scope: &Option<Extension<AdminScope>>, url: &str) -> Option<axum::response::Response> {
+    let Extension(scope) = scope.as_ref()?;
+    scope.shared_upstreams.contains(url).then(|| {
+        Problem::new(StatusCode::FORBIDDEN, ProblemType::Forbidden)
+            .detail(format!("Upstream {} is shared with routes outside namespace {}", url, scope.namespace))
+            .into_response()
+    })
+}
+
+// ===== 摘流/恢复：对所有引用该上游的负载均衡器同时生效，在途请求不受影响 =====
+fn set_draining(route_rules: &[RouteRule], url: &str, draining: bool) -> axum::response::Response {
+    if !configured_upstreams(route_rules).contains(url) {
+        return Problem::new(StatusCode::NOT_FOUND, ProblemType::NotFound)
+            .detail(format!("Upstream not found: {}", url))
+            .into_response();
+    }
+
+    let previous = upstream::set_draining(url, draining);
+    // 启用分布式协调时同步给其他副本
+    if draining {
+        crate::coordination::publish(crate::coordination::Kind::Drain, url, None, "管理端摘流");
+    } else {
+        crate::coordination::withdraw(crate::coordination::Kind::Drain, url);
+    }
+    if previous != draining {
+        tracing::info!(upstream = %url, draining, "上游摘流状态已变更");
+    }
+    Json(upstream::status(url)).into_response()
+}
@@ -0,0 +1,92 @@
+use axum::{http::StatusCode, response::IntoResponse, Extension, Json};
+use serde::{Deserialize, Serialize};
+use crate::admin::config_dump::BalancerDump;
+use crate::config::RouteRule;
+use crate::namespace::AdminScope;
+use crate::problem::{Problem, ProblemType};
+use crate::proxy;
+
+#[derive(Debug, Deserialize)]
+pub struct WeightUpdate {
+    pub upstream: String,
+    // 0 表示不再分配新请求
+    pub weight: u32,
+    // 仅修改指定的负载均衡器（strategy:upstreams 键）；不填则修改所有引用该上游的负载均衡器
+    #[serde(default)]
+    pub balancer: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WeightUpdated {
+    pub updated: Vec<String>,
+    // 包含该上游但策略不支持权重（robin/iphash）
+    pub unsupported: Vec<String>,
+    pub balancers: Vec<BalancerDump>,
+}
+
+fn balancer_dumps() -> Vec<BalancerDump> {
+    proxy::balancer_snapshots()
+        .into_iter()
+        .map(|(key, snapshot)| BalancerDump { key, snapshot })
+        .collect()
+}
+
+// 命名空间令牌只能看到本命名空间路由的负载均衡器
+fn scoped_dumps(route_rules: &[RouteRule], scope: &Option<Extension<AdminScope>>) -> Vec<BalancerDump> {
+    let dumps = balancer_dumps();
+    if scope.is_none() {
+        return dumps;
+    }
+    let keys = proxy::balancer_keys(route_rules);
+    dumps.into_iter().filter(|dump| keys.contains(&dump.key)).collect()
+}
+
+pub async fn get_weights(
+    Extension(route_rules): Extension<Vec<RouteRule>>,
+    scope: Option<Extension<AdminScope>>,
+) -> Json<Vec<BalancerDump>> {
+    proxy::ensure_balancers(&route_rules);
+    Json(scoped_dumps(&route_rules, &scope))
+}
+
+// ===== 运行时调整上游权重，立即反映到负载均衡器快照 =====
+pub async fn set_weight(
+    Extension(route_rules): Extension<Vec<RouteRule>>,
+    scope: Option<Extension<AdminScope>>,
+    Json(update): Json<WeightUpdate>,
+) -> impl IntoResponse {
+    proxy::ensure_balancers(&route_rules);
+    let change = match &scope {
+        None => proxy::set_upstream_weight(&update.upstream, update.weight, update.balancer.as_deref()),
+        // 命名空间令牌只修改本命名空间路由的负载均衡器，不影响共用该上游的其他路由
+        Some(_) => {
+            let mut change = proxy::WeightChange::default();
+            let keys = proxy::balancer_keys(&route_rules);
+            for key in keys.iter().filter(|key| update.balancer.as_ref().is_none_or(|b| b == *key)) {
+                let one = proxy::set_upstream_weight(&update.upstream, update.weight, Some(key));
+                change.updated.extend(one.updated);
+                change.unsupported.extend(one.unsupported);
+            }
+            change
+        }
+    };
+
+    if change.updated.is_empty() {
+        let problem = if change.unsupported.is_empty() {
+            Problem::new(StatusCode::NOT_FOUND, ProblemType::NotFound)
+                .detail(format!("Upstream not found: {}", update.upstream))
+        } else {
+            Problem::new(StatusCode::BAD_REQUEST, ProblemType::BadRequest)
+                .detail("Balancer strategy does not support weights")
+        };
+        return problem.into_response();
+    }
+
+    tracing::info!(upstream = %update.upstream, weight = update.weight, "上游权重已更新");
+    Json(WeightUpdated {
+        updated: change.updated,
+        unsupported: change.unsupported,
+        balancers: scoped_dumps(&route_rules, &scope),
+    })
+    .into_response()
+}
@@ -0,0 +1,405 @@
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::config::RouteRule;
+use crate::metrics::ANOMALY_ACTIONS;
+
+/// 统计分桶的时长（秒），也是规则的评估间隔
+pub const BUCKET_SECS: u64 = 10;
+// 最近 1 分钟为当前窗口，此前 30 分钟为延迟基线
+const CURRENT_BUCKETS: u64 = 6;
+const BASELINE_BUCKETS: u64 = 180;
+const BUCKETS: usize = (CURRENT_BUCKETS + BASELINE_BUCKETS) as usize;
+
+const DEFAULT_MIN_REQUESTS: u64 = 20;
+const DEFAULT_DURATION_SECS: u64 = 300;
+
+/// 触发条件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    // 最近 1 分钟 5xx 比例达到 threshold
+    ErrorRate,
+    // 最近 1 分钟平均延迟达到基线的 threshold 倍
+    Latency,
+}
+
+impl Trigger {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "error_rate" => Some(Trigger::ErrorRate),
+            "latency" => Some(Trigger::Latency),
+            _ => None,
+        }
+    }
+}
+
+/// 处置动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    // 临时把路由限流降到 qps
+    RateLimit,
+    // 路由熔断：直接返回 503，不再转发
+    OpenCircuit,
+    // 转发到 fallback 上游
+    Fallback,
+}
+
+impl Action {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "rate_limit" => Some(Action::RateLimit),
+            "open_circuit" => Some(Action::OpenCircuit),
+            "fallback" => Some(Action::Fallback),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Action::RateLimit => "rate_limit",
+            Action::OpenCircuit => "open_circuit",
+            Action::Fallback => "fallback",
+        }
+    }
+}
+
+/// 路由级异常防护规则：检测到异常后自动执行处置动作，持续 duration_secs 后恢复
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnomalyRule {
+    // error_rate / latency
+    pub trigger: String,
+    // error_rate 为 5xx 比例（0~1），latency 为相对基线的倍数（大于 1）
+    pub threshold: f64,
+    // 当前窗口（以及 latency 的基线）至少需要的请求数，默认 20
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_requests: Option<u64>,
+    // rate_limit / open_circuit / fallback
+    pub action: String,
+    // rate_limit：降级后的路由 QPS
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub qps: Option<u32>,
+    // fallback：切换到的上游
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<Vec<String>>,
+    // 处置持续时间，默认 300 秒
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_secs: Option<u64>,
+}
+
+impl AnomalyRule {
+    pub fn validation_errors(&self, index: usize) -> Vec<String> {
+        let mut errors = Vec::new();
+        let field = format!("anomaly[{}]", index);
+        match Trigger::parse(&self.trigger) {
+            None => errors.push(format!("{}.trigger必须是error_rate或latency: {}", field, self.trigger)),
+            Some(Trigger::ErrorRate) if !(self.threshold > 0.0 && self.threshold <= 1.0) => {
+                errors.push(format!("{}.threshold必须在0到1之间", field));
+            }
+            Some(Trigger::Latency) if self.threshold <= 1.0 => {
+                errors.push(format!("{}.threshold必须大于1", field));
+            }
+            _ => {}
+        }
+        match Action::parse(&self.action) {
+            None => errors.push(format!("{}.action必须是rate_limit、open_circuit或fallback: {}", field, self.action)),
+            Some(Action::RateLimit) if self.qps.is_none_or(|q| q == 0) => {
+                errors.push(format!("{}.action为rate_limit时qps必须大于0", field));
+            }
+            Some(Action::Fallback) if self.fallback.as_ref().is_none_or(|f| f.is_empty()) => {
+                errors.push(format!("{}.action为fallback时必须配置fallback上游", field));
+            }
+            _ => {}
+        }
+        if self.min_requests == Some(0) {
+            errors.push(format!("{}.min_requests必须大于0", field));
+        }
+        if self.duration_secs == Some(0) {
+            errors.push(format!("{}.duration_secs必须大于0", field));
+        }
+        errors
+    }
+
+    fn duration(&self) -> Duration {
+        Duration::from_secs(self.duration_secs.unwrap_or(DEFAULT_DURATION_SECS))
+    }
+
+    // 命中时返回观测到的异常描述
+    fn triggered(&self, current: Counts, baseline: Counts) -> Option<String> {
+        let min = self.min_requests.unwrap_or(DEFAULT_MIN_REQUESTS);
+        if current.total < min {
+            return None;
+        }
+        match Trigger::parse(&self.trigger)? {
+            Trigger::ErrorRate => {
+                let rate = current.errors as f64 / current.total as f64;
+                (rate >= self.threshold)
+                    .then(|| format!("最近1分钟5xx比例{:.1}%，阈值{:.1}%", rate * 100.0, self.threshold * 100.0))
+            }
+            Trigger::Latency => {
+                let (now, base) = (current.mean_latency_ms()?, baseline.mean_latency_ms()?);
+                (baseline.total >= min && base > 0.0 && now >= base * self.threshold)
+                    .then(|| format!("最近1分钟平均延迟{:.0}ms，基线{:.0}ms", now, base))
+            }
+        }
+    }
+}
+
+// ===== 按 10 秒分桶的请求统计 =====
+#[derive(Debug, Clone, Copy, Default)]
+struct Bucket {
+    slot: u64,
+    total: u64,
+    errors: u64,
+    latency_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Counts {
+    total: u64,
+    errors: u64,
+    latency_ms: u64,
+}
+
+impl Counts {
+    fn mean_latency_ms(&self) -> Option<f64> {
+        (self.total > 0).then(|| self.latency_ms as f64 / self.total as f64)
+    }
+}
+
+struct Window {
+    buckets: Mutex<Vec<Bucket>>,
+    // 处置恢复后，当前窗口只统计该分桶之后的请求，避免旧数据再次触发
+    since: AtomicU64,
+}
+
+impl Window {
+    fn new() -> Self {
+        Self { buckets: Mutex::new(vec![Bucket::default(); BUCKETS]), since: AtomicU64::new(0) }
+    }
+
+    fn record_at(&self, slot: u64, status: u16, elapsed: Duration) {
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = &mut buckets[(slot % BUCKETS as u64) as usize];
+        if bucket.slot != slot {
+            *bucket = Bucket { slot, ..Default::default() };
+        }
+        bucket.total += 1;
+        bucket.errors += u64::from(status >= 500);
+        bucket.latency_ms += elapsed.as_millis() as u64;
+    }
+
+    // [from, to] 分桶内的合计
+    fn counts(&self, from: u64, to: u64) -> Counts {
+        let buckets = self.buckets.lock().unwrap();
+        buckets
+            .iter()
+            .filter(|b| b.total > 0 && b.slot >= from && b.slot <= to)
+            .fold(Counts::default(), |acc, b| Counts {
+                total: acc.total + b.total,
+                errors: acc.errors + b.errors,
+                latency_ms: acc.latency_ms + b.latency_ms,
+            })
+    }
+
+    fn current(&self, slot: u64) -> Counts {
+        let from = (slot + 1).saturating_sub(CURRENT_BUCKETS).max(self.since.load(Ordering::Relaxed));
+        self.counts(from, slot)
+    }
+
+    fn baseline(&self, slot: u64) -> Counts {
+        match slot.checked_sub(CURRENT_BUCKETS) {
+            Some(to) => self.counts((to + 1).saturating_sub(BASELINE_BUCKETS), to),
+            None => Counts::default(),
+        }
+    }
+}
+
+static WINDOWS: Lazy<DashMap<String, Window>> = Lazy::new(DashMap::new);
+
+fn current_slot() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() / BUCKET_SECS).unwrap_or(0)
+}
+
+/// 记录配置了异常防护规则的路由的一次请求
+pub fn record(route: &str, status: u16, elapsed: Duration) {
+    if !WINDOWS.contains_key(route) {
+        WINDOWS.entry(route.to_string()).or_insert_with(Window::new);
+    }
+    if let Some(window) = WINDOWS.get(route) {
+        window.record_at(current_slot(), status, elapsed);
+    }
+}
+
+// ===== 生效中的处置 =====
+struct Mitigation {
+    until: Instant,
+    rule: AnomalyRule,
+}
+
+static ACTIVE: Lazy<DashMap<(String, Action), Mitigation>> = Lazy::new(DashMap::new);
+
+fn active<T>(route: &str, action: Action, f: impl FnOnce(&Mitigation) -> T) -> Option<T> {
+    let mitigation = ACTIVE.get(&(route.to_string(), action))?;
+    (mitigation.until > Instant::now()).then(|| f(&mitigation))
+}
+
+/// 路由生效的 QPS 上限：限流处置生效时取降级值与配置值中较小者
+pub fn limited_qps(route: &str, configured: Option<u32>) -> Option<u32> {
+    match active(route, Action::RateLimit, |m| m.rule.qps).flatten() {
+        Some(qps) => Some(configured.map_or(qps, |c| c.min(qps))),
+        None => configured,
+    }
+}
+
+/// 路由熔断中时返回剩余时间
+pub fn circuit_open(route: &str) -> Option<Duration> {
+    active(route, Action::OpenCircuit, |m| m.until.saturating_duration_since(Instant::now()))
+}
+
+/// 切换到 fallback 上游时返回其列表
+pub fn fallback_upstreams(route: &str) -> Option<Vec<String>> {
+    active(route, Action::Fallback, |m| m.rule.fallback.clone()).flatten()
+}
+
+fn activate(route: &str, rule: &AnomalyRule, action: Action, reason: String, now: Instant) {
+    let detail = match action {
+        Action::RateLimit => format!("{}，路由限流降至{} QPS", reason, rule.qps.unwrap_or_default()),
+        Action::OpenCircuit => format!("{}，路由熔断", reason),
+        Action::Fallback => format!("{}，切换到 {}", reason, rule.fallback.iter().flatten().cloned().collect::<Vec<_>>().join(",")),
+    };
+    let detail = format!("{}，持续{}秒", detail, rule.duration().as_secs());
+    ACTIVE.insert((route.to_string(), action), Mitigation { until: now + rule.duration(), rule: rule.clone() });
+    ANOMALY_ACTIONS.with_label_values(&[route, action.as_str()]).inc();
+    crate::audit::record("anomaly", action.as_str(), route, detail);
+}
+
+// 一轮评估：先恢复到期的处置，再对未在处置中的规则检查是否触发
+fn evaluate(guarded: &[(String, Vec<AnomalyRule>)], slot: u64, now: Instant) {
+    ACTIVE.retain(|(route, action), mitigation| {
+        if mitigation.until > now {
+            return true;
+        }
+        if let Some(window) = WINDOWS.get(route) {
+            window.since.store(slot + 1, Ordering::Relaxed);
+        }
+        crate::audit::record("anomaly", &format!("{}_recovered", action.as_str()), route, "处置到期，已恢复".to_string());
+        false
+    });
+
+    for (route, rules) in guarded {
+        let Some(window) = WINDOWS.get(route) else {
+            continue;
+        };
+        let (current, baseline) = (window.current(slot), window.baseline(slot));
+        drop(window);
+        for rule in rules {
+            let Some(action) = Action::parse(&rule.action) else {
+                continue;
+            };
+            if ACTIVE.contains_key(&(route.clone(), action)) {
+                continue;
+            }
+            if let Some(reason) = rule.triggered(current, baseline) {
+                activate(route, rule, action, reason, now);
+            }
+        }
+    }
+}
+
+/// 为配置了异常防护规则的路由启动后台评估
+pub fn spawn_monitor(route_rules: &[RouteRule]) {
+    let guarded: Vec<(String, Vec<AnomalyRule>)> = route_rules
+        .iter()
+        .filter_map(|r| Some((crate::proxy::route_label(r), r.anomaly.clone().filter(|a| !a.is_empty())?)))
+        .collect();
+    if guarded.is_empty() {
+        return;
+    }
+    tracing::info!(routes = guarded.len(), "启动异常检测");
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(BUCKET_SECS));
+        loop {
+            ticker.tick().await;
+            evaluate(&guarded, current_slot(), Instant::now());
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(trigger: &str, threshold: f64, action: &str) -> AnomalyRule {
+        AnomalyRule {
+            trigger: trigger.to_string(),
+            threshold,
+            min_requests: Some(10),
+            action: action.to_string(),
+            qps: Some(5),
+            fallback: Some(vec!["http://backup:8080".to_string()]),
+            duration_secs: Some(60),
+        }
+    }
+
+    #[test]
+    fn test_triggers() {
+        let window = Window::new();
+        // 基线：10 分钟前 20 个 50ms 的请求；当前：10 个 150ms 的请求，其中 3 个 5xx
+        for _ in 0..20 {
+            window.record_at(1000 - 60, 200, Duration::from_millis(50));
+        }
+        for i in 0..10 {
+            window.record_at(1000, if i < 3 { 503 } else { 200 }, Duration::from_millis(150));
+        }
+        let (current, baseline) = (window.current(1000), window.baseline(1000));
+        assert_eq!((current.total, current.errors), (10, 3));
+        assert_eq!(baseline.total, 20);
+
+        assert!(rule("error_rate", 0.2, "open_circuit").triggered(current, baseline).is_some());
+        assert!(rule("error_rate", 0.5, "open_circuit").triggered(current, baseline).is_none());
+        assert!(rule("latency", 2.0, "rate_limit").triggered(current, baseline).is_some());
+        assert!(rule("latency", 4.0, "rate_limit").triggered(current, baseline).is_none());
+
+        // 恢复后当前窗口从头统计
+        window.since.store(1001, Ordering::Relaxed);
+        assert_eq!(window.current(1001), Counts::default());
+    }
+
+    #[test]
+    fn test_mitigation_lifecycle() {
+        let route = "anomaly-test-route".to_string();
+        let window = Window::new();
+        for _ in 0..10 {
+            window.record_at(500, 502, Duration::from_millis(5));
+        }
+        WINDOWS.insert(route.clone(), window);
+        let guarded = vec![(route.clone(), vec![rule("error_rate", 0.5, "rate_limit"), rule("error_rate", 0.5, "fallback")])];
+
+        let now = Instant::now();
+        evaluate(&guarded, 500, now);
+        assert_eq!(limited_qps(&route, Some(100)), Some(5));
+        assert_eq!(fallback_upstreams(&route), Some(vec!["http://backup:8080".to_string()]));
+        assert_eq!(circuit_open(&route), None);
+        assert!(crate::audit::recent(crate::audit::CAPACITY).iter().any(|e| e.target == route && e.action == "rate_limit"));
+
+        // 到期后恢复，旧数据不会立即再次触发
+        evaluate(&guarded, 510, now + Duration::from_secs(61));
+        assert_eq!(limited_qps(&route, Some(100)), Some(100));
+        assert_eq!(fallback_upstreams(&route), None);
+        assert!(crate::audit::recent(crate::audit::CAPACITY).iter().any(|e| e.target == route && e.action == "fallback_recovered"));
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(rule("latency", 2.0, "open_circuit").validation_errors(0).is_empty());
+        let bad = AnomalyRule { trigger: "cpu".to_string(), threshold: 2.0, action: "rate_limit".to_string(), duration_secs: Some(0), ..Default::default() };
+        assert_eq!(bad.validation_errors(1).len(), 3);
+        let bad = AnomalyRule { trigger: "error_rate".to_string(), threshold: 2.0, action: "fallback".to_string(), ..Default::default() };
+        assert_eq!(bad.validation_errors(0).len(), 2);
+    }
+}
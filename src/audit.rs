@@ -0,0 +1,37 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 内存中保留的审计记录条数，超出后丢弃最早的记录
+pub const CAPACITY: usize = 1000;
+
+/// 一条审计记录：谁（actor）对什么（target）做了什么（action）
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct AuditEntry {
+    // Unix 时间戳（秒）
+    pub time: u64,
+    pub actor: &'static str,
+    pub action: String,
+    pub target: String,
+    pub detail: String,
+}
+
+static ENTRIES: Lazy<Mutex<VecDeque<AuditEntry>>> = Lazy::new(|| Mutex::new(VecDeque::with_capacity(CAPACITY)));
+
+/// 记录一条审计事件：同时输出到 helios::audit 日志目标，并保留在内存中供管理端查询
+pub fn record(actor: &'static str, action: &str, target: &str, detail: String) {
+    tracing::warn!(target: "helios::audit", actor, action, target, detail = %detail, "审计事件");
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let mut entries = ENTRIES.lock().unwrap();
+    if entries.len() >= CAPACITY {
+        entries.pop_front();
+    }
+    entries.push_back(AuditEntry { time, actor, action: action.to_string(), target: target.to_string(), detail });
+}
+
+/// 最近的审计记录，新记录在前
+pub fn recent(limit: usize) -> Vec<AuditEntry> {
+    ENTRIES.lock().unwrap().iter().rev().take(limit).cloned().collect()
+}
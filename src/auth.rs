@@ -1,19 +1,43 @@
 use axum::{
     async_trait,
     extract::{FromRequestParts},
-    http::{request::Parts, StatusCode},
+    http::{request::Parts, Extensions, HeaderMap, StatusCode},
     response::{IntoResponse},
 };
 use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation, TokenData};
 use serde::{Deserialize, Serialize};
 use crate::config::Settings;
+use crate::metrics::AUTH_SHADOW_COUNTER;
+use crate::problem::{Problem, ProblemType};
 use thiserror::Error;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Claims {
     pub sub: String,       // 用户 ID
     pub exp: usize,        // 过期时间（秒）
     pub tenant_id: String, // 多租户 ID
+    // 调用方优先级等级（low/normal/high/critical），用于 QoS 调度
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+    // 其余声明（如 plan），按名称读取，见 Claims::get
+    #[serde(flatten)]
+    pub extra: std::collections::BTreeMap<String, serde_json::Value>,
+}
+
+impl Claims {
+    /// 按名称读取声明的取值，数字与布尔转为字符串
+    pub fn get(&self, name: &str) -> Option<String> {
+        match name {
+            "sub" => Some(self.sub.clone()),
+            "tenant_id" => Some(self.tenant_id.clone()),
+            "priority" => self.priority.clone(),
+            _ => match self.extra.get(name)? {
+                serde_json::Value::String(s) => Some(s.clone()),
+                value @ (serde_json::Value::Number(_) | serde_json::Value::Bool(_)) => Some(value.to_string()),
+                _ => None,
+            },
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -24,22 +48,186 @@ pub enum AuthError {
     InvalidToken,
     #[error("jwt decode error")]
     DecodeError(#[from] jsonwebtoken::errors::Error),
+    #[error("missing api key")]
+    MissingApiKey,
+    #[error("invalid api key")]
+    InvalidApiKey,
     #[error("config missing")]
     ConfigMissing,
 }
 
 impl IntoResponse for AuthError {
     fn into_response(self) -> axum::response::Response {
-        let (status, msg) = match self {
-            AuthError::MissingHeader => (StatusCode::UNAUTHORIZED, "Missing authorization header"),
-            AuthError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token"),
-            AuthError::DecodeError(_) => (StatusCode::UNAUTHORIZED, "Token decode error"),
-            AuthError::ConfigMissing => (StatusCode::INTERNAL_SERVER_ERROR, "Config missing"),
+        let (status, kind, detail) = match self {
+            AuthError::MissingHeader => (StatusCode::UNAUTHORIZED, ProblemType::Unauthorized, "Missing authorization header"),
+            AuthError::InvalidToken => (StatusCode::UNAUTHORIZED, ProblemType::Unauthorized, "Invalid token"),
+            AuthError::DecodeError(_) => (StatusCode::UNAUTHORIZED, ProblemType::Unauthorized, "Token decode error"),
+            AuthError::MissingApiKey => (StatusCode::UNAUTHORIZED, ProblemType::Unauthorized, "Missing API key"),
+            AuthError::InvalidApiKey => (StatusCode::UNAUTHORIZED, ProblemType::Unauthorized, "Invalid API key"),
+            AuthError::ConfigMissing => (StatusCode::INTERNAL_SERVER_ERROR, ProblemType::Internal, "Config missing"),
         };
-        (status, msg).into_response()
+        Problem::new(status, kind).detail(detail).into_response()
+    }
+}
+
+impl AuthError {
+    /// 用于指标标签与日志的简短原因
+    pub fn reason(&self) -> &'static str {
+        match self {
+            AuthError::MissingHeader => "missing_header",
+            AuthError::InvalidToken => "invalid_token",
+            AuthError::DecodeError(_) => "decode_error",
+            AuthError::MissingApiKey => "missing_api_key",
+            AuthError::InvalidApiKey => "invalid_api_key",
+            AuthError::ConfigMissing => "config_missing",
+        }
+    }
+}
+
+/// 路由的鉴权模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMode {
+    /// 校验失败直接返回 401
+    Required,
+    /// 仅记录会产生的结论（日志与计数），始终放行，用于上线前验证策略
+    Shadow,
+    /// 尽力识别身份：token 有效则透传用户信息，缺失或无效则以匿名身份转发
+    Optional,
+    /// 以 X-API-Key 识别身份（api_keys），缺失或未知的 key 返回 401
+    ApiKey,
+    /// 不鉴权，以匿名身份转发
+    Disabled,
+}
+
+/// optional 模式下告知上游的鉴权结果：authenticated / anonymous
+pub const AUTH_STATUS_HEADER: &str = "x-auth-status";
+
+impl AuthMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "required" | "jwt" => Some(AuthMode::Required),
+            "shadow" => Some(AuthMode::Shadow),
+            "optional" => Some(AuthMode::Optional),
+            "api_key" => Some(AuthMode::ApiKey),
+            "none" => Some(AuthMode::Disabled),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthMode::Required => "required",
+            AuthMode::Shadow => "shadow",
+            AuthMode::Optional => "optional",
+            AuthMode::ApiKey => "api_key",
+            AuthMode::Disabled => "none",
+        }
+    }
+
+    pub fn for_route(rule: &crate::config::RouteRule) -> Self {
+        rule.auth.as_deref().and_then(Self::parse).unwrap_or(AuthMode::Required)
     }
 }
 
+/// 命中路由的鉴权模式，由路由匹配中间件写入请求扩展
+#[derive(Debug, Clone)]
+pub struct RouteAuth {
+    pub mode: AuthMode,
+    pub route: String,
+}
+
+/// 从 Authorization: Bearer 头中解析并校验 JWT，返回 Claims
+pub fn decode_bearer(headers: &HeaderMap, decoding_key: &str) -> Result<Claims, AuthError> {
+    let auth_header = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .ok_or(AuthError::MissingHeader)?;
+
+    if !auth_header.starts_with("Bearer ") {
+        return Err(AuthError::InvalidToken);
+    }
+    let token = auth_header.trim_start_matches("Bearer ").trim();
+
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.validate_exp = true;
+
+    let token_data: TokenData<Claims> = decode(
+        token,
+        &DecodingKey::from_secret(decoding_key.as_bytes()),
+        &validation,
+    )?;
+
+    Ok(token_data.claims)
+}
+
+/// API Key 到身份的映射，供 auth = "api_key" 的路由以 X-API-Key 识别调用方
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKey {
+    pub key: String,
+    pub sub: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub tenant_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+    // 其他声明（如 plan）
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub claims: std::collections::BTreeMap<String, String>,
+}
+
+/// 按 X-API-Key 头查找调用方身份
+pub fn identify_api_key(headers: &HeaderMap, keys: &[ApiKey]) -> Result<Claims, AuthError> {
+    let presented = headers
+        .get(crate::rate_limit::API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|key| !key.is_empty())
+        .ok_or(AuthError::MissingApiKey)?;
+    // 逐个做定长比较且不提前退出，避免按字节比较的时序侧信道
+    let mut found = None;
+    for k in keys {
+        if crate::admin::constant_time_eq(k.key.as_bytes(), presented.as_bytes()) && found.is_none() {
+            found = Some(k);
+        }
+    }
+    let key = found.ok_or(AuthError::InvalidApiKey)?;
+    Ok(Claims {
+        sub: key.sub.clone(),
+        exp: 0,
+        tenant_id: key.tenant_id.clone(),
+        priority: key.priority.clone(),
+        extra: key.claims.iter().map(|(k, v)| (k.clone(), v.clone().into())).collect(),
+    })
+}
+
+pub fn api_key_errors(settings: &Settings) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (i, key) in settings.api_keys.iter().enumerate() {
+        if key.key.is_empty() {
+            errors.push(format!("api_keys[{}].key不能为空", i));
+        }
+        if key.sub.is_empty() {
+            errors.push(format!("api_keys[{}].sub不能为空", i));
+        }
+        if settings.api_keys[..i].iter().any(|other| other.key == key.key) {
+            errors.push(format!("api_keys[{}].key与之前的项重复", i));
+        }
+        if key.claims.keys().any(|k| matches!(k.as_str(), "sub" | "tenant_id" | "priority" | "exp")) {
+            errors.push(format!("api_keys[{}].claims不能包含 sub、tenant_id、priority、exp", i));
+        }
+    }
+    errors
+}
+
+/// 识别请求身份：能映射为身份的客户端证书优先，其次为 Authorization: Bearer 中的 JWT
+pub fn identify(headers: &HeaderMap, extensions: &Extensions, settings: &Settings) -> Result<Claims, AuthError> {
+    if let Some(cert) = extensions.get::<crate::mtls::ClientCert>()
+        && let Some(claims) = crate::mtls::claims(cert, &settings.client_cert_identities)
+    {
+        return Ok(claims);
+    }
+    decode_bearer(headers, &settings.jwt_decoding_key)
+}
+
 /// Extractor: 从请求 header 中验证 JWT 并把 Claims 放进请求扩展里
 #[derive(Debug, Clone)]
 pub struct JwtAuth(pub Claims);
@@ -54,7 +242,13 @@ where
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
         // 白名单标记则跳过鉴权，返回空 Claims
         if parts.extensions.get::<crate::proxy::WhitelistBypass>().is_some() {
-            return Ok(JwtAuth(Claims { sub: String::new(), exp: 0, tenant_id: String::new() }));
+            return Ok(JwtAuth(Claims::default()));
+        }
+
+        let route_auth = parts.extensions.get::<RouteAuth>().cloned();
+        // none 模式：不鉴权，以匿名身份放行
+        if route_auth.as_ref().is_some_and(|ra| ra.mode == AuthMode::Disabled) {
+            return Ok(JwtAuth(Claims::default()));
         }
 
         // we expect Settings stored in extensions for global access
@@ -64,31 +258,128 @@ where
             .ok_or(AuthError::ConfigMissing)?
             .clone();
 
-        let auth_header = parts
-            .headers
-            .get(axum::http::header::AUTHORIZATION)
-            .and_then(|v| v.to_str().ok())
-            .ok_or(AuthError::MissingHeader)?;
-
-        if !auth_header.starts_with("Bearer ") {
-            return Err(AuthError::InvalidToken);
+        // api_key 模式：只认 X-API-Key，不读取 JWT 与会话
+        if route_auth.as_ref().is_some_and(|ra| ra.mode == AuthMode::ApiKey) {
+            let claims = identify_api_key(&parts.headers, &settings.api_keys)?;
+            parts.extensions.insert(JwtAuth(claims.clone()));
+            return Ok(JwtAuth(claims));
         }
-        let token = auth_header.trim_start_matches("Bearer ").trim();
 
-        let mut validation = Validation::new(Algorithm::HS256);
-        validation.validate_exp = true;
+        // 没有 Authorization 头时，尝试用会话 Cookie 换回 token，并转发给上游
+        if !parts.headers.contains_key(axum::http::header::AUTHORIZATION)
+            && let Some(sessions) = parts.extensions.get::<std::sync::Arc<crate::session::Sessions>>().cloned()
+            && let Some(session) = sessions.lookup(&parts.headers).await
+            && let Ok(value) = axum::http::HeaderValue::from_str(&format!("Bearer {}", session.token))
+        {
+            parts.headers.insert(axum::http::header::AUTHORIZATION, value);
+        }
 
-        let token_data: TokenData<Claims> = decode(
-            token,
-            &DecodingKey::from_secret(settings.jwt_decoding_key.as_bytes()),
-            &validation,
-        )?;
+        let claims = match identify(&parts.headers, &parts.extensions, &settings) {
+            Ok(claims) => {
+                if let Some(ra) = route_auth.as_ref().filter(|ra| ra.mode == AuthMode::Shadow) {
+                    AUTH_SHADOW_COUNTER.with_label_values(&[&ra.route, "allow", ""]).inc();
+                }
+                claims
+            }
+            Err(err) => match route_auth.map(|ra| (ra.mode, ra.route)) {
+                // shadow 模式：记录本会拒绝的请求，然后以空 Claims 放行
+                Some((AuthMode::Shadow, route)) => {
+                    AUTH_SHADOW_COUNTER.with_label_values(&[&route, "deny", err.reason()]).inc();
+                    tracing::warn!(route = %route, reason = err.reason(), path = %parts.uri.path(), "shadow 鉴权：该请求在 required 模式下会被拒绝");
+                    return Ok(JwtAuth(Claims::default()));
+                }
+                // optional 模式：以匿名身份放行，由 propagate_auth_headers 标记
+                Some((AuthMode::Optional, _)) => {
+                    return Ok(JwtAuth(Claims::default()));
+                }
+                _ => return Err(err),
+            },
+        };
 
-        let claims = token_data.claims;
-        
         // // 将解析后的 Claims 存储到 extensions 中，供后续中间件使用
         parts.extensions.insert(JwtAuth(claims.clone()));
 
         Ok(JwtAuth(claims))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn keys() -> Vec<ApiKey> {
+        vec![
+            ApiKey { key: "key-a".into(), sub: "svc-a".into(), tenant_id: "t1".into(), ..Default::default() },
+            ApiKey {
+                key: "key-b".into(),
+                sub: "svc-b".into(),
+                tenant_id: "t2".into(),
+                priority: Some("high".into()),
+                claims: [("plan".to_string(), "pro".to_string())].into(),
+            },
+        ]
+    }
+
+    fn headers(key: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(crate::rate_limit::API_KEY_HEADER, key.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_auth_mode_parse() {
+        assert_eq!(AuthMode::parse("jwt"), Some(AuthMode::Required));
+        assert_eq!(AuthMode::parse("api_key"), Some(AuthMode::ApiKey));
+        assert_eq!(AuthMode::parse("none"), Some(AuthMode::Disabled));
+        assert_eq!(AuthMode::parse("bogus"), None);
+    }
+
+    #[test]
+    fn test_identify_api_key() {
+        let keys = keys();
+        assert!(matches!(identify_api_key(&HeaderMap::new(), &keys), Err(AuthError::MissingApiKey)));
+        assert!(matches!(identify_api_key(&headers(""), &keys), Err(AuthError::MissingApiKey)));
+        assert!(matches!(identify_api_key(&headers("key-c"), &keys), Err(AuthError::InvalidApiKey)));
+        // 前缀相同、长度不同的 key 也不能通过
+        assert!(matches!(identify_api_key(&headers("key-"), &keys), Err(AuthError::InvalidApiKey)));
+
+        let claims = identify_api_key(&headers("key-b"), &keys).unwrap();
+        assert_eq!(claims.sub, "svc-b");
+        assert_eq!(claims.tenant_id, "t2");
+        assert_eq!(claims.priority.as_deref(), Some("high"));
+        assert_eq!(claims.extra.get("plan"), Some(&serde_json::json!("pro")));
+
+        let claims = identify_api_key(&headers("key-a"), &keys).unwrap();
+        assert_eq!((claims.sub.as_str(), claims.tenant_id.as_str()), ("svc-a", "t1"));
+    }
+
+    #[tokio::test]
+    async fn test_disabled_mode_strips_identity_headers() {
+        let app = Router::new()
+            .route(
+                "/",
+                get(|headers: HeaderMap| async move {
+                    format!("{:?}/{:?}", headers.get("uid"), headers.get("tenant_id"))
+                }),
+            )
+            .layer(axum::middleware::from_fn(crate::proxy::propagate_auth_headers))
+            .layer(axum::middleware::from_extractor::<JwtAuth>());
+
+        // 带伪造的身份头与无效的 token，none 模式既不拒绝也不转发这些头
+        let mut req = Request::builder()
+            .uri("/")
+            .header("uid", "forged")
+            .header("tenant_id", "forged")
+            .header(axum::http::header::AUTHORIZATION, "Bearer not-a-jwt")
+            .body(Body::empty())
+            .unwrap();
+        req.extensions_mut().insert(RouteAuth { mode: AuthMode::Disabled, route: "r".into() });
+
+        let resp = app.oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"None/None");
+    }
+}
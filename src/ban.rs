@@ -0,0 +1,291 @@
+use axum::extract::Request;
+use dashmap::DashMap;
+use once_cell::sync::{Lazy, OnceCell};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::config::Settings;
+use crate::metrics::BAN_EVENTS;
+
+// 计数表超过该条目数时清理已过期的窗口
+const MAX_TRACKED: usize = 100_000;
+
+/// 封禁阈值命中的状态码：具体状态码（如 401）或整类（4xx / 5xx）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusMatch {
+    Exact(u16),
+    Class(u16),
+}
+
+impl StatusMatch {
+    pub fn parse(s: &str) -> Option<Self> {
+        if let Some(class) = s.strip_suffix("xx") {
+            return match class.parse::<u16>() {
+                Ok(c @ 1..=5) if class.len() == 1 => Some(StatusMatch::Class(c)),
+                _ => None,
+            };
+        }
+        match s.parse::<u16>() {
+            Ok(code @ 100..=599) => Some(StatusMatch::Exact(code)),
+            _ => None,
+        }
+    }
+
+    fn matches(&self, status: u16) -> bool {
+        match self {
+            StatusMatch::Exact(code) => status == *code,
+            StatusMatch::Class(class) => status / 100 == *class,
+        }
+    }
+}
+
+/// 自动封禁策略：同一来源在窗口内命中某状态码的次数超过阈值后封禁一段时间
+#[derive(Debug, Clone)]
+pub struct BanPolicy {
+    // (配置中的状态码键, 匹配方式, 窗口内允许的次数)
+    thresholds: Vec<(String, StatusMatch, u32)>,
+    window: Duration,
+    duration: Duration,
+}
+
+impl BanPolicy {
+    pub fn from_settings(settings: &Settings) -> Option<Self> {
+        let thresholds: Vec<(String, StatusMatch, u32)> = settings
+            .ban_thresholds
+            .iter()
+            .filter_map(|(key, max)| Some((key.clone(), StatusMatch::parse(key)?, *max)))
+            .collect();
+        if thresholds.is_empty() {
+            return None;
+        }
+        Some(Self {
+            thresholds,
+            window: Duration::from_secs(settings.ban_window_secs.unwrap_or(60)),
+            duration: Duration::from_secs(settings.ban_duration_secs.unwrap_or(600)),
+        })
+    }
+}
+
+static POLICY: OnceCell<BanPolicy> = OnceCell::new();
+
+/// 按 ban_thresholds 启用自动封禁（以首次配置为准）
+pub fn configure(settings: &Settings) {
+    if let Some(policy) = BanPolicy::from_settings(settings) {
+        let _ = POLICY.set(policy);
+    }
+}
+
+pub fn enabled() -> bool {
+    POLICY.get().is_some()
+}
+
+/// 请求的来源标识：客户端 IP，以及携带的 token（Authorization 或 X-API-Key，只保留摘要）
+pub fn sources(req: &Request) -> Vec<String> {
+    let mut sources = vec![format!("ip:{}", crate::rate_limit::client_ip(req))];
+    let token = req
+        .headers()
+        .get(axum::http::header::AUTHORIZATION)
+        .or_else(|| req.headers().get(crate::rate_limit::API_KEY_HEADER))
+        .map(|v| v.as_bytes())
+        .filter(|v| !v.is_empty());
+    if let Some(token) = token {
+        sources.push(format!("token:{}", &hex::encode(Sha256::digest(token))[..16]));
+    }
+    sources
+}
+
+// ===== 计数与封禁表 =====
+#[derive(Debug)]
+struct Counter {
+    window_start: Instant,
+    counts: BTreeMap<String, u32>,
+}
+
+#[derive(Debug, Clone)]
+struct Ban {
+    until: Instant,
+    reason: String,
+}
+
+static COUNTERS: Lazy<DashMap<String, Counter>> = Lazy::new(DashMap::new);
+static BANS: Lazy<DashMap<String, Ban>> = Lazy::new(DashMap::new);
+
+/// 任一来源处于封禁中时返回剩余时间
+pub fn banned(sources: &[String]) -> Option<Duration> {
+    let now = Instant::now();
+    sources
+        .iter()
+        .filter_map(|source| BANS.get(source).map(|ban| ban.until))
+        .filter(|until| *until > now)
+        .max()
+        .map(|until| until - now)
+}
+
+/// 记录一次响应状态，超过阈值的来源被封禁
+pub fn observe(sources: &[String], status: u16) {
+    if let Some(policy) = POLICY.get() {
+        observe_at(policy, sources, status, Instant::now());
+    }
+}
+
+fn observe_at(policy: &BanPolicy, sources: &[String], status: u16, now: Instant) {
+    let hits: Vec<&(String, StatusMatch, u32)> = policy.thresholds.iter().filter(|(_, m, _)| m.matches(status)).collect();
+    if hits.is_empty() {
+        return;
+    }
+    if COUNTERS.len() > MAX_TRACKED {
+        COUNTERS.retain(|_, c| now.duration_since(c.window_start) < policy.window);
+    }
+    for source in sources {
+        let exceeded = {
+            let mut counter = COUNTERS
+                .entry(source.clone())
+                .or_insert_with(|| Counter { window_start: now, counts: BTreeMap::new() });
+            if now.duration_since(counter.window_start) >= policy.window {
+                *counter = Counter { window_start: now, counts: BTreeMap::new() };
+            }
+            hits.iter().find_map(|(key, _, max)| {
+                let count = counter.counts.entry(key.clone()).or_default();
+                *count += 1;
+                (*count > *max).then(|| format!("{}秒内{}次{}", policy.window.as_secs(), count, key))
+            })
+        };
+        if let Some(reason) = exceeded {
+            COUNTERS.remove(source);
+            ban(source, reason, now, policy.duration);
+        }
+    }
+}
+
+fn ban(source: &str, reason: String, now: Instant, duration: Duration) {
+    BAN_EVENTS.with_label_values(&["banned"]).inc();
+    crate::audit::record("ban", "ban", source, format!("{}，封禁{}秒", reason, duration.as_secs()));
+    crate::coordination::publish(crate::coordination::Kind::Ban, source, Some(duration), &reason);
+    BANS.insert(source.to_string(), Ban { until: now + duration, reason });
+}
+
+/// 应用其他副本共享的封禁；本地已有更晚到期的封禁时保留本地的
+pub fn apply_shared(source: &str, remaining: Duration, reason: &str) {
+    let until = Instant::now() + remaining;
+    let mut ban = BANS.entry(source.to_string()).or_insert_with(|| Ban { until, reason: reason.to_string() });
+    if ban.until < until {
+        *ban = Ban { until, reason: reason.to_string() };
+    }
+}
+
+/// 撤销其他副本已解除的封禁
+pub fn lift_shared(source: &str) {
+    COUNTERS.remove(source);
+    BANS.remove(source);
+}
+
+/// 被拒绝的封禁请求计数
+pub fn record_rejected() {
+    BAN_EVENTS.with_label_values(&["rejected"]).inc();
+}
+
+// ===== 管理端：查看与解除封禁 =====
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BanView {
+    pub source: String,
+    pub reason: String,
+    pub remaining_secs: u64,
+}
+
+/// 生效中的封禁（按剩余时间从长到短），同时清理已到期的记录
+pub fn list() -> Vec<BanView> {
+    let now = Instant::now();
+    BANS.retain(|_, ban| ban.until > now);
+    let mut bans: Vec<BanView> = BANS
+        .iter()
+        .map(|entry| BanView {
+            source: entry.key().clone(),
+            reason: entry.reason.clone(),
+            remaining_secs: entry.until.saturating_duration_since(now).as_secs(),
+        })
+        .collect();
+    bans.sort_by(|a, b| b.remaining_secs.cmp(&a.remaining_secs).then_with(|| a.source.cmp(&b.source)));
+    bans
+}
+
+/// 解除封禁，返回此前是否处于封禁中
+pub fn lift(source: &str) -> bool {
+    COUNTERS.remove(source);
+    let lifted = BANS.remove(source).is_some_and(|(_, ban)| ban.until > Instant::now());
+    if lifted {
+        BAN_EVENTS.with_label_values(&["lifted"]).inc();
+        crate::audit::record("admin", "unban", source, "管理端解除封禁".to_string());
+    }
+    crate::coordination::withdraw(crate::coordination::Kind::Ban, source);
+    lifted
+}
+
+/// 校验封禁阈值
+pub fn validation_errors(settings: &Settings) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (key, max) in &settings.ban_thresholds {
+        if StatusMatch::parse(key).is_none() {
+            errors.push(format!("ban_thresholds的键必须是状态码或4xx/5xx: {}", key));
+        }
+        if *max == 0 {
+            errors.push(format!("ban_thresholds.{}必须大于0", key));
+        }
+    }
+    if settings.ban_window_secs == Some(0) {
+        errors.push("ban_window_secs必须大于0".to_string());
+    }
+    if settings.ban_duration_secs == Some(0) {
+        errors.push("ban_duration_secs必须大于0".to_string());
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_match() {
+        assert_eq!(StatusMatch::parse("401"), Some(StatusMatch::Exact(401)));
+        assert_eq!(StatusMatch::parse("4xx"), Some(StatusMatch::Class(4)));
+        assert_eq!(StatusMatch::parse("9xx"), None);
+        assert_eq!(StatusMatch::parse("40x"), None);
+        assert_eq!(StatusMatch::parse("600"), None);
+        assert!(StatusMatch::Class(4).matches(429));
+    }
+
+    #[test]
+    fn test_ban_after_threshold() {
+        let policy = BanPolicy {
+            thresholds: vec![("401".to_string(), StatusMatch::Exact(401), 3)],
+            window: Duration::from_secs(60),
+            duration: Duration::from_secs(600),
+        };
+        let sources = vec!["ip:10.9.9.1".to_string(), "token:test-ban-token".to_string()];
+        let now = Instant::now();
+        for _ in 0..3 {
+            observe_at(&policy, &sources, 401, now);
+        }
+        observe_at(&policy, &sources, 200, now);
+        assert_eq!(banned(&sources), None);
+
+        observe_at(&policy, &sources, 401, now);
+        assert!(banned(&sources).is_some());
+        assert!(banned(&["token:test-ban-token".to_string()]).is_some());
+        assert!(list().iter().any(|b| b.source == "ip:10.9.9.1" && b.reason.contains("4次401")));
+
+        assert!(lift("ip:10.9.9.1"));
+        assert!(!lift("ip:10.9.9.1"));
+        assert!(banned(&sources[..1]).is_none());
+
+        // 窗口过期后重新计数
+        let other = vec!["ip:10.9.9.2".to_string()];
+        for _ in 0..3 {
+            observe_at(&policy, &other, 401, now);
+        }
+        observe_at(&policy, &other, 401, now + Duration::from_secs(61));
+        assert_eq!(banned(&other), None);
+    }
+}
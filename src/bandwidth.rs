@@ -0,0 +1,114 @@
+use axum::body::{Body, Bytes};
+use axum::http::{Request, Response};
+use http_body::{Frame, SizeHint};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+
+use crate::metrics::{ROUTE_BYTES, TENANT_BYTES};
+
+/// 记录一次传输的字节数：按路由，以及按租户（有租户时）
+pub fn record(route: &str, tenant: Option<&str>, upload: u64, download: u64) {
+    let tenant = tenant.filter(|t| !t.is_empty());
+    for (direction, bytes) in [("upload", upload), ("download", download)] {
+        if bytes == 0 {
+            continue;
+        }
+        ROUTE_BYTES.with_label_values(&[route, direction]).inc_by(bytes);
+        if let Some(tenant) = tenant {
+            TENANT_BYTES.with_label_values(&[tenant, direction]).inc_by(bytes);
+        }
+    }
+}
+
+// 响应体结束（或客户端断开导致响应体被丢弃）时上报本次请求的字节数
+struct Report {
+    route: String,
+    tenant: Option<String>,
+    upload: Arc<AtomicU64>,
+    download: Arc<AtomicU64>,
+}
+
+impl Drop for Report {
+    fn drop(&mut self) {
+        record(
+            &self.route,
+            self.tenant.as_deref(),
+            self.upload.load(Ordering::Relaxed),
+            self.download.load(Ordering::Relaxed),
+        );
+    }
+}
+
+// 统计经过的数据帧字节数，不改变帧内容与长度提示
+struct Counting {
+    inner: Body,
+    bytes: Arc<AtomicU64>,
+    _report: Option<Report>,
+}
+
+impl http_body::Body for Counting {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, axum::Error>>> {
+        let poll = Pin::new(&mut self.inner).poll_frame(cx);
+        if let Poll::Ready(Some(Ok(frame))) = &poll
+            && let Some(chunk) = frame.data_ref()
+        {
+            self.bytes.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+        }
+        poll
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// 统计请求体（上传）字节数，返回的计数交给 count_response 一并上报
+pub fn count_request(req: &mut Request<Body>) -> Arc<AtomicU64> {
+    let bytes = Arc::new(AtomicU64::new(0));
+    let inner = std::mem::take(req.body_mut());
+    *req.body_mut() = Body::new(Counting { inner, bytes: bytes.clone(), _report: None });
+    bytes
+}
+
+/// 统计响应体（下载）字节数，响应体发送完毕后按路由与租户上报上传与下载字节数
+pub fn count_response(resp: Response<Body>, route: String, tenant: Option<String>, upload: Arc<AtomicU64>) -> Response<Body> {
+    let download = Arc::new(AtomicU64::new(0));
+    let report = Report { route, tenant, upload, download: download.clone() };
+    resp.map(|inner| Body::new(Counting { inner, bytes: download, _report: Some(report) }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body::Body as _;
+
+    #[tokio::test]
+    async fn test_count_request_and_response() {
+        let mut req = Request::new(Body::from("hello"));
+        let upload = count_request(&mut req);
+        let body = axum::body::to_bytes(req.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, "hello");
+
+        let resp = count_response(Response::new(Body::from("0123456789")), "bw-test".into(), Some("t1".into()), upload);
+        assert_eq!(resp.body().size_hint().exact(), Some(10));
+        axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+
+        assert_eq!(ROUTE_BYTES.with_label_values(&["bw-test", "upload"]).get(), 5);
+        assert_eq!(ROUTE_BYTES.with_label_values(&["bw-test", "download"]).get(), 10);
+        assert_eq!(TENANT_BYTES.with_label_values(&["t1", "download"]).get(), 10);
+
+        // 没有租户时只按路由统计
+        record("bw-test", Some(""), 1, 0);
+        assert_eq!(ROUTE_BYTES.with_label_values(&["bw-test", "upload"]).get(), 6);
+        assert_eq!(TENANT_BYTES.with_label_values(&["", "upload"]).get(), 0);
+    }
+}
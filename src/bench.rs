@@ -0,0 +1,151 @@
+use axum::Router;
+use reqwest::Method;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+use crate::cli::BenchOptions;
+use crate::config::{RouteRule, Settings};
+
+// 单个 worker 的采样结果
+#[derive(Default)]
+struct Samples {
+    latencies: Vec<Duration>,
+    statuses: BTreeMap<String, usize>,
+}
+
+// ===== 本地压测：经完整的路由、鉴权、限流与代理流水线 =====
+pub async fn run(opts: BenchOptions, settings: Settings, mut route_rules: Vec<RouteRule>) -> anyhow::Result<()> {
+    // 路由冲突诊断：重叠、不可达的路由与无效的白名单项
+    crate::route_diagnostics::enforce(&settings, &route_rules)?;
+    crate::upstream_url::enforce(&settings, &route_rules)?;
+    crate::rate_limit::enforce(&settings, &route_rules)?;
+    if opts.stub {
+        let stub_addr = spawn_stub_upstream().await?;
+        for rule in &mut route_rules {
+            rule.upstream = vec![format!("http://{}", stub_addr)];
+        }
+        println!("桩上游: http://{}", stub_addr);
+    }
+
+    let rate_limits = crate::rate_limit::init_rate_limits(&settings);
+    let (app, _) = crate::build_app(&settings, rate_limits, route_rules, Vec::new());
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let gateway_addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        let make_svc = app.into_make_service_with_connect_info::<SocketAddr>();
+        let _ = axum::serve(listener, make_svc).await;
+    });
+
+    let method = Method::from_bytes(opts.method.as_bytes())?;
+    let url = format!("http://{}{}", gateway_addr, opts.path);
+    let client = reqwest::Client::builder()
+        .pool_max_idle_per_host(opts.concurrency)
+        .build()?;
+    let issued = Arc::new(AtomicUsize::new(0));
+    let pacer = opts.qps.map(|qps| {
+        let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / qps.max(1) as f64));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        Arc::new(Mutex::new(interval))
+    });
+
+    println!(
+        "压测 {} {}：并发 {}，请求 {}{}",
+        opts.method,
+        opts.path,
+        opts.concurrency,
+        opts.requests,
+        opts.qps.map(|q| format!("，QPS 上限 {}", q)).unwrap_or_default()
+    );
+
+    let started = Instant::now();
+    let mut workers = Vec::with_capacity(opts.concurrency);
+    for _ in 0..opts.concurrency {
+        let client = client.clone();
+        let method = method.clone();
+        let url = url.clone();
+        let headers = opts.headers.clone();
+        let issued = issued.clone();
+        let pacer = pacer.clone();
+        let total = opts.requests;
+        workers.push(tokio::spawn(async move {
+            let mut samples = Samples::default();
+            while issued.fetch_add(1, Ordering::Relaxed) < total {
+                if let Some(pacer) = &pacer {
+                    pacer.lock().await.tick().await;
+                }
+                let mut rb = client.request(method.clone(), &url);
+                for (k, v) in &headers {
+                    rb = rb.header(k, v);
+                }
+                let start = Instant::now();
+                let status = match rb.send().await {
+                    Ok(resp) => {
+                        let status = resp.status().as_u16().to_string();
+                        let _ = resp.bytes().await;
+                        status
+                    }
+                    Err(_) => "error".to_string(),
+                };
+                samples.latencies.push(start.elapsed());
+                *samples.statuses.entry(status).or_insert(0) += 1;
+            }
+            samples
+        }));
+    }
+
+    let mut all = Samples::default();
+    for worker in workers {
+        let samples = worker.await?;
+        all.latencies.extend(samples.latencies);
+        for (status, n) in samples.statuses {
+            *all.statuses.entry(status).or_insert(0) += n;
+        }
+    }
+    let elapsed = started.elapsed();
+    all.latencies.sort();
+
+    println!("耗时 {:.2}s，吞吐 {:.1} req/s", elapsed.as_secs_f64(), all.latencies.len() as f64 / elapsed.as_secs_f64());
+    println!("状态码: {:?}", all.statuses);
+    for (label, p) in [("p50", 50.0), ("p90", 90.0), ("p99", 99.0), ("max", 100.0)] {
+        println!("{:>4}: {:.3}ms", label, percentile(&all.latencies, p).as_secs_f64() * 1000.0);
+    }
+    Ok(())
+}
+
+/// 最近秩法求百分位，输入需已排序
+pub fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+// 桩上游：对任何请求返回 200
+async fn spawn_stub_upstream() -> anyhow::Result<SocketAddr> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let app = Router::new().fallback(|| async { "ok" });
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, app).await;
+    });
+    Ok(addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        let samples: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&samples, 50.0), Duration::from_millis(50));
+        assert_eq!(percentile(&samples, 99.0), Duration::from_millis(99));
+        assert_eq!(percentile(&samples, 100.0), Duration::from_millis(100));
+        assert_eq!(percentile(&[], 50.0), Duration::ZERO);
+    }
+}
@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use helios::stub::StubUpstream;
+
+const USAGE: &str = "\
+用法: stub_upstream [选项]
+  -p, --port <PORT>          监听端口，可重复以启动多个实例（默认 30000）
+  --host <HOST>              监听地址（默认 0.0.0.0）
+  --name <NAME>              响应中的服务名（默认 service-<端口>）
+  --latency-ms <MS>          每个请求的固定延迟（默认 0）
+  --error-rate <RATE>        返回错误的比例 0~1（默认 0）
+  --error-status <CODE>      错误响应的状态码（默认 500）
+  --no-echo-headers          响应体中不回显请求头";
+
+struct Options {
+    host: String,
+    ports: Vec<u16>,
+    name: Option<String>,
+    latency_ms: u64,
+    error_rate: f64,
+    error_status: u16,
+    echo_headers: bool,
+}
+
+fn parse(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut opts = Options {
+        host: "0.0.0.0".to_string(),
+        ports: Vec::new(),
+        name: None,
+        latency_ms: 0,
+        error_rate: 0.0,
+        error_status: 500,
+        echo_headers: true,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} 缺少取值", arg));
+        match arg.as_str() {
+            "-p" | "--port" => opts.ports.push(value()?.parse().map_err(|_| "端口无效".to_string())?),
+            "--host" => opts.host = value()?,
+            "--name" => opts.name = Some(value()?),
+            "--latency-ms" => opts.latency_ms = value()?.parse().map_err(|_| "延迟无效".to_string())?,
+            "--error-rate" => opts.error_rate = value()?.parse().map_err(|_| "错误比例无效".to_string())?,
+            "--error-status" => opts.error_status = value()?.parse().map_err(|_| "状态码无效".to_string())?,
+            "--no-echo-headers" => opts.echo_headers = false,
+            other => return Err(format!("未知选项: {}", other)),
+        }
+    }
+    if opts.ports.is_empty() {
+        opts.ports.push(30000);
+    }
+    if !(0.0..=1.0).contains(&opts.error_rate) {
+        return Err("错误比例必须在0到1之间".to_string());
+    }
+    Ok(opts)
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+    let opts = match parse(std::env::args().skip(1)) {
+        Ok(opts) => opts,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            std::process::exit(2);
+        }
+    };
+    let error_status = StatusCode::from_u16(opts.error_status)?;
+
+    let mut handles = Vec::new();
+    for port in &opts.ports {
+        let listener = tokio::net::TcpListener::bind((opts.host.as_str(), *port)).await?;
+        let name = opts.name.clone().unwrap_or_else(|| format!("service-{}", port));
+        let handle = StubUpstream::new(name.as_str())
+            .latency(Duration::from_millis(opts.latency_ms))
+            .error_rate(opts.error_rate)
+            .error_status(error_status)
+            .echo_headers(opts.echo_headers)
+            .spawn_on(listener)?;
+        tracing::info!("{} listening on http://{}", name, handle.addr());
+        handles.push(handle);
+    }
+
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
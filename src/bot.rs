@@ -0,0 +1,156 @@
+use axum::http::{HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+
+/// 标记模式下转发给上游的请求头，值为命中的规则
+pub const BOT_HEADER: &str = "x-gateway-bot";
+
+/// 未配置时使用的扫描器 User-Agent 片段
+pub const DEFAULT_USER_AGENTS: &[&str] = &["sqlmap", "nikto", "nmap", "masscan", "zgrab", "nuclei", "dirbuster", "gobuster"];
+/// 未配置时使用的扫描路径
+pub const DEFAULT_SCAN_PATHS: &[&str] = &["/wp-admin", "/wp-login.php", "/xmlrpc.php", "/.env", "/.git", "/phpmyadmin"];
+/// 未配置时要求携带的请求头
+pub const DEFAULT_REQUIRE_HEADERS: &[&str] = &["user-agent"];
+
+/// 路由级爬虫与扫描器识别规则，未配置的列表使用内置默认值
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct BotRules {
+    // block（默认，返回 403）或 tag（转发并附带 X-Gateway-Bot 头）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+    // User-Agent 包含任一片段即命中（不区分大小写）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agents: Option<Vec<String>>,
+    // 缺少任一请求头即命中
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub require_headers: Option<Vec<String>>,
+    // 路径中出现任一路径段即命中（不区分大小写），如 /.env 命中 /api/.env
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scan_paths: Option<Vec<String>>,
+}
+
+/// 命中的规则类型，作为指标标签与标记头的值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    UserAgent,
+    MissingHeader,
+    ScanPath,
+}
+
+impl Rule {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rule::UserAgent => "user_agent",
+            Rule::MissingHeader => "missing_header",
+            Rule::ScanPath => "scan_path",
+        }
+    }
+}
+
+fn list<'a>(configured: &'a Option<Vec<String>>, default: &'a [&'static str]) -> Box<dyn Iterator<Item = &'a str> + 'a> {
+    match configured {
+        Some(items) => Box::new(items.iter().map(String::as_str)),
+        None => Box::new(default.iter().copied()),
+    }
+}
+
+impl BotRules {
+    pub fn blocks(&self) -> bool {
+        self.action.as_deref() != Some("tag")
+    }
+
+    /// 返回第一个命中的规则
+    pub fn detect(&self, headers: &HeaderMap, path: &str) -> Option<Rule> {
+        let path = path.to_ascii_lowercase();
+        if list(&self.scan_paths, DEFAULT_SCAN_PATHS).any(|p| {
+            let p = p.to_ascii_lowercase();
+            path.ends_with(&p) || path.contains(&format!("{}/", p))
+        }) {
+            return Some(Rule::ScanPath);
+        }
+        if list(&self.require_headers, DEFAULT_REQUIRE_HEADERS).any(|h| !headers.contains_key(h)) {
+            return Some(Rule::MissingHeader);
+        }
+        let agent = headers.get(axum::http::header::USER_AGENT).and_then(|v| v.to_str().ok())?.to_ascii_lowercase();
+        list(&self.user_agents, DEFAULT_USER_AGENTS)
+            .any(|pattern| agent.contains(&pattern.to_ascii_lowercase()))
+            .then_some(Rule::UserAgent)
+    }
+
+    /// 标记模式：写入标记头，覆盖客户端自带的同名头
+    pub fn tag(headers: &mut HeaderMap, rule: Rule) {
+        headers.insert(BOT_HEADER, HeaderValue::from_static(rule.as_str()));
+    }
+
+    pub fn validation_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if let Some(action) = &self.action
+            && !matches!(action.as_str(), "block" | "tag")
+        {
+            errors.push(format!("bot.action必须是block或tag: {}", action));
+        }
+        for pattern in self.user_agents.iter().flatten().filter(|p| p.trim().is_empty()) {
+            errors.push(format!("bot.user_agents不能包含空字符串: {:?}", pattern));
+        }
+        for header in self.require_headers.iter().flatten() {
+            if axum::http::HeaderName::from_bytes(header.as_bytes()).is_err() {
+                errors.push(format!("bot.require_headers包含非法的头名: {}", header));
+            }
+        }
+        for path in self.scan_paths.iter().flatten().filter(|p| !p.starts_with('/') || p.len() < 2) {
+            errors.push(format!("bot.scan_paths必须以 / 开头且不能为根路径: {}", path));
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn test_detect_defaults() {
+        let rules = BotRules::default();
+        let browser = headers(&[("user-agent", "Mozilla/5.0")]);
+        assert_eq!(rules.detect(&browser, "/api/orders"), None);
+        assert_eq!(rules.detect(&browser, "/api/.env"), Some(Rule::ScanPath));
+        assert_eq!(rules.detect(&browser, "/WP-Admin/setup.php"), Some(Rule::ScanPath));
+        assert_eq!(rules.detect(&browser, "/api/.environment"), None);
+        assert_eq!(rules.detect(&HeaderMap::new(), "/api/orders"), Some(Rule::MissingHeader));
+        assert_eq!(rules.detect(&headers(&[("user-agent", "sqlmap/1.7")]), "/api/orders"), Some(Rule::UserAgent));
+        assert!(rules.blocks());
+    }
+
+    #[test]
+    fn test_configured_rules() {
+        let rules = BotRules {
+            action: Some("tag".to_string()),
+            user_agents: Some(vec!["python-requests".to_string()]),
+            require_headers: Some(vec!["accept".to_string()]),
+            scan_paths: Some(Vec::new()),
+        };
+        assert!(!rules.blocks());
+        assert_eq!(rules.detect(&headers(&[("accept", "*/*"), ("user-agent", "sqlmap")]), "/.env"), None);
+        assert_eq!(rules.detect(&headers(&[("user-agent", "Python-Requests/2.31")]), "/"), Some(Rule::MissingHeader));
+        assert_eq!(
+            rules.detect(&headers(&[("accept", "*/*"), ("user-agent", "Python-Requests/2.31")]), "/"),
+            Some(Rule::UserAgent)
+        );
+
+        let bad = BotRules {
+            action: Some("drop".to_string()),
+            require_headers: Some(vec!["bad header".to_string()]),
+            scan_paths: Some(vec!["env".to_string(), "/".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(bad.validation_errors().len(), 4);
+    }
+}
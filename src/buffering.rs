@@ -0,0 +1,407 @@
+use axum::body::{Body, Bytes};
+use http_body::{Body as _, Frame};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::config::RouteRule;
+
+/// 缓冲请求体/响应体时未配置上限的默认值
+pub const DEFAULT_MAX_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+/// 路由的缓冲策略：缓冲的一侧整体读入内存（可重试、可校验、可解压），不缓冲的一侧边收边转发
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Buffering {
+    None,
+    Request,
+    Response,
+    Full,
+}
+
+impl Buffering {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "none" => Some(Buffering::None),
+            "request" => Some(Buffering::Request),
+            "response" => Some(Buffering::Response),
+            "full" => Some(Buffering::Full),
+            _ => None,
+        }
+    }
+
+    /// 未配置时完整缓冲，与此前的行为一致
+    pub fn for_route(rule: &RouteRule) -> Self {
+        rule.buffering.as_deref().and_then(Self::parse).unwrap_or(Buffering::Full)
+    }
+
+    pub fn request(&self) -> bool {
+        matches!(self, Buffering::Request | Buffering::Full)
+    }
+
+    pub fn response(&self) -> bool {
+        matches!(self, Buffering::Response | Buffering::Full)
+    }
+}
+
+/// 流式响应的刷新策略：immediate 收到上游数据即转发（默认），coalesce 攒够字节数或等待超时后再转发
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flush {
+    Immediate,
+    Coalesce { max_bytes: usize, max_delay: Duration },
+}
+
+impl Flush {
+    pub const DEFAULT_BYTES: usize = 64 * 1024;
+    pub const DEFAULT_INTERVAL_MS: u64 = 100;
+
+    pub fn for_route(rule: &RouteRule) -> Self {
+        match rule.flush.as_deref() {
+            Some("coalesce") => Flush::Coalesce {
+                max_bytes: rule.flush_bytes.unwrap_or(Self::DEFAULT_BYTES),
+                max_delay: Duration::from_millis(rule.flush_interval_ms.unwrap_or(Self::DEFAULT_INTERVAL_MS)),
+            },
+            _ => Flush::Immediate,
+        }
+    }
+
+    /// 按刷新策略把上游响应体转为流式响应体
+    pub fn body(&self, resp: reqwest::Response) -> Body {
+        match *self {
+            Flush::Immediate => Body::from_stream(resp.bytes_stream()),
+            Flush::Coalesce { max_bytes, max_delay } => {
+                let inner = axum::http::Response::<reqwest::Body>::from(resp).into_body();
+                Body::new(Coalesce { inner, buf: Vec::new(), max_bytes, max_delay, deadline: None, pending: None, done: false })
+            }
+        }
+    }
+}
+
+// 合并上游的小数据块：缓冲达到 max_bytes，或首个未发送的数据块等待超过 max_delay 时输出一帧
+struct Coalesce<B> {
+    inner: B,
+    buf: Vec<u8>,
+    max_bytes: usize,
+    max_delay: Duration,
+    deadline: Option<Pin<Box<tokio::time::Sleep>>>,
+    // 缓冲非空时收到的非数据帧（trailers），在缓冲输出后再返回
+    pending: Option<Frame<Bytes>>,
+    done: bool,
+}
+
+impl<B> Coalesce<B> {
+    fn take(&mut self) -> Frame<Bytes> {
+        self.deadline = None;
+        Frame::data(Bytes::from(std::mem::take(&mut self.buf)))
+    }
+}
+
+impl<B> http_body::Body for Coalesce<B>
+where
+    B: http_body::Body<Data = Bytes> + Unpin,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, B::Error>>> {
+        let this = &mut *self;
+        loop {
+            if this.buf.is_empty()
+                && let Some(frame) = this.pending.take()
+            {
+                return Poll::Ready(Some(Ok(frame)));
+            }
+            if this.done {
+                return Poll::Ready((!this.buf.is_empty()).then(|| Ok(this.take())));
+            }
+            match Pin::new(&mut this.inner).poll_frame(cx) {
+                Poll::Ready(Some(Ok(frame))) => match frame.into_data() {
+                    Ok(chunk) => {
+                        this.buf.extend_from_slice(&chunk);
+                        if this.buf.len() >= this.max_bytes {
+                            return Poll::Ready(Some(Ok(this.take())));
+                        }
+                        if this.deadline.is_none() && !this.buf.is_empty() {
+                            this.deadline = Some(Box::pin(tokio::time::sleep(this.max_delay)));
+                        }
+                    }
+                    Err(frame) if this.buf.is_empty() => return Poll::Ready(Some(Ok(frame))),
+                    Err(frame) => {
+                        this.pending = Some(frame);
+                        return Poll::Ready(Some(Ok(this.take())));
+                    }
+                },
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                Poll::Ready(None) => this.done = true,
+                Poll::Pending => {
+                    return match this.deadline.as_mut().map(|d| d.as_mut().poll(cx)) {
+                        Some(Poll::Ready(())) => Poll::Ready(Some(Ok(this.take()))),
+                        _ => Poll::Pending,
+                    };
+                }
+            }
+        }
+    }
+}
+
+/// 流式转发的响应体超过 limit 字节时中断（响应头已发出，客户端收到不完整的响应）
+pub fn limit_body(body: Body, limit: usize, route: String) -> Body {
+    Body::new(Limited { inner: body, remaining: limit, limit, route })
+}
+
+struct Limited {
+    inner: Body,
+    remaining: usize,
+    limit: usize,
+    route: String,
+}
+
+impl http_body::Body for Limited {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, axum::Error>>> {
+        let this = &mut *self;
+        match Pin::new(&mut this.inner).poll_frame(cx) {
+            Poll::Ready(Some(Ok(frame))) => {
+                let len = frame.data_ref().map_or(0, Bytes::len);
+                if len > this.remaining {
+                    tracing::warn!(route = %this.route, limit = this.limit, "上游流式响应体超过大小上限，中断转发");
+                    crate::metrics::RESPONSE_TOO_LARGE.with_label_values(&[&this.route, "streamed"]).inc();
+                    this.remaining = 0;
+                    let err = std::io::Error::other(format!("Upstream response body exceeds {} bytes", this.limit));
+                    return Poll::Ready(Some(Err(axum::Error::new(err))));
+                }
+                this.remaining -= len;
+                Poll::Ready(Some(Ok(frame)))
+            }
+            other => other,
+        }
+    }
+}
+
+/// 读取请求体/响应体失败的原因
+#[derive(Debug)]
+pub enum ReadError {
+    TooLarge(usize),
+    Body(String),
+}
+
+/// 缓冲读取请求体，超过上限立即停止
+pub async fn read_request(mut body: Body, limit: usize) -> Result<Bytes, ReadError> {
+    if body.size_hint().lower() > limit as u64 {
+        return Err(ReadError::TooLarge(limit));
+    }
+    let mut buf = Vec::new();
+    while let Some(frame) = std::future::poll_fn(|cx| Pin::new(&mut body).poll_frame(cx)).await {
+        let frame = frame.map_err(|e| ReadError::Body(e.to_string()))?;
+        if let Ok(chunk) = frame.into_data() {
+            if buf.len() + chunk.len() > limit {
+                return Err(ReadError::TooLarge(limit));
+            }
+            buf.extend_from_slice(&chunk);
+        }
+    }
+    Ok(Bytes::from(buf))
+}
+
+/// 缓冲读取上游响应体，超过上限立即停止
+pub async fn read_response(mut resp: reqwest::Response, limit: usize) -> Result<Bytes, ReadError> {
+    if resp.content_length().is_some_and(|len| len > limit as u64) {
+        return Err(ReadError::TooLarge(limit));
+    }
+    let mut buf = Vec::new();
+    while let Some(chunk) = resp.chunk().await.map_err(|e| ReadError::Body(e.to_string()))? {
+        if buf.len() + chunk.len() > limit {
+            return Err(ReadError::TooLarge(limit));
+        }
+        buf.extend_from_slice(&chunk);
+    }
+    Ok(Bytes::from(buf))
+}
+
+// ===== 发往上游的请求体 =====
+pub enum RequestBody {
+    Buffered(Bytes),
+    // 流式请求体只能发送一次
+    Streaming(Option<reqwest::Body>),
+}
+
+impl RequestBody {
+    /// 不缓冲时直接转发客户端的请求体流；已知为空的请求体按缓冲处理，避免以 chunked 发送
+    pub fn streaming(body: Body) -> Self {
+        if body.size_hint().exact() == Some(0) {
+            return RequestBody::Buffered(Bytes::new());
+        }
+        RequestBody::Streaming(Some(reqwest::Body::wrap_stream(body.into_data_stream())))
+    }
+
+    /// 能否在换节点重试时重新发送
+    pub fn replayable(&self) -> bool {
+        matches!(self, RequestBody::Buffered(_))
+    }
+
+    pub fn take(&mut self) -> reqwest::Body {
+        match self {
+            RequestBody::Buffered(bytes) => bytes.clone().into(),
+            RequestBody::Streaming(body) => body.take().unwrap_or_else(|| Bytes::new().into()),
+        }
+    }
+}
+
+/// 校验缓冲策略与依赖缓冲的路由配置
+pub fn validation_errors(rule: &RouteRule) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (field, limit) in [
+        ("max_request_body_bytes", rule.max_request_body_bytes),
+        ("max_response_body_bytes", rule.max_response_body_bytes),
+        ("max_streamed_response_bytes", rule.max_streamed_response_bytes),
+    ] {
+        if limit == Some(0) {
+            errors.push(format!("{}必须大于0", field));
+        }
+    }
+    match rule.flush.as_deref() {
+        None | Some("immediate") => {}
+        Some("coalesce") => {
+            if rule.flush_bytes == Some(0) {
+                errors.push("flush_bytes必须大于0".to_string());
+            }
+        }
+        Some(other) => errors.push(format!("flush必须为immediate或coalesce，当前为: {}", other)),
+    }
+    if rule.flush.is_some() && Buffering::for_route(rule).response() {
+        errors.push("flush只对流式响应生效，buffering必须为request或none".to_string());
+    }
+    let Some(mode) = &rule.buffering else {
+        return errors;
+    };
+    let Some(buffering) = Buffering::parse(mode) else {
+        errors.push(format!("buffering必须为request、response、none或full，当前为: {}", mode));
+        return errors;
+    };
+    if rule.request_schema.is_some() && !buffering.request() {
+        errors.push("request_schema需要缓冲请求体，buffering必须为request或full".to_string());
+    }
+    if rule.decompress.unwrap_or(false) && !buffering.response() {
+        errors.push("decompress需要缓冲响应体，buffering必须为response或full".to_string());
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_limits() {
+        assert_eq!(read_request(Body::from("hello"), 5).await.unwrap(), "hello");
+        assert!(matches!(read_request(Body::from("hello!"), 5).await, Err(ReadError::TooLarge(5))));
+
+        let resp = reqwest::Response::from(axum::http::Response::new("0123456789"));
+        assert!(matches!(read_response(resp, 8).await, Err(ReadError::TooLarge(8))));
+        let resp = reqwest::Response::from(axum::http::Response::new("0123"));
+        assert_eq!(read_response(resp, 8).await.unwrap(), "0123");
+    }
+
+    #[tokio::test]
+    async fn test_limit_body() {
+        let body = limit_body(Body::from("0123456789"), 16, "limit-test".to_string());
+        assert_eq!(axum::body::to_bytes(body, usize::MAX).await.unwrap(), "0123456789");
+        let body = limit_body(Body::from("0123456789"), 8, "limit-test".to_string());
+        assert!(axum::body::to_bytes(body, usize::MAX).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_coalesce_flush() {
+        use http_body::Body as _;
+        // 上游分三次发送 4 字节：攒够 8 字节输出一帧，剩余部分在结束时输出
+        let (mut tx, body) = http_body_channel();
+        let mut body = Coalesce { inner: body, buf: Vec::new(), max_bytes: 8, max_delay: Duration::from_secs(60), deadline: None, pending: None, done: false };
+        for chunk in ["aaaa", "bbbb", "cccc"] {
+            tx.push(chunk);
+        }
+        tx.close();
+        let mut frames = Vec::new();
+        while let Some(frame) = std::future::poll_fn(|cx| Pin::new(&mut body).poll_frame(cx)).await {
+            frames.push(frame.unwrap().into_data().unwrap());
+        }
+        assert_eq!(frames, ["aaaabbbb", "cccc"]);
+
+        // 上游暂停时，超过 max_delay 后输出已缓冲的数据
+        let (mut tx, body) = http_body_channel();
+        let mut body = Coalesce { inner: body, buf: Vec::new(), max_bytes: 1024, max_delay: Duration::from_millis(20), deadline: None, pending: None, done: false };
+        tx.push("progress 10%");
+        let frame = tokio::time::timeout(Duration::from_secs(1), std::future::poll_fn(|cx| Pin::new(&mut body).poll_frame(cx)))
+            .await
+            .expect("coalesced data should flush after max_delay");
+        assert_eq!(frame.unwrap().unwrap().into_data().unwrap(), "progress 10%");
+    }
+
+    // 测试用的可控响应体：数据块按推入顺序返回，未关闭且无数据时返回 Pending
+    #[derive(Default)]
+    struct ChannelBody {
+        chunks: std::collections::VecDeque<Bytes>,
+        closed: bool,
+    }
+
+    #[derive(Clone)]
+    struct Sender(std::sync::Arc<std::sync::Mutex<ChannelBody>>);
+
+    impl Sender {
+        fn push(&mut self, chunk: &'static str) {
+            self.0.lock().unwrap().chunks.push_back(Bytes::from(chunk));
+        }
+
+        fn close(&mut self) {
+            self.0.lock().unwrap().closed = true;
+        }
+    }
+
+    struct Receiver(std::sync::Arc<std::sync::Mutex<ChannelBody>>);
+
+    impl http_body::Body for Receiver {
+        type Data = Bytes;
+        type Error = std::convert::Infallible;
+
+        fn poll_frame(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, Self::Error>>> {
+            let mut state = self.0.lock().unwrap();
+            match state.chunks.pop_front() {
+                Some(chunk) => Poll::Ready(Some(Ok(Frame::data(chunk)))),
+                None if state.closed => Poll::Ready(None),
+                None => Poll::Pending,
+            }
+        }
+    }
+
+    fn http_body_channel() -> (Sender, Receiver) {
+        let state = std::sync::Arc::new(std::sync::Mutex::new(ChannelBody::default()));
+        (Sender(state.clone()), Receiver(state))
+    }
+
+    #[test]
+    fn test_validation() {
+        let rule = RouteRule { buffering: Some("response".to_string()), ..Default::default() };
+        assert_eq!(Buffering::for_route(&rule), Buffering::Response);
+        assert!(!Buffering::for_route(&rule).request());
+        assert_eq!(Buffering::for_route(&RouteRule::default()), Buffering::Full);
+        assert!(validation_errors(&rule).is_empty());
+
+        let streaming = RouteRule {
+            buffering: Some("none".to_string()),
+            request_schema: Some("order.json".to_string()),
+            decompress: Some(true),
+            max_response_body_bytes: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(validation_errors(&streaming).len(), 3);
+        let bad = RouteRule { buffering: Some("both".to_string()), ..Default::default() };
+        assert_eq!(validation_errors(&bad).len(), 1);
+
+        let flush = RouteRule { buffering: Some("request".to_string()), flush: Some("coalesce".to_string()), ..Default::default() };
+        assert!(validation_errors(&flush).is_empty());
+        assert!(matches!(Flush::for_route(&flush), Flush::Coalesce { max_bytes: Flush::DEFAULT_BYTES, .. }));
+        let buffered = RouteRule { flush: Some("immediate".to_string()), ..Default::default() };
+        assert_eq!(validation_errors(&buffered).len(), 1);
+    }
+}
@@ -0,0 +1,427 @@
+//! 请求抓取：为指定路由完整记录请求与响应（方法、地址、状态码、脱敏后的头部、截断后的请求体与响应体、耗时），
+//! 以 OpenTelemetry 日志记录（OTLP/HTTP JSON）批量导出到 otel_logs_endpoint，用于单独排查某个有问题的对接方，
+//! 而不必打开全局调试日志。记录带上 W3C traceparent 中的 trace id，网关以新的 span id 把 traceparent 传给上游，
+//! 便于与上下游的链路数据关联。记录按 sample_rate 采样；导出队列已满时直接丢弃，不阻塞请求。
+use axum::{
+    body::{Body, Bytes},
+    extract::Request,
+    http::{HeaderMap, HeaderValue, Response},
+};
+use base64::Engine;
+use http_body::{Frame, SizeHint};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+
+use crate::config::{RouteRule, Settings};
+use crate::metrics::CAPTURE_RECORDS;
+
+pub const TRACEPARENT: &str = "traceparent";
+
+const DEFAULT_MAX_BODY_BYTES: usize = 4096;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+const DEFAULT_SERVICE_NAME: &str = "helios";
+// 导出队列长度与单批最多的记录数
+const QUEUE_SIZE: usize = 1024;
+const BATCH_SIZE: usize = 100;
+
+// 值会被替换为 [REDACTED] 的头部
+const REDACTED_HEADERS: [&str; 5] = ["authorization", "proxy-authorization", "cookie", "set-cookie", "x-api-key"];
+
+/// 路由级抓取配置
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CapturePolicy {
+    // 采样比例 0~1，默认全部记录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<f64>,
+    // 请求体与响应体各自最多记录的字节数，默认 4096
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_body_bytes: Option<usize>,
+    // 是否记录请求头与响应头，默认 true
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headers: Option<bool>,
+}
+
+pub fn validation_errors(rule: &RouteRule) -> Vec<String> {
+    let mut errors = Vec::new();
+    let Some(policy) = &rule.capture else {
+        return errors;
+    };
+    if policy.sample_rate.is_some_and(|rate| !(0.0..=1.0).contains(&rate)) {
+        errors.push("capture.sample_rate必须在0到1之间".to_string());
+    }
+    if policy.max_body_bytes.is_some_and(|n| n > MAX_BODY_BYTES) {
+        errors.push(format!("capture.max_body_bytes不能超过{}", MAX_BODY_BYTES));
+    }
+    errors
+}
+
+pub fn settings_errors(settings: &Settings) -> Vec<String> {
+    let mut errors = Vec::new();
+    if let Some(endpoint) = &settings.otel_logs_endpoint
+        && !(endpoint.starts_with("http://") || endpoint.starts_with("https://"))
+    {
+        errors.push(format!("otel_logs_endpoint必须是 http(s) 地址: {}", endpoint));
+    }
+    errors
+}
+
+// ===== W3C trace context =====
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+}
+
+impl TraceContext {
+    /// 解析 `00-<trace id>-<span id>-<flags>`，全零 id 视为无效
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split('-');
+        let (version, trace_id, span_id, flags) = (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        if version.len() != 2 || version == "ff" || flags.len() != 2 || (version == "00" && parts.next().is_some()) {
+            return None;
+        }
+        hex::decode(version).ok()?;
+        hex::decode(flags).ok()?;
+        let mut context = Self { trace_id: [0; 16], span_id: [0; 8] };
+        hex::decode_to_slice(trace_id, &mut context.trace_id).ok()?;
+        hex::decode_to_slice(span_id, &mut context.span_id).ok()?;
+        (context.trace_id != [0; 16] && context.span_id != [0; 8]).then_some(context)
+    }
+
+    /// 沿用请求中的 trace id（没有则新建），为网关这一跳生成新的 span id
+    pub fn child_of(headers: &HeaderMap) -> Self {
+        let parent = headers.get(TRACEPARENT).and_then(|v| v.to_str().ok()).and_then(Self::parse);
+        Self { trace_id: parent.map_or_else(rand::random, |p| p.trace_id), span_id: rand::random() }
+    }
+
+    pub fn header(&self) -> String {
+        format!("00-{}-{}-01", hex::encode(self.trace_id), hex::encode(self.span_id))
+    }
+}
+
+// ===== 导出 =====
+struct Exporter {
+    client: reqwest::Client,
+    url: String,
+    resource: Value,
+}
+
+static QUEUE: OnceCell<mpsc::Sender<Value>> = OnceCell::new();
+
+/// 配置了 otel_logs_endpoint 时启动后台导出；须在 tokio 运行时中调用
+pub fn spawn_exporter(settings: &Settings, routes: &[RouteRule]) {
+    let Some(endpoint) = &settings.otel_logs_endpoint else {
+        if let Some(rule) = routes.iter().find(|r| r.capture.is_some()) {
+            tracing::warn!(route = %crate::proxy::route_label(rule), "路由配置了 capture 但未设置 otel_logs_endpoint，不会抓取请求");
+        }
+        return;
+    };
+    let (tx, rx) = mpsc::channel(QUEUE_SIZE);
+    if QUEUE.set(tx).is_err() {
+        return;
+    }
+    let service = settings.otel_service_name.as_deref().unwrap_or(DEFAULT_SERVICE_NAME);
+    let exporter = Exporter {
+        client: reqwest::Client::builder().timeout(Duration::from_secs(10)).build().unwrap_or_default(),
+        url: format!("{}/v1/logs", endpoint.trim_end_matches('/')),
+        resource: json!({ "attributes": [attribute("service.name", json!({ "stringValue": service }))] }),
+    };
+    tokio::spawn(run(exporter, rx));
+}
+
+async fn run(exporter: Exporter, mut records: mpsc::Receiver<Value>) {
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    while records.recv_many(&mut batch, BATCH_SIZE).await > 0 {
+        let count = batch.len() as u64;
+        let payload = json!({
+            "resourceLogs": [{
+                "resource": exporter.resource,
+                "scopeLogs": [{ "scope": { "name": "helios.capture" }, "logRecords": std::mem::take(&mut batch) }],
+            }]
+        });
+        let result = exporter.client.post(&exporter.url).json(&payload).send().await.and_then(|r| r.error_for_status());
+        match result {
+            Ok(_) => CAPTURE_RECORDS.with_label_values(&["exported"]).inc_by(count),
+            Err(err) => {
+                tracing::warn!(count, "导出抓取记录失败: {}", err);
+                CAPTURE_RECORDS.with_label_values(&["failed"]).inc_by(count);
+            }
+        }
+    }
+}
+
+fn send(record: Value) {
+    let Some(queue) = QUEUE.get() else { return };
+    if queue.try_send(record).is_err() {
+        CAPTURE_RECORDS.with_label_values(&["dropped"]).inc();
+    }
+}
+
+// ===== 记录 =====
+#[derive(Debug, Default)]
+struct Captured {
+    bytes: Vec<u8>,
+    total: usize,
+}
+
+impl Captured {
+    fn push(&mut self, chunk: &[u8], limit: usize) {
+        let room = limit.saturating_sub(self.bytes.len());
+        self.bytes.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        self.total += chunk.len();
+    }
+}
+
+/// 复制流经的数据块（最多 limit 字节），不改变转发内容
+struct Tee {
+    inner: Body,
+    captured: Arc<Mutex<Captured>>,
+    limit: usize,
+    // 响应体读完或被丢弃时导出记录
+    pending: Option<Pending>,
+}
+
+impl http_body::Body for Tee {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, axum::Error>>> {
+        let this = &mut *self;
+        let polled = Pin::new(&mut this.inner).poll_frame(cx);
+        if let Poll::Ready(Some(Ok(frame))) = &polled
+            && let (Some(data), Ok(mut captured)) = (frame.data_ref(), this.captured.lock())
+        {
+            captured.push(data, this.limit);
+        }
+        // 已知长度的响应体读完最后一块后，服务端不会再轮询到 None
+        if let Some(pending) = &mut this.pending
+            && (matches!(polled, Poll::Ready(None)) || this.inner.is_end_stream())
+        {
+            pending.complete = true;
+        }
+        polled
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for Tee {
+    fn drop(&mut self) {
+        if let Some(pending) = self.pending.take() {
+            let response_body = std::mem::take(&mut *self.captured.lock().unwrap_or_else(|e| e.into_inner()));
+            send(pending.record(response_body));
+        }
+    }
+}
+
+/// 请求开始时确定的记录内容，响应结束后补全
+struct Pending {
+    route: String,
+    trace: TraceContext,
+    started: Instant,
+    started_at: SystemTime,
+    headers: bool,
+    attributes: Vec<Value>,
+    request_body: Arc<Mutex<Captured>>,
+    status: u16,
+    response_headers: HeaderMap,
+    complete: bool,
+}
+
+impl Pending {
+    fn record(self, response_body: Captured) -> Value {
+        let mut attributes = self.attributes;
+        attributes.push(attribute("http.response.status_code", json!({ "intValue": self.status.to_string() })));
+        attributes.push(attribute("duration_ms", json!({ "intValue": self.started.elapsed().as_millis().to_string() })));
+        let request_body = std::mem::take(&mut *self.request_body.lock().unwrap_or_else(|e| e.into_inner()));
+        attributes.extend(body_attributes("http.request.body", request_body));
+        if self.headers {
+            attributes.extend(header_attributes("http.response.header", &self.response_headers));
+        }
+        attributes.extend(body_attributes("http.response.body", response_body));
+        attributes.push(attribute("http.response.body.complete", json!({ "boolValue": self.complete })));
+        let nanos = |t: SystemTime| t.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos().to_string();
+        // 5xx 记为 ERROR，其余为 INFO
+        let (severity, severity_text) = if self.status >= 500 { (17, "ERROR") } else { (9, "INFO") };
+        json!({
+            "timeUnixNano": nanos(self.started_at),
+            "observedTimeUnixNano": nanos(SystemTime::now()),
+            "severityNumber": severity,
+            "severityText": severity_text,
+            "body": { "stringValue": format!("captured request on route {}", self.route) },
+            "attributes": attributes,
+            "traceId": hex::encode(self.trace.trace_id),
+            "spanId": hex::encode(self.trace.span_id),
+            "flags": 1,
+        })
+    }
+}
+
+fn attribute(key: &str, value: Value) -> Value {
+    json!({ "key": key, "value": value })
+}
+
+fn header_attributes(prefix: &str, headers: &HeaderMap) -> Vec<Value> {
+    headers
+        .keys()
+        .map(|name| {
+            let value = if REDACTED_HEADERS.contains(&name.as_str()) {
+                "[REDACTED]".to_string()
+            } else {
+                headers.get_all(name).iter().map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned()).collect::<Vec<_>>().join(", ")
+            };
+            attribute(&format!("{}.{}", prefix, name), json!({ "stringValue": value }))
+        })
+        .collect()
+}
+
+// 文本原样记录，二进制内容以 base64 记录（OTLP 的 bytesValue）
+fn body_attributes(prefix: &str, captured: Captured) -> Vec<Value> {
+    let truncated = captured.total > captured.bytes.len();
+    let value = match String::from_utf8(captured.bytes) {
+        Ok(text) => json!({ "stringValue": text }),
+        Err(err) => json!({ "bytesValue": base64::engine::general_purpose::STANDARD.encode(err.into_bytes()) }),
+    };
+    vec![
+        attribute(prefix, value),
+        attribute(&format!("{}.size", prefix), json!({ "intValue": captured.total.to_string() })),
+        attribute(&format!("{}.truncated", prefix), json!({ "boolValue": truncated })),
+    ]
+}
+
+/// 请求的抓取句柄：由 start 创建，拿到响应后调用 finish
+pub struct Capture(Pending, usize);
+
+/// 路由开启抓取且命中采样时，复制请求体并向上游传递 traceparent
+pub fn start(rule: &RouteRule, req: &mut Request<Body>) -> Option<Capture> {
+    let policy = rule.capture.as_ref()?;
+    QUEUE.get()?;
+    if rand::random::<f64>() >= policy.sample_rate.unwrap_or(1.0) {
+        return None;
+    }
+    let limit = policy.max_body_bytes.unwrap_or(DEFAULT_MAX_BODY_BYTES);
+    let headers = policy.headers.unwrap_or(true);
+    let route = crate::proxy::route_label(rule);
+    let trace = TraceContext::child_of(req.headers());
+
+    let mut attributes = vec![
+        attribute("route", json!({ "stringValue": route })),
+        attribute("http.request.method", json!({ "stringValue": req.method().as_str() })),
+        attribute("url.path", json!({ "stringValue": req.uri().path() })),
+    ];
+    if let Some(query) = req.uri().query() {
+        attributes.push(attribute("url.query", json!({ "stringValue": query })));
+    }
+    if let Some(id) = req.headers().get(crate::problem::REQUEST_ID_HEADER).and_then(|v| v.to_str().ok()) {
+        attributes.push(attribute("request_id", json!({ "stringValue": id })));
+    }
+    if headers {
+        attributes.extend(header_attributes("http.request.header", req.headers()));
+    }
+    if let Ok(value) = HeaderValue::from_str(&trace.header()) {
+        req.headers_mut().insert(TRACEPARENT, value);
+    }
+
+    let request_body = Arc::new(Mutex::new(Captured::default()));
+    let body = std::mem::take(req.body_mut());
+    *req.body_mut() = Body::new(Tee { inner: body, captured: request_body.clone(), limit, pending: None });
+    let pending = Pending {
+        route,
+        trace,
+        started: Instant::now(),
+        started_at: SystemTime::now(),
+        headers,
+        attributes,
+        request_body,
+        status: 0,
+        response_headers: HeaderMap::new(),
+        complete: false,
+    };
+    Some(Capture(pending, limit))
+}
+
+impl Capture {
+    /// 复制响应体，响应体读完或客户端断开时导出记录
+    pub fn finish(self, resp: Response<Body>) -> Response<Body> {
+        let Capture(mut pending, limit) = self;
+        pending.status = resp.status().as_u16();
+        if pending.headers {
+            pending.response_headers = resp.headers().clone();
+        }
+        resp.map(|body| Body::new(Tee { inner: body, captured: Default::default(), limit, pending: Some(pending) }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traceparent() {
+        let header = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let parent = TraceContext::parse(header).unwrap();
+        assert_eq!(hex::encode(parent.trace_id), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(parent.header(), header);
+        assert!(TraceContext::parse("00-00000000000000000000000000000000-00f067aa0ba902b7-01").is_none());
+        assert!(TraceContext::parse("ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").is_none());
+        assert!(TraceContext::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7").is_none());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(TRACEPARENT, HeaderValue::from_static(header));
+        let child = TraceContext::child_of(&headers);
+        assert_eq!(child.trace_id, parent.trace_id);
+        assert_ne!(child.span_id, parent.span_id);
+    }
+
+    #[test]
+    fn test_record_redacts_and_truncates() {
+        let mut captured = Captured::default();
+        captured.push(b"hello ", 8);
+        captured.push(b"world", 8);
+        assert_eq!((captured.bytes.as_slice(), captured.total), (&b"hello wo"[..], 11));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", HeaderValue::from_static("Bearer secret"));
+        headers.insert("content-type", HeaderValue::from_static("application/json"));
+        let pending = Pending {
+            route: "/api/**".to_string(),
+            trace: TraceContext { trace_id: [1; 16], span_id: [2; 8] },
+            started: Instant::now(),
+            started_at: SystemTime::now(),
+            headers: true,
+            attributes: header_attributes("http.request.header", &headers),
+            request_body: Arc::new(Mutex::new(captured)),
+            status: 502,
+            response_headers: HeaderMap::new(),
+            complete: true,
+        };
+        let record = pending.record(Captured { bytes: vec![0xff, 0x00], total: 2 });
+        let attrs: std::collections::BTreeMap<&str, &Value> = record["attributes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|a| (a["key"].as_str().unwrap(), &a["value"]))
+            .collect();
+        assert_eq!(attrs["http.request.header.authorization"]["stringValue"], "[REDACTED]");
+        assert_eq!(attrs["http.request.header.content-type"]["stringValue"], "application/json");
+        assert_eq!(attrs["http.request.body"]["stringValue"], "hello wo");
+        assert_eq!(attrs["http.request.body.truncated"]["boolValue"], true);
+        assert_eq!(attrs["http.response.body"]["bytesValue"], "/wA=");
+        assert_eq!(attrs["http.response.status_code"]["intValue"], "502");
+        assert_eq!(record["severityText"], "ERROR");
+        assert_eq!(record["traceId"], "01".repeat(16));
+    }
+}
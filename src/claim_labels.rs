@@ -0,0 +1,168 @@
+use axum::{body::Body, extract::Request, http::Response, middleware::Next};
+use dashmap::DashMap;
+use once_cell::sync::OnceCell;
+use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, Opts};
+use std::time::Duration;
+
+use crate::auth::{Claims, JwtAuth};
+use crate::config::Settings;
+
+/// 可作为标签的声明数量上限，避免标签组合失控
+pub const MAX_CLAIM_LABELS: usize = 3;
+
+/// 超出基数上限的取值归入该桶
+pub const OTHER: &str = "other";
+/// 未鉴权或缺少该声明
+pub const NONE: &str = "none";
+
+// ===== 按 JWT 声明打标的请求指标 =====
+pub struct ClaimMetrics {
+    claims: Vec<String>,
+    max_values: usize,
+    // 每个声明已分配标签的取值
+    seen: Vec<DashMap<String, ()>>,
+    requests: IntCounterVec,
+    duration: HistogramVec,
+}
+
+static CLAIM_METRICS: OnceCell<ClaimMetrics> = OnceCell::new();
+
+impl ClaimMetrics {
+    fn new(claims: Vec<String>, max_values: usize) -> prometheus::Result<Self> {
+        let labels: Vec<&str> = claims.iter().map(String::as_str).collect();
+        let requests = IntCounterVec::new(
+            Opts::new("gateway_claim_requests_total", "Proxied requests labelled by selected JWT claims"),
+            &[&["route", "status"], labels.as_slice()].concat(),
+        )?;
+        let duration = HistogramVec::new(
+            HistogramOpts::new("gateway_claim_request_duration_seconds", "Request duration labelled by selected JWT claims"),
+            &[&["route"], labels.as_slice()].concat(),
+        )?;
+        let seen = claims.iter().map(|_| DashMap::new()).collect();
+        Ok(Self { claims, max_values, seen, requests, duration })
+    }
+
+    // 取值进入标签前的分桶：新取值在未达到上限时登记，否则归入 other
+    fn bucket(&self, index: usize, value: Option<String>) -> String {
+        let Some(value) = value.filter(|v| !v.is_empty()) else {
+            return NONE.to_string();
+        };
+        let seen = &self.seen[index];
+        if seen.contains_key(&value) {
+            return value;
+        }
+        if seen.len() >= self.max_values {
+            return OTHER.to_string();
+        }
+        seen.insert(value.clone(), ());
+        value
+    }
+
+    fn labels(&self, claims: Option<&Claims>) -> Vec<String> {
+        self.claims
+            .iter()
+            .enumerate()
+            .map(|(i, name)| self.bucket(i, claims.and_then(|c| c.get(name))))
+            .collect()
+    }
+
+    fn observe(&self, route: &str, status: u16, claims: Option<&Claims>, elapsed: Duration) {
+        let values = self.labels(claims);
+        let values: Vec<&str> = values.iter().map(String::as_str).collect();
+        let status = status.to_string();
+        self.requests.with_label_values(&[&[route, status.as_str()], values.as_slice()].concat()).inc();
+        self.duration.with_label_values(&[&[route], values.as_slice()].concat()).observe(elapsed.as_secs_f64());
+    }
+}
+
+/// 按 metrics_claim_labels 注册指标；未配置时不启用（指标只能注册一次，以首次配置为准）
+pub fn configure(settings: &Settings) {
+    if settings.metrics_claim_labels.is_empty() || CLAIM_METRICS.get().is_some() {
+        return;
+    }
+    let max_values = settings.metrics_claim_max_values.unwrap_or(100);
+    let metrics = match ClaimMetrics::new(settings.metrics_claim_labels.clone(), max_values) {
+        Ok(metrics) => metrics,
+        Err(err) => {
+            tracing::error!("声明标签指标创建失败: {}", err);
+            return;
+        }
+    };
+    let registered = prometheus::register(Box::new(metrics.requests.clone()))
+        .and_then(|_| prometheus::register(Box::new(metrics.duration.clone())));
+    match registered {
+        Ok(()) => {
+            let _ = CLAIM_METRICS.set(metrics);
+        }
+        Err(err) => tracing::error!("声明标签指标注册失败: {}", err),
+    }
+}
+
+pub fn enabled() -> bool {
+    CLAIM_METRICS.get().is_some()
+}
+
+/// 记录一次请求
+pub fn observe(route: &str, status: u16, claims: Option<&Claims>, elapsed: Duration) {
+    if let Some(metrics) = CLAIM_METRICS.get() {
+        metrics.observe(route, status, claims, elapsed);
+    }
+}
+
+/// 中间件栈最内层：把鉴权得到的 Claims 带到响应上，供分派处记录指标与按租户统计流量
+pub async fn capture_layer(req: Request, next: Next) -> Response<Body> {
+    let claims = req.extensions().get::<JwtAuth>().cloned();
+    let mut resp = next.run(req).await;
+    if let Some(claims) = claims {
+        resp.extensions_mut().insert(claims);
+    }
+    resp
+}
+
+/// 校验声明名：须为合法的 Prometheus 标签名，且不与内置标签重复
+pub fn validation_errors(claims: &[String]) -> Vec<String> {
+    let mut errors = Vec::new();
+    if claims.len() > MAX_CLAIM_LABELS {
+        errors.push(format!("metrics_claim_labels最多{}个", MAX_CLAIM_LABELS));
+    }
+    for claim in claims {
+        let valid = claim.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && claim.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !claim.starts_with("__");
+        if !valid || matches!(claim.as_str(), "route" | "status") {
+            errors.push(format!("metrics_claim_labels包含非法的标签名: {}", claim));
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cardinality_cap() {
+        let metrics = ClaimMetrics::new(vec!["tenant_id".to_string(), "plan".to_string()], 2).unwrap();
+        let claims = |tenant: &str| {
+            let mut claims = Claims { tenant_id: tenant.to_string(), ..Default::default() };
+            claims.extra.insert("plan".to_string(), serde_json::json!("pro"));
+            claims
+        };
+        assert_eq!(metrics.labels(Some(&claims("t1"))), ["t1", "pro"]);
+        assert_eq!(metrics.labels(Some(&claims("t2"))), ["t2", "pro"]);
+        assert_eq!(metrics.labels(Some(&claims("t3"))), ["other", "pro"]);
+        assert_eq!(metrics.labels(Some(&claims("t1"))), ["t1", "pro"]);
+        assert_eq!(metrics.labels(None), ["none", "none"]);
+
+        metrics.observe("orders", 200, Some(&claims("t1")), Duration::from_millis(5));
+        assert_eq!(metrics.requests.with_label_values(&["orders", "200", "t1", "pro"]).get(), 1);
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(validation_errors(&["tenant_id".to_string(), "plan".to_string()]).is_empty());
+        assert_eq!(validation_errors(&["status".to_string(), "bad-name".to_string()]).len(), 2);
+        let many: Vec<String> = ["a", "b", "c", "d"].map(String::from).to_vec();
+        assert_eq!(validation_errors(&many).len(), 1);
+    }
+}
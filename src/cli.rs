@@ -0,0 +1,157 @@
+/// 命令行子命令
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    // 默认：启动网关
+    Serve,
+    // 本地开发模式，见 dev 模块
+    Dev,
+    Bench(BenchOptions),
+    // 检查路由与设置中的弃用写法和不安全配置
+    Lint { dir: String },
+    // 把旧版路由文件改写为当前格式，write 为 false 时只列出改动
+    Migrate { dir: String, write: bool },
+    Help,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct BenchOptions {
+    pub path: String,
+    pub method: String,
+    pub concurrency: usize,
+    pub requests: usize,
+    // 总 QPS 上限，不填则尽可能快
+    pub qps: Option<u32>,
+    // 启动内置桩上游并把所有路由的上游替换为它
+    pub stub: bool,
+    pub headers: Vec<(String, String)>,
+}
+
+pub const USAGE: &str = "\
+用法:
+  helios                         启动网关
+  helios --dev                   开发模式：自签名 HTTPS、测试 JWT 密钥、逐请求日志与管理端
+  helios bench <path> [选项]     经本地路由与代理流水线压测指定路径
+      -c, --concurrency <N>      并发数（默认 10）
+      -n, --requests <N>         请求总数（默认 1000）
+      -q, --qps <N>              总 QPS 上限
+      -X, --method <METHOD>      请求方法（默认 GET）
+      -H, --header <K: V>        附加请求头，可重复
+      --stub                     使用内置桩上游代替真实上游
+  helios lint [dir]              检查路由文件（默认当前目录）与设置中的弃用写法和不安全配置
+  helios check [dir]             同 lint，可用于 CI 中检查插件等配置能否通过加载校验
+  helios migrate [dir] [--write] 把旧版路由文件改写为当前格式，不加 --write 只列出改动
+  helios help                    显示帮助";
+
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    match args.next().as_deref() {
+        None => Ok(Command::Serve),
+        Some("help" | "-h" | "--help") => Ok(Command::Help),
+        Some("dev" | "--dev") => Ok(Command::Dev),
+        Some("bench") => parse_bench(args).map(Command::Bench),
+        Some("lint" | "check") => parse_dir(args, &[]).map(|(dir, _)| Command::Lint { dir }),
+        Some("migrate") => {
+            parse_dir(args, &["--write"]).map(|(dir, flags)| Command::Migrate { dir, write: !flags.is_empty() })
+        }
+        Some(other) => Err(format!("未知子命令: {}", other)),
+    }
+}
+
+fn parse_bench<I: Iterator<Item = String>>(mut args: I) -> Result<BenchOptions, String> {
+    let mut opts = BenchOptions {
+        path: String::new(),
+        method: "GET".to_string(),
+        concurrency: 10,
+        requests: 1000,
+        qps: None,
+        stub: false,
+        headers: Vec::new(),
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-c" | "--concurrency" => opts.concurrency = parse_value(&arg, args.next())?,
+            "-n" | "--requests" => opts.requests = parse_value(&arg, args.next())?,
+            "-q" | "--qps" => opts.qps = Some(parse_value(&arg, args.next())?),
+            "-X" | "--method" => opts.method = args.next().ok_or(format!("{} 缺少取值", arg))?.to_uppercase(),
+            "-H" | "--header" => {
+                let raw = args.next().ok_or(format!("{} 缺少取值", arg))?;
+                let (k, v) = raw.split_once(':').ok_or(format!("请求头格式应为 K: V，实际为 {}", raw))?;
+                opts.headers.push((k.trim().to_string(), v.trim().to_string()));
+            }
+            "--stub" => opts.stub = true,
+            s if s.starts_with('-') => return Err(format!("未知选项: {}", s)),
+            _ if opts.path.is_empty() => opts.path = arg,
+            _ => return Err(format!("多余的参数: {}", arg)),
+        }
+    }
+
+    if opts.path.is_empty() {
+        return Err("bench 需要指定路径，如 helios bench /proxy/user/1".to_string());
+    }
+    if opts.concurrency == 0 || opts.requests == 0 {
+        return Err("并发数与请求总数必须大于 0".to_string());
+    }
+    Ok(opts)
+}
+
+// 可选的目录参数（默认当前目录）与出现的开关选项
+fn parse_dir<I: Iterator<Item = String>>(args: I, allowed: &[&str]) -> Result<(String, Vec<String>), String> {
+    let mut dir = None;
+    let mut flags = Vec::new();
+    for arg in args {
+        if arg.starts_with('-') {
+            if !allowed.contains(&arg.as_str()) {
+                return Err(format!("未知选项: {}", arg));
+            }
+            flags.push(arg);
+        } else if dir.is_none() {
+            dir = Some(arg);
+        } else {
+            return Err(format!("多余的参数: {}", arg));
+        }
+    }
+    Ok((dir.unwrap_or_else(|| ".".to_string()), flags))
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or(format!("{} 缺少取值", flag))?;
+    value.parse().map_err(|_| format!("{} 的取值无效: {}", flag, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse(args("")).unwrap(), Command::Serve);
+        assert_eq!(parse(args("--help")).unwrap(), Command::Help);
+        assert_eq!(parse(args("--dev")).unwrap(), Command::Dev);
+        assert_eq!(parse(args("lint")).unwrap(), Command::Lint { dir: ".".to_string() });
+        assert_eq!(parse(args("check routes")).unwrap(), Command::Lint { dir: "routes".to_string() });
+        assert_eq!(parse(args("migrate conf --write")).unwrap(), Command::Migrate { dir: "conf".to_string(), write: true });
+        assert!(parse(args("migrate --force")).is_err());
+        assert!(parse(args("unknown")).is_err());
+    }
+
+    #[test]
+    fn test_parse_bench() {
+        let Command::Bench(opts) = parse(args("bench /proxy/user/1 -c 4 -n 200 --qps 50 --stub -H x-foo:bar")).unwrap() else {
+            panic!("应解析为 bench");
+        };
+        assert_eq!(opts.path, "/proxy/user/1");
+        assert_eq!(opts.concurrency, 4);
+        assert_eq!(opts.requests, 200);
+        assert_eq!(opts.qps, Some(50));
+        assert!(opts.stub);
+        assert_eq!(opts.headers, vec![("x-foo".to_string(), "bar".to_string())]);
+
+        assert!(parse(args("bench")).is_err());
+        assert!(parse(args("bench /a -c zero")).is_err());
+    }
+}
@@ -0,0 +1,258 @@
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::body::Bytes;
+use std::io::{self, Read, Write};
+use crate::config::Settings;
+
+/// 支持的内容编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Identity,
+    Gzip,
+    Brotli,
+    Zstd,
+}
+
+impl Encoding {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "identity" => Some(Encoding::Identity),
+            "gzip" | "x-gzip" => Some(Encoding::Gzip),
+            "br" => Some(Encoding::Brotli),
+            "zstd" => Some(Encoding::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Identity => "identity",
+            Encoding::Gzip => "gzip",
+            Encoding::Brotli => "br",
+            Encoding::Zstd => "zstd",
+        }
+    }
+}
+
+/// 解压，明文超过 limit 字节时立即停止并返回 FileTooLarge，避免压缩炸弹占满内存
+pub fn decode(encoding: Encoding, data: &[u8], limit: usize) -> io::Result<Vec<u8>> {
+    let reader: Box<dyn Read + '_> = match encoding {
+        Encoding::Identity => Box::new(data),
+        Encoding::Gzip => Box::new(flate2::read::MultiGzDecoder::new(data)),
+        Encoding::Brotli => Box::new(brotli::Decompressor::new(data, 4096)),
+        Encoding::Zstd => Box::new(zstd::stream::read::Decoder::new(data)?),
+    };
+    let mut out = Vec::new();
+    reader.take(limit as u64 + 1).read_to_end(&mut out)?;
+    if out.len() > limit {
+        return Err(io::Error::new(io::ErrorKind::FileTooLarge, format!("解压后超过 {} 字节", limit)));
+    }
+    Ok(out)
+}
+
+/// 压缩（选用偏速度的压缩级别，网关侧不宜占用过多 CPU）
+pub fn encode(encoding: Encoding, data: &[u8]) -> io::Result<Vec<u8>> {
+    match encoding {
+        Encoding::Identity => Ok(data.to_vec()),
+        Encoding::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+        Encoding::Brotli => {
+            let mut out = Vec::new();
+            {
+                let mut writer = brotli::CompressorWriter::new(&mut out, 4096, 4, 22);
+                writer.write_all(data)?;
+            }
+            Ok(out)
+        }
+        Encoding::Zstd => zstd::stream::encode_all(data, 3),
+    }
+}
+
+/// 按 Accept-Encoding（含 q 值）选择客户端可接受的编码，同等权重时优先 br > zstd > gzip
+pub fn negotiate(accept_encoding: Option<&str>) -> Encoding {
+    let Some(accept) = accept_encoding else {
+        return Encoding::Identity;
+    };
+    let preference = [Encoding::Brotli, Encoding::Zstd, Encoding::Gzip];
+    let mut best: Option<(f32, usize, Encoding)> = None;
+    for item in accept.split(',') {
+        let mut parts = item.split(';');
+        let Some(encoding) = parts.next().and_then(Encoding::parse) else {
+            continue;
+        };
+        let q = parts
+            .filter_map(|p| p.trim().strip_prefix("q="))
+            .find_map(|q| q.parse::<f32>().ok())
+            .unwrap_or(1.0);
+        let Some(rank) = preference.iter().position(|e| *e == encoding) else {
+            continue;
+        };
+        if q > 0.0 && best.is_none_or(|(bq, br, _)| q > bq || (q == bq && rank < br)) {
+            best = Some((q, rank, encoding));
+        }
+    }
+    best.map(|(_, _, e)| e).unwrap_or(Encoding::Identity)
+}
+
+/// 默认不压缩的 MIME 类型：本身已压缩，再压缩只会浪费 CPU 甚至变大
+pub const DEFAULT_SKIP_TYPES: &[&str] = &[
+    "image/*",
+    "video/*",
+    "audio/*",
+    "font/woff2",
+    "application/zip",
+    "application/gzip",
+    "application/zstd",
+    "application/x-7z-compressed",
+    "application/x-rar-compressed",
+    "application/x-bzip2",
+    "application/pdf",
+];
+
+/// 重新压缩的过滤条件
+#[derive(Debug, Clone, Copy)]
+pub struct CompressionPolicy<'a> {
+    // 小于该字节数的响应不压缩
+    pub min_bytes: usize,
+    // 不压缩的 MIME 类型，支持 type/* 通配
+    pub skip_types: &'a [String],
+}
+
+impl<'a> CompressionPolicy<'a> {
+    pub fn from_settings(settings: &'a Settings) -> Self {
+        Self {
+            min_bytes: settings.compression_min_bytes.unwrap_or(1024),
+            skip_types: &settings.compression_skip_types,
+        }
+    }
+
+    /// 是否值得压缩
+    pub fn should_compress(&self, content_type: Option<&str>, len: usize) -> bool {
+        if len < self.min_bytes {
+            return false;
+        }
+        let Some(content_type) = content_type else {
+            return true;
+        };
+        let mime = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+        !self.skip_types.iter().any(|pattern| {
+            let pattern = pattern.trim().to_ascii_lowercase();
+            match pattern.strip_suffix("/*") {
+                Some(major) => mime.split('/').next() == Some(major),
+                None => mime == pattern,
+            }
+        })
+    }
+}
+
+/// 解压上游响应并按客户端偏好重新压缩，同步修正 Content-Encoding / Content-Length / Vary；
+/// 上游使用不支持的编码时原样返回，不满足压缩条件时以明文返回；明文超过 limit 字节时返回 FileTooLarge
+pub fn transcode(
+    headers: &mut HeaderMap,
+    body: Bytes,
+    accept_encoding: Option<&str>,
+    policy: &CompressionPolicy,
+    limit: usize,
+) -> io::Result<Bytes> {
+    let source = match headers.get(header::CONTENT_ENCODING).and_then(|v| v.to_str().ok()) {
+        None => Encoding::Identity,
+        Some(value) => match Encoding::parse(value) {
+            Some(encoding) => encoding,
+            None => return Ok(body),
+        },
+    };
+    // 此处解压后的明文即可供后续检查/改写使用
+    let plain = decode(source, &body, limit)?;
+
+    let content_type = headers.get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok());
+    let target = if policy.should_compress(content_type, plain.len()) {
+        negotiate(accept_encoding)
+    } else {
+        Encoding::Identity
+    };
+    let out = encode(target, &plain)?;
+
+    headers.remove(header::CONTENT_LENGTH);
+    if target == Encoding::Identity {
+        headers.remove(header::CONTENT_ENCODING);
+    } else {
+        headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static(target.as_str()));
+    }
+    headers.append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    Ok(Bytes::from(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_all_encodings() {
+        let data = b"hello helios ".repeat(100);
+        for encoding in [Encoding::Gzip, Encoding::Brotli, Encoding::Zstd] {
+            let compressed = encode(encoding, &data).unwrap();
+            assert!(compressed.len() < data.len());
+            assert_eq!(decode(encoding, &compressed, data.len()).unwrap(), data);
+            assert_eq!(decode(encoding, &compressed, data.len() - 1).unwrap_err().kind(), io::ErrorKind::FileTooLarge);
+        }
+    }
+
+    #[test]
+    fn test_negotiate_accept_encoding() {
+        assert_eq!(negotiate(None), Encoding::Identity);
+        assert_eq!(negotiate(Some("gzip, deflate, br")), Encoding::Brotli);
+        assert_eq!(negotiate(Some("gzip;q=1.0, zstd;q=0.5")), Encoding::Gzip);
+        assert_eq!(negotiate(Some("br;q=0, gzip")), Encoding::Gzip);
+        assert_eq!(negotiate(Some("deflate")), Encoding::Identity);
+    }
+
+    fn policy() -> CompressionPolicy<'static> {
+        CompressionPolicy { min_bytes: 0, skip_types: &[] }
+    }
+
+    #[test]
+    fn test_compression_policy_filters() {
+        let skip: Vec<String> = DEFAULT_SKIP_TYPES.iter().map(|s| s.to_string()).collect();
+        let policy = CompressionPolicy { min_bytes: 1024, skip_types: &skip };
+        assert!(!policy.should_compress(Some("application/json"), 100));
+        assert!(policy.should_compress(Some("application/json; charset=utf-8"), 4096));
+        assert!(!policy.should_compress(Some("image/png"), 4096));
+        assert!(!policy.should_compress(Some("Application/ZIP"), 4096));
+        assert!(policy.should_compress(None, 4096));
+    }
+
+    #[test]
+    fn test_transcode_skips_small_payloads() {
+        let data = b"tiny".to_vec();
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        let body = Bytes::from(encode(Encoding::Gzip, &data).unwrap());
+        let policy = CompressionPolicy { min_bytes: 1024, skip_types: &[] };
+        let out = transcode(&mut headers, body, Some("gzip"), &policy, 1 << 20).unwrap();
+        assert!(headers.get(header::CONTENT_ENCODING).is_none());
+        assert_eq!(out.as_ref(), data.as_slice());
+    }
+
+    #[test]
+    fn test_transcode_gzip_to_client_preference() {
+        let data = b"{\"items\":[1,2,3]}".repeat(20);
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        headers.insert(header::CONTENT_LENGTH, HeaderValue::from_static("123"));
+
+        let body = Bytes::from(encode(Encoding::Gzip, &data).unwrap());
+        let out = transcode(&mut headers, body, Some("zstd"), &policy(), 1 << 20).unwrap();
+        assert_eq!(headers.get(header::CONTENT_ENCODING).unwrap(), "zstd");
+        assert!(headers.get(header::CONTENT_LENGTH).is_none());
+        assert_eq!(decode(Encoding::Zstd, &out, 1 << 20).unwrap(), data);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("br"));
+        let body = Bytes::from(encode(Encoding::Brotli, &data).unwrap());
+        let out = transcode(&mut headers, body, None, &policy(), 1 << 20).unwrap();
+        assert!(headers.get(header::CONTENT_ENCODING).is_none());
+        assert_eq!(out.as_ref(), data.as_slice());
+    }
+}
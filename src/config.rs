@@ -1,23 +1,316 @@
 use config::{Config, ConfigError, File};
-use serde::Deserialize;
-use std::{env, path::PathBuf, time::Duration};
+use serde::{Deserialize, Serialize};
+use std::{env, path::{Path, PathBuf}, time::Duration};
 use crate::path_matcher::RoutePattern;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct RouteRule {
+    // 可选的路由标识，profile 覆盖文件按 id 合并到同名路由
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    // 所属命名空间：继承命名空间的默认值，前缀受其范围约束，可由该命名空间的管理端令牌管理
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
     // 支持单个或多个前缀
-    #[serde(with = "prefix_deserializer")]
+    #[serde(deserialize_with = "prefix_deserializer::deserialize")]
     pub prefix: Vec<String>,
-    // 支持单个或多个上游
-    #[serde(with = "upstream_deserializer")]
+    // 支持单个或多个上游；配置了 regions 时可省略，自动汇总各区域的上游
+    #[serde(default, deserialize_with = "upstream_deserializer::deserialize")]
     pub upstream: Vec<String>,
+    // 上游地址可引用路径变量与 JWT 声明（如 http://svc-{version}.internal:8080、{claim.tenant_id}），
+    // 按请求渲染；渲染结果的主机须命中该列表，支持 *.example.com
+    #[serde(default, deserialize_with = "opt_vec_string_deser::deserialize", skip_serializing_if = "Option::is_none")]
+    pub upstream_hosts: Option<Vec<String>>,
+    // 按区域分组的上游，持续探测延迟并路由到延迟最低的健康区域
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regions: Option<BTreeMap<String, Vec<String>>>,
+    // 租户专属上游：JWT 的 tenant_id 命中时转发到对应的上游组（在负载均衡之前查询），可经管理端在运行时调整
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tenant_upstreams: BTreeMap<String, Vec<String>>,
     // 负载均衡策略，默认为轮询
     #[serde(default = "default_strategy")]
     pub strategy: String,
-    // 白名单路径（命中则跳过鉴权），支持 string 或 array
+    // 白名单路径（命中则跳过鉴权），支持 string 或 array；可用 "GET /path" 或 "GET,HEAD /path" 限定方法
     #[serde(default, deserialize_with = "opt_vec_string_deser::deserialize")] 
     pub whitelist: Option<Vec<String>>,
+    // 命中白名单、免鉴权的请求使用的更严格限制：超时、请求体大小、每个客户端的 QPS
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub whitelist_limits: Option<crate::whitelist::WhitelistLimits>,
+    // 路由谓词：请求的 Content-Type / Accept 命中其中之一才匹配该路由，支持 type/* 通配
+    #[serde(default, deserialize_with = "opt_vec_string_deser::deserialize", skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "opt_vec_string_deser::deserialize", skip_serializing_if = "Option::is_none")]
+    pub accept: Option<Vec<String>>,
+    // 允许的方法：配置后网关本地应答 OPTIONS（附 Allow 头），其他方法返回 405；允许 GET 时隐含 HEAD
+    #[serde(default, deserialize_with = "opt_vec_string_deser::deserialize", skip_serializing_if = "Option::is_none")]
+    pub methods: Option<Vec<String>>,
+    // 定时生效：当前时间命中任一 cron 表达式（分 时 日 月 周，如 "* 9-17 * * mon-fri"）时才匹配该路由，
+    // 同一前缀下生效时段内优先于不带定时的路由；时区为 UTC 偏移（如 +08:00），默认取全局 schedule_timezone
+    #[serde(default, deserialize_with = "opt_vec_string_deser::deserialize", skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule_timezone: Option<String>,
+    // 特性开关：开关服务中的布尔开关决定是否匹配该路由，数值开关（0-100）按调用方稳定放量；
+    // 同一前缀下开关命中时优先于不带开关的路由。开关服务不可用且缓存失效时取 feature_flag_default（默认 false）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feature_flag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feature_flag_default: Option<bool>,
+    // 鉴权模式：required（默认，别名 jwt，校验失败返回 401）、shadow（仅记录校验结论，不拦截请求）、
+    // optional（token 有效则透传身份，否则以匿名身份转发）、api_key（以 X-API-Key 识别身份）或 none（不鉴权）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<String>,
+    // 请求体 JSON Schema 文件路径（相对于工作目录），配置后校验 Content-Type 与请求体
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_schema: Option<String>,
+    // 查询参数改写：drop / rename / inject
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<crate::query::QueryRewrite>,
+    // API 版本路由：按路径 /v{n}、请求头或 Accept 参数转发到版本对应的上游组
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub versioning: Option<crate::versioning::Versioning>,
+    // 跨域配置：网关直接应答预检并按（路由, Origin）缓存结果
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cors: Option<crate::cors::CorsPolicy>,
+    // 路由插件：按声明顺序执行的插件实例，如 { name = "headers", config = { ... } }；配置在加载时按插件的配置模式校验
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugins: Option<Vec<crate::plugin::PluginConfig>>,
+    // 该路由执行的中间件阶段及顺序，覆盖全局 middleware_pipeline；空数组表示不执行任何阶段
+    #[serde(default, deserialize_with = "opt_vec_string_deser::deserialize", skip_serializing_if = "Option::is_none")]
+    pub middleware: Option<Vec<String>>,
+    // 对象存储源站：从 S3 兼容存储读取对象（可选缓存），配置后无需 upstream
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub s3: Option<crate::s3::S3Origin>,
+    // 允许的 Upgrade 协议（如 websocket、h2c，* 表示任意），上游返回 101 后转为双向字节隧道
+    #[serde(default, deserialize_with = "opt_vec_string_deser::deserialize", skip_serializing_if = "Option::is_none")]
+    pub upgrade: Option<Vec<String>>,
+    // WebSocket 连接限制：单帧与单条消息大小、每秒消息数、空闲超时，仅对升级为 websocket 的连接生效
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub websocket: Option<crate::websocket::WebSocketLimits>,
+    // 每个请求消耗的限流令牌数，默认 1；重型接口（搜索、导出）可设置更大的值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<u32>,
+    // 路由级限流：该路由所有客户端共享的每秒令牌数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub qps: Option<u32>,
+    // 优先级等级：low / normal（默认）/ high / critical；low 的路由在网关过载时被优先拒绝，
+    // 启用 QoS 调度时高等级请求优先获得准入名额
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+    // 错误页模板：4xx / 5xx / 具体状态码 -> 模板文件（.html / .json / 其他按纯文本），替换网关生成的错误响应
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_pages: Option<BTreeMap<String, String>>,
+    // 路由级 SLO：可用性与延迟目标，按滚动窗口统计达标率与错误预算消耗速度
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slo: Option<crate::slo::Slo>,
+    // 异常防护规则：5xx 比例突增或延迟相对基线翻倍时自动降级限流、熔断或切换到 fallback 上游
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anomaly: Option<Vec<crate::anomaly::AnomalyRule>>,
+    // 路由级访问日志：是否记录、采样比例与记录的字段，未配置的项沿用全局设置
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_log: Option<crate::access_log::AccessLogPolicy>,
+    // 请求抓取：完整记录请求与响应并以 OpenTelemetry 日志导出（采样比例、请求体/响应体记录上限、是否记录头部）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capture: Option<crate::capture::CapturePolicy>,
+    // 爬虫与扫描器识别：User-Agent 片段、必需的请求头与扫描路径，命中后拦截或标记
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bot: Option<crate::bot::BotRules>,
+    // 蜜罐路由：命中的请求拖延后拒绝，不转发到上游（无需配置 upstream）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tarpit: Option<crate::tarpit::Tarpit>,
+    // 额外去掉的上游响应头（在 response_strip_headers 之外），支持结尾 * 前缀匹配
+    #[serde(default, deserialize_with = "opt_vec_string_deser::deserialize", skip_serializing_if = "Option::is_none")]
+    pub strip_response_headers: Option<Vec<String>>,
+    // 缓冲策略：full（默认，请求体与响应体均缓冲）/ request / response / none；不缓冲的一侧流式转发
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buffering: Option<String>,
+    // 缓冲请求体/响应体的大小上限（字节），默认 10 MiB；超限的请求返回 413，超限的上游响应返回 502
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_request_body_bytes: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_response_body_bytes: Option<usize>,
+    // 不缓冲响应体时的流式响应体上限：Content-Length 超限时直接返回 502，边收边转发时超限则中断响应；默认不限制
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_streamed_response_bytes: Option<usize>,
+    // 流式响应的刷新策略：immediate（默认，收到上游数据即转发）或 coalesce（攒够 flush_bytes 或等待 flush_interval_ms 后转发）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flush: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flush_bytes: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flush_interval_ms: Option<u64>,
+    // 是否解压上游响应体（gzip/br/zstd）以供检查，并按客户端偏好重新压缩；默认关闭，原样透传
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decompress: Option<bool>,
+    // 响应缓存：如负缓存（短时间缓存上游的 404/410 等错误响应），需要缓冲响应体
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<crate::response_cache::CachePolicy>,
+    // 为缺少 ETag 的缓冲响应（GET/HEAD 的 200）计算强 ETag，并对命中 If-None-Match 的请求返回 304；默认关闭
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<bool>,
+    // 在转发的响应上附加 Server-Timing 头（dns、connect、ttfb、total），便于前端定位耗时；默认关闭
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_timing: Option<bool>,
+    // 按客户端请求头的原始大小写与顺序转发（仅 HTTP/1.1 上游），用于校验头名大小写的老旧后端；默认关闭
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preserve_header_case: Option<bool>,
+    // 上游 HTTP 版本：http1（仅 HTTP/1.1）或 http2（直接使用 HTTP/2），默认自动协商：
+    // https 上游逐连接经 ALPN 选择 h2 或 http/1.1，http 上游使用 HTTP/1.1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_version: Option<String>,
+    // gRPC 路由：允许管理端经上游的 Server Reflection 列出服务与方法，并校验前缀是否对应实际存在的方法；默认关闭
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grpc_reflection: Option<bool>,
+    // 失败重试：连接错误或可重试的状态码时按退避间隔换节点重发，配置后取代全局 upstream_retries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<crate::retry::RetryPolicy>,
+    // 请求对冲：上游在该毫秒数内未返回时向另一个上游再发一次，取先成功的响应并取消另一个；需要缓冲请求体
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hedge_after_ms: Option<u64>,
+    // 流量镜像：按比例把请求副本发往影子上游，可对比两边的状态码、响应头与规范化后的 JSON 响应体；需要缓冲请求体
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirror: Option<crate::mirror::MirrorPolicy>,
+    // 内部重定向：上游或插件的响应带 X-Accel-Redirect 时，以其中的路径在网关内部重新分派请求，不返回原响应；默认关闭
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub internal_redirect: Option<bool>,
+    // 只能经内部重定向访问的路由，客户端直接请求时返回 404
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub internal: Option<bool>,
+    // 受保护文件下发：上游响应带 X-Sendfile 或 X-Accel-Redirect 时，直接发送其指向的本地文件或对象存储中的对象
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sendfile: Option<crate::sendfile::SendfilePolicy>,
+    // 分阶段上游超时（毫秒）：连接、首字节（收到响应头）、数据块间空闲与总时长；配置任一项后
+    // request_timeout_secs 只作为首字节超时的默认值，未配置的空闲与总时长不限制
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_timeout_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_byte_timeout_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_timeout_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_timeout_ms: Option<u64>,
+    // 上游重定向：pass（默认，原样返回）、follow（网关跟随，最多 redirect_max_hops 跳，默认 5）
+    // 或 rewrite（原样返回，把指向上游的 Location 改写为网关对外的路径）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirect: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirect_max_hops: Option<usize>,
+    // 是否复用上游连接，默认开启；部分老旧后端需要关闭
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<bool>,
+    // 上游连接是否设置 TCP_NODELAY，默认开启
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tcp_nodelay: Option<bool>,
+}
+
+impl RouteRule {
+    /// 补全派生字段：未配置 upstream 时汇总 regions 中的全部上游
+    pub fn normalize(&mut self) {
+        if self.upstream.is_empty()
+            && let Some(regions) = &self.regions
+        {
+            for upstream in regions.values().flatten() {
+                if !self.upstream.contains(upstream) {
+                    self.upstream.push(upstream.clone());
+                }
+            }
+        }
+    }
+
+    /// 路由涉及的全部上游（含各 API 版本的上游组与镜像的影子上游）
+    pub fn all_upstreams(&self) -> impl Iterator<Item = &String> {
+        let versioned = self.versioning.iter().flat_map(|v| v.versions.values()).flat_map(|v| v.upstream.iter());
+        let fallback = self.anomaly.iter().flatten().flat_map(|a| a.fallback.iter().flatten());
+        let tenants = self.tenant_upstreams.values().flatten();
+        let mirror = self.mirror.iter().map(|m| &m.upstream);
+        self.upstream.iter().chain(versioned).chain(fallback).chain(tenants).chain(mirror)
+    }
+}
+
+impl Default for RouteRule {
+    fn default() -> Self {
+        Self {
+            id: None,
+            namespace: None,
+            prefix: Vec::new(),
+            upstream: Vec::new(),
+            upstream_hosts: None,
+            regions: None,
+            tenant_upstreams: BTreeMap::new(),
+            strategy: default_strategy(),
+            whitelist: None,
+            whitelist_limits: None,
+            content_type: None,
+            accept: None,
+            methods: None,
+            schedule: None,
+            schedule_timezone: None,
+            feature_flag: None,
+            feature_flag_default: None,
+            auth: None,
+            request_schema: None,
+            query: None,
+            versioning: None,
+            cors: None,
+            plugins: None,
+            middleware: None,
+            s3: None,
+            upgrade: None,
+            websocket: None,
+            cost: None,
+            qps: None,
+            priority: None,
+            error_pages: None,
+            slo: None,
+            anomaly: None,
+            access_log: None,
+            capture: None,
+            bot: None,
+            tarpit: None,
+            strip_response_headers: None,
+            buffering: None,
+            max_request_body_bytes: None,
+            max_response_body_bytes: None,
+            max_streamed_response_bytes: None,
+            flush: None,
+            flush_bytes: None,
+            flush_interval_ms: None,
+            decompress: None,
+            cache: None,
+            etag: None,
+            server_timing: None,
+            preserve_header_case: None,
+            http_version: None,
+            grpc_reflection: None,
+            retry: None,
+            hedge_after_ms: None,
+            mirror: None,
+            internal_redirect: None,
+            internal: None,
+            sendfile: None,
+            connect_timeout_ms: None,
+            first_byte_timeout_ms: None,
+            idle_timeout_ms: None,
+            total_timeout_ms: None,
+            redirect: None,
+            redirect_max_hops: None,
+            keep_alive: None,
+            tcp_nodelay: None,
+        }
+    }
+}
+
+/// MIME 类型匹配，双方均可使用 type/* 或 */* 通配，忽略参数（; 之后）与大小写
+pub fn mime_matches(pattern: &str, media: &str) -> bool {
+    let essence = |s: &str| s.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    let (pattern, media) = (essence(pattern), essence(media));
+    let (Some((p_type, p_sub)), Some((m_type, m_sub))) = (pattern.split_once('/'), media.split_once('/')) else {
+        return false;
+    };
+    let part = |a: &str, b: &str| a == "*" || b == "*" || a == b;
+    part(p_type, m_type) && part(p_sub, m_sub)
 }
 
 // 默认负载均衡策略
@@ -92,19 +385,271 @@ mod opt_vec_string_deser {
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+// 反序列化 Vec<String>：数组，或逗号分隔的字符串（便于通过环境变量配置）
+mod comma_vec_deser {
+    use serde::{Deserialize, Deserializer};
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum StringOrVec {
+            String(String),
+            Vec(Vec<String>),
+        }
+
+        Ok(match StringOrVec::deserialize(deserializer)? {
+            StringOrVec::String(s) => s
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect(),
+            StringOrVec::Vec(v) => v,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Settings {
     pub gateway_bind: String,
     pub jwt_decoding_key: String,
     pub global_qps: u32,
     pub client_qps: u32,
+    // 分层限流：每个租户（JWT tenant_id）、每个用户（JWT sub）的 QPS，不配置则不启用
+    pub tenant_qps: Option<u32>,
+    pub user_qps: Option<u32>,
+    // 按键限流器清理空闲键的间隔（秒），默认 60
+    pub rate_limit_evict_interval_secs: Option<u64>,
     pub request_timeout_secs: Option<u64>,
+    // 限流豁免：IP 或网段（如 10.0.0.0/8）
+    #[serde(default, deserialize_with = "comma_vec_deser::deserialize")]
+    pub rate_limit_exempt_ips: Vec<String>,
+    // 限流豁免：X-API-Key 请求头取值
+    #[serde(default, deserialize_with = "comma_vec_deser::deserialize")]
+    pub rate_limit_exempt_api_keys: Vec<String>,
+    // API Key 身份：auth = "api_key" 的路由按 X-API-Key 查找调用方（sub、tenant_id、priority 及其他声明）
+    #[serde(default)]
+    pub api_keys: Vec<crate::auth::ApiKey>,
+    // 限流豁免：JWT subject（sub）
+    #[serde(default, deserialize_with = "comma_vec_deser::deserialize")]
+    pub rate_limit_exempt_subjects: Vec<String>,
+    // 自动封禁：状态码（如 401、429，或 4xx）-> 窗口内允许的次数，同一 IP 或 token 超过后封禁一段时间
+    #[serde(default)]
+    pub ban_thresholds: BTreeMap<String, u32>,
+    pub ban_window_secs: Option<u64>,
+    pub ban_duration_secs: Option<u64>,
+    // 管理端访问令牌，未配置则不开放 /admin
+    pub admin_token: Option<String>,
+    // 命名空间管理端令牌：命名空间 -> 令牌，持有者只能访问本命名空间的路由与上游
+    #[serde(default)]
+    pub admin_namespace_tokens: BTreeMap<String, String>,
+    // HTTPS 监听的证书与私钥（PEM）；配置客户端 CA 后要求客户端证书（tls_client_auth = optional 时可不出示），
+    // 证书经 client_cert_identities 映射为与 JWT 声明等价的身份
+    pub tls_cert_file: Option<String>,
+    pub tls_key_file: Option<String>,
+    pub tls_client_ca_file: Option<String>,
+    pub tls_client_auth: Option<String>,
+    #[serde(default)]
+    pub client_cert_identities: Vec<crate::mtls::CertIdentity>,
+    // 以请求头把 TLS 连接信息转发给上游：字段（protocol、cipher、sni、client_subject、client_verify）-> 头名，
+    // 如 protocol = "X-SSL-Protocol"；客户端自带的同名头总是被去掉
+    #[serde(default)]
+    pub tls_headers: BTreeMap<String, String>,
+    // 调试头 X-Gateway-Debug 的签名密钥
+    pub debug_secret: Option<String>,
+    // 无需签名即可获取调试信息的 IP 或网段
+    #[serde(default, deserialize_with = "comma_vec_deser::deserialize")]
+    pub debug_trusted_ips: Vec<String>,
+    // 运维端点（/metrics、/healthz、/admin）：独立监听地址、允许访问的 IP 或网段、/metrics 与 /healthz 的访问令牌
+    pub ops_bind: Option<String>,
+    #[serde(default, deserialize_with = "comma_vec_deser::deserialize")]
+    pub ops_allowed_ips: Vec<String>,
+    pub ops_token: Option<String>,
+    // 上游预热：启动或恢复上游后预先建立的空闲连接数、预热请求数及请求路径
+    pub upstream_warmup_connections: Option<usize>,
+    pub upstream_warmup_requests: Option<usize>,
+    pub upstream_warmup_path: Option<String>,
+    // Kubernetes 控制器模式：ingress / gateway-api（HTTPRoute），只处理 ingressClassName 或所属 Gateway 名为
+    // kubernetes_class（默认 helios）的资源；命名空间（不配置则为全部）、API 地址（默认集群内地址）与轮询间隔（秒，默认 10）
+    pub kubernetes_controller: Option<String>,
+    pub kubernetes_class: Option<String>,
+    pub kubernetes_namespace: Option<String>,
+    pub kubernetes_api_server: Option<String>,
+    pub kubernetes_poll_secs: Option<u64>,
+    // Docker 服务发现：为带 gateway.prefix 标签的运行中容器生成路由；Docker API 地址（unix:// 套接字或 tcp:// / http:// 地址，
+    // 默认 unix:///var/run/docker.sock）、取容器 IP 的网络（不配置则取第一个）与轮询间隔（秒，默认 5）
+    pub docker_discovery: Option<bool>,
+    pub docker_host: Option<String>,
+    pub docker_network: Option<String>,
+    pub docker_poll_secs: Option<u64>,
+    // 上游启动检查：off（默认）/ resolve 只做 DNS 解析 / connect 解析后建立 TCP 连接；
+    // 存在不可达上游时 fail_fast 拒绝启动，否则输出告警后降级启动；单个上游的检查超时（毫秒，默认 2000）
+    pub upstream_preflight: Option<String>,
+    pub upstream_preflight_fail_fast: Option<bool>,
+    pub upstream_preflight_timeout_ms: Option<u64>,
+    // 上游 https 强制：开启后以 http 访问内网网段之外的主机将拒绝启动；内网网段（不配置则为默认私有网段）、允许明文访问的主机名（支持 *.example.com）
+    pub upstream_require_https: Option<bool>,
+    #[serde(default, deserialize_with = "comma_vec_deser::deserialize")]
+    pub upstream_private_networks: Vec<String>,
+    #[serde(default, deserialize_with = "comma_vec_deser::deserialize")]
+    pub upstream_plaintext_hosts: Vec<String>,
+    // iphash 有界负载系数（>= 1）：节点在途请求达到平均值的该倍数时，新请求顺延到哈希环上的下一个节点，默认 1.25
+    pub iphash_load_factor: Option<f64>,
+    // iphash 哈希环：每个节点的虚拟节点数（默认 150）与哈希函数（xxhash 默认 / fnv），两者都是跨版本稳定的
+    pub iphash_virtual_nodes: Option<usize>,
+    pub iphash_hash: Option<String>,
+    // 连接错误时换节点重试的次数，默认 0
+    pub upstream_retries: Option<u32>,
+    // 请求对冲的全局预算：对冲请求数不超过开启对冲的路由请求数的该百分比，默认 10
+    pub hedge_budget_percent: Option<u64>,
+    // 熔断：连续失败（连接错误或 5xx）达到次数后摘除节点，0 或不配置表示不启用
+    pub circuit_breaker_failures: Option<u32>,
+    pub circuit_breaker_open_secs: Option<u64>,
+    // 分布式协调：经 consul / etcd 在副本间共享熔断、封禁与摘流状态；存储地址（默认本机 8500 / 2379 端口）、
+    // 键前缀（默认 helios）、访问令牌与轮询间隔（秒，默认 2）
+    pub coordination_backend: Option<String>,
+    pub coordination_endpoint: Option<String>,
+    pub coordination_prefix: Option<String>,
+    pub coordination_token: Option<String>,
+    pub coordination_poll_secs: Option<u64>,
+    // 定时路由默认的时区（UTC 偏移，如 +08:00），默认 UTC
+    pub schedule_timezone: Option<String>,
+    // 特性开关服务：http / unleash / flagd、服务地址、访问令牌、刷新间隔（秒，默认 15）
+    // 与缓存有效期（秒，默认 300，超过后路由按 feature_flag_default 处理）
+    pub feature_flag_provider: Option<String>,
+    pub feature_flag_endpoint: Option<String>,
+    pub feature_flag_token: Option<String>,
+    pub feature_flag_refresh_secs: Option<u64>,
+    pub feature_flag_max_stale_secs: Option<u64>,
+    // 多区域路由的延迟探测间隔与探测路径
+    pub region_probe_interval_secs: Option<u64>,
+    pub region_probe_path: Option<String>,
+    // URL 规范化：合并重复斜杠、解析点段、结尾斜杠（keep/strip/add）、百分号解码（none/unreserved/all）、%2F 是否解码
+    pub url_merge_slashes: Option<bool>,
+    pub url_resolve_dot_segments: Option<bool>,
+    pub url_trailing_slash: Option<String>,
+    pub url_percent_decode: Option<String>,
+    pub url_decode_slash: Option<bool>,
+    // 含 .. 段的路径：reject（默认）或 normalize
+    pub url_traversal_action: Option<String>,
+    // 路由冲突诊断：warn（默认，输出告警）/ error（拒绝启动）/ off
+    pub route_diagnostics: Option<String>,
+    // 重新压缩响应的最小字节数，以及不压缩的 MIME 类型（支持 type/*）
+    pub compression_min_bytes: Option<usize>,
+    #[serde(default = "default_compression_skip_types", deserialize_with = "comma_vec_deser::deserialize")]
+    pub compression_skip_types: Vec<String>,
+    // 按 JWT 声明（如 tenant_id、plan）打标的请求指标，以及每个声明的取值个数上限（超出归入 other）
+    #[serde(default, deserialize_with = "comma_vec_deser::deserialize")]
+    pub metrics_claim_labels: Vec<String>,
+    pub metrics_claim_max_values: Option<usize>,
+    // 访问日志：是否启用、采样比例（0~1，5xx 始终记录）与记录的字段（逗号分隔，不配置则记录全部）
+    pub access_log: Option<bool>,
+    pub access_log_sample_rate: Option<f64>,
+    #[serde(default, deserialize_with = "comma_vec_deser::deserialize")]
+    pub access_log_fields: Vec<String>,
+    // 请求抓取记录的 OTLP/HTTP 导出地址（发送到 {地址}/v1/logs）与上报的服务名（默认 helios）
+    pub otel_logs_endpoint: Option<String>,
+    pub otel_service_name: Option<String>,
+    // 转发前去掉的上游响应头（支持结尾 * 前缀匹配），配置后替换默认列表；响应头总字节数上限
+    #[serde(default = "default_response_strip_headers", deserialize_with = "comma_vec_deser::deserialize")]
+    pub response_strip_headers: Vec<String>,
+    pub max_response_header_bytes: Option<usize>,
+    // 会话：memory 或 redis://...（需 redis-session 特性），不配置则不启用
+    pub session_store: Option<String>,
+    pub session_cookie_name: Option<String>,
+    pub session_ttl_secs: Option<u64>,
+    // 中间件阶段及顺序，逗号分隔；不配置则为 rate_limit,cors,whitelist,auth,propagate_headers
+    #[serde(default, deserialize_with = "comma_vec_deser::deserialize")]
+    pub middleware_pipeline: Vec<String>,
+    // 正向代理（CONNECT）：用户（JWT subject，* 为所有用户）-> 允许的目标 host[:port]
+    pub forward_proxy_enabled: Option<bool>,
+    #[serde(default)]
+    pub forward_proxy_allow: BTreeMap<String, Vec<String>>,
+    pub forward_proxy_connect_timeout_secs: Option<u64>,
+    // 上游并发上限：URL -> 最大在途请求数，* 为未单独配置的上游的默认值
+    #[serde(default)]
+    pub upstream_max_in_flight: BTreeMap<String, u32>,
+    // 负载降载：CPU 占用（%）、常驻内存（MB）、事件循环延迟（毫秒）任一超过阈值时拒绝低优先级路由
+    pub load_shed_cpu_percent: Option<u32>,
+    pub load_shed_memory_mb: Option<u64>,
+    pub load_shed_event_loop_lag_ms: Option<u64>,
+    pub load_shed_sample_interval_ms: Option<u64>,
+    // QoS 调度：并发准入上限、排队上限与排队超时；API Key -> 调用方等级
+    pub qos_max_concurrency: Option<usize>,
+    pub qos_max_queue: Option<usize>,
+    pub qos_queue_timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub qos_api_key_classes: BTreeMap<String, String>,
+    // 同等级排队请求按调用方（API Key，其次租户，再次客户端 IP）加权公平出队：调用方 -> 权重，默认 1
+    #[serde(default)]
+    pub qos_fair_weights: BTreeMap<String, u32>,
+    // 错误响应（application/problem+json）的 type URI：基地址 + 错误类型，或按错误类型单独指定
+    pub problem_type_base: Option<String>,
+    #[serde(default)]
+    pub problem_types: BTreeMap<String, String>,
+}
+
+fn default_compression_skip_types() -> Vec<String> {
+    crate::compression::DEFAULT_SKIP_TYPES.iter().map(|s| s.to_string()).collect()
+}
+
+fn default_response_strip_headers() -> Vec<String> {
+    crate::header_filter::DEFAULT_STRIP_HEADERS.iter().map(|s| s.to_string()).collect()
 }
 
 impl Settings {
     pub fn request_timeout(&self) -> Duration {
         Duration::from_secs(self.request_timeout_secs.unwrap_or(10))
     }
+
+    /// 序列化为 JSON 并隐藏密钥类字段，供管理端展示
+    pub fn redacted(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(obj) = value.as_object_mut() {
+            for key in ["jwt_decoding_key", "admin_token", "ops_token", "rate_limit_exempt_api_keys", "debug_secret", "coordination_token", "feature_flag_token"] {
+                if let Some(field) = obj.get_mut(key)
+                    && !field.is_null()
+                    && field.as_array().is_none_or(|items| !items.is_empty())
+                {
+                    *field = serde_json::Value::String(REDACTED.to_string());
+                }
+            }
+            // API Key 只保留对应的身份
+            for key in obj.get_mut("api_keys").and_then(|v| v.as_array_mut()).into_iter().flatten() {
+                if let Some(key) = key.as_object_mut() {
+                    key.insert("key".to_string(), serde_json::Value::String(REDACTED.to_string()));
+                }
+            }
+            // 命名空间令牌只保留命名空间名
+            if let Some(tokens) = obj.get_mut("admin_namespace_tokens").and_then(|v| v.as_object_mut()) {
+                for token in tokens.values_mut() {
+                    *token = serde_json::Value::String(REDACTED.to_string());
+                }
+            }
+            // 按 API Key（公平权重还可能是租户或 IP）配置的 QoS 等级与权重：键改为短哈希，保留值
+            for field in ["qos_api_key_classes", "qos_fair_weights"] {
+                if let Some(map) = obj.get_mut(field).and_then(|v| v.as_object_mut()) {
+                    *map = std::mem::take(map).into_iter().map(|(key, value)| (masked_key(&key), value)).collect();
+                }
+            }
+            // Redis 地址可能带密码
+            if self.session_store.as_deref().is_some_and(|s| s.contains('@')) {
+                obj.insert("session_store".to_string(), serde_json::Value::String(REDACTED.to_string()));
+            }
+        }
+        value
+    }
+}
+
+/// 脱敏后的占位符
+pub const REDACTED: &str = "***";
+
+// 以占位符加短哈希代替原始的键：不泄露 API Key，仍可按哈希对照是哪一项
+fn masked_key(key: &str) -> String {
+    use sha2::Digest;
+    format!("{}{}", REDACTED, hex::encode(&sha2::Sha256::digest(key.as_bytes())[..4]))
 }
 
 // 增强的路径匹配器
@@ -119,6 +664,30 @@ impl RouteRule {
         false
     }
 
+    /// Content-Type / Accept 谓词是否满足（未配置的谓词视为满足）
+    pub fn matches_headers(&self, headers: &axum::http::HeaderMap) -> bool {
+        let header_matches = |name: axum::http::HeaderName, patterns: &Option<Vec<String>>| {
+            let Some(patterns) = patterns else {
+                return true;
+            };
+            headers
+                .get_all(name)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(','))
+                .any(|media| patterns.iter().any(|p| mime_matches(p, media)))
+        };
+        header_matches(axum::http::header::CONTENT_TYPE, &self.content_type)
+            && header_matches(axum::http::header::ACCEPT, &self.accept)
+    }
+
+    /// 配置的谓词个数，同等前缀得分时谓词更多的路由优先
+    pub fn predicate_count(&self) -> usize {
+        self.content_type.is_some() as usize + self.accept.is_some() as usize
+            + self.schedule.is_some() as usize
+            + self.feature_flag.is_some() as usize
+    }
+
     fn matches_prefix(&self, prefix: &str, path: &str) -> bool {
         // 检查是否包含模式匹配字符
         if prefix.contains('{') || prefix.contains('*') || prefix.contains('?') {
@@ -151,45 +720,451 @@ impl RouteRule {
 
     // 校验配置
     pub fn validate(&self) -> Result<(), String> {
+        let errors = self.validation_errors();
+        if errors.is_empty() { Ok(()) } else { Err(errors.join("; ")) }
+    }
+
+    /// 收集全部校验错误，而不是遇到第一个就返回
+    pub fn validation_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
         if self.prefix.is_empty() {
-            return Err("prefix不能为空".to_string());
+            errors.push("prefix不能为空".to_string());
         }
         for (i, p) in self.prefix.iter().enumerate() {
             if p.trim().is_empty() {
-                return Err(format!("prefix[{}]不能为空", i));
+                errors.push(format!("prefix[{}]不能为空", i));
+            } else if (p.contains('{') || p.contains('*') || p.contains('?'))
+                && let Err(err) = RoutePattern::from_pattern(p)
+            {
+                errors.push(format!("prefix[{}]模式无法编译: {}", i, err));
+            }
+        }
+        if self.upstream.is_empty() && self.s3.is_none() && self.tarpit.is_none() {
+            errors.push("upstream不能为空".to_string());
+        }
+        if let Some(whitelist) = &self.whitelist {
+            errors.extend(crate::whitelist::validation_errors(whitelist));
+        }
+        if let Some(limits) = &self.whitelist_limits {
+            errors.extend(limits.validation_errors());
+            if self.whitelist.as_ref().is_none_or(Vec::is_empty) {
+                errors.push("whitelist_limits需要配置whitelist".to_string());
+            }
+        }
+        if let Some(s3) = &self.s3 {
+            errors.extend(s3.validation_errors());
+        }
+        if self.cost == Some(0) {
+            errors.push("cost必须大于0".to_string());
+        }
+        if self.qps == Some(0) {
+            errors.push("qps必须大于0".to_string());
+        }
+        // 令牌桶容量等于 QPS，cost 超过容量的请求永远拿不到足够的令牌
+        if let Some(cost) = self.cost {
+            if let Some(qps) = self.qps.filter(|&qps| qps > 0 && cost > qps) {
+                errors.push(format!("cost({})不能大于路由qps({})", cost, qps));
+            }
+            if let Some(qps) = self.whitelist_limits.as_ref().and_then(|l| l.client_qps).filter(|&qps| qps > 0 && cost > qps) {
+                errors.push(format!("cost({})不能大于whitelist_limits.client_qps({})", cost, qps));
+            }
+        }
+        errors.extend(crate::buffering::validation_errors(self));
+        errors.extend(crate::timeouts::validation_errors(self));
+        errors.extend(crate::plugin::validation_errors(self));
+        errors.extend(crate::schedule::validation_errors(self));
+        errors.extend(crate::feature_flags::validation_errors(self));
+        errors.extend(crate::methods::validation_errors(self));
+        errors.extend(crate::tenant_upstreams::validation_errors(self));
+        errors.extend(crate::retry::validation_errors(self));
+        errors.extend(crate::mirror::validation_errors(self));
+        errors.extend(crate::sendfile::validation_errors(self));
+        errors.extend(crate::capture::validation_errors(self));
+        if let Some(slo) = &self.slo {
+            errors.extend(slo.validation_errors());
+        }
+        for (i, anomaly) in self.anomaly.iter().flatten().enumerate() {
+            errors.extend(anomaly.validation_errors(i));
+        }
+        if let Some(policy) = &self.access_log {
+            errors.extend(policy.validation_errors());
+        }
+        if let Some(bot) = &self.bot {
+            errors.extend(bot.validation_errors());
+        }
+        if let Some(tarpit) = &self.tarpit {
+            errors.extend(tarpit.validation_errors());
+        }
+        if let Some(headers) = &self.strip_response_headers {
+            errors.extend(crate::header_filter::validation_errors("strip_response_headers", headers));
+        }
+        if let Some(pages) = &self.error_pages {
+            errors.extend(crate::error_pages::validation_errors(pages));
+        }
+        if let Some(priority) = &self.priority
+            && crate::qos::Class::parse(priority).is_none()
+        {
+            errors.push(format!("priority必须为low、normal、high或critical，当前为: {}", priority));
+        }
+        if let Some(protocols) = &self.upgrade {
+            if protocols.iter().any(|p| p.trim().is_empty()) {
+                errors.push("upgrade不能包含空协议名".to_string());
+            }
+            if self.http_version.as_deref() == Some("http2") {
+                errors.push("upgrade需要 HTTP/1.1，不能与 http_version = \"http2\" 同时使用".to_string());
+            }
+        }
+        if let Some(limits) = &self.websocket {
+            errors.extend(limits.validation_errors());
+            if !self.upgrade.as_deref().is_some_and(|allowed| crate::tunnel::allows(allowed, "websocket")) {
+                errors.push("websocket需要在upgrade中允许websocket协议".to_string());
+            }
+        }
+        errors.extend(crate::redirect::validation_errors(self));
+        if self.etag == Some(true) && !crate::buffering::Buffering::for_route(self).response() {
+            errors.push("etag需要缓冲响应体，buffering须为 full 或 response".to_string());
+        }
+        if let Some(cache) = &self.cache {
+            errors.extend(cache.validation_errors());
+            if !crate::buffering::Buffering::for_route(self).response() {
+                errors.push("cache需要缓冲响应体，buffering须为 full 或 response".to_string());
             }
         }
-        if self.upstream.is_empty() {
-            return Err("upstream不能为空".to_string());
+        if self.preserve_header_case == Some(true) && self.redirect.as_deref() == Some("follow") {
+            errors.push("preserve_header_case不支持跟随重定向，不能与 redirect = \"follow\" 同时使用".to_string());
+        }
+        if self.preserve_header_case == Some(true) && self.http_version.as_deref() == Some("http2") {
+            errors.push("preserve_header_case只支持 HTTP/1.1 上游，不能与 http_version = \"http2\" 同时使用".to_string());
+        }
+        if self.hedge_after_ms == Some(0) {
+            errors.push("hedge_after_ms必须大于0".to_string());
+        }
+        if self.hedge_after_ms.is_some() && !crate::buffering::Buffering::for_route(self).request() {
+            errors.push("hedge_after_ms需要缓冲请求体，buffering须为 full 或 request".to_string());
+        }
+        if self.grpc_reflection == Some(true) && self.http_version.as_deref() == Some("http1") {
+            errors.push("grpc_reflection需要 HTTP/2 上游，不能与 http_version = \"http1\" 同时使用".to_string());
+        }
+        for (region, upstreams) in self.regions.iter().flatten() {
+            if upstreams.is_empty() {
+                errors.push(format!("regions.{}不能为空", region));
+            }
         }
         for (i, u) in self.upstream.iter().enumerate() {
             if u.trim().is_empty() {
-                return Err(format!("upstream[{}]不能为空", i));
+                errors.push(format!("upstream[{}]不能为空", i));
             }
         }
-        
+        errors.extend(crate::upstream_template::validation_errors(self));
+        for u in self.all_upstreams().filter(|u| !u.trim().is_empty() && !crate::upstream_template::is_template(u)) {
+            if let Err(err) = crate::upstream_url::parse(u) {
+                errors.push(format!("上游地址无效: {}（{}）", u, err));
+            }
+        }
+        for (field, patterns) in [("content_type", &self.content_type), ("accept", &self.accept)] {
+            for (i, p) in patterns.iter().flatten().enumerate() {
+                if !p.contains('/') {
+                    errors.push(format!("{}[{}]不是合法的媒体类型: {}", field, i, p));
+                }
+            }
+        }
+
         // 校验负载均衡策略
-        match self.strategy.as_str() {
-            "robin" | "random" | "iphash" => Ok(()),
-            _ => Err(format!("不支持的负载均衡策略: {}", self.strategy)),
+        if !crate::load_balancer::registry::is_registered(&self.strategy) {
+            errors.push(format!(
+                "不支持的负载均衡策略: {}（可用: {}）",
+                self.strategy,
+                crate::load_balancer::registry::names().join(", ")
+            ));
+        }
+        if let Some(query) = &self.query {
+            errors.extend(query.validation_errors());
+        }
+        if let Some(versioning) = &self.versioning {
+            errors.extend(versioning.validation_errors());
+        }
+        if let Some(cors) = &self.cors {
+            errors.extend(cors.validation_errors());
         }
+        if let Some(stages) = &self.middleware
+            && let Err(err) = crate::pipeline::parse_stages(stages)
+        {
+            errors.push(format!("middleware配置有误: {}", err));
+        }
+        if let Some(schema) = &self.request_schema
+            && let Err(err) = crate::schema::load(schema)
+        {
+            errors.push(format!("request_schema无法加载: {}", err));
+        }
+        if let Some(mode) = &self.auth
+            && crate::auth::AuthMode::parse(mode).is_none()
+        {
+            errors.push(format!("不支持的鉴权模式: {}（可选 required/jwt、shadow、optional、api_key、none）", mode));
+        }
+        if let Some(version) = &self.http_version
+            && !matches!(version.as_str(), "http1" | "http2")
+        {
+            errors.push(format!("不支持的 HTTP 版本: {}（可选 http1、http2）", version));
+        }
+        errors
     }
 }
 
+impl Settings {
+    /// 收集全部设置项校验错误
+    pub fn validation_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.gateway_bind.trim().is_empty() {
+            errors.push("gateway_bind不能为空".to_string());
+        }
+        if self.jwt_decoding_key.is_empty() {
+            errors.push("jwt_decoding_key不能为空".to_string());
+        }
+        if self.global_qps == 0 {
+            errors.push("global_qps必须大于0".to_string());
+        }
+        if self.client_qps == 0 {
+            errors.push("client_qps必须大于0".to_string());
+        }
+        if self.tenant_qps == Some(0) {
+            errors.push("tenant_qps必须大于0".to_string());
+        }
+        if self.user_qps == Some(0) {
+            errors.push("user_qps必须大于0".to_string());
+        }
+        if self.rate_limit_evict_interval_secs == Some(0) {
+            errors.push("rate_limit_evict_interval_secs必须大于0".to_string());
+        }
+        for (field, items) in [
+            ("rate_limit_exempt_ips", &self.rate_limit_exempt_ips),
+            ("debug_trusted_ips", &self.debug_trusted_ips),
+            ("ops_allowed_ips", &self.ops_allowed_ips),
+        ] {
+            for item in items {
+                if crate::rate_limit::parse_net(item).is_none() {
+                    errors.push(format!("{}包含无效的 IP/网段: {}", field, item));
+                }
+            }
+        }
+        errors.extend(crate::header_filter::validation_errors("response_strip_headers", &self.response_strip_headers));
+        errors.extend(crate::claim_labels::validation_errors(&self.metrics_claim_labels));
+        if self.metrics_claim_max_values == Some(0) {
+            errors.push("metrics_claim_max_values必须大于0".to_string());
+        }
+        if self.access_log_sample_rate.is_some_and(|rate| !(0.0..=1.0).contains(&rate)) {
+            errors.push("access_log_sample_rate必须在0到1之间".to_string());
+        }
+        errors.extend(crate::access_log::fields_errors("access_log_fields", &self.access_log_fields));
+        if self.iphash_load_factor.is_some_and(|factor| !(factor >= 1.0 && factor.is_finite())) {
+            errors.push("iphash_load_factor必须是不小于1的数".to_string());
+        }
+        if self.iphash_virtual_nodes.is_some_and(|n| n == 0 || n > 10_000) {
+            errors.push("iphash_virtual_nodes必须在1到10000之间".to_string());
+        }
+        if let Some(hash) = &self.iphash_hash
+            && crate::load_balancer::hash::HashFunction::parse(hash).is_none()
+        {
+            errors.push(format!("iphash_hash仅支持 xxhash、fnv: {}", hash));
+        }
+        if self.max_response_header_bytes == Some(0) {
+            errors.push("max_response_header_bytes必须大于0".to_string());
+        }
+        if let Some(bind) = &self.ops_bind {
+            if bind.trim().is_empty() {
+                errors.push("ops_bind不能为空".to_string());
+            } else if bind == &self.gateway_bind {
+                errors.push("ops_bind不能与gateway_bind相同".to_string());
+            }
+        }
+        if let Some(mode) = &self.url_trailing_slash
+            && crate::normalize::TrailingSlash::parse(mode).is_none()
+        {
+            errors.push(format!("url_trailing_slash仅支持 keep、strip、add: {}", mode));
+        }
+        if let Some(mode) = &self.url_percent_decode
+            && crate::normalize::PercentDecode::parse(mode).is_none()
+        {
+            errors.push(format!("url_percent_decode仅支持 none、unreserved、all: {}", mode));
+        }
+        // %2F..%2F 解码后成为 /../，必须随后解析点段
+        if self.url_decode_slash == Some(true) && self.url_resolve_dot_segments == Some(false) {
+            errors.push("url_decode_slash需要url_resolve_dot_segments，否则 %2F..%2F 会解码为未解析的 /../".to_string());
+        }
+        if let Some(mode) = &self.route_diagnostics
+            && !matches!(mode.as_str(), "warn" | "error" | "off")
+        {
+            errors.push(format!("route_diagnostics仅支持 warn、error、off: {}", mode));
+        }
+        if let Some(action) = &self.url_traversal_action
+            && crate::normalize::TraversalAction::parse(action).is_none()
+        {
+            errors.push(format!("url_traversal_action仅支持 reject、normalize: {}", action));
+        }
+        if let Some(store) = &self.session_store {
+            let redis = store.starts_with("redis://") || store.starts_with("rediss://");
+            if store != "memory" && !redis {
+                errors.push(format!("session_store仅支持 memory 或 redis:// 地址: {}", store));
+            } else if redis && !cfg!(feature = "redis-session") {
+                errors.push("session_store使用 Redis 需要以 redis-session 特性编译".to_string());
+            }
+        }
+        if let Some(name) = &self.session_cookie_name
+            && !crate::session::valid_cookie_name(name)
+        {
+            errors.push(format!("session_cookie_name不是合法的 Cookie 名称: {:?}", name));
+        }
+        if let Some(path) = &self.upstream_warmup_path
+            && !path.starts_with('/')
+        {
+            errors.push(format!("upstream_warmup_path必须以 / 开头: {}", path));
+        }
+        errors.extend(crate::upstream_url::validation_errors(self));
+        errors.extend(crate::preflight::validation_errors(self));
+        errors.extend(crate::kubernetes::validation_errors(self));
+        errors.extend(crate::docker::validation_errors(self));
+        errors.extend(crate::coordination::validation_errors(self));
+        errors.extend(crate::hedging::validation_errors(self));
+        errors.extend(crate::schedule::settings_errors(self));
+        errors.extend(crate::capture::settings_errors(self));
+        errors.extend(crate::feature_flags::settings_errors(self));
+        errors.extend(crate::mtls::validation_errors(self));
+        errors.extend(crate::auth::api_key_errors(self));
+        errors.extend(crate::forward_proxy::validation_errors(&self.forward_proxy_allow));
+        errors.extend(crate::qos::validation_errors(self));
+        errors.extend(crate::ban::validation_errors(self));
+        errors.extend(crate::problem::validation_errors(&self.problem_types));
+        for (namespace, token) in &self.admin_namespace_tokens {
+            if token.is_empty() {
+                errors.push(format!("admin_namespace_tokens.{}不能为空", namespace));
+            } else if self.admin_token.as_ref() == Some(token) {
+                errors.push(format!("admin_namespace_tokens.{}不能与admin_token相同", namespace));
+            }
+        }
+        if self.qos_max_concurrency == Some(0) {
+            errors.push("qos_max_concurrency必须大于0".to_string());
+        }
+        if self.load_shed_cpu_percent.is_some_and(|p| p == 0 || p > 100) {
+            errors.push("load_shed_cpu_percent必须在1到100之间".to_string());
+        }
+        if self.load_shed_memory_mb == Some(0) {
+            errors.push("load_shed_memory_mb必须大于0".to_string());
+        }
+        if self.load_shed_event_loop_lag_ms == Some(0) {
+            errors.push("load_shed_event_loop_lag_ms必须大于0".to_string());
+        }
+        for (upstream, max) in &self.upstream_max_in_flight {
+            if *max == 0 {
+                errors.push(format!("upstream_max_in_flight.{}必须大于0", upstream));
+            }
+        }
+        if self.forward_proxy_enabled == Some(true) && self.forward_proxy_allow.is_empty() {
+            errors.push("启用正向代理时forward_proxy_allow不能为空".to_string());
+        }
+        if !self.middleware_pipeline.is_empty()
+            && let Err(err) = crate::pipeline::parse_stages(&self.middleware_pipeline)
+        {
+            errors.push(format!("middleware_pipeline配置有误: {}", err));
+        }
+        errors
+    }
+}
+
+/// 当前 profile（GATEWAY_ENV），如 dev/staging/prod
+pub fn active_profile() -> Option<String> {
+    env::var("GATEWAY_ENV").ok().map(|p| p.trim().to_string()).filter(|p| !p.is_empty())
+}
+
+// 基础配置文件，及 profile 覆盖文件（config.<profile>.*，深度合并且优先级更高）
+fn settings_file_names() -> Vec<String> {
+    let mut names = vec!["config".to_string()];
+    if let Some(profile) = active_profile() {
+        names.push(format!("config.{}", profile));
+    }
+    names
+}
+
 pub fn load_settings() -> Result<Settings, config::ConfigError> {
+    load_settings_with_defaults(&[])
+}
+
+/// 同 load_settings，配置文件与环境变量都未提供的键使用 defaults 中的取值
+pub fn load_settings_with_defaults(defaults: &[(&str, &str)]) -> Result<Settings, config::ConfigError> {
     // 先加载环境变量
     dotenvy::dotenv().ok();
 
-    let builder = Config::builder()
-        .add_source(File::with_name("config").required(false))
-        .add_source(config::Environment::default());
+    let file_names = settings_file_names();
+    let mut builder = Config::builder();
+    for (key, value) in defaults {
+        builder = builder.set_default(*key, *value)?;
+    }
+    for name in &file_names {
+        builder = builder.add_source(File::with_name(name).required(false));
+    }
+    let cfg = builder.add_source(config::Environment::default()).build()?;
+
+    // 环境变量里本就有大量无关键名，只校验配置文件中出现的键
+    let mut file_builder = Config::builder();
+    for name in &file_names {
+        file_builder = file_builder.add_source(File::with_name(name).required(false));
+    }
+    let file_keys: Vec<String> = file_builder
+        .build()
+        .and_then(|c| c.try_deserialize::<HashMap<String, config::Value>>())
+        .map(|m| m.into_keys().collect())
+        .unwrap_or_default();
+
+    let mut ignored = Vec::new();
+    let settings: Settings = serde_ignored::deserialize(cfg, |path| ignored.push(path.to_string()))?;
+
+    let mut errors: Vec<String> = ignored
+        .into_iter()
+        .filter(|key| file_keys.contains(key))
+        .map(|key| {
+            let location = file_names
+                .iter()
+                .rev()
+                .find_map(|name| {
+                    let path = format!("{}.toml", name);
+                    find_key_line(&path, &key).map(|line| format!("{}:{}: ", path, line))
+                })
+                .unwrap_or_default();
+            format!("{}未知的配置项: {}", location, key)
+        })
+        .collect();
+    errors.extend(settings.validation_errors());
 
-    let cfg = builder.build()?;
-    cfg.try_deserialize::<Settings>()
+    if errors.is_empty() {
+        Ok(settings)
+    } else {
+        Err(ConfigError::Message(format!("配置错误:\n  {}", errors.join("\n  "))))
+    }
+}
+
+// 在 TOML 文件中定位顶层键所在的行号
+fn find_key_line(path: &str, key: &str) -> Option<usize> {
+    let content = std::fs::read_to_string(path).ok()?;
+    content.lines().position(|line| {
+        line.trim_start()
+            .strip_prefix(key)
+            .is_some_and(|rest| rest.trim_start().starts_with('='))
+    }).map(|i| i + 1)
 }
 
 #[derive(Debug, Deserialize)]
-struct RoutesFile { routes: Vec<RouteRule> }
+#[serde(deny_unknown_fields)]
+struct RoutesFile {
+    // 额外引入的路由文件，支持 glob，相对于当前文件所在目录
+    #[serde(default)]
+    include: Vec<String>,
+    // 文件内路由的默认命名空间（路由可用 namespace 覆盖）
+    #[serde(default)]
+    namespace: Option<String>,
+    // 命名空间定义，须在使用它的文件中或更早加载的文件中声明
+    #[serde(default)]
+    namespaces: BTreeMap<String, crate::namespace::Namespace>,
+    #[serde(default)]
+    routes: Vec<toml::Spanned<toml::Value>>,
+}
 
 pub fn load_route_rules() -> Result<Vec<RouteRule>, ConfigError> {
     // 可执行文件同级目录
@@ -198,28 +1173,222 @@ pub fn load_route_rules() -> Result<Vec<RouteRule>, ConfigError> {
         .and_then(|p| p.parent().map(|p| p.to_path_buf()))
         .unwrap_or_else(|| env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
 
-    // 打包后同目录下的 routes.toml
-    let packaged_routes = exe_dir.join("routes.toml");
+    // 优先读取项目根目录（开发时），其次读取打包目录（部署时：bin 同目录）
+    let Some(base) = [PathBuf::from("."), exe_dir]
+        .into_iter()
+        .find(|d| d.join("routes.toml").is_file() || d.join("routes.d").is_dir())
+    else {
+        tracing::warn!("未找到 routes.toml 或 routes.d/，网关将不转发任何请求");
+        return Ok(Vec::new());
+    };
 
-    // 构建配置，优先读取项目根目录的 routes.toml，其次读取打包目录
-    let c = Config::builder()
-        .add_source(File::with_name("routes").required(false)) // 开发时：./routes.toml
-        .add_source(File::from(packaged_routes).required(false)) // 部署时：bin 同目录
-        .build()?;
+    load_route_rules_from(&base)
+        .map_err(|errors| ConfigError::Message(format!("路由配置错误:\n  {}", errors.join("\n  "))))
+}
 
-    // 反序列化到结构体
-    let rf: RoutesFile = c.try_deserialize()?;
+/// 从目录加载路由：先 routes.toml（及其 include），再按文件名顺序加载 routes.d/*.toml
+pub fn load_route_rules_from(base: &Path) -> Result<Vec<RouteRule>, Vec<String>> {
+    let mut queue: Vec<PathBuf> = Vec::new();
+    let main = base.join("routes.toml");
+    if main.is_file() {
+        queue.push(main);
+    }
+    let routes_d = base.join("routes.d");
+    if routes_d.is_dir() {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(&routes_d)
+            .map_err(|e| vec![format!("读取 {} 失败: {}", routes_d.display(), e)])?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "toml") && !is_profile_overlay(p))
+            .collect();
+        files.sort();
+        queue.extend(files);
+    }
 
-    // 校验所有路由规则
-    for (i, rule) in rf.routes.iter().enumerate() {
-        if let Err(err) = rule.validate() {
-            return Err(ConfigError::Message(format!(
-                "路由规则 #{} 配置错误: {}", i + 1, err
-            )));
+    let mut rules = Vec::new();
+    let mut errors = Vec::new();
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    let mut namespaces = BTreeMap::new();
+    let profile = active_profile();
+    // 深度优先：include 的文件紧跟在引入它的文件之后
+    queue.reverse();
+    while let Some(path) = queue.pop() {
+        let key = path.canonicalize().unwrap_or_else(|_| path.clone());
+        if !seen.insert(key) {
+            continue;
+        }
+        let mut file = path.display().to_string();
+        let mut content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                errors.push(format!("读取 {} 失败: {}", file, e));
+                continue;
+            }
+        };
+        // 合并后的内容不再对应原文件行号
+        let mut with_lines = true;
+        if let Some(overlay) = profile.as_deref().and_then(|p| profile_overlay_path(&path, p)) {
+            match apply_profile_overlay(&content, &overlay) {
+                Ok(merged) => {
+                    content = merged;
+                    file = format!("{}+{}", file, overlay.display());
+                    with_lines = false;
+                }
+                Err(e) => {
+                    errors.push(format!("合并 {} 失败: {}", overlay.display(), e));
+                    continue;
+                }
+            }
+        }
+        match parse_routes_file(&file, &content, with_lines, &mut namespaces) {
+            Ok((file_rules, includes)) => {
+                rules.extend(file_rules);
+                let dir = path.parent().unwrap_or(Path::new("."));
+                let mut included = Vec::new();
+                for pattern in includes {
+                    match expand_include(dir, &pattern) {
+                        Ok(paths) if paths.is_empty() => {
+                            tracing::warn!("{}: include \"{}\" 未匹配到任何文件", file, pattern)
+                        }
+                        Ok(paths) => included.extend(paths),
+                        Err(e) => errors.push(format!("{}: include \"{}\" 无效: {}", file, pattern, e)),
+                    }
+                }
+                queue.extend(included.into_iter().rev());
+            }
+            Err(file_errors) => errors.extend(file_errors),
         }
     }
 
-    Ok(rf.routes)
+    if errors.is_empty() { Ok(rules) } else { Err(errors) }
+}
+
+// 展开 include 模式，按路径排序保证加载顺序确定
+fn expand_include(dir: &Path, pattern: &str) -> Result<Vec<PathBuf>, glob::PatternError> {
+    let full = dir.join(pattern);
+    let mut paths: Vec<PathBuf> = glob::glob(&full.to_string_lossy())?
+        .filter_map(Result::ok)
+        .filter(|p| p.is_file() && !is_profile_overlay(p))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+// 带二级后缀的文件（如 orders.prod.toml）视为 profile 覆盖文件，不单独加载
+fn is_profile_overlay(path: &Path) -> bool {
+    path.file_stem().and_then(|s| s.to_str()).is_some_and(|stem| stem.contains('.'))
+}
+
+// X.toml 对应的 profile 覆盖文件 X.<profile>.toml
+fn profile_overlay_path(path: &Path, profile: &str) -> Option<PathBuf> {
+    let stem = path.file_stem()?.to_str()?;
+    let overlay = path.with_file_name(format!("{}.{}.toml", stem, profile));
+    overlay.is_file().then_some(overlay)
+}
+
+/// 深度合并 TOML：表逐键递归合并，routes 数组按 id 合并（无 id 或未找到则追加），其余值直接覆盖
+pub fn merge_toml(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base), toml::Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match (key.as_str(), base.get_mut(&key), value) {
+                    ("routes", Some(toml::Value::Array(base_routes)), toml::Value::Array(overlay_routes)) => {
+                        merge_routes(base_routes, overlay_routes)
+                    }
+                    (_, Some(existing), value) => merge_toml(existing, value),
+                    (_, None, value) => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn merge_routes(base: &mut Vec<toml::Value>, overlay: Vec<toml::Value>) {
+    for route in overlay {
+        let id = route.get("id").and_then(|v| v.as_str()).map(str::to_string);
+        match id.and_then(|id| base.iter_mut().find(|b| b.get("id").and_then(|v| v.as_str()) == Some(id.as_str()))) {
+            Some(existing) => merge_toml(existing, route),
+            None => base.push(route),
+        }
+    }
+}
+
+// 读取 profile 覆盖文件并与基础内容合并，返回合并后的 TOML 文本
+fn apply_profile_overlay(base_content: &str, overlay: &Path) -> Result<String, String> {
+    let mut base: toml::Value = toml::from_str(base_content).map_err(|e| e.message().to_string())?;
+    let overlay_content = std::fs::read_to_string(overlay).map_err(|e| e.to_string())?;
+    let overlay_value: toml::Value = toml::from_str(&overlay_content)
+        .map_err(|e| format!("{}: {}", overlay.display(), e.message()))?;
+    merge_toml(&mut base, overlay_value);
+    toml::to_string(&base).map_err(|e| e.to_string())
+}
+
+/// 解析并校验单个路由文件，一次性返回全部错误（带文件名与行号）；Ok 中附带其 include 列表。
+/// 文件中声明的命名空间加入 namespaces，供本文件及之后加载的文件使用
+fn parse_routes_file(
+    file: &str,
+    content: &str,
+    with_lines: bool,
+    namespaces: &mut BTreeMap<String, crate::namespace::Namespace>,
+) -> Result<(Vec<RouteRule>, Vec<String>), Vec<String>> {
+    let routes_file: RoutesFile = toml::from_str(content).map_err(|e| {
+        let location = e.span().map(|span| format!(":{}", line_of(content, span.start))).unwrap_or_default();
+        vec![format!("{}{}: {}", file, location, e.message())]
+    })?;
+
+    let mut rules = Vec::new();
+    let mut errors = Vec::new();
+    for (name, namespace) in routes_file.namespaces {
+        errors.extend(crate::namespace::validation_errors(&name, &namespace).into_iter().map(|e| format!("{}: {}", file, e)));
+        if namespaces.insert(name.clone(), namespace).is_some() {
+            errors.push(format!("{}: 命名空间 {} 重复定义", file, name));
+        }
+    }
+    for (i, spanned) in routes_file.routes.into_iter().enumerate() {
+        let context = if with_lines {
+            format!("{}:{}: 路由规则 #{}", file, line_of(content, spanned.span().start), i + 1)
+        } else {
+            format!("{}: 路由规则 #{}", file, i + 1)
+        };
+        let mut value = spanned.into_inner();
+        if let (Some(namespace), Some(table)) = (&routes_file.namespace, value.as_table_mut()) {
+            table.entry("namespace").or_insert_with(|| toml::Value::String(namespace.clone()));
+        }
+        // 先合并命名空间默认值再反序列化，路由自身的配置优先
+        let namespace = match value.get("namespace").and_then(|v| v.as_str()) {
+            Some(name) => match namespaces.get(name) {
+                Some(namespace) => Some((name.to_string(), namespace)),
+                None => {
+                    errors.push(format!("{}: 未定义的命名空间: {}", context, name));
+                    continue;
+                }
+            },
+            None => None,
+        };
+        if let Some((_, namespace)) = &namespace {
+            namespace.apply(&mut value);
+        }
+        match RouteRule::deserialize(value) {
+            Ok(mut rule) => {
+                rule.normalize();
+                errors.extend(rule.validation_errors().into_iter().map(|e| format!("{}: {}", context, e)));
+                if let Some((name, namespace)) = &namespace {
+                    errors.extend(namespace.prefix_errors(name, &rule).into_iter().map(|e| format!("{}: {}", context, e)));
+                }
+                rules.push(rule);
+            }
+            Err(err) => errors.push(format!("{}: {}", context, err.message())),
+        }
+    }
+
+    if errors.is_empty() { Ok((rules, routes_file.include)) } else { Err(errors) }
+}
+
+// 字节偏移 -> 行号（从 1 开始）
+fn line_of(content: &str, offset: usize) -> usize {
+    content[..offset.min(content.len())].matches('\n').count() + 1
 }
 
 #[cfg(test)]
@@ -233,13 +1402,13 @@ mod tests {
                 prefix: vec!["/user".to_string(), "/users".to_string()],
                 upstream: vec!["http://localhost:30000".to_string()],
                 strategy: "robin".to_string(),
-                whitelist: None,
+                ..Default::default()
             },
             RouteRule {
                 prefix: vec!["/api/user/{id}".to_string()],
                 upstream: vec!["http://localhost:30001".to_string(), "http://localhost:30002".to_string()],
                 strategy: "random".to_string(),
-                whitelist: None,
+                ..Default::default()
             },
         ];
 
@@ -264,13 +1433,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_route_rule_header_predicates() {
+        let grpc = RouteRule {
+            prefix: vec!["/svc/**".to_string()],
+            content_type: Some(vec!["application/grpc".to_string()]),
+            ..Default::default()
+        };
+        let json = RouteRule {
+            prefix: vec!["/svc/**".to_string()],
+            accept: Some(vec!["application/json".to_string(), "application/*+json".to_string()]),
+            ..Default::default()
+        };
+
+        let mut headers = axum::http::HeaderMap::new();
+        assert!(!grpc.matches_headers(&headers));
+        assert!(!json.matches_headers(&headers));
+        assert!(RouteRule::default().matches_headers(&headers));
+
+        headers.insert(axum::http::header::CONTENT_TYPE, "application/grpc".parse().unwrap());
+        assert!(grpc.matches_headers(&headers));
+
+        headers.insert(axum::http::header::ACCEPT, "text/html, application/*;q=0.9".parse().unwrap());
+        assert!(json.matches_headers(&headers));
+
+        assert!(mime_matches("application/json", "Application/JSON; charset=utf-8"));
+        assert!(!mime_matches("application/json", "application/grpc"));
+    }
+
     #[test]
     fn test_route_rule_validation() {
         let valid_route = RouteRule {
             prefix: vec!["/user".to_string()],
             upstream: vec!["http://localhost:30000".to_string()],
             strategy: "robin".to_string(),
-            whitelist: None,
+            ..Default::default()
         };
         assert!(valid_route.validate().is_ok());
 
@@ -278,7 +1475,7 @@ mod tests {
             prefix: vec![],
             upstream: vec!["http://localhost:30000".to_string()],
             strategy: "robin".to_string(),
-            whitelist: None,
+            ..Default::default()
         };
         assert!(invalid_prefix.validate().is_err());
 
@@ -286,7 +1483,7 @@ mod tests {
             prefix: vec!["/user".to_string()],
             upstream: vec![],
             strategy: "robin".to_string(),
-            whitelist: None,
+            ..Default::default()
         };
         assert!(invalid_upstream.validate().is_err());
 
@@ -294,8 +1491,210 @@ mod tests {
             prefix: vec!["/user".to_string()],
             upstream: vec!["http://localhost:30000".to_string()],
             strategy: "unknown".to_string(),
-            whitelist: None,
+            ..Default::default()
         };
         assert!(invalid_strategy.validate().is_err());
+
+        // cost 超过路由 qps 时该路由的请求永远无法通过
+        let costly = RouteRule { cost: Some(5), qps: Some(5), ..valid_route.clone() };
+        assert!(costly.validate().is_ok());
+        let costly = RouteRule { cost: Some(6), qps: Some(5), ..valid_route };
+        assert_eq!(costly.validation_errors(), ["cost(6)不能大于路由qps(5)"]);
+    }
+
+    #[test]
+    fn test_route_client_options_validation() {
+        let rule: RouteRule = serde_json::from_value(serde_json::json!({
+            "prefix": "/legacy/**",
+            "upstream": "http://localhost:30000",
+            "http_version": "http1",
+            "keep_alive": false,
+            "tcp_nodelay": false,
+        }))
+        .unwrap();
+        assert!(rule.validate().is_ok());
+        assert_eq!((rule.keep_alive, rule.tcp_nodelay), (Some(false), Some(false)));
+
+        let h2 = RouteRule { http_version: Some("http2".to_string()), ..rule.clone() };
+        assert!(h2.validate().is_ok());
+        let h3 = RouteRule { http_version: Some("http3".to_string()), ..rule };
+        let errors = h3.validation_errors();
+        assert!(errors.iter().any(|e| e.contains("不支持的 HTTP 版本: http3")), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_settings_redacted() {
+        let settings: Settings = serde_json::from_value(serde_json::json!({
+            "gateway_bind": "0.0.0.0:8080",
+            "jwt_decoding_key": "top-secret",
+            "global_qps": 100,
+            "client_qps": 10,
+            "rate_limit_exempt_api_keys": ["k1"],
+            "coordination_token": "consul-acl-token",
+            "feature_flag_token": "flags-sdk-key",
+            "qos_api_key_classes": { "gold-key-1": "high" },
+            "qos_fair_weights": { "gold-key-1": 4 },
+        }))
+        .unwrap();
+
+        let dump = settings.redacted();
+        assert_eq!(dump["coordination_token"], REDACTED);
+        assert_eq!(dump["feature_flag_token"], REDACTED);
+        let classes = dump["qos_api_key_classes"].as_object().unwrap();
+        assert!(classes.keys().all(|k| k.starts_with(REDACTED) && !k.contains("gold-key-1")), "{:?}", classes);
+        assert_eq!(classes.values().collect::<Vec<_>>(), ["high"]);
+        // 同一个键在两处脱敏后一致，可以对照
+        let weights = dump["qos_fair_weights"].as_object().unwrap();
+        assert_eq!(weights.keys().collect::<Vec<_>>(), classes.keys().collect::<Vec<_>>());
+        assert_eq!(weights.values().collect::<Vec<_>>(), [4]);
+        assert_eq!(dump["jwt_decoding_key"], REDACTED);
+        assert_eq!(dump["rate_limit_exempt_api_keys"], REDACTED);
+        assert!(dump["admin_token"].is_null());
+        assert_eq!(dump["global_qps"], 100);
+    }
+
+    #[test]
+    fn test_settings_url_normalization_validation() {
+        let settings: Settings = serde_json::from_value(serde_json::json!({
+            "gateway_bind": "0.0.0.0:8080",
+            "jwt_decoding_key": "top-secret",
+            "global_qps": 100,
+            "client_qps": 10,
+            "url_decode_slash": true,
+            "url_resolve_dot_segments": false,
+        }))
+        .unwrap();
+        assert!(settings.validation_errors().iter().any(|e| e.contains("url_decode_slash")));
+    }
+
+    #[test]
+    fn test_settings_session_validation() {
+        use crate::testing::settings;
+        assert!(settings(serde_json::json!({ "session_store": "memory", "session_cookie_name": "sid" })).validation_errors().is_empty());
+        let errors = settings(serde_json::json!({ "session_store": "memory", "session_cookie_name": "my session" })).validation_errors();
+        assert!(errors.iter().any(|e| e.contains("session_cookie_name")), "{:?}", errors);
+        // 未以 redis-session 特性编译时 Redis 地址在加载时即报错，而不是静默不启用会话
+        let errors = settings(serde_json::json!({ "session_store": "redis://localhost:6379" })).validation_errors();
+        assert_eq!(errors.iter().any(|e| e.contains("redis-session")), !cfg!(feature = "redis-session"), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_parse_route_rules_reports_all_errors() {
+        let content = r#"
+[[routes]]
+prefix = "/a"
+upstream = "http://localhost:30000"
+
+[[routes]]
+prefix = "/b"
+upstream = "http://localhost:30001"
+strateg = "random"
+
+[[routes]]
+prefix = []
+upstream = "http://localhost:30002"
+strategy = "unknown"
+"#;
+        let errors = parse_routes_file("routes.toml", content, true, &mut BTreeMap::new()).unwrap_err();
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        assert!(errors[0].starts_with("routes.toml:6: 路由规则 #2"), "{}", errors[0]);
+        assert!(errors[0].contains("strateg"));
+        assert!(errors[1].starts_with("routes.toml:11: 路由规则 #3"), "{}", errors[1]);
+
+        let (rules, _) = parse_routes_file("routes.toml", "[[routes]]\nprefix = \"/a\"\nupstream = \"http://x\"\n", true, &mut BTreeMap::new()).unwrap();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].strategy, "robin");
+    }
+
+    #[test]
+    fn test_load_route_rules_from_directory() {
+        let base = std::env::temp_dir().join(format!("helios-routes-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir_all(base.join("routes.d")).unwrap();
+        std::fs::create_dir_all(base.join("teams")).unwrap();
+        std::fs::write(
+            base.join("routes.toml"),
+            "include = [\"teams/*.toml\"]\n[[routes]]\nprefix = \"/main\"\nupstream = \"http://main\"\n",
+        ).unwrap();
+        std::fs::write(base.join("teams/order.toml"), "[[routes]]\nprefix = \"/order\"\nupstream = \"http://order\"\n").unwrap();
+        std::fs::write(base.join("routes.d/20-b.toml"), "[[routes]]\nprefix = \"/b\"\nupstream = \"http://b\"\n").unwrap();
+        std::fs::write(base.join("routes.d/10-a.toml"), "[[routes]]\nprefix = \"/a\"\nupstream = \"http://a\"\n").unwrap();
+
+        let rules = load_route_rules_from(&base).unwrap();
+        let prefixes: Vec<&str> = rules.iter().map(|r| r.prefix[0].as_str()).collect();
+        assert_eq!(prefixes, vec!["/main", "/order", "/a", "/b"]);
+
+        // 命名空间在 routes.toml 中声明，routes.d 中的文件整体归属该命名空间并继承默认值
+        std::fs::write(
+            base.join("routes.toml"),
+            "include = [\"teams/*.toml\"]\n[namespaces.shop]\nowner = \"shop-team\"\nprefixes = [\"/a\", \"/b\"]\n[namespaces.shop.defaults]\nqps = 20\n\n[[routes]]\nprefix = \"/main\"\nupstream = \"http://main\"\n",
+        ).unwrap();
+        std::fs::write(base.join("routes.d/20-b.toml"), "namespace = \"shop\"\n[[routes]]\nprefix = \"/b\"\nupstream = \"http://b\"\nqps = 5\n").unwrap();
+        std::fs::write(base.join("routes.d/10-a.toml"), "[[routes]]\nnamespace = \"shop\"\nprefix = \"/a\"\nupstream = \"http://a\"\n").unwrap();
+        let rules = load_route_rules_from(&base).unwrap();
+        assert_eq!(rules[0].namespace, None);
+        assert_eq!((rules[2].namespace.as_deref(), rules[2].qps), (Some("shop"), Some(20)));
+        assert_eq!((rules[3].namespace.as_deref(), rules[3].qps), (Some("shop"), Some(5)));
+
+        std::fs::write(base.join("routes.d/20-b.toml"), "namespace = \"shop\"\n[[routes]]\nprefix = \"/c\"\nupstream = \"http://c\"\n").unwrap();
+        std::fs::write(base.join("teams/order.toml"), "[[routes]]\nnamespace = \"nope\"\nprefix = \"/order\"\nupstream = \"http://order\"\n").unwrap();
+        let errors = load_route_rules_from(&base).unwrap_err();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(errors[0].contains("未定义的命名空间: nope"), "{}", errors[0]);
+        assert!(errors[1].contains("prefix /c 不在命名空间 shop（负责人 shop-team）允许"), "{}", errors[1]);
+        std::fs::write(base.join("routes.d/20-b.toml"), "[[routes]]\nprefix = \"/b\"\nupstream = \"http://b\"\n").unwrap();
+        std::fs::write(base.join("teams/order.toml"), "[[routes]]\nprefix = \"/order\"\nupstream = \"http://order\"\n").unwrap();
+
+        // 每个文件的错误都带上文件名
+        std::fs::write(base.join("routes.d/30-bad.toml"), "[[routes]]\nprefix = \"/c\"\n").unwrap();
+        let errors = load_route_rules_from(&base).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("30-bad.toml"), "{}", errors[0]);
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_merge_toml_profile_overlay() {
+        let mut base: toml::Value = toml::from_str(r#"
+gateway_bind = "0.0.0.0:8080"
+[limits]
+global = 100
+client = 10
+
+[[routes]]
+id = "user"
+prefix = "/user/**"
+upstream = "http://localhost:30000"
+
+[[routes]]
+prefix = "/static/**"
+upstream = "http://localhost:30001"
+"#).unwrap();
+        let overlay: toml::Value = toml::from_str(r#"
+[limits]
+global = 5000
+
+[[routes]]
+id = "user"
+upstream = ["http://user-a.prod:8080", "http://user-b.prod:8080"]
+strategy = "iphash"
+
+[[routes]]
+prefix = "/prod-only/**"
+upstream = "http://prod-only:8080"
+"#).unwrap();
+        merge_toml(&mut base, overlay);
+
+        assert_eq!(base["gateway_bind"].as_str(), Some("0.0.0.0:8080"));
+        assert_eq!(base["limits"]["global"].as_integer(), Some(5000));
+        assert_eq!(base["limits"]["client"].as_integer(), Some(10));
+
+        let routes = base["routes"].as_array().unwrap();
+        assert_eq!(routes.len(), 3);
+        assert_eq!(routes[0]["prefix"].as_str(), Some("/user/**"));
+        assert_eq!(routes[0]["strategy"].as_str(), Some("iphash"));
+        assert_eq!(routes[0]["upstream"].as_array().unwrap().len(), 2);
+        assert_eq!(routes[2]["prefix"].as_str(), Some("/prod-only/**"));
     }
 }
@@ -0,0 +1,418 @@
+//! 分布式协调：经 Consul KV 或 etcd 在网关副本间共享熔断、封禁与摘流状态，
+//! 一个副本的判断对整个集群生效，而不必每个副本各自触发。
+//!
+//! 每条状态是协调存储中 `<prefix>/<bans|breakers|drains>/<名称>` 下的一个键，值为
+//! `{"until_ms": 到期的 Unix 毫秒（0 表示不过期）, "reason": ...}`。本地变更异步写入；后台定期读取全部键，
+//! 把其他副本写入的状态应用到本地，此前应用过、现已删除的键则在本地撤销。
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use once_cell::sync::OnceCell;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+
+use crate::config::Settings;
+
+const DEFAULT_PREFIX: &str = "helios";
+const DEFAULT_POLL_SECS: u64 = 2;
+const DEFAULT_CONSUL: &str = "http://127.0.0.1:8500";
+const DEFAULT_ETCD: &str = "http://127.0.0.1:2379";
+// 不过期的熔断与封禁在本地按一年处理
+const NO_EXPIRY: Duration = Duration::from_secs(365 * 24 * 3600);
+
+/// 协调存储
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Consul,
+    Etcd,
+}
+
+impl Backend {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "consul" => Some(Self::Consul),
+            "etcd" => Some(Self::Etcd),
+            _ => None,
+        }
+    }
+
+    fn default_endpoint(&self) -> &'static str {
+        match self {
+            Self::Consul => DEFAULT_CONSUL,
+            Self::Etcd => DEFAULT_ETCD,
+        }
+    }
+}
+
+/// 共享的状态类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    // 来源封禁（名称为 ip:... / token:...）
+    Ban,
+    // 上游熔断（名称为上游 URL）
+    Breaker,
+    // 上游摘流（名称为上游 URL）
+    Drain,
+}
+
+impl Kind {
+    fn dir(&self) -> &'static str {
+        match self {
+            Self::Ban => "bans",
+            Self::Breaker => "breakers",
+            Self::Drain => "drains",
+        }
+    }
+
+    fn from_dir(dir: &str) -> Option<Self> {
+        [Self::Ban, Self::Breaker, Self::Drain].into_iter().find(|k| k.dir() == dir)
+    }
+}
+
+// 名称中的 / 会被当作层级，编码后作为键的最后一段
+fn encode_name(name: &str) -> String {
+    name.replace('%', "%25").replace('/', "%2F")
+}
+
+fn decode_name(name: &str) -> String {
+    name.replace("%2F", "/").replace("%25", "%")
+}
+
+fn unix_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+// ===== 本地变更 -> 协调存储 =====
+#[derive(Debug)]
+enum Op {
+    Put(Kind, String, Value),
+    Delete(Kind, String),
+}
+
+static OPS: OnceCell<mpsc::UnboundedSender<Op>> = OnceCell::new();
+
+/// 共享一条状态，duration 为 None 表示不过期；未启用协调时不做任何事
+pub fn publish(kind: Kind, name: &str, duration: Option<Duration>, reason: &str) {
+    if let Some(ops) = OPS.get() {
+        let until_ms = duration.map_or(0, |d| unix_ms() + d.as_millis() as u64);
+        let _ = ops.send(Op::Put(kind, name.to_string(), json!({ "until_ms": until_ms, "reason": reason })));
+    }
+}
+
+/// 撤销一条共享状态
+pub fn withdraw(kind: Kind, name: &str) {
+    if let Some(ops) = OPS.get() {
+        let _ = ops.send(Op::Delete(kind, name.to_string()));
+    }
+}
+
+// ===== 存储访问 =====
+struct Store {
+    backend: Backend,
+    endpoint: String,
+    prefix: String,
+    token: Option<String>,
+    client: reqwest::Client,
+}
+
+impl Store {
+    fn key(&self, kind: Kind, name: &str) -> String {
+        format!("{}/{}/{}", self.prefix, kind.dir(), encode_name(name))
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, format!("{}{}", self.endpoint, path));
+        match (&self.token, self.backend) {
+            (Some(token), Backend::Consul) => request.header("X-Consul-Token", token),
+            (Some(token), Backend::Etcd) => request.header(reqwest::header::AUTHORIZATION, token),
+            (None, _) => request,
+        }
+    }
+
+    async fn put(&self, key: &str, value: &Value) -> anyhow::Result<()> {
+        let request = match self.backend {
+            Backend::Consul => self.request(reqwest::Method::PUT, &format!("/v1/kv/{}", key)).body(value.to_string()),
+            Backend::Etcd => self
+                .request(reqwest::Method::POST, "/v3/kv/put")
+                .json(&json!({ "key": BASE64.encode(key), "value": BASE64.encode(value.to_string()) })),
+        };
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        let request = match self.backend {
+            Backend::Consul => self.request(reqwest::Method::DELETE, &format!("/v1/kv/{}", key)),
+            Backend::Etcd => self.request(reqwest::Method::POST, "/v3/kv/deleterange").json(&json!({ "key": BASE64.encode(key) })),
+        };
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+
+    /// 前缀下的全部键值
+    async fn list(&self) -> anyhow::Result<Vec<(String, String)>> {
+        let prefix = format!("{}/", self.prefix);
+        match self.backend {
+            Backend::Consul => {
+                let resp = self.request(reqwest::Method::GET, &format!("/v1/kv/{}?recurse=true", prefix)).send().await?;
+                // 前缀下没有键时返回 404
+                if resp.status() == reqwest::StatusCode::NOT_FOUND {
+                    return Ok(Vec::new());
+                }
+                let body: Value = resp.error_for_status()?.json().await?;
+                Ok(decode_kvs(&body, "Key", "Value", false))
+            }
+            Backend::Etcd => {
+                let body: Value = self
+                    .request(reqwest::Method::POST, "/v3/kv/range")
+                    .json(&json!({ "key": BASE64.encode(&prefix), "range_end": BASE64.encode(range_end(&prefix)) }))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                Ok(decode_kvs(&body["kvs"], "key", "value", true))
+            }
+        }
+    }
+}
+
+// etcd 的前缀查询：range_end 为前缀最后一个字节加一
+fn range_end(prefix: &str) -> Vec<u8> {
+    let mut end = prefix.as_bytes().to_vec();
+    if let Some(last) = end.last_mut() {
+        *last += 1;
+    }
+    end
+}
+
+// Consul 的键为明文、值为 base64；etcd 的键和值都是 base64
+fn decode_kvs(items: &Value, key_field: &str, value_field: &str, key_base64: bool) -> Vec<(String, String)> {
+    let decode = |s: &str| BASE64.decode(s).ok().and_then(|b| String::from_utf8(b).ok());
+    items
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|item| {
+            let key = item[key_field].as_str()?;
+            let key = if key_base64 { decode(key)? } else { key.to_string() };
+            Some((key, decode(item[value_field].as_str()?)?))
+        })
+        .collect()
+}
+
+/// 存储中的一条状态
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    key: String,
+    kind: Kind,
+    name: String,
+    until_ms: u64,
+    reason: String,
+}
+
+fn parse_entries(prefix: &str, kvs: Vec<(String, String)>) -> Vec<Entry> {
+    kvs.into_iter()
+        .filter_map(|(key, value)| {
+            let rest = key.strip_prefix(prefix)?.strip_prefix('/')?;
+            let (dir, name) = rest.split_once('/')?;
+            let value: Value = serde_json::from_str(&value).ok()?;
+            Some(Entry {
+                kind: Kind::from_dir(dir)?,
+                name: decode_name(name),
+                until_ms: value["until_ms"].as_u64().unwrap_or(0),
+                reason: value["reason"].as_str().unwrap_or_default().to_string(),
+                key,
+            })
+        })
+        .collect()
+}
+
+// ===== 协调存储 -> 本地 =====
+fn apply(entry: &Entry, remaining: Option<Duration>) {
+    match entry.kind {
+        Kind::Ban => crate::ban::apply_shared(&entry.name, remaining.unwrap_or(NO_EXPIRY), &entry.reason),
+        Kind::Breaker => crate::upstream::eject_shared(&entry.name, remaining.unwrap_or(NO_EXPIRY)),
+        Kind::Drain => {
+            if !crate::upstream::set_draining(&entry.name, true) {
+                tracing::info!(upstream = %entry.name, "按其他副本的共享状态摘流");
+            }
+        }
+    }
+}
+
+fn revoke(kind: Kind, name: &str) {
+    match kind {
+        Kind::Ban => crate::ban::lift_shared(name),
+        Kind::Breaker => crate::upstream::close_shared(name),
+        Kind::Drain => {
+            if crate::upstream::set_draining(name, false) {
+                tracing::info!(upstream = %name, "按其他副本的共享状态恢复");
+            }
+        }
+    }
+}
+
+/// 应用一次读取到的全部状态，返回此次应用的键集合与已过期、应从存储删除的键
+fn sync(entries: &[Entry], applied: &BTreeSet<(Kind, String)>, now_ms: u64) -> (BTreeSet<(Kind, String)>, Vec<String>) {
+    let mut current = BTreeSet::new();
+    let mut expired = Vec::new();
+    for entry in entries {
+        let remaining = match entry.until_ms {
+            0 => None,
+            until if until > now_ms => Some(Duration::from_millis(until - now_ms)),
+            _ => {
+                expired.push(entry.key.clone());
+                continue;
+            }
+        };
+        apply(entry, remaining);
+        current.insert((entry.kind, entry.name.clone()));
+    }
+    for (kind, name) in applied.difference(&current) {
+        revoke(*kind, name);
+    }
+    (current, expired)
+}
+
+/// 配置了 coordination_backend 时启动后台同步；须在 tokio 运行时中调用
+pub fn spawn(settings: &Settings) {
+    let Some(backend) = settings.coordination_backend.as_deref().and_then(Backend::parse) else {
+        return;
+    };
+    let (tx, rx) = mpsc::unbounded_channel();
+    if OPS.set(tx).is_err() {
+        return;
+    }
+    let store = Store {
+        backend,
+        endpoint: settings.coordination_endpoint.as_deref().unwrap_or(backend.default_endpoint()).trim_end_matches('/').to_string(),
+        prefix: settings.coordination_prefix.as_deref().unwrap_or(DEFAULT_PREFIX).trim_matches('/').to_string(),
+        token: settings.coordination_token.clone(),
+        client: reqwest::Client::builder().timeout(Duration::from_secs(5)).build().unwrap_or_default(),
+    };
+    let interval = Duration::from_secs(settings.coordination_poll_secs.unwrap_or(DEFAULT_POLL_SECS).max(1));
+    tokio::spawn(run(store, rx, interval));
+}
+
+async fn run(store: Store, mut ops: mpsc::UnboundedReceiver<Op>, interval: Duration) {
+    let mut applied = BTreeSet::new();
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        tokio::select! {
+            Some(op) = ops.recv() => {
+                let result = match &op {
+                    Op::Put(kind, name, value) => store.put(&store.key(*kind, name), value).await,
+                    Op::Delete(kind, name) => store.delete(&store.key(*kind, name)).await,
+                };
+                if let Err(err) = result {
+                    tracing::warn!(?op, "写入协调存储失败: {}", err);
+                }
+            }
+            _ = interval.tick() => {
+                let kvs = match store.list().await {
+                    Ok(kvs) => kvs,
+                    Err(err) => {
+                        tracing::warn!("读取协调存储失败，保留本地状态: {}", err);
+                        continue;
+                    }
+                };
+                let entries = parse_entries(&store.prefix, kvs);
+                let (current, expired) = sync(&entries, &applied, unix_ms());
+                applied = current;
+                for key in expired {
+                    let _ = store.delete(&key).await;
+                }
+            }
+        }
+    }
+}
+
+pub fn validation_errors(settings: &Settings) -> Vec<String> {
+    let mut errors = Vec::new();
+    if let Some(backend) = &settings.coordination_backend
+        && Backend::parse(backend).is_none()
+    {
+        errors.push(format!("coordination_backend仅支持 consul、etcd: {}", backend));
+    }
+    if let Some(endpoint) = &settings.coordination_endpoint
+        && reqwest::Url::parse(endpoint).is_err()
+    {
+        errors.push(format!("coordination_endpoint不是有效的地址: {}", endpoint));
+    }
+    if settings.coordination_prefix.as_deref().is_some_and(|p| p.trim_matches('/').is_empty()) {
+        errors.push("coordination_prefix不能为空".to_string());
+    }
+    if settings.coordination_poll_secs == Some(0) {
+        errors.push("coordination_poll_secs必须大于0".to_string());
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entries() {
+        let value = |until: u64| json!({ "until_ms": until, "reason": "5次401" }).to_string();
+        let kvs = vec![
+            ("helios/bans/ip:10.8.0.1".to_string(), value(5000)),
+            ("helios/breakers/http:%2F%2Forders:8080".to_string(), value(0)),
+            ("helios/unknown/x".to_string(), value(0)),
+            ("other/bans/ip:10.8.0.2".to_string(), value(0)),
+        ];
+        let entries = parse_entries("helios", kvs);
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].kind, entries[0].name.as_str(), entries[0].until_ms), (Kind::Ban, "ip:10.8.0.1", 5000));
+        assert_eq!(entries[0].reason, "5次401");
+        assert_eq!((entries[1].kind, entries[1].name.as_str()), (Kind::Breaker, "http://orders:8080"));
+        assert_eq!(encode_name("http://orders:8080/a%2F"), "http:%2F%2Forders:8080%2Fa%252F");
+        assert_eq!(decode_name(&encode_name("http://orders:8080/a%2F")), "http://orders:8080/a%2F");
+        assert_eq!(range_end("helios/"), b"helios0".to_vec());
+    }
+
+    #[test]
+    fn test_decode_kvs() {
+        let consul = json!([{ "Key": "helios/drains/x", "Value": BASE64.encode("{}") }, { "Key": "helios/empty", "Value": null }]);
+        assert_eq!(decode_kvs(&consul, "Key", "Value", false), vec![("helios/drains/x".to_string(), "{}".to_string())]);
+        let etcd = json!([{ "key": BASE64.encode("helios/drains/x"), "value": BASE64.encode("{}") }]);
+        assert_eq!(decode_kvs(&etcd, "key", "value", true), vec![("helios/drains/x".to_string(), "{}".to_string())]);
+    }
+
+    #[test]
+    fn test_sync_applies_and_revokes() {
+        let entry = |kind: Kind, name: &str, until_ms: u64| Entry {
+            key: format!("helios/{}/{}", kind.dir(), encode_name(name)),
+            kind,
+            name: name.to_string(),
+            until_ms,
+            reason: "shared".to_string(),
+        };
+        let upstream = "http://coordination-test:8080";
+        let source = "ip:10.8.1.1".to_string();
+        let now = 1_000_000;
+        let entries = vec![
+            entry(Kind::Ban, &source, now + 60_000),
+            entry(Kind::Breaker, upstream, now + 30_000),
+            entry(Kind::Drain, upstream, 0),
+            entry(Kind::Ban, "ip:10.8.1.2", now - 1),
+        ];
+        let (applied, expired) = sync(&entries, &BTreeSet::new(), now);
+        assert_eq!(applied.len(), 3);
+        assert_eq!(expired, vec!["helios/bans/ip:10.8.1.2".to_string()]);
+        assert!(crate::ban::banned(std::slice::from_ref(&source)).is_some());
+        let status = crate::upstream::status(upstream);
+        assert!(status.ejected && status.draining);
+
+        // 其他副本解除后，本地随之撤销
+        let (applied, _) = sync(&entries[2..3], &applied, now);
+        assert_eq!(applied.len(), 1);
+        assert!(crate::ban::banned(&[source]).is_none());
+        let status = crate::upstream::status(upstream);
+        assert!(!status.ejected && status.draining);
+        sync(&[], &applied, now);
+        assert!(!crate::upstream::status(upstream).draining);
+    }
+}
@@ -0,0 +1,244 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, Method, Response, StatusCode},
+    middleware::Next,
+    response::IntoResponse,
+};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::metrics::CORS_PREFLIGHT_COUNTER;
+use crate::problem::{Problem, ProblemType};
+
+// 缓存条目上限，超过后整体清空，避免大量伪造 Origin 撑大缓存
+const MAX_CACHED_PREFLIGHTS: usize = 10_000;
+
+/// 路由级 CORS 配置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CorsPolicy {
+    // 允许的来源：完整 origin、* 或 *.example.com 形式的子域通配
+    #[serde(default)]
+    pub allow_origins: Vec<String>,
+    #[serde(default = "default_allow_methods")]
+    pub allow_methods: Vec<String>,
+    #[serde(default = "default_allow_headers")]
+    pub allow_headers: Vec<String>,
+    #[serde(default)]
+    pub expose_headers: Vec<String>,
+    #[serde(default)]
+    pub allow_credentials: bool,
+    // 预检结果的有效期（Access-Control-Max-Age），网关侧缓存使用同一时长
+    #[serde(default = "default_max_age_secs")]
+    pub max_age_secs: u64,
+}
+
+fn default_allow_methods() -> Vec<String> {
+    ["GET", "POST", "PUT", "PATCH", "DELETE"].map(String::from).to_vec()
+}
+
+fn default_allow_headers() -> Vec<String> {
+    ["authorization", "content-type"].map(String::from).to_vec()
+}
+
+fn default_max_age_secs() -> u64 {
+    600
+}
+
+impl CorsPolicy {
+    /// 来源是否被允许
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.allow_origins.iter().any(|allowed| {
+            if allowed == "*" {
+                return true;
+            }
+            match allowed.split_once("://*.") {
+                Some((scheme, domain)) => origin
+                    .strip_prefix(scheme)
+                    .and_then(|rest| rest.strip_prefix("://"))
+                    .and_then(|host| host.strip_suffix(domain))
+                    .is_some_and(|sub| sub.ends_with('.') && sub.len() > 1),
+                None => allowed.eq_ignore_ascii_case(origin),
+            }
+        })
+    }
+
+    // 携带凭据时不能返回 *，统一回显具体来源
+    fn allow_origin_value(&self, origin: &str) -> Option<HeaderValue> {
+        if self.allow_origins.iter().all(|o| o == "*") && !self.allow_credentials {
+            return Some(HeaderValue::from_static("*"));
+        }
+        HeaderValue::from_str(origin).ok()
+    }
+
+    /// 生成预检响应头；来源不被允许时为 None
+    pub fn preflight_headers(&self, origin: &str) -> Option<HeaderMap> {
+        if !self.allows_origin(origin) {
+            return None;
+        }
+        let mut headers = self.response_headers(origin)?;
+        let list = |items: &[String]| HeaderValue::from_str(&items.join(", ")).ok();
+        if let Some(methods) = list(&self.allow_methods) {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, methods);
+        }
+        if let Some(allow) = list(&self.allow_headers) {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, allow);
+        }
+        headers.insert(header::ACCESS_CONTROL_MAX_AGE, HeaderValue::from(self.max_age_secs));
+        Some(headers)
+    }
+
+    /// 普通跨域请求附加的响应头
+    pub fn response_headers(&self, origin: &str) -> Option<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, self.allow_origin_value(origin)?);
+        headers.insert(header::VARY, HeaderValue::from_static("Origin"));
+        if self.allow_credentials {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+        }
+        if !self.expose_headers.is_empty()
+            && let Ok(expose) = HeaderValue::from_str(&self.expose_headers.join(", "))
+        {
+            headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, expose);
+        }
+        Some(headers)
+    }
+
+    /// 校验错误
+    pub fn validation_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.allow_origins.is_empty() {
+            errors.push("cors.allow_origins不能为空".to_string());
+        }
+        if self.allow_origins.iter().any(|o| o.trim().is_empty()) {
+            errors.push("cors.allow_origins不能包含空来源".to_string());
+        }
+        for method in &self.allow_methods {
+            if Method::from_bytes(method.as_bytes()).is_err() {
+                errors.push(format!("cors.allow_methods包含非法方法: {}", method));
+            }
+        }
+        errors
+    }
+}
+
+// ===== 预检结果缓存：按（路由, Origin）缓存计算好的响应头 =====
+struct CachedPreflight {
+    // None 表示该来源被拒绝
+    headers: Option<HeaderMap>,
+    expires_at: Instant,
+}
+
+static PREFLIGHT_CACHE: Lazy<DashMap<(String, String), CachedPreflight>> = Lazy::new(DashMap::new);
+
+fn cached_preflight(route: &str, origin: &str, policy: &CorsPolicy) -> Option<HeaderMap> {
+    let key = (route.to_string(), origin.to_string());
+    if let Some(entry) = PREFLIGHT_CACHE.get(&key)
+        && entry.expires_at > Instant::now()
+    {
+        CORS_PREFLIGHT_COUNTER.with_label_values(&[route, "hit"]).inc();
+        return entry.headers.clone();
+    }
+
+    CORS_PREFLIGHT_COUNTER.with_label_values(&[route, "miss"]).inc();
+    let headers = policy.preflight_headers(origin);
+    if PREFLIGHT_CACHE.len() >= MAX_CACHED_PREFLIGHTS {
+        PREFLIGHT_CACHE.clear();
+    }
+    PREFLIGHT_CACHE.insert(
+        key,
+        CachedPreflight { headers: headers.clone(), expires_at: Instant::now() + Duration::from_secs(policy.max_age_secs) },
+    );
+    headers
+}
+
+// ===== CORS 中间件：在鉴权之前应答预检，并为跨域响应附加头部 =====
+pub async fn cors_layer(req: Request<Body>, next: Next) -> Response<Body> {
+    let Some(origin) = req.headers().get(header::ORIGIN).and_then(|v| v.to_str().ok()).map(str::to_string) else {
+        return next.run(req).await;
+    };
+    let matched = req
+        .extensions()
+        .get::<crate::pipeline::MatchedRoute>()
+        .and_then(|matched| Some((crate::proxy::route_label(&matched.0), matched.0.cors.clone()?)));
+    let Some((route, policy)) = matched else {
+        return next.run(req).await;
+    };
+
+    let is_preflight = req.method() == Method::OPTIONS && req.headers().contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+    if is_preflight {
+        let Some(headers) = cached_preflight(&route, &origin, &policy) else {
+            CORS_PREFLIGHT_COUNTER.with_label_values(&[&route, "rejected"]).inc();
+            return Problem::new(StatusCode::FORBIDDEN, ProblemType::CorsOriginNotAllowed)
+                .detail(format!("Origin {} is not allowed", origin))
+                .into_response();
+        };
+        let mut resp = Response::new(Body::empty());
+        *resp.status_mut() = StatusCode::NO_CONTENT;
+        resp.headers_mut().extend(headers);
+        return resp;
+    }
+
+    let mut resp = next.run(req).await;
+    if policy.allows_origin(&origin)
+        && let Some(headers) = policy.response_headers(&origin)
+    {
+        resp.headers_mut().extend(headers);
+    }
+    resp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(origins: &[&str]) -> CorsPolicy {
+        CorsPolicy {
+            allow_origins: origins.iter().map(|o| o.to_string()).collect(),
+            allow_methods: default_allow_methods(),
+            allow_headers: default_allow_headers(),
+            expose_headers: vec!["x-request-id".to_string()],
+            allow_credentials: false,
+            max_age_secs: 60,
+        }
+    }
+
+    #[test]
+    fn test_allows_origin() {
+        let p = policy(&["https://app.example.com", "https://*.example.org"]);
+        assert!(p.allows_origin("https://app.example.com"));
+        assert!(p.allows_origin("https://a.b.example.org"));
+        assert!(!p.allows_origin("https://example.org"));
+        assert!(!p.allows_origin("http://a.example.org"));
+        assert!(!p.allows_origin("https://evil.com"));
+        assert!(policy(&["*"]).allows_origin("https://anything.dev"));
+    }
+
+    #[test]
+    fn test_preflight_headers() {
+        let mut p = policy(&["*"]);
+        let headers = p.preflight_headers("https://app.dev").unwrap();
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "60");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_METHODS], "GET, POST, PUT, PATCH, DELETE");
+
+        p.allow_credentials = true;
+        let headers = p.preflight_headers("https://app.dev").unwrap();
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://app.dev");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+
+        assert!(policy(&["https://app.dev"]).preflight_headers("https://evil.dev").is_none());
+    }
+
+    #[test]
+    fn test_preflight_cache() {
+        let p = policy(&["https://app.dev"]);
+        assert!(cached_preflight("cors-test", "https://app.dev", &p).is_some());
+        // 缓存期内即使策略变化也返回缓存结果
+        assert!(cached_preflight("cors-test", "https://app.dev", &policy(&[])).is_some());
+        assert!(cached_preflight("cors-test", "https://evil.dev", &p).is_none());
+    }
+}
@@ -0,0 +1,153 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{HeaderName, HeaderValue, Response},
+    middleware::Next,
+};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use crate::config::Settings;
+use crate::rate_limit::{client_ip, parse_net};
+
+/// 调试请求头，取值格式：`<unix 秒>.<hex(HMAC-SHA256(debug_secret, unix 秒))>`
+pub const DEBUG_HEADER: &str = "x-gateway-debug";
+
+// 签名有效期，防止截获的调试头被长期重放
+const SIGNATURE_TTL_SECS: u64 = 300;
+
+/// 单个请求的调试信息，由代理流程逐步填充
+#[derive(Debug)]
+pub struct DebugTrace {
+    start: Instant,
+    inner: Mutex<DebugInner>,
+}
+
+#[derive(Debug, Default)]
+struct DebugInner {
+    fields: Vec<(&'static str, String)>,
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl DebugTrace {
+    fn new() -> Self {
+        Self { start: Instant::now(), inner: Mutex::new(DebugInner::default()) }
+    }
+
+    /// 记录一项诊断字段，输出为 `x-gateway-debug-<key>` 响应头
+    pub fn set(&self, key: &'static str, value: impl Into<String>) {
+        self.inner.lock().unwrap().fields.push((key, value.into()));
+    }
+
+    /// 记录某个阶段从 since 到现在的耗时
+    pub fn phase(&self, name: &'static str, since: Instant) {
+        self.inner.lock().unwrap().phases.push((name, since.elapsed()));
+    }
+
+    fn apply(&self, resp: &mut Response<Body>) {
+        let inner = self.inner.lock().unwrap();
+        let headers = resp.headers_mut();
+        for (key, value) in &inner.fields {
+            if let (Ok(name), Ok(v)) = (
+                HeaderName::try_from(format!("{}-{}", DEBUG_HEADER, key)),
+                HeaderValue::from_str(value),
+            ) {
+                headers.insert(name, v);
+            }
+        }
+
+        let mut timing: Vec<String> = inner
+            .phases
+            .iter()
+            .map(|(name, d)| format!("{}={:.3}ms", name, d.as_secs_f64() * 1000.0))
+            .collect();
+        timing.push(format!("total={:.3}ms", self.start.elapsed().as_secs_f64() * 1000.0));
+        if let Ok(v) = HeaderValue::from_str(&timing.join(", ")) {
+            headers.insert(HeaderName::from_static("x-gateway-debug-timing"), v);
+        }
+    }
+}
+
+/// 校验调试头签名
+pub fn verify_signature(value: &str, secret: &str, now_secs: u64) -> bool {
+    let Some((ts, sig)) = value.split_once('.') else {
+        return false;
+    };
+    let Ok(ts_secs) = ts.parse::<u64>() else {
+        return false;
+    };
+    if now_secs.abs_diff(ts_secs) > SIGNATURE_TTL_SECS {
+        return false;
+    }
+    let Ok(sig_bytes) = hex::decode(sig) else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(ts.as_bytes());
+    mac.verify_slice(&sig_bytes).is_ok()
+}
+
+fn is_debug_allowed(req: &Request, settings: &Settings) -> bool {
+    let ip: IpAddr = client_ip(req);
+    if settings
+        .debug_trusted_ips
+        .iter()
+        .filter_map(|s| parse_net(s))
+        .any(|net| net.contains(&ip))
+    {
+        return true;
+    }
+
+    let (Some(secret), Some(value)) = (
+        settings.debug_secret.as_deref().filter(|s| !s.is_empty()),
+        req.headers().get(DEBUG_HEADER).and_then(|v| v.to_str().ok()),
+    ) else {
+        return false;
+    };
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    verify_signature(value, secret, now)
+}
+
+// ===== 调试追踪中间件：授权请求在响应头中附带诊断信息 =====
+pub async fn debug_layer(mut req: Request, next: Next) -> Response<Body> {
+    let allowed = req
+        .extensions()
+        .get::<Settings>()
+        .map(|s| is_debug_allowed(&req, s))
+        .unwrap_or(false);
+
+    if !allowed {
+        return next.run(req).await;
+    }
+
+    let trace = Arc::new(DebugTrace::new());
+    req.extensions_mut().insert(trace.clone());
+    let mut resp = next.run(req).await;
+    trace.apply(&mut resp);
+    resp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(secret: &str, ts: u64) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(ts.to_string().as_bytes());
+        format!("{}.{}", ts, hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let now = 1_700_000_000;
+        assert!(verify_signature(&sign("s3cret", now), "s3cret", now));
+        assert!(!verify_signature(&sign("other", now), "s3cret", now));
+        // 过期签名
+        assert!(!verify_signature(&sign("s3cret", now - 1000), "s3cret", now));
+        assert!(!verify_signature("garbage", "s3cret", now));
+    }
+}
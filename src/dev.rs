@@ -0,0 +1,173 @@
+//! 本地开发模式（helios --dev）：自签名 TLS 证书、公开的测试 JWT 密钥、逐请求访问日志与管理端，
+//! 无需生产环境的密钥即可在本机运行完整网关。切勿在生产环境使用
+use axum::{body::Body, extract::ConnectInfo, Router};
+use config::ConfigError;
+use hyper::body::Incoming;
+use hyper_util::rt::TokioIo;
+use jsonwebtoken::{encode, EncodingKey, Header};
+use openssl::asn1::Asn1Time;
+use openssl::bn::{BigNum, MsbOption};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::PKey;
+use openssl::x509::extension::SubjectAlternativeName;
+use openssl::x509::{X509NameBuilder, X509};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::net::TcpListener;
+use tokio_native_tls::TlsAcceptor;
+use tower::ServiceExt;
+
+use crate::auth::Claims;
+use crate::config::Settings;
+
+/// 开发模式固定使用的 JWT 密钥（HS256），公开文档化，仅用于本地测试
+pub const DEV_JWT_SECRET: &str = "helios-dev-secret";
+/// 未配置 admin_token 时开发模式使用的管理端令牌
+pub const DEV_ADMIN_TOKEN: &str = "dev";
+
+// 配置文件与环境变量均未提供时使用的必填项
+const DEFAULTS: &[(&str, &str)] = &[
+    ("gateway_bind", "127.0.0.1:8443"),
+    ("jwt_decoding_key", DEV_JWT_SECRET),
+    ("global_qps", "10000"),
+    ("client_qps", "1000"),
+];
+
+// 自签名证书有效期（天）
+const CERT_DAYS: u32 = 30;
+
+/// 加载设置，缺少的必填项使用开发默认值
+pub fn load_settings() -> Result<Settings, ConfigError> {
+    crate::config::load_settings_with_defaults(DEFAULTS)
+}
+
+/// 覆盖为开发设置：测试 JWT 密钥、管理端令牌、全量访问日志，本机请求可直接获取调试信息
+pub fn apply(settings: &mut Settings) {
+    settings.jwt_decoding_key = DEV_JWT_SECRET.to_string();
+    settings.admin_token.get_or_insert_with(|| DEV_ADMIN_TOKEN.to_string());
+    settings.access_log = Some(true);
+    settings.access_log_sample_rate = Some(1.0);
+    for net in ["127.0.0.0/8", "::1/128"] {
+        if !settings.debug_trusted_ips.iter().any(|n| n == net) {
+            settings.debug_trusted_ips.push(net.to_string());
+        }
+    }
+}
+
+/// 用测试密钥签发的 JWT（sub、tenant_id 均为 dev），有效期 24 小时
+pub fn token() -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as usize;
+    let claims = Claims {
+        sub: "dev".to_string(),
+        tenant_id: "dev".to_string(),
+        exp: now + 24 * 3600,
+        ..Default::default()
+    };
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(DEV_JWT_SECRET.as_bytes())).unwrap_or_default()
+}
+
+/// 生成仅存在于内存中的 localhost 自签名证书（P-256）
+pub fn tls_acceptor() -> Result<TlsAcceptor, String> {
+    self_signed().map_err(|e| format!("生成开发证书失败: {}", e))
+}
+
+fn self_signed() -> Result<TlsAcceptor, Box<dyn std::error::Error>> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+    let key = PKey::from_ec_key(EcKey::generate(&group)?)?;
+    let mut name = X509NameBuilder::new()?;
+    name.append_entry_by_nid(Nid::COMMONNAME, "localhost")?;
+    let name = name.build();
+
+    let mut serial = BigNum::new()?;
+    serial.rand(64, MsbOption::MAYBE_ZERO, false)?;
+    let serial = serial.to_asn1_integer()?;
+    let (not_before, not_after) = (Asn1Time::days_from_now(0)?, Asn1Time::days_from_now(CERT_DAYS)?);
+    let mut cert = X509::builder()?;
+    cert.set_version(2)?;
+    cert.set_serial_number(&serial)?;
+    cert.set_subject_name(&name)?;
+    cert.set_issuer_name(&name)?;
+    cert.set_pubkey(&key)?;
+    cert.set_not_before(&not_before)?;
+    cert.set_not_after(&not_after)?;
+    let san = SubjectAlternativeName::new()
+        .dns("localhost")
+        .ip("127.0.0.1")
+        .ip("::1")
+        .build(&cert.x509v3_context(None, None))?;
+    cert.append_extension(san)?;
+    cert.sign(&key, MessageDigest::sha256())?;
+
+    let identity = native_tls::Identity::from_pkcs8(&cert.build().to_pem()?, &key.private_key_to_pem_pkcs8()?)?;
+    Ok(TlsAcceptor::from(native_tls::TlsAcceptor::new(identity)?))
+}
+
+/// 以 HTTPS（HTTP/1.1）处理请求，直到监听失败
+pub async fn serve_tls(listener: TcpListener, app: Router, acceptor: TlsAcceptor) -> std::io::Result<()> {
+    let addr = listener.local_addr()?;
+    tracing::warn!("开发模式：使用自签名证书，JWT 密钥为公开的测试密钥，切勿用于生产环境");
+    tracing::info!("管理端: https://{}/admin", addr);
+    tracing::info!("测试令牌: Authorization: Bearer {}", token());
+    loop {
+        let (stream, addr) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(err) => {
+                tracing::warn!("接受连接失败: {}", err);
+                continue;
+            }
+        };
+        let (app, acceptor) = (app.clone(), acceptor.clone());
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(err) => {
+                    tracing::debug!(client = %addr, "TLS 握手失败: {}", err);
+                    return;
+                }
+            };
+            let service = hyper::service::service_fn(move |mut req: hyper::Request<Incoming>| {
+                req.extensions_mut().insert(ConnectInfo(addr));
+                app.clone().oneshot(req.map(Body::new))
+            });
+            let conn = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .with_upgrades();
+            if let Err(err) = conn.await {
+                tracing::debug!(client = %addr, "连接异常结束: {}", err);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dev_settings_and_token() {
+        let mut settings: Settings = serde_json::from_value(serde_json::json!({
+            "gateway_bind": "127.0.0.1:0",
+            "jwt_decoding_key": "production-secret",
+            "global_qps": 100,
+            "client_qps": 100,
+        }))
+        .unwrap();
+        apply(&mut settings);
+        assert_eq!(settings.jwt_decoding_key, DEV_JWT_SECRET);
+        assert_eq!(settings.admin_token.as_deref(), Some(DEV_ADMIN_TOKEN));
+        assert_eq!(settings.access_log, Some(true));
+        apply(&mut settings);
+        assert_eq!(settings.debug_trusted_ips.len(), 2);
+
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert("authorization", format!("Bearer {}", token()).parse().unwrap());
+        let claims = crate::auth::decode_bearer(&headers, &settings.jwt_decoding_key).unwrap();
+        assert_eq!(claims.sub, "dev");
+    }
+
+    #[test]
+    fn test_self_signed_certificate() {
+        assert!(tls_acceptor().is_ok());
+    }
+}
@@ -0,0 +1,68 @@
+//! 运行时发现的路由（Kubernetes 资源、Docker 容器标签等），各来源整体替换自己的路由；
+//! 请求匹配时静态路由优先，未命中再匹配这里的路由
+use arc_swap::ArcSwap;
+use axum::http::HeaderMap;
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use std::sync::Arc;
+
+use crate::config::RouteRule;
+
+// 来源 -> 该来源当前的路由
+static SOURCES: Lazy<DashMap<&'static str, Vec<RouteRule>>> = Lazy::new(DashMap::new);
+// 按来源名排序合并后的路由，供请求路径无锁读取
+static MERGED: Lazy<ArcSwap<Vec<RouteRule>>> = Lazy::new(|| ArcSwap::from_pointee(Vec::new()));
+
+/// 替换某个来源的全部路由
+pub fn set(source: &'static str, routes: Vec<RouteRule>) {
+    SOURCES.insert(source, routes);
+    let mut sources: Vec<_> = SOURCES.iter().map(|e| (*e.key(), e.value().clone())).collect();
+    sources.sort_by_key(|(source, _)| *source);
+    MERGED.store(Arc::new(sources.into_iter().flat_map(|(_, routes)| routes).collect()));
+}
+
+/// 当前全部发现的路由
+pub fn routes() -> Arc<Vec<RouteRule>> {
+    MERGED.load_full()
+}
+
+/// 在发现的路由中匹配
+pub fn find_best_match(path: &str, headers: &HeaderMap) -> Option<RouteRule> {
+    let routes = MERGED.load();
+    crate::proxy::find_best_match(&routes, path, headers).cloned()
+}
+
+/// 只保留校验通过的路由，其余输出告警后丢弃
+pub fn valid_only(source: &str, routes: Vec<RouteRule>) -> Vec<RouteRule> {
+    routes
+        .into_iter()
+        .filter(|rule| {
+            let errors = rule.validation_errors();
+            if !errors.is_empty() {
+                tracing::warn!(source, route = ?rule.id, "跳过无效的发现路由: {}", errors.join("; "));
+            }
+            errors.is_empty()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sources_are_replaced_independently() {
+        let rule = |id: &str, prefix: &str| RouteRule {
+            id: Some(id.to_string()),
+            prefix: vec![prefix.to_string()],
+            upstream: vec!["http://10.0.0.1:80".to_string()],
+            ..Default::default()
+        };
+        set("test-b", vec![rule("b", "/disc-b")]);
+        set("test-a", vec![rule("a1", "/disc-a"), rule("a2", "/disc-a2")]);
+        assert!(find_best_match("/disc-a/x", &HeaderMap::new()).is_some());
+        set("test-a", Vec::new());
+        assert!(find_best_match("/disc-a/x", &HeaderMap::new()).is_none());
+        assert_eq!(find_best_match("/disc-b", &HeaderMap::new()).unwrap().id.as_deref(), Some("b"));
+    }
+}
@@ -0,0 +1,257 @@
+//! Docker 服务发现：轮询 Docker Engine API，为带 gateway.* 标签的运行中容器生成路由（类似 Traefik），
+//! 适合单机 docker compose 部署
+use axum::body::Body;
+use axum::http::{header, Request};
+use hyper_util::rt::TokioIo;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::config::{RouteRule, Settings};
+
+const DEFAULT_HOST: &str = "unix:///var/run/docker.sock";
+const DEFAULT_POLL_SECS: u64 = 5;
+// 容器列表响应体上限
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+// 容器标签：路由前缀（逗号分隔，必填）、上游端口、负载均衡策略、白名单（逗号分隔）、鉴权模式，
+// gateway.enable=false 时忽略该容器
+const LABEL_PREFIX: &str = "gateway.prefix";
+const LABEL_PORT: &str = "gateway.port";
+const LABEL_STRATEGY: &str = "gateway.strategy";
+const LABEL_WHITELIST: &str = "gateway.whitelist";
+const LABEL_AUTH: &str = "gateway.auth";
+const LABEL_ENABLE: &str = "gateway.enable";
+// compose 为容器打的服务标签，同一服务的多个副本合并为一条路由
+const LABEL_COMPOSE_PROJECT: &str = "com.docker.compose.project";
+const LABEL_COMPOSE_SERVICE: &str = "com.docker.compose.service";
+
+fn split_list(s: &str) -> Vec<String> {
+    s.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect()
+}
+
+fn container_name(container: &Value) -> String {
+    let labels = &container["Labels"];
+    match (labels[LABEL_COMPOSE_PROJECT].as_str(), labels[LABEL_COMPOSE_SERVICE].as_str()) {
+        (Some(project), Some(service)) => format!("{}/{}", project, service),
+        _ => container["Names"][0]
+            .as_str()
+            .map(|n| n.trim_start_matches('/').to_string())
+            .unwrap_or_else(|| container["Id"].as_str().unwrap_or_default().chars().take(12).collect()),
+    }
+}
+
+// 容器地址：指定网络上的 IP，未指定时取按网络名排序的第一个
+fn container_ip(container: &Value, network: Option<&str>) -> Option<String> {
+    let networks = container["NetworkSettings"]["Networks"].as_object()?;
+    let ip = match network {
+        Some(name) => networks.get(name)?["IPAddress"].as_str(),
+        None => {
+            let mut names: Vec<&String> = networks.keys().collect();
+            names.sort();
+            names.into_iter().filter_map(|n| networks[n]["IPAddress"].as_str()).find(|ip| !ip.is_empty())
+        }
+    };
+    ip.filter(|ip| !ip.is_empty()).map(str::to_string)
+}
+
+// 上游端口：gateway.port 标签，未配置时要求容器只暴露一个端口
+fn container_port(container: &Value) -> Option<u16> {
+    if let Some(port) = container["Labels"][LABEL_PORT].as_str() {
+        return port.trim().parse().ok();
+    }
+    let mut ports: Vec<u64> = container["Ports"].as_array()?.iter().filter_map(|p| p["PrivatePort"].as_u64()).collect();
+    ports.sort();
+    ports.dedup();
+    match ports.as_slice() {
+        [port] => u16::try_from(*port).ok(),
+        _ => None,
+    }
+}
+
+/// 容器列表（`GET /containers/json`）-> 路由；同一服务的副本作为同一组上游，路由配置取自名字排序最前的容器
+pub fn translate_containers(containers: &[Value], network: Option<&str>) -> Vec<RouteRule> {
+    let mut services: BTreeMap<String, RouteRule> = BTreeMap::new();
+    let mut sorted: Vec<&Value> = containers.iter().collect();
+    sorted.sort_by_key(|c| c["Names"][0].as_str().unwrap_or_default().to_string());
+    for container in sorted {
+        let labels = &container["Labels"];
+        let Some(prefix) = labels[LABEL_PREFIX].as_str() else {
+            continue;
+        };
+        if labels[LABEL_ENABLE].as_str() == Some("false") {
+            continue;
+        }
+        let name = container_name(container);
+        let (Some(ip), Some(port)) = (container_ip(container, network), container_port(container)) else {
+            tracing::warn!(container = %name, "跳过无法确定地址的容器（需要所在网络的 IP，及 gateway.port 标签或唯一暴露的端口）");
+            continue;
+        };
+        let upstream = format!("http://{}:{}", ip, port);
+        if let Some(rule) = services.get_mut(&name) {
+            rule.upstream.push(upstream);
+            continue;
+        }
+        let label = |key: &str| labels[key].as_str().map(str::to_string);
+        let mut rule = RouteRule {
+            id: Some(format!("docker:{}", name)),
+            prefix: split_list(prefix),
+            upstream: vec![upstream],
+            whitelist: label(LABEL_WHITELIST).map(|w| split_list(&w)),
+            auth: label(LABEL_AUTH),
+            ..Default::default()
+        };
+        if let Some(strategy) = label(LABEL_STRATEGY) {
+            rule.strategy = strategy;
+        }
+        services.insert(name, rule);
+    }
+    services.into_values().collect()
+}
+
+/// 发现配置
+#[derive(Debug, Clone)]
+struct Discovery {
+    host: String,
+    network: Option<String>,
+    interval: Duration,
+}
+
+/// 配置了 docker_discovery 时后台轮询容器列表；须在 tokio 运行时中调用
+pub fn spawn_discovery(settings: &Settings) {
+    if settings.docker_discovery != Some(true) {
+        return;
+    }
+    let discovery = Discovery {
+        host: settings.docker_host.clone().unwrap_or_else(|| DEFAULT_HOST.to_string()),
+        network: settings.docker_network.clone(),
+        interval: Duration::from_secs(settings.docker_poll_secs.unwrap_or(DEFAULT_POLL_SECS).max(1)),
+    };
+    tokio::spawn(run(discovery));
+}
+
+// 只列出带前缀标签的运行中容器
+fn list_path() -> String {
+    let filters = format!(r#"{{"label":["{}"],"status":["running"]}}"#, LABEL_PREFIX);
+    let url = reqwest::Url::parse_with_params("http://docker/containers/json", [("filters", filters)]).expect("固定地址");
+    format!("{}?{}", url.path(), url.query().unwrap_or_default())
+}
+
+async fn list_containers(client: &reqwest::Client, host: &str) -> anyhow::Result<Value> {
+    if let Some(socket) = host.strip_prefix("unix://") {
+        let stream = tokio::net::UnixStream::connect(socket).await?;
+        let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+        tokio::spawn(conn);
+        let request = Request::get(list_path()).header(header::HOST, "docker").body(Body::empty())?;
+        let response = sender.send_request(request).await?;
+        anyhow::ensure!(response.status().is_success(), "Docker API 返回 {}", response.status());
+        let body = axum::body::to_bytes(Body::new(response.into_body()), MAX_BODY_BYTES).await?;
+        return Ok(serde_json::from_slice(&body)?);
+    }
+    let base = host.replacen("tcp://", "http://", 1);
+    let url = format!("{}{}", base.trim_end_matches('/'), list_path());
+    Ok(client.get(url).send().await?.error_for_status()?.json().await?)
+}
+
+async fn run(discovery: Discovery) {
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(10)).build().unwrap_or_default();
+    let mut last = None;
+    let mut interval = tokio::time::interval(discovery.interval);
+    loop {
+        interval.tick().await;
+        let containers = match tokio::time::timeout(Duration::from_secs(10), list_containers(&client, &discovery.host)).await {
+            Ok(Ok(Value::Array(containers))) => containers,
+            Ok(Ok(_)) => {
+                tracing::warn!("解析 Docker 容器列表失败: 响应不是数组");
+                continue;
+            }
+            Ok(Err(err)) => {
+                tracing::warn!("获取 Docker 容器列表失败，沿用上次的路由: {}", err);
+                continue;
+            }
+            Err(_) => {
+                tracing::warn!("获取 Docker 容器列表超时，沿用上次的路由");
+                continue;
+            }
+        };
+        let routes = crate::discovery::valid_only("docker", translate_containers(&containers, discovery.network.as_deref()));
+        // 容器无变化时不重建路由
+        let snapshot = serde_json::to_value(&routes).ok();
+        if snapshot.is_some() && snapshot == last {
+            continue;
+        }
+        last = snapshot;
+        tracing::info!(routes = routes.len(), "已从 Docker 容器标签更新路由");
+        crate::discovery::set("docker", routes);
+    }
+}
+
+pub fn validation_errors(settings: &Settings) -> Vec<String> {
+    let mut errors = Vec::new();
+    if let Some(host) = &settings.docker_host
+        && !["unix://", "tcp://", "http://", "https://"].iter().any(|scheme| host.starts_with(scheme))
+    {
+        errors.push(format!("docker_host仅支持 unix://、tcp://、http://、https:// 地址: {}", host));
+    }
+    if settings.docker_poll_secs == Some(0) {
+        errors.push("docker_poll_secs必须大于0".to_string());
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn container(name: &str, labels: Value, networks: Value, ports: Value) -> Value {
+        json!({
+            "Id": "0123456789abcdef",
+            "Names": [format!("/{}", name)],
+            "Labels": labels,
+            "Ports": ports,
+            "NetworkSettings": { "Networks": networks },
+        })
+    }
+
+    #[test]
+    fn test_translate_containers() {
+        let compose = |service: &str| json!({ "com.docker.compose.project": "shop", "com.docker.compose.service": service });
+        let mut orders = compose("orders");
+        orders["gateway.prefix"] = json!("/api/orders, /api/carts");
+        orders["gateway.port"] = json!("8080");
+        orders["gateway.whitelist"] = json!("/api/orders/health");
+        orders["gateway.strategy"] = json!("iphash");
+        let mut web = compose("web");
+        web["gateway.prefix"] = json!("/**");
+        let mut disabled = compose("admin");
+        disabled["gateway.prefix"] = json!("/admin");
+        disabled["gateway.enable"] = json!("false");
+        let containers = vec![
+            container("shop-orders-2", orders.clone(), json!({ "shop_default": { "IPAddress": "172.18.0.3" } }), json!([])),
+            container("shop-orders-1", orders, json!({ "shop_default": { "IPAddress": "172.18.0.2" } }), json!([])),
+            container(
+                "shop-web-1",
+                web,
+                json!({ "bridge": { "IPAddress": "172.17.0.5" }, "shop_default": { "IPAddress": "172.18.0.5" } }),
+                json!([{ "PrivatePort": 80, "PublicPort": 8000 }, { "PrivatePort": 80, "PublicPort": 8000 }]),
+            ),
+            container("shop-admin-1", disabled, json!({ "shop_default": { "IPAddress": "172.18.0.9" } }), json!([])),
+            container("plain", json!({ "gateway.prefix": "/plain" }), json!({ "bridge": { "IPAddress": "172.17.0.2" } }), json!([])),
+        ];
+
+        let routes = translate_containers(&containers, None);
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].id.as_deref(), Some("docker:shop/orders"));
+        assert_eq!(routes[0].prefix, vec!["/api/orders", "/api/carts"]);
+        assert_eq!(routes[0].upstream, vec!["http://172.18.0.2:8080", "http://172.18.0.3:8080"]);
+        assert_eq!(routes[0].strategy, "iphash");
+        assert_eq!(routes[0].whitelist, Some(vec!["/api/orders/health".to_string()]));
+        // 未指定网络时取排序最前的网络，端口取唯一暴露的端口
+        assert_eq!(routes[1].upstream, vec!["http://172.17.0.5:80"]);
+        assert!(routes.iter().all(|r| r.validation_errors().is_empty()));
+
+        let routes = translate_containers(&containers, Some("shop_default"));
+        assert_eq!(routes[1].upstream, vec!["http://172.18.0.5:80"]);
+    }
+}
@@ -0,0 +1,191 @@
+use axum::{
+    body::Body,
+    http::{header, HeaderValue, Response},
+};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+// 网关生成的 problem+json 响应体很小，超过该大小的响应不做替换
+const MAX_PROBLEM_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Escape {
+    Html,
+    Json,
+    None,
+}
+
+/// 已加载的错误页模板，按扩展名决定 Content-Type 与变量转义方式
+#[derive(Debug)]
+pub struct Template {
+    source: String,
+    content_type: &'static str,
+    escape: Escape,
+}
+
+impl Template {
+    fn parse(path: &str, source: String) -> Self {
+        let ext = path.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).unwrap_or_default();
+        let (content_type, escape) = match ext.as_str() {
+            "html" | "htm" => ("text/html; charset=utf-8", Escape::Html),
+            "json" => ("application/json; charset=utf-8", Escape::Json),
+            _ => ("text/plain; charset=utf-8", Escape::None),
+        };
+        Self { source, content_type, escape }
+    }
+
+    /// 替换 {{变量}}，未知变量替换为空
+    pub fn render(&self, vars: &BTreeMap<&str, String>) -> String {
+        let mut out = String::with_capacity(self.source.len());
+        let mut rest = self.source.as_str();
+        while let Some(start) = rest.find("{{") {
+            let Some(len) = rest[start + 2..].find("}}") else {
+                break;
+            };
+            out.push_str(&rest[..start]);
+            let name = rest[start + 2..start + 2 + len].trim();
+            if let Some(value) = vars.get(name) {
+                out.push_str(&escape(value, self.escape));
+            }
+            rest = &rest[start + 2 + len + 2..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+fn escape(value: &str, mode: Escape) -> String {
+    match mode {
+        Escape::Html => value
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+            .replace('\'', "&#39;"),
+        // 模板中变量写在 JSON 字符串内部，只转义不加引号
+        Escape::Json => {
+            let quoted = serde_json::Value::String(value.to_string()).to_string();
+            quoted[1..quoted.len() - 1].to_string()
+        }
+        Escape::None => value.to_string(),
+    }
+}
+
+// ===== 模板缓存（按文件路径，启动校验路由时即加载） =====
+static TEMPLATES: Lazy<DashMap<String, Arc<Template>>> = Lazy::new(DashMap::new);
+
+/// 读取模板文件（相对于工作目录）
+pub fn load(path: &str) -> Result<Arc<Template>, String> {
+    if let Some(template) = TEMPLATES.get(path) {
+        return Ok(template.clone());
+    }
+    let source = std::fs::read_to_string(path).map_err(|e| format!("读取 {} 失败: {}", path, e))?;
+    let template = Arc::new(Template::parse(path, source));
+    TEMPLATES.insert(path.to_string(), template.clone());
+    Ok(template)
+}
+
+/// 状态码对应的模板路径：精确状态码（如 404）优先，其次状态类（4xx/5xx）
+pub fn template_for(pages: &BTreeMap<String, String>, status: u16) -> Option<&String> {
+    pages
+        .get(&status.to_string())
+        .or_else(|| pages.get(&format!("{}xx", status / 100)))
+}
+
+/// 校验键名与模板文件
+pub fn validation_errors(pages: &BTreeMap<String, String>) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (key, path) in pages {
+        let valid_key = matches!(key.as_str(), "4xx" | "5xx")
+            || key.parse::<u16>().is_ok_and(|status| (400..600).contains(&status));
+        if !valid_key {
+            errors.push(format!("error_pages的键必须为4xx、5xx或400~599的状态码，当前为: {}", key));
+        }
+        if let Err(err) = load(path) {
+            errors.push(format!("error_pages.{}无法加载: {}", key, err));
+        }
+    }
+    errors
+}
+
+// ===== 用模板替换网关生成的错误响应（problem+json），上游返回的错误原样透传 =====
+pub async fn apply(pages: &BTreeMap<String, String>, route: &str, resp: Response<Body>) -> Response<Body> {
+    let status = resp.status();
+    let is_problem = resp
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|ct| ct.as_bytes().starts_with(crate::problem::CONTENT_TYPE.as_bytes()));
+    let Some(path) = template_for(pages, status.as_u16()).filter(|_| is_problem) else {
+        return resp;
+    };
+    let template = match load(path) {
+        Ok(template) => template,
+        Err(err) => {
+            tracing::warn!(route, "错误页模板加载失败，返回原始错误: {}", err);
+            return resp;
+        }
+    };
+
+    let (mut parts, body) = resp.into_parts();
+    let problem: serde_json::Value = match axum::body::to_bytes(body, MAX_PROBLEM_BYTES).await {
+        Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_default(),
+        Err(_) => serde_json::Value::Null,
+    };
+    let field = |name: &str| match &problem[name] {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => String::new(),
+        other => other.to_string(),
+    };
+    let mut vars: BTreeMap<&str, String> = BTreeMap::new();
+    vars.insert("status", status.as_u16().to_string());
+    vars.insert("title", field("title"));
+    vars.insert("detail", field("detail"));
+    vars.insert("type", field("type"));
+    vars.insert("request_id", field("request_id"));
+    vars.insert("path", field("instance"));
+    vars.insert("route", route.to_string());
+
+    parts.headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(template.content_type));
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(template.render(&vars)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> BTreeMap<&'static str, String> {
+        BTreeMap::from([("status", "503".to_string()), ("detail", "<b>\"down\"</b>".to_string())])
+    }
+
+    #[test]
+    fn test_render_escapes_by_type() {
+        let html = Template::parse("5xx.html", "<h1>{{ status }}</h1><p>{{detail}}</p>{{missing}}".to_string());
+        assert_eq!(html.render(&vars()), "<h1>503</h1><p>&lt;b&gt;&quot;down&quot;&lt;/b&gt;</p>");
+        assert_eq!(html.content_type, "text/html; charset=utf-8");
+
+        let json = Template::parse("5xx.json", r#"{"code":{{status}},"message":"{{detail}}"}"#.to_string());
+        let rendered: serde_json::Value = serde_json::from_str(&json.render(&vars())).unwrap();
+        assert_eq!(rendered["message"], "<b>\"down\"</b>");
+
+        // 未闭合的占位符原样保留
+        let text = Template::parse("err.txt", "status {{status".to_string());
+        assert_eq!(text.render(&vars()), "status {{status");
+    }
+
+    #[test]
+    fn test_template_for() {
+        let pages = BTreeMap::from([
+            ("4xx".to_string(), "4xx.html".to_string()),
+            ("404".to_string(), "404.html".to_string()),
+        ]);
+        assert_eq!(template_for(&pages, 404).map(String::as_str), Some("404.html"));
+        assert_eq!(template_for(&pages, 429).map(String::as_str), Some("4xx.html"));
+        assert_eq!(template_for(&pages, 502), None);
+
+        let bad = BTreeMap::from([("3xx".to_string(), "missing.html".to_string())]);
+        assert_eq!(validation_errors(&bad).len(), 2);
+    }
+}
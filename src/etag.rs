@@ -0,0 +1,97 @@
+use axum::{
+    body::{Body, Bytes},
+    http::{header, HeaderMap, HeaderValue, Method, Response, StatusCode},
+};
+use sha2::{Digest, Sha256};
+
+// 304 响应需保留的头，其余（Content-Type、Content-Length 等）描述的是响应体
+const NOT_MODIFIED_HEADERS: &[header::HeaderName] = &[
+    header::CACHE_CONTROL,
+    header::CONTENT_LOCATION,
+    header::DATE,
+    header::EXPIRES,
+    header::VARY,
+];
+
+/// 按最终发送的响应体计算强 ETag（不同编码的响应体各自不同）
+pub fn compute(body: &[u8]) -> String {
+    format!("\"{}\"", &hex::encode(Sha256::digest(body))[..32])
+}
+
+// If-None-Match 使用弱比较：忽略 W/ 前缀，* 匹配任意
+fn none_match(if_none_match: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    if_none_match.trim() == "*" || if_none_match.split(',').any(|tag| opaque(tag) == opaque(etag))
+}
+
+/// 为缓冲的 200 响应补全 ETag（上游已提供的保持不变）；请求的 If-None-Match 命中时返回 304
+pub fn apply(
+    method: &Method,
+    req_headers: &HeaderMap,
+    status: StatusCode,
+    headers: &mut HeaderMap,
+    body: &Bytes,
+) -> Option<Response<Body>> {
+    if status != StatusCode::OK || !matches!(*method, Method::GET | Method::HEAD) {
+        return None;
+    }
+    let etag = match headers.get(header::ETAG).and_then(|v| v.to_str().ok()) {
+        Some(existing) => existing.to_string(),
+        None => {
+            let etag = compute(body);
+            headers.insert(header::ETAG, HeaderValue::from_str(&etag).ok()?);
+            etag
+        }
+    };
+    let if_none_match = req_headers.get(header::IF_NONE_MATCH).and_then(|v| v.to_str().ok())?;
+    if !none_match(if_none_match, &etag) {
+        return None;
+    }
+    let mut builder = Response::builder().status(StatusCode::NOT_MODIFIED).header(header::ETAG, &etag);
+    for name in NOT_MODIFIED_HEADERS {
+        for value in headers.get_all(name) {
+            builder = builder.header(name, value);
+        }
+    }
+    builder.body(Body::empty()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_and_match() {
+        let body = Bytes::from_static(b"{\"items\":[]}");
+        let etag = compute(&body);
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        assert!(apply(&Method::GET, &HeaderMap::new(), StatusCode::OK, &mut headers, &body).is_none());
+        assert_eq!(headers[header::ETAG], etag.as_str());
+
+        let mut req_headers = HeaderMap::new();
+        req_headers.insert(header::IF_NONE_MATCH, HeaderValue::from_str(&format!("\"other\", W/{}", etag)).unwrap());
+        let resp = apply(&Method::GET, &req_headers, StatusCode::OK, &mut headers, &body).unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers()[header::ETAG], etag.as_str());
+        assert_eq!(resp.headers()[header::CACHE_CONTROL], "no-cache");
+
+        // 内容变化后不再命中；非 GET/HEAD 或非 200 不处理
+        let changed = Bytes::from_static(b"{\"items\":[1]}");
+        assert!(apply(&Method::GET, &req_headers, StatusCode::OK, &mut HeaderMap::new(), &changed).is_none());
+        let mut untouched = HeaderMap::new();
+        assert!(apply(&Method::POST, &req_headers, StatusCode::OK, &mut untouched, &body).is_none());
+        assert!(apply(&Method::GET, &req_headers, StatusCode::CREATED, &mut untouched, &body).is_none());
+        assert!(!untouched.contains_key(header::ETAG));
+    }
+
+    #[test]
+    fn test_upstream_etag_is_kept() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ETAG, HeaderValue::from_static("W/\"v1\""));
+        let mut req_headers = HeaderMap::new();
+        req_headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"v1\""));
+        let resp = apply(&Method::GET, &req_headers, StatusCode::OK, &mut headers, &Bytes::new()).unwrap();
+        assert_eq!(resp.headers()[header::ETAG], "W/\"v1\"");
+    }
+}
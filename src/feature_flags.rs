@@ -0,0 +1,350 @@
+//! 外部特性开关：后台定期从开关服务（通用 HTTP、Unleash 或 flagd）拉取全部开关并缓存在本地，
+//! 路由的 feature_flag 谓词在请求路径上只查缓存。布尔开关决定路由是否参与匹配；数值开关（0-100）表示按比例放量，
+//! 同一调用方（X-API-Key，其次 Authorization、X-Forwarded-For）稳定落在同一侧，可用来驱动灰度路由的流量比例。
+//!
+//! 开关服务不可用时保留上一次拉取的结果；超过 feature_flag_max_stale_secs 仍未刷新成功（或从未成功）时，
+//! 缓存视为失效，各路由按自己的 feature_flag_default 决定是否生效（默认不生效）。
+use axum::http::HeaderMap;
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::{RouteRule, Settings};
+use crate::load_balancer::hash::HashFunction;
+use crate::metrics::FEATURE_FLAG_REFRESH;
+
+const DEFAULT_REFRESH_SECS: u64 = 15;
+const DEFAULT_MAX_STALE_SECS: u64 = 300;
+// 按比例放量时用于分桶的请求头，依次取第一个存在的
+const STICKY_HEADERS: [&str; 3] = [crate::rate_limit::API_KEY_HEADER, "authorization", "x-forwarded-for"];
+
+/// 开关服务类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    // GET 地址，返回 {"开关名": true | 0-100, ...}（或包在 "flags" 字段中）
+    Http,
+    // Unleash 客户端接口 GET {地址}/api/client/features
+    Unleash,
+    // flagd 的 Connect 接口 POST {地址}/flagd.evaluation.v1.Service/ResolveAll
+    Flagd,
+}
+
+impl Provider {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "http" => Some(Self::Http),
+            "unleash" => Some(Self::Unleash),
+            "flagd" => Some(Self::Flagd),
+            _ => None,
+        }
+    }
+}
+
+/// 开关取值
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlagValue {
+    Enabled(bool),
+    // 放量比例，0-100
+    Percent(f64),
+}
+
+impl FlagValue {
+    fn from_json(value: &Value) -> Option<Self> {
+        match value {
+            Value::Bool(enabled) => Some(Self::Enabled(*enabled)),
+            Value::Number(n) => n.as_f64().map(|p| Self::Percent(p.clamp(0.0, 100.0))),
+            _ => None,
+        }
+    }
+}
+
+// 开关名 -> 取值；最近一次刷新成功的时间（Unix 毫秒，0 表示从未成功）与缓存有效期
+static FLAGS: Lazy<DashMap<String, FlagValue>> = Lazy::new(DashMap::new);
+static REFRESHED_MS: AtomicU64 = AtomicU64::new(0);
+static MAX_STALE_MS: AtomicU64 = AtomicU64::new(DEFAULT_MAX_STALE_SECS * 1000);
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+/// 缓存中的开关取值；缓存失效或开关不存在时为 None
+pub fn value(name: &str) -> Option<FlagValue> {
+    let refreshed = REFRESHED_MS.load(Ordering::Relaxed);
+    if refreshed == 0 || now_ms().saturating_sub(refreshed) > MAX_STALE_MS.load(Ordering::Relaxed) {
+        return None;
+    }
+    FLAGS.get(name).map(|v| *v)
+}
+
+// 用新拉取的开关整体替换缓存
+fn store(flags: HashMap<String, FlagValue>) {
+    FLAGS.retain(|name, _| flags.contains_key(name));
+    for (name, value) in flags {
+        FLAGS.insert(name, value);
+    }
+    REFRESHED_MS.store(now_ms(), Ordering::Relaxed);
+}
+
+// 请求落在 [0, 100) 中的位置：同一开关下同一调用方的结果不变，没有可用请求头时随机
+fn bucket(flag: &str, headers: &HeaderMap) -> f64 {
+    let key = STICKY_HEADERS.iter().find_map(|h| headers.get(*h)).map(|v| v.as_bytes());
+    let Some(key) = key else {
+        return rand::random::<f64>() * 100.0;
+    };
+    let hash = HashFunction::Xxhash.hash(&[flag.as_bytes(), b"\0", key].concat());
+    (hash % 10_000) as f64 / 100.0
+}
+
+/// 路由的 feature_flag 谓词是否满足（未配置视为满足）
+pub fn active(rule: &RouteRule, headers: &HeaderMap) -> bool {
+    let Some(flag) = &rule.feature_flag else {
+        return true;
+    };
+    match value(flag) {
+        Some(FlagValue::Enabled(enabled)) => enabled,
+        Some(FlagValue::Percent(percent)) => bucket(flag, headers) < percent,
+        None => rule.feature_flag_default.unwrap_or(false),
+    }
+}
+
+pub fn validation_errors(rule: &RouteRule) -> Vec<String> {
+    let mut errors = Vec::new();
+    if rule.feature_flag.as_deref().is_some_and(|f| f.trim().is_empty()) {
+        errors.push("feature_flag不能为空".to_string());
+    }
+    if rule.feature_flag_default.is_some() && rule.feature_flag.is_none() {
+        errors.push("feature_flag_default需要同时配置feature_flag".to_string());
+    }
+    errors
+}
+
+pub fn settings_errors(settings: &Settings) -> Vec<String> {
+    let mut errors = Vec::new();
+    if let Some(provider) = &settings.feature_flag_provider {
+        if Provider::parse(provider).is_none() {
+            errors.push(format!("feature_flag_provider仅支持 http、unleash、flagd: {}", provider));
+        }
+        if settings.feature_flag_endpoint.is_none() {
+            errors.push("配置了feature_flag_provider时必须设置feature_flag_endpoint".to_string());
+        }
+    }
+    if settings.feature_flag_refresh_secs == Some(0) {
+        errors.push("feature_flag_refresh_secs必须大于0".to_string());
+    }
+    errors
+}
+
+// 通用 HTTP：顶层对象或其中的 flags 对象
+fn parse_http(body: &Value) -> HashMap<String, FlagValue> {
+    let flags = body.get("flags").filter(|f| f.is_object()).unwrap_or(body);
+    flags
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, value)| Some((name.clone(), FlagValue::from_json(value)?)))
+        .collect()
+}
+
+// Unleash：关闭的开关为 false；开启时取各策略中最大的放量比例（rollout / percentage 参数，没有比例的策略视为 100%）
+fn parse_unleash(body: &Value) -> HashMap<String, FlagValue> {
+    let features = body.get("features").and_then(Value::as_array).into_iter().flatten();
+    features
+        .filter_map(|feature| {
+            let name = feature.get("name")?.as_str()?.to_string();
+            if !feature.get("enabled").and_then(Value::as_bool).unwrap_or(false) {
+                return Some((name, FlagValue::Enabled(false)));
+            }
+            let strategies = feature.get("strategies").and_then(Value::as_array).filter(|s| !s.is_empty());
+            let percent = strategies.map(|strategies| {
+                strategies
+                    .iter()
+                    .map(|s| {
+                        let params = s.get("parameters");
+                        let param = |key: &str| params.and_then(|p| p.get(key)).and_then(|v| v.as_str()?.parse::<f64>().ok());
+                        param("rollout").or_else(|| param("percentage")).unwrap_or(100.0)
+                    })
+                    .fold(0.0, f64::max)
+            });
+            let value = match percent {
+                Some(p) if p < 100.0 => FlagValue::Percent(p.max(0.0)),
+                _ => FlagValue::Enabled(true),
+            };
+            Some((name, value))
+        })
+        .collect()
+}
+
+// flagd：ResolveAll 返回 {"flags": {"开关名": {"boolValue": true} | {"doubleValue": 25}, ...}}
+fn parse_flagd(body: &Value) -> HashMap<String, FlagValue> {
+    let flags = body.get("flags").and_then(Value::as_object).into_iter().flatten();
+    flags
+        .filter_map(|(name, flag)| {
+            let value = ["boolValue", "doubleValue", "value"].iter().find_map(|k| flag.get(*k))?;
+            Some((name.clone(), FlagValue::from_json(value)?))
+        })
+        .collect()
+}
+
+struct Client {
+    provider: Provider,
+    endpoint: String,
+    token: Option<String>,
+    http: reqwest::Client,
+}
+
+impl Client {
+    async fn fetch(&self) -> Result<HashMap<String, FlagValue>, String> {
+        let request = match self.provider {
+            Provider::Http => self.http.get(&self.endpoint),
+            Provider::Unleash => self.http.get(format!("{}/api/client/features", self.endpoint)),
+            Provider::Flagd => self.http.post(format!("{}/flagd.evaluation.v1.Service/ResolveAll", self.endpoint)).json(&json!({})),
+        };
+        let request = match (&self.token, self.provider) {
+            // Unleash 的客户端令牌直接作为 Authorization 的值
+            (Some(token), Provider::Unleash) => request.header("authorization", token),
+            (Some(token), _) => request.bearer_auth(token),
+            (None, _) => request,
+        };
+        let response = request.send().await.and_then(|r| r.error_for_status()).map_err(|e| e.to_string())?;
+        let body: Value = response.json().await.map_err(|e| e.to_string())?;
+        Ok(match self.provider {
+            Provider::Http => parse_http(&body),
+            Provider::Unleash => parse_unleash(&body),
+            Provider::Flagd => parse_flagd(&body),
+        })
+    }
+}
+
+/// 启动后台刷新；未配置开关服务时，配置了 feature_flag 的路由一律按 feature_flag_default 处理
+pub fn spawn(settings: &Settings, routes: &[RouteRule]) {
+    let max_stale = settings.feature_flag_max_stale_secs.unwrap_or(DEFAULT_MAX_STALE_SECS);
+    MAX_STALE_MS.store(max_stale.saturating_mul(1000), Ordering::Relaxed);
+    let (Some(provider), Some(endpoint)) = (
+        settings.feature_flag_provider.as_deref().and_then(Provider::parse),
+        &settings.feature_flag_endpoint,
+    ) else {
+        if let Some(rule) = routes.iter().find(|r| r.feature_flag.is_some()) {
+            tracing::warn!(route = %crate::proxy::route_label(rule), "路由配置了 feature_flag 但未设置开关服务，按 feature_flag_default 处理");
+        }
+        return;
+    };
+    let client = Client {
+        provider,
+        endpoint: endpoint.trim_end_matches('/').to_string(),
+        token: settings.feature_flag_token.clone(),
+        http: reqwest::Client::builder().timeout(Duration::from_secs(5)).build().unwrap_or_default(),
+    };
+    let interval = Duration::from_secs(settings.feature_flag_refresh_secs.unwrap_or(DEFAULT_REFRESH_SECS).max(1));
+    tokio::spawn(run(client, interval));
+}
+
+async fn run(client: Client, interval: Duration) {
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        interval.tick().await;
+        match client.fetch().await {
+            Ok(flags) => {
+                FEATURE_FLAG_REFRESH.with_label_values(&["ok"]).inc();
+                store(flags);
+            }
+            Err(err) => {
+                FEATURE_FLAG_REFRESH.with_label_values(&["error"]).inc();
+                tracing::warn!("拉取特性开关失败，沿用上次的结果: {}", err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_providers() {
+        let http = parse_http(&json!({ "flags": { "new-checkout": true, "canary": 25, "theme": "dark" } }));
+        assert_eq!(http.len(), 2);
+        assert_eq!(http["new-checkout"], FlagValue::Enabled(true));
+        assert_eq!(http["canary"], FlagValue::Percent(25.0));
+        assert_eq!(parse_http(&json!({ "beta": false }))["beta"], FlagValue::Enabled(false));
+
+        let unleash = parse_unleash(&json!({ "features": [
+            { "name": "off", "enabled": false, "strategies": [{ "name": "default" }] },
+            { "name": "on", "enabled": true, "strategies": [] },
+            { "name": "rollout", "enabled": true, "strategies": [
+                { "name": "flexibleRollout", "parameters": { "rollout": "10", "stickiness": "default" } },
+                { "name": "gradualRolloutRandom", "parameters": { "percentage": "30" } },
+            ] },
+            { "name": "mixed", "enabled": true, "strategies": [
+                { "name": "flexibleRollout", "parameters": { "rollout": "10" } },
+                { "name": "default" },
+            ] },
+        ] }));
+        assert_eq!(unleash["off"], FlagValue::Enabled(false));
+        assert_eq!(unleash["on"], FlagValue::Enabled(true));
+        assert_eq!(unleash["rollout"], FlagValue::Percent(30.0));
+        assert_eq!(unleash["mixed"], FlagValue::Enabled(true));
+
+        let flagd = parse_flagd(&json!({ "flags": {
+            "gate": { "reason": "STATIC", "variant": "on", "boolValue": true },
+            "split": { "reason": "TARGETING_MATCH", "doubleValue": 150 },
+            "name": { "stringValue": "x" },
+        } }));
+        assert_eq!(flagd.len(), 2);
+        assert_eq!(flagd["gate"], FlagValue::Enabled(true));
+        assert_eq!(flagd["split"], FlagValue::Percent(100.0));
+    }
+
+    #[test]
+    fn test_route_predicate_and_fallback() {
+        let rule = |flag: &str, default: Option<bool>| RouteRule {
+            feature_flag: Some(flag.to_string()),
+            feature_flag_default: default,
+            ..Default::default()
+        };
+        let headers = HeaderMap::new();
+        assert!(active(&RouteRule::default(), &headers));
+
+        store(HashMap::from([
+            ("ff-test-on".to_string(), FlagValue::Enabled(true)),
+            ("ff-test-off".to_string(), FlagValue::Enabled(false)),
+            ("ff-test-half".to_string(), FlagValue::Percent(50.0)),
+        ]));
+        assert!(active(&rule("ff-test-on", Some(false)), &headers));
+        assert!(!active(&rule("ff-test-off", Some(true)), &headers));
+        // 开关服务中不存在的开关取路由默认值
+        assert!(active(&rule("ff-test-missing", Some(true)), &headers));
+        assert!(!active(&rule("ff-test-missing", None), &headers));
+
+        // 同一调用方结果稳定，整体接近放量比例
+        let caller = |key: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(crate::rate_limit::API_KEY_HEADER, key.parse().unwrap());
+            headers
+        };
+        let half = rule("ff-test-half", None);
+        let first = active(&half, &caller("k-1"));
+        assert!((0..20).all(|_| active(&half, &caller("k-1")) == first));
+        let hits = (0..2000).filter(|i| active(&half, &caller(&format!("k-{}", i)))).count();
+        assert!((800..1200).contains(&hits), "{}", hits);
+        assert_ne!(bucket("ff-test-half", &caller("k-1")), bucket("ff-test-other", &caller("k-1")));
+    }
+
+    #[test]
+    fn test_validation() {
+        let rule = RouteRule { feature_flag: Some(" ".to_string()), ..Default::default() };
+        assert_eq!(validation_errors(&rule).len(), 1);
+        let rule = RouteRule { feature_flag_default: Some(true), ..Default::default() };
+        assert_eq!(validation_errors(&rule).len(), 1);
+
+        let settings = crate::testing::settings(json!({ "feature_flag_provider": "launchdarkly" }));
+        assert_eq!(settings_errors(&settings).len(), 2);
+        let settings = crate::testing::settings(json!({
+            "feature_flag_provider": "unleash",
+            "feature_flag_endpoint": "http://unleash:4242",
+        }));
+        assert!(settings_errors(&settings).is_empty());
+    }
+}
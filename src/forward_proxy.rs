@@ -0,0 +1,191 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::Request,
+    http::{header, HeaderMap, Method, Response, StatusCode},
+    middleware::Next,
+    response::IntoResponse,
+};
+use hyper_util::rt::TokioIo;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::net::TcpStream;
+
+use crate::config::Settings;
+use crate::metrics::{GaugeGuard, FORWARD_PROXY_COUNTER, TUNNELS_ACTIVE};
+use crate::problem::{Problem, ProblemType};
+
+/// 对所有已认证用户生效的放行规则键
+pub const ANY_USER: &str = "*";
+
+/// 目标是否命中放行规则：host[:port]，host 支持 * 与 *.example.com，port 支持 *，省略时为 443
+pub fn destination_allowed(patterns: &[String], host: &str, port: u16) -> bool {
+    patterns.iter().any(|pattern| {
+        let (p_host, p_port) = match pattern.rsplit_once(':') {
+            Some((h, p)) => (h, p),
+            None => (pattern.as_str(), "443"),
+        };
+        let port_ok = p_port == "*" || p_port.parse::<u16>().is_ok_and(|p| p == port);
+        let host = host.to_ascii_lowercase();
+        let p_host = p_host.to_ascii_lowercase();
+        let host_ok = if p_host == "*" {
+            true
+        } else if let Some(domain) = p_host.strip_prefix("*.") {
+            host.strip_suffix(domain).is_some_and(|sub| sub.len() > 1 && sub.ends_with('.'))
+        } else {
+            host == p_host
+        };
+        port_ok && host_ok
+    })
+}
+
+/// 用户可访问的目标列表（用户自身规则与 * 规则合并，用户名不区分大小写）
+pub fn user_patterns<'a>(allow: &'a BTreeMap<String, Vec<String>>, user: &str) -> Vec<&'a String> {
+    allow
+        .iter()
+        .filter(|(name, _)| name.as_str() == ANY_USER || name.eq_ignore_ascii_case(user))
+        .flat_map(|(_, patterns)| patterns)
+        .collect()
+}
+
+/// 校验放行规则的格式
+pub fn validation_errors(allow: &BTreeMap<String, Vec<String>>) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (user, patterns) in allow {
+        for pattern in patterns {
+            let port = pattern.rsplit_once(':').map(|(_, p)| p);
+            if pattern.trim().is_empty() || port.is_some_and(|p| p != "*" && p.parse::<u16>().is_err()) {
+                errors.push(format!("forward_proxy_allow.{}包含非法目标: {}", user, pattern));
+            }
+        }
+    }
+    errors
+}
+
+// CONNECT 的 2xx 响应不能带 Content-Length，而 axum 会为长度已知的空 Body 补上该头，这里用长度未知的空 Body
+struct TunnelEstablished;
+
+impl http_body::Body for TunnelEstablished {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Result<http_body::Frame<Bytes>, Infallible>>> {
+        Poll::Ready(None)
+    }
+}
+
+fn reject(status: StatusCode, kind: ProblemType, message: &str) -> Response<Body> {
+    let mut problem = Problem::new(status, kind).detail(message);
+    if status == StatusCode::PROXY_AUTHENTICATION_REQUIRED {
+        problem = problem.header(header::PROXY_AUTHENTICATE, "Bearer");
+    }
+    problem.into_response()
+}
+
+// ===== 正向代理：处理 CONNECT，认证后按用户放行规则建立到目标的 TCP 隧道 =====
+pub async fn connect_layer(mut req: Request, next: Next) -> Response<Body> {
+    if req.method() != Method::CONNECT {
+        return next.run(req).await;
+    }
+    let Some(settings) = req.extensions().get::<Settings>().cloned() else {
+        return next.run(req).await;
+    };
+    if !settings.forward_proxy_enabled.unwrap_or(false) {
+        return reject(StatusCode::METHOD_NOT_ALLOWED, ProblemType::MethodNotAllowed, "Forward proxy is disabled");
+    }
+
+    let Some(authority) = req.uri().authority().cloned() else {
+        return reject(StatusCode::BAD_REQUEST, ProblemType::BadRequest, "CONNECT requires host:port");
+    };
+    let (host, port) = (authority.host().to_string(), authority.port_u16().unwrap_or(443));
+
+    // 认证：Proxy-Authorization: Bearer <JWT>
+    let mut auth_headers = HeaderMap::new();
+    if let Some(value) = req.headers().get(header::PROXY_AUTHORIZATION) {
+        auth_headers.insert(header::AUTHORIZATION, value.clone());
+    }
+    let user = match crate::auth::decode_bearer(&auth_headers, &settings.jwt_decoding_key) {
+        Ok(claims) => claims.sub,
+        Err(err) => {
+            FORWARD_PROXY_COUNTER.with_label_values(&["", "unauthenticated"]).inc();
+            tracing::warn!(target_host = %host, reason = err.reason(), "正向代理认证失败");
+            return reject(StatusCode::PROXY_AUTHENTICATION_REQUIRED, ProblemType::ProxyAuthenticationRequired, "Proxy authentication required");
+        }
+    };
+
+    let patterns: Vec<String> = user_patterns(&settings.forward_proxy_allow, &user).into_iter().cloned().collect();
+    if !destination_allowed(&patterns, &host, port) {
+        FORWARD_PROXY_COUNTER.with_label_values(&[&user, "denied"]).inc();
+        tracing::warn!(user = %user, target_host = %host, port, "正向代理目标不在放行列表中");
+        return reject(StatusCode::FORBIDDEN, ProblemType::Forbidden, "Destination not allowed");
+    }
+
+    let connect_timeout = Duration::from_secs(settings.forward_proxy_connect_timeout_secs.unwrap_or(5));
+    let mut target = match tokio::time::timeout(connect_timeout, TcpStream::connect((host.as_str(), port))).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(err)) => {
+            FORWARD_PROXY_COUNTER.with_label_values(&[&user, "failed"]).inc();
+            return reject(StatusCode::BAD_GATEWAY, ProblemType::UpstreamError, &format!("Connect error: {}", err));
+        }
+        Err(_) => {
+            FORWARD_PROXY_COUNTER.with_label_values(&[&user, "failed"]).inc();
+            return reject(StatusCode::GATEWAY_TIMEOUT, ProblemType::UpstreamTimeout, "Connect timeout");
+        }
+    };
+    FORWARD_PROXY_COUNTER.with_label_values(&[&user, "allowed"]).inc();
+    tracing::info!(user = %user, target_host = %host, port, "正向代理隧道建立");
+
+    let on_upgrade = hyper::upgrade::on(&mut req);
+    tokio::spawn(async move {
+        let _active = GaugeGuard::new(&TUNNELS_ACTIVE);
+        let mut client = match on_upgrade.await {
+            Ok(io) => TokioIo::new(io),
+            Err(err) => {
+                tracing::warn!(user = %user, "CONNECT 升级失败: {}", err);
+                return;
+            }
+        };
+        if let Err(err) = tokio::io::copy_bidirectional(&mut client, &mut target).await {
+            tracing::debug!(user = %user, target_host = %host, "正向代理隧道异常关闭: {}", err);
+        }
+    });
+
+    Response::builder().status(StatusCode::OK).body(Body::new(TunnelEstablished)).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_destination_allowed() {
+        let allow = patterns(&["api.github.com", "*.internal.example:8443", "mirror.local:*"]);
+        assert!(destination_allowed(&allow, "api.github.com", 443));
+        assert!(!destination_allowed(&allow, "api.github.com", 80));
+        assert!(destination_allowed(&allow, "svc.internal.example", 8443));
+        assert!(!destination_allowed(&allow, "internal.example", 8443));
+        assert!(destination_allowed(&allow, "mirror.local", 22));
+        assert!(!destination_allowed(&allow, "evil.com", 443));
+        assert!(destination_allowed(&patterns(&["*:*"]), "anything", 1));
+    }
+
+    #[test]
+    fn test_user_patterns() {
+        let allow = BTreeMap::from([
+            ("*".to_string(), patterns(&["pypi.org"])),
+            ("alice".to_string(), patterns(&["github.com"])),
+        ]);
+        assert_eq!(user_patterns(&allow, "Alice").len(), 2);
+        assert_eq!(user_patterns(&allow, "bob").len(), 1);
+        assert!(validation_errors(&allow).is_empty());
+
+        let bad = BTreeMap::from([("bob".to_string(), patterns(&["host:http"]))]);
+        assert_eq!(validation_errors(&bad).len(), 1);
+    }
+}
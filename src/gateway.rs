@@ -0,0 +1,217 @@
+use axum::Router;
+use config::ConfigError;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+
+use crate::config::{self as settings, RouteRule, Settings};
+
+/// 自定义过滤器：包裹网关服务，如 `|app| app.layer(axum::middleware::from_fn(my_filter))`
+pub type Filter = Box<dyn FnOnce(Router) -> Router + Send>;
+
+/// 网关构建器：配置可来自文件与环境变量，也可由嵌入方直接提供
+pub struct GatewayBuilder {
+    settings: Settings,
+    routes: Option<Vec<RouteRule>>,
+    filters: Vec<Filter>,
+    dev: bool,
+}
+
+impl GatewayBuilder {
+    pub fn new(settings: Settings) -> Self {
+        Self { settings, routes: None, filters: Vec::new(), dev: false }
+    }
+
+    /// 与 helios 可执行文件相同：从 config.toml / 环境变量加载设置，从 routes.toml / routes.d 加载路由
+    pub fn from_config() -> Result<Self, ConfigError> {
+        let builder = Self::new(settings::load_settings()?);
+        Ok(builder.routes(settings::load_route_rules()?))
+    }
+
+    /// 以代码提供的路由替换已有路由（不再读取路由文件）
+    pub fn routes(mut self, routes: Vec<RouteRule>) -> Self {
+        self.routes = Some(routes);
+        self
+    }
+
+    /// 追加一条路由
+    pub fn route(mut self, route: RouteRule) -> Self {
+        self.routes.get_or_insert_with(Vec::new).push(route);
+        self
+    }
+
+    /// 注入自定义过滤器，按添加顺序由内向外包裹；在请求 ID、配置等扩展注入之后、路由匹配之前执行
+    pub fn filter<F>(mut self, filter: F) -> Self
+    where
+        F: FnOnce(Router) -> Router + Send + 'static,
+    {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// 开发模式：覆盖为开发设置（测试 JWT 密钥、管理端令牌、全量访问日志），并以自签名证书提供 HTTPS
+    pub fn dev(mut self) -> Self {
+        crate::dev::apply(&mut self.settings);
+        self.dev = true;
+        self
+    }
+
+    /// 校验配置并组装网关服务，同时启动后台任务（限流键清理、上游预热、区域探测、降载与异常监控）；
+    /// 须在 tokio 运行时中调用
+    pub fn build(self) -> Result<Gateway, ConfigError> {
+        let Self { settings, routes, filters, dev } = self;
+        let route_rules = routes.unwrap_or_default();
+        let errors: Vec<String> = route_rules
+            .iter()
+            .enumerate()
+            .flat_map(|(i, rule)| rule.validation_errors().into_iter().map(move |e| format!("routes[{}]: {}", i, e)))
+            .collect();
+        if !errors.is_empty() {
+            return Err(ConfigError::Message(format!("路由配置错误:\n  {}", errors.join("\n  "))));
+        }
+        // 路由冲突诊断：重叠、不可达的路由与无效的白名单项
+        crate::route_diagnostics::enforce(&settings, &route_rules)?;
+        // 可选：以明文访问外部主机的上游拒绝启动
+        crate::upstream_url::enforce(&settings, &route_rules)?;
+        // 路由 cost 超过任一层级限流桶容量时请求总会被 429，拒绝启动
+        crate::rate_limit::enforce(&settings, &route_rules)?;
+        // 可选：启动前解析或连接所有上游，在 serve 时执行
+        let preflight = crate::preflight::Preflight::from_settings(&settings, &route_rules);
+
+        // 构建速率限制器（全局与每客户端），注入到扩展
+        let rate_limits = crate::rate_limit::init_rate_limits(&settings);
+        crate::rate_limit::spawn_eviction(&settings, rate_limits.clone());
+        // 可选：后台预热所有上游连接（上游模板按请求渲染，不预热）
+        let upstreams = route_rules.iter().flat_map(|r| r.all_upstreams());
+        crate::upstream::spawn_warm_up(&settings, upstreams.filter(|u| !crate::upstream_template::is_template(u)).cloned());
+        // 可选：从 Kubernetes Ingress / HTTPRoute 资源生成路由
+        crate::kubernetes::spawn_controller(&settings);
+        crate::docker::spawn_discovery(&settings);
+        // 可选：在副本间共享熔断、封禁与摘流状态
+        crate::coordination::spawn(&settings);
+        // 多区域路由的延迟探测
+        crate::region::spawn_probes(&settings, &route_rules);
+        // 可选：按自身资源占用降载
+        crate::shedding::spawn_monitor(&settings);
+        // 可选：按路由的异常防护规则自动处置
+        crate::anomaly::spawn_monitor(&route_rules);
+        crate::capture::spawn_exporter(&settings, &route_rules);
+        crate::feature_flags::spawn(&settings, &route_rules);
+        let preserve_header_case = crate::header_case::enabled(&route_rules);
+        // 声明立即刷新的流式路由需要关闭 Nagle，避免小数据块在内核中等待合并
+        let tcp_nodelay = route_rules.iter().any(|r| r.flush.as_deref() == Some("immediate"));
+        let https = crate::mtls::acceptor(&settings).map_err(ConfigError::Message)?;
+        let tls = if dev && https.is_none() { Some(crate::dev::tls_acceptor().map_err(ConfigError::Message)?) } else { None };
+        let (app, ops_app) = crate::build_app(&settings, rate_limits, route_rules, filters);
+        Ok(Gateway { settings, app, ops_app, preflight, tls, https, preserve_header_case, tcp_nodelay })
+    }
+}
+
+/// 组装完成的网关：可直接 serve，也可取出 app 自行挂载
+pub struct Gateway {
+    settings: Settings,
+    /// 网关服务（未配置 ops_bind 时包含运维端点）
+    pub app: Router,
+    /// 配置了 ops_bind 时独立提供的运维端点服务
+    pub ops_app: Option<Router>,
+    preflight: Option<crate::preflight::Preflight>,
+    // 开发模式的自签名证书
+    tls: Option<tokio_native_tls::TlsAcceptor>,
+    // 配置了 tls_cert_file 时的 HTTPS（可要求客户端证书）
+    https: Option<tokio_rustls::TlsAcceptor>,
+    preserve_header_case: bool,
+    tcp_nodelay: bool,
+}
+
+impl Gateway {
+    /// 执行上游启动检查（如已配置）后监听 gateway_bind（及 ops_bind）并处理请求，直到服务退出
+    pub async fn serve(self) -> anyhow::Result<()> {
+        let Self { settings, app, ops_app, preflight, tls, https, preserve_header_case, tcp_nodelay } = self;
+
+        if let Some(preflight) = &preflight {
+            preflight.run().await?;
+        }
+
+        // 可选：运维端点使用独立的内部监听地址
+        if let (Some(ops_app), Some(bind)) = (ops_app, &settings.ops_bind) {
+            let ops_listener = TcpListener::bind(bind).await?;
+            tracing::info!("运维端点监听于 http://{}", ops_listener.local_addr()?);
+            tokio::spawn(async move {
+                let make_svc = ops_app.into_make_service_with_connect_info::<SocketAddr>();
+                if let Err(err) = axum::serve(ops_listener, make_svc).await {
+                    tracing::error!("运维端点服务异常退出: {}", err);
+                }
+            });
+        }
+
+        // 启动服务（带客户端地址信息）
+        let listener = TcpListener::bind(&settings.gateway_bind).await?;
+        if let Some(acceptor) = https {
+            tracing::info!("🚀 Gateway listening on https://{}", listener.local_addr()?);
+            crate::mtls::serve(listener, app, acceptor).await?;
+            return Ok(());
+        }
+        if let Some(acceptor) = tls {
+            tracing::info!("🚀 Gateway listening on https://{}", listener.local_addr()?);
+            crate::dev::serve_tls(listener, app, acceptor).await?;
+            return Ok(());
+        }
+        tracing::info!("🚀 Gateway listening on http://{}", listener.local_addr()?);
+
+        // 有路由需要保留请求头大小写时，改用可记录原始写法的连接处理
+        if preserve_header_case {
+            crate::header_case::serve(listener, app, tcp_nodelay).await?;
+            return Ok(());
+        }
+        let make_svc = app.into_make_service_with_connect_info::<SocketAddr>();
+        axum::serve(listener, make_svc).tcp_nodelay(tcp_nodelay).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_builder_with_programmatic_routes_and_filter() {
+        let settings: Settings = serde_json::from_value(serde_json::json!({
+            "gateway_bind": "127.0.0.1:0",
+            "jwt_decoding_key": "test",
+            "global_qps": 100,
+            "client_qps": 100,
+        }))
+        .unwrap();
+
+        let bad = RouteRule { prefix: vec!["/bad".to_string()], ..Default::default() };
+        assert!(GatewayBuilder::new(settings.clone()).route(bad).build().is_err());
+
+        let route = RouteRule {
+            prefix: vec!["/trap/**".to_string()],
+            tarpit: Some(crate::tarpit::Tarpit { delay_ms: Some(0), ..Default::default() }),
+            ..Default::default()
+        };
+        let gateway = GatewayBuilder::new(settings)
+            .route(route)
+            .filter(|app| {
+                app.layer(axum::middleware::from_fn(|req, next: axum::middleware::Next| async move {
+                    let mut resp = next.run(req).await;
+                    resp.headers_mut().insert("x-embedded", "1".parse().unwrap());
+                    resp
+                }))
+            })
+            .build()
+            .unwrap();
+
+        let req = Request::builder()
+            .uri("/trap/x")
+            .extension(axum::extract::ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 1234))))
+            .body(Body::empty())
+            .unwrap();
+        let resp = gateway.app.oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.headers()["x-embedded"], "1");
+    }
+}
@@ -0,0 +1,348 @@
+//! gRPC 反射：经上游的 Server Reflection 服务列出可用的服务与方法，供管理端查看，
+//! 并检查 gRPC 路由的前缀是否对应上游实际存在的方法。
+//!
+//! 只用到反射协议中的 list_services 与 file_containing_symbol 两种请求，protobuf 编解码在此手写，
+//! 每次请求单独发送一个 HTTP/2 流（发送一条消息后结束请求流，读取第一条响应消息）。
+use axum::http::HeaderMap;
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::config::RouteRule;
+
+// 优先使用 v1，上游只实现 v1alpha 时回退
+const REFLECTION_PATHS: [&str; 2] = [
+    "/grpc.reflection.v1.ServerReflection/ServerReflectionInfo",
+    "/grpc.reflection.v1alpha.ServerReflection/ServerReflectionInfo",
+];
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 上游提供的一个服务
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ServiceInfo {
+    // 全限定名，如 orders.v1.OrderService
+    pub name: String,
+    pub methods: Vec<MethodInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MethodInfo {
+    pub name: String,
+    // 请求路径，如 /orders.v1.OrderService/GetOrder
+    pub path: String,
+    pub input_type: String,
+    pub output_type: String,
+    pub client_streaming: bool,
+    pub server_streaming: bool,
+}
+
+// ===== protobuf 编解码（仅支持 varint 与 length-delimited） =====
+fn encode_varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn encode_string(field: u32, value: &str, out: &mut Vec<u8>) {
+    encode_varint(((field << 3) | 2) as u64, out);
+    encode_varint(value.len() as u64, out);
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn decode_varint(buf: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *buf.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte < 0x80 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// 消息中的一个字段
+#[derive(Debug, PartialEq)]
+enum Field<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+/// 逐个解析消息字段（字段号, 值），遇到不支持的编码或截断时停止
+fn fields(buf: &[u8]) -> Vec<(u32, Field<'_>)> {
+    let mut pos = 0;
+    let mut fields = Vec::new();
+    while pos < buf.len() {
+        let Some(key) = decode_varint(buf, &mut pos) else { break };
+        let field = (key >> 3) as u32;
+        let value = match key & 7 {
+            0 => match decode_varint(buf, &mut pos) {
+                Some(v) => Field::Varint(v),
+                None => break,
+            },
+            2 => {
+                let Some(len) = decode_varint(buf, &mut pos) else { break };
+                let Some(bytes) = buf.get(pos..pos + len as usize) else { break };
+                pos += len as usize;
+                Field::Bytes(bytes)
+            }
+            // 定长字段（fixed64 / fixed32）跳过
+            1 if pos + 8 <= buf.len() => {
+                pos += 8;
+                continue;
+            }
+            5 if pos + 4 <= buf.len() => {
+                pos += 4;
+                continue;
+            }
+            _ => break,
+        };
+        fields.push((field, value));
+    }
+    fields
+}
+
+fn bytes_of(buf: &[u8], field: u32) -> impl Iterator<Item = &[u8]> {
+    fields(buf).into_iter().filter_map(move |(f, v)| match v {
+        Field::Bytes(b) if f == field => Some(b),
+        _ => None,
+    })
+}
+
+fn string_of(buf: &[u8], field: u32) -> Option<String> {
+    bytes_of(buf, field).last().map(|b| String::from_utf8_lossy(b).into_owned())
+}
+
+fn bool_of(buf: &[u8], field: u32) -> bool {
+    fields(buf).into_iter().any(|(f, v)| f == field && v == Field::Varint(1))
+}
+
+// ServerReflectionRequest：file_containing_symbol = 4，list_services = 7
+fn list_services_request() -> Vec<u8> {
+    let mut msg = Vec::new();
+    encode_string(7, "", &mut msg);
+    msg
+}
+
+fn file_containing_symbol_request(symbol: &str) -> Vec<u8> {
+    let mut msg = Vec::new();
+    encode_string(4, symbol, &mut msg);
+    msg
+}
+
+// ServerReflectionResponse：file_descriptor_response = 4（file_descriptor_proto = 1），
+// list_services_response = 6（service = 1，name = 1），error_response = 7（error_message = 2）
+fn parse_list_services(resp: &[u8]) -> Result<Vec<String>, String> {
+    reflection_error(resp)?;
+    Ok(bytes_of(resp, 6)
+        .flat_map(|list| bytes_of(list, 1).filter_map(|svc| string_of(svc, 1)).collect::<Vec<_>>())
+        .collect())
+}
+
+fn parse_file_descriptors(resp: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+    reflection_error(resp)?;
+    Ok(bytes_of(resp, 4).flat_map(|fd| bytes_of(fd, 1).map(<[u8]>::to_vec).collect::<Vec<_>>()).collect())
+}
+
+fn reflection_error(resp: &[u8]) -> Result<(), String> {
+    match bytes_of(resp, 7).last() {
+        Some(err) => Err(string_of(err, 2).unwrap_or_else(|| "反射请求失败".to_string())),
+        None => Ok(()),
+    }
+}
+
+/// 在 FileDescriptorProto（package = 2，service = 6）中查找服务；
+/// ServiceDescriptorProto：name = 1，method = 2；MethodDescriptorProto：name = 1，input_type = 2，
+/// output_type = 3，client_streaming = 5，server_streaming = 6
+fn find_service(descriptors: &[Vec<u8>], full_name: &str) -> Option<ServiceInfo> {
+    for fd in descriptors {
+        let package = string_of(fd, 2).unwrap_or_default();
+        for svc in bytes_of(fd, 6) {
+            let name = string_of(svc, 1).unwrap_or_default();
+            let qualified = if package.is_empty() { name } else { format!("{}.{}", package, name) };
+            if qualified != full_name {
+                continue;
+            }
+            let methods = bytes_of(svc, 2)
+                .map(|m| {
+                    let name = string_of(m, 1).unwrap_or_default();
+                    let type_name = |field| string_of(m, field).unwrap_or_default().trim_start_matches('.').to_string();
+                    MethodInfo {
+                        path: format!("/{}/{}", qualified, name),
+                        input_type: type_name(2),
+                        output_type: type_name(3),
+                        client_streaming: bool_of(m, 5),
+                        server_streaming: bool_of(m, 6),
+                        name,
+                    }
+                })
+                .collect();
+            return Some(ServiceInfo { name: qualified, methods });
+        }
+    }
+    None
+}
+
+// ===== 访问上游 =====
+// gRPC 消息帧：1 字节压缩标记 + 4 字节大端长度
+fn frame(msg: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(msg.len() + 5);
+    out.push(0);
+    out.extend_from_slice(&(msg.len() as u32).to_be_bytes());
+    out.extend_from_slice(msg);
+    out
+}
+
+fn unframe(body: &[u8]) -> Option<&[u8]> {
+    let len = u32::from_be_bytes(body.get(1..5)?.try_into().ok()?) as usize;
+    if body[0] != 0 {
+        return None;
+    }
+    body.get(5..5 + len)
+}
+
+/// 向上游的反射服务发送一条请求并返回第一条响应消息
+async fn call(client: &reqwest::Client, upstream: &str, msg: &[u8]) -> Result<Vec<u8>, String> {
+    let mut last_error = String::new();
+    for path in REFLECTION_PATHS {
+        let resp = client
+            .post(format!("{}{}", upstream.trim_end_matches('/'), path))
+            .header("content-type", "application/grpc")
+            .header("te", "trailers")
+            .body(frame(msg))
+            .send()
+            .await
+            .map_err(|e| format!("请求反射服务失败: {}", e))?;
+        // 未实现该版本时通常直接以 grpc-status 响应头结束
+        let status = grpc_status(resp.headers());
+        let body = resp.bytes().await.map_err(|e| format!("读取反射响应失败: {}", e))?;
+        match (status, unframe(&body)) {
+            (_, Some(msg)) => return Ok(msg.to_vec()),
+            (Some((code, message)), None) => last_error = format!("grpc-status {}: {}", code, message),
+            (None, None) => last_error = "反射响应为空".to_string(),
+        }
+    }
+    Err(last_error)
+}
+
+fn grpc_status(headers: &HeaderMap) -> Option<(String, String)> {
+    let code = headers.get("grpc-status")?.to_str().ok()?.to_string();
+    let message = headers.get("grpc-message").and_then(|v| v.to_str().ok()).unwrap_or_default().to_string();
+    Some((code, message))
+}
+
+/// 列出上游的全部服务与方法（不含反射服务自身）
+pub async fn inspect(upstream: &str) -> Result<Vec<ServiceInfo>, String> {
+    let client = reqwest::Client::builder()
+        .http2_prior_knowledge()
+        .timeout(TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let names = parse_list_services(&call(&client, upstream, &list_services_request()).await?)?;
+    let mut services = Vec::new();
+    for name in names.into_iter().filter(|n| !n.starts_with("grpc.reflection.")) {
+        let descriptors = parse_file_descriptors(&call(&client, upstream, &file_containing_symbol_request(&name)).await?)?;
+        services.push(find_service(&descriptors, &name).unwrap_or(ServiceInfo { name, methods: Vec::new() }));
+    }
+    Ok(services)
+}
+
+/// 没有匹配到任何方法的路由前缀（通常是服务名或方法名写错）
+pub fn unmatched_prefixes(rule: &RouteRule, services: &[ServiceInfo]) -> Vec<String> {
+    let paths: Vec<&str> = services.iter().flat_map(|s| s.methods.iter().map(|m| m.path.as_str())).collect();
+    rule.prefix
+        .iter()
+        .filter(|prefix| {
+            let single = RouteRule { prefix: vec![(*prefix).clone()], ..Default::default() };
+            !paths.iter().any(|path| single.matches(path))
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(build: impl FnOnce(&mut Vec<u8>)) -> Vec<u8> {
+        let mut out = Vec::new();
+        build(&mut out);
+        out
+    }
+
+    fn encode_bytes(field: u32, value: &[u8], out: &mut Vec<u8>) {
+        encode_varint(((field << 3) | 2) as u64, out);
+        encode_varint(value.len() as u64, out);
+        out.extend_from_slice(value);
+    }
+
+    #[test]
+    fn test_parse_reflection_responses() {
+        let list = message(|m| {
+            let services = message(|l| {
+                for name in ["orders.v1.OrderService", "grpc.reflection.v1.ServerReflection"] {
+                    encode_bytes(1, &message(|s| encode_string(1, name, s)), l);
+                }
+            });
+            encode_bytes(6, &services, m);
+        });
+        assert_eq!(parse_list_services(&list).unwrap(), vec!["orders.v1.OrderService", "grpc.reflection.v1.ServerReflection"]);
+
+        let method = |name: &str, streaming: bool| {
+            message(|m| {
+                encode_string(1, name, m);
+                encode_string(2, ".orders.v1.GetOrderRequest", m);
+                encode_string(3, ".orders.v1.Order", m);
+                if streaming {
+                    encode_varint(6 << 3, m);
+                    encode_varint(1, m);
+                }
+            })
+        };
+        let service = message(|s| {
+            encode_string(1, "OrderService", s);
+            encode_bytes(2, &method("GetOrder", false), s);
+            encode_bytes(2, &method("WatchOrders", true), s);
+        });
+        let fd = message(|f| {
+            encode_string(1, "orders.proto", f);
+            encode_string(2, "orders.v1", f);
+            encode_bytes(6, &service, f);
+        });
+        let resp = message(|r| encode_bytes(4, &message(|d| encode_bytes(1, &fd, d)), r));
+        let descriptors = parse_file_descriptors(&resp).unwrap();
+        let svc = find_service(&descriptors, "orders.v1.OrderService").unwrap();
+        assert_eq!(svc.methods.len(), 2);
+        assert_eq!(svc.methods[0].path, "/orders.v1.OrderService/GetOrder");
+        assert_eq!(svc.methods[0].input_type, "orders.v1.GetOrderRequest");
+        assert!(!svc.methods[0].server_streaming && svc.methods[1].server_streaming);
+        assert!(find_service(&descriptors, "orders.v1.Other").is_none());
+
+        let error = message(|r| encode_bytes(7, &message(|e| encode_string(2, "symbol not found", e)), r));
+        assert_eq!(parse_file_descriptors(&error), Err("symbol not found".to_string()));
+        assert_eq!(unframe(&frame(b"abc")), Some(&b"abc"[..]));
+    }
+
+    #[test]
+    fn test_unmatched_prefixes() {
+        let method = |path: &str| MethodInfo {
+            name: String::new(),
+            path: path.to_string(),
+            input_type: String::new(),
+            output_type: String::new(),
+            client_streaming: false,
+            server_streaming: false,
+        };
+        let services = vec![ServiceInfo {
+            name: "orders.v1.OrderService".to_string(),
+            methods: vec![method("/orders.v1.OrderService/GetOrder")],
+        }];
+        let rule = RouteRule {
+            prefix: vec!["/orders.v1.OrderService/**".to_string(), "/orders.v1.OrderServce/**".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(unmatched_prefixes(&rule, &services), vec!["/orders.v1.OrderServce/**"]);
+    }
+}
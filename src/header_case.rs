@@ -0,0 +1,123 @@
+use axum::{body::Body, extract::ConnectInfo, http::{Extensions, HeaderMap, Method}, Router};
+use hyper::body::Incoming;
+use hyper_tls::HttpsConnector;
+use hyper_util::client::legacy::{connect::HttpConnector, Client};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use once_cell::sync::Lazy;
+use std::fmt;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tower::ServiceExt;
+
+use crate::config::RouteRule;
+
+/// 是否有路由需要保留请求头的原始大小写
+pub fn enabled(route_rules: &[RouteRule]) -> bool {
+    route_rules.iter().any(|r| r.preserve_header_case.unwrap_or(false))
+}
+
+// ===== 服务端：记录客户端请求头的原始大小写 =====
+
+/// 以 preserve_header_case 的 HTTP/1.1 连接处理请求（axum::serve 无法开启该选项），
+/// hyper 会把请求头的原始写法放进请求扩展，转发时由客户端按原样写出
+pub async fn serve(listener: TcpListener, app: Router, tcp_nodelay: bool) -> std::io::Result<()> {
+    loop {
+        let (stream, addr) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(err) => {
+                tracing::warn!("接受连接失败: {}", err);
+                continue;
+            }
+        };
+        if tcp_nodelay && let Err(err) = stream.set_nodelay(true) {
+            tracing::debug!(client = %addr, "设置 TCP_NODELAY 失败: {}", err);
+        }
+        let app = app.clone();
+        tokio::spawn(async move {
+            let service = hyper::service::service_fn(move |mut req: hyper::Request<Incoming>| {
+                req.extensions_mut().insert(ConnectInfo(addr));
+                app.clone().oneshot(req.map(Body::new))
+            });
+            let conn = hyper::server::conn::http1::Builder::new()
+                .preserve_header_case(true)
+                .serve_connection(TokioIo::new(stream), service)
+                .with_upgrades();
+            if let Err(err) = conn.await {
+                tracing::debug!(client = %addr, "连接异常结束: {}", err);
+            }
+        });
+    }
+}
+
+// ===== 客户端：按原始大小写与顺序写出请求头（仅 HTTP/1.1） =====
+static CLIENT: Lazy<Client<HttpsConnector<HttpConnector>, reqwest::Body>> = Lazy::new(|| {
+    let mut http = HttpConnector::new();
+    http.enforce_http(false);
+    Client::builder(TokioExecutor::new())
+        .http1_preserve_header_case(true)
+        .build(HttpsConnector::new_with_connector(http))
+});
+
+/// 上游请求失败的原因，两种客户端共用
+#[derive(Debug)]
+pub enum SendError {
+    Reqwest(reqwest::Error),
+    Hyper(String),
+    Timeout,
+}
+
+impl SendError {
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            SendError::Reqwest(err) => err.is_timeout(),
+            SendError::Hyper(_) => false,
+            SendError::Timeout => true,
+        }
+    }
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::Reqwest(err) => write!(f, "{}", err),
+            SendError::Hyper(err) => write!(f, "{}", err),
+            SendError::Timeout => write!(f, "operation timed out"),
+        }
+    }
+}
+
+impl From<reqwest::Error> for SendError {
+    fn from(err: reqwest::Error) -> Self {
+        SendError::Reqwest(err)
+    }
+}
+
+/// 发送上游请求；extensions 取自客户端请求，其中带有请求头的原始大小写。
+/// 响应转换为 reqwest::Response，后续处理与普通转发一致
+pub async fn send(
+    method: Method,
+    url: &str,
+    headers: HeaderMap,
+    extensions: Extensions,
+    body: reqwest::Body,
+    timeout: Option<Duration>,
+) -> Result<reqwest::Response, SendError> {
+    let mut req = hyper::Request::builder()
+        .method(method)
+        .uri(url)
+        .body(body)
+        .map_err(|err| SendError::Hyper(err.to_string()))?;
+    *req.headers_mut() = headers;
+    *req.extensions_mut() = extensions;
+
+    let pending = CLIENT.request(req);
+    let result = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, pending).await.map_err(|_| SendError::Timeout)?,
+        None => pending.await,
+    };
+    let resp = result.map_err(|err| match std::error::Error::source(&err) {
+        Some(source) => SendError::Hyper(format!("{}: {}", err, source)),
+        None => SendError::Hyper(err.to_string()),
+    })?;
+    Ok(reqwest::Response::from(resp.map(reqwest::Body::wrap)))
+}
@@ -0,0 +1,88 @@
+use axum::http::HeaderMap;
+
+use crate::config::{RouteRule, Settings};
+
+/// 默认去掉的上游响应头：暴露后端实现与版本
+pub const DEFAULT_STRIP_HEADERS: &[&str] = &["server", "x-powered-by", "x-aspnet-version", "x-aspnetmvc-version"];
+
+/// 上游响应头过滤：去掉敏感头，并限制响应头总大小
+#[derive(Debug, Clone, Copy)]
+pub struct HeaderFilter<'a> {
+    // 全局与路由的去除列表，不区分大小写，支持结尾 * 前缀匹配（如 x-debug-*）
+    pub strip: &'a [String],
+    pub route_strip: &'a [String],
+    pub max_bytes: Option<usize>,
+}
+
+impl<'a> HeaderFilter<'a> {
+    pub fn new(settings: &'a Settings, rule: &'a RouteRule) -> Self {
+        Self {
+            strip: &settings.response_strip_headers,
+            route_strip: rule.strip_response_headers.as_deref().unwrap_or_default(),
+            max_bytes: settings.max_response_header_bytes,
+        }
+    }
+
+    fn strips(&self, name: &str) -> bool {
+        self.strip.iter().chain(self.route_strip).any(|pattern| {
+            let pattern = pattern.to_ascii_lowercase();
+            match pattern.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == pattern,
+            }
+        })
+    }
+
+    /// 去掉命中的响应头；剩余响应头超过上限时返回其总字节数
+    pub fn apply(&self, headers: &mut HeaderMap) -> Result<(), usize> {
+        let stripped: Vec<_> = headers.keys().filter(|name| self.strips(name.as_str())).cloned().collect();
+        for name in stripped {
+            headers.remove(name);
+        }
+        let size = header_bytes(headers);
+        match self.max_bytes {
+            Some(max) if size > max => Err(size),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 响应头按 HTTP/1.1 编码的字节数（`name: value\r\n`）
+pub fn header_bytes(headers: &HeaderMap) -> usize {
+    headers.iter().map(|(name, value)| name.as_str().len() + value.len() + 4).sum()
+}
+
+/// 校验去除列表
+pub fn validation_errors(field: &str, patterns: &[String]) -> Vec<String> {
+    patterns
+        .iter()
+        .filter(|p| p.trim().is_empty() || p.as_str() == "*")
+        .map(|p| format!("{}不能包含空头名或单独的 *: {:?}", field, p))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_and_limit() {
+        let strip: Vec<String> = DEFAULT_STRIP_HEADERS.iter().map(|s| s.to_string()).collect();
+        let route_strip = vec!["X-Debug-*".to_string()];
+        let filter = HeaderFilter { strip: &strip, route_strip: &route_strip, max_bytes: None };
+
+        let mut headers = HeaderMap::new();
+        headers.insert("server", "nginx/1.25".parse().unwrap());
+        headers.insert("x-powered-by", "Express".parse().unwrap());
+        headers.insert("x-debug-sql", "select 1".parse().unwrap());
+        headers.insert("content-type", "text/plain".parse().unwrap());
+        assert!(filter.apply(&mut headers).is_ok());
+        assert_eq!(headers.len(), 1);
+        assert_eq!(header_bytes(&headers), "content-type: text/plain\r\n".len());
+
+        let limited = HeaderFilter { max_bytes: Some(10), ..filter };
+        assert_eq!(limited.apply(&mut headers), Err(26));
+
+        assert_eq!(validation_errors("response_strip_headers", &["*".to_string(), "".to_string()]).len(), 2);
+    }
+}
@@ -0,0 +1,182 @@
+//! 请求对冲：主请求在 hedge_after_ms 内没有返回时，向另一个上游再发一次同样的请求，取先成功的响应，
+//! 落败的请求被直接丢弃（取消 reqwest / hyper 的请求 future，连接随之关闭而不会回到连接池）。
+//!
+//! 对冲会放大上游负载，全局预算限制对冲请求数不超过开启对冲的路由请求数的 hedge_budget_percent（令牌桶：
+//! 每个请求存入 percent/100 个令牌，每次对冲消耗 1 个），并以指标记录对冲带来的额外负载比例。
+use once_cell::sync::Lazy;
+use std::future::Future;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::Settings;
+use crate::metrics::{HEDGE_COUNTER, HEDGE_EXTRA_LOAD_PERCENT};
+
+const DEFAULT_BUDGET_PERCENT: u64 = 10;
+// 令牌以千分之一为单位；桶容量 10 个令牌，允许短时突发
+const MILLI: i64 = 1000;
+const MAX_TOKENS: i64 = 10 * MILLI;
+// 额外负载比例的统计窗口
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// 对冲预算（令牌桶）
+struct Budget {
+    percent: AtomicU64,
+    tokens: AtomicI64,
+}
+
+impl Budget {
+    const fn new() -> Self {
+        Self { percent: AtomicU64::new(DEFAULT_BUDGET_PERCENT), tokens: AtomicI64::new(MAX_TOKENS) }
+    }
+
+    /// 开启对冲的路由收到一个请求：按预算比例存入令牌
+    fn deposit(&self) {
+        let amount = self.percent.load(Ordering::Relaxed) as i64 * MILLI / 100;
+        let _ = self.tokens.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |t| Some((t + amount).min(MAX_TOKENS)));
+    }
+
+    /// 取一个令牌，预算耗尽时返回 false
+    fn withdraw(&self) -> bool {
+        self.tokens
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |t| (t >= MILLI).then_some(t - MILLI))
+            .is_ok()
+    }
+
+    fn refund(&self) {
+        self.tokens.fetch_add(MILLI, Ordering::Relaxed);
+    }
+}
+
+static BUDGET: Budget = Budget::new();
+
+/// 应用对冲预算
+pub fn configure(settings: &Settings) {
+    BUDGET.percent.store(settings.hedge_budget_percent.unwrap_or(DEFAULT_BUDGET_PERCENT), Ordering::Relaxed);
+}
+
+// ===== 额外负载比例：当前窗口内对冲请求数 / 请求数 =====
+struct Window {
+    start: Instant,
+    requests: u64,
+    hedges: u64,
+}
+
+static WINDOW_STATS: Lazy<Mutex<Window>> = Lazy::new(|| Mutex::new(Window { start: Instant::now(), requests: 0, hedges: 0 }));
+
+fn count(requests: u64, hedges: u64) {
+    let Ok(mut window) = WINDOW_STATS.lock() else { return };
+    if window.start.elapsed() >= WINDOW {
+        *window = Window { start: Instant::now(), requests: 0, hedges: 0 };
+    }
+    window.requests += requests;
+    window.hedges += hedges;
+    if window.requests > 0 {
+        HEDGE_EXTRA_LOAD_PERCENT.set(window.hedges as f64 * 100.0 / window.requests as f64);
+    }
+}
+
+fn record(event: &str, route: &str) {
+    HEDGE_COUNTER.with_label_values(&[event, route]).inc();
+}
+
+/// 发起对冲的那一方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attempt {
+    Primary,
+    Hedge,
+}
+
+/// 先执行 primary，delay 内未完成且预算允许时调用 start_hedge 发起对冲（返回 None 表示没有可用的其他上游），
+/// 两者中先得到 succeeded 结果的一方胜出，另一方立即丢弃；先完成的一方失败时等待另一方
+pub async fn race<Fut, T>(
+    route: &str,
+    delay: Duration,
+    primary: Fut,
+    start_hedge: impl FnOnce() -> Option<Fut>,
+    succeeded: impl Fn(&T) -> bool,
+) -> (Attempt, T)
+where
+    Fut: Future<Output = T>,
+{
+    BUDGET.deposit();
+    count(1, 0);
+    tokio::pin!(primary);
+    tokio::select! {
+        result = &mut primary => return (Attempt::Primary, result),
+        _ = tokio::time::sleep(delay) => {}
+    }
+    if !BUDGET.withdraw() {
+        record("budget_exhausted", route);
+        return (Attempt::Primary, primary.await);
+    }
+    let Some(hedge) = start_hedge() else {
+        // 没有发出对冲，退还令牌
+        BUDGET.refund();
+        return (Attempt::Primary, primary.await);
+    };
+    record("issued", route);
+    count(0, 1);
+    tokio::pin!(hedge);
+    // 先完成的一方成功时，另一方的 future 随返回析构，请求被取消
+    let (winner, result, cancelled) = tokio::select! {
+        result = &mut primary => {
+            if succeeded(&result) { (Attempt::Primary, result, true) } else { (Attempt::Hedge, hedge.await, false) }
+        }
+        result = &mut hedge => {
+            if succeeded(&result) { (Attempt::Hedge, result, true) } else { (Attempt::Primary, primary.await, false) }
+        }
+    };
+    record(if winner == Attempt::Hedge { "won" } else { "lost" }, route);
+    if cancelled {
+        record("cancelled", route);
+    }
+    (winner, result)
+}
+
+pub fn validation_errors(settings: &Settings) -> Vec<String> {
+    let mut errors = Vec::new();
+    if settings.hedge_budget_percent.is_some_and(|p| p > 100) {
+        errors.push("hedge_budget_percent必须在0到100之间".to_string());
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_race_cancels_loser() {
+        let delay = |ms: u64, value: u32| async move {
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+            value
+        };
+        // 主请求在对冲延迟内完成：不发起对冲
+        let (winner, value) = race("hedge-test", Duration::from_millis(50), delay(1, 1), || Some(delay(1, 2)), |_| true).await;
+        assert_eq!((winner, value), (Attempt::Primary, 1));
+
+        // 主请求慢：对冲先返回，主请求被丢弃
+        let started = Instant::now();
+        let (winner, value) = race("hedge-test", Duration::from_millis(10), delay(5000, 1), || Some(delay(10, 2)), |_| true).await;
+        assert_eq!((winner, value), (Attempt::Hedge, 2));
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        // 对冲先完成但失败：等待主请求
+        let (winner, value) = race("hedge-test", Duration::from_millis(10), delay(60, 1), || Some(delay(1, 0)), |v| *v > 0).await;
+        assert_eq!((winner, value), (Attempt::Primary, 1));
+    }
+
+    #[test]
+    fn test_budget_caps_hedges() {
+        let budget = Budget::new();
+        budget.tokens.store(0, Ordering::Relaxed);
+        let hedges = (0..100)
+            .filter(|_| {
+                budget.deposit();
+                budget.withdraw()
+            })
+            .count();
+        assert_eq!(hedges, 10);
+    }
+}
@@ -0,0 +1,129 @@
+//! 内部重定向：开启 internal_redirect 的路由收到带 X-Accel-Redirect 的响应（来自上游或插件）时，
+//! 网关不把该响应返回给客户端，而是以响应头中的路径在网关内部重新分派一次请求（与 nginx 的 X-Accel-Redirect 一致），
+//! 客户端无需再发起一次请求。典型用法是由业务后端鉴权，再由另一个路由（如对象存储源站）提供下载内容。
+//!
+//! 重新分派的请求沿用原请求的请求头与客户端信息，不带请求体，方法除 HEAD 外改为 GET，并重新执行目标路由的全部阶段。
+//! 目标路径先经过与客户端路径相同的检查与规范化；访问日志与各类统计只由最外层的分派记录一次。
+//! 标记为 internal 的路由只能经内部重定向访问，客户端直接请求时返回 404。
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, Extensions, HeaderMap, Method, Response, StatusCode, Uri},
+    response::IntoResponse,
+};
+
+use crate::config::RouteRule;
+use crate::problem::{Problem, ProblemType};
+
+pub const HEADER: &str = "x-accel-redirect";
+/// 单个请求最多经过的内部重定向次数
+pub const MAX_HOPS: usize = 10;
+
+/// 已经过的内部重定向次数，写入重新分派的请求扩展
+#[derive(Debug, Clone, Copy)]
+pub struct Hops(pub usize);
+
+/// 路由是否处理 X-Accel-Redirect
+pub fn enabled(rule: &RouteRule) -> bool {
+    rule.internal_redirect.unwrap_or(false)
+}
+
+/// 客户端直接请求 internal 路由时的响应；经内部重定向到达时返回 None
+pub fn reject_external(rule: &RouteRule, req: &Request<Body>) -> Option<Response<Body>> {
+    if !rule.internal.unwrap_or(false) || req.extensions().get::<Hops>().is_some() {
+        return None;
+    }
+    Some(
+        Problem::new(StatusCode::NOT_FOUND, ProblemType::NotFound)
+            .detail(format!("No route for path: {}", req.uri().path()))
+            .into_response(),
+    )
+}
+
+/// 发起重新分派所需的原请求信息
+pub struct Origin {
+    method: Method,
+    headers: HeaderMap,
+    extensions: Extensions,
+    hops: usize,
+}
+
+impl Origin {
+    pub fn capture(req: &Request<Body>) -> Self {
+        let hops = req.extensions().get::<Hops>().map_or(0, |hops| hops.0);
+        Self { method: req.method().clone(), headers: req.headers().clone(), extensions: req.extensions().clone(), hops }
+    }
+
+    /// 响应带有站内路径的 X-Accel-Redirect 时返回重新分派的请求，超过跳数上限时返回错误响应；
+    /// 没有或不是站内路径时返回 None
+    pub fn follow(self, resp: &Response<Body>) -> Option<Result<Request<Body>, Response<Body>>> {
+        let target = resp.headers().get(HEADER)?.to_str().ok()?.trim();
+        if !target.starts_with('/') || target.starts_with("//") {
+            return None;
+        }
+        let uri = target.parse::<Uri>().ok()?;
+        if self.hops >= MAX_HOPS {
+            tracing::warn!(location = %target, "内部重定向次数超过上限");
+            return Some(Err(Problem::new(StatusCode::INTERNAL_SERVER_ERROR, ProblemType::Internal)
+                .detail(format!("More than {} internal redirects", MAX_HOPS))
+                .into_response()));
+        }
+        let method = if self.method == Method::HEAD { Method::HEAD } else { Method::GET };
+        let mut req = Request::builder().method(method).uri(uri).body(Body::empty()).ok()?;
+        let mut headers = self.headers;
+        for name in [header::CONTENT_LENGTH, header::CONTENT_TYPE, header::CONTENT_ENCODING, header::TRANSFER_ENCODING] {
+            headers.remove(name);
+        }
+        *req.headers_mut() = headers;
+        *req.extensions_mut() = self.extensions;
+        req.extensions_mut().insert(Hops(self.hops + 1));
+        Some(Ok(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redirect_to(location: &str) -> Response<Body> {
+        Response::builder().header(HEADER, location).body(Body::from("authorized")).unwrap()
+    }
+
+    #[test]
+    fn test_follow() {
+        let req = Request::builder()
+            .method("POST")
+            .uri("/downloads/1")
+            .header("authorization", "Bearer t")
+            .header("content-type", "application/json")
+            .body(Body::from("{}"))
+            .unwrap();
+        let next = Origin::capture(&req).follow(&redirect_to("/files/report.pdf?sig=1")).unwrap().unwrap();
+        assert_eq!(next.method(), Method::GET);
+        assert_eq!(next.uri(), "/files/report.pdf?sig=1");
+        assert_eq!(next.headers()["authorization"], "Bearer t");
+        assert!(next.headers().get("content-type").is_none());
+        assert_eq!(next.extensions().get::<Hops>().unwrap().0, 1);
+
+        // 没有该头或不是站内路径时不重定向
+        let origin = || Origin::capture(&req);
+        assert!(origin().follow(&Response::new(Body::empty())).is_none());
+        assert!(origin().follow(&redirect_to("https://evil.example.com/x")).is_none());
+        assert!(origin().follow(&redirect_to("//evil.example.com/x")).is_none());
+
+        let mut looping = Request::new(Body::empty());
+        looping.extensions_mut().insert(Hops(MAX_HOPS));
+        let err = Origin::capture(&looping).follow(&redirect_to("/again")).unwrap().unwrap_err();
+        assert_eq!(err.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_reject_external() {
+        let rule = RouteRule { internal: Some(true), ..Default::default() };
+        let mut req = Request::builder().uri("/files/report.pdf").body(Body::empty()).unwrap();
+        assert_eq!(reject_external(&rule, &req).unwrap().status(), StatusCode::NOT_FOUND);
+        req.extensions_mut().insert(Hops(1));
+        assert!(reject_external(&rule, &req).is_none());
+        assert!(reject_external(&RouteRule::default(), &Request::new(Body::empty())).is_none());
+    }
+}
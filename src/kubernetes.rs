@@ -0,0 +1,296 @@
+//! Kubernetes 控制器模式：轮询 Ingress 或 Gateway API HTTPRoute 资源，运行时转换为路由规则，
+//! 网关可直接作为集群入口部署，无需另外维护 routes.toml
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::config::{RouteRule, Settings};
+
+const DEFAULT_API_SERVER: &str = "https://kubernetes.default.svc";
+const DEFAULT_CLASS: &str = "helios";
+const DEFAULT_POLL_SECS: u64 = 10;
+// 集群内 ServiceAccount 的令牌与 CA 证书
+const TOKEN_FILE: &str = "/var/run/secrets/kubernetes.io/serviceaccount/token";
+const CA_FILE: &str = "/var/run/secrets/kubernetes.io/serviceaccount/ca.crt";
+
+// 路由注解：负载均衡策略、白名单（逗号分隔）与鉴权模式
+const ANNOTATION_STRATEGY: &str = "helios.io/strategy";
+const ANNOTATION_WHITELIST: &str = "helios.io/whitelist";
+const ANNOTATION_AUTH: &str = "helios.io/auth";
+
+/// 监听的资源类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    Ingress,
+    HttpRoute,
+}
+
+impl Resource {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "ingress" => Some(Self::Ingress),
+            "gateway-api" => Some(Self::HttpRoute),
+            _ => None,
+        }
+    }
+
+    fn list_path(&self, namespace: Option<&str>) -> String {
+        let (group, plural) = match self {
+            Self::Ingress => ("networking.k8s.io/v1", "ingresses"),
+            Self::HttpRoute => ("gateway.networking.k8s.io/v1", "httproutes"),
+        };
+        match namespace {
+            Some(ns) => format!("/apis/{}/namespaces/{}/{}", group, ns, plural),
+            None => format!("/apis/{}/{}", group, plural),
+        }
+    }
+}
+
+fn annotations(meta: &Value) -> impl Fn(&str) -> Option<String> + '_ {
+    move |key| meta["annotations"][key].as_str().map(str::to_string)
+}
+
+// 按注解补全路由的策略、白名单与鉴权模式
+fn apply_annotations(rule: &mut RouteRule, meta: &Value) {
+    let annotation = annotations(meta);
+    if let Some(strategy) = annotation(ANNOTATION_STRATEGY) {
+        rule.strategy = strategy;
+    }
+    if let Some(whitelist) = annotation(ANNOTATION_WHITELIST) {
+        rule.whitelist = Some(whitelist.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect());
+    }
+    rule.auth = annotation(ANNOTATION_AUTH);
+}
+
+// Kubernetes 的路径前缀转发完整路径，而网关会去掉普通前缀：把前缀同时拼到上游地址上；
+// 根路径使用 /** 模式（模式前缀不会被去掉）
+fn route_for(id: String, path: &str, backends: Vec<String>) -> RouteRule {
+    let path = path.trim_end_matches('/');
+    let (prefix, upstream) = if path.is_empty() {
+        ("/**".to_string(), backends)
+    } else {
+        (path.to_string(), backends.into_iter().map(|b| format!("{}{}", b, path)).collect())
+    };
+    RouteRule { id: Some(id), prefix: vec![prefix], upstream, ..Default::default() }
+}
+
+fn service_url(name: &str, namespace: &str, port: &Value) -> Option<String> {
+    // 具名端口需要查询 Service 才能解析，这里只支持端口号
+    let port = port.as_u64()?;
+    Some(format!("http://{}.{}.svc:{}", name, namespace, port))
+}
+
+/// Ingress（networking.k8s.io/v1）-> 路由：每个 path 一条路由；host 不参与匹配
+pub fn translate_ingress(item: &Value, class: &str) -> Vec<RouteRule> {
+    let meta = &item["metadata"];
+    let spec = &item["spec"];
+    let ingress_class = spec["ingressClassName"].as_str().or(meta["annotations"]["kubernetes.io/ingress.class"].as_str());
+    if ingress_class != Some(class) {
+        return Vec::new();
+    }
+    let namespace = meta["namespace"].as_str().unwrap_or("default");
+    let name = meta["name"].as_str().unwrap_or_default();
+
+    let mut routes = Vec::new();
+    let paths = spec["rules"].as_array().into_iter().flatten().flat_map(|r| r["http"]["paths"].as_array().into_iter().flatten());
+    for (i, path) in paths.enumerate() {
+        let service = &path["backend"]["service"];
+        let Some(backend) = service["name"].as_str().and_then(|svc| service_url(svc, namespace, &service["port"]["number"])) else {
+            tracing::warn!(ingress = %format!("{}/{}", namespace, name), "跳过不支持的后端（仅支持 Service 与端口号）");
+            continue;
+        };
+        let mut rule = route_for(format!("k8s:{}/{}#{}", namespace, name, i), path["path"].as_str().unwrap_or("/"), vec![backend]);
+        apply_annotations(&mut rule, meta);
+        routes.push(rule);
+    }
+    routes
+}
+
+/// HTTPRoute（gateway.networking.k8s.io/v1）-> 路由：每个 match 一条路由，backendRefs 作为上游组（忽略权重）；
+/// 只处理 parentRefs 引用了名为 class 的 Gateway 的资源
+pub fn translate_http_route(item: &Value, class: &str) -> Vec<RouteRule> {
+    let meta = &item["metadata"];
+    let spec = &item["spec"];
+    let attached = spec["parentRefs"].as_array().into_iter().flatten().any(|p| p["name"].as_str() == Some(class));
+    if !attached {
+        return Vec::new();
+    }
+    let namespace = meta["namespace"].as_str().unwrap_or("default");
+    let name = meta["name"].as_str().unwrap_or_default();
+
+    let mut routes = Vec::new();
+    for (i, rule) in spec["rules"].as_array().into_iter().flatten().enumerate() {
+        let backends: Vec<String> = rule["backendRefs"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|b| service_url(b["name"].as_str()?, b["namespace"].as_str().unwrap_or(namespace), &b["port"]))
+            .collect();
+        if backends.is_empty() {
+            continue;
+        }
+        let matches = rule["matches"].as_array().cloned().unwrap_or_default();
+        let paths: Vec<&str> = if matches.is_empty() {
+            vec!["/"]
+        } else {
+            matches.iter().map(|m| m["path"]["value"].as_str().unwrap_or("/")).collect()
+        };
+        for (j, path) in paths.into_iter().enumerate() {
+            let mut route = route_for(format!("k8s:{}/{}#{}.{}", namespace, name, i, j), path, backends.clone());
+            apply_annotations(&mut route, meta);
+            routes.push(route);
+        }
+    }
+    routes
+}
+
+/// 控制器配置
+#[derive(Debug, Clone)]
+struct Controller {
+    resource: Resource,
+    class: String,
+    namespace: Option<String>,
+    api_server: String,
+    interval: Duration,
+}
+
+/// 配置了 kubernetes_controller 时后台轮询集群资源；须在 tokio 运行时中调用
+pub fn spawn_controller(settings: &Settings) {
+    let Some(resource) = settings.kubernetes_controller.as_deref().and_then(Resource::parse) else {
+        return;
+    };
+    let controller = Controller {
+        resource,
+        class: settings.kubernetes_class.clone().unwrap_or_else(|| DEFAULT_CLASS.to_string()),
+        namespace: settings.kubernetes_namespace.clone(),
+        api_server: settings.kubernetes_api_server.clone().unwrap_or_else(|| DEFAULT_API_SERVER.to_string()),
+        interval: Duration::from_secs(settings.kubernetes_poll_secs.unwrap_or(DEFAULT_POLL_SECS).max(1)),
+    };
+    tokio::spawn(run(controller));
+}
+
+fn api_client() -> reqwest::Client {
+    let mut builder = reqwest::Client::builder().timeout(Duration::from_secs(10));
+    if let Some(cert) = std::fs::read(CA_FILE).ok().and_then(|pem| reqwest::Certificate::from_pem(&pem).ok()) {
+        builder = builder.add_root_certificate(cert);
+    }
+    builder.build().unwrap_or_default()
+}
+
+async fn run(controller: Controller) {
+    let client = api_client();
+    let url = format!("{}{}", controller.api_server.trim_end_matches('/'), controller.resource.list_path(controller.namespace.as_deref()));
+    let mut last_version = None;
+    let mut interval = tokio::time::interval(controller.interval);
+    loop {
+        interval.tick().await;
+        // 令牌会轮换，每次重新读取；集群外经 kubectl proxy 访问时没有令牌
+        let mut request = client.get(&url);
+        if let Ok(token) = std::fs::read_to_string(TOKEN_FILE) {
+            request = request.bearer_auth(token.trim());
+        }
+        let list: Value = match request.send().await.and_then(|r| r.error_for_status()) {
+            Ok(resp) => match resp.json().await {
+                Ok(list) => list,
+                Err(err) => {
+                    tracing::warn!("解析 Kubernetes 资源列表失败: {}", err);
+                    continue;
+                }
+            },
+            Err(err) => {
+                tracing::warn!("获取 Kubernetes 资源失败，沿用上次的路由: {}", err);
+                continue;
+            }
+        };
+        let version = list["metadata"]["resourceVersion"].as_str().map(str::to_string);
+        if version.is_some() && version == last_version {
+            continue;
+        }
+        last_version = version;
+
+        let routes = list["items"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(|item| match controller.resource {
+                Resource::Ingress => translate_ingress(item, &controller.class),
+                Resource::HttpRoute => translate_http_route(item, &controller.class),
+            })
+            .collect();
+        let routes = crate::discovery::valid_only("kubernetes", routes);
+        tracing::info!(routes = routes.len(), "已从 Kubernetes 资源更新路由");
+        crate::discovery::set("kubernetes", routes);
+    }
+}
+
+pub fn validation_errors(settings: &Settings) -> Vec<String> {
+    let mut errors = Vec::new();
+    if let Some(mode) = &settings.kubernetes_controller
+        && Resource::parse(mode).is_none()
+    {
+        errors.push(format!("kubernetes_controller仅支持 ingress、gateway-api: {}", mode));
+    }
+    if settings.kubernetes_poll_secs == Some(0) {
+        errors.push("kubernetes_poll_secs必须大于0".to_string());
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_translate_ingress() {
+        let ingress = json!({
+            "metadata": {
+                "name": "shop", "namespace": "prod",
+                "annotations": { "helios.io/whitelist": "/api/orders/health", "helios.io/strategy": "iphash" },
+            },
+            "spec": {
+                "ingressClassName": "helios",
+                "rules": [{ "host": "shop.example.com", "http": { "paths": [
+                    { "path": "/api/orders", "pathType": "Prefix", "backend": { "service": { "name": "orders", "port": { "number": 8080 } } } },
+                    { "path": "/", "pathType": "Prefix", "backend": { "service": { "name": "web", "port": { "number": 80 } } } },
+                    { "path": "/named", "pathType": "Prefix", "backend": { "service": { "name": "web", "port": { "name": "http" } } } },
+                ] } }],
+            },
+        });
+        let routes = translate_ingress(&ingress, "helios");
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].id.as_deref(), Some("k8s:prod/shop#0"));
+        assert_eq!(routes[0].prefix, vec!["/api/orders"]);
+        assert_eq!(routes[0].upstream, vec!["http://orders.prod.svc:8080/api/orders"]);
+        assert_eq!(routes[0].strategy, "iphash");
+        assert_eq!(routes[0].whitelist, Some(vec!["/api/orders/health".to_string()]));
+        assert_eq!(routes[1].prefix, vec!["/**"]);
+        assert_eq!(routes[1].upstream, vec!["http://web.prod.svc:80"]);
+        assert!(routes.iter().all(|r| r.validation_errors().is_empty()));
+        // 转发完整路径：去掉前缀后拼在带前缀的上游地址上
+        let forward = crate::proxy::reconstruct_forward_path("/api/orders/1", &routes[0].prefix, &Default::default());
+        assert_eq!(format!("{}{}", routes[0].upstream[0], forward), "http://orders.prod.svc:8080/api/orders/1");
+
+        assert!(translate_ingress(&ingress, "nginx").is_empty());
+    }
+
+    #[test]
+    fn test_translate_http_route() {
+        let route = json!({
+            "metadata": { "name": "users", "namespace": "prod", "annotations": { "helios.io/auth": "optional" } },
+            "spec": {
+                "parentRefs": [{ "name": "helios" }],
+                "rules": [
+                    { "matches": [{ "path": { "type": "PathPrefix", "value": "/users" } }, { "path": { "value": "/accounts/" } }],
+                      "backendRefs": [{ "name": "users-v1", "port": 8080 }, { "name": "users-v2", "namespace": "canary", "port": 8080 }] },
+                    { "backendRefs": [] },
+                ],
+            },
+        });
+        let routes = translate_http_route(&route, "helios");
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].upstream, vec!["http://users-v1.prod.svc:8080/users", "http://users-v2.canary.svc:8080/users"]);
+        assert_eq!(routes[1].prefix, vec!["/accounts"]);
+        assert_eq!(routes[1].auth.as_deref(), Some("optional"));
+        assert!(translate_http_route(&route, "other").is_empty());
+    }
+}
@@ -0,0 +1,147 @@
+//! Helios 网关库：路由构建、中间件栈与配置加载，可嵌入到自定义程序中，
+//! 通过 [`GatewayBuilder`] 注入自定义过滤器、以代码提供路由。
+
+use axum::{Router, routing::get, Extension};
+use std::sync::Arc;
+
+mod proxy;
+mod auth;
+mod mtls;
+pub mod config;
+mod metrics;
+mod rate_limit;
+mod path_matcher;
+pub mod load_balancer;
+pub mod plugin;
+mod admin;
+mod debug;
+pub mod logging;
+pub mod cli;
+pub mod bench;
+pub mod dev;
+pub mod lint;
+#[cfg(any(test, feature = "stub"))]
+pub mod stub;
+#[cfg(any(test, feature = "stub"))]
+pub mod testing;
+mod upstream;
+mod upstream_url;
+mod preflight;
+mod coordination;
+mod discovery;
+mod kubernetes;
+mod docker;
+mod upstream_template;
+mod tenant_upstreams;
+mod grpc_reflection;
+mod region;
+mod redirect;
+mod internal_redirect;
+mod sendfile;
+mod session;
+mod compression;
+mod normalize;
+mod query;
+mod schema;
+mod versioning;
+mod cors;
+mod pipeline;
+mod s3;
+mod tunnel;
+mod forward_proxy;
+mod shedding;
+mod qos;
+mod problem;
+mod error_pages;
+mod etag;
+mod server_timing;
+mod buffering;
+mod ops;
+mod whitelist;
+mod header_filter;
+mod response_cache;
+mod access_log;
+mod capture;
+mod feature_flags;
+mod methods;
+mod claim_labels;
+mod slo;
+mod audit;
+mod anomaly;
+mod ban;
+mod namespace;
+mod hedging;
+mod retry;
+mod mirror;
+mod timeouts;
+mod schedule;
+mod bot;
+mod tarpit;
+mod header_case;
+mod websocket;
+mod bandwidth;
+mod route_diagnostics;
+mod gateway;
+
+pub use gateway::{Filter, Gateway, GatewayBuilder};
+
+// ===== 组装路由与中间件 =====
+/// 返回网关服务，以及配置了 ops_bind 时独立提供的运维端点服务
+pub(crate) fn build_app(
+    settings: &config::Settings,
+    rate_limits: Arc<rate_limit::RateLimits>,
+    route_rules: Vec<config::RouteRule>,
+    filters: Vec<Filter>,
+) -> (Router, Option<Router>) {
+    // 上游并发上限写入共享的上游状态表
+    upstream::configure_max_in_flight(&settings.upstream_max_in_flight);
+    load_balancer::ip_hash::set_load_factor(settings.iphash_load_factor);
+    load_balancer::ip_hash::configure_ring(
+        settings.iphash_virtual_nodes,
+        settings.iphash_hash.as_deref().and_then(load_balancer::hash::HashFunction::parse),
+    );
+    problem::configure(settings);
+    claim_labels::configure(settings);
+    ban::configure(settings);
+    hedging::configure(settings);
+    schedule::configure(settings);
+
+    let app = Router::new().route("/", get(|| async { "Rust Gateway is running 🚀" }));
+    // 配置 ops_bind 后，网关端口不再提供运维端点
+    let (app, ops_app) = match settings.ops_bind {
+        Some(_) => (app, Some(ops::router())),
+        None => (app.merge(ops::router()), None),
+    };
+    let app = app
+        .merge(session::router())
+        .merge(proxy::router())
+        .layer(axum::middleware::from_fn(metrics::prometheus_middleware))
+        // 可选：正向代理（CONNECT 请求不参与路由匹配）
+        .layer(axum::middleware::from_fn(forward_proxy::connect_layer));
+    // 嵌入方注入的自定义过滤器：在请求 ID、配置等扩展注入之后、路由匹配之前执行
+    let app = filters.into_iter().fold(app, |app, filter| filter(app));
+
+    // 可选：QoS 准入调度（运维端点需读取同一调度器的状态）
+    let scheduler = qos::Scheduler::from_settings(settings);
+    let shared = |app: Router| {
+        let app = app
+            // 请求 ID：透传给上游、回显在响应头，并写入错误响应
+            .layer(axum::middleware::from_fn(problem::request_id_layer))
+            .layer(Extension(settings.clone()))
+            .layer(Extension(rate_limits.clone()))
+            .layer(Extension(route_rules.clone()));
+        match &scheduler {
+            Some(scheduler) => app.layer(Extension(scheduler.clone())),
+            None => app,
+        }
+    };
+    let ops_app = ops_app.map(shared);
+    let app = shared(app);
+
+    // 可选：Cookie 会话
+    let app = match session::Sessions::from_settings(settings) {
+        Some(sessions) => app.layer(Extension(sessions)),
+        None => app,
+    };
+    (app, ops_app)
+}
@@ -0,0 +1,304 @@
+//! 配置检查（helios lint）与路由文件迁移（helios migrate）
+use std::path::{Path, PathBuf};
+
+use crate::config::{RouteRule, Settings};
+use crate::proxy::route_label;
+
+/// 旧版路由字段 -> 当前字段
+pub const LEGACY_KEYS: &[(&str, &str)] = &[
+    ("prefixes", "prefix"),
+    ("upstreams", "upstream"),
+    ("white_list", "whitelist"),
+    ("load_balance", "strategy"),
+];
+
+/// 旧版负载均衡策略名 -> 当前策略名
+pub const LEGACY_STRATEGIES: &[(&str, &str)] = &[
+    ("round_robin", "robin"),
+    ("roundrobin", "robin"),
+    ("ip_hash", "iphash"),
+    ("weighted_random", "random"),
+];
+
+// 通常只应对内开放的路径段
+const SENSITIVE_SEGMENTS: &[&str] = &["admin", "internal", "management", "actuator", "debug", "private"];
+
+// 密钥的最小长度（字节）
+const MIN_SECRET_LEN: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Warn,
+    Error,
+}
+
+impl Level {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Warn => "warn",
+            Level::Error => "error",
+        }
+    }
+}
+
+/// 一条检查结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub level: Level,
+    pub location: String,
+    pub message: String,
+    pub suggestion: Option<String>,
+}
+
+impl Finding {
+    fn new(level: Level, location: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { level, location: location.into(), message: message.into(), suggestion: None }
+    }
+
+    fn suggest(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+}
+
+// ===== 迁移 =====
+
+/// 一处迁移改动
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub line: usize,
+    pub description: String,
+}
+
+/// 按行改写路由文件中的旧版字段与策略名，保留注释与格式；只处理 [[routes]] 表中的键
+pub fn migrate_content(content: &str) -> (String, Vec<Change>) {
+    let mut changes = Vec::new();
+    let mut in_route = false;
+    let mut out = String::with_capacity(content.len());
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('[') {
+            in_route = trimmed.starts_with("[[routes]]");
+            out.push_str(line);
+            continue;
+        }
+        let Some((key, value)) = trimmed.split_once('=').filter(|_| in_route) else {
+            out.push_str(line);
+            continue;
+        };
+        let indent = &line[..line.len() - trimmed.len()];
+        let (key, key_pad) = (key.trim_end(), &key[key.trim_end().len()..]);
+        let mut new_key = key;
+        if let Some((_, current)) = LEGACY_KEYS.iter().find(|(old, _)| *old == key) {
+            changes.push(Change { line: i + 1, description: format!("{} -> {}", key, current) });
+            new_key = current;
+        }
+        let mut new_value = value.to_string();
+        if new_key == "strategy" {
+            for (old, current) in LEGACY_STRATEGIES {
+                let quoted = format!("\"{}\"", old);
+                if value.trim_start().starts_with(&quoted) {
+                    new_value = value.replacen(&quoted, &format!("\"{}\"", current), 1);
+                    changes.push(Change { line: i + 1, description: format!("strategy \"{}\" -> \"{}\"", old, current) });
+                    break;
+                }
+            }
+        }
+        out.push_str(&format!("{}{}{}={}", indent, new_key, key_pad, new_value));
+    }
+    (out, changes)
+}
+
+/// 目录中的路由文件：routes.toml、routes.<profile>.toml 与 routes.d/*.toml（含 profile 覆盖文件）
+pub fn route_files(base: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for dir in [base.to_path_buf(), base.join("routes.d")] {
+        let Ok(entries) = std::fs::read_dir(&dir) else { continue };
+        let mut found: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "toml"))
+            .filter(|p| dir != base || p.file_stem().and_then(|s| s.to_str()).is_some_and(|s| s == "routes" || s.starts_with("routes.")))
+            .collect();
+        found.sort();
+        files.extend(found);
+    }
+    files
+}
+
+/// 迁移目录中的路由文件；write 为 false 时只返回将要做的改动
+pub fn migrate_dir(base: &Path, write: bool) -> Result<Vec<(PathBuf, Vec<Change>)>, String> {
+    let mut result = Vec::new();
+    for path in route_files(base) {
+        let content = std::fs::read_to_string(&path).map_err(|e| format!("读取 {} 失败: {}", path.display(), e))?;
+        let (migrated, changes) = migrate_content(&content);
+        if changes.is_empty() {
+            continue;
+        }
+        if write {
+            std::fs::write(&path, migrated).map_err(|e| format!("写入 {} 失败: {}", path.display(), e))?;
+        }
+        result.push((path, changes));
+    }
+    Ok(result)
+}
+
+// ===== 检查 =====
+
+/// 检查目录中的路由文件与当前设置（settings 加载失败时为 None，只检查路由）
+pub fn lint_dir(base: &Path, settings: Option<&Settings>) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    // 旧版字段会导致路由文件无法加载，先按行扫描给出位置
+    for path in route_files(base) {
+        let Ok(content) = std::fs::read_to_string(&path) else { continue };
+        for change in migrate_content(&content).1 {
+            findings.push(
+                Finding::new(Level::Error, format!("{}:{}", path.display(), change.line), format!("已弃用的写法: {}", change.description))
+                    .suggest("运行 helios migrate --write 改写为当前格式"),
+            );
+        }
+    }
+    match crate::config::load_route_rules_from(base) {
+        Ok(rules) => findings.extend(lint_rules(settings, &rules)),
+        // 存在旧版写法时加载失败在意料之中，已在上面报告
+        Err(_) if !findings.is_empty() => {}
+        Err(errors) => findings.extend(errors.into_iter().map(|e| Finding::new(Level::Error, "routes", e))),
+    }
+    if let Some(settings) = settings {
+        findings.extend(lint_settings(settings));
+    }
+    findings
+}
+
+fn is_sensitive(path: &str) -> bool {
+    path.split('/').any(|segment| SENSITIVE_SEGMENTS.contains(&segment.to_ascii_lowercase().as_str()))
+}
+
+// 白名单项与某个前缀相同，或以 /** 结尾且包含整个前缀
+fn covers_route(entry: &str, rule: &RouteRule) -> bool {
+    let base = entry.strip_suffix("/**");
+    rule.prefix.iter().any(|prefix| {
+        prefix == entry
+            || base.is_some_and(|base| prefix == base || prefix.strip_prefix(base).is_some_and(|rest| rest.starts_with('/')))
+    })
+}
+
+/// 路由的安全与冲突检查
+pub fn lint_rules(settings: Option<&Settings>, rules: &[RouteRule]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (i, rule) in rules.iter().enumerate() {
+        let location = format!("routes[{}]（{}）", i, route_label(rule));
+        let sensitive: Vec<&String> = rule.prefix.iter().filter(|p| is_sensitive(p)).collect();
+        if !sensitive.is_empty() {
+            let mode = rule.auth.as_deref().unwrap_or("required");
+            if !matches!(mode, "required" | "jwt" | "api_key") {
+                findings.push(
+                    Finding::new(Level::Warn, &location, format!("敏感前缀 {:?} 的鉴权模式为 {}，未认证的请求也会被转发", sensitive, mode))
+                        .suggest("去掉 auth 使用默认的 required，或不经公网入口暴露该前缀"),
+                );
+            }
+        }
+        if rule.auth.as_deref() == Some("api_key") && settings.is_some_and(|s| s.api_keys.is_empty()) {
+            findings.push(
+                Finding::new(Level::Warn, &location, "鉴权模式为 api_key，但未配置 api_keys，所有请求都会返回 401")
+                    .suggest("在 api_keys 中配置调用方的 key 与身份"),
+            );
+        }
+        for entry in rule.whitelist.iter().flatten() {
+            if is_sensitive(entry) {
+                findings.push(
+                    Finding::new(Level::Warn, &location, format!("白名单项 {} 位于敏感路径，无需 JWT 即可访问", entry))
+                        .suggest("确认该路径确实需要公开，否则从 whitelist 中移除"),
+                );
+            } else if covers_route(entry, rule) {
+                findings.push(
+                    Finding::new(Level::Warn, &location, format!("白名单项 {} 覆盖了整条路由，路由上的鉴权不会生效", entry))
+                        .suggest("只把需要公开的具体路径加入白名单，或使用 auth = \"optional\""),
+                );
+            }
+        }
+    }
+
+    // 以明文访问内网之外主机的上游：按开启 upstream_require_https 时的规则检查
+    let policy = match settings {
+        Some(settings) => crate::upstream_url::HttpsPolicy::new(settings),
+        None => crate::upstream_url::HttpsPolicy::default(),
+    };
+    for violation in policy.violations(rules) {
+        findings.push(
+            Finding::new(Level::Warn, "upstream", violation)
+                .suggest("改用 https，或确认是内网主机后加入 upstream_plaintext_hosts / upstream_private_networks"),
+        );
+    }
+
+    for diagnostic in crate::route_diagnostics::diagnose(rules) {
+        findings.push(Finding::new(Level::Warn, format!("routes/{}", diagnostic.kind.as_str()), diagnostic.message));
+    }
+    findings
+}
+
+/// 全局设置中的不安全配置
+pub fn lint_settings(settings: &Settings) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if settings.jwt_decoding_key.len() < MIN_SECRET_LEN {
+        findings.push(
+            Finding::new(Level::Warn, "jwt_decoding_key", format!("JWT 密钥短于 {} 字节，容易被暴力破解", MIN_SECRET_LEN))
+                .suggest("使用随机生成的长密钥，如 openssl rand -hex 32"),
+        );
+    }
+    if settings.jwt_decoding_key == crate::dev::DEV_JWT_SECRET {
+        findings.push(Finding::new(Level::Error, "jwt_decoding_key", "使用了开发模式的公开测试密钥").suggest("替换为生产密钥"));
+    }
+    if settings.admin_token.as_deref().is_some_and(|t| !t.is_empty() && t.len() < 16) {
+        findings.push(Finding::new(Level::Warn, "admin_token", "管理端令牌过短").suggest("使用至少 16 字节的随机令牌"));
+    }
+    let open = |nets: &[String]| nets.iter().any(|n| matches!(n.as_str(), "0.0.0.0/0" | "::/0"));
+    if open(&settings.debug_trusted_ips) {
+        findings.push(
+            Finding::new(Level::Error, "debug_trusted_ips", "对所有来源开放调试信息，会暴露上游地址与内部耗时")
+                .suggest("只保留内网网段，或改用 debug_secret 签名"),
+        );
+    }
+    if open(&settings.ops_allowed_ips) {
+        findings.push(Finding::new(Level::Warn, "ops_allowed_ips", "运维端点对所有来源开放").suggest("限制为内网网段或配置 ops_token"));
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_content() {
+        let content = "# 用户服务\n[[routes]]\nprefixes = [\"/user/**\"]  # 前缀\nupstreams = \"http://10.0.0.1:80\"\nload_balance = \"ip_hash\"\n\n[routes.cache]\nupstreams = 1\n";
+        let (migrated, changes) = migrate_content(content);
+        assert_eq!(
+            migrated,
+            "# 用户服务\n[[routes]]\nprefix = [\"/user/**\"]  # 前缀\nupstream = \"http://10.0.0.1:80\"\nstrategy = \"iphash\"\n\n[routes.cache]\nupstreams = 1\n"
+        );
+        assert_eq!(changes.len(), 4);
+        assert_eq!(changes[0].line, 3);
+        // 已是当前格式的内容保持不变
+        assert_eq!(migrate_content(&migrated).1, Vec::new());
+    }
+
+    #[test]
+    fn test_lint_rules() {
+        let rule = |prefix: &str, upstream: &str| RouteRule {
+            prefix: vec![prefix.to_string()],
+            upstream: vec![upstream.to_string()],
+            ..Default::default()
+        };
+        let mut admin = rule("/admin/**", "http://10.0.0.1:80");
+        admin.auth = Some("optional".to_string());
+        let mut public = rule("/api/**", "http://api.example.com");
+        public.whitelist = Some(vec!["/api/**".to_string(), "/api/internal/stats".to_string()]);
+        let findings = lint_rules(None, &[admin, public, rule("/orders/**", "https://orders.example.com")]);
+        let messages: Vec<&str> = findings.iter().map(|f| f.message.as_str()).collect();
+        assert_eq!(findings.len(), 4, "{:?}", messages);
+        assert!(findings[0].message.contains("/admin/**"));
+        assert!(messages.iter().any(|m| m.contains("覆盖了整条路由")));
+        assert!(messages.iter().any(|m| m.contains("/api/internal/stats")));
+        assert!(messages.iter().any(|m| m.contains("http://api.example.com")));
+    }
+}
@@ -1,10 +1,58 @@
-use std::collections::BTreeMap;
-use std::hash::{Hash, Hasher};
+use std::collections::{BTreeMap, HashMap};
 use std::net::SocketAddr;
-use std::collections::hash_map::DefaultHasher;
 use arc_swap::ArcSwap;
 use std::sync::Arc;
-use crate::load_balancer::LoadBalancer;
+use std::sync::atomic::{AtomicU8, AtomicU64, AtomicUsize, Ordering};
+use crate::load_balancer::hash::HashFunction;
+use crate::load_balancer::{BalancerSnapshot, LoadBalancer, UpstreamSnapshot};
+
+/// 每个节点默认的虚拟节点数
+pub const DEFAULT_VIRTUAL_NODES: usize = 150;
+
+// 新建哈希环使用的虚拟节点数（0 表示默认值）与哈希函数（HashFunction 的序号）
+static VIRTUAL_NODES: AtomicUsize = AtomicUsize::new(0);
+static HASH_FUNCTION: AtomicU8 = AtomicU8::new(0);
+
+/// 设置之后新建的 iphash 负载均衡器的虚拟节点数与哈希函数，None 恢复默认值；须在创建负载均衡器之前调用
+pub fn configure_ring(virtual_nodes: Option<usize>, hash: Option<HashFunction>) {
+    VIRTUAL_NODES.store(virtual_nodes.unwrap_or(0), Ordering::Relaxed);
+    HASH_FUNCTION.store(hash.unwrap_or_default() as u8, Ordering::Relaxed);
+}
+
+fn ring_config() -> (usize, HashFunction) {
+    let virtual_nodes = match VIRTUAL_NODES.load(Ordering::Relaxed) {
+        0 => DEFAULT_VIRTUAL_NODES,
+        n => n,
+    };
+    let hash = match HASH_FUNCTION.load(Ordering::Relaxed) {
+        1 => HashFunction::Fnv,
+        _ => HashFunction::Xxhash,
+    };
+    (virtual_nodes, hash)
+}
+
+/// 有界负载系数的默认值：单个节点的在途请求不超过平均值的 1.25 倍
+pub const DEFAULT_LOAD_FACTOR: f64 = 1.25;
+
+// 有界负载系数（f64 的位表示），0 表示不限制
+static LOAD_FACTOR: AtomicU64 = AtomicU64::new(0);
+
+/// 设置有界负载系数（>= 1），None 恢复默认值
+pub fn set_load_factor(factor: Option<f64>) {
+    LOAD_FACTOR.store(factor.unwrap_or(DEFAULT_LOAD_FACTOR).to_bits(), Ordering::Relaxed);
+}
+
+fn load_factor() -> f64 {
+    match LOAD_FACTOR.load(Ordering::Relaxed) {
+        0 => DEFAULT_LOAD_FACTOR,
+        bits => f64::from_bits(bits),
+    }
+}
+
+// 节点当前负载：在途请求数
+fn in_flight(url: &str) -> i64 {
+    crate::upstream::state(url).in_flight().max(0)
+}
 
 /// 负载均衡器状态（不可变对象）
 #[derive(Debug)]
@@ -12,16 +60,17 @@ struct BalancerState {
     hash_ring: BTreeMap<u64, String>,
     upstreams: Vec<String>,
     virtual_nodes: usize,
+    hash_function: HashFunction,
 }
 
 impl BalancerState {
-    fn build(upstreams: Vec<String>, virtual_nodes: usize) -> Self {
+    fn build(upstreams: Vec<String>, virtual_nodes: usize, hash_function: HashFunction) -> Self {
         let mut hash_ring = BTreeMap::new();
 
         for upstream in &upstreams {
             for i in 0..virtual_nodes {
                 let key = format!("{}#{}", upstream, i);
-                let hash = Self::hash(&key);
+                let hash = hash_function.hash(key.as_bytes());
                 hash_ring.insert(hash, upstream.clone());
             }
         }
@@ -30,13 +79,12 @@ impl BalancerState {
             hash_ring,
             upstreams,
             virtual_nodes,
+            hash_function,
         }
     }
 
-    fn hash(key: &str) -> u64 {
-        let mut hasher = DefaultHasher::new();
-        key.hash(&mut hasher);
-        hasher.finish()
+    fn hash(&self, key: &str) -> u64 {
+        self.hash_function.hash(key.as_bytes())
     }
 
     fn find_upstream(&self, hash: u64) -> Option<String> {
@@ -51,6 +99,24 @@ impl BalancerState {
         // 环回到第一个节点
         self.hash_ring.iter().next().map(|(_, v)| v.clone())
     }
+
+    // 从 hash 开始顺时针遍历哈希环
+    fn walk(&self, hash: u64) -> impl Iterator<Item = &String> {
+        self.hash_ring.range(hash..).chain(self.hash_ring.range(..hash)).map(|(_, v)| v)
+    }
+
+    /// 有界负载的一致性哈希：跳过不可用节点，以及负载已达 ceil(factor * (总负载 + 1) / 可用节点数) 的节点；
+    /// 大多数键仍落在原节点上，只有热点节点的溢出流量顺延到下一个节点。
+    /// 负载只读取一次：factor >= 1 时上界大于平均负载，总有可用节点低于上界
+    fn find_bounded(&self, hash: u64, available: &dyn Fn(&str) -> bool, load: &dyn Fn(&str) -> i64, factor: f64) -> Option<String> {
+        let loads: HashMap<&String, i64> = self.upstreams.iter().filter(|u| available(u)).map(|u| (u, load(u))).collect();
+        if loads.is_empty() {
+            return None;
+        }
+        let total: i64 = loads.values().sum();
+        let bound = (factor * (total + 1) as f64 / loads.len() as f64).ceil() as i64;
+        self.walk(hash).find(|v| loads.get(v).is_some_and(|&l| l < bound)).cloned()
+    }
 }
 
 /// 无锁 IP 哈希负载均衡器
@@ -60,8 +126,14 @@ pub struct IpHashBalancer {
 }
 
 impl IpHashBalancer {
+    /// 使用 configure_ring 设置的虚拟节点数与哈希函数（默认每个节点 150 个虚拟节点、xxhash）
     pub fn new(upstreams: Vec<String>) -> Self {
-        let state = BalancerState::build(upstreams, 150); // 每个节点 150 个虚拟节点
+        let (virtual_nodes, hash_function) = ring_config();
+        Self::with_ring(upstreams, virtual_nodes, hash_function)
+    }
+
+    pub fn with_ring(upstreams: Vec<String>, virtual_nodes: usize, hash_function: HashFunction) -> Self {
+        let state = BalancerState::build(upstreams, virtual_nodes.max(1), hash_function);
         Self {
             state: ArcSwap::from_pointee(state),
         }
@@ -74,13 +146,24 @@ impl IpHashBalancer {
             Some(addr) => addr.ip().to_string(),
             None => "127.0.0.1".to_string(),
         };
-        let hash = BalancerState::hash(&ip_str);
+        let hash = state.hash(&ip_str);
         state.find_upstream(hash)
     }
 
+    /// 同 select，但跳过不可用节点与负载超出上界的节点
+    pub fn select_available(&self, client_ip: Option<&SocketAddr>, available: &dyn Fn(&str) -> bool) -> Option<String> {
+        let ip_str = match client_ip {
+            Some(addr) => addr.ip().to_string(),
+            None => "127.0.0.1".to_string(),
+        };
+        let state = self.state.load();
+        state.find_bounded(state.hash(&ip_str), available, &in_flight, load_factor())
+    }
+
     /// 更新所有 upstreams
     pub fn update_upstreams(&self, new_upstreams: Vec<String>) {
-        let new_state = BalancerState::build(new_upstreams, self.state.load().virtual_nodes);
+        let state = self.state.load();
+        let new_state = BalancerState::build(new_upstreams, state.virtual_nodes, state.hash_function);
         self.state.store(Arc::new(new_state));
     }
 
@@ -113,6 +196,20 @@ impl LoadBalancer for IpHashBalancer {
     fn select(&self, client_ip: Option<&SocketAddr>) -> Option<String> {
         self.select(client_ip)
     }
+
+    fn select_available(&self, client_ip: Option<&SocketAddr>, available: &dyn Fn(&str) -> bool) -> Option<String> {
+        IpHashBalancer::select_available(self, client_ip, available)
+    }
+
+    fn snapshot(&self) -> BalancerSnapshot {
+        BalancerSnapshot {
+            strategy: "iphash",
+            upstreams: self.get_upstreams()
+                .into_iter()
+                .map(|url| UpstreamSnapshot { url, weight: 1 })
+                .collect(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -158,4 +255,57 @@ mod tests {
         // 更新后应该仍然能选择到upstream
         assert!(updated.is_some());
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_unavailable_node_fails_over() {
+        let balancer = IpHashBalancer::new(vec![
+            "http://localhost:30000".to_string(),
+            "http://localhost:30001".to_string(),
+            "http://localhost:30002".to_string(),
+        ]);
+
+        let ip = std::net::SocketAddr::new(std::net::IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 7)), 8080);
+        let original = balancer.select(Some(&ip)).unwrap();
+        assert_eq!(balancer.select_available(Some(&ip), &|_| true), Some(original.clone()));
+
+        let failover = balancer.select_available(Some(&ip), &|u| u != original).unwrap();
+        assert_ne!(failover, original);
+        assert_eq!(balancer.select_available(Some(&ip), &|_| false), None);
+    }
+
+    #[test]
+    fn test_bounded_load_spills_to_next_node() {
+        let upstreams: Vec<String> = (0..3).map(|i| format!("http://bounded-{}:80", i)).collect();
+        let state = BalancerState::build(upstreams.clone(), 150, HashFunction::Xxhash);
+        let hash = state.hash("10.0.0.8");
+        let primary = state.walk(hash).next().unwrap().clone();
+        let next = state.walk(hash).find(|u| **u != primary).unwrap().clone();
+
+        // 负载均衡时保持亲和
+        let idle = |_: &str| 0;
+        assert_eq!(state.find_bounded(hash, &|_| true, &idle, 1.25), Some(primary.clone()));
+
+        // 主节点负载达到上界 ceil(1.25 * 7 / 3) = 3 时顺延到环上的下一个节点
+        let hot = |u: &str| if u == primary { 6 } else { 0 };
+        assert_eq!(state.find_bounded(hash, &|_| true, &hot, 1.25), Some(next.clone()));
+
+        // 主节点不可用时同样顺延；负载均等时上界 ceil(1.25 * 31 / 3) = 13 高于各节点负载，仍返回亲和节点
+        let all_hot = |_: &str| 10;
+        assert_eq!(state.find_bounded(hash, &|u| u != primary, &idle, 1.25), Some(next));
+        assert_eq!(state.find_bounded(hash, &|_| true, &all_hot, 1.25), Some(primary));
+        assert_eq!(state.find_bounded(hash, &|_| false, &idle, 1.25), None);
+    }
+
+    #[test]
+    fn test_ring_config() {
+        let upstreams: Vec<String> = (0..4).map(|i| format!("http://ring-{}:80", i)).collect();
+        for hash_function in [HashFunction::Xxhash, HashFunction::Fnv] {
+            let balancer = IpHashBalancer::with_ring(upstreams.clone(), 20, hash_function);
+            let state = balancer.state.load();
+            assert_eq!(state.hash_ring.len(), 80);
+            // 稳定哈希：同样的配置总是得到同样的环
+            let again = BalancerState::build(upstreams.clone(), 20, hash_function);
+            assert_eq!(state.hash_ring, again.hash_ring);
+        }
+    }
+}
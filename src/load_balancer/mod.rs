@@ -1,12 +1,46 @@
 pub mod round_robin;
 pub mod weighted_random;
 pub mod ip_hash;
+pub mod hash;
+pub mod registry;
+pub mod traffic;
 
-use std::sync::Arc;
 use std::net::SocketAddr;
+use serde::Serialize;
 
 pub trait LoadBalancer: Send + Sync {
     fn select(&self, client_ip: Option<&SocketAddr>) -> Option<String>;
+
+    /// 仅在 available 返回 true 的节点中选择；默认按 select 的顺序逐个尝试
+    fn select_available(&self, client_ip: Option<&SocketAddr>, available: &dyn Fn(&str) -> bool) -> Option<String> {
+        let first = self.select(client_ip)?;
+        if available(&first) {
+            return Some(first);
+        }
+        let attempts = self.snapshot().upstreams.len();
+        (1..attempts).find_map(|_| self.select(client_ip).filter(|u| available(u)))
+    }
+
+    /// 当前状态的只读快照
+    fn snapshot(&self) -> BalancerSnapshot;
+
+    /// 运行时调整某个上游的权重，返回是否生效（不支持权重的策略返回 false）
+    fn set_weight(&self, _upstream: &str, _weight: u32) -> bool {
+        false
+    }
+}
+
+/// 负载均衡器快照：策略及其持有的上游
+#[derive(Debug, Clone, Serialize)]
+pub struct BalancerSnapshot {
+    pub strategy: &'static str,
+    pub upstreams: Vec<UpstreamSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpstreamSnapshot {
+    pub url: String,
+    pub weight: u32,
 }
 
 pub use round_robin::RoundRobinBalancer;
@@ -0,0 +1,104 @@
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use std::sync::Arc;
+
+use crate::load_balancer::{IpHashBalancer, LoadBalancer, RoundRobinBalancer, WeightedRandomBalancer, WeightedUpstream};
+
+/// 按上游列表创建负载均衡器
+pub type Factory = Arc<dyn Fn(&[String]) -> Arc<dyn LoadBalancer + Send + Sync> + Send + Sync>;
+
+// 策略名 -> 构造函数，内置 robin / random / iphash
+static REGISTRY: Lazy<DashMap<String, Factory>> = Lazy::new(|| {
+    let registry: DashMap<String, Factory> = DashMap::new();
+    registry.insert("robin".to_string(), Arc::new(|upstreams| Arc::new(RoundRobinBalancer::new(upstreams.to_vec()))));
+    registry.insert(
+        "random".to_string(),
+        Arc::new(|upstreams| {
+            Arc::new(WeightedRandomBalancer::new(
+                upstreams.iter().map(|u| WeightedUpstream { url: u.clone(), weight: 1 }).collect(),
+            ))
+        }),
+    );
+    registry.insert("iphash".to_string(), Arc::new(|upstreams| Arc::new(IpHashBalancer::new(upstreams.to_vec()))));
+    registry
+});
+
+/// 注册自定义策略，路由中以 strategy = "<name>" 引用；须在加载路由配置之前调用。
+/// 名称已被占用（包括内置策略）时不覆盖并返回 false
+pub fn register<F>(name: &str, factory: F) -> bool
+where
+    F: Fn(&[String]) -> Arc<dyn LoadBalancer + Send + Sync> + Send + Sync + 'static,
+{
+    match REGISTRY.entry(name.to_string()) {
+        dashmap::Entry::Occupied(_) => false,
+        dashmap::Entry::Vacant(entry) => {
+            entry.insert(Arc::new(factory));
+            true
+        }
+    }
+}
+
+pub fn is_registered(name: &str) -> bool {
+    REGISTRY.contains_key(name)
+}
+
+/// 已注册的策略名（排序后），用于校验错误提示
+pub fn names() -> Vec<String> {
+    let mut names: Vec<String> = REGISTRY.iter().map(|entry| entry.key().clone()).collect();
+    names.sort();
+    names
+}
+
+/// 按策略名创建负载均衡器，未注册的策略返回 None
+pub fn create(name: &str, upstreams: &[String]) -> Option<Arc<dyn LoadBalancer + Send + Sync>> {
+    // 先取出构造函数再调用，避免构造期间持有分片锁
+    let factory = REGISTRY.get(name).map(|entry| entry.value().clone())?;
+    Some(factory(upstreams))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load_balancer::{BalancerSnapshot, UpstreamSnapshot};
+    use std::net::SocketAddr;
+
+    // 总是选择第一个上游
+    struct First(Vec<String>);
+
+    impl LoadBalancer for First {
+        fn select(&self, _client_ip: Option<&SocketAddr>) -> Option<String> {
+            self.0.first().cloned()
+        }
+
+        fn snapshot(&self) -> BalancerSnapshot {
+            BalancerSnapshot {
+                strategy: "first",
+                upstreams: self.0.iter().map(|u| UpstreamSnapshot { url: u.clone(), weight: 1 }).collect(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_register_custom_strategy() {
+        assert!(!register("robin", |upstreams| Arc::new(First(upstreams.to_vec()))));
+        assert!(!is_registered("first"));
+        assert!(register("first", |upstreams| Arc::new(First(upstreams.to_vec()))));
+        assert!(!register("first", |upstreams| Arc::new(First(upstreams.to_vec()))));
+        assert!(names().iter().any(|n| n == "first"));
+
+        let upstreams = vec!["http://a:1".to_string(), "http://b:1".to_string()];
+        let balancer = create("first", &upstreams).unwrap();
+        assert_eq!(balancer.select(None).as_deref(), Some("http://a:1"));
+        assert_eq!(balancer.snapshot().strategy, "first");
+        assert_eq!(create("robin", &upstreams).unwrap().snapshot().strategy, "robin");
+        assert!(create("missing", &upstreams).is_none());
+
+        let rule = crate::config::RouteRule {
+            prefix: vec!["/api".to_string()],
+            upstream: upstreams,
+            strategy: "first".to_string(),
+            ..Default::default()
+        };
+        assert!(rule.validation_errors().iter().all(|e| !e.contains("负载均衡策略")));
+    }
+}
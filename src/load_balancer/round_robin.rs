@@ -2,7 +2,7 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use arc_swap::ArcSwap;
 use std::net::SocketAddr;
-use crate::load_balancer::LoadBalancer;
+use crate::load_balancer::{BalancerSnapshot, LoadBalancer, UpstreamSnapshot};
 
 #[derive(Debug)]
 pub struct RoundRobinBalancer {
@@ -39,4 +39,14 @@ impl LoadBalancer for RoundRobinBalancer {
         let index = self.current.fetch_add(1, Ordering::Relaxed) % ups.len();
         ups.get(index).cloned()
     }
+
+    fn snapshot(&self) -> BalancerSnapshot {
+        BalancerSnapshot {
+            strategy: "robin",
+            upstreams: self.get_upstreams()
+                .iter()
+                .map(|url| UpstreamSnapshot { url: url.clone(), weight: 1 })
+                .collect(),
+        }
+    }
 }
@@ -0,0 +1,101 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::RouteRule;
+use crate::upstream::{self, UpstreamStatus};
+
+/// 所有负载均衡器在同一时刻的流量分布快照
+#[derive(Debug, Clone, Serialize)]
+pub struct TrafficSnapshot {
+    // 生成时间（Unix 毫秒）
+    pub taken_at_ms: u64,
+    pub balancers: Vec<BalancerTraffic>,
+}
+
+/// 单个负载均衡器（按 strategy:upstreams 键）的上游集合
+#[derive(Debug, Clone, Serialize)]
+pub struct BalancerTraffic {
+    pub key: String,
+    pub strategy: &'static str,
+    pub upstreams: Vec<UpstreamTraffic>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpstreamTraffic {
+    pub weight: u32,
+    // 处于完整轮转中：未摘流、未在预热且未熔断
+    pub healthy: bool,
+    // 新请求预计分到的比例：按权重在健康节点间分摊，不健康或权重为 0 的节点为 0
+    pub share: f64,
+    #[serde(flatten)]
+    pub status: UpstreamStatus,
+}
+
+/// 汇总路由引用的全部负载均衡器（尚未产生流量的路由也会先创建）。
+/// 同一上游的运行时状态只读取一次，出现在多个负载均衡器中时取值一致
+pub fn snapshot(route_rules: &[RouteRule]) -> TrafficSnapshot {
+    crate::proxy::ensure_balancers(route_rules);
+    let balancers = crate::proxy::balancer_snapshots();
+
+    let mut statuses: HashMap<String, UpstreamStatus> = HashMap::new();
+    for (_, balancer) in &balancers {
+        for u in &balancer.upstreams {
+            statuses.entry(u.url.clone()).or_insert_with(|| upstream::status(&u.url));
+        }
+    }
+
+    let balancers = balancers
+        .into_iter()
+        .map(|(key, balancer)| {
+            let healthy = |status: &UpstreamStatus| !status.draining && !status.warming && !status.ejected;
+            let total: u64 = balancer
+                .upstreams
+                .iter()
+                .filter(|u| healthy(&statuses[&u.url]))
+                .map(|u| u.weight as u64)
+                .sum();
+            let upstreams = balancer
+                .upstreams
+                .into_iter()
+                .map(|u| {
+                    let status = statuses[&u.url].clone();
+                    let healthy = healthy(&status);
+                    let share = if healthy && total > 0 { u.weight as f64 / total as f64 } else { 0.0 };
+                    UpstreamTraffic { weight: u.weight, healthy, share, status }
+                })
+                .collect();
+            BalancerTraffic { key, strategy: balancer.strategy, upstreams }
+        })
+        .collect();
+
+    let taken_at_ms = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+    TrafficSnapshot { taken_at_ms, balancers }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_excludes_unhealthy_from_share() {
+        let upstreams = vec!["http://traffic-a:1".to_string(), "http://traffic-b:1".to_string(), "http://traffic-c:1".to_string()];
+        let rule = RouteRule {
+            prefix: vec!["/traffic".to_string()],
+            upstream: upstreams.clone(),
+            strategy: "random".to_string(),
+            ..Default::default()
+        };
+        upstream::set_draining(&upstreams[2], true);
+        upstream::record_result(&upstreams[0], true);
+
+        let snapshot = snapshot(std::slice::from_ref(&rule));
+        let balancer = snapshot.balancers.iter().find(|b| b.key == format!("random:{}", upstreams.join(","))).unwrap();
+        assert_eq!(balancer.strategy, "random");
+        let shares: Vec<f64> = balancer.upstreams.iter().map(|u| u.share).collect();
+        assert_eq!(shares, vec![0.5, 0.5, 0.0]);
+        assert!(!balancer.upstreams[2].healthy);
+        assert_eq!(balancer.upstreams[0].status.requests, 1);
+        assert_eq!(balancer.upstreams[0].status.failures, 1);
+    }
+}
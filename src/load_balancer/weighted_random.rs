@@ -2,7 +2,7 @@ use std::net::SocketAddr;
 use rand::Rng;
 use arc_swap::ArcSwap;
 use std::sync::Arc;
-use crate::load_balancer::LoadBalancer;
+use crate::load_balancer::{BalancerSnapshot, LoadBalancer, UpstreamSnapshot};
 
 /// 单个上游节点及权重
 #[derive(Debug, Clone)]
@@ -24,11 +24,10 @@ impl WeightedRandomBalancerInner {
         let mut prefix_sums = Vec::with_capacity(upstreams.len());
         let mut total_weight = 0;
 
+        // 与 upstreams 一一对应；权重为 0 的节点前缀和不增长，永远不会被选中
         for u in &upstreams {
-            if u.weight > 0 {  // 过滤掉权重为0的节点
-                total_weight += u.weight;
-                prefix_sums.push(total_weight);
-            }
+            total_weight += u.weight;
+            prefix_sums.push(total_weight);
         }
 
         Self {
@@ -47,10 +46,30 @@ impl WeightedRandomBalancerInner {
         let mut rng = rand::thread_rng();
         let random_weight = rng.gen_range(1..=self.total_weight);
 
-        match self.prefix_sums.binary_search(&random_weight) {
-            Ok(idx) => Some(self.upstreams[idx].url.clone()),
-            Err(idx) => Some(self.upstreams[idx].url.clone()),
+        // 第一个前缀和 >= random_weight 的节点
+        let idx = self.prefix_sums.partition_point(|&sum| sum < random_weight);
+        Some(self.upstreams[idx].url.clone())
+    }
+
+    /// 在可用节点中按权重随机选择，O(n)
+    pub fn select_available(&self, available: &dyn Fn(&str) -> bool) -> Option<String> {
+        let candidates: Vec<&WeightedUpstream> = self.upstreams
+            .iter()
+            .filter(|u| u.weight > 0 && available(&u.url))
+            .collect();
+        let total: u32 = candidates.iter().map(|u| u.weight).sum();
+        if total == 0 {
+            return None;
         }
+
+        let mut remaining = rand::thread_rng().gen_range(1..=total);
+        for u in candidates {
+            if remaining <= u.weight {
+                return Some(u.url.clone());
+            }
+            remaining -= u.weight;
+        }
+        None
     }
 }
 
@@ -73,17 +92,51 @@ impl WeightedRandomBalancer {
         self.inner.load().select()
     }
 
+    /// 获取当前节点及权重
+    pub fn get_upstreams(&self) -> Vec<WeightedUpstream> {
+        self.inner.load().upstreams.clone()
+    }
+
     /// 更新上游节点列表，线程安全
     pub fn update(&self, new_upstreams: Vec<WeightedUpstream>) {
         let new_inner = WeightedRandomBalancerInner::new(new_upstreams);
         self.inner.store(Arc::new(new_inner));
     }
+
+    /// 修改单个节点的权重（0 表示不再分配新请求），节点不存在时返回 false
+    pub fn set_weight(&self, url: &str, weight: u32) -> bool {
+        let mut upstreams = self.get_upstreams();
+        let Some(target) = upstreams.iter_mut().find(|u| u.url == url) else {
+            return false;
+        };
+        target.weight = weight;
+        self.update(upstreams);
+        true
+    }
 }
 
 impl LoadBalancer for WeightedRandomBalancer {
     fn select(&self, _client_ip: Option<&SocketAddr>) -> Option<String> {
         self.select_inner()
     }
+
+    fn snapshot(&self) -> BalancerSnapshot {
+        BalancerSnapshot {
+            strategy: "random",
+            upstreams: self.get_upstreams()
+                .into_iter()
+                .map(|u| UpstreamSnapshot { url: u.url, weight: u.weight })
+                .collect(),
+        }
+    }
+
+    fn select_available(&self, _client_ip: Option<&SocketAddr>, available: &dyn Fn(&str) -> bool) -> Option<String> {
+        self.inner.load().select_available(available)
+    }
+
+    fn set_weight(&self, upstream: &str, weight: u32) -> bool {
+        WeightedRandomBalancer::set_weight(self, upstream, weight)
+    }
 }
 
 #[cfg(test)]
@@ -105,9 +158,8 @@ mod tests {
         }
 
         // 检查大致比例
-        println!("{:?}", counts);
-        assert!(counts["http://localhost:30000"] < counts["http://localhost:30001"]);
-        assert!(counts["http://localhost:30001"] < counts["http://localhost:30002"]);
+        assert!(counts["http://localhost:30000"] < counts["http://localhost:30001"], "{:?}", counts);
+        assert!(counts["http://localhost:30001"] < counts["http://localhost:30002"], "{:?}", counts);
     }
 
     #[test]
@@ -129,4 +181,37 @@ mod tests {
         }
         assert!(urls.iter().any(|u| u == "http://localhost:30002"));
     }
+
+    #[test]
+    fn test_zero_weight_drains_node() {
+        let balancer = WeightedRandomBalancer::new(vec![
+            WeightedUpstream { url: "http://localhost:30000".to_string(), weight: 1 },
+            WeightedUpstream { url: "http://localhost:30001".to_string(), weight: 1 },
+            WeightedUpstream { url: "http://localhost:30002".to_string(), weight: 1 },
+        ]);
+
+        assert!(balancer.set_weight("http://localhost:30001", 0));
+        assert!(!balancer.set_weight("http://unknown", 5));
+        for _ in 0..1000 {
+            assert_ne!(balancer.select(None).unwrap(), "http://localhost:30001");
+        }
+
+        assert!(balancer.set_weight("http://localhost:30000", 0));
+        assert!(balancer.set_weight("http://localhost:30002", 0));
+        assert_eq!(balancer.select(None), None);
+    }
+
+    #[test]
+    fn test_select_available_skips_filtered() {
+        let balancer = WeightedRandomBalancer::new(vec![
+            WeightedUpstream { url: "http://localhost:30000".to_string(), weight: 5 },
+            WeightedUpstream { url: "http://localhost:30001".to_string(), weight: 1 },
+        ]);
+
+        for _ in 0..200 {
+            let url = balancer.select_available(None, &|u| u != "http://localhost:30000");
+            assert_eq!(url.as_deref(), Some("http://localhost:30001"));
+        }
+        assert_eq!(balancer.select_available(None, &|_| false), None);
+    }
 }
@@ -0,0 +1,112 @@
+use once_cell::sync::OnceCell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tracing_subscriber::{fmt, prelude::*, reload, EnvFilter, Registry};
+
+type FilterHandle = reload::Handle<EnvFilter, Registry>;
+
+// 可热更新的日志过滤器句柄
+static FILTER_HANDLE: OnceCell<FilterHandle> = OnceCell::new();
+// 过滤器变更代数，用于防止过期的定时恢复覆盖更新的设置
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// 初始化日志：若无 RUST_LOG 则默认 info，过滤器支持运行时替换
+pub fn init() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let (filter_layer, handle) = reload::Layer::new(filter);
+    // 过滤器只作用于日志输出层，tokio-console 需要不受过滤的 runtime 事件
+    let registry = tracing_subscriber::registry().with(fmt::layer().with_filter(filter_layer));
+
+    #[cfg(feature = "tokio-console")]
+    let registry = registry.with(console_subscriber::spawn());
+
+    registry.init();
+    let _ = FILTER_HANDLE.set(handle);
+}
+
+/// 当前生效的过滤规则
+pub fn current_filter() -> Option<String> {
+    current(FILTER_HANDLE.get()?)
+}
+
+fn current(handle: &FilterHandle) -> Option<String> {
+    handle.with_current(|f| f.to_string()).ok()
+}
+
+/// 替换过滤规则；指定 ttl 时到期后自动恢复为替换前的规则
+pub fn set_filter(directives: &str, ttl: Option<Duration>) -> Result<String, String> {
+    let handle = FILTER_HANDLE.get().ok_or("日志未初始化")?;
+    apply(handle, directives, ttl)
+}
+
+// 解析规则并替换到给定的过滤器句柄，返回替换前的规则；规则非法时不做任何改动
+fn apply(handle: &FilterHandle, directives: &str, ttl: Option<Duration>) -> Result<String, String> {
+    let new_filter = EnvFilter::try_new(directives).map_err(|e| e.to_string())?;
+    let previous = current(handle).unwrap_or_else(|| "info".to_string());
+
+    handle.reload(new_filter).map_err(|e| e.to_string())?;
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    tracing::info!("日志过滤规则已更新: {} (ttl: {:?})", directives, ttl);
+
+    if let Some(ttl) = ttl {
+        let (handle, restore) = (handle.clone(), previous.clone());
+        tokio::spawn(async move {
+            tokio::time::sleep(ttl).await;
+            // 期间有新的变更则放弃恢复
+            if GENERATION.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            if let Ok(filter) = EnvFilter::try_new(&restore) {
+                let _ = handle.reload(filter);
+                tracing::info!("日志过滤规则已恢复: {}", restore);
+            }
+        });
+    }
+
+    Ok(previous)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing::Level;
+
+    // 与 init 相同的结构，但只在当前线程生效且不输出
+    fn subscriber() -> (impl tracing::Subscriber + Send + Sync, FilterHandle) {
+        let (filter_layer, handle) = reload::Layer::new(EnvFilter::new("info"));
+        let subscriber = Registry::default().with(fmt::layer().with_writer(std::io::sink).with_filter(filter_layer));
+        (subscriber, handle)
+    }
+
+    #[tokio::test]
+    async fn test_apply_filter() {
+        let (subscriber, handle) = subscriber();
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let proxy_debug = || tracing::enabled!(target: "helios::proxy", Level::DEBUG);
+        let other_info = || tracing::enabled!(target: "helios::admin", Level::INFO);
+        assert!(!proxy_debug());
+        assert!(other_info());
+
+        // 非法的规则被拒绝，原规则保持不变
+        assert!(apply(&handle, "info,helios::proxy=loud", None).is_err());
+        assert_eq!(current(&handle).as_deref(), Some("info"));
+
+        // 按模块调整级别
+        assert_eq!(apply(&handle, "warn,helios::proxy=debug", None).unwrap(), "info");
+        tracing::callsite::rebuild_interest_cache();
+        assert!(proxy_debug());
+        assert!(!other_info());
+
+        // 到期后恢复为替换前的规则
+        assert!(apply(&handle, "error", Some(Duration::from_millis(50))).is_ok());
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let restored = current(&handle).unwrap();
+        assert!(restored.contains("helios::proxy=debug") && restored.contains("warn"), "{}", restored);
+
+        // 到期前有新的变更时不再恢复
+        assert!(apply(&handle, "error", Some(Duration::from_millis(50))).is_ok());
+        assert!(apply(&handle, "debug", None).is_ok());
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(current(&handle).as_deref(), Some("debug"));
+    }
+}
@@ -1,47 +1,75 @@
-use axum::{Router, routing::get, Extension};
-use tokio::net::TcpListener;
-use tracing_subscriber::EnvFilter;
-use std::net::SocketAddr;
-
-mod proxy;
-mod auth;
-mod config;
-mod metrics;
-mod rate_limit;
-mod path_matcher;
-mod load_balancer;
+use helios::{bench, cli, config, lint, logging, GatewayBuilder};
+use std::path::Path;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // 初始化日志：若无 RUST_LOG 则默认 info
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"))
-        )
-        .init();
-    // 加载环境配置
-    let settings = config::load_settings()?;
-    // 构建速率限制器（全局与每客户端），注入到扩展
-    let rate_limits = rate_limit::init_rate_limits(&settings);
+    let command = match cli::parse(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if command == cli::Command::Help {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
+
+    // 初始化日志：若无 RUST_LOG 则默认 info，支持经管理端动态调整
+    logging::init();
 
-    // 加载路由前缀规则，并注入扩展
-    let route_rules = config::load_route_rules().unwrap_or_default();
+    match &command {
+        cli::Command::Lint { dir } => {
+            // 设置加载失败时只检查路由
+            let settings = config::load_settings().map_err(|e| eprintln!("设置加载失败，跳过设置检查: {}\n", e)).ok();
+            let findings = lint::lint_dir(Path::new(dir), settings.as_ref());
+            for finding in &findings {
+                println!("{:5} {}: {}", finding.level.as_str(), finding.location, finding.message);
+                if let Some(suggestion) = &finding.suggestion {
+                    println!("      建议: {}", suggestion);
+                }
+            }
+            println!("共 {} 项问题", findings.len());
+            std::process::exit(if findings.is_empty() { 0 } else { 1 });
+        }
+        cli::Command::Migrate { dir, write } => {
+            let migrated = lint::migrate_dir(Path::new(dir), *write).map_err(anyhow::Error::msg)?;
+            for (path, changes) in &migrated {
+                for change in changes {
+                    println!("{}:{}: {}", path.display(), change.line, change.description);
+                }
+            }
+            match (migrated.is_empty(), write) {
+                (true, _) => println!("路由文件已是当前格式"),
+                (false, true) => println!("已改写 {} 个文件", migrated.len()),
+                (false, false) => println!("以上改动未写入，加 --write 改写文件"),
+            }
+            return Ok(());
+        }
+        _ => {}
+    }
 
-    // 路由
-    let app = Router::new()
-        .route("/", get(|| async { "Rust Gateway is running 🚀" }))
-        .route("/metrics", get(metrics::metrics_handler))
-        .merge(proxy::router())
-        .layer(axum::middleware::from_fn(metrics::prometheus_middleware))
-        .layer(Extension(settings.clone()))
-        .layer(Extension(rate_limits.clone()))
-        .layer(Extension(route_rules));
+    let dev = command == cli::Command::Dev;
+    // 加载环境配置（开发模式下缺少的必填项使用开发默认值）
+    let settings = if dev { helios::dev::load_settings()? } else { config::load_settings()? };
+    // 加载路由前缀规则，并注入扩展（配置有误则直接启动失败）
+    let route_rules = config::load_route_rules()?;
 
-    // 启动服务（带客户端地址信息）
-    let listener = TcpListener::bind(&settings.gateway_bind).await?;
-    tracing::info!("🚀 Gateway listening on http://{}", listener.local_addr()?);
+    if let cli::Command::Bench(opts) = command {
+        // 压测时默认不输出逐请求日志
+        if std::env::var("RUST_LOG").is_err() {
+            let _ = logging::set_filter("warn", None);
+        }
+        return bench::run(opts, settings, route_rules).await;
+    }
 
-    let make_svc = app.into_make_service_with_connect_info::<SocketAddr>();
-    axum::serve(listener, make_svc).await?;
-    Ok(())
+    let mut builder = GatewayBuilder::new(settings).routes(route_rules);
+    if dev {
+        // 开发模式默认输出网关自身的 debug 日志
+        if std::env::var("RUST_LOG").is_err() {
+            let _ = logging::set_filter("info,helios=debug", None);
+        }
+        builder = builder.dev();
+    }
+    builder.build()?.serve().await
 }
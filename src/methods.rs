@@ -0,0 +1,131 @@
+//! 路由允许的方法：路由声明 methods 后，网关在本地应答 OPTIONS（附 Allow 头），
+//! 其他未声明的方法直接返回 405，不再转发给上游。允许 GET 时隐含允许 HEAD，OPTIONS 始终允许。
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderValue, Method, Response, StatusCode},
+    response::IntoResponse,
+};
+
+use crate::config::RouteRule;
+use crate::problem::{Problem, ProblemType};
+
+/// 路由实际允许的方法（大写、去重，按声明顺序），未声明 methods 时为 None
+pub fn allowed(rule: &RouteRule) -> Option<Vec<String>> {
+    let declared = rule.methods.as_ref()?;
+    let mut methods: Vec<String> = Vec::with_capacity(declared.len() + 2);
+    let mut add = |method: &str| {
+        let method = method.trim().to_ascii_uppercase();
+        if !methods.contains(&method) {
+            methods.push(method);
+        }
+    };
+    for method in declared {
+        add(method);
+    }
+    if declared.iter().any(|m| m.eq_ignore_ascii_case("GET")) {
+        add("HEAD");
+    }
+    add("OPTIONS");
+    Some(methods)
+}
+
+/// 按路由的 methods 在本地应答：OPTIONS（CORS 预检除外）返回 204 与 Allow，未允许的方法返回 405；
+/// 其余请求返回 None 继续处理
+pub fn check(rule: &RouteRule, req: &Request<Body>) -> Option<Response<Body>> {
+    let methods = allowed(rule)?;
+    let allow = HeaderValue::from_str(&methods.join(", ")).ok()?;
+    let method = req.method();
+    if method == Method::OPTIONS {
+        // 配置了 CORS 的路由，预检交给 cors 阶段应答
+        if rule.cors.is_some() && req.headers().contains_key(header::ACCESS_CONTROL_REQUEST_METHOD) {
+            return None;
+        }
+        let mut resp = Response::new(Body::empty());
+        *resp.status_mut() = StatusCode::NO_CONTENT;
+        resp.headers_mut().insert(header::ALLOW, allow);
+        return Some(resp);
+    }
+    if methods.iter().any(|m| m == method.as_str()) {
+        return None;
+    }
+    Some(
+        Problem::new(StatusCode::METHOD_NOT_ALLOWED, ProblemType::MethodNotAllowed)
+            .detail(format!("Method {} is not allowed for this route", method))
+            .header(header::ALLOW, allow)
+            .into_response(),
+    )
+}
+
+pub fn validation_errors(rule: &RouteRule) -> Vec<String> {
+    let mut errors = Vec::new();
+    let Some(methods) = &rule.methods else {
+        return errors;
+    };
+    if methods.is_empty() {
+        errors.push("methods不能为空".to_string());
+    }
+    for method in methods {
+        if method.trim().is_empty() || Method::from_bytes(method.trim().as_bytes()).is_err() {
+            errors.push(format!("methods包含非法方法: {}", method));
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(methods: &[&str]) -> RouteRule {
+        RouteRule { methods: Some(methods.iter().map(|m| m.to_string()).collect()), ..Default::default() }
+    }
+
+    fn request(method: &str) -> Request<Body> {
+        Request::builder().method(method).uri("/api/orders").body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn test_allow_and_reject() {
+        assert_eq!(allowed(&RouteRule::default()), None);
+        let rule = rule(&["get", "POST", "GET"]);
+        assert_eq!(allowed(&rule).unwrap(), vec!["GET", "POST", "HEAD", "OPTIONS"]);
+
+        assert!(check(&rule, &request("GET")).is_none());
+        assert!(check(&rule, &request("HEAD")).is_none());
+
+        let options = check(&rule, &request("OPTIONS")).unwrap();
+        assert_eq!(options.status(), StatusCode::NO_CONTENT);
+        assert_eq!(options.headers()[header::ALLOW], "GET, POST, HEAD, OPTIONS");
+
+        let rejected = check(&rule, &request("DELETE")).unwrap();
+        assert_eq!(rejected.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(rejected.headers()[header::ALLOW], "GET, POST, HEAD, OPTIONS");
+
+        assert!(check(&RouteRule::default(), &request("DELETE")).is_none());
+    }
+
+    #[test]
+    fn test_cors_preflight_passes_through() {
+        let mut rule = rule(&["PUT"]);
+        let preflight = || {
+            Request::builder()
+                .method("OPTIONS")
+                .uri("/api/orders")
+                .header(header::ACCESS_CONTROL_REQUEST_METHOD, "PUT")
+                .body(Body::empty())
+                .unwrap()
+        };
+        // 未配置 CORS 时预检同样由本地应答
+        assert_eq!(check(&rule, &preflight()).unwrap().status(), StatusCode::NO_CONTENT);
+        rule.cors = Some(toml::from_str("allow_origins = [\"https://a.example.com\"]").unwrap());
+        assert!(check(&rule, &preflight()).is_none());
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(validation_errors(&rule(&["GET", "PATCH"])).is_empty());
+        assert_eq!(validation_errors(&rule(&[])).len(), 1);
+        assert_eq!(validation_errors(&rule(&["GET", "BAD METHOD", ""])).len(), 2);
+    }
+}
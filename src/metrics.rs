@@ -1,6 +1,8 @@
+use std::collections::HashMap;
 use std::time::Instant;
 
-use prometheus::{Encoder, TextEncoder, IntCounterVec, register_int_counter_vec, register_histogram_vec, HistogramVec};
+use prometheus::core::Collector;
+use prometheus::{Encoder, TextEncoder, IntCounterVec, IntGauge, register_int_counter_vec, register_int_gauge, register_histogram_vec, HistogramVec, GaugeVec, register_gauge_vec, Gauge, register_gauge, IntGaugeVec, register_int_gauge_vec};
 use once_cell::sync::Lazy;
 use axum::{extract::Request, http::StatusCode, middleware::Next, response::IntoResponse};
 
@@ -22,7 +24,331 @@ pub static HTTP_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
     .unwrap()
 });
 
+pub static RATE_LIMITED_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_rate_limited_total",
+        "Requests rejected by rate limiting",
+        &["scope"]
+    )
+    .unwrap()
+});
+
+pub static UPSTREAM_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_upstream_requests_total",
+        "Proxied requests per upstream and outcome",
+        &["upstream", "outcome"]
+    )
+    .unwrap()
+});
+
+pub static RESILIENCE_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_resilience_events_total",
+        "Retries, circuit breaker transitions and fallbacks",
+        &["event", "route", "upstream"]
+    )
+    .unwrap()
+});
+
+pub static HEDGE_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_hedge_events_total",
+        "Hedged requests: issued, won, lost, cancelled losers and hedges skipped for budget",
+        &["event", "route"]
+    )
+    .unwrap()
+});
+
+pub static HEDGE_EXTRA_LOAD_PERCENT: Lazy<Gauge> = Lazy::new(|| {
+    register_gauge!(
+        "gateway_hedge_extra_load_percent",
+        "Hedged requests as a percentage of requests on hedging routes in the current minute"
+    )
+    .unwrap()
+});
+
+pub static UPSTREAM_PROTOCOL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_upstream_protocol_total",
+        "Upstream responses by negotiated HTTP protocol",
+        &["upstream", "protocol"]
+    )
+    .unwrap()
+});
+
+pub static CAPTURE_RECORDS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_capture_records_total",
+        "Captured request records by export outcome (exported, dropped, failed)",
+        &["outcome"]
+    )
+    .unwrap()
+});
+
+pub static FEATURE_FLAG_REFRESH: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_feature_flag_refresh_total",
+        "Feature flag provider refreshes by result (ok, error)",
+        &["result"]
+    )
+    .unwrap()
+});
+
+pub static MIRROR_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_mirror_requests_total",
+        "Mirrored requests sent to shadow upstreams by result (ok, error)",
+        &["route", "result"]
+    )
+    .unwrap()
+});
+
+pub static MIRROR_COMPARISONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_mirror_comparisons_total",
+        "Primary and shadow response comparisons by result (match, mismatch)",
+        &["route", "result"]
+    )
+    .unwrap()
+});
+
+pub static SENDFILE_DELIVERIES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_sendfile_deliveries_total",
+        "Files delivered in place of upstream responses by source (file, s3) and result (ok, not_found, error)",
+        &["route", "source", "result"]
+    )
+    .unwrap()
+});
+
+pub static RESPONSE_TOO_LARGE: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_response_too_large_total",
+        "Upstream responses rejected or cut off for exceeding the route size limit",
+        &["route", "stage"]
+    )
+    .unwrap()
+});
+
+pub static AUTH_SHADOW_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_auth_shadow_verdicts_total",
+        "Would-be authentication verdicts on shadow-mode routes",
+        &["route", "verdict", "reason"]
+    )
+    .unwrap()
+});
+
+pub static BLOCKED_PATH_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_blocked_paths_total",
+        "Requests rejected for suspicious paths (traversal, control or encoded bytes)",
+        &["reason"]
+    )
+    .unwrap()
+});
+
+pub static CORS_PREFLIGHT_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_cors_preflights_total",
+        "CORS preflight requests by cache result (hit, miss, rejected)",
+        &["route", "result"]
+    )
+    .unwrap()
+});
+
+pub static FORWARD_PROXY_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_forward_proxy_connects_total",
+        "CONNECT requests by user and result (allowed, denied, unauthenticated, failed)",
+        &["user", "result"]
+    )
+    .unwrap()
+});
+
+pub static LOAD_SHED_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_load_shed_total",
+        "Low-priority requests rejected while the gateway is overloaded",
+        &["route", "reason"]
+    )
+    .unwrap()
+});
+
+pub static QOS_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_qos_admissions_total",
+        "Admission decisions by priority class (admitted, shed, timeout)",
+        &["class", "result"]
+    )
+    .unwrap()
+});
+
+pub static QOS_QUEUED: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "gateway_qos_queued",
+        "Requests waiting for an admission slot"
+    )
+    .unwrap()
+});
+
+pub static PROCESS_CPU_PERCENT: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "gateway_process_cpu_percent",
+        "Gateway process CPU usage across all cores (0-100)"
+    )
+    .unwrap()
+});
+
+pub static PROCESS_MEMORY_BYTES: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "gateway_process_resident_memory_bytes",
+        "Gateway process resident memory"
+    )
+    .unwrap()
+});
+
+pub static EVENT_LOOP_LAG_MS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "gateway_event_loop_lag_ms",
+        "Delay of a periodic timer beyond its scheduled wake-up"
+    )
+    .unwrap()
+});
+
+pub static UPSTREAM_IN_FLIGHT: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "gateway_upstream_in_flight",
+        "Upstream requests currently in flight"
+    )
+    .unwrap()
+});
+
+pub static TUNNELS_ACTIVE: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "gateway_tunnels_active",
+        "Upgraded connections currently tunneled to upstreams"
+    )
+    .unwrap()
+});
+
+/// 作用域内的 gauge 计数，离开作用域时自动减一
+pub struct GaugeGuard(&'static IntGauge);
+
+impl GaugeGuard {
+    pub fn new(gauge: &'static IntGauge) -> Self {
+        gauge.inc();
+        Self(gauge)
+    }
+}
+
+impl Drop for GaugeGuard {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+pub static SLO_COMPLIANCE: Lazy<GaugeVec> = Lazy::new(|| {
+    register_gauge_vec!(
+        "gateway_slo_compliance",
+        "Share of good requests per route SLO objective over rolling windows",
+        &["route", "objective", "window"]
+    )
+    .unwrap()
+});
+
+pub static SLO_BURN_RATE: Lazy<GaugeVec> = Lazy::new(|| {
+    register_gauge_vec!(
+        "gateway_slo_burn_rate",
+        "Error budget burn rate per route SLO objective over rolling windows",
+        &["route", "objective", "window"]
+    )
+    .unwrap()
+});
+
+pub static ANOMALY_ACTIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_anomaly_actions_total",
+        "Protective actions applied automatically after a route anomaly was detected",
+        &["route", "action"]
+    )
+    .unwrap()
+});
+
+pub static BAN_EVENTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_ban_events_total",
+        "Automatic client bans issued, requests rejected while banned and bans lifted",
+        &["event"]
+    )
+    .unwrap()
+});
+
+pub static BOT_MATCHES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_bot_matches_total",
+        "Requests matched by bot and scanner detection rules",
+        &["route", "rule", "action"]
+    )
+    .unwrap()
+});
+
+pub static TARPIT_HITS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_tarpit_hits_total",
+        "Requests caught by tarpit routes, delayed or rejected at once when the tarpit is full",
+        &["route", "outcome"]
+    )
+    .unwrap()
+});
+
+pub static TARPIT_IN_FLIGHT: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "gateway_tarpit_in_flight",
+        "Requests currently being delayed by tarpit routes"
+    )
+    .unwrap()
+});
+
+pub static WEBSOCKET_CLOSED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_websocket_closed_total",
+        "WebSocket connections closed by the gateway for exceeding route limits",
+        &["route", "reason"]
+    )
+    .unwrap()
+});
+
+pub static ROUTE_BYTES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_route_bytes_total",
+        "Bytes transferred per route, by direction (upload / download)",
+        &["route", "direction"]
+    )
+    .unwrap()
+});
+
+pub static TENANT_BYTES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "gateway_tenant_bytes_total",
+        "Bytes transferred per tenant, by direction (upload / download)",
+        &["tenant", "direction"]
+    )
+    .unwrap()
+});
+
+pub static RATE_LIMIT_KEYS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "gateway_rate_limit_keys",
+        "Keys currently tracked by keyed rate limiters (client / tenant / user)",
+        &["limiter"]
+    )
+    .unwrap()
+});
+
 pub async fn metrics_handler() -> impl IntoResponse {
+    crate::slo::refresh_gauges();
     let encoder = TextEncoder::new();
     let metric_families = prometheus::gather();
     let mut buffer = Vec::new();
@@ -30,6 +356,23 @@ pub async fn metrics_handler() -> impl IntoResponse {
     (StatusCode::OK, [(axum::http::header::CONTENT_TYPE, encoder.format_type().to_string())], buffer)
 }
 
+/// 读取计数器当前所有标签组合的取值，供管理端展示
+pub fn counter_samples(counter: &IntCounterVec) -> Vec<(HashMap<String, String>, u64)> {
+    counter
+        .collect()
+        .iter()
+        .flat_map(|mf| mf.get_metric().iter())
+        .map(|m| {
+            let labels = m
+                .get_label()
+                .iter()
+                .map(|l| (l.name().to_string(), l.value().to_string()))
+                .collect();
+            (labels, m.get_counter().value() as u64)
+        })
+        .collect()
+}
+
 // ===== Prometheus 中间件 =====
 pub async fn prometheus_middleware(req: Request, next: Next) -> impl IntoResponse {
     let method = req.method().to_string();
@@ -45,4 +388,4 @@ pub async fn prometheus_middleware(req: Request, next: Next) -> impl IntoRespons
     }
 
     response
-}
\ No newline at end of file
+}
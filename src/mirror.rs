@@ -0,0 +1,373 @@
+//! 流量镜像：按 sample_rate 把路由的请求复制一份发往影子上游，影子响应不返回给客户端，也不影响主请求的结果。
+//! 开启 compare 后对比主上游与影子上游的响应：状态码、compare_headers 中的响应头，以及规范化后的 JSON 响应体
+//! （忽略对象键顺序、数值写法与 ignore_fields 中的字段；非 JSON 响应体按字节比较），按路由统计一致与不一致的次数，
+//! 并在内存中保留最近的不一致样本供管理端查看，用于重写后的服务在切换前验证行为是否一致。
+//!
+//! 只镜像缓冲了请求体的请求；响应体对比需要缓冲的响应，流式与 HEAD 响应只对比状态码与响应头。
+use axum::{
+    body::Bytes,
+    http::{HeaderMap, HeaderName, Method, StatusCode},
+};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+
+use crate::config::RouteRule;
+use crate::metrics::{MIRROR_COMPARISONS, MIRROR_REQUESTS};
+
+/// 内存中保留的不一致样本条数，超出后丢弃最早的样本
+pub const CAPACITY: usize = 200;
+// 单个样本最多记录的差异项与每个值最多显示的字符数
+const MAX_DIFFERENCES: usize = 20;
+const MAX_VALUE_CHARS: usize = 120;
+
+/// 路由级镜像配置
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MirrorPolicy {
+    // 影子上游
+    pub upstream: String,
+    // 镜像比例 0~1，默认全部镜像
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<f64>,
+    // 是否对比主上游与影子上游的响应，默认关闭（只镜像）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compare: Option<bool>,
+    // 参与对比的响应头（不区分大小写），默认不对比响应头
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compare_headers: Option<Vec<String>>,
+    // 对比 JSON 响应体时忽略的字段（JSON Pointer，* 匹配任意键或下标，如 /items/*/updated_at）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ignore_fields: Option<Vec<String>>,
+    // 不一致样本的保留比例 0~1，默认全部保留
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diff_sample_rate: Option<f64>,
+}
+
+pub fn validation_errors(rule: &RouteRule) -> Vec<String> {
+    let mut errors = Vec::new();
+    let Some(policy) = &rule.mirror else {
+        return errors;
+    };
+    if policy.upstream.trim().is_empty() {
+        errors.push("mirror.upstream不能为空".to_string());
+    }
+    for (field, rate) in [("sample_rate", policy.sample_rate), ("diff_sample_rate", policy.diff_sample_rate)] {
+        if rate.is_some_and(|rate| !(0.0..=1.0).contains(&rate)) {
+            errors.push(format!("mirror.{}必须在0到1之间", field));
+        }
+    }
+    for name in policy.compare_headers.iter().flatten() {
+        if HeaderName::from_bytes(name.as_bytes()).is_err() {
+            errors.push(format!("mirror.compare_headers包含非法头名: {}", name));
+        }
+    }
+    for field in policy.ignore_fields.iter().flatten() {
+        if !field.starts_with('/') {
+            errors.push(format!("mirror.ignore_fields必须是以/开头的JSON Pointer: {}", field));
+        }
+    }
+    if !policy.compare.unwrap_or(false) && (policy.compare_headers.is_some() || policy.ignore_fields.is_some()) {
+        errors.push("mirror.compare_headers与ignore_fields需要开启compare".to_string());
+    }
+    if !crate::buffering::Buffering::for_route(rule).request() {
+        errors.push("mirror需要缓冲请求体，buffering必须为request或full".to_string());
+    }
+    errors
+}
+
+/// 本次请求是否镜像
+pub fn sampled(policy: &MirrorPolicy) -> bool {
+    rand::random::<f64>() < policy.sample_rate.unwrap_or(1.0)
+}
+
+/// 一次响应中参与对比的部分；流式与 HEAD 响应没有响应体
+#[derive(Debug, Clone)]
+pub struct Observed {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Option<Bytes>,
+}
+
+/// 进行中的影子请求，主响应就绪后调用 compare 对比
+pub struct Shadow {
+    policy: MirrorPolicy,
+    route: String,
+    method: String,
+    path: String,
+    response: JoinHandle<Option<Observed>>,
+}
+
+/// 在后台向影子上游发送请求副本；开启 compare 时返回 Shadow，否则读完影子响应后直接丢弃
+pub fn send(
+    policy: &MirrorPolicy,
+    route: &str,
+    method: &Method,
+    path: &str,
+    request: reqwest::RequestBuilder,
+    body_limit: usize,
+) -> Option<Shadow> {
+    let label = route.to_string();
+    let compare = policy.compare.unwrap_or(false);
+    let response = tokio::spawn(async move {
+        let resp = match request.send().await {
+            Ok(resp) => resp,
+            Err(err) => {
+                tracing::debug!(route = %label, "镜像请求失败: {}", err);
+                MIRROR_REQUESTS.with_label_values(&[&label, "error"]).inc();
+                return None;
+            }
+        };
+        let (status, headers) = (resp.status(), resp.headers().clone());
+        let body = match crate::buffering::read_response(resp, body_limit).await {
+            Ok(body) => body,
+            Err(_) => {
+                MIRROR_REQUESTS.with_label_values(&[&label, "error"]).inc();
+                return None;
+            }
+        };
+        MIRROR_REQUESTS.with_label_values(&[&label, "ok"]).inc();
+        compare.then_some(Observed { status, headers, body: Some(body) })
+    });
+    compare.then(|| Shadow { policy: policy.clone(), route: route.to_string(), method: method.to_string(), path: path.to_string(), response })
+}
+
+impl Shadow {
+    /// 在后台等待影子响应并与主响应对比，不阻塞主响应；主响应没有响应体时只对比状态码与响应头
+    pub fn compare(self, primary: Observed) {
+        tokio::spawn(async move {
+            let Ok(Some(shadow)) = self.response.await else {
+                return;
+            };
+            let differences = diff(&self.policy, &primary, &shadow);
+            if differences.is_empty() {
+                MIRROR_COMPARISONS.with_label_values(&[&self.route, "match"]).inc();
+                return;
+            }
+            MIRROR_COMPARISONS.with_label_values(&[&self.route, "mismatch"]).inc();
+            if rand::random::<f64>() < self.policy.diff_sample_rate.unwrap_or(1.0) {
+                record(MirrorDiff {
+                    time: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+                    route: self.route,
+                    method: self.method,
+                    path: self.path,
+                    primary_status: primary.status.as_u16(),
+                    shadow_status: shadow.status.as_u16(),
+                    differences,
+                });
+            }
+        });
+    }
+}
+
+// ===== 对比 =====
+
+/// 主响应与影子响应的差异，每项形如 `status: 200 != 500`、`body/items/0/price: 3 != 4`（左为主上游）
+pub fn diff(policy: &MirrorPolicy, primary: &Observed, shadow: &Observed) -> Vec<String> {
+    let mut differences = Vec::new();
+    if primary.status != shadow.status {
+        differences.push(format!("status: {} != {}", primary.status.as_u16(), shadow.status.as_u16()));
+    }
+    for name in policy.compare_headers.iter().flatten() {
+        let (a, b) = (header_value(&primary.headers, name), header_value(&shadow.headers, name));
+        if a != b {
+            differences.push(format!("header {}: {} != {}", name.to_ascii_lowercase(), a, b));
+        }
+    }
+    if let (Some(a), Some(b)) = (&primary.body, &shadow.body) {
+        match (serde_json::from_slice::<Value>(a), serde_json::from_slice::<Value>(b)) {
+            (Ok(mut a), Ok(mut b)) => {
+                for field in policy.ignore_fields.iter().flatten() {
+                    let tokens: Vec<String> = field.split('/').skip(1).map(|t| t.replace("~1", "/").replace("~0", "~")).collect();
+                    ignore(&mut a, &tokens);
+                    ignore(&mut b, &tokens);
+                }
+                diff_json("body", &a, &b, &mut differences);
+            }
+            _ if a != b => differences.push(format!("body: {} bytes != {} bytes (内容不同)", a.len(), b.len())),
+            _ => {}
+        }
+    }
+    differences.truncate(MAX_DIFFERENCES);
+    differences
+}
+
+fn header_value(headers: &HeaderMap, name: &str) -> String {
+    let values: Vec<&str> = headers.get_all(name).iter().map(|v| v.to_str().unwrap_or("<binary>")).collect();
+    if values.is_empty() { "-".to_string() } else { values.join(", ") }
+}
+
+// 把 JSON Pointer 指向的字段置为忽略：对象中删除该键，数组中替换为 null 以保持下标对齐
+fn ignore(value: &mut Value, tokens: &[String]) {
+    let Some((token, rest)) = tokens.split_first() else {
+        return;
+    };
+    let wildcard = token == "*";
+    if rest.is_empty() {
+        match value {
+            Value::Object(map) if wildcard => map.clear(),
+            Value::Object(map) => {
+                map.remove(token);
+            }
+            Value::Array(items) => {
+                for (i, item) in items.iter_mut().enumerate() {
+                    if wildcard || token.parse() == Ok(i) {
+                        *item = Value::Null;
+                    }
+                }
+            }
+            _ => {}
+        }
+        return;
+    }
+    let children: Vec<&mut Value> = match value {
+        Value::Object(map) if wildcard => map.values_mut().collect(),
+        Value::Object(map) => map.get_mut(token).into_iter().collect(),
+        Value::Array(items) if wildcard => items.iter_mut().collect(),
+        Value::Array(items) => token.parse::<usize>().ok().and_then(|i| items.get_mut(i)).into_iter().collect(),
+        _ => Vec::new(),
+    };
+    for child in children {
+        ignore(child, rest);
+    }
+}
+
+fn diff_json(path: &str, a: &Value, b: &Value, out: &mut Vec<String>) {
+    if out.len() >= MAX_DIFFERENCES {
+        return;
+    }
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, x) in a {
+                diff_json(&format!("{}/{}", path, escape(key)), x, b.get(key).unwrap_or(&Value::Null), out);
+            }
+            for (key, y) in b.iter().filter(|(key, _)| !a.contains_key(*key)) {
+                diff_json(&format!("{}/{}", path, escape(key)), &Value::Null, y, out);
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for i in 0..a.len().max(b.len()) {
+                diff_json(&format!("{}/{}", path, i), a.get(i).unwrap_or(&Value::Null), b.get(i).unwrap_or(&Value::Null), out);
+            }
+        }
+        // 1 与 1.0 视为相同
+        (Value::Number(x), Value::Number(y)) if x.as_f64() == y.as_f64() => {}
+        _ if a != b => out.push(format!("{}: {} != {}", path, show(a), show(b))),
+        _ => {}
+    }
+}
+
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn show(value: &Value) -> String {
+    let text = value.to_string();
+    match text.char_indices().nth(MAX_VALUE_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    }
+}
+
+// ===== 不一致样本 =====
+
+/// 一条不一致样本
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MirrorDiff {
+    // Unix 时间戳（秒）
+    pub time: u64,
+    pub route: String,
+    pub method: String,
+    pub path: String,
+    pub primary_status: u16,
+    pub shadow_status: u16,
+    pub differences: Vec<String>,
+}
+
+static DIFFS: Lazy<Mutex<VecDeque<MirrorDiff>>> = Lazy::new(|| Mutex::new(VecDeque::with_capacity(CAPACITY)));
+
+fn record(diff: MirrorDiff) {
+    let mut diffs = DIFFS.lock().unwrap();
+    if diffs.len() >= CAPACITY {
+        diffs.pop_front();
+    }
+    diffs.push_back(diff);
+}
+
+/// 最近的不一致样本，新样本在前；route 为空时返回所有路由的样本
+pub fn recent(route: Option<&str>, limit: usize) -> Vec<MirrorDiff> {
+    let diffs = DIFFS.lock().unwrap();
+    diffs.iter().rev().filter(|d| route.is_none_or(|route| d.route == route)).take(limit).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn observed(status: u16, content_type: &str, body: Value) -> Observed {
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", content_type.parse().unwrap());
+        Observed { status: StatusCode::from_u16(status).unwrap(), headers, body: Some(Bytes::from(body.to_string())) }
+    }
+
+    #[test]
+    fn test_diff() {
+        let policy = MirrorPolicy {
+            compare: Some(true),
+            compare_headers: Some(vec!["Content-Type".to_string()]),
+            ignore_fields: Some(vec!["/request_id".to_string(), "/items/*/updated_at".to_string()]),
+            ..Default::default()
+        };
+        let primary = observed(200, "application/json", json!({
+            "request_id": "a",
+            "total": 3,
+            "items": [{ "id": 1, "price": 3, "updated_at": 1 }],
+        }));
+        // 键顺序、数值写法与忽略字段的差异不计入
+        let same = observed(200, "application/json", json!({
+            "items": [{ "updated_at": 2, "price": 3.0, "id": 1 }],
+            "total": 3,
+            "request_id": "b",
+        }));
+        assert!(diff(&policy, &primary, &same).is_empty());
+
+        let changed = observed(500, "text/plain", json!({ "total": 4, "items": [{ "id": 1, "price": 5 }, { "id": 2 }], "extra": true }));
+        assert_eq!(
+            diff(&policy, &primary, &changed),
+            vec![
+                "status: 200 != 500",
+                "header content-type: application/json != text/plain",
+                "body/items/0/price: 3 != 5",
+                "body/items/1: null != {\"id\":2}",
+                "body/total: 3 != 4",
+                "body/extra: null != true",
+            ]
+        );
+
+        // 非 JSON 响应体按字节比较；没有响应体时只比较状态码与响应头
+        let text = |body: &'static str| Observed { status: StatusCode::OK, headers: HeaderMap::new(), body: Some(Bytes::from(body)) };
+        assert_eq!(diff(&MirrorPolicy::default(), &text("ok"), &text("not ok")), vec!["body: 2 bytes != 6 bytes (内容不同)"]);
+        let streamed = Observed { body: None, ..text("ok") };
+        assert!(diff(&MirrorPolicy::default(), &streamed, &text("not ok")).is_empty());
+    }
+
+    #[test]
+    fn test_validation() {
+        let rule = |policy: Value, buffering: Option<&str>| RouteRule {
+            mirror: Some(serde_json::from_value(policy).unwrap()),
+            buffering: buffering.map(str::to_string),
+            ..Default::default()
+        };
+        assert!(validation_errors(&rule(json!({ "upstream": "http://v2:8080", "compare": true, "ignore_fields": ["/id"] }), None)).is_empty());
+        let errors = validation_errors(&rule(
+            json!({ "upstream": " ", "sample_rate": 2.0, "compare_headers": ["bad header"], "ignore_fields": ["id"] }),
+            Some("none"),
+        ));
+        assert_eq!(errors.len(), 6, "{:?}", errors);
+    }
+}
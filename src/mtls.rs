@@ -0,0 +1,508 @@
+//! 双向 TLS：配置 tls_cert_file / tls_key_file 后网关以 HTTPS 监听，再配置 tls_client_ca_file 则要求客户端出示
+//! 由这些 CA 签发的证书。证书的 CN、SAN（DNS / 邮箱）与 SPIFFE ID（URI SAN）经 client_cert_identities 映射为
+//! 网关身份（sub、tenant_id、roles 及其他声明），之后的鉴权、分层限流与身份头透传与 JWT 声明完全一致。
+//!
+//! 配置 tls_headers 后，协商出的协议版本、加密套件、SNI 与客户端证书主题以 nginx 风格的请求头（X-SSL-*）转发给上游。
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request},
+    http::{HeaderName, HeaderValue},
+    Router,
+};
+use hyper::body::Incoming;
+use hyper_util::rt::TokioIo;
+use openssl::nid::Nid;
+use openssl::x509::X509;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tower::ServiceExt;
+
+use crate::auth::Claims;
+use crate::config::Settings;
+
+const SPIFFE_SCHEME: &str = "spiffe://";
+
+/// tls_headers 支持的字段
+pub const TLS_HEADER_FIELDS: [&str; 5] = ["protocol", "cipher", "sni", "client_subject", "client_verify"];
+
+/// 证书到身份的映射规则：匹配条件全部满足才命中，按配置顺序取第一条
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CertIdentity {
+    // 匹配条件（至少一项），支持 * 通配：证书 CN、任一 DNS / 邮箱 SAN、SPIFFE ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cn: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub san: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spiffe_id: Option<String>,
+    // 映射得到的身份，可引用 {cn}、{spiffe_id}；sub 默认取 SPIFFE ID，其次为 CN
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    // 角色，以逗号连接后作为 roles 声明
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+    // 其他声明（如 priority、plan）
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub claims: BTreeMap<String, String>,
+}
+
+/// 握手时校验通过的客户端证书，由 HTTPS 监听写入请求扩展
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientCert {
+    // 主题 DN（RFC 4514 格式，如 CN=orders-api,O=Example）
+    pub subject: Option<String>,
+    pub cn: Option<String>,
+    // DNS 与邮箱 SAN
+    pub sans: Vec<String>,
+    pub spiffe_id: Option<String>,
+}
+
+impl ClientCert {
+    pub fn from_der(der: &[u8]) -> Option<Self> {
+        let cert = X509::from_der(der).ok()?;
+        let cn = cert
+            .subject_name()
+            .entries_by_nid(Nid::COMMONNAME)
+            .next()
+            .and_then(|entry| entry.data().to_string().ok());
+        let entries: Vec<_> = cert.subject_name().entries().collect();
+        let subject = entries
+            .iter()
+            .rev()
+            .filter_map(|entry| {
+                let key = entry.object().nid().short_name().ok()?;
+                Some(format!("{}={}", key, escape_dn(&entry.data().to_string().ok()?)))
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        let mut parsed = Self { subject: (!subject.is_empty()).then_some(subject), cn, ..Default::default() };
+        for name in cert.subject_alt_names().iter().flatten() {
+            if let Some(san) = name.dnsname().or(name.email()) {
+                parsed.sans.push(san.to_string());
+            } else if let Some(uri) = name.uri().filter(|uri| uri.starts_with(SPIFFE_SCHEME)) {
+                parsed.spiffe_id.get_or_insert_with(|| uri.to_string());
+            }
+        }
+        Some(parsed)
+    }
+
+    fn render(&self, template: &str) -> String {
+        template
+            .replace("{cn}", self.cn.as_deref().unwrap_or_default())
+            .replace("{spiffe_id}", self.spiffe_id.as_deref().unwrap_or_default())
+    }
+}
+
+// DN 属性值中的特殊字符按 RFC 4514 转义
+fn escape_dn(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, ',' | '+' | '"' | '\\' | '<' | '>' | ';' | '=') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// TLS 连接的协商结果，由 HTTPS 监听写入请求扩展
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsInfo {
+    // 如 TLSv1.3
+    pub protocol: String,
+    // IANA 名称，如 TLS13_AES_128_GCM_SHA256
+    pub cipher: String,
+    // 客户端握手时发送的服务器名
+    pub sni: Option<String>,
+}
+
+impl TlsInfo {
+    pub fn from_connection(conn: &rustls::ServerConnection) -> Self {
+        Self {
+            protocol: conn.protocol_version().and_then(|v| v.as_str()).unwrap_or_default().replace('_', "."),
+            cipher: conn.negotiated_cipher_suite().and_then(|s| s.suite().as_str()).unwrap_or_default().to_string(),
+            sni: conn.server_name().map(str::to_string),
+        }
+    }
+}
+
+/// 按 tls_headers（字段 -> 头名）把 TLS 连接信息写入转发给上游的请求头；
+/// 总是先去掉客户端自带的同名头，防止经明文连接伪造
+pub fn forward_headers(names: &BTreeMap<String, String>, req: &mut Request<Body>) {
+    if names.is_empty() {
+        return;
+    }
+    let info = req.extensions().get::<TlsInfo>().cloned();
+    let cert = req.extensions().get::<ClientCert>().cloned();
+    let headers = req.headers_mut();
+    for (field, name) in names {
+        let Ok(name) = HeaderName::from_bytes(name.as_bytes()) else {
+            continue;
+        };
+        headers.remove(&name);
+        let Some(info) = &info else {
+            continue;
+        };
+        let value = match field.as_str() {
+            "protocol" => Some(info.protocol.clone()),
+            "cipher" => Some(info.cipher.clone()),
+            "sni" => info.sni.clone(),
+            "client_subject" => cert.as_ref().and_then(|cert| cert.subject.clone()),
+            "client_verify" => Some(if cert.is_some() { "SUCCESS" } else { "NONE" }.to_string()),
+            _ => None,
+        };
+        if let Some(value) = value.filter(|v| !v.is_empty()).and_then(|v| HeaderValue::from_str(&v).ok()) {
+            headers.insert(name, value);
+        }
+    }
+}
+
+// * 匹配任意长度的字符
+fn wildcard(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = value.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+impl CertIdentity {
+    fn matches(&self, cert: &ClientCert) -> bool {
+        let field = |pattern: &Option<String>, value: Option<&String>| {
+            pattern.as_ref().is_none_or(|p| value.is_some_and(|v| wildcard(p, v)))
+        };
+        field(&self.cn, cert.cn.as_ref())
+            && field(&self.spiffe_id, cert.spiffe_id.as_ref())
+            && self.san.as_ref().is_none_or(|p| cert.sans.iter().any(|san| wildcard(p, san)))
+    }
+
+    fn claims(&self, cert: &ClientCert) -> Option<Claims> {
+        let sub = match &self.sub {
+            Some(sub) => cert.render(sub),
+            None => cert.spiffe_id.clone().or_else(|| cert.cn.clone())?,
+        };
+        let mut claims: BTreeMap<String, serde_json::Value> =
+            self.claims.iter().map(|(k, v)| (k.clone(), cert.render(v).into())).collect();
+        claims.insert("sub".to_string(), sub.into());
+        claims.insert("exp".to_string(), 0.into());
+        claims.insert("tenant_id".to_string(), self.tenant_id.as_deref().map(|t| cert.render(t)).unwrap_or_default().into());
+        if !self.roles.is_empty() {
+            claims.insert("roles".to_string(), self.roles.join(",").into());
+        }
+        serde_json::from_value(serde_json::to_value(claims).ok()?).ok()
+    }
+}
+
+/// 按映射规则得到证书对应的身份，没有命中的规则时返回 None
+pub fn claims(cert: &ClientCert, identities: &[CertIdentity]) -> Option<Claims> {
+    identities.iter().find(|identity| identity.matches(cert))?.claims(cert)
+}
+
+pub fn validation_errors(settings: &Settings) -> Vec<String> {
+    let mut errors = Vec::new();
+    if settings.tls_cert_file.is_some() != settings.tls_key_file.is_some() {
+        errors.push("tls_cert_file与tls_key_file必须同时配置".to_string());
+    }
+    if settings.tls_client_ca_file.is_some() && settings.tls_cert_file.is_none() {
+        errors.push("tls_client_ca_file需要同时配置tls_cert_file与tls_key_file".to_string());
+    }
+    if let Some(mode) = &settings.tls_client_auth
+        && !matches!(mode.as_str(), "required" | "optional")
+    {
+        errors.push(format!("tls_client_auth只支持 required / optional: {}", mode));
+    }
+    if !settings.client_cert_identities.is_empty() && settings.tls_client_ca_file.is_none() {
+        errors.push("client_cert_identities需要配置tls_client_ca_file".to_string());
+    }
+    for (field, name) in &settings.tls_headers {
+        if !TLS_HEADER_FIELDS.contains(&field.as_str()) {
+            errors.push(format!("tls_headers不支持的字段: {}（可选 {}）", field, TLS_HEADER_FIELDS.join("、")));
+        }
+        if HeaderName::from_bytes(name.as_bytes()).is_err() {
+            errors.push(format!("tls_headers.{}不是合法的头名: {}", field, name));
+        }
+    }
+    for (i, identity) in settings.client_cert_identities.iter().enumerate() {
+        if identity.cn.is_none() && identity.san.is_none() && identity.spiffe_id.is_none() {
+            errors.push(format!("client_cert_identities[{}]至少需要 cn、san、spiffe_id 之一", i));
+        }
+        if identity.spiffe_id.as_ref().is_some_and(|id| !id.starts_with(SPIFFE_SCHEME)) {
+            errors.push(format!("client_cert_identities[{}].spiffe_id必须以 {} 开头", i, SPIFFE_SCHEME));
+        }
+        if identity.claims.keys().any(|k| matches!(k.as_str(), "sub" | "tenant_id" | "roles" | "exp")) {
+            errors.push(format!("client_cert_identities[{}].claims不能包含 sub、tenant_id、roles、exp", i));
+        }
+    }
+    errors
+}
+
+/// 按设置创建 TLS 握手器；未配置证书时返回 None
+pub fn acceptor(settings: &Settings) -> Result<Option<TlsAcceptor>, String> {
+    let (Some(cert_file), Some(key_file)) = (&settings.tls_cert_file, &settings.tls_key_file) else {
+        return Ok(None);
+    };
+    let certs = CertificateDer::pem_file_iter(cert_file)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("读取 tls_cert_file 失败（{}）: {}", cert_file, e))?;
+    let key = PrivateKeyDer::from_pem_file(key_file).map_err(|e| format!("读取 tls_key_file 失败（{}）: {}", key_file, e))?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = rustls::ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?;
+    let builder = match &settings.tls_client_ca_file {
+        Some(ca_file) => {
+            let mut roots = rustls::RootCertStore::empty();
+            for cert in CertificateDer::pem_file_iter(ca_file).map_err(|e| format!("读取 tls_client_ca_file 失败（{}）: {}", ca_file, e))? {
+                let cert = cert.map_err(|e| format!("读取 tls_client_ca_file 失败（{}）: {}", ca_file, e))?;
+                roots.add(cert).map_err(|e| format!("tls_client_ca_file 中的证书无效（{}）: {}", ca_file, e))?;
+            }
+            let verifier = rustls::server::WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+            let verifier = match settings.tls_client_auth.as_deref() {
+                Some("optional") => verifier.allow_unauthenticated(),
+                _ => verifier,
+            };
+            builder.with_client_cert_verifier(verifier.build().map_err(|e| format!("客户端证书校验配置无效: {}", e))?)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let mut config = builder.with_single_cert(certs, key).map_err(|e| format!("TLS 证书无效: {}", e))?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(Some(TlsAcceptor::from(Arc::new(config))))
+}
+
+/// 以 HTTPS（HTTP/1.1）处理请求，客户端出示的证书写入请求扩展，直到监听失败
+pub async fn serve(listener: TcpListener, app: Router, acceptor: TlsAcceptor) -> std::io::Result<()> {
+    loop {
+        let (stream, addr) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(err) => {
+                tracing::warn!("接受连接失败: {}", err);
+                continue;
+            }
+        };
+        let (app, acceptor) = (app.clone(), acceptor.clone());
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(err) => {
+                    tracing::debug!(client = %addr, "TLS 握手失败: {}", err);
+                    return;
+                }
+            };
+            let cert = stream.get_ref().1.peer_certificates().and_then(|chain| chain.first()).and_then(|der| ClientCert::from_der(der));
+            let tls = TlsInfo::from_connection(stream.get_ref().1);
+            let service = hyper::service::service_fn(move |mut req: hyper::Request<Incoming>| {
+                req.extensions_mut().insert(ConnectInfo(addr));
+                req.extensions_mut().insert(tls.clone());
+                if let Some(cert) = &cert {
+                    req.extensions_mut().insert(cert.clone());
+                }
+                app.clone().oneshot(req.map(Body::new))
+            });
+            let conn = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .with_upgrades();
+            if let Err(err) = conn.await {
+                tracing::debug!(client = %addr, "连接异常结束: {}", err);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::asn1::Asn1Time;
+    use openssl::bn::{BigNum, MsbOption};
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::{PKey, Private};
+    use openssl::x509::extension::{BasicConstraints, SubjectAlternativeName};
+    use openssl::x509::X509NameBuilder;
+    use serde_json::json;
+
+    // ca 为 None 时签发自签名的 CA 证书；san 为 DNS 名或 spiffe:// URI
+    fn issue(cn: &str, san: &str, ca: Option<(&X509, &PKey<Private>)>) -> (X509, PKey<Private>) {
+        let key = PKey::from_ec_key(EcKey::generate(&EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap()).unwrap()).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_nid(Nid::COMMONNAME, cn).unwrap();
+        let name = name.build();
+        let mut serial = BigNum::new().unwrap();
+        serial.rand(64, MsbOption::MAYBE_ZERO, false).unwrap();
+        let mut cert = X509::builder().unwrap();
+        cert.set_version(2).unwrap();
+        cert.set_serial_number(&serial.to_asn1_integer().unwrap()).unwrap();
+        cert.set_subject_name(&name).unwrap();
+        cert.set_issuer_name(ca.map_or(&name, |(ca, _)| ca.subject_name())).unwrap();
+        cert.set_pubkey(&key).unwrap();
+        cert.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        cert.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+        match ca {
+            Some((ca, _)) => {
+                let mut names = SubjectAlternativeName::new();
+                if san.starts_with(SPIFFE_SCHEME) {
+                    names.uri(san);
+                } else {
+                    names.dns(san);
+                }
+                let san = names.build(&cert.x509v3_context(Some(ca), None)).unwrap();
+                cert.append_extension(san).unwrap();
+            }
+            None => cert.append_extension(BasicConstraints::new().critical().ca().build().unwrap()).unwrap(),
+        }
+        cert.sign(ca.map_or(&key, |(_, key)| key), MessageDigest::sha256()).unwrap();
+        (cert.build(), key)
+    }
+
+    fn identities(value: serde_json::Value) -> Vec<CertIdentity> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_cert_identity_mapping() {
+        let cert = ClientCert {
+            subject: Some("CN=orders-api".to_string()),
+            cn: Some("orders-api".to_string()),
+            sans: vec!["orders.svc.example.com".to_string()],
+            spiffe_id: Some("spiffe://example.org/ns/orders/sa/api".to_string()),
+        };
+        let rules = identities(json!([
+            { "spiffe_id": "spiffe://example.org/ns/billing/*", "tenant_id": "billing" },
+            { "spiffe_id": "spiffe://example.org/ns/orders/*", "san": "*.svc.example.com", "tenant_id": "orders",
+              "roles": ["reader", "writer"], "claims": { "priority": "high", "service": "{cn}" } },
+        ]));
+        let claims = claims(&cert, &rules).unwrap();
+        assert_eq!(claims.sub, "spiffe://example.org/ns/orders/sa/api");
+        assert_eq!(claims.tenant_id, "orders");
+        assert_eq!(claims.priority.as_deref(), Some("high"));
+        assert_eq!(claims.get("roles").as_deref(), Some("reader,writer"));
+        assert_eq!(claims.get("service").as_deref(), Some("orders-api"));
+
+        let rules = identities(json!([{ "cn": "orders-*", "sub": "svc-{cn}" }]));
+        assert_eq!(super::claims(&cert, &rules).unwrap().sub, "svc-orders-api");
+        assert!(super::claims(&ClientCert { cn: Some("billing".to_string()), ..Default::default() }, &rules).is_none());
+
+        assert!(wildcard("*", ""));
+        assert!(wildcard("a*b*c", "a-b-b-c"));
+        assert!(!wildcard("a*bc", "abc-"));
+        assert!(!wildcard("a*a", "a"));
+    }
+
+    #[test]
+    fn test_forward_headers() {
+        let names: BTreeMap<String, String> = [("protocol", "X-SSL-Protocol"), ("client_subject", "X-SSL-Client-S-DN"), ("client_verify", "X-SSL-Client-Verify")]
+            .into_iter()
+            .map(|(field, name)| (field.to_string(), name.to_string()))
+            .collect();
+        let request = || Request::builder().header("x-ssl-client-s-dn", "CN=forged").body(Body::empty()).unwrap();
+
+        // 明文连接：只去掉客户端自带的同名头
+        let mut req = request();
+        forward_headers(&names, &mut req);
+        assert!(req.headers().get("x-ssl-client-s-dn").is_none() && req.headers().get("x-ssl-protocol").is_none());
+
+        let mut req = request();
+        req.extensions_mut().insert(TlsInfo { protocol: "TLSv1.3".to_string(), ..Default::default() });
+        forward_headers(&names, &mut req);
+        assert_eq!(req.headers()["x-ssl-protocol"], "TLSv1.3");
+        assert_eq!(req.headers()["x-ssl-client-verify"], "NONE");
+        assert!(req.headers().get("x-ssl-client-s-dn").is_none());
+
+        req.extensions_mut().insert(ClientCert { subject: Some("CN=a\\,b,O=Example".to_string()), ..Default::default() });
+        forward_headers(&names, &mut req);
+        assert_eq!(req.headers()["x-ssl-client-s-dn"], "CN=a\\,b,O=Example");
+        assert_eq!(req.headers()["x-ssl-client-verify"], "SUCCESS");
+        assert_eq!(escape_dn("a,b=c"), "a\\,b\\=c");
+    }
+
+    // 以 rustls 客户端发出一个 HTTP/1.1 请求，返回完整响应
+    async fn request(addr: std::net::SocketAddr, ca: &X509, identity: Option<(&X509, &PKey<Private>)>) -> std::io::Result<String> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(CertificateDer::from(ca.to_der().unwrap())).unwrap();
+        let builder = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots);
+        let config = match identity {
+            Some((cert, key)) => builder
+                .with_client_auth_cert(
+                    vec![CertificateDer::from(cert.to_der().unwrap())],
+                    PrivateKeyDer::from_pem_slice(&key.private_key_to_pem_pkcs8().unwrap()).unwrap(),
+                )
+                .unwrap(),
+            None => builder.with_no_client_auth(),
+        };
+        let stream = tokio::net::TcpStream::connect(addr).await?;
+        let server_name = rustls::pki_types::ServerName::try_from("localhost").unwrap();
+        let mut stream = tokio_rustls::TlsConnector::from(Arc::new(config)).connect(server_name, stream).await?;
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await?;
+        Ok(response)
+    }
+
+    #[tokio::test]
+    async fn test_mtls_handshake_exposes_client_cert() {
+        let (ca, ca_key) = issue("test-ca", "", None);
+        let (server, server_key) = issue("localhost", "localhost", Some((&ca, &ca_key)));
+        let (client, client_key) = issue("orders-api", "spiffe://example.org/ns/orders/sa/api", Some((&ca, &ca_key)));
+
+        let dir = std::env::temp_dir().join(format!("helios-mtls-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, pem: Vec<u8>| {
+            let path = dir.join(name);
+            std::fs::write(&path, pem).unwrap();
+            path.to_string_lossy().into_owned()
+        };
+        let settings: Settings = serde_json::from_value(json!({
+            "gateway_bind": "127.0.0.1:0",
+            "jwt_decoding_key": "secret",
+            "global_qps": 100,
+            "client_qps": 100,
+            "tls_cert_file": write("server.pem", server.to_pem().unwrap()),
+            "tls_key_file": write("server.key", server_key.private_key_to_pem_pkcs8().unwrap()),
+            "tls_client_ca_file": write("ca.pem", ca.to_pem().unwrap()),
+        }))
+        .unwrap();
+        assert!(validation_errors(&settings).is_empty());
+        let acceptor = acceptor(&settings).unwrap().unwrap();
+
+        let app = Router::new().fallback(|cert: Option<axum::Extension<ClientCert>>, tls: axum::Extension<TlsInfo>| async move {
+            let cert = cert.map(|cert| cert.0).unwrap_or_default();
+            format!("{} {} {}", cert.subject.unwrap_or_default(), tls.sni.as_deref().unwrap_or_default(), cert.spiffe_id.unwrap_or_default())
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let task = tokio::spawn(serve(listener, app, acceptor));
+
+        let response = request(addr, &ca, Some((&client, &client_key))).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.ends_with("CN=orders-api localhost spiffe://example.org/ns/orders/sa/api"), "{}", response);
+        // 未出示证书的客户端在握手阶段被拒绝
+        assert!(request(addr, &ca, None).await.is_err());
+
+        task.abort();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,167 @@
+//! 路由命名空间：按团队/负责人把路由分组。命名空间可声明路由默认值（鉴权、限流、响应头策略等）与允许使用的前缀范围，
+//! 并可在设置中为其单独配置管理端令牌，持有者只能查看与操作本命名空间的路由，便于平台团队下放路由管理。
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use crate::config::RouteRule;
+
+// 默认值中不允许出现的字段：路由标识、匹配条件与上游须由路由自己声明
+const RESERVED: [&str; 4] = ["id", "prefix", "upstream", "namespace"];
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Namespace {
+    // 负责人（团队或联系人），出现在校验错误中便于定位
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    // 命名空间内路由的前缀必须等于或位于这些路径之下；不配置则不限制
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefixes: Option<Vec<String>>,
+    // 路由默认值：路由未配置的字段取这里的值，表类型字段（如 cors）逐键合并
+    #[serde(default)]
+    pub defaults: toml::Table,
+}
+
+impl Namespace {
+    fn label(&self, name: &str) -> String {
+        match &self.owner {
+            Some(owner) => format!("{}（负责人 {}）", name, owner),
+            None => name.to_string(),
+        }
+    }
+
+    /// 把默认值合并到路由的原始配置之下，路由自身的配置优先
+    pub fn apply(&self, route: &mut toml::Value) {
+        let mut merged = toml::Value::Table(self.defaults.clone());
+        let own = std::mem::replace(route, toml::Value::Boolean(false));
+        crate::config::merge_toml(&mut merged, own);
+        *route = merged;
+    }
+
+    /// 路由前缀超出命名空间允许范围时的错误
+    pub fn prefix_errors(&self, name: &str, rule: &RouteRule) -> Vec<String> {
+        let Some(allowed) = &self.prefixes else {
+            return Vec::new();
+        };
+        rule.prefix
+            .iter()
+            .filter(|p| !allowed.iter().any(|a| within(p, a)))
+            .map(|p| format!("prefix {} 不在命名空间 {}允许的前缀范围内", p, self.label(name)))
+            .collect()
+    }
+}
+
+// 前缀等于 allowed 或位于其下一级路径之中
+fn within(prefix: &str, allowed: &str) -> bool {
+    let allowed = allowed.trim_end_matches('/');
+    allowed.is_empty() || prefix == allowed || prefix.strip_prefix(allowed).is_some_and(|rest| rest.starts_with('/'))
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+pub fn validation_errors(name: &str, namespace: &Namespace) -> Vec<String> {
+    let mut errors = Vec::new();
+    if !valid_name(name) {
+        errors.push(format!("命名空间名只能包含字母、数字、- 与 _: {}", name));
+    }
+    for key in RESERVED.iter().filter(|key| namespace.defaults.contains_key(**key)) {
+        errors.push(format!("命名空间 {} 的 defaults 不能包含 {}", name, key));
+    }
+    for prefix in namespace.prefixes.iter().flatten() {
+        if !prefix.starts_with('/') || prefix.contains(['*', '{', '?']) {
+            errors.push(format!("命名空间 {} 的 prefixes 只支持以 / 开头的普通路径: {}", name, prefix));
+        }
+    }
+    // 以一条最小路由检验默认值的字段名与取值类型
+    let mut probe = toml::Table::new();
+    probe.insert("prefix".to_string(), toml::Value::String("/".to_string()));
+    probe.insert("upstream".to_string(), toml::Value::String("http://localhost".to_string()));
+    let mut probe = toml::Value::Table(probe);
+    namespace.apply(&mut probe);
+    if let Err(err) = RouteRule::deserialize(probe) {
+        errors.push(format!("命名空间 {} 的 defaults 无效: {}", name, err.message()));
+    }
+    errors
+}
+
+/// 管理端请求以命名空间令牌鉴权时的访问范围
+#[derive(Debug, Clone)]
+pub struct AdminScope {
+    pub namespace: String,
+    // 本命名空间与其他路由共用的上游，不允许摘流
+    pub shared_upstreams: BTreeSet<String>,
+}
+
+impl AdminScope {
+    /// 从全部路由中取出本命名空间的路由
+    pub fn new(namespace: &str, routes: &[RouteRule]) -> (Self, Vec<RouteRule>) {
+        let (own, others): (Vec<&RouteRule>, Vec<&RouteRule>) =
+            routes.iter().partition(|r| r.namespace.as_deref() == Some(namespace));
+        let foreign: BTreeSet<&String> = others.iter().flat_map(|r| r.all_upstreams()).collect();
+        let shared_upstreams = own
+            .iter()
+            .flat_map(|r| r.all_upstreams())
+            .filter(|u| foreign.contains(u))
+            .cloned()
+            .collect();
+        let scope = Self { namespace: namespace.to_string(), shared_upstreams };
+        (scope, own.into_iter().cloned().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn namespace(content: &str) -> Namespace {
+        toml::from_str(content).unwrap()
+    }
+
+    #[test]
+    fn test_namespace_defaults_and_prefixes() {
+        let ns = namespace(
+            r#"
+owner = "orders-team"
+prefixes = ["/api/orders"]
+[defaults]
+auth = "optional"
+qps = 200
+strip_response_headers = ["X-Internal-*"]
+[defaults.cors]
+allow_origins = ["https://shop.example.com"]
+"#,
+        );
+        assert!(validation_errors("orders", &ns).is_empty(), "{:?}", validation_errors("orders", &ns));
+
+        let mut route: toml::Value = toml::from_str("prefix = \"/api/orders/**\"\nupstream = \"http://orders\"\nqps = 50").unwrap();
+        ns.apply(&mut route);
+        let rule = RouteRule::deserialize(route).unwrap();
+        assert_eq!(rule.auth.as_deref(), Some("optional"));
+        assert_eq!(rule.qps, Some(50));
+        assert!(rule.cors.is_some());
+        assert!(ns.prefix_errors("orders", &rule).is_empty());
+
+        let outside = RouteRule { prefix: vec!["/api/ordersx".to_string(), "/api/orders".to_string()], ..Default::default() };
+        assert_eq!(ns.prefix_errors("orders", &outside), vec!["prefix /api/ordersx 不在命名空间 orders（负责人 orders-team）允许的前缀范围内"]);
+
+        let bad = namespace("[defaults]\nprefix = \"/\"\nqsp = 1");
+        let errors = validation_errors("team a", &bad);
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+    }
+
+    #[test]
+    fn test_admin_scope() {
+        let route = |ns: &str, upstream: &str| RouteRule {
+            namespace: Some(ns.to_string()),
+            prefix: vec![format!("/{}", ns)],
+            upstream: vec![upstream.to_string()],
+            ..Default::default()
+        };
+        let routes = vec![route("a", "http://a"), route("a", "http://shared"), route("b", "http://shared")];
+        let (scope, own) = AdminScope::new("a", &routes);
+        assert_eq!(own.len(), 2);
+        assert_eq!(scope.shared_upstreams.into_iter().collect::<Vec<_>>(), vec!["http://shared"]);
+    }
+}
@@ -0,0 +1,378 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{Response, StatusCode, Uri},
+    middleware::Next,
+    response::IntoResponse,
+};
+use crate::config::Settings;
+use crate::metrics::BLOCKED_PATH_COUNTER;
+use crate::problem::{Problem, ProblemType};
+
+/// 结尾斜杠处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrailingSlash {
+    Keep,
+    Strip,
+    Add,
+}
+
+/// 百分号解码范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PercentDecode {
+    None,
+    /// 仅解码 RFC 3986 非保留字符（字母、数字、-._~），不改变语义
+    Unreserved,
+    /// 解码所有可打印 ASCII（`%`、`?`、`#` 除外；`/` 由 decode_slash 单独控制）
+    All,
+}
+
+/// URL 规范化策略，在路由匹配与白名单检查之前统一应用
+#[derive(Debug, Clone, Copy)]
+pub struct NormalizePolicy {
+    pub merge_slashes: bool,
+    pub resolve_dot_segments: bool,
+    pub trailing_slash: TrailingSlash,
+    pub percent_decode: PercentDecode,
+    // %2F 是否解码为 /（默认否，避免改变路径层级）
+    pub decode_slash: bool,
+}
+
+impl Default for NormalizePolicy {
+    fn default() -> Self {
+        Self {
+            merge_slashes: true,
+            resolve_dot_segments: true,
+            trailing_slash: TrailingSlash::Keep,
+            percent_decode: PercentDecode::Unreserved,
+            decode_slash: false,
+        }
+    }
+}
+
+impl TrailingSlash {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "keep" => Some(TrailingSlash::Keep),
+            "strip" => Some(TrailingSlash::Strip),
+            "add" => Some(TrailingSlash::Add),
+            _ => None,
+        }
+    }
+}
+
+impl PercentDecode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "none" => Some(PercentDecode::None),
+            "unreserved" => Some(PercentDecode::Unreserved),
+            "all" => Some(PercentDecode::All),
+            _ => None,
+        }
+    }
+}
+
+impl NormalizePolicy {
+    pub fn from_settings(settings: &Settings) -> Self {
+        let default = Self::default();
+        Self {
+            merge_slashes: settings.url_merge_slashes.unwrap_or(default.merge_slashes),
+            resolve_dot_segments: settings.url_resolve_dot_segments.unwrap_or(default.resolve_dot_segments),
+            trailing_slash: settings
+                .url_trailing_slash
+                .as_deref()
+                .and_then(TrailingSlash::parse)
+                .unwrap_or(default.trailing_slash),
+            percent_decode: settings
+                .url_percent_decode
+                .as_deref()
+                .and_then(PercentDecode::parse)
+                .unwrap_or(default.percent_decode),
+            decode_slash: settings.url_decode_slash.unwrap_or(default.decode_slash),
+        }
+    }
+}
+
+// ===== 路径规范化 =====
+/// 依次执行：百分号解码 -> 合并重复斜杠 -> 解析 . / .. 段 -> 结尾斜杠处理
+pub fn normalize_path(path: &str, policy: &NormalizePolicy) -> String {
+    let mut path = percent_decode(path, policy);
+
+    if policy.merge_slashes {
+        let mut merged = String::with_capacity(path.len());
+        for c in path.chars() {
+            if c == '/' && merged.ends_with('/') {
+                continue;
+            }
+            merged.push(c);
+        }
+        path = merged;
+    }
+
+    if policy.resolve_dot_segments {
+        path = resolve_dot_segments(&path);
+    }
+
+    match policy.trailing_slash {
+        TrailingSlash::Keep => {}
+        TrailingSlash::Strip => {
+            while path.len() > 1 && path.ends_with('/') {
+                path.pop();
+            }
+        }
+        TrailingSlash::Add => {
+            if !path.ends_with('/') {
+                path.push('/');
+            }
+        }
+    }
+
+    if !path.starts_with('/') {
+        path.insert(0, '/');
+    }
+    path
+}
+
+fn percent_decode(path: &str, policy: &NormalizePolicy) -> String {
+    if policy.percent_decode == PercentDecode::None {
+        return path.to_string();
+    }
+    let bytes = path.as_bytes();
+    let mut out = String::with_capacity(path.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && let Some(decoded) = hex_byte(bytes[i + 1], bytes[i + 2])
+            && should_decode(decoded, policy)
+        {
+            out.push(decoded as char);
+            i += 3;
+            continue;
+        }
+        out.push(bytes[i] as char);
+        i += 1;
+    }
+    out
+}
+
+fn should_decode(byte: u8, policy: &NormalizePolicy) -> bool {
+    if byte == b'/' {
+        return policy.decode_slash;
+    }
+    match policy.percent_decode {
+        PercentDecode::None => false,
+        PercentDecode::Unreserved => byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~'),
+        PercentDecode::All => byte.is_ascii_graphic() && !matches!(byte, b'%' | b'?' | b'#'),
+    }
+}
+
+fn hex_byte(hi: u8, lo: u8) -> Option<u8> {
+    let hi = (hi as char).to_digit(16)?;
+    let lo = (lo as char).to_digit(16)?;
+    Some((hi * 16 + lo) as u8)
+}
+
+// RFC 3986 5.2.4，越过根目录的 .. 被丢弃
+fn resolve_dot_segments(path: &str) -> String {
+    let trailing = path.ends_with('/') || path.ends_with("/.") || path.ends_with("/..");
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/').skip(1) {
+        match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            s => segments.push(s),
+        }
+    }
+    // 去掉 split 末尾产生的空段，再按原样补结尾斜杠
+    if segments.last() == Some(&"") {
+        segments.pop();
+    }
+    let mut out = format!("/{}", segments.join("/"));
+    if trailing && !out.ends_with('/') {
+        out.push('/');
+    }
+    out
+}
+
+// ===== 可疑路径检测：在规范化之前检查原始路径 =====
+/// 原始路径中的可疑写法，返回原因（用作指标标签）
+pub fn inspect_path(raw: &str) -> Option<&'static str> {
+    if raw.bytes().any(|b| b < 0x20 || b == 0x7f) {
+        return Some("control_char");
+    }
+    if raw.contains('\\') {
+        return Some("backslash");
+    }
+
+    let lower = raw.to_ascii_lowercase();
+    let bytes = lower.as_bytes();
+    for (i, _) in lower.match_indices('%') {
+        let (Some(&hi), Some(&lo)) = (bytes.get(i + 1), bytes.get(i + 2)) else {
+            continue;
+        };
+        let Some(byte) = hex_byte(hi, lo) else {
+            continue;
+        };
+        match byte {
+            0x00..=0x1f | 0x7f => return Some("encoded_control"),
+            b'\\' => return Some("encoded_backslash"),
+            // %25xx：二次编码，常用于绕过只解码一次的检查
+            b'%' if bytes.get(i + 3).zip(bytes.get(i + 4)).is_some_and(|(a, b)| hex_byte(*a, *b).is_some()) => {
+                return Some("double_encoding");
+            }
+            // 0xC0/0xC1 只会出现在非法的超长 UTF-8 编码中（如 %c0%ae 表示 .）
+            0xc0 | 0xc1 => return Some("overlong_utf8"),
+            _ => {}
+        }
+    }
+
+    let has_dot_segment = lower
+        .split('/')
+        .any(|segment| matches!(segment, ".." | "%2e%2e" | ".%2e" | "%2e."));
+    if has_dot_segment {
+        return Some("dot_segment");
+    }
+    None
+}
+
+/// 如何处理含 .. 段的路径：reject（默认，返回 400）或 normalize（交由规范化解析）；其他可疑写法始终拒绝
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraversalAction {
+    Reject,
+    Normalize,
+}
+
+impl TraversalAction {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "reject" => Some(TraversalAction::Reject),
+            "normalize" => Some(TraversalAction::Normalize),
+            _ => None,
+        }
+    }
+}
+
+// ===== 规范化中间件：改写请求 URI，之后的白名单检查、路由匹配与转发都基于规范化路径 =====
+pub async fn normalize_layer(mut req: Request, next: Next) -> Response<Body> {
+    if let Some(rejected) = normalize_request(&mut req) {
+        return rejected;
+    }
+    next.run(req).await
+}
+
+/// 检查并规范化请求路径；路径可疑或规范化结果不合法时返回 400 响应。
+/// 内部重定向的目标路径不经过外层中间件，重新分派前同样经此处理
+pub fn normalize_request(req: &mut Request) -> Option<Response<Body>> {
+    let settings = req.extensions().get::<Settings>();
+    let policy = settings.map(NormalizePolicy::from_settings).unwrap_or_default();
+    let traversal = settings
+        .and_then(|s| s.url_traversal_action.as_deref())
+        .and_then(TraversalAction::parse)
+        .unwrap_or(TraversalAction::Reject);
+
+    let path = req.uri().path();
+    if let Some(reason) = inspect_path(path) {
+        let allowed = reason == "dot_segment" && traversal == TraversalAction::Normalize && policy.resolve_dot_segments;
+        if !allowed {
+            BLOCKED_PATH_COUNTER.with_label_values(&[reason]).inc();
+            tracing::warn!(reason, path = %path, "拒绝可疑请求路径");
+            return Some(
+                Problem::new(StatusCode::BAD_REQUEST, ProblemType::InvalidPath)
+                    .detail(format!("Rejected request path ({})", reason))
+                    .into_response(),
+            );
+        }
+    }
+
+    let normalized = normalize_path(path, &policy);
+    if normalized != path {
+        // 解码出 URI 不允许的字符（如 url_percent_decode = "all" 时的 %3C）时拒绝，不能以未规范化的路径继续
+        let Some(uri) = rebuild_uri(req.uri(), &normalized) else {
+            BLOCKED_PATH_COUNTER.with_label_values(&["invalid_normalized"]).inc();
+            tracing::warn!(path = %path, normalized = %normalized, "规范化后的路径不是合法的 URI");
+            return Some(
+                Problem::new(StatusCode::BAD_REQUEST, ProblemType::InvalidPath)
+                    .detail("Rejected request path (invalid_normalized)")
+                    .into_response(),
+            );
+        };
+        tracing::debug!("URL 规范化: {} -> {}", req.uri(), uri);
+        *req.uri_mut() = uri;
+    }
+    None
+}
+
+// 以规范化后的路径替换原 URI 的路径，保留查询串
+fn rebuild_uri(uri: &Uri, path: &str) -> Option<Uri> {
+    let path_and_query = match uri.query() {
+        Some(q) => format!("{}?{}", path, q),
+        None => path.to_string(),
+    };
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(path_and_query.parse().ok()?);
+    Uri::from_parts(parts).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_policy() {
+        let p = NormalizePolicy::default();
+        assert_eq!(normalize_path("/proxy//admin", &p), "/proxy/admin");
+        assert_eq!(normalize_path("/api/./v1/../users", &p), "/api/users");
+        assert_eq!(normalize_path("/../../etc/passwd", &p), "/etc/passwd");
+        assert_eq!(normalize_path("/api/%7Euser/%61bc", &p), "/api/~user/abc");
+        // %2e%2e 先解码再解析点段
+        assert_eq!(normalize_path("/api/%2e%2e/admin", &p), "/admin");
+        // %2F 默认保持编码
+        assert_eq!(normalize_path("/files/a%2Fb", &p), "/files/a%2Fb");
+        assert_eq!(normalize_path("/users/", &p), "/users/");
+    }
+
+    #[test]
+    fn test_inspect_suspicious_paths() {
+        assert_eq!(inspect_path("/api/users/1"), None);
+        assert_eq!(inspect_path("/api/v1.2/file..txt"), None);
+        assert_eq!(inspect_path("/api/../admin"), Some("dot_segment"));
+        assert_eq!(inspect_path("/api/%2E%2e/admin"), Some("dot_segment"));
+        assert_eq!(inspect_path("/api/.%2e/admin"), Some("dot_segment"));
+        assert_eq!(inspect_path("/api/%00.json"), Some("encoded_control"));
+        assert_eq!(inspect_path("/api/%5c..%5cadmin"), Some("encoded_backslash"));
+        assert_eq!(inspect_path("/api/%252e%252e/admin"), Some("double_encoding"));
+        assert_eq!(inspect_path("/api/%c0%ae%c0%ae/admin"), Some("overlong_utf8"));
+        assert_eq!(inspect_path("/api/100%25"), None);
+    }
+
+    #[test]
+    fn test_configurable_policy() {
+        let p = NormalizePolicy {
+            merge_slashes: false,
+            resolve_dot_segments: false,
+            trailing_slash: TrailingSlash::Strip,
+            percent_decode: PercentDecode::All,
+            decode_slash: true,
+        };
+        assert_eq!(normalize_path("/a//b/./c/", &p), "/a//b/./c");
+        assert_eq!(normalize_path("/files/a%2Fb%20c", &p), "/files/a/b%20c");
+        assert_eq!(normalize_path("/q%3Fx%25", &p), "/q%3Fx%25");
+        assert_eq!(normalize_path("/", &p), "/");
+
+        let add = NormalizePolicy { trailing_slash: TrailingSlash::Add, ..NormalizePolicy::default() };
+        assert_eq!(normalize_path("/users", &add), "/users/");
+    }
+
+    #[test]
+    fn test_rebuild_uri() {
+        let uri: Uri = "/a%3Cb%3E?x=1".parse().unwrap();
+        let all = NormalizePolicy { percent_decode: PercentDecode::All, ..NormalizePolicy::default() };
+        let normalized = normalize_path(uri.path(), &all);
+        assert_eq!(normalized, "/a<b>");
+        assert!(rebuild_uri(&uri, &normalized).is_none());
+        assert_eq!(rebuild_uri(&uri, "/a/b").unwrap(), "/a/b?x=1");
+    }
+}
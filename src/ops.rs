@@ -0,0 +1,78 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, Response, StatusCode},
+    middleware::{self, Next},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+
+use crate::config::Settings;
+use crate::problem::{Problem, ProblemType};
+use crate::rate_limit::{client_ip, parse_net};
+
+// ===== 运维端点：/metrics、/healthz 与管理端 =====
+pub fn router() -> Router {
+    let probes = Router::new()
+        .route("/metrics", get(crate::metrics::metrics_handler))
+        .route("/healthz", get(healthz))
+        .route_layer(middleware::from_fn(require_ops_token));
+    // 管理端沿用 admin_token 鉴权，来源 IP 限制对全部运维端点生效
+    Router::new()
+        .merge(probes)
+        .merge(crate::admin::router())
+        .route_layer(middleware::from_fn(require_allowed_ip))
+}
+
+async fn healthz() -> &'static str {
+    "ok"
+}
+
+/// 来源 IP 是否在 ops_allowed_ips 内；未配置时不限制
+pub fn ip_allowed(allowed: &[String], ip: std::net::IpAddr) -> bool {
+    allowed.is_empty() || allowed.iter().filter_map(|s| parse_net(s)).any(|net| net.contains(&ip))
+}
+
+async fn require_allowed_ip(req: Request, next: Next) -> Response<Body> {
+    let allowed = req.extensions().get::<Settings>().is_none_or(|s| ip_allowed(&s.ops_allowed_ips, client_ip(&req)));
+    if !allowed {
+        tracing::warn!(path = %req.uri().path(), client_ip = %client_ip(&req), "运维端点拒绝非白名单来源");
+        return Problem::new(StatusCode::FORBIDDEN, ProblemType::Forbidden)
+            .detail("Operational endpoints are not reachable from this address")
+            .into_response();
+    }
+    next.run(req).await
+}
+
+// 配置 ops_token 后，/metrics 与 /healthz 需携带 Bearer（或 Basic 密码）令牌
+async fn require_ops_token(req: Request, next: Next) -> Response<Body> {
+    let token = req
+        .extensions()
+        .get::<Settings>()
+        .and_then(|s| s.ops_token.clone())
+        .filter(|t| !t.is_empty());
+    if let Some(token) = token
+        && !crate::admin::is_authorized(req.headers(), &token)
+    {
+        return Problem::new(StatusCode::UNAUTHORIZED, ProblemType::Unauthorized)
+            .detail("Operations token required")
+            .header(header::WWW_AUTHENTICATE, "Bearer")
+            .into_response();
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ip_allowed() {
+        let allowed = vec!["10.0.0.0/8".to_string(), "127.0.0.1".to_string()];
+        assert!(ip_allowed(&allowed, "10.1.2.3".parse().unwrap()));
+        assert!(ip_allowed(&allowed, "127.0.0.1".parse().unwrap()));
+        assert!(!ip_allowed(&allowed, "192.168.1.1".parse().unwrap()));
+        assert!(ip_allowed(&[], "192.168.1.1".parse().unwrap()));
+    }
+}
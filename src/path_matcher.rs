@@ -1,39 +1,68 @@
+use dashmap::DashMap;
 use regex::Regex;
 use std::collections::HashMap;
 use once_cell::sync::Lazy;
-use std::sync::Mutex;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 
-/// RoutePattern: 存储原始 pattern、编译后的正则、变量名顺序
+/// RoutePattern: 存储原始 pattern、编译后的正则、变量名顺序（共享同一份编译结果，clone 只增加引用计数）
+#[derive(Clone)]
 pub struct RoutePattern {
-    pattern: String,
+    inner: Arc<Compiled>,
+}
+
+struct Compiled {
     regex: Regex,
     var_names: Vec<String>,
 }
 
-// 使用once_cell缓存编译后的模式，避免重复编译
-static PATTERN_CACHE: Lazy<Mutex<HashMap<String, RoutePattern>>> = 
-    Lazy::new(|| Mutex::new(HashMap::new()));
+// ===== 编译结果缓存：分片哈希表，查找只锁住单个分片的读锁 =====
+// 缓存上限，超过后淘汰最久未使用的 1/8 条目
+const CACHE_CAPACITY: usize = 1024;
+
+struct CacheEntry {
+    pattern: RoutePattern,
+    // 最近一次使用时的缓存代数
+    last_used: AtomicU64,
+}
+
+static PATTERN_CACHE: Lazy<DashMap<String, CacheEntry>> = Lazy::new(DashMap::new);
+// 缓存代数：每次插入新条目加一，命中时记录当前代数，淘汰时代数最小的最久未使用
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+// 淘汰最久未使用的条目
+fn evict_lru() {
+    let mut ages: Vec<(u64, String)> = PATTERN_CACHE
+        .iter()
+        .map(|entry| (entry.last_used.load(Ordering::Relaxed), entry.key().clone()))
+        .collect();
+    ages.sort_unstable();
+    for (_, key) in ages.into_iter().take(CACHE_CAPACITY / 8) {
+        PATTERN_CACHE.remove(&key);
+    }
+}
 
 impl RoutePattern {
     /// 将像 "/api/{id:[0-9]+}/file/**" 这样的 pattern 编译成 Regex 并记录变量名
     pub fn from_pattern(pattern: &str) -> Result<Self, regex::Error> {
-        // 先检查缓存
-        {
-            let cache = PATTERN_CACHE.lock().unwrap();
-            if let Some(cached) = cache.get(pattern) {
-                return Ok(cached.clone());
+        // 先检查缓存；代数未变时不再写入，避免热点条目的缓存行争用
+        let now = GENERATION.load(Ordering::Relaxed);
+        if let Some(cached) = PATTERN_CACHE.get(pattern) {
+            if cached.last_used.load(Ordering::Relaxed) != now {
+                cached.last_used.store(now, Ordering::Relaxed);
             }
+            return Ok(cached.pattern.clone());
         }
 
-        // 编译新模式
+        // 编译新模式并存入缓存
         let new_pattern = Self::compile_pattern(pattern)?;
-        
-        // 存入缓存
-        {
-            let mut cache = PATTERN_CACHE.lock().unwrap();
-            cache.insert(pattern.to_string(), new_pattern.clone());
+        if PATTERN_CACHE.len() >= CACHE_CAPACITY {
+            evict_lru();
         }
-
+        PATTERN_CACHE.insert(
+            pattern.to_string(),
+            CacheEntry { pattern: new_pattern.clone(), last_used: AtomicU64::new(GENERATION.fetch_add(1, Ordering::Relaxed) + 1) },
+        );
         Ok(new_pattern)
     }
 
@@ -122,17 +151,18 @@ impl RoutePattern {
         // compile
         let regex = Regex::new(&re)?;
         Ok(RoutePattern {
-            pattern: pattern.to_string(),
-            regex,
-            var_names,
+            inner: Arc::new(Compiled {
+                regex,
+                var_names,
+            }),
         })
     }
 
     /// 尝试匹配 path，匹配成功返回 Some(map) 包含命名参数
-    pub fn match_path<'a>(&self, path: &str) -> Option<HashMap<String, String>> {
-        if let Some(caps) = self.regex.captures(path) {
+    pub fn match_path(&self, path: &str) -> Option<HashMap<String, String>> {
+        if let Some(caps) = self.inner.regex.captures(path) {
             let mut map = HashMap::new();
-            for name in &self.var_names {
+            for name in &self.inner.var_names {
                 if let Some(m) = caps.name(name) {
                     map.insert(name.clone(), m.as_str().to_string());
                 }
@@ -142,19 +172,14 @@ impl RoutePattern {
         None
     }
 
-    /// 检查是否匹配路径（不提取变量）
-    pub fn matches(&self, path: &str) -> bool {
-        self.regex.is_match(path)
+    /// 编译后的正则表达式
+    pub fn regex_str(&self) -> &str {
+        self.inner.regex.as_str()
     }
-}
 
-impl Clone for RoutePattern {
-    fn clone(&self) -> Self {
-        Self {
-            pattern: self.pattern.clone(),
-            regex: self.regex.clone(),
-            var_names: self.var_names.clone(),
-        }
+    /// 检查是否匹配路径（不提取变量）
+    pub fn matches(&self, path: &str) -> bool {
+        self.inner.regex.is_match(path)
     }
 }
 
@@ -216,6 +241,52 @@ mod tests {
         // 如果缓存工作正常，第二次调用应该很快
     }
 
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        RoutePattern::from_pattern("/lru/hot/{id}").unwrap();
+        for i in 0..CACHE_CAPACITY + 200 {
+            RoutePattern::from_pattern(&format!("/lru/cold/{}", i)).unwrap();
+            RoutePattern::from_pattern("/lru/hot/{id}").unwrap();
+        }
+        assert!(PATTERN_CACHE.len() <= CACHE_CAPACITY);
+        assert!(PATTERN_CACHE.contains_key("/lru/hot/{id}"));
+        assert!(!PATTERN_CACHE.contains_key("/lru/cold/0"));
+    }
+
+    /// 多线程查找基准，与单个 Mutex<HashMap> 的旧实现对比：
+    /// cargo test --release path_matcher::tests::bench_concurrent_lookup -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_concurrent_lookup() {
+        use std::sync::Mutex;
+        use std::time::Instant;
+        const THREADS: usize = 8;
+        const LOOKUPS: usize = 200_000;
+        let patterns: Vec<String> = (0..16).map(|i| format!("/bench/{}/{{id}}/**", i)).collect();
+        let baseline: Mutex<HashMap<String, RoutePattern>> = Mutex::new(HashMap::new());
+        for p in &patterns {
+            baseline.lock().unwrap().insert(p.clone(), RoutePattern::from_pattern(p).unwrap());
+        }
+
+        let run = |lookup: &(dyn Fn(&str) -> RoutePattern + Sync)| {
+            let start = Instant::now();
+            std::thread::scope(|scope| {
+                for t in 0..THREADS {
+                    let patterns = &patterns;
+                    scope.spawn(move || {
+                        for i in 0..LOOKUPS {
+                            std::hint::black_box(lookup(&patterns[(i + t) % patterns.len()]));
+                        }
+                    });
+                }
+            });
+            start.elapsed()
+        };
+        let mutex = run(&|p| baseline.lock().unwrap().get(p).cloned().unwrap());
+        let sharded = run(&|p| RoutePattern::from_pattern(p).unwrap());
+        println!("{} 线程 x {} 次查找: Mutex<HashMap> {:?}，分片缓存 {:?}", THREADS, LOOKUPS, mutex, sharded);
+    }
+
     #[test]
     fn test_traditional_prefix_matching() {
         let test_cases = vec![
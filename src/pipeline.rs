@@ -0,0 +1,348 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, Response, StatusCode},
+    middleware,
+    response::IntoResponse,
+    routing::any,
+    Router,
+};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use crate::config::{RouteRule, Settings};
+use crate::problem::{Problem, ProblemType};
+
+/// 可编排的中间件阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    RateLimit,
+    Cors,
+    Whitelist,
+    Auth,
+    PropagateHeaders,
+}
+
+impl Stage {
+    /// 默认执行顺序
+    pub const DEFAULT: [Stage; 5] = [Stage::RateLimit, Stage::Cors, Stage::Whitelist, Stage::Auth, Stage::PropagateHeaders];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "rate_limit" => Some(Stage::RateLimit),
+            "cors" => Some(Stage::Cors),
+            "whitelist" => Some(Stage::Whitelist),
+            "auth" => Some(Stage::Auth),
+            "propagate_headers" => Some(Stage::PropagateHeaders),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stage::RateLimit => "rate_limit",
+            Stage::Cors => "cors",
+            Stage::Whitelist => "whitelist",
+            Stage::Auth => "auth",
+            Stage::PropagateHeaders => "propagate_headers",
+        }
+    }
+}
+
+/// 解析阶段列表：名称须合法且不重复，whitelist 与 propagate_headers 依赖 auth 的相对位置
+pub fn parse_stages(names: &[String]) -> Result<Vec<Stage>, String> {
+    let mut stages = Vec::with_capacity(names.len());
+    for name in names {
+        let stage = Stage::parse(name.trim()).ok_or_else(|| format!("未知的中间件阶段: {}", name))?;
+        if stages.contains(&stage) {
+            return Err(format!("中间件阶段重复: {}", name));
+        }
+        stages.push(stage);
+    }
+    let position = |stage| stages.iter().position(|s| *s == stage);
+    if let Some(propagate) = position(Stage::PropagateHeaders)
+        && position(Stage::Auth).is_none_or(|auth| auth > propagate)
+    {
+        return Err("propagate_headers须在auth之后".to_string());
+    }
+    if let (Some(whitelist), Some(auth)) = (position(Stage::Whitelist), position(Stage::Auth))
+        && whitelist > auth
+    {
+        return Err("whitelist须在auth之前".to_string());
+    }
+    Ok(stages)
+}
+
+/// 请求实际使用的阶段：路由配置优先，其次全局配置，最后默认顺序
+pub fn stages_for(rule: Option<&RouteRule>, settings: Option<&Settings>) -> Vec<Stage> {
+    let configured = rule
+        .and_then(|r| r.middleware.as_deref())
+        .or_else(|| settings.map(|s| s.middleware_pipeline.as_slice()).filter(|p| !p.is_empty()));
+    configured
+        .and_then(|names| parse_stages(names).ok())
+        .unwrap_or_else(|| Stage::DEFAULT.to_vec())
+}
+
+/// 路由解析结果，供后续各阶段与代理处理器复用
+#[derive(Clone)]
+pub struct MatchedRoute(pub Arc<RouteRule>);
+
+// 按阶段列表缓存组装好的中间件栈
+static PIPELINES: Lazy<DashMap<Vec<Stage>, Router>> = Lazy::new(DashMap::new);
+
+fn build(stages: &[Stage]) -> Router {
+    let mut router = Router::new()
+        .route("/*path", any(crate::proxy::proxy_handler))
+        .route_layer(middleware::from_fn(crate::claim_labels::capture_layer));
+    // 自内向外叠加，列表中靠前的阶段先执行
+    for stage in stages.iter().rev() {
+        router = match stage {
+            Stage::RateLimit => router.route_layer(middleware::from_fn(crate::rate_limit::rate_limit_layer)),
+            Stage::Cors => router.route_layer(middleware::from_fn(crate::cors::cors_layer)),
+            Stage::Whitelist => router.route_layer(middleware::from_fn(crate::proxy::check_whitelist_middleware)),
+            Stage::Auth => router.route_layer(middleware::from_extractor::<crate::auth::JwtAuth>()),
+            Stage::PropagateHeaders => router.route_layer(middleware::from_fn(crate::proxy::propagate_auth_headers)),
+        };
+    }
+    router
+}
+
+// ===== 路由解析并分派到对应的中间件栈 =====
+pub async fn dispatch(mut req: Request<Body>) -> Response<Body> {
+    // 内部重定向重新分派的请求：访问日志、封禁、SLO、异常与流量统计由最外层的分派对整个请求记录一次
+    let redirected = req.extensions().get::<crate::internal_redirect::Hops>().is_some();
+
+    // 自动封禁：被封禁的来源在匹配路由、鉴权与转发之前直接拒绝
+    let ban_sources = (crate::ban::enabled() && !redirected).then(|| crate::ban::sources(&req));
+    if let Some(remaining) = ban_sources.as_deref().and_then(crate::ban::banned) {
+        crate::ban::record_rejected();
+        return Problem::new(StatusCode::FORBIDDEN, ProblemType::Forbidden)
+            .detail("Client temporarily banned")
+            .header(header::RETRY_AFTER, remaining.as_secs().max(1))
+            .into_response();
+    }
+
+    let path = req.uri().path();
+    let match_path = path.strip_prefix("/proxy").unwrap_or(path);
+    let rule = req
+        .extensions()
+        .get::<Vec<RouteRule>>()
+        .and_then(|rules| crate::proxy::find_best_match(rules, match_path, req.headers()))
+        .cloned()
+        // 静态路由优先，未命中时再匹配运行时发现的路由（Kubernetes、Docker）
+        .or_else(|| crate::discovery::find_best_match(match_path, req.headers()))
+        .map(Arc::new);
+
+    // 访问日志：按路由的采样比例与字段记录，响应返回后输出
+    let access = req
+        .extensions()
+        .get::<Settings>()
+        .filter(|_| !redirected)
+        .and_then(|s| crate::access_log::Sampling::resolve(s, rule.as_deref()))
+        .map(|sampling| crate::access_log::Pending::new(sampling, &req, rule.as_deref().map(crate::proxy::route_label)));
+
+    // 蜜罐路由：拖延后拒绝，不执行任何中间件阶段
+    if let Some(rule) = rule.as_deref()
+        && let Some(tarpit) = &rule.tarpit
+    {
+        let resp = tarpit.serve(&crate::proxy::route_label(rule)).await;
+        if let Some(sources) = &ban_sources {
+            crate::ban::observe(sources, resp.status().as_u16());
+        }
+        if let Some(access) = access {
+            access.finish(&resp);
+        }
+        return resp;
+    }
+
+    // 过载时最先拒绝低优先级路由，不再执行后续阶段
+    if let Some(rule) = rule.as_deref()
+        && crate::shedding::is_low_priority(rule)
+        && let Some(reason) = crate::shedding::overloaded()
+    {
+        crate::metrics::LOAD_SHED_COUNTER
+            .with_label_values(&[&crate::proxy::route_label(rule), reason.as_str()])
+            .inc();
+        let resp = Problem::new(StatusCode::SERVICE_UNAVAILABLE, ProblemType::Overloaded)
+            .detail(format!("Low-priority request shed due to {}", reason.as_str()))
+            .header(header::RETRY_AFTER, "1")
+            .into_response();
+        if let Some(access) = access {
+            access.finish(&resp);
+        }
+        return resp;
+    }
+
+    // 异常防护触发的路由熔断：到期前直接拒绝
+    if let Some(rule) = rule.as_deref()
+        && rule.anomaly.is_some()
+        && let Some(remaining) = crate::anomaly::circuit_open(&crate::proxy::route_label(rule))
+    {
+        let resp = Problem::new(StatusCode::SERVICE_UNAVAILABLE, ProblemType::ServiceUnavailable)
+            .detail("Route circuit opened after an anomaly was detected")
+            .header(header::RETRY_AFTER, remaining.as_secs().max(1))
+            .into_response();
+        if let Some(access) = access {
+            access.finish(&resp);
+        }
+        return resp;
+    }
+
+    // 只能经内部重定向访问的路由：客户端直接请求时如同路由不存在
+    if let Some(resp) = rule.as_deref().and_then(|rule| crate::internal_redirect::reject_external(rule, &req)) {
+        if let Some(access) = access {
+            access.finish(&resp);
+        }
+        return resp;
+    }
+
+    // 路由声明了允许的方法：OPTIONS 与未允许的方法在本地应答，不执行后续阶段
+    if let Some(resp) = rule.as_deref().and_then(|rule| crate::methods::check(rule, &req)) {
+        if let Some(access) = access {
+            access.finish(&resp);
+        }
+        return resp;
+    }
+
+    // 爬虫与扫描器识别：block 直接拒绝，tag 附带标记头继续转发
+    if let Some(rule) = rule.as_deref()
+        && let Some(bot) = &rule.bot
+        && let Some(hit) = bot.detect(req.headers(), req.uri().path())
+    {
+        let action = if bot.blocks() { "block" } else { "tag" };
+        crate::metrics::BOT_MATCHES
+            .with_label_values(&[&crate::proxy::route_label(rule), hit.as_str(), action])
+            .inc();
+        if bot.blocks() {
+            let resp = Problem::new(StatusCode::FORBIDDEN, ProblemType::Forbidden)
+                .detail("Request blocked by bot detection")
+                .with("rule", hit.as_str())
+                .into_response();
+            // 被拦截的请求同样计入自动封禁
+            if let Some(sources) = &ban_sources {
+                crate::ban::observe(sources, resp.status().as_u16());
+            }
+            if let Some(access) = access {
+                access.finish(&resp);
+            }
+            return resp;
+        }
+        crate::bot::BotRules::tag(req.headers_mut(), hit);
+    }
+
+    let stages = stages_for(rule.as_deref(), req.extensions().get::<Settings>());
+    if let Some(trace) = req.extensions().get::<Arc<crate::debug::DebugTrace>>() {
+        let names: Vec<&str> = stages.iter().map(Stage::as_str).collect();
+        trace.set("pipeline", names.join(","));
+    }
+    let error_pages = rule.as_deref().and_then(|r| Some((r.error_pages.clone()?, crate::proxy::route_label(r))));
+    let claim_metrics = (crate::claim_labels::enabled() && !redirected)
+        .then(|| (rule.as_deref().map(crate::proxy::route_label).unwrap_or_default(), std::time::Instant::now()));
+    let recorded = rule.as_deref().filter(|_| !redirected);
+    let slo = recorded.and_then(|r| Some((r.slo.clone()?, crate::proxy::route_label(r), std::time::Instant::now())));
+    let anomaly = recorded.filter(|r| r.anomaly.is_some()).map(|r| (crate::proxy::route_label(r), std::time::Instant::now()));
+    let bandwidth = recorded.map(|r| (crate::proxy::route_label(r), crate::bandwidth::count_request(&mut req)));
+    let redirect = rule.as_deref().filter(|r| crate::internal_redirect::enabled(r)).map(|_| crate::internal_redirect::Origin::capture(&req));
+    let sendfile = rule.as_deref().and_then(|r| crate::sendfile::Delivery::capture(r, &req));
+    if let Some(rule) = rule {
+        req.extensions_mut().insert(crate::auth::RouteAuth {
+            mode: crate::auth::AuthMode::for_route(&rule),
+            route: crate::proxy::route_label(&rule),
+        });
+        req.extensions_mut().insert(MatchedRoute(rule));
+    }
+
+    // 客户端自带的身份头在执行任何阶段前去掉，只由 propagate_headers 按校验通过的身份重新写入；
+    // 编排中没有该阶段（如只有 auth）时也不会把伪造的身份转发给上游
+    req.headers_mut().remove("uid");
+    req.headers_mut().remove("tenant_id");
+
+    let router = PIPELINES.entry(stages.clone()).or_insert_with(|| build(&stages)).clone();
+    let mut resp = match router.oneshot(req).await {
+        Ok(resp) => resp,
+        Err(never) => match never {},
+    }
+    .into_response();
+    // 受保护文件下发：以 X-Sendfile / X-Accel-Redirect 指向的文件代替上游响应
+    if let Some(delivery) = sendfile {
+        resp = delivery.respond(resp).await;
+    }
+    // 内部重定向：以 X-Accel-Redirect 中的路径在网关内部重新分派，原响应不返回给客户端
+    if let Some(origin) = redirect {
+        match origin.follow(&resp) {
+            // 重定向目标同样经过路径检查与规范化，再匹配路由
+            Some(Ok(mut next)) => match crate::normalize::normalize_request(&mut next) {
+                Some(rejected) => resp = rejected,
+                None => resp = Box::pin(dispatch(next)).await,
+            },
+            Some(Err(too_many)) => resp = too_many,
+            None => {
+                resp.headers_mut().remove(crate::internal_redirect::HEADER);
+            }
+        }
+    }
+    let resp = match error_pages {
+        Some((pages, route)) => crate::error_pages::apply(&pages, &route, resp).await,
+        None => resp,
+    };
+    if let Some((slo, route, start)) = slo {
+        crate::slo::record(&route, &slo, resp.status().as_u16(), start.elapsed());
+    }
+    if let Some((route, start)) = anomaly {
+        crate::anomaly::record(&route, resp.status().as_u16(), start.elapsed());
+    }
+    if let Some((route, start)) = claim_metrics {
+        let claims = resp.extensions().get::<crate::auth::JwtAuth>().map(|jwt| &jwt.0);
+        crate::claim_labels::observe(&route, resp.status().as_u16(), claims, start.elapsed());
+    }
+    if let Some(sources) = ban_sources {
+        crate::ban::observe(&sources, resp.status().as_u16());
+    }
+    if let Some(access) = access {
+        access.finish(&resp);
+    }
+    // 流量统计：响应体发送完毕后按路由与租户上报
+    match bandwidth {
+        Some((route, upload)) => {
+            let tenant = resp.extensions().get::<crate::auth::JwtAuth>().map(|jwt| jwt.0.tenant_id.clone());
+            crate::bandwidth::count_response(resp, route, tenant, upload)
+        }
+        None => resp,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_stages() {
+        assert_eq!(
+            parse_stages(&names(&["auth", "rate_limit"])).unwrap(),
+            vec![Stage::Auth, Stage::RateLimit]
+        );
+        assert!(parse_stages(&names(&["auth", "propagate_headers", "whitelist"])).is_err());
+        assert!(parse_stages(&names(&["propagate_headers", "auth"])).is_err());
+        assert!(parse_stages(&names(&["propagate_headers"])).is_err());
+        assert!(parse_stages(&names(&["auth", "auth"])).is_err());
+        assert!(parse_stages(&names(&["gzip"])).is_err());
+        assert!(parse_stages(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_stages_for() {
+        assert_eq!(stages_for(None, None), Stage::DEFAULT.to_vec());
+
+        let rule = RouteRule { middleware: Some(names(&["cors"])), ..Default::default() };
+        assert_eq!(stages_for(Some(&rule), None), vec![Stage::Cors]);
+
+        let rule = RouteRule { middleware: Some(Vec::new()), ..Default::default() };
+        assert!(stages_for(Some(&rule), None).is_empty());
+    }
+}
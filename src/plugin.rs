@@ -0,0 +1,418 @@
+//! 路由插件：按名称注册插件（配置模式 + 构造函数），路由以 plugins 数组声明插件实例及其配置。
+//! 配置在加载路由时按模式校验并试构造实例，错误与其他路由错误一样带文件名与行号，`helios lint` / `helios check` 同样报告；
+//! 实例按（路由, 插件, 配置）缓存，路由配置变化（如服务发现更新路由）后以新配置重新构造。
+use axum::body::Body;
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Request, Response, StatusCode};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::config::RouteRule;
+
+/// 路由中的一个插件实例
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PluginConfig {
+    pub name: String,
+    // 插件配置，按插件声明的模式校验
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub config: Value,
+}
+
+/// 配置项类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    String,
+    Integer,
+    Boolean,
+    StringList,
+    // 字符串到字符串的映射
+    StringMap,
+}
+
+impl FieldType {
+    fn matches(self, value: &Value) -> bool {
+        match self {
+            FieldType::String => value.is_string(),
+            FieldType::Integer => value.is_i64(),
+            FieldType::Boolean => value.is_boolean(),
+            FieldType::StringList => value.as_array().is_some_and(|items| items.iter().all(Value::is_string)),
+            FieldType::StringMap => value.as_object().is_some_and(|map| map.values().all(Value::is_string)),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            FieldType::String => "字符串",
+            FieldType::Integer => "整数",
+            FieldType::Boolean => "布尔值",
+            FieldType::StringList => "字符串数组",
+            FieldType::StringMap => "字符串表",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Field {
+    name: &'static str,
+    ty: FieldType,
+    required: bool,
+    range: Option<(i64, i64)>,
+    one_of: Option<Vec<&'static str>>,
+}
+
+/// 插件配置模式：声明配置项的名称、类型、是否必填及取值范围，未声明的配置项视为错误
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    fields: Vec<Field>,
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push(mut self, name: &'static str, ty: FieldType, required: bool) -> Self {
+        self.fields.push(Field { name, ty, required, range: None, one_of: None });
+        self
+    }
+
+    /// 可选配置项
+    pub fn field(self, name: &'static str, ty: FieldType) -> Self {
+        self.push(name, ty, false)
+    }
+
+    /// 必填配置项
+    pub fn required(self, name: &'static str, ty: FieldType) -> Self {
+        self.push(name, ty, true)
+    }
+
+    /// 限定上一个整数配置项的取值范围（含两端）
+    pub fn range(mut self, min: i64, max: i64) -> Self {
+        if let Some(field) = self.fields.last_mut() {
+            field.range = Some((min, max));
+        }
+        self
+    }
+
+    /// 限定上一个字符串配置项的可选值
+    pub fn one_of(mut self, values: &[&'static str]) -> Self {
+        if let Some(field) = self.fields.last_mut() {
+            field.one_of = Some(values.to_vec());
+        }
+        self
+    }
+
+    /// 校验配置，返回全部错误；未填写配置（null）视为空表
+    pub fn validate(&self, config: &Value) -> Vec<String> {
+        let empty = serde_json::Map::new();
+        let Some(map) = config.as_object().or(config.is_null().then_some(&empty)) else {
+            return vec!["config必须是表".to_string()];
+        };
+        let mut errors = Vec::new();
+        for key in map.keys().filter(|key| !self.fields.iter().any(|f| f.name == key.as_str())) {
+            errors.push(format!("未知的配置项: {}", key));
+        }
+        for field in &self.fields {
+            let Some(value) = map.get(field.name) else {
+                if field.required {
+                    errors.push(format!("缺少必填项: {}", field.name));
+                }
+                continue;
+            };
+            if !field.ty.matches(value) {
+                errors.push(format!("{}必须是{}", field.name, field.ty.as_str()));
+                continue;
+            }
+            if let (Some((min, max)), Some(n)) = (field.range, value.as_i64())
+                && !(min..=max).contains(&n)
+            {
+                errors.push(format!("{}必须在{}到{}之间", field.name, min, max));
+            }
+            if let (Some(allowed), Some(s)) = (&field.one_of, value.as_str())
+                && !allowed.contains(&s)
+            {
+                errors.push(format!("{}仅支持 {}: {}", field.name, allowed.join("、"), s));
+            }
+        }
+        errors
+    }
+}
+
+/// 插件：转发前处理请求，返回给客户端前处理响应
+pub trait Plugin: Send + Sync {
+    /// 按声明顺序调用；返回 Some 时直接以该响应应答，不再转发，也不再调用之后的插件
+    fn on_request(&self, _req: &mut Request<Body>) -> Option<Response<Body>> {
+        None
+    }
+
+    /// 按相反顺序调用，只调用处理过请求的插件
+    fn on_response(&self, _resp: &mut Response<Body>) {}
+}
+
+/// 按（已通过模式校验的）配置构造插件实例，配置语义有误时返回错误说明
+pub type Factory = Arc<dyn Fn(&Value) -> Result<Arc<dyn Plugin>, String> + Send + Sync>;
+
+#[derive(Clone)]
+struct Registration {
+    schema: Arc<Schema>,
+    factory: Factory,
+}
+
+// 插件名 -> 配置模式与构造函数，内置 headers / mock
+static REGISTRY: Lazy<DashMap<String, Registration>> = Lazy::new(|| {
+    let registry = DashMap::new();
+    let builtin = |schema: Schema, factory: Factory| Registration { schema: Arc::new(schema), factory };
+    registry.insert("headers".to_string(), builtin(Headers::schema(), Arc::new(|config| Ok(Arc::new(Headers::new(config)?)))));
+    registry.insert("mock".to_string(), builtin(Mock::schema(), Arc::new(|config| Ok(Arc::new(Mock::new(config)?)))));
+    registry
+});
+
+/// 注册自定义插件，路由中以 `{ name = "<name>", config = { ... } }` 引用；须在加载路由配置之前调用。
+/// 名称已被占用（包括内置插件）时不覆盖并返回 false
+pub fn register<F>(name: &str, schema: Schema, factory: F) -> bool
+where
+    F: Fn(&Value) -> Result<Arc<dyn Plugin>, String> + Send + Sync + 'static,
+{
+    match REGISTRY.entry(name.to_string()) {
+        dashmap::Entry::Occupied(_) => false,
+        dashmap::Entry::Vacant(entry) => {
+            entry.insert(Registration { schema: Arc::new(schema), factory: Arc::new(factory) });
+            true
+        }
+    }
+}
+
+/// 已注册的插件名（排序后），用于校验错误提示
+pub fn names() -> Vec<String> {
+    let mut names: Vec<String> = REGISTRY.iter().map(|entry| entry.key().clone()).collect();
+    names.sort();
+    names
+}
+
+// 校验配置并构造实例；先取出注册信息再构造，避免构造期间持有分片锁
+fn build(plugin: &PluginConfig) -> Result<Arc<dyn Plugin>, String> {
+    let registration = REGISTRY
+        .get(&plugin.name)
+        .map(|entry| entry.value().clone())
+        .ok_or_else(|| format!("未注册的插件: {}，可用: {}", plugin.name, names().join(", ")))?;
+    let errors = registration.schema.validate(&plugin.config);
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+    (registration.factory)(&plugin.config)
+}
+
+pub fn validation_errors(rule: &RouteRule) -> Vec<String> {
+    rule.plugins
+        .iter()
+        .flatten()
+        .enumerate()
+        .filter_map(|(i, plugin)| build(plugin).err().map(|e| format!("plugins[{}]（{}）: {}", i, plugin.name, e)))
+        .collect()
+}
+
+// (路由, 插件, 配置) -> 实例
+static INSTANCES: Lazy<DashMap<String, Arc<dyn Plugin>>> = Lazy::new(DashMap::new);
+
+/// 路由的插件实例，按声明顺序；首次使用某个配置时构造
+pub fn instances(rule: &RouteRule) -> Vec<Arc<dyn Plugin>> {
+    let Some(plugins) = &rule.plugins else {
+        return Vec::new();
+    };
+    let route = crate::proxy::route_label(rule);
+    plugins
+        .iter()
+        .filter_map(|plugin| {
+            let key = format!("{}|{}|{}", route, plugin.name, plugin.config);
+            if let Some(instance) = INSTANCES.get(&key) {
+                return Some(instance.value().clone());
+            }
+            match build(plugin) {
+                Ok(instance) => Some(INSTANCES.entry(key).or_insert(instance).value().clone()),
+                // 加载时已校验，只有运行时注入的路由可能走到这里
+                Err(err) => {
+                    tracing::error!(route = %route, plugin = %plugin.name, "插件配置无效，已跳过: {}", err);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+// ===== 内置插件 =====
+
+// 读取字符串表配置项并解析为请求头
+fn header_map(config: &Value, key: &str) -> Result<HeaderMap, String> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in config[key].as_object().into_iter().flatten() {
+        let name = HeaderName::try_from(name.as_str()).map_err(|_| format!("{}包含无效的请求头名: {}", key, name))?;
+        let value = HeaderValue::from_str(value.as_str().unwrap_or_default())
+            .map_err(|_| format!("{}.{}的取值无效", key, name))?;
+        headers.insert(name, value);
+    }
+    Ok(headers)
+}
+
+fn header_names(config: &Value, key: &str) -> Result<Vec<HeaderName>, String> {
+    config[key]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(|name| HeaderName::try_from(name).map_err(|_| format!("{}包含无效的请求头名: {}", key, name)))
+        .collect()
+}
+
+/// headers：设置或去掉请求头（转发给上游前）与响应头（返回给客户端前）
+struct Headers {
+    request_set: HeaderMap,
+    request_remove: Vec<HeaderName>,
+    response_set: HeaderMap,
+    response_remove: Vec<HeaderName>,
+}
+
+impl Headers {
+    fn schema() -> Schema {
+        Schema::new()
+            .field("request_set", FieldType::StringMap)
+            .field("request_remove", FieldType::StringList)
+            .field("response_set", FieldType::StringMap)
+            .field("response_remove", FieldType::StringList)
+    }
+
+    fn new(config: &Value) -> Result<Self, String> {
+        Ok(Self {
+            request_set: header_map(config, "request_set")?,
+            request_remove: header_names(config, "request_remove")?,
+            response_set: header_map(config, "response_set")?,
+            response_remove: header_names(config, "response_remove")?,
+        })
+    }
+
+    fn apply(headers: &mut HeaderMap, set: &HeaderMap, remove: &[HeaderName]) {
+        for name in remove {
+            headers.remove(name);
+        }
+        for (name, value) in set {
+            headers.insert(name, value.clone());
+        }
+    }
+}
+
+impl Plugin for Headers {
+    fn on_request(&self, req: &mut Request<Body>) -> Option<Response<Body>> {
+        Self::apply(req.headers_mut(), &self.request_set, &self.request_remove);
+        None
+    }
+
+    fn on_response(&self, resp: &mut Response<Body>) {
+        Self::apply(resp.headers_mut(), &self.response_set, &self.response_remove);
+    }
+}
+
+/// mock：不转发，直接以固定的状态码与响应体应答（如接口下线、联调桩）
+struct Mock {
+    status: StatusCode,
+    body: String,
+    content_type: HeaderValue,
+}
+
+impl Mock {
+    fn schema() -> Schema {
+        Schema::new()
+            .required("status", FieldType::Integer)
+            .range(200, 599)
+            .field("body", FieldType::String)
+            .field("content_type", FieldType::String)
+    }
+
+    fn new(config: &Value) -> Result<Self, String> {
+        let status = config["status"].as_i64().and_then(|s| u16::try_from(s).ok()).unwrap_or(200);
+        let content_type = config["content_type"].as_str().unwrap_or("text/plain; charset=utf-8");
+        Ok(Self {
+            status: StatusCode::from_u16(status).map_err(|_| format!("无效的状态码: {}", status))?,
+            body: config["body"].as_str().unwrap_or_default().to_string(),
+            content_type: HeaderValue::from_str(content_type).map_err(|_| format!("content_type无效: {}", content_type))?,
+        })
+    }
+}
+
+impl Plugin for Mock {
+    fn on_request(&self, _req: &mut Request<Body>) -> Option<Response<Body>> {
+        let mut resp = Response::new(Body::from(self.body.clone()));
+        *resp.status_mut() = self.status;
+        resp.headers_mut().insert(header::CONTENT_TYPE, self.content_type.clone());
+        Some(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rule(plugins: Value) -> RouteRule {
+        RouteRule {
+            prefix: vec!["/plugin-test".to_string()],
+            upstream: vec!["http://localhost:30000".to_string()],
+            plugins: Some(serde_json::from_value(plugins).unwrap()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_schema_validation() {
+        let schema = Schema::new()
+            .required("status", FieldType::Integer)
+            .range(200, 599)
+            .field("mode", FieldType::String)
+            .one_of(&["a", "b"])
+            .field("tags", FieldType::StringList);
+        assert!(schema.validate(&json!({ "status": 503, "mode": "a", "tags": ["x"] })).is_empty());
+        assert_eq!(schema.validate(&Value::Null), vec!["缺少必填项: status"]);
+        assert_eq!(
+            schema.validate(&json!({ "status": 99, "mode": "c", "tags": [1], "extra": true })),
+            vec!["未知的配置项: extra", "status必须在200到599之间", "mode仅支持 a、b: c", "tags必须是字符串数组"]
+        );
+        assert_eq!(schema.validate(&json!("x")), vec!["config必须是表"]);
+    }
+
+    #[test]
+    fn test_route_plugin_validation() {
+        let valid = rule(json!([
+            { "name": "headers", "config": { "request_set": { "x-team": "orders" }, "response_remove": ["server"] } },
+            { "name": "mock", "config": { "status": 503, "body": "maintenance" } },
+        ]));
+        assert!(validation_errors(&valid).is_empty(), "{:?}", validation_errors(&valid));
+        assert_eq!(instances(&valid).len(), 2);
+
+        let invalid = rule(json!([
+            { "name": "nope" },
+            { "name": "headers", "config": { "request_set": { "bad header": "x" } } },
+            { "name": "mock" },
+        ]));
+        let errors = validation_errors(&invalid);
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        assert!(errors[0].starts_with("plugins[0]（nope）: 未注册的插件: nope，可用: "), "{}", errors[0]);
+        assert!(errors[1].contains("request_set包含无效的请求头名"), "{}", errors[1]);
+        assert_eq!(errors[2], "plugins[2]（mock）: 缺少必填项: status");
+    }
+
+    #[test]
+    fn test_register_custom_plugin() {
+        struct Deny;
+        impl Plugin for Deny {
+            fn on_request(&self, _req: &mut Request<Body>) -> Option<Response<Body>> {
+                Some(Response::builder().status(StatusCode::FORBIDDEN).body(Body::empty()).unwrap())
+            }
+        }
+        let schema = Schema::new().required("reason", FieldType::String);
+        assert!(register("deny-test", schema.clone(), |_| Ok(Arc::new(Deny))));
+        assert!(!register("deny-test", schema, |_| Ok(Arc::new(Deny))));
+        assert!(!register("mock", Schema::new(), |_| Ok(Arc::new(Deny))));
+        assert!(validation_errors(&rule(json!([{ "name": "deny-test", "config": { "reason": "x" } }]))).is_empty());
+    }
+}
@@ -0,0 +1,189 @@
+use std::time::Duration;
+
+use reqwest::Url;
+use tokio::net::{lookup_host, TcpStream};
+use tokio::task::JoinSet;
+
+use crate::config::{RouteRule, Settings};
+
+// 单个上游解析与连接的默认超时
+const DEFAULT_TIMEOUT_MS: u64 = 2000;
+
+/// 启动前对上游的检查方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreflightMode {
+    // 只做 DNS 解析
+    Resolve,
+    // 解析后建立一次 TCP 连接
+    Connect,
+}
+
+impl PreflightMode {
+    /// off 返回 Some(None)，非法值返回 None
+    pub fn parse(mode: &str) -> Option<Option<Self>> {
+        match mode {
+            "off" => Some(None),
+            "resolve" => Some(Some(Self::Resolve)),
+            "connect" => Some(Some(Self::Connect)),
+            _ => None,
+        }
+    }
+}
+
+/// 启动前检查的配置
+#[derive(Debug, Clone)]
+pub struct Preflight {
+    mode: PreflightMode,
+    fail_fast: bool,
+    timeout: Duration,
+    upstreams: Vec<String>,
+}
+
+impl Preflight {
+    /// 未配置 upstream_preflight 或为 off 时返回 None；上游模板按请求渲染，不检查
+    pub fn from_settings(settings: &Settings, rules: &[RouteRule]) -> Option<Self> {
+        let mode = PreflightMode::parse(settings.upstream_preflight.as_deref()?)??;
+        let mut upstreams: Vec<String> = rules
+            .iter()
+            .flat_map(|r| r.all_upstreams())
+            .filter(|u| !crate::upstream_template::is_template(u))
+            .cloned()
+            .collect();
+        upstreams.sort();
+        upstreams.dedup();
+        Some(Self {
+            mode,
+            fail_fast: settings.upstream_preflight_fail_fast.unwrap_or(false),
+            timeout: Duration::from_millis(settings.upstream_preflight_timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS)),
+            upstreams,
+        })
+    }
+
+    /// 并发检查所有上游，返回不可达的上游及原因（按上游地址排序）
+    pub async fn check(&self) -> Vec<(String, String)> {
+        let mut tasks = JoinSet::new();
+        for upstream in &self.upstreams {
+            let (upstream, mode, timeout) = (upstream.clone(), self.mode, self.timeout);
+            tasks.spawn(async move {
+                let result = tokio::time::timeout(timeout, check_upstream(&upstream, mode))
+                    .await
+                    .unwrap_or_else(|_| Err(format!("超时（{}ms）", timeout.as_millis())));
+                (upstream, result)
+            });
+        }
+        let mut failures = Vec::new();
+        while let Some(joined) = tasks.join_next().await {
+            if let Ok((upstream, Err(reason))) = joined {
+                failures.push((upstream, reason));
+            }
+        }
+        failures.sort();
+        failures
+    }
+
+    /// 执行检查并输出结果；fail_fast 时存在不可达的上游则返回错误，否则降级启动
+    pub async fn run(&self) -> anyhow::Result<()> {
+        let failures = self.check().await;
+        if failures.is_empty() {
+            tracing::info!(upstreams = self.upstreams.len(), "上游启动检查通过");
+            return Ok(());
+        }
+        let report: Vec<String> = failures.iter().map(|(upstream, reason)| format!("{}: {}", upstream, reason)).collect();
+        if self.fail_fast {
+            anyhow::bail!("上游启动检查未通过，{} 个上游不可达:\n  {}", failures.len(), report.join("\n  "));
+        }
+        for (upstream, reason) in &failures {
+            tracing::warn!(upstream = %upstream, reason = %reason, "上游不可达");
+        }
+        tracing::warn!(unreachable = failures.len(), total = self.upstreams.len(), "上游启动检查未通过，以降级状态启动");
+        Ok(())
+    }
+}
+
+async fn check_upstream(upstream: &str, mode: PreflightMode) -> Result<(), String> {
+    let url = Url::parse(upstream).map_err(|e| format!("地址无效: {}", e))?;
+    let host = url.host_str().ok_or("缺少主机名")?;
+    let port = url.port_or_known_default().ok_or("缺少端口")?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs: Vec<_> = lookup_host((host, port)).await.map_err(|e| format!("DNS 解析失败: {}", e))?.collect();
+    if addrs.is_empty() {
+        return Err("DNS 解析无结果".to_string());
+    }
+    if mode == PreflightMode::Resolve {
+        return Ok(());
+    }
+    let mut last_error = String::new();
+    for addr in addrs {
+        match TcpStream::connect(addr).await {
+            Ok(_) => return Ok(()),
+            Err(e) => last_error = format!("连接 {} 失败: {}", addr, e),
+        }
+    }
+    Err(last_error)
+}
+
+pub fn validation_errors(settings: &Settings) -> Vec<String> {
+    let mut errors = Vec::new();
+    if let Some(mode) = &settings.upstream_preflight
+        && PreflightMode::parse(mode).is_none()
+    {
+        errors.push(format!("upstream_preflight仅支持 off、resolve、connect: {}", mode));
+    }
+    if settings.upstream_preflight_timeout_ms == Some(0) {
+        errors.push("upstream_preflight_timeout_ms必须大于0".to_string());
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(extra: serde_json::Value) -> Settings {
+        let mut value = serde_json::json!({
+            "gateway_bind": "127.0.0.1:0",
+            "jwt_decoding_key": "test",
+            "global_qps": 100,
+            "client_qps": 100,
+        });
+        value.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_connect_preflight() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let live = format!("http://{}", listener.local_addr().unwrap());
+        // 取一个空闲端口后立即释放，作为不可达的上游
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dead = format!("http://{}", closed.local_addr().unwrap());
+        drop(closed);
+
+        let rules = vec![RouteRule {
+            prefix: vec!["/api/**".to_string()],
+            upstream: vec![live.clone(), dead.clone(), live.clone()],
+            ..Default::default()
+        }];
+        let preflight = Preflight::from_settings(&settings(serde_json::json!({ "upstream_preflight": "connect" })), &rules).unwrap();
+        assert_eq!(preflight.upstreams.len(), 2);
+        let failures = preflight.check().await;
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, dead);
+        // 默认降级启动，fail_fast 时报错
+        assert!(preflight.run().await.is_ok());
+        let strict = settings(serde_json::json!({ "upstream_preflight": "connect", "upstream_preflight_fail_fast": true }));
+        assert!(Preflight::from_settings(&strict, &rules).unwrap().run().await.is_err());
+
+        // 只解析时不建立连接
+        let resolve = Preflight::from_settings(&settings(serde_json::json!({ "upstream_preflight": "resolve" })), &rules).unwrap();
+        assert!(resolve.check().await.is_empty());
+        assert!(Preflight::from_settings(&settings(serde_json::json!({ "upstream_preflight": "off" })), &rules).is_none());
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(validation_errors(&settings(serde_json::json!({ "upstream_preflight": "connect" }))).is_empty());
+        assert_eq!(validation_errors(&settings(serde_json::json!({ "upstream_preflight": "ping" }))).len(), 1);
+        assert_eq!(validation_errors(&settings(serde_json::json!({ "upstream_preflight_timeout_ms": 0 }))).len(), 1);
+    }
+}
@@ -0,0 +1,295 @@
+use arc_swap::ArcSwap;
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderName, HeaderValue, Response, StatusCode},
+    middleware::Next,
+    response::IntoResponse,
+};
+use once_cell::sync::Lazy;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::config::Settings;
+
+/// 关联请求与错误响应的请求头，未携带时由网关生成并透传给上游
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+pub const CONTENT_TYPE: &str = "application/problem+json";
+
+/// 错误类型：slug 用于拼接 type URI，title 为该类错误的固定摘要
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProblemType {
+    BadRequest,
+    InvalidPath,
+    ValidationFailed,
+    UnsupportedApiVersion,
+    Unauthorized,
+    ProxyAuthenticationRequired,
+    Forbidden,
+    CorsOriginNotAllowed,
+    NotFound,
+    MethodNotAllowed,
+    PayloadTooLarge,
+    RateLimited,
+    Overloaded,
+    ServerBusy,
+    UpstreamSaturated,
+    NoUpstream,
+    UpstreamError,
+    UpstreamTimeout,
+    ServiceUnavailable,
+    Internal,
+}
+
+impl ProblemType {
+    pub const ALL: [ProblemType; 20] = [
+        ProblemType::BadRequest,
+        ProblemType::InvalidPath,
+        ProblemType::ValidationFailed,
+        ProblemType::UnsupportedApiVersion,
+        ProblemType::Unauthorized,
+        ProblemType::ProxyAuthenticationRequired,
+        ProblemType::Forbidden,
+        ProblemType::CorsOriginNotAllowed,
+        ProblemType::NotFound,
+        ProblemType::MethodNotAllowed,
+        ProblemType::PayloadTooLarge,
+        ProblemType::RateLimited,
+        ProblemType::Overloaded,
+        ProblemType::ServerBusy,
+        ProblemType::UpstreamSaturated,
+        ProblemType::NoUpstream,
+        ProblemType::UpstreamError,
+        ProblemType::UpstreamTimeout,
+        ProblemType::ServiceUnavailable,
+        ProblemType::Internal,
+    ];
+
+    pub fn slug(&self) -> &'static str {
+        match self {
+            ProblemType::BadRequest => "bad-request",
+            ProblemType::InvalidPath => "invalid-path",
+            ProblemType::ValidationFailed => "validation-failed",
+            ProblemType::UnsupportedApiVersion => "unsupported-api-version",
+            ProblemType::Unauthorized => "unauthorized",
+            ProblemType::ProxyAuthenticationRequired => "proxy-authentication-required",
+            ProblemType::Forbidden => "forbidden",
+            ProblemType::CorsOriginNotAllowed => "cors-origin-not-allowed",
+            ProblemType::NotFound => "not-found",
+            ProblemType::MethodNotAllowed => "method-not-allowed",
+            ProblemType::PayloadTooLarge => "payload-too-large",
+            ProblemType::RateLimited => "rate-limited",
+            ProblemType::Overloaded => "overloaded",
+            ProblemType::ServerBusy => "server-busy",
+            ProblemType::UpstreamSaturated => "upstream-saturated",
+            ProblemType::NoUpstream => "no-upstream",
+            ProblemType::UpstreamError => "upstream-error",
+            ProblemType::UpstreamTimeout => "upstream-timeout",
+            ProblemType::ServiceUnavailable => "service-unavailable",
+            ProblemType::Internal => "internal-error",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            ProblemType::BadRequest => "Bad Request",
+            ProblemType::InvalidPath => "Bad Request Path",
+            ProblemType::ValidationFailed => "Request Validation Failed",
+            ProblemType::UnsupportedApiVersion => "Unsupported API Version",
+            ProblemType::Unauthorized => "Unauthorized",
+            ProblemType::ProxyAuthenticationRequired => "Proxy Authentication Required",
+            ProblemType::Forbidden => "Forbidden",
+            ProblemType::CorsOriginNotAllowed => "CORS Origin Not Allowed",
+            ProblemType::NotFound => "Not Found",
+            ProblemType::MethodNotAllowed => "Method Not Allowed",
+            ProblemType::PayloadTooLarge => "Payload Too Large",
+            ProblemType::RateLimited => "Too Many Requests",
+            ProblemType::Overloaded => "Gateway Overloaded",
+            ProblemType::ServerBusy => "Server Busy",
+            ProblemType::UpstreamSaturated => "All Upstreams At Capacity",
+            ProblemType::NoUpstream => "No Upstream Available",
+            ProblemType::UpstreamError => "Bad Gateway",
+            ProblemType::UpstreamTimeout => "Gateway Timeout",
+            ProblemType::ServiceUnavailable => "Service Unavailable",
+            ProblemType::Internal => "Internal Server Error",
+        }
+    }
+
+    fn from_slug(slug: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.slug() == slug)
+    }
+}
+
+// ===== type URI 配置：按 slug 覆盖 > 基地址 + slug > about:blank =====
+#[derive(Debug, Default)]
+struct TypeUris {
+    base: Option<String>,
+    overrides: BTreeMap<String, String>,
+}
+
+impl TypeUris {
+    fn resolve(&self, kind: ProblemType) -> String {
+        if let Some(uri) = self.overrides.get(kind.slug()) {
+            return uri.clone();
+        }
+        match &self.base {
+            Some(base) => format!("{}{}", base, kind.slug()),
+            None => "about:blank".to_string(),
+        }
+    }
+}
+
+static TYPE_URIS: Lazy<ArcSwap<TypeUris>> = Lazy::new(|| ArcSwap::from_pointee(TypeUris::default()));
+
+/// 应用 problem_type_base / problem_types 配置
+pub fn configure(settings: &Settings) {
+    TYPE_URIS.store(Arc::new(TypeUris {
+        base: settings.problem_type_base.clone().filter(|b| !b.is_empty()),
+        overrides: settings.problem_types.clone(),
+    }));
+}
+
+/// 校验 problem_types 的键
+pub fn validation_errors(types: &BTreeMap<String, String>) -> Vec<String> {
+    types
+        .keys()
+        .filter(|slug| ProblemType::from_slug(slug).is_none())
+        .map(|slug| format!("problem_types包含未知的错误类型: {}", slug))
+        .collect()
+}
+
+// ===== 请求上下文：请求 ID 与路径，供任意位置生成的错误响应引用 =====
+#[derive(Debug, Clone)]
+struct RequestContext {
+    request_id: String,
+    path: String,
+}
+
+tokio::task_local! {
+    static CONTEXT: RequestContext;
+}
+
+// 沿用客户端传入的请求 ID（限长且为可见 ASCII），否则随机生成
+fn request_id(req: &Request) -> String {
+    req.headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= 128 && id.bytes().all(|b| b.is_ascii_graphic()))
+        .map(str::to_string)
+        .unwrap_or_else(|| hex::encode(rand::random::<[u8; 8]>()))
+}
+
+pub async fn request_id_layer(mut req: Request, next: Next) -> Response<Body> {
+    let id = request_id(&req);
+    let value = HeaderValue::from_str(&id).expect("request id is visible ASCII");
+    req.headers_mut().insert(REQUEST_ID_HEADER, value.clone());
+    let context = RequestContext { request_id: id, path: req.uri().path().to_string() };
+
+    let mut resp = CONTEXT.scope(context, next.run(req)).await;
+    resp.headers_mut().entry(REQUEST_ID_HEADER).or_insert(value);
+    resp
+}
+
+// ===== RFC 7807 错误响应 =====
+#[derive(Debug)]
+pub struct Problem {
+    status: StatusCode,
+    kind: ProblemType,
+    detail: Option<String>,
+    extensions: Map<String, Value>,
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl Problem {
+    pub fn new(status: StatusCode, kind: ProblemType) -> Self {
+        Self { status, kind, detail: None, extensions: Map::new(), headers: Vec::new() }
+    }
+
+    /// 本次错误的具体说明
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// 扩展字段，与标准字段同级输出
+    pub fn with(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.extensions.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// 附加响应头
+    pub fn header(mut self, name: HeaderName, value: impl TryInto<HeaderValue>) -> Self {
+        if let Ok(value) = value.try_into() {
+            self.headers.push((name, value));
+        }
+        self
+    }
+
+    fn body(&self) -> Value {
+        let mut body = Map::new();
+        body.insert("type".to_string(), TYPE_URIS.load().resolve(self.kind).into());
+        body.insert("title".to_string(), self.kind.title().into());
+        body.insert("status".to_string(), self.status.as_u16().into());
+        if let Some(detail) = &self.detail {
+            body.insert("detail".to_string(), detail.clone().into());
+        }
+        if let Ok(context) = CONTEXT.try_with(RequestContext::clone) {
+            body.insert("instance".to_string(), context.path.into());
+            body.insert("request_id".to_string(), context.request_id.into());
+        }
+        for (key, value) in &self.extensions {
+            body.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+        Value::Object(body)
+    }
+}
+
+impl IntoResponse for Problem {
+    fn into_response(self) -> axum::response::Response {
+        let mut builder = Response::builder().status(self.status).header(header::CONTENT_TYPE, CONTENT_TYPE);
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        builder.body(Body::from(self.body().to_string())).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_type_uris() {
+        let uris = TypeUris {
+            base: Some("https://errors.example.com/".to_string()),
+            overrides: BTreeMap::from([("rate-limited".to_string(), "https://docs.example.com/quota".to_string())]),
+        };
+        assert_eq!(uris.resolve(ProblemType::NoUpstream), "https://errors.example.com/no-upstream");
+        assert_eq!(uris.resolve(ProblemType::RateLimited), "https://docs.example.com/quota");
+        assert_eq!(TypeUris::default().resolve(ProblemType::NoUpstream), "about:blank");
+
+        assert!(validation_errors(&uris.overrides).is_empty());
+        assert_eq!(validation_errors(&BTreeMap::from([("oops".to_string(), String::new())])).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_problem_body() {
+        let problem = Problem::new(StatusCode::TOO_MANY_REQUESTS, ProblemType::RateLimited)
+            .detail("Rate limit exceeded at tenant level")
+            .with("scope", "tenant")
+            .with("status", 999);
+        let context = RequestContext { request_id: "abc123".to_string(), path: "/proxy/orders".to_string() };
+        let body = CONTEXT.scope(context, async { problem.body() }).await;
+        assert_eq!(body["title"], "Too Many Requests");
+        assert_eq!(body["status"], 429);
+        assert_eq!(body["scope"], "tenant");
+        assert_eq!(body["instance"], "/proxy/orders");
+        assert_eq!(body["request_id"], "abc123");
+
+        // 请求上下文之外生成的错误不带 instance
+        let body = Problem::new(StatusCode::BAD_GATEWAY, ProblemType::UpstreamError).body();
+        assert!(body.get("instance").is_none());
+    }
+}
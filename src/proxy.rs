@@ -1,37 +1,137 @@
 use axum::{
     body::Body,
-    extract::Request,
-    http::Response,
+    extract::{ConnectInfo, Request},
+    http::{HeaderMap, Response, StatusCode},
+    response::IntoResponse,
     routing::any,
-    Router, middleware,
+    Router,
 };
 use reqwest::Client;
 use tracing::info;
 use crate::config::Settings;
-use crate::rate_limit::rate_limit_layer;
+use crate::metrics::{GaugeGuard, RESILIENCE_COUNTER, UPSTREAM_COUNTER, UPSTREAM_IN_FLIGHT, UPSTREAM_PROTOCOL};
+use crate::debug::{DebugTrace, DEBUG_HEADER};
+use crate::problem::{Problem, ProblemType};
+use crate::buffering::{Buffering, Flush, ReadError, RequestBody, DEFAULT_MAX_BODY_BYTES};
+use crate::header_case::SendError;
+use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use dashmap::DashMap;
 use once_cell::sync::Lazy;
-use crate::load_balancer::{RoundRobinBalancer, WeightedRandomBalancer, IpHashBalancer, LoadBalancer, WeightedUpstream};
+use crate::load_balancer::{RoundRobinBalancer, LoadBalancer, BalancerSnapshot};
 use axum::middleware::Next;
-use axum::http::HeaderValue;
+use axum::http::{HeaderValue, Method};
 
 // ===== 全局客户端 =====
+/// 单域名最大空闲连接数
+pub const POOL_MAX_IDLE_PER_HOST: usize = 1000;
+
 /// 全局 HTTP 客户端（高并发优化）
-pub static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
-    Client::builder()
-        // 单域名最大空闲连接数，提高并发处理能力
-        .pool_max_idle_per_host(1000)
+pub static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| build_client(&ClientOptions::default()));
+
+/// 按路由连接偏好区分的上游客户端
+static ROUTE_CLIENTS: Lazy<DashMap<ClientOptions, Client>> = Lazy::new(DashMap::new);
+
+/// 上游连接偏好：HTTP 版本、keep-alive、TCP_NODELAY，跟随重定向的跳数（0 表示不跟随），
+/// 以及连接超时、读取空闲超时与客户端级总超时（配置了分阶段超时的路由不设总超时，由请求单独控制）
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClientOptions {
+    pub http_version: Option<String>,
+    pub keep_alive: bool,
+    pub tcp_nodelay: bool,
+    pub max_redirects: usize,
+    pub connect_timeout: Duration,
+    pub read_timeout: Option<Duration>,
+    pub total_timeout: Option<Duration>,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            http_version: None,
+            keep_alive: true,
+            tcp_nodelay: true,
+            max_redirects: 0,
+            connect_timeout: crate::timeouts::DEFAULT_CONNECT,
+            read_timeout: None,
+            total_timeout: Some(Duration::from_secs(10)),
+        }
+    }
+}
+
+impl ClientOptions {
+    pub fn for_route(rule: &crate::config::RouteRule) -> Self {
+        let mut opts = Self {
+            http_version: rule.http_version.clone(),
+            keep_alive: rule.keep_alive.unwrap_or(true),
+            tcp_nodelay: rule.tcp_nodelay.unwrap_or(true),
+            max_redirects: crate::redirect::RedirectPolicy::for_route(rule).max_hops(),
+            ..Self::default()
+        };
+        if crate::timeouts::Timeouts::split(rule) {
+            let timeouts = crate::timeouts::Timeouts::for_route(rule, Duration::ZERO);
+            opts.connect_timeout = timeouts.connect;
+            opts.read_timeout = timeouts.idle;
+            opts.total_timeout = None;
+        }
+        opts
+    }
+}
+
+fn build_client(opts: &ClientOptions) -> Client {
+    let mut builder = Client::builder()
+        // 记录新建连接的 DNS 解析与建连耗时，用于 Server-Timing
+        .dns_resolver(Arc::new(crate::server_timing::TimedResolver))
+        .connector_layer(crate::server_timing::ConnectTiming)
+        // 单域名最大空闲连接数，提高并发处理能力；关闭 keep-alive 时不保留空闲连接
+        .pool_max_idle_per_host(if opts.keep_alive { POOL_MAX_IDLE_PER_HOST } else { 0 })
         // 空闲连接在 90 秒后自动回收，防止无限增长
         .pool_idle_timeout(Some(Duration::from_secs(90)))
-        // 全局请求超时，避免慢请求阻塞连接池
-        .timeout(Duration::from_secs(10))
         // TCP 连接建立超时
-        .connect_timeout(Duration::from_secs(5))
-        .build()
-        .expect("Failed to build HTTP client")
-});
+        .connect_timeout(opts.connect_timeout)
+        .tcp_nodelay(opts.tcp_nodelay)
+        // 默认不跟随上游重定向，Location 原样交给客户端
+        .redirect(match opts.max_redirects {
+            0 => reqwest::redirect::Policy::none(),
+            hops => reqwest::redirect::Policy::limited(hops),
+        });
+    // 全局请求超时，避免慢请求阻塞连接池
+    if let Some(timeout) = opts.total_timeout {
+        builder = builder.timeout(timeout);
+    }
+    // 两次读取之间的空闲超时
+    if let Some(timeout) = opts.read_timeout {
+        builder = builder.read_timeout(timeout);
+    }
+    match opts.http_version.as_deref() {
+        Some("http1") => builder = builder.http1_only(),
+        Some("http2") => builder = builder.http2_prior_knowledge(),
+        _ => {}
+    }
+    builder.build().expect("Failed to build HTTP client")
+}
+
+/// 记住上游协商出的协议；同一上游的协议发生变化（如升级或回退到 HTTP/1.1）时输出日志
+fn record_protocol(upstream: &str, protocol: crate::upstream::Protocol) {
+    UPSTREAM_PROTOCOL.with_label_values(&[upstream, protocol.as_str()]).inc();
+    match crate::upstream::record_protocol(upstream, protocol) {
+        None => tracing::debug!(upstream = %upstream, protocol = protocol.as_str(), "上游协议已确定"),
+        Some(previous) if previous != protocol => {
+            tracing::info!(upstream = %upstream, from = previous.as_str(), to = protocol.as_str(), "上游协议发生变化")
+        }
+        Some(_) => {}
+    }
+}
+
+/// 路由对应的上游客户端，默认偏好直接复用全局客户端
+pub fn client_for(rule: &crate::config::RouteRule) -> Client {
+    let opts = ClientOptions::for_route(rule);
+    if opts == ClientOptions::default() {
+        return HTTP_CLIENT.clone();
+    }
+    ROUTE_CLIENTS.entry(opts.clone()).or_insert_with(|| build_client(&opts)).clone()
+}
 
 // ===== 全局负载均衡器存储 =====
 static BALANCERS: Lazy<DashMap<String, Arc<dyn LoadBalancer + Send + Sync>>> = Lazy::new(DashMap::new);
@@ -42,164 +142,799 @@ pub struct WhitelistBypass;
 
 // ===== 代理服务路由 =====
 pub fn router() -> Router {
-    use crate::auth::JwtAuth;
-
+    // 中间件阶段按配置动态组装，见 pipeline 模块
     Router::new()
-        .route("/*path", any(proxy_handler))
-        // 执行顺序（自下而上）：check_whitelist -> JwtAuth -> propagate_auth_headers
-        .route_layer(middleware::from_fn(propagate_auth_headers))
-        .route_layer(middleware::from_extractor::<JwtAuth>())
-        .route_layer(middleware::from_fn(check_whitelist_middleware))
-        .layer(axum::middleware::from_fn(rate_limit_layer))
+        .route("/*path", any(crate::pipeline::dispatch))
+        .layer(axum::middleware::from_fn(crate::debug::debug_layer))
+        // 最外层：先规范化 URL，再做路由解析与各中间件阶段
+        .layer(axum::middleware::from_fn(crate::normalize::normalize_layer))
 }
 
 // ===== 代理处理器 =====
-async fn proxy_handler(req: Request<Body>) -> Response<Body> {
+pub async fn proxy_handler(mut req: Request<Body>) -> Response<Body> {
+    // 请求抓取在插件之外，记录客户端实际发出的请求与收到的响应
+    let capture = req
+        .extensions()
+        .get::<crate::pipeline::MatchedRoute>()
+        .cloned()
+        .and_then(|matched| crate::capture::start(&matched.0, &mut req));
+    let resp = run_plugins(req).await;
+    match capture {
+        Some(capture) => capture.finish(resp),
+        None => resp,
+    }
+}
+
+async fn run_plugins(mut req: Request<Body>) -> Response<Body> {
+    // 路由插件：依次处理请求，任一插件直接应答时不再转发；响应按相反顺序交给处理过请求的插件
+    let plugins = req
+        .extensions()
+        .get::<crate::pipeline::MatchedRoute>()
+        .map(|matched| crate::plugin::instances(&matched.0))
+        .unwrap_or_default();
+    if plugins.is_empty() {
+        return proxy_route(req).await;
+    }
+    let mut ran = 0;
+    let mut answered = None;
+    for plugin in &plugins {
+        ran += 1;
+        answered = plugin.on_request(&mut req);
+        if answered.is_some() {
+            break;
+        }
+    }
+    let mut resp = match answered {
+        Some(resp) => resp,
+        None => proxy_route(req).await,
+    };
+    for plugin in plugins[..ran].iter().rev() {
+        plugin.on_response(&mut resp);
+    }
+    resp
+}
+
+async fn proxy_route(mut req: Request<Body>) -> Response<Body> {
+    // 协议升级句柄需在借用请求之前取出
+    let on_upgrade = req.extensions_mut().remove::<hyper::upgrade::OnUpgrade>();
     let settings = req.extensions().get::<Settings>().cloned();
-    let route_rules = req.extensions().get::<Vec<crate::config::RouteRule>>().cloned();
+    let matched = req.extensions().get::<crate::pipeline::MatchedRoute>().cloned();
+    let trace = req.extensions().get::<Arc<DebugTrace>>().cloned();
+    let client_addr = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|ci| ci.0);
+    let route_start = Instant::now();
 
     // 去掉 /proxy 前缀
     let full_path = req.uri().path();
     let match_path = full_path.strip_prefix("/proxy").unwrap_or(full_path);
-    let query_suffix = req.uri().query().map(|q| format!("?{}", q)).unwrap_or_default();
-
-    // 选择上游
-    let selected: Option<(String, String)> = if let Some(rules) = &route_rules {
-        if let Some(best_match) = find_best_match(rules, match_path) {
-            let path_variables = best_match.extract_variables(match_path);
-            let selected_upstream = get_or_create_balancer(&best_match.upstream, &best_match.strategy)
-                .select(None)
-                .unwrap_or_else(|| best_match.upstream[0].clone());
-            let forward_path = reconstruct_forward_path(match_path, &best_match.prefix, &path_variables);
-            Some((selected_upstream, forward_path))
-        } else {
-            None
+    let query = req.uri().query();
+
+    // QoS 准入：并发已满时按等级排队（同等级内按调用方公平出队），许可持有到响应返回
+    let _permit = match (&matched, req.extensions().get::<Arc<crate::qos::Scheduler>>()) {
+        (Some(crate::pipeline::MatchedRoute(rule)), Some(scheduler)) => {
+            let no_classes = Default::default();
+            let api_key_classes = settings.as_ref().map_or(&no_classes, |s| &s.qos_api_key_classes);
+            let claims = req.extensions().get::<crate::auth::JwtAuth>().map(|jwt| &jwt.0);
+            let class = crate::qos::classify(rule, req.headers(), claims, api_key_classes);
+            if let Some(trace) = &trace {
+                trace.set("qos-class", class.as_str().to_string());
+            }
+            let flow = crate::qos::flow(req.headers(), claims, client_addr.map(|addr| addr.ip()));
+            match scheduler.acquire(class, &flow).await {
+                Ok(permit) => Some(permit),
+                Err(rejected) => {
+                    tracing::warn!(route = %route_label(rule), class = class.as_str(), reason = rejected.as_str(), "QoS 拒绝请求");
+                    return Problem::new(StatusCode::SERVICE_UNAVAILABLE, ProblemType::ServerBusy)
+                        .detail(format!("No admission slot for {} priority request ({})", class.as_str(), rejected.as_str()))
+                        .header(axum::http::header::RETRY_AFTER, "1")
+                        .into_response();
+                }
+            }
+        }
+        _ => None,
+    };
+
+    // 选择上游（跳过已摘流、预热中、熔断中的节点）
+    let selected = if let Some(crate::pipeline::MatchedRoute(best_match)) = &matched {
+        let best_match = best_match.as_ref();
+        // 对象存储路由直接读取对象，不经过上游选择
+        if let Some(origin) = &best_match.s3 {
+            let timeout = settings.as_ref().map(Settings::request_timeout).unwrap_or(Duration::from_secs(10));
+            return crate::s3::serve(origin, &best_match.prefix, match_path, req.method(), req.headers(), timeout).await;
+        }
+        let path_variables = best_match.extract_variables(match_path);
+        let route = route_label(best_match);
+        // API 版本：版本配置了上游组时转发到该组，未配置的版本直接拒绝
+        let version = match best_match.versioning.as_ref().map(|v| v.resolve(match_path, req.headers())) {
+            Some(Err(version)) => {
+                return Problem::new(StatusCode::BAD_REQUEST, ProblemType::UnsupportedApiVersion)
+                    .detail(format!("Unsupported API version: {}", version))
+                    .with("version", version)
+                    .into_response();
+            }
+            Some(Ok(version)) => version,
+            None => None,
+        };
+        if let (Some(trace), Some((version, _))) = (&trace, &version) {
+            trace.set("api-version", version.clone());
+        }
+        // 租户专属上游：优先于版本上游组与区域选择
+        let tenant_upstreams = req
+            .extensions()
+            .get::<crate::auth::JwtAuth>()
+            .and_then(|jwt| crate::tenant_upstreams::upstreams(best_match, &route, &jwt.0.tenant_id));
+        let balancer = match (&version, tenant_upstreams) {
+            (_, Some(upstreams)) => {
+                if let Some(trace) = &trace {
+                    trace.set("tenant-upstream", upstreams.join(","));
+                }
+                get_or_create_balancer(&upstreams, &best_match.strategy)
+            }
+            (Some((_, api)), None) if !api.upstream.is_empty() => get_or_create_balancer(&api.upstream, &best_match.strategy),
+            // 上游模板：按路径变量与 JWT 声明渲染出本次请求的上游组
+            _ if crate::upstream_template::is_templated(best_match) => {
+                let claims = req.extensions().get::<crate::auth::JwtAuth>().map(|jwt| &jwt.0);
+                match crate::upstream_template::render_upstreams(best_match, &path_variables, claims) {
+                    Ok(upstreams) => {
+                        if let Some(trace) = &trace {
+                            trace.set("upstream-template", upstreams.join(","));
+                        }
+                        get_or_create_balancer(&upstreams, &best_match.strategy)
+                    }
+                    Err(err) => {
+                        tracing::warn!(route = %route, "上游模板渲染失败: {}", err);
+                        let (status, problem_type) = match err {
+                            crate::upstream_template::TemplateError::HostNotAllowed(_) => (StatusCode::FORBIDDEN, ProblemType::Forbidden),
+                            _ => (StatusCode::BAD_REQUEST, ProblemType::BadRequest),
+                        };
+                        return Problem::new(status, problem_type).detail(err.to_string()).into_response();
+                    }
+                }
+            }
+            _ => route_balancer(best_match, &route, trace.as_deref()),
+        };
+        let selected_upstream = match select_upstream(balancer.as_ref(), client_addr.as_ref(), &route, &[]) {
+            Ok(upstream) => upstream,
+            Err(Unavailable::Saturated) => {
+                record_resilience_event("saturated", &route, "*");
+                return Problem::new(StatusCode::SERVICE_UNAVAILABLE, ProblemType::UpstreamSaturated)
+                    .detail("All upstreams are at capacity")
+                    .header(axum::http::header::RETRY_AFTER, "1")
+                    .into_response();
+            }
+            Err(Unavailable::NoUpstream) => {
+                return Problem::new(StatusCode::SERVICE_UNAVAILABLE, ProblemType::NoUpstream)
+                    .detail(format!("No available upstream for path: {}", match_path))
+                    .into_response();
+            }
+        };
+        let forward_path = reconstruct_forward_path(match_path, &best_match.prefix, &path_variables);
+        if let Some(trace) = &trace {
+            trace.set("route", best_match.prefix.join(","));
         }
+        Some((selected_upstream, forward_path, client_for(best_match), balancer, route, best_match, version.map(|(_, api)| api)))
     } else {
         None
     };
 
-    let (upstream, forward_path) = match selected {
+    let (mut upstream, forward_path, client, balancer, route, rule, api_version) = match selected {
         Some(v) => v,
         None => {
-            return Response::builder()
-                .status(502)
-                .header(axum::http::header::CONTENT_TYPE, "application/json; charset=utf-8")
-                .body(Body::from(format!("{{\"error\":\"No upstream configured for path: {}\"}}", match_path)))
-                .unwrap();
+            return Problem::new(StatusCode::BAD_GATEWAY, ProblemType::NoUpstream)
+                .detail(format!("No upstream configured for path: {}", match_path))
+                .into_response();
         }
     };
 
+    // 转发时去掉的网关路径前缀，用于改写上游重定向
+    let external_base = crate::redirect::external_base(full_path, &forward_path).to_string();
+
+    // 可选：命中负缓存时不再访问上游
+    let cache_key = rule.cache.as_ref().filter(|_| crate::response_cache::cacheable(req.method())).map(|policy| {
+        let claims = req.extensions().get::<crate::auth::JwtAuth>().map(|jwt| &jwt.0);
+        crate::response_cache::key(policy, &route, req.method(), match_path, query, req.headers(), claims)
+    });
+    if let Some(key) = &cache_key
+        && let Some(cached) = crate::response_cache::lookup(key, req.method())
+    {
+        if let Some(trace) = &trace {
+            trace.set("cache", "hit".to_string());
+        }
+        return cached;
+    }
+
+    // 按路由规则改写查询参数，未配置则原样透传
+    let query_suffix = match &rule.query {
+        Some(rewrite) => rewrite.apply(query),
+        None => query.map(|q| format!("?{}", q)).unwrap_or_default(),
+    };
+
     info!("路径匹配: {} -> {} (转发到: {})", match_path, forward_path, upstream);
+    if let Some(trace) = &trace {
+        trace.set("forward-path", forward_path.clone());
+        trace.phase("route", route_start);
+    }
+
+    // TLS 终止时把协商结果与客户端证书以配置的请求头转发给上游
+    if let Some(settings) = &settings {
+        crate::mtls::forward_headers(&settings.tls_headers, &mut req);
+    }
+
+    let method = req.method().clone();
+    // HEAD 原样以 HEAD 转发：不读取请求体，也不缓冲响应体
+    let head = method == Method::HEAD;
+    let req_headers = req.headers().clone();
+    // 保留请求头大小写：转发时需要客户端请求扩展中记录的原始写法
+    let header_case = rule.preserve_header_case.unwrap_or(false).then(|| req.extensions().clone());
 
-    // 构建 reqwest 请求
-    let mut rb = HTTP_CLIENT
-        .request(req.method().clone(), format!("{}{}{}", upstream, forward_path, query_suffix));
+    // 协议升级：路由允许该协议时建立隧道；否则不转发 Upgrade 头，按普通请求处理
+    if let Some(protocol) = crate::tunnel::requested_protocol(&req_headers)
+        && rule.upgrade.as_deref().is_some_and(|allowed| crate::tunnel::allows(allowed, protocol))
+        && let Some(on_upgrade) = on_upgrade
+    {
+        if let Some(trace) = &trace {
+            trace.set("upgrade", protocol.to_string());
+        }
+        let url = format!("{}{}{}", upstream, forward_path, query_suffix);
+        // WebSocket 连接按帧执行路由限制，其他协议原样拷贝
+        let limits = rule
+            .websocket
+            .clone()
+            .filter(|_| protocol.split(',').any(|p| p.trim().eq_ignore_ascii_case("websocket")));
+        let tenant = req.extensions().get::<crate::auth::JwtAuth>().map(|jwt| jwt.0.tenant_id.clone());
+        return crate::tunnel::tunnel(on_upgrade, url, method, &req_headers, route, tenant, limits).await;
+    }
 
-    // 设置超时
-    if let Some(s) = &settings {
-        rb = rb.timeout(s.request_timeout());
+    // 请求体：缓冲时整体读入（可用于重试与校验），否则直接流式转发
+    let buffering = Buffering::for_route(rule);
+    // 免鉴权的请求使用 whitelist_limits 中更严格的限制
+    let whitelist_limits = rule.whitelist_limits.as_ref().filter(|_| req.extensions().get::<WhitelistBypass>().is_some());
+    let whitelist_body_limit = whitelist_limits.and_then(|limits| limits.max_request_body_bytes);
+    let mut request_body = if head {
+        RequestBody::Buffered(Default::default())
+    } else if buffering.request() || whitelist_body_limit.is_some() {
+        let limit = rule.max_request_body_bytes.unwrap_or(DEFAULT_MAX_BODY_BYTES);
+        let limit = whitelist_body_limit.map_or(limit, |whitelisted| whitelisted.min(limit));
+        match crate::buffering::read_request(req.into_body(), limit).await {
+            Ok(bytes) => RequestBody::Buffered(bytes),
+            Err(ReadError::TooLarge(limit)) => {
+                return Problem::new(StatusCode::PAYLOAD_TOO_LARGE, ProblemType::PayloadTooLarge)
+                    .detail(format!("Request body exceeds {} bytes", limit))
+                    .into_response();
+            }
+            Err(ReadError::Body(err)) => {
+                return Problem::new(StatusCode::INTERNAL_SERVER_ERROR, ProblemType::Internal)
+                    .detail(format!("Body read error: {}", err))
+                    .into_response();
+            }
+        }
+    } else {
+        RequestBody::streaming(req.into_body())
+    };
+
+    // 按路由配置的 JSON Schema 校验请求体（无请求体的 GET/HEAD/DELETE 等跳过）
+    if let Some(schema) = &rule.request_schema
+        && let RequestBody::Buffered(body_bytes) = &request_body
+        && (!body_bytes.is_empty() || matches!(method, Method::POST | Method::PUT | Method::PATCH))
+        && let Err(violations) = crate::schema::validate_request(schema, &req_headers, body_bytes)
+    {
+        return Problem::new(StatusCode::BAD_REQUEST, ProblemType::ValidationFailed)
+            .detail("Request body does not match the route schema")
+            .with("violations", serde_json::to_value(violations).unwrap_or_default())
+            .into_response();
     }
 
+    let resilience = settings.as_ref().map(Resilience::from_settings).unwrap_or_default();
+    let mut tried: Vec<String> = Vec::new();
+    let upstream_start = Instant::now();
+    let _in_flight = GaugeGuard::new(&UPSTREAM_IN_FLIGHT);
+
     // 复制 headers
-    for (name, value) in req.headers().iter() {
-        if name == &axum::http::header::HOST { continue; }
-        rb = rb.header(name, value);
+    let mut forward_headers = HeaderMap::new();
+    for (name, value) in req_headers.iter() {
+        if name == axum::http::header::HOST || name == axum::http::header::UPGRADE || name == DEBUG_HEADER { continue; }
+        forward_headers.append(name, value.clone());
+    }
+    let mut timeouts = crate::timeouts::Timeouts::for_route(rule, settings.as_ref().map(Settings::request_timeout).unwrap_or(Duration::from_secs(10)));
+    if let Some(limit) = whitelist_limits.and_then(crate::whitelist::WhitelistLimits::timeout) {
+        timeouts = timeouts.cap(limit);
     }
 
-    // 读取请求体并转换为reqwest::Body
-    let body_bytes = match axum::body::to_bytes(req.into_body(), usize::MAX).await {
-        Ok(bytes) => bytes,
-        Err(err) => {
-            return Response::builder()
-                .status(500)
-                .header(axum::http::header::CONTENT_TYPE, "application/json; charset=utf-8")
-                .body(Body::from(format!("{{\"error\":\"Body read error: {}\"}}", err)))
-                .unwrap();
+    // 向一个上游发送一次请求并计入该上游的结果与熔断状态；对冲落败被丢弃的请求不计入
+    let send = |upstream: String, body: reqwest::Body| {
+        let url = format!("{}{}{}", upstream, forward_path, query_suffix);
+        let (method, headers, client, header_case, route) =
+            (method.clone(), forward_headers.clone(), client.clone(), header_case.clone(), route.clone());
+        async move {
+            let _upstream_in_flight = crate::upstream::InFlightGuard::new(&upstream);
+            let attempt_start = Instant::now();
+            let (mut result, connection) = crate::server_timing::measure(async move {
+                match header_case {
+                    Some(extensions) => {
+                        crate::header_case::send(method, &url, headers, extensions, body, timeouts.first_byte.or(timeouts.total)).await
+                    }
+                    None => {
+                        // 构建 reqwest 请求
+                        let mut rb = client.request(method, url).headers(headers);
+                        // 总超时覆盖读取响应体；首字节超时只约束等待响应头
+                        if let Some(total) = timeouts.total {
+                            rb = rb.timeout(total);
+                        }
+                        let pending = rb.body(body).send();
+                        match timeouts.first_byte {
+                            Some(first_byte) => match tokio::time::timeout(first_byte, pending).await {
+                                Ok(result) => result.map_err(SendError::from),
+                                Err(_) => Err(SendError::Timeout),
+                            },
+                            None => pending.await.map_err(SendError::from),
+                        }
+                    }
+                }
+            })
+            .await;
+            if let Ok(resp) = &mut result {
+                resp.extensions_mut().insert(crate::server_timing::Attempt { connection, ttfb: attempt_start.elapsed() });
+            }
+
+            let failed = match &result {
+                Ok(resp) => resp.status().is_server_error(),
+                Err(_) => true,
+            };
+            crate::upstream::record_result(&upstream, failed);
+            if let Ok(resp) = &result
+                && let Some(protocol) = crate::upstream::Protocol::from_version(resp.version())
+            {
+                record_protocol(&upstream, protocol);
+            }
+            if failed {
+                if let Some(threshold) = resilience.breaker_failures
+                    && crate::upstream::record_failure(&upstream, threshold, resilience.breaker_open)
+                {
+                    record_resilience_event("breaker_open", &route, &upstream);
+                }
+            } else if crate::upstream::record_success(&upstream) {
+                record_resilience_event("breaker_close", &route, &upstream);
+            }
+            (upstream, result)
         }
     };
+    // 重试次数：路由配置了 retry 时按其策略（含方法限制），否则沿用全局 upstream_retries
+    let retry = rule.retry.as_ref();
+    let max_retries = retry.map_or(resilience.retries, |policy| policy.retries(&method));
+    // 重试及其退避计入总时长（含白名单请求的超时上限），不会让请求超过截止时间
+    let deadline = timeouts.total.map(|total| upstream_start + total);
+    // 请求对冲：需要可重发的请求体
+    let hedge_after = rule.hedge_after_ms.filter(|_| request_body.replayable()).map(Duration::from_millis);
 
-    // 流式转发 body
-    let resp_result = rb
-        .body(body_bytes)
-        .send()
-        .await;
+    // 流量镜像：按比例把请求副本发往影子上游，影子响应不返回给客户端
+    let shadow = match (&rule.mirror, &request_body) {
+        (Some(policy), RequestBody::Buffered(body)) if crate::mirror::sampled(policy) => {
+            let url = format!("{}{}{}", policy.upstream, forward_path, query_suffix);
+            let mut request = client.request(method.clone(), url).headers(forward_headers.clone()).body(body.clone());
+            if let Some(total) = timeouts.total.or(timeouts.first_byte) {
+                request = request.timeout(total);
+            }
+            let limit = rule.max_response_body_bytes.unwrap_or(DEFAULT_MAX_BODY_BYTES);
+            crate::mirror::send(policy, &route, &method, &forward_path, request, limit)
+        }
+        _ => None,
+    };
+
+    let resp_result = loop {
+        let primary = send(upstream.clone(), request_body.take());
+        let (attempted, result) = match hedge_after {
+            Some(delay) => {
+                let exclude: Vec<String> = tried.iter().cloned().chain([upstream.clone()]).collect();
+                let hedge = || {
+                    let next = select_upstream(balancer.as_ref(), client_addr.as_ref(), &route, &exclude).ok()?;
+                    Some(send(next, request_body.take()))
+                };
+                let succeeded = |(_, result): &(String, Result<reqwest::Response, SendError>)| {
+                    result.as_ref().is_ok_and(|resp| !resp.status().is_server_error())
+                };
+                crate::hedging::race(&route, delay, primary, hedge, succeeded).await.1
+            }
+            None => primary.await,
+        };
+        upstream = attempted;
+
+        // 连接错误换节点重试；路由的 retry 还会重试指定的状态码。流式请求体已被消费，无法重发
+        let retryable = match &result {
+            Ok(resp) => retry.is_some_and(|policy| policy.retries_status(resp.status())),
+            Err(_) => true,
+        };
+        // 截止时间已过，或退避结束时会超过截止时间，则不再重试，返回本次结果
+        let backoff = retry.map_or(Duration::ZERO, |policy| policy.backoff(tried.len() as u32 + 1));
+        let in_time = deadline.is_none_or(|deadline| Instant::now() + backoff < deadline);
+        if retryable && in_time && request_body.replayable() && tried.len() < max_retries as usize {
+            let outcome = match &result {
+                Ok(resp) if !resp.status().is_server_error() => "ok",
+                Ok(_) => "5xx",
+                Err(_) => "error",
+            };
+            UPSTREAM_COUNTER.with_label_values(&[&upstream, outcome]).inc();
+            tried.push(upstream.clone());
+            // 优先换到未尝试过的节点；路由的 retry 在节点都试过后仍可重试已尝试的节点
+            let next = select_upstream(balancer.as_ref(), client_addr.as_ref(), &route, &tried)
+                .or_else(|err| match retry {
+                    Some(_) => select_upstream(balancer.as_ref(), client_addr.as_ref(), &route, &[]),
+                    None => Err(err),
+                });
+            if let Ok(next) = next {
+                record_resilience_event("retry", &route, &next);
+                if !backoff.is_zero() {
+                    tokio::time::sleep(backoff).await;
+                }
+                upstream = next;
+                continue;
+            }
+        }
+        break result;
+    };
+
+    if let Some(trace) = &trace {
+        trace.set("upstream", upstream.clone());
+    }
 
     match resp_result {
         Ok(resp) => {
+            if let Some(trace) = &trace {
+                trace.phase("upstream", upstream_start);
+                if let Some(protocol) = crate::upstream::Protocol::from_version(resp.version()) {
+                    trace.set("upstream-protocol", protocol.as_str().to_string());
+                }
+            }
+            // 可选：转发的响应附上 Server-Timing（本次转发各阶段的耗时）
+            let attempt = rule.server_timing.unwrap_or(false).then(|| resp.extensions().get().copied()).flatten();
+            let timed = |mut response: Response<Body>| {
+                if let Some(attempt) = &attempt {
+                    crate::server_timing::apply(&mut response, attempt, upstream.starts_with("https://"), route_start.elapsed());
+                }
+                response
+            };
             let status = resp.status();
-            let headers = resp.headers().clone();
+            let mut headers = resp.headers().clone();
+            // 镜像对比：缓冲的响应读完后连同响应体对比，HEAD 与流式响应只对比状态码与响应头
+            let shadow = match shadow {
+                Some(shadow) if head || !buffering.response() => {
+                    shadow.compare(crate::mirror::Observed { status, headers: headers.clone(), body: None });
+                    None
+                }
+                shadow => shadow.map(|shadow| (shadow, headers.clone())),
+            };
+            let outcome = if status.is_server_error() { "5xx" } else { "ok" };
+            UPSTREAM_COUNTER.with_label_values(&[&upstream, outcome]).inc();
 
-            let mut builder = Response::builder().status(status);
+            // 去掉敏感的上游响应头，并限制响应头总大小
+            if let Some(settings) = &settings
+                && let Err(size) = crate::header_filter::HeaderFilter::new(settings, rule).apply(&mut headers)
+            {
+                tracing::warn!(route = %route, upstream = %upstream, size, "上游响应头超过大小上限");
+                return Problem::new(StatusCode::BAD_GATEWAY, ProblemType::UpstreamError)
+                    .detail(format!("Upstream response headers exceed {} bytes", settings.max_response_header_bytes.unwrap_or_default()))
+                    .into_response();
+            }
 
-            // 转发响应头
-            for (name, value) in headers.iter() {
-                builder = builder.header(name, value);
+            // 指向上游的 Location 改写为网关对外的路径
+            if crate::redirect::RedirectPolicy::for_route(rule) == crate::redirect::RedirectPolicy::Rewrite {
+                let upstreams: Vec<&str> = rule.all_upstreams().map(String::as_str).chain([upstream.as_str()]).collect();
+                crate::redirect::rewrite_location(&mut headers, &external_base, &upstreams, &upstream);
             }
 
-            // 兜底 Content-Type
-            if !builder.headers_ref().map(|h| h.contains_key(axum::http::header::CONTENT_TYPE)).unwrap_or(false) {
-                builder = builder.header(axum::http::header::CONTENT_TYPE, "application/octet-stream");
+            // 弃用版本附带 Deprecation / Sunset / Link 头
+            if let Some(api) = api_version {
+                api.apply_headers(&mut headers);
+            }
+
+            // HEAD 响应没有响应体：直接转发上游的响应头（含 Content-Length），不受响应体大小上限约束
+            if head {
+                return timed(forward_response(status, &headers, Body::empty()));
+            }
+
+            // 不缓冲响应体：收到响应头即开始向客户端转发
+            if !buffering.response() {
+                let Some(limit) = rule.max_streamed_response_bytes else {
+                    return timed(forward_response(status, &headers, Flush::for_route(rule).body(resp)));
+                };
+                if resp.content_length().is_some_and(|len| len > limit as u64) {
+                    return response_too_large(&route, &upstream, "streamed", limit);
+                }
+                let body = crate::buffering::limit_body(Flush::for_route(rule).body(resp), limit, route.clone());
+                return timed(forward_response(status, &headers, body));
             }
 
             // 读取响应体
-            let bytes = match resp.bytes().await {
+            let limit = rule.max_response_body_bytes.unwrap_or(DEFAULT_MAX_BODY_BYTES);
+            let mut bytes = match crate::buffering::read_response(resp, limit).await {
                 Ok(bytes) => bytes,
-                Err(err) => {
-                    return Response::builder()
-                        .status(500)
-                        .header(axum::http::header::CONTENT_TYPE, "application/json; charset=utf-8")
-                        .body(Body::from(format!("{{\"error\":\"Response body error: {}\"}}", err)))
-                        .unwrap();
+                Err(ReadError::TooLarge(limit)) => return response_too_large(&route, &upstream, "buffered", limit),
+                Err(ReadError::Body(err)) => {
+                    return Problem::new(StatusCode::BAD_GATEWAY, ProblemType::UpstreamError)
+                        .detail(format!("Response body error: {}", err))
+                        .into_response();
                 }
             };
+            if let Some((shadow, headers)) = shadow {
+                shadow.compare(crate::mirror::Observed { status, headers, body: Some(bytes.clone()) });
+            }
+
+            // 需要检查响应体的路由：解压上游响应，再按客户端 Accept-Encoding 重新压缩
+            if rule.decompress.unwrap_or(false) {
+                let accept = req_headers.get(axum::http::header::ACCEPT_ENCODING).and_then(|v| v.to_str().ok());
+                let policy = settings
+                    .as_ref()
+                    .map(crate::compression::CompressionPolicy::from_settings)
+                    .unwrap_or(crate::compression::CompressionPolicy { min_bytes: 1024, skip_types: &[] });
+                // 解压后的明文同样受缓冲上限约束
+                match crate::compression::transcode(&mut headers, bytes.clone(), accept, &policy, limit) {
+                    Ok(transcoded) => bytes = transcoded,
+                    Err(err) if err.kind() == std::io::ErrorKind::FileTooLarge => {
+                        return response_too_large(&route, &upstream, "decompressed", limit);
+                    }
+                    Err(err) => tracing::warn!(route = %route, "响应体解压失败，原样转发: {}", err),
+                }
+            }
 
-            builder.body(Body::from(bytes)).unwrap()
+            if let (Some(policy), Some(key)) = (&rule.cache, cache_key) {
+                crate::response_cache::store(policy, key, status, &headers, &bytes);
+            }
+
+            // 可选：补全 ETag，条件请求命中时直接返回 304
+            if rule.etag.unwrap_or(false)
+                && let Some(not_modified) = crate::etag::apply(&method, &req_headers, status, &mut headers, &bytes)
+            {
+                return timed(not_modified);
+            }
+
+            if let Some(trace) = &trace {
+                trace.phase("body", upstream_start);
+            }
+            timed(forward_response(status, &headers, Body::from(bytes)))
         }
-        Err(err) => Response::builder()
-            .status(500)
-            .header(axum::http::header::CONTENT_TYPE, "application/json; charset=utf-8")
-            .body(Body::from(format!("{{\"error\":\"Proxy error: {}\"}}", err)))
-            .unwrap(),
+        Err(err) => {
+            UPSTREAM_COUNTER.with_label_values(&[&upstream, "error"]).inc();
+            let (status, kind) = if err.is_timeout() {
+                (StatusCode::GATEWAY_TIMEOUT, ProblemType::UpstreamTimeout)
+            } else {
+                (StatusCode::BAD_GATEWAY, ProblemType::UpstreamError)
+            };
+            Problem::new(status, kind).detail(format!("Proxy error: {}", err)).into_response()
+        }
+    }
+}
+
+// 上游响应体超过路由的大小上限：stage 为 buffered（缓冲读取）、decompressed（解压后）或 streamed（Content-Length）
+fn response_too_large(route: &str, upstream: &str, stage: &str, limit: usize) -> Response<Body> {
+    tracing::warn!(route, upstream, stage, limit, "上游响应体超过大小上限");
+    crate::metrics::RESPONSE_TOO_LARGE.with_label_values(&[route, stage]).inc();
+    Problem::new(StatusCode::BAD_GATEWAY, ProblemType::UpstreamError)
+        .detail(format!("Upstream response body exceeds {} bytes", limit))
+        .with("limit", limit)
+        .into_response()
+}
+
+// 转发上游响应头并补全 Content-Type
+fn forward_response(status: StatusCode, headers: &HeaderMap, body: Body) -> Response<Body> {
+    let mut builder = Response::builder().status(status);
+    for (name, value) in headers.iter() {
+        builder = builder.header(name, value);
+    }
+
+    // 兜底 Content-Type
+    if !headers.contains_key(axum::http::header::CONTENT_TYPE) {
+        builder = builder.header(axum::http::header::CONTENT_TYPE, "application/octet-stream");
+    }
+    builder.body(body).unwrap()
+}
+
+// ===== 容错参数（重试与熔断） =====
+#[derive(Debug, Clone, Copy, Default)]
+struct Resilience {
+    retries: u32,
+    // 连续失败达到该次数后熔断；None 表示不启用熔断
+    breaker_failures: Option<u32>,
+    breaker_open: Duration,
+}
+
+impl Resilience {
+    fn from_settings(settings: &Settings) -> Self {
+        Self {
+            retries: settings.upstream_retries.unwrap_or(0),
+            breaker_failures: settings.circuit_breaker_failures.filter(|n| *n > 0),
+            breaker_open: Duration::from_secs(settings.circuit_breaker_open_secs.unwrap_or(30)),
+        }
+    }
+}
+
+// 多区域路由：按探测延迟依次尝试各区域，选中第一个有可用节点的区域
+fn route_balancer(
+    rule: &crate::config::RouteRule,
+    route: &str,
+    trace: Option<&DebugTrace>,
+) -> Arc<dyn LoadBalancer + Send + Sync> {
+    // 异常防护切换到 fallback 上游时优先于区域选择
+    if rule.anomaly.is_some()
+        && let Some(fallback) = crate::anomaly::fallback_upstreams(route)
+    {
+        if let Some(trace) = trace {
+            trace.set("anomaly-fallback", fallback.join(","));
+        }
+        return get_or_create_balancer(&fallback, &rule.strategy);
+    }
+    let Some(regions) = &rule.regions else {
+        return get_or_create_balancer(&rule.upstream, &rule.strategy);
+    };
+
+    let ranked = crate::region::ranked_regions(regions);
+    let chosen = ranked
+        .iter()
+        .position(|(_, upstreams)| upstreams.iter().any(|u| crate::upstream::is_available(u)))
+        .unwrap_or(0);
+    let Some((region, upstreams)) = ranked.get(chosen) else {
+        return get_or_create_balancer(&rule.upstream, &rule.strategy);
+    };
+    if chosen > 0 {
+        record_resilience_event("region_failover", route, region);
+    }
+    if let Some(trace) = trace {
+        trace.set("region", region.to_string());
+    }
+    get_or_create_balancer(upstreams, &rule.strategy)
+}
+
+/// 路由在指标与日志中的标识：优先使用 id，否则为前缀列表
+pub fn route_label(rule: &crate::config::RouteRule) -> String {
+    rule.id.clone().unwrap_or_else(|| rule.prefix.join(","))
+}
+
+// 选不出上游的原因
+enum Unavailable {
+    NoUpstream,
+    // 有可分配的节点，但均已达到并发上限
+    Saturated,
+}
+
+// 优先选择完整可用的节点；若全部不可用（预热中或熔断中），退而选择未摘流的节点并记录 fallback。
+// 已达并发上限的节点在两轮中都会被跳过，所有候选都饱和时才拒绝
+fn select_upstream(
+    balancer: &(dyn LoadBalancer + Send + Sync),
+    client_addr: Option<&SocketAddr>,
+    route: &str,
+    exclude: &[String],
+) -> Result<String, Unavailable> {
+    let not_tried = |u: &str| !exclude.iter().any(|t| t == u);
+    let open = |u: &str| not_tried(u) && crate::upstream::has_capacity(u);
+    if let Some(upstream) = balancer.select_available(client_addr, &|u| crate::upstream::is_available(u) && open(u)) {
+        return Ok(upstream);
+    }
+    if let Some(upstream) = balancer.select_available(client_addr, &|u| crate::upstream::is_routable(u) && open(u)) {
+        record_resilience_event("fallback", route, &upstream);
+        return Ok(upstream);
     }
+    match balancer.select_available(client_addr, &|u| crate::upstream::is_routable(u) && not_tried(u)) {
+        Some(_) => Err(Unavailable::Saturated),
+        None => Err(Unavailable::NoUpstream),
+    }
+}
+
+// 容错行为同时记录结构化日志与计数器，便于审计
+fn record_resilience_event(event: &str, route: &str, upstream: &str) {
+    RESILIENCE_COUNTER.with_label_values(&[event, route, upstream]).inc();
+    tracing::warn!(event, route, upstream, "容错事件");
 }
 
 // ===== 获取或创建负载均衡器 =====
+fn balancer_key(upstreams: &[String], strategy: &str) -> String {
+    format!("{}:{}", strategy, upstreams.join(","))
+}
+
 fn get_or_create_balancer(upstreams: &[String], strategy: &str) -> Arc<dyn LoadBalancer + Send + Sync> {
-    let key = format!("{}:{}", strategy, upstreams.join(","));
+    let key = balancer_key(upstreams, strategy);
     BALANCERS
         .entry(key.clone())
         .or_insert_with(|| {
-            match strategy {
-                "random" => Arc::new(WeightedRandomBalancer::new(
-                    upstreams.iter().map(|u| WeightedUpstream {
-                        url: u.clone(),
-                        weight: 1,
-                    }).collect()
-                )),
-                "iphash" => Arc::new(IpHashBalancer::new(upstreams.to_vec())),
-                _ => Arc::new(RoundRobinBalancer::new(upstreams.to_vec())), // 默认轮询
-            }
+            // 按策略名从注册表创建，未注册的策略（校验已拒绝）退回轮询
+            crate::load_balancer::registry::create(strategy, upstreams)
+                .unwrap_or_else(|| Arc::new(RoundRobinBalancer::new(upstreams.to_vec())))
         })
         .clone()
 }
 
+/// 为所有路由预先创建负载均衡器，保证管理端修改能作用于尚未产生流量的路由
+pub fn ensure_balancers(rules: &[crate::config::RouteRule]) {
+    // 上游模板在请求时才能渲染出实际地址
+    for rule in rules.iter().filter(|r| !r.upstream.is_empty() && !crate::upstream_template::is_templated(r)) {
+        get_or_create_balancer(&rule.upstream, &rule.strategy);
+        for api in rule.versioning.iter().flat_map(|v| v.versions.values()) {
+            if !api.upstream.is_empty() {
+                get_or_create_balancer(&api.upstream, &rule.strategy);
+            }
+        }
+    }
+}
+
+/// 路由使用的负载均衡器键（与 ensure_balancers 创建的一致）
+pub fn balancer_keys(rules: &[crate::config::RouteRule]) -> std::collections::BTreeSet<String> {
+    let mut keys = std::collections::BTreeSet::new();
+    for rule in rules.iter().filter(|r| !r.upstream.is_empty() && !crate::upstream_template::is_templated(r)) {
+        keys.insert(balancer_key(&rule.upstream, &rule.strategy));
+        for api in rule.versioning.iter().flat_map(|v| v.versions.values()) {
+            if !api.upstream.is_empty() {
+                keys.insert(balancer_key(&api.upstream, &rule.strategy));
+            }
+        }
+    }
+    keys
+}
+
+/// 调整上游权重的结果：已生效的负载均衡器，以及包含该上游但策略不支持权重的负载均衡器
+#[derive(Debug, Default)]
+pub struct WeightChange {
+    pub updated: Vec<String>,
+    pub unsupported: Vec<String>,
+}
+
+// ===== 修改所有（或指定）负载均衡器中某个上游的权重 =====
+pub fn set_upstream_weight(upstream: &str, weight: u32, balancer: Option<&str>) -> WeightChange {
+    let mut change = WeightChange::default();
+    for entry in BALANCERS.iter() {
+        if balancer.is_some_and(|key| key != entry.key()) {
+            continue;
+        }
+        if !entry.value().snapshot().upstreams.iter().any(|u| u.url == upstream) {
+            continue;
+        }
+        if entry.value().set_weight(upstream, weight) {
+            change.updated.push(entry.key().clone());
+        } else {
+            change.unsupported.push(entry.key().clone());
+        }
+    }
+    change.updated.sort();
+    change.unsupported.sort();
+    change
+}
+
+// ===== 已实例化的负载均衡器快照（按 strategy:upstreams 键） =====
+pub fn balancer_snapshots() -> Vec<(String, BalancerSnapshot)> {
+    let mut snapshots: Vec<(String, BalancerSnapshot)> = BALANCERS
+        .iter()
+        .map(|entry| (entry.key().clone(), entry.value().snapshot()))
+        .collect();
+    snapshots.sort_by(|a, b| a.0.cmp(&b.0));
+    snapshots
+}
+
 // ===== 查找最佳匹配规则（预编译正则可选） =====
-fn find_best_match<'a>(rules: &'a [crate::config::RouteRule], path: &str) -> Option<&'a crate::config::RouteRule> {
-    let mut best_match: Option<&crate::config::RouteRule> = None;
-    let mut best_score = 0;
+pub fn find_best_match<'a>(
+    rules: &'a [crate::config::RouteRule],
+    path: &str,
+    headers: &HeaderMap,
+) -> Option<&'a crate::config::RouteRule> {
+    find_best_match_scored(rules, path, headers).map(|(rule, _)| rule)
+}
+
+/// 同 find_best_match，并返回命中规则的得分；得分相同时配置了更多谓词（Content-Type、Accept、定时、特性开关）的规则优先，
+/// 不在生效时段内的定时路由与特性开关未命中的路由不参与匹配
+pub fn find_best_match_scored<'a>(
+    rules: &'a [crate::config::RouteRule],
+    path: &str,
+    headers: &HeaderMap,
+) -> Option<(&'a crate::config::RouteRule, i32)> {
+    let mut best_match: Option<(&crate::config::RouteRule, i32)> = None;
 
     for rule in rules {
-        if rule.matches(path) {
-            let score = rule.prefix.iter().map(|p| {
-                if p.contains('{') || p.contains('*') || p.contains('?') {
-                    1000 + p.len() as i32
-                } else { p.len() as i32 }
-            }).max().unwrap_or(0);
-
-            if score > best_score {
-                best_score = score;
-                best_match = Some(rule);
+        if rule.matches(path)
+            && rule.matches_headers(headers)
+            && crate::schedule::active(rule)
+            && crate::feature_flags::active(rule, headers)
+        {
+            let score = route_score(rule);
+            let better = match best_match {
+                None => score > 0,
+                Some((best, s)) => score > s || (score == s && rule.predicate_count() > best.predicate_count()),
+            };
+            if better {
+                best_match = Some((rule, score));
             }
         }
     }
@@ -207,8 +942,17 @@ fn find_best_match<'a>(rules: &'a [crate::config::RouteRule], path: &str) -> Opt
     best_match
 }
 
+/// 模式前缀优先于普通前缀，同类按长度比较
+pub fn route_score(rule: &crate::config::RouteRule) -> i32 {
+    rule.prefix.iter().map(|p| {
+        if p.contains('{') || p.contains('*') || p.contains('?') {
+            1000 + p.len() as i32
+        } else { p.len() as i32 }
+    }).max().unwrap_or(0)
+}
+
 // ===== 重构转发路径 =====
-fn reconstruct_forward_path(
+pub fn reconstruct_forward_path(
     original_path: &str,
     prefixes: &[String],
     _variables: &std::collections::HashMap<String, String>,
@@ -222,57 +966,110 @@ fn reconstruct_forward_path(
 }
 
 // ===== 白名单检查中间件 =====
-async fn check_whitelist_middleware(mut req: Request<Body>, next: Next) -> Response<Body> {
+pub async fn check_whitelist_middleware(mut req: Request<Body>, next: Next) -> Response<Body> {
     let path = req.uri().path();
     let match_path = path.strip_prefix("/proxy").unwrap_or(path);
 
-    if let Some(rules) = req.extensions().get::<Vec<crate::config::RouteRule>>() {
-        // 找到第一个匹配的路由，检查其 whitelist 是否命中
-        if let Some(rule) = find_best_match(rules, match_path) {
-            if let Some(whitelist) = &rule.whitelist {
-                // 任意一个白名单模式命中即可
-                let hit = whitelist.iter().any(|w| {
-                    // 复用 RouteRule 的匹配逻辑
-                    // 这里把单个白名单项当作一个前缀来匹配
-                    if w.contains('{') || w.contains('*') || w.contains('?') {
-                        crate::path_matcher::RoutePattern::from_pattern(w)
-                            .map(|rp| rp.matches(match_path))
-                            .unwrap_or(false)
-                    } else {
-                        match_path == w || match_path.starts_with(&format!("{}/", w))
-                    }
-                });
-                if hit {
-                    // 标记跳过鉴权
-                    req.extensions_mut().insert(WhitelistBypass);
-                }
-            }
+    // 检查已解析路由的 whitelist 是否命中
+    let matched = req.extensions().get::<crate::pipeline::MatchedRoute>().map(|matched| matched.0.clone());
+    let Some(rule) = matched.filter(|rule| crate::whitelist::hit(rule, req.method(), match_path)) else {
+        return next.run(req).await;
+    };
+    // 免鉴权的请求另按客户端 IP 限流（限流豁免名单同样适用）
+    if let Some(qps) = rule.whitelist_limits.as_ref().and_then(|limits| limits.client_qps)
+        && let Some(limits) = req.extensions().get::<Arc<crate::rate_limit::RateLimits>>()
+    {
+        let client_ip = crate::rate_limit::client_ip(&req);
+        let cost = crate::rate_limit::request_cost(&req);
+        if !limits.exemptions.is_exempt(&client_ip, req.headers(), req.extensions().get::<Settings>())
+            && let Err(limited) = limits.admit_whitelisted(&route_label(&rule), qps, &client_ip, cost)
+        {
+            return crate::rate_limit::limited_response(&limited, cost);
         }
     }
+    // 标记跳过鉴权
+    req.extensions_mut().insert(WhitelistBypass);
 
     next.run(req).await
 }
 
 // ===== 透传租户和用户id信息中间件 =====
-async fn propagate_auth_headers(mut req: Request<Body>, next: Next) -> Response<Body> {
+pub async fn propagate_auth_headers(mut req: Request<Body>, next: Next) -> Response<Body> {
     // 先提取 JWT 信息，避免借用冲突
     let (uid, tenant_id) = if let Some(jwt) = req.extensions().get::<crate::auth::JwtAuth>() {
         (jwt.0.sub.clone(), jwt.0.tenant_id.clone())
     } else {
         (String::new(), String::new())
     };
+
+    // 未识别出身份（匿名、白名单、none 模式或 shadow 模式下校验失败）：不论何种模式都去掉客户端自带的身份头，防止伪造
+    if uid.is_empty() {
+        req.headers_mut().remove("uid");
+        req.headers_mut().remove("tenant_id");
+    }
+    // optional 模式：告知上游鉴权结果
+    let mode = req.extensions().get::<crate::auth::RouteAuth>().map(|ra| ra.mode);
+    if mode == Some(crate::auth::AuthMode::Optional) {
+        let status = if uid.is_empty() { "anonymous" } else { "authenticated" };
+        req.headers_mut().insert(crate::auth::AUTH_STATUS_HEADER, HeaderValue::from_static(status));
+    }
     
     // 然后修改 headers
-    if !uid.is_empty() {
-        if let Ok(v) = HeaderValue::from_str(&uid) {
-            req.headers_mut().insert("uid", v);
-        }
+    if !uid.is_empty() && let Ok(v) = HeaderValue::from_str(&uid) {
+        req.headers_mut().insert("uid", v);
     }
-    if !tenant_id.is_empty() {
-        if let Ok(v) = HeaderValue::from_str(&tenant_id) {
-            req.headers_mut().insert("tenant_id", v);
-        }
+    if !tenant_id.is_empty() && let Ok(v) = HeaderValue::from_str(&tenant_id) {
+        req.headers_mut().insert("tenant_id", v);
     }
     
     next.run(req).await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RouteRule;
+
+    #[test]
+    fn test_client_options_for_route() {
+        assert_eq!(ClientOptions::for_route(&RouteRule::default()), ClientOptions::default());
+
+        let rule = RouteRule {
+            http_version: Some("http1".to_string()),
+            keep_alive: Some(false),
+            tcp_nodelay: Some(false),
+            ..Default::default()
+        };
+        let opts = ClientOptions::for_route(&rule);
+        assert_eq!(opts.http_version.as_deref(), Some("http1"));
+        assert!(!opts.keep_alive);
+        assert!(!opts.tcp_nodelay);
+        assert_eq!(opts.total_timeout, Some(Duration::from_secs(10)));
+
+        // 分阶段超时的路由由请求单独控制总时长
+        let rule = RouteRule { connect_timeout_ms: Some(200), idle_timeout_ms: Some(1000), ..Default::default() };
+        let opts = ClientOptions::for_route(&rule);
+        assert_eq!(opts.connect_timeout, Duration::from_millis(200));
+        assert_eq!(opts.read_timeout, Some(Duration::from_millis(1000)));
+        assert_eq!(opts.total_timeout, None);
+    }
+
+    #[test]
+    fn test_client_for() {
+        // 默认偏好复用全局客户端，不单独建立
+        client_for(&RouteRule::default());
+        assert!(!ROUTE_CLIENTS.contains_key(&ClientOptions::default()));
+
+        // 偏好相同的路由共用一个客户端，不同的偏好各自建立
+        let http1 = RouteRule { http_version: Some("http1".to_string()), tcp_nodelay: Some(false), ..Default::default() };
+        let same = RouteRule { prefix: vec!["/other/**".to_string()], ..http1.clone() };
+        let no_keep_alive = RouteRule { keep_alive: Some(false), ..http1.clone() };
+        client_for(&http1);
+        client_for(&same);
+        client_for(&no_keep_alive);
+        assert_eq!(ClientOptions::for_route(&http1), ClientOptions::for_route(&same));
+        assert!(ROUTE_CLIENTS.contains_key(&ClientOptions::for_route(&http1)));
+        assert!(ROUTE_CLIENTS.contains_key(&ClientOptions::for_route(&no_keep_alive)));
+        assert_ne!(ClientOptions::for_route(&http1), ClientOptions::for_route(&no_keep_alive));
+    }
+}
@@ -0,0 +1,445 @@
+use axum::http::HeaderMap;
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+use crate::config::{RouteRule, Settings};
+use crate::metrics::{QOS_COUNTER, QOS_QUEUED};
+
+/// 优先级等级，数值越大越先被调度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Class {
+    Low = 0,
+    Normal = 1,
+    High = 2,
+    // 不受并发上限约束，用于健康检查等必须及时响应的接口
+    Critical = 3,
+}
+
+impl Class {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "low" => Some(Class::Low),
+            "normal" => Some(Class::Normal),
+            "high" => Some(Class::High),
+            "critical" => Some(Class::Critical),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Class::Low => "low",
+            Class::Normal => "normal",
+            Class::High => "high",
+            Class::Critical => "critical",
+        }
+    }
+}
+
+/// 请求的等级：调用方等级（API Key 档位，其次 JWT 的 priority 声明）优先，其次路由的 priority，默认 normal
+pub fn classify(
+    rule: &RouteRule,
+    headers: &HeaderMap,
+    claims: Option<&crate::auth::Claims>,
+    api_key_classes: &BTreeMap<String, String>,
+) -> Class {
+    let api_key_class = headers
+        .get(crate::rate_limit::API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|key| api_key_classes.get(key))
+        .and_then(|class| Class::parse(class));
+    let claim_class = claims.and_then(|c| c.priority.as_deref()).and_then(Class::parse);
+    let route_class = rule.priority.as_deref().and_then(Class::parse);
+    api_key_class.or(claim_class).or(route_class).unwrap_or(Class::Normal)
+}
+
+/// 未获准入的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejected {
+    // 排队已满，或排队中被更高等级的请求挤出
+    Shed,
+    Timeout,
+}
+
+impl Rejected {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rejected::Shed => "shed",
+            Rejected::Timeout => "timeout",
+        }
+    }
+}
+
+/// 公平排队的调用方：X-API-Key，其次 JWT 的 tenant_id，再次客户端 IP
+pub fn flow(headers: &HeaderMap, claims: Option<&crate::auth::Claims>, client_ip: Option<IpAddr>) -> String {
+    let api_key = headers.get(crate::rate_limit::API_KEY_HEADER).and_then(|v| v.to_str().ok()).filter(|k| !k.is_empty());
+    let tenant = claims.map(|c| c.tenant_id.as_str()).filter(|t| !t.is_empty());
+    match (api_key, tenant, client_ip) {
+        (Some(key), _, _) => key.to_string(),
+        (None, Some(tenant), _) => tenant.to_string(),
+        (None, None, Some(ip)) => ip.to_string(),
+        (None, None, None) => String::new(),
+    }
+}
+
+// 虚拟时间的单位：权重为 1 的调用方每个请求推进 VIRTUAL_UNIT
+const VIRTUAL_UNIT: u64 = 1_000_000;
+
+/// 单个等级的加权公平队列：每个请求的虚拟完成时间 = max(当前虚拟时间, 该调用方上一个请求的完成时间) + 单位/权重，
+/// 按完成时间先后出队。同一调用方内部仍是先到先得，突发的调用方只会排在自己之前的请求之后，不会挤在其他调用方前面
+#[derive(Default)]
+struct FairQueue {
+    // (虚拟完成时间, 入队序号) -> (调用方, 等待者)
+    waiters: BTreeMap<(u64, u64), (String, oneshot::Sender<()>)>,
+    // 调用方 -> (排队中的请求数, 最后一个请求的虚拟完成时间)
+    flows: HashMap<String, (usize, u64)>,
+    virtual_time: u64,
+    seq: u64,
+}
+
+impl FairQueue {
+    fn len(&self) -> usize {
+        self.waiters.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.waiters.is_empty()
+    }
+
+    // 调用方下一个请求的虚拟完成时间
+    fn finish_tag(&self, flow: &str, weight: u32) -> u64 {
+        let start = self.flows.get(flow).map_or(self.virtual_time, |(_, last)| (*last).max(self.virtual_time));
+        start + VIRTUAL_UNIT / u64::from(weight.max(1))
+    }
+
+    fn push(&mut self, flow: String, weight: u32, tx: oneshot::Sender<()>) {
+        let tag = self.finish_tag(&flow, weight);
+        let entry = self.flows.entry(flow.clone()).or_default();
+        *entry = (entry.0 + 1, tag);
+        self.seq += 1;
+        self.waiters.insert((tag, self.seq), (flow, tx));
+    }
+
+    fn forget(&mut self, flow: &str) {
+        if let Some(entry) = self.flows.get_mut(flow) {
+            entry.0 -= 1;
+            if entry.0 == 0 {
+                self.flows.remove(flow);
+            }
+        }
+    }
+
+    /// 取出虚拟完成时间最早的等待者
+    fn pop(&mut self) -> Option<oneshot::Sender<()>> {
+        let ((tag, _), (flow, tx)) = self.waiters.pop_first()?;
+        self.virtual_time = self.virtual_time.max(tag);
+        self.forget(&flow);
+        Some(tx)
+    }
+
+    /// 挤出虚拟完成时间最晚的等待者（积压最多的调用方的最后一个请求）
+    fn evict_last(&mut self) {
+        if let Some((_, (flow, _))) = self.waiters.pop_last() {
+            self.forget(&flow);
+        }
+    }
+
+    fn last_tag(&self) -> Option<u64> {
+        self.waiters.last_key_value().map(|((tag, _), _)| *tag)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    in_use: usize,
+    // 按等级排队的等待者，下标为 Class 的取值；critical 不排队
+    queues: [FairQueue; 3],
+}
+
+impl State {
+    fn queued(&self) -> usize {
+        self.queues.iter().map(FairQueue::len).sum()
+    }
+}
+
+// ===== 准入调度：并发达到上限后按等级排队，空出的名额优先交给高等级，同等级内在调用方之间公平分配 =====
+pub struct Scheduler {
+    max_concurrency: usize,
+    max_queue: usize,
+    queue_timeout: Duration,
+    // 调用方（API Key 或租户）-> 公平排队权重，默认 1
+    weights: BTreeMap<String, u32>,
+    state: Mutex<State>,
+}
+
+/// 准入许可，释放时把名额交给排队中等级最高的请求
+pub struct Permit {
+    scheduler: Option<Arc<Scheduler>>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if let Some(scheduler) = self.scheduler.take() {
+            scheduler.release();
+        }
+    }
+}
+
+impl Scheduler {
+    pub fn new(max_concurrency: usize, max_queue: usize, queue_timeout: Duration, weights: BTreeMap<String, u32>) -> Arc<Self> {
+        Arc::new(Self { max_concurrency, max_queue, queue_timeout, weights, state: Mutex::new(State::default()) })
+    }
+
+    /// 未配置 qos_max_concurrency 时不启用
+    pub fn from_settings(settings: &Settings) -> Option<Arc<Self>> {
+        let max_concurrency = settings.qos_max_concurrency.filter(|n| *n > 0)?;
+        Some(Self::new(
+            max_concurrency,
+            settings.qos_max_queue.unwrap_or(max_concurrency),
+            Duration::from_millis(settings.qos_queue_timeout_ms.unwrap_or(1000)),
+            settings.qos_fair_weights.clone(),
+        ))
+    }
+
+    fn permit(self: &Arc<Self>) -> Permit {
+        Permit { scheduler: Some(self.clone()) }
+    }
+
+    /// 申请准入：有空闲名额立即通过，否则以调用方 flow 排队直至获得名额、被挤出或超时
+    pub async fn acquire(self: &Arc<Self>, class: Class, flow: &str) -> Result<Permit, Rejected> {
+        if class == Class::Critical {
+            QOS_COUNTER.with_label_values(&[class.as_str(), "admitted"]).inc();
+            return Ok(Permit { scheduler: None });
+        }
+
+        let mut rx = {
+            let mut state = self.state.lock().unwrap();
+            if state.in_use < self.max_concurrency {
+                state.in_use += 1;
+                QOS_COUNTER.with_label_values(&[class.as_str(), "admitted"]).inc();
+                return Ok(self.permit());
+            }
+            let weight = self.weights.get(flow).copied().unwrap_or(1);
+            if state.queued() >= self.max_queue {
+                // 排队已满：挤出等级更低的最后一个等待者；同等级中有排在自己之后的（积压更多的调用方）则挤出它，否则拒绝自己
+                let queue = &state.queues[class as usize];
+                let tag = queue.finish_tag(flow, weight);
+                let victim = (0..class as usize)
+                    .find(|&c| !state.queues[c].is_empty())
+                    .or_else(|| queue.last_tag().is_some_and(|last| last > tag).then_some(class as usize));
+                match victim {
+                    Some(c) => {
+                        state.queues[c].evict_last();
+                        QOS_QUEUED.dec();
+                    }
+                    None => {
+                        QOS_COUNTER.with_label_values(&[class.as_str(), Rejected::Shed.as_str()]).inc();
+                        return Err(Rejected::Shed);
+                    }
+                }
+            }
+            let (tx, rx) = oneshot::channel();
+            state.queues[class as usize].push(flow.to_string(), weight, tx);
+            QOS_QUEUED.inc();
+            rx
+        };
+
+        let result = match tokio::time::timeout(self.queue_timeout, &mut rx).await {
+            Ok(Ok(())) => Ok(self.permit()),
+            // 发送端被丢弃：被高等级请求挤出
+            Ok(Err(_)) => Err(Rejected::Shed),
+            Err(_) => {
+                // 超时与名额交付可能同时发生，关闭后再检查一次，避免名额丢失
+                rx.close();
+                match rx.try_recv() {
+                    Ok(()) => Ok(self.permit()),
+                    Err(_) => Err(Rejected::Timeout),
+                }
+            }
+        };
+        let outcome = match &result {
+            Ok(_) => "admitted",
+            Err(rejected) => rejected.as_str(),
+        };
+        QOS_COUNTER.with_label_values(&[class.as_str(), outcome]).inc();
+        result
+    }
+
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap();
+        // 从最高等级开始交付名额；等待者已超时离开则跳过
+        for queue in state.queues.iter_mut().rev() {
+            while let Some(tx) = queue.pop() {
+                QOS_QUEUED.dec();
+                if tx.send(()).is_ok() {
+                    return;
+                }
+            }
+        }
+        state.in_use -= 1;
+    }
+
+    pub fn max_concurrency(&self) -> usize {
+        self.max_concurrency
+    }
+
+    /// 当前占用的名额与排队数
+    pub fn usage(&self) -> (usize, usize) {
+        let state = self.state.lock().unwrap();
+        (state.in_use, state.queued())
+    }
+}
+
+/// 校验等级名称与公平排队权重
+pub fn validation_errors(settings: &Settings) -> Vec<String> {
+    let mut errors: Vec<String> = settings
+        .qos_api_key_classes
+        .iter()
+        .filter(|(_, class)| Class::parse(class).is_none())
+        .map(|(key, class)| format!("qos_api_key_classes.{}的等级非法: {}（可选 low/normal/high/critical）", key, class))
+        .collect();
+    for (flow, weight) in &settings.qos_fair_weights {
+        if *weight == 0 {
+            errors.push(format!("qos_fair_weights.{}必须大于0", flow));
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let classes = BTreeMap::from([("bulk-key".to_string(), "low".to_string())]);
+        let rule = RouteRule { priority: Some("high".to_string()), ..Default::default() };
+        let mut headers = HeaderMap::new();
+        assert_eq!(classify(&rule, &headers, None, &classes), Class::High);
+        assert_eq!(classify(&RouteRule::default(), &headers, None, &classes), Class::Normal);
+
+        let claims = crate::auth::Claims { priority: Some("critical".to_string()), ..Default::default() };
+        assert_eq!(classify(&rule, &headers, Some(&claims), &classes), Class::Critical);
+
+        headers.insert(crate::rate_limit::API_KEY_HEADER, "bulk-key".parse().unwrap());
+        assert_eq!(classify(&rule, &headers, Some(&claims), &classes), Class::Low);
+    }
+
+    #[tokio::test]
+    async fn test_higher_class_served_first() {
+        let scheduler = Scheduler::new(1, 4, Duration::from_secs(5), BTreeMap::new());
+        let held = scheduler.acquire(Class::Normal, "").await.unwrap();
+
+        let low = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move { scheduler.acquire(Class::Low, "").await.map(drop) }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let high = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move { scheduler.acquire(Class::High, "").await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(scheduler.usage(), (1, 2));
+
+        // 释放后名额先交给后到的 high
+        drop(held);
+        let high_permit = high.await.unwrap().unwrap();
+        assert!(!low.is_finished());
+        drop(high_permit);
+        assert!(low.await.unwrap().is_ok());
+        assert_eq!(scheduler.usage(), (0, 0));
+
+        // critical 不受并发上限约束
+        let _a = scheduler.acquire(Class::Normal, "").await.unwrap();
+        assert!(scheduler.acquire(Class::Critical, "").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_sheds_lower_class() {
+        let scheduler = Scheduler::new(1, 1, Duration::from_millis(200), BTreeMap::new());
+        let held = scheduler.acquire(Class::Normal, "").await.unwrap();
+
+        let low = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move { scheduler.acquire(Class::Low, "").await.map(drop) }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        // 排队已满：同等级的请求被拒绝，更高等级的请求挤出 low
+        assert_eq!(scheduler.acquire(Class::Low, "").await.err(), Some(Rejected::Shed));
+        let high = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move { scheduler.acquire(Class::High, "").await.map(drop) }
+        });
+        assert_eq!(low.await.unwrap(), Err(Rejected::Shed));
+
+        drop(held);
+        assert!(high.await.unwrap().is_ok());
+
+        // 排队超时
+        let _held = scheduler.acquire(Class::Normal, "").await.unwrap();
+        assert_eq!(scheduler.acquire(Class::Normal, "").await.err(), Some(Rejected::Timeout));
+    }
+
+    #[tokio::test]
+    async fn test_fair_dequeue_across_flows() {
+        let weights = BTreeMap::from([("gold".to_string(), 2)]);
+        let scheduler = Scheduler::new(1, 16, Duration::from_secs(5), weights);
+        let held = scheduler.acquire(Class::Normal, "").await.unwrap();
+
+        // 每个请求获得名额后记录调用方并立即释放，名额随即交给下一个
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        for flow in ["burst", "burst", "burst", "burst", "quiet", "gold", "gold", "gold"] {
+            let (scheduler, order) = (scheduler.clone(), order.clone());
+            tasks.push(tokio::spawn(async move {
+                let permit = scheduler.acquire(Class::Normal, flow).await;
+                order.lock().unwrap().push(flow);
+                drop(permit);
+            }));
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        drop(held);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        // burst 先到但只按自己的份额出队；gold 权重为 2
+        assert_eq!(*order.lock().unwrap(), vec!["gold", "burst", "quiet", "gold", "gold", "burst", "burst", "burst"]);
+
+        // 排队已满时挤出积压最多的调用方的最后一个请求
+        let scheduler = Scheduler::new(1, 2, Duration::from_secs(5), BTreeMap::new());
+        let held = scheduler.acquire(Class::Normal, "").await.unwrap();
+        let first = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move { scheduler.acquire(Class::Normal, "burst").await.map(drop) }
+        });
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let second = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move { scheduler.acquire(Class::Normal, "burst").await.map(drop) }
+        });
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let quiet = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move { scheduler.acquire(Class::Normal, "quiet").await.map(drop) }
+        });
+        assert_eq!(second.await.unwrap(), Err(Rejected::Shed));
+        drop(held);
+        assert!(first.await.unwrap().is_ok());
+        assert!(quiet.await.unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_flow() {
+        let mut headers = HeaderMap::new();
+        let claims = crate::auth::Claims { tenant_id: "acme".to_string(), ..Default::default() };
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        assert_eq!(flow(&headers, None, Some(ip)), "10.0.0.1");
+        assert_eq!(flow(&headers, Some(&claims), Some(ip)), "acme");
+        headers.insert(crate::rate_limit::API_KEY_HEADER, "key-1".parse().unwrap());
+        assert_eq!(flow(&headers, Some(&claims), Some(ip)), "key-1");
+    }
+}
@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 路由级查询参数改写：依次执行 drop -> rename -> inject
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct QueryRewrite {
+    // 需要去掉的参数，支持结尾 * 前缀匹配（如 utm_*）
+    #[serde(default)]
+    pub drop: Vec<String>,
+    // 参数改名：旧名 -> 新名
+    #[serde(default)]
+    pub rename: BTreeMap<String, String>,
+    // 注入参数，已存在同名参数时覆盖
+    #[serde(default)]
+    pub inject: BTreeMap<String, String>,
+}
+
+impl QueryRewrite {
+    /// 改写原始查询串（不含 ?），返回改写后的 query_suffix（非空时带 ?）
+    pub fn apply(&self, query: Option<&str>) -> String {
+        let mut pairs: Vec<(String, Option<String>)> = query
+            .unwrap_or_default()
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| match pair.split_once('=') {
+                Some((k, v)) => (k.to_string(), Some(v.to_string())),
+                None => (pair.to_string(), None),
+            })
+            .filter(|(key, _)| !self.drops(&decode(key)))
+            .collect();
+
+        for (key, _) in pairs.iter_mut() {
+            if let Some(new_key) = self.rename.get(&decode(key)) {
+                *key = encode(new_key);
+            }
+        }
+
+        for (key, value) in &self.inject {
+            pairs.retain(|(k, _)| decode(k) != *key);
+            pairs.push((encode(key), Some(encode(value))));
+        }
+
+        if pairs.is_empty() {
+            return String::new();
+        }
+        let joined: Vec<String> = pairs
+            .into_iter()
+            .map(|(k, v)| match v {
+                Some(v) => format!("{}={}", k, v),
+                None => k,
+            })
+            .collect();
+        format!("?{}", joined.join("&"))
+    }
+
+    fn drops(&self, key: &str) -> bool {
+        self.drop.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => key.starts_with(prefix),
+            None => key == pattern,
+        })
+    }
+
+    /// 校验错误
+    pub fn validation_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.drop.iter().any(|p| p.is_empty() || p == "*") {
+            errors.push("query.drop不能包含空参数名或单独的 *".to_string());
+        }
+        for (from, to) in &self.rename {
+            if from.is_empty() || to.is_empty() {
+                errors.push("query.rename的参数名不能为空".to_string());
+            }
+        }
+        if self.inject.keys().any(|k| k.is_empty()) {
+            errors.push("query.inject的参数名不能为空".to_string());
+        }
+        errors
+    }
+}
+
+// 参数名按解码后比较（+ 视为空格）
+fn decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                match u8::from_str_radix(&s[i + 1..i + 3], 16) {
+                    Ok(byte) => {
+                        out.push(byte);
+                        i += 2;
+                    }
+                    Err(_) => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// application/x-www-form-urlencoded 风格编码，仅保留非保留字符
+fn encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_rewrite() {
+        let rewrite = QueryRewrite {
+            drop: vec!["utm_*".to_string(), "fbclid".to_string()],
+            rename: BTreeMap::from([("q".to_string(), "search".to_string())]),
+            inject: BTreeMap::from([("api-version".to_string(), "2024-01-01".to_string())]),
+        };
+        assert_eq!(
+            rewrite.apply(Some("q=rust&utm_source=x&utm_medium=y&fbclid=abc&page=2")),
+            "?search=rust&page=2&api-version=2024-01-01"
+        );
+        // 注入覆盖客户端传入的同名参数
+        assert_eq!(rewrite.apply(Some("api-version=old&flag")), "?flag&api-version=2024-01-01");
+        assert_eq!(rewrite.apply(None), "?api-version=2024-01-01");
+
+        let drop_only = QueryRewrite { drop: vec!["utm_*".to_string()], ..Default::default() };
+        assert_eq!(drop_only.apply(Some("utm_source=x")), "");
+    }
+
+    #[test]
+    fn test_encoded_keys_and_values() {
+        let rewrite = QueryRewrite {
+            rename: BTreeMap::from([("user name".to_string(), "user".to_string())]),
+            inject: BTreeMap::from([("tag".to_string(), "a&b c".to_string())]),
+            ..Default::default()
+        };
+        assert_eq!(rewrite.apply(Some("user%20name=bob")), "?user=bob&tag=a%26b%20c");
+    }
+}
@@ -1,22 +1,178 @@
 use axum::{
     body::Body,
-    extract::Request,
-    http::Response,
+    extract::{ConnectInfo, Request},
+    http::{header, HeaderMap, HeaderName, Response, StatusCode},
     middleware::Next,
+    response::IntoResponse,
 };
-use std::net::IpAddr;
+use dashmap::DashMap;
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr};
 use std::num::NonZeroU32;
 use std::sync::Arc;
+use std::time::Duration;
 use governor::{
-    Quota, RateLimiter,
-    clock::DefaultClock,
+    InsufficientCapacity, NotUntil, Quota, RateLimiter,
+    clock::{Clock, DefaultClock, QuantaInstant},
     state::{keyed::DefaultKeyedStateStore, InMemoryState, NotKeyed},
 };
-use crate::config::Settings;
+use config::ConfigError;
+use ipnet::IpNet;
+use crate::config::{RouteRule, Settings};
+use crate::metrics::{RATE_LIMITED_COUNTER, RATE_LIMIT_KEYS};
+use crate::problem::{Problem, ProblemType};
+
+/// 携带 API Key 的请求头
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// 429 响应中标明拒绝请求的限流层级
+pub const SCOPE_HEADER: &str = "x-ratelimit-scope";
+
+type DirectLimiter = RateLimiter<NotKeyed, InMemoryState, DefaultClock>;
+type KeyedLimiter<K> = RateLimiter<K, DefaultKeyedStateStore<K>, DefaultClock>;
 
 pub struct RateLimits {
-    pub per_ip: RateLimiter<IpAddr, DefaultKeyedStateStore<IpAddr>, DefaultClock>,
-    pub global: RateLimiter<NotKeyed, InMemoryState, DefaultClock>,
+    pub per_ip: KeyedLimiter<IpAddr>,
+    pub global: DirectLimiter,
+    // 分层限流：租户（JWT tenant_id）、用户（JWT sub），未配置则不启用
+    pub per_tenant: Option<KeyedLimiter<String>>,
+    pub per_user: Option<KeyedLimiter<String>>,
+    // 路由级限流器按（路由, QPS）懒创建，该路由所有客户端共享
+    pub per_route: DashMap<(String, u32), DirectLimiter>,
+    // 经白名单免鉴权的请求按（路由, QPS）懒创建，按客户端 IP 计数，见 whitelist_limits.client_qps
+    pub per_whitelist: DashMap<(String, u32), KeyedLimiter<IpAddr>>,
+    pub exemptions: Exemptions,
+}
+
+/// 参与分层限流的请求维度
+#[derive(Debug, Default)]
+pub struct LimitKeys<'a> {
+    pub tenant: Option<&'a str>,
+    pub user: Option<&'a str>,
+    // 路由标签与路由级 QPS
+    pub route: Option<(&'a str, u32)>,
+}
+
+/// 被限流的层级及建议的重试等待时间（cost 超过桶容量时无法给出）
+#[derive(Debug, PartialEq, Eq)]
+pub struct Limited {
+    pub scope: &'static str,
+    pub retry_after: Option<Duration>,
+}
+
+/// 限流豁免名单：命中任意一项则完全跳过限流
+#[derive(Debug, Default)]
+pub struct Exemptions {
+    nets: Vec<IpNet>,
+    api_keys: HashSet<String>,
+    subjects: HashSet<String>,
+}
+
+impl Exemptions {
+    pub fn new(ips: &[String], api_keys: &[String], subjects: &[String]) -> Self {
+        let nets = ips
+            .iter()
+            .filter_map(|item| match parse_net(item) {
+                Some(net) => Some(net),
+                None => {
+                    tracing::warn!("忽略无法解析的限流豁免 IP: {}", item);
+                    None
+                }
+            })
+            .collect();
+        Self {
+            nets,
+            api_keys: api_keys.iter().cloned().collect(),
+            subjects: subjects.iter().cloned().collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nets.is_empty() && self.api_keys.is_empty() && self.subjects.is_empty()
+    }
+
+    pub fn ip_range_count(&self) -> usize {
+        self.nets.len()
+    }
+
+    pub fn api_key_count(&self) -> usize {
+        self.api_keys.len()
+    }
+
+    pub fn subject_count(&self) -> usize {
+        self.subjects.len()
+    }
+
+    pub fn contains_ip(&self, ip: &IpAddr) -> bool {
+        self.nets.iter().any(|net| net.contains(ip))
+    }
+
+    pub fn contains_api_key(&self, key: &str) -> bool {
+        self.api_keys.contains(key)
+    }
+
+    pub fn contains_subject(&self, sub: &str) -> bool {
+        self.subjects.contains(sub)
+    }
+
+    /// 依次检查 IP、API Key、JWT subject（仅在配置了 subject 时才解析 token）
+    pub fn is_exempt(&self, ip: &IpAddr, headers: &HeaderMap, settings: Option<&Settings>) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        if self.contains_ip(ip) {
+            return true;
+        }
+        if let Some(key) = headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok())
+            && self.contains_api_key(key)
+        {
+            return true;
+        }
+        if !self.subjects.is_empty()
+            && let Some(settings) = settings
+            && let Ok(claims) = crate::auth::decode_bearer(headers, &settings.jwt_decoding_key)
+        {
+            return self.contains_subject(&claims.sub);
+        }
+        false
+    }
+}
+
+// 支持单个 IP（视为 /32 或 /128）和 CIDR 网段
+pub fn parse_net(s: &str) -> Option<IpNet> {
+    s.parse::<IpNet>()
+        .ok()
+        .or_else(|| s.parse::<IpAddr>().ok().map(IpNet::from))
+}
+
+/// 路由 cost 超过全局、客户端、租户或用户限流桶容量（即对应的 QPS）时，该路由的请求永远无法通过
+pub fn cost_errors(settings: &Settings, rules: &[RouteRule]) -> Vec<String> {
+    let buckets = [
+        ("global_qps", Some(settings.global_qps)),
+        ("client_qps", Some(settings.client_qps)),
+        ("tenant_qps", settings.tenant_qps),
+        ("user_qps", settings.user_qps),
+    ];
+    let mut errors = Vec::new();
+    for (i, rule) in rules.iter().enumerate() {
+        let Some(cost) = rule.cost else { continue };
+        for (name, qps) in buckets {
+            if let Some(qps) = qps.filter(|&qps| qps > 0 && cost > qps) {
+                errors.push(format!("routes[{}]: cost({})不能大于{}({})", i, cost, name, qps));
+            }
+        }
+    }
+    errors
+}
+
+/// 存在 cost 超过限流桶容量的路由则拒绝启动
+pub fn enforce(settings: &Settings, rules: &[RouteRule]) -> Result<(), ConfigError> {
+    let errors = cost_errors(settings, rules);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ConfigError::Message(format!("路由 cost 超过限流额度:\n  {}", errors.join("\n  "))))
+    }
 }
 
 pub fn init_rate_limits(settings: &Settings) -> Arc<RateLimits> {
@@ -24,7 +180,150 @@ pub fn init_rate_limits(settings: &Settings) -> Arc<RateLimits> {
     let global_qps_nz = NonZeroU32::new(settings.global_qps).unwrap_or(NonZeroU32::new(1).unwrap());
     let per_ip = RateLimiter::keyed(Quota::per_second(client_qps_nz));
     let global = RateLimiter::direct(Quota::per_second(global_qps_nz));
-    Arc::new(RateLimits { per_ip, global })
+    let keyed = |qps: Option<u32>| qps.and_then(NonZeroU32::new).map(|qps| RateLimiter::keyed(Quota::per_second(qps)));
+    let exemptions = Exemptions::new(
+        &settings.rate_limit_exempt_ips,
+        &settings.rate_limit_exempt_api_keys,
+        &settings.rate_limit_exempt_subjects,
+    );
+    Arc::new(RateLimits {
+        per_ip,
+        global,
+        per_tenant: keyed(settings.tenant_qps),
+        per_user: keyed(settings.user_qps),
+        per_route: DashMap::new(),
+        per_whitelist: DashMap::new(),
+        exemptions,
+    })
+}
+
+/// 后台定期清理按键限流器中的空闲键，避免扫描流量带来的大量一次性 IP 持续占用内存
+pub fn spawn_eviction(settings: &Settings, rate_limits: Arc<RateLimits>) {
+    let interval = Duration::from_secs(settings.rate_limit_evict_interval_secs.unwrap_or(60));
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            rate_limits.evict_idle();
+        }
+    });
+}
+
+fn verdict(
+    outcome: Result<Result<(), NotUntil<QuantaInstant>>, InsufficientCapacity>,
+    scope: &'static str,
+) -> Result<(), Limited> {
+    match outcome {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(not_until)) => Err(Limited {
+            scope,
+            retry_after: Some(not_until.wait_time_from(DefaultClock::default().now())),
+        }),
+        Err(_) => Err(Limited { scope, retry_after: None }),
+    }
+}
+
+impl RateLimits {
+    /// 各按键限流器当前跟踪的键数量
+    pub fn tracked_keys(&self) -> [(&'static str, usize); 3] {
+        [
+            ("client", self.per_ip.len()),
+            ("tenant", self.per_tenant.as_ref().map_or(0, |l| l.len())),
+            ("user", self.per_user.as_ref().map_or(0, |l| l.len())),
+        ]
+    }
+
+    /// 清理令牌桶已回满的空闲键（与新键无区别，删除不影响限流结果），并回收表空间
+    pub fn evict_idle(&self) {
+        self.per_ip.retain_recent();
+        self.per_ip.shrink_to_fit();
+        for limiter in [&self.per_tenant, &self.per_user].into_iter().flatten() {
+            limiter.retain_recent();
+            limiter.shrink_to_fit();
+        }
+        for limiter in self.per_whitelist.iter() {
+            limiter.retain_recent();
+            limiter.shrink_to_fit();
+        }
+        for (limiter, keys) in self.tracked_keys() {
+            RATE_LIMIT_KEYS.with_label_values(&[limiter]).set(keys as i64);
+        }
+    }
+
+    /// 是否需要从 JWT 中识别租户或用户
+    pub fn needs_identity(&self) -> bool {
+        self.per_tenant.is_some() || self.per_user.is_some()
+    }
+
+    /// 按 cost 扣减各层级令牌，任一层级额度不足即拒绝并返回该层级。
+    /// governor 不支持退回令牌，因此由窄到宽检查 user -> client -> tenant -> route -> global，
+    /// 被窄层级拒绝的请求不会消耗租户、路由与全局这些共享额度
+    pub fn admit(&self, client_ip: &IpAddr, keys: &LimitKeys, cost: NonZeroU32) -> Result<(), Limited> {
+        if let (Some(limiter), Some(user)) = (&self.per_user, keys.user) {
+            verdict(limiter.check_key_n(&user.to_string(), cost), "user")?;
+        }
+        verdict(self.per_ip.check_key_n(client_ip, cost), "client")?;
+        if let (Some(limiter), Some(tenant)) = (&self.per_tenant, keys.tenant) {
+            verdict(limiter.check_key_n(&tenant.to_string(), cost), "tenant")?;
+        }
+        if let Some((route, qps)) = keys.route
+            && let Some(qps_nz) = NonZeroU32::new(qps)
+        {
+            let limiter = self
+                .per_route
+                .entry((route.to_string(), qps))
+                .or_insert_with(|| RateLimiter::direct(Quota::per_second(qps_nz)));
+            verdict(limiter.check_n(cost), "route")?;
+        }
+        verdict(self.global.check_n(cost), "global")?;
+        Ok(())
+    }
+
+    /// 经白名单免鉴权的请求在该路由上按客户端 IP 的额外限流
+    pub fn admit_whitelisted(&self, route: &str, qps: u32, client_ip: &IpAddr, cost: NonZeroU32) -> Result<(), Limited> {
+        let Some(qps_nz) = NonZeroU32::new(qps) else {
+            return Ok(());
+        };
+        let limiter = self
+            .per_whitelist
+            .entry((route.to_string(), qps))
+            .or_insert_with(|| RateLimiter::keyed(Quota::per_second(qps_nz)));
+        verdict(limiter.check_key_n(client_ip, cost), "whitelist")
+    }
+}
+
+/// 限流拒绝的 429 响应
+pub fn limited_response(limited: &Limited, cost: NonZeroU32) -> Response<Body> {
+    RATE_LIMITED_COUNTER.with_label_values(&[limited.scope]).inc();
+    if limited.retry_after.is_none() {
+        tracing::warn!(scope = limited.scope, cost = cost.get(), "请求 cost 超过限流桶容量，该请求永远无法通过");
+    }
+    let mut problem = Problem::new(StatusCode::TOO_MANY_REQUESTS, ProblemType::RateLimited)
+        .detail(format!("Rate limit exceeded ({})", limited.scope))
+        .with("scope", limited.scope)
+        .header(HeaderName::from_static(SCOPE_HEADER), limited.scope);
+    if let Some(wait) = limited.retry_after {
+        problem = problem.header(header::RETRY_AFTER, wait.as_secs_f64().ceil().max(1.0) as u64);
+    }
+    problem.into_response()
+}
+
+/// 请求消耗的令牌数：命中路由的 cost，默认 1
+pub fn request_cost(req: &Request) -> NonZeroU32 {
+    req.extensions()
+        .get::<crate::pipeline::MatchedRoute>()
+        .and_then(|matched| matched.0.cost)
+        .and_then(NonZeroU32::new)
+        .unwrap_or(NonZeroU32::MIN)
+}
+
+/// 获取客户端 IP（由 into_make_service_with_connect_info 注入）
+pub fn client_ip(req: &Request) -> IpAddr {
+    req.extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ci| ci.0.ip())
+        .unwrap_or_else(|| "127.0.0.1".parse().unwrap())
 }
 
 pub async fn rate_limit_layer(req: Request, next: Next) -> Response<Body> {
@@ -34,26 +333,157 @@ pub async fn rate_limit_layer(req: Request, next: Next) -> Response<Body> {
         .cloned();
 
     if let Some(limits) = limits {
-        if limits.global.check().is_err() {
-            return Response::builder()
-                .status(429)
-                .body(Body::from("Too Many Requests (global)"))
-                .unwrap();
+        let client_ip = client_ip(&req);
+
+        // 豁免名单在 governor 限流器之前检查
+        if limits.exemptions.is_exempt(&client_ip, req.headers(), req.extensions().get::<Settings>()) {
+            return next.run(req).await;
         }
 
-        let client_ip = req
-            .extensions()
-            .get::<axum::extract::ConnectInfo<IpAddr>>()
-            .map(|ci| ci.0)
-            .unwrap_or_else(|| "127.0.0.1".parse().unwrap());
+        // 启用租户/用户层级时识别身份：鉴权阶段已执行则复用其结果，否则自行解析 token
+        let claims = if limits.needs_identity() {
+            req.extensions().get::<crate::auth::JwtAuth>().map(|jwt| jwt.0.clone()).or_else(|| {
+                let settings = req.extensions().get::<Settings>()?;
+                crate::auth::identify(req.headers(), req.extensions(), settings).ok()
+            })
+        } else {
+            None
+        };
+        let matched = req.extensions().get::<crate::pipeline::MatchedRoute>().map(|m| m.0.clone());
+        let route_label = matched.as_deref().map(crate::proxy::route_label);
+        let keys = LimitKeys {
+            tenant: claims.as_ref().map(|c| c.tenant_id.as_str()).filter(|t| !t.is_empty()),
+            user: claims.as_ref().map(|c| c.sub.as_str()).filter(|u| !u.is_empty()),
+            // 异常防护可临时降低路由 QPS
+            route: route_label.as_deref().and_then(|label| Some((label, crate::anomaly::limited_qps(label, matched.as_deref()?.qps)?))),
+        };
 
-        if limits.per_ip.check_key(&client_ip).is_err() {
-            return Response::builder()
-                .status(429)
-                .body(Body::from("Too Many Requests (client)"))
-                .unwrap();
+        // 路由声明的 cost 决定本次请求消耗的令牌数，重型接口更快耗尽共享配额
+        let cost = request_cost(&req);
+        if let Err(limited) = limits.admit(&client_ip, &keys, cost) {
+            return limited_response(&limited, cost);
         }
     }
 
     next.run(req).await
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(global: u32, client: u32, tenant: Option<u32>, user: Option<u32>) -> RateLimits {
+        let keyed = |qps: Option<u32>| qps.map(|q| RateLimiter::keyed(Quota::per_second(NonZeroU32::new(q).unwrap())));
+        RateLimits {
+            per_ip: RateLimiter::keyed(Quota::per_second(NonZeroU32::new(client).unwrap())),
+            global: RateLimiter::direct(Quota::per_second(NonZeroU32::new(global).unwrap())),
+            per_tenant: keyed(tenant),
+            per_user: keyed(user),
+            per_route: DashMap::new(),
+            per_whitelist: DashMap::new(),
+            exemptions: Exemptions::default(),
+        }
+    }
+
+    #[test]
+    fn test_evict_idle_keys() {
+        let limits = limits(1000, 1000, Some(1000), None);
+        let keys = LimitKeys { tenant: Some("t1"), ..Default::default() };
+        for i in 0..10 {
+            let ip: IpAddr = format!("10.1.0.{}", i).parse().unwrap();
+            assert!(limits.admit(&ip, &keys, NonZeroU32::new(1).unwrap()).is_ok());
+        }
+        assert_eq!(limits.tracked_keys(), [("client", 10), ("tenant", 1), ("user", 0)]);
+
+        // 1000 QPS 的令牌桶 1ms 回满一个令牌
+        std::thread::sleep(Duration::from_millis(20));
+        limits.evict_idle();
+        assert_eq!(limits.tracked_keys(), [("client", 0), ("tenant", 0), ("user", 0)]);
+        assert_eq!(RATE_LIMIT_KEYS.with_label_values(&["client"]).get(), 0);
+    }
+
+    fn scope(result: Result<(), Limited>) -> Option<&'static str> {
+        result.err().map(|limited| limited.scope)
+    }
+
+    #[test]
+    fn test_cost_draws_down_bucket() {
+        let limits = limits(10, 100, None, None);
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let keys = LimitKeys::default();
+        let heavy = NonZeroU32::new(4).unwrap();
+        assert!(limits.admit(&ip, &keys, heavy).is_ok());
+        assert!(limits.admit(&ip, &keys, heavy).is_ok());
+        assert_eq!(scope(limits.admit(&ip, &keys, heavy)), Some("global"));
+        assert!(limits.admit(&ip, &keys, NonZeroU32::new(2).unwrap()).is_ok());
+        // 超过桶容量的 cost 永远无法满足
+        let limited = limits.admit(&"10.0.0.2".parse().unwrap(), &keys, NonZeroU32::new(11).unwrap()).unwrap_err();
+        assert_eq!(limited, Limited { scope: "global", retry_after: None });
+    }
+
+    #[test]
+    fn test_hierarchical_limits() {
+        let limits = limits(100, 100, Some(3), Some(2));
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let one = NonZeroU32::MIN;
+        let alice = LimitKeys { tenant: Some("t1"), user: Some("alice"), route: None };
+        let bob = LimitKeys { tenant: Some("t1"), user: Some("bob"), route: None };
+
+        assert!(limits.admit(&ip, &alice, one).is_ok());
+        assert!(limits.admit(&ip, &alice, one).is_ok());
+        let limited = limits.admit(&ip, &alice, one).unwrap_err();
+        assert_eq!(limited.scope, "user");
+        assert!(limited.retry_after.is_some());
+
+        // 同租户的其他用户共享租户额度
+        assert!(limits.admit(&ip, &bob, one).is_ok());
+        assert_eq!(scope(limits.admit(&ip, &bob, one)), Some("tenant"));
+
+        // 路由级额度最严格时由路由层拒绝
+        let route = LimitKeys { route: Some(("search", 1)), ..Default::default() };
+        assert!(limits.admit(&ip, &route, one).is_ok());
+        assert_eq!(scope(limits.admit(&ip, &route, one)), Some("route"));
+    }
+
+    #[test]
+    fn test_cost_exceeding_bucket_capacity() {
+        let settings = crate::testing::settings(serde_json::json!({ "client_qps": 10, "user_qps": 3 }));
+        let rule = |cost: Option<u32>| RouteRule {
+            prefix: vec!["/api".to_string()],
+            upstream: vec!["http://127.0.0.1:9001".to_string()],
+            cost,
+            ..Default::default()
+        };
+        assert!(cost_errors(&settings, &[rule(None), rule(Some(3))]).is_empty());
+        assert_eq!(cost_errors(&settings, &[rule(Some(1)), rule(Some(4))]), ["routes[1]: cost(4)不能大于user_qps(3)"]);
+        assert_eq!(
+            cost_errors(&settings, &[rule(Some(20))]),
+            ["routes[0]: cost(20)不能大于client_qps(10)", "routes[0]: cost(20)不能大于user_qps(3)"]
+        );
+        assert!(enforce(&settings, &[rule(Some(20))]).is_err());
+    }
+
+    #[test]
+    fn test_exempt_ip_ranges() {
+        let ex = Exemptions::new(
+            &["10.0.0.0/8".to_string(), "192.168.1.7".to_string(), "bad-ip".to_string()],
+            &[],
+            &[],
+        );
+        assert!(ex.contains_ip(&"10.1.2.3".parse().unwrap()));
+        assert!(ex.contains_ip(&"192.168.1.7".parse().unwrap()));
+        assert!(!ex.contains_ip(&"192.168.1.8".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_exempt_api_key_header() {
+        let ex = Exemptions::new(&[], &["batch-job-key".to_string()], &[]);
+        let ip: IpAddr = "8.8.8.8".parse().unwrap();
+
+        let mut headers = HeaderMap::new();
+        assert!(!ex.is_exempt(&ip, &headers, None));
+
+        headers.insert(API_KEY_HEADER, "batch-job-key".parse().unwrap());
+        assert!(ex.is_exempt(&ip, &headers, None));
+    }
+}
@@ -0,0 +1,151 @@
+use axum::http::{header, HeaderMap, HeaderValue};
+use reqwest::Url;
+
+use crate::config::RouteRule;
+
+/// follow 模式默认最多跟随的跳数
+pub const DEFAULT_MAX_HOPS: usize = 5;
+
+/// 上游重定向的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectPolicy {
+    // 原样返回给客户端（默认）
+    Pass,
+    // 网关代为跟随，最多 N 跳
+    Follow(usize),
+    // 原样返回，但把指向上游的 Location 改写为网关对外的路径
+    Rewrite,
+}
+
+impl RedirectPolicy {
+    pub fn parse(mode: &str, max_hops: Option<usize>) -> Option<Self> {
+        match mode {
+            "pass" => Some(Self::Pass),
+            "follow" => Some(Self::Follow(max_hops.unwrap_or(DEFAULT_MAX_HOPS))),
+            "rewrite" => Some(Self::Rewrite),
+            _ => None,
+        }
+    }
+
+    pub fn for_route(rule: &RouteRule) -> Self {
+        rule.redirect
+            .as_deref()
+            .and_then(|mode| Self::parse(mode, rule.redirect_max_hops))
+            .unwrap_or(Self::Pass)
+    }
+
+    /// 上游客户端最多跟随的跳数，0 表示不跟随
+    pub fn max_hops(&self) -> usize {
+        match self {
+            Self::Follow(hops) => *hops,
+            _ => 0,
+        }
+    }
+}
+
+pub fn validation_errors(rule: &RouteRule) -> Vec<String> {
+    let mut errors = Vec::new();
+    if let Some(mode) = &rule.redirect
+        && RedirectPolicy::parse(mode, None).is_none()
+    {
+        errors.push(format!("redirect仅支持 pass、follow、rewrite: {}", mode));
+    }
+    if rule.redirect_max_hops == Some(0) {
+        errors.push("redirect_max_hops必须大于0".to_string());
+    }
+    if rule.redirect_max_hops.is_some() && rule.redirect.as_deref() != Some("follow") {
+        errors.push("redirect_max_hops仅在 redirect = \"follow\" 时生效".to_string());
+    }
+    errors
+}
+
+// 去掉上游地址自带的路径前缀（如 http://svc/base 中的 /base）
+fn strip_base<'a>(path: &'a str, upstream: &Url) -> &'a str {
+    let base = upstream.path().trim_end_matches('/');
+    match path.strip_prefix(base) {
+        Some(rest) if !base.is_empty() && (rest.is_empty() || rest.starts_with('/')) => rest,
+        _ => path,
+    }
+}
+
+/// 把指向上游的 Location 改写为网关对外的路径。
+/// external_base 为网关路径中转发时被去掉的前缀（如 /proxy/api），
+/// upstreams 为路由的全部上游，selected 为本次请求转发到的上游
+pub fn rewrite_location(headers: &mut HeaderMap, external_base: &str, upstreams: &[&str], selected: &str) {
+    let Some(location) = headers.get(header::LOCATION).and_then(|v| v.to_str().ok()) else {
+        return;
+    };
+    let rewritten = if location.starts_with('/') && !location.starts_with("//") {
+        // 以 / 开头的路径相对于上游根路径
+        let Ok(selected) = Url::parse(selected) else { return };
+        format!("{}{}", external_base, strip_base(location, &selected))
+    } else {
+        let Ok(target) = Url::parse(location) else { return };
+        let Some(upstream) = upstreams
+            .iter()
+            .filter_map(|u| Url::parse(u).ok())
+            .find(|u| u.origin() == target.origin())
+        else {
+            return;
+        };
+        let mut path = format!("{}{}", external_base, strip_base(target.path(), &upstream));
+        if path.is_empty() {
+            path.push('/');
+        }
+        if let Some(query) = target.query() {
+            path.push('?');
+            path.push_str(query);
+        }
+        if let Some(fragment) = target.fragment() {
+            path.push('#');
+            path.push_str(fragment);
+        }
+        path
+    };
+    if let Ok(value) = HeaderValue::from_str(&rewritten) {
+        headers.insert(header::LOCATION, value);
+    }
+}
+
+/// 网关路径中转发时被去掉的前缀：完整路径去掉转发路径后的部分
+pub fn external_base<'a>(full_path: &'a str, forward_path: &str) -> &'a str {
+    full_path.strip_suffix(forward_path).unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewritten(location: &str, selected: &str) -> String {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::LOCATION, HeaderValue::from_str(location).unwrap());
+        rewrite_location(&mut headers, "/proxy/api", &["http://10.0.0.1:8080", "http://10.0.0.2:8080/base"], selected);
+        headers[header::LOCATION].to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_rewrite_location() {
+        assert_eq!(rewritten("http://10.0.0.1:8080/users/2?x=1", "http://10.0.0.1:8080"), "/proxy/api/users/2?x=1");
+        assert_eq!(rewritten("http://10.0.0.2:8080/base/login#top", "http://10.0.0.1:8080"), "/proxy/api/login#top");
+        assert_eq!(rewritten("/users/3", "http://10.0.0.1:8080"), "/proxy/api/users/3");
+        assert_eq!(rewritten("/base/users/3", "http://10.0.0.2:8080/base"), "/proxy/api/users/3");
+        // 外部地址与协议相对地址保持不变
+        assert_eq!(rewritten("https://sso.example.com/login", "http://10.0.0.1:8080"), "https://sso.example.com/login");
+        assert_eq!(rewritten("//cdn.example.com/a", "http://10.0.0.1:8080"), "//cdn.example.com/a");
+        assert_eq!(external_base("/proxy/api/users/1", "/users/1"), "/proxy/api");
+    }
+
+    #[test]
+    fn test_policy() {
+        let rule = |mode: Option<&str>, hops: Option<usize>| RouteRule {
+            redirect: mode.map(str::to_string),
+            redirect_max_hops: hops,
+            ..Default::default()
+        };
+        assert_eq!(RedirectPolicy::for_route(&rule(None, None)), RedirectPolicy::Pass);
+        assert_eq!(RedirectPolicy::for_route(&rule(Some("follow"), None)).max_hops(), DEFAULT_MAX_HOPS);
+        assert_eq!(RedirectPolicy::for_route(&rule(Some("follow"), Some(2))), RedirectPolicy::Follow(2));
+        assert!(validation_errors(&rule(Some("bounce"), None)).len() == 1);
+        assert!(validation_errors(&rule(Some("pass"), Some(3))).len() == 1);
+    }
+}
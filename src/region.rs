@@ -0,0 +1,88 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{Duration, Instant};
+use crate::config::{RouteRule, Settings};
+use crate::upstream::{self, ProbeLatency};
+
+/// 区域当前的探测延迟：区域内可用节点中最低的一个（无可用节点视为探测失败）
+pub fn region_latency(upstreams: &[String]) -> ProbeLatency {
+    upstreams
+        .iter()
+        .filter(|u| upstream::is_routable(u))
+        .map(|u| upstream::state(u).probe_latency())
+        .min()
+        .unwrap_or(ProbeLatency::Failed)
+}
+
+/// 按延迟从低到高排序的区域；尚未探测的排在已测区域之后，探测失败的排在最后
+pub fn ranked_regions(regions: &BTreeMap<String, Vec<String>>) -> Vec<(&str, &[String])> {
+    let mut ranked: Vec<(ProbeLatency, &str, &[String])> = regions
+        .iter()
+        .map(|(name, upstreams)| (region_latency(upstreams), name.as_str(), upstreams.as_slice()))
+        .collect();
+    ranked.sort_by_key(|(latency, name, _)| (*latency, *name));
+    ranked.into_iter().map(|(_, name, upstreams)| (name, upstreams)).collect()
+}
+
+// ===== 后台延迟探测 =====
+/// 为所有配置了 regions 的路由启动周期性探测
+pub fn spawn_probes(settings: &Settings, route_rules: &[RouteRule]) {
+    let urls: BTreeSet<String> = route_rules
+        .iter()
+        .filter_map(|r| r.regions.as_ref())
+        .flat_map(|regions| regions.values().flatten().cloned())
+        .collect();
+    if urls.is_empty() {
+        return;
+    }
+
+    let interval = Duration::from_secs(settings.region_probe_interval_secs.unwrap_or(10).max(1));
+    let path = settings.region_probe_path.clone().unwrap_or_else(|| "/".to_string());
+    tracing::info!(upstreams = urls.len(), interval_secs = interval.as_secs(), "启动区域延迟探测");
+    for url in urls {
+        tokio::spawn(probe_loop(url, path.clone(), interval));
+    }
+}
+
+async fn probe_loop(url: String, path: String, interval: Duration) {
+    let target = format!("{}{}", url, path);
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let start = Instant::now();
+        // 探测关注的是网络往返与服务可达，5xx 视为失败，其余状态码均视为可达
+        let latency = match crate::proxy::HTTP_CLIENT.get(&target).timeout(interval).send().await {
+            Ok(resp) if !resp.status().is_server_error() => Some(start.elapsed()),
+            _ => None,
+        };
+        if latency.is_none() {
+            tracing::debug!(upstream = %url, "区域延迟探测失败");
+        }
+        upstream::state(&url).record_probe(latency);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regions_ranked_by_latency() {
+        let regions = BTreeMap::from([
+            ("ap".to_string(), vec!["http://region-ap:8080".to_string()]),
+            ("eu".to_string(), vec!["http://region-eu:8080".to_string()]),
+            ("us".to_string(), vec!["http://region-us-a:8080".to_string(), "http://region-us-b:8080".to_string()]),
+        ]);
+        upstream::state("http://region-ap:8080").record_probe(None);
+        upstream::state("http://region-eu:8080").record_probe(Some(Duration::from_millis(80)));
+        upstream::state("http://region-us-a:8080").record_probe(Some(Duration::from_millis(120)));
+        upstream::state("http://region-us-b:8080").record_probe(Some(Duration::from_millis(30)));
+
+        let names: Vec<&str> = ranked_regions(&regions).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["us", "eu", "ap"]);
+
+        // 区域内最快节点摘流后，按剩余节点计算
+        upstream::set_draining("http://region-us-b:8080", true);
+        let names: Vec<&str> = ranked_regions(&regions).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["eu", "us", "ap"]);
+    }
+}
@@ -0,0 +1,240 @@
+use axum::{
+    body::{Body, Bytes},
+    http::{HeaderMap, Method, Response, StatusCode},
+};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::auth::Claims;
+
+// 缓存条目上限，超过后清空
+const MAX_ENTRIES: usize = 10_000;
+// 可缓存的单个响应体大小上限（错误响应通常很小）
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// 路由的响应缓存配置
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CachePolicy {
+    // 负缓存：上游返回 negative_statuses 中的状态码时缓存 negative_ttl_secs 秒，不配置则不启用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub negative_ttl_secs: Option<u64>,
+    // 负缓存的状态码，默认 404、410
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub negative_statuses: Option<Vec<u16>>,
+    // 缓存键只包含这些查询参数（按名称排序），与 key_query_exclude 二选一；都不配置时使用完整查询串
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_query: Option<Vec<String>>,
+    // 缓存键忽略的查询参数（如 utm_source、_t）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_query_exclude: Option<Vec<String>>,
+    // 计入缓存键的请求头（不区分大小写），如 Accept-Language
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub key_headers: Vec<String>,
+    // 计入缓存键的 JWT 声明，如 tenant_id，保证不同租户不会共用缓存条目
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub key_claims: Vec<String>,
+}
+
+impl CachePolicy {
+    fn negative_ttl(&self) -> Option<Duration> {
+        self.negative_ttl_secs.filter(|ttl| *ttl > 0).map(Duration::from_secs)
+    }
+
+    fn is_negative(&self, status: StatusCode) -> bool {
+        match &self.negative_statuses {
+            Some(statuses) => statuses.contains(&status.as_u16()),
+            None => matches!(status, StatusCode::NOT_FOUND | StatusCode::GONE),
+        }
+    }
+
+    pub fn validation_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.negative_ttl_secs == Some(0) {
+            errors.push("cache.negative_ttl_secs必须大于0".to_string());
+        }
+        for status in self.negative_statuses.iter().flatten() {
+            if !(400..=599).contains(status) {
+                errors.push(format!("cache.negative_statuses只能包含4xx或5xx状态码: {}", status));
+            }
+        }
+        if self.key_query.is_some() && self.key_query_exclude.is_some() {
+            errors.push("cache.key_query与cache.key_query_exclude不能同时配置".to_string());
+        }
+        for header in &self.key_headers {
+            if axum::http::HeaderName::from_bytes(header.as_bytes()).is_err() {
+                errors.push(format!("cache.key_headers包含非法请求头: {}", header));
+            }
+        }
+        if self.key_claims.iter().any(|c| c.trim().is_empty()) {
+            errors.push("cache.key_claims不能包含空声明名".to_string());
+        }
+        if self.negative_statuses.is_some() && self.negative_ttl_secs.is_none() {
+            errors.push("cache.negative_statuses需要同时配置negative_ttl_secs".to_string());
+        }
+        errors
+    }
+}
+
+struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    expires_at: Instant,
+}
+
+static RESPONSES: Lazy<DashMap<String, CachedResponse>> = Lazy::new(DashMap::new);
+
+/// 缓存键：路由、方法、请求路径与查询串，以及路由声明的请求头与 JWT 声明；
+/// HEAD 与 GET 共用缓存键，命中时返回缓存的 GET 响应头而不带响应体
+pub fn key(
+    policy: &CachePolicy,
+    route: &str,
+    method: &Method,
+    path: &str,
+    query: Option<&str>,
+    headers: &HeaderMap,
+    claims: Option<&Claims>,
+) -> String {
+    let method = if method == Method::HEAD { &Method::GET } else { method };
+    let mut key = format!("{} {} {}", route, method, path);
+    let query = key_query(policy, query.unwrap_or_default());
+    if !query.is_empty() {
+        key.push('?');
+        key.push_str(&query);
+    }
+    for name in &policy.key_headers {
+        let values: Vec<&str> = headers.get_all(name.as_str()).iter().filter_map(|v| v.to_str().ok()).collect();
+        key.push_str(&format!(" {}={}", name.to_ascii_lowercase(), values.join(",")));
+    }
+    for name in &policy.key_claims {
+        let value = claims.and_then(|c| c.get(name)).unwrap_or_default();
+        key.push_str(&format!(" claim.{}={}", name, value));
+    }
+    key
+}
+
+// 按 key_query / key_query_exclude 筛选查询参数并排序；都不配置时原样使用
+fn key_query(policy: &CachePolicy, query: &str) -> String {
+    if policy.key_query.is_none() && policy.key_query_exclude.is_none() {
+        return query.to_string();
+    }
+    let keep = |name: &str| match (&policy.key_query, &policy.key_query_exclude) {
+        (Some(include), _) => include.iter().any(|n| n == name),
+        (None, Some(exclude)) => !exclude.iter().any(|n| n == name),
+        (None, None) => true,
+    };
+    let mut pairs: Vec<&str> = query
+        .split('&')
+        .filter(|pair| !pair.is_empty() && keep(pair.split('=').next().unwrap_or_default()))
+        .collect();
+    pairs.sort();
+    pairs.join("&")
+}
+
+/// 只缓存 GET/HEAD
+pub fn cacheable(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD)
+}
+
+/// 命中未过期的缓存时直接返回
+pub fn lookup(key: &str, method: &Method) -> Option<Response<Body>> {
+    let entry = RESPONSES.get(key)?;
+    if entry.expires_at <= Instant::now() {
+        drop(entry);
+        RESPONSES.remove(key);
+        return None;
+    }
+    let mut builder = Response::builder().status(entry.status).header("x-cache", "HIT");
+    for (name, value) in &entry.headers {
+        builder = builder.header(name, value);
+    }
+    let body = if method == Method::HEAD { Body::empty() } else { Body::from(entry.body.clone()) };
+    builder.body(body).ok()
+}
+
+/// 按路由策略缓存上游的错误响应
+pub fn store(policy: &CachePolicy, key: String, status: StatusCode, headers: &HeaderMap, body: &Bytes) {
+    let Some(ttl) = policy.negative_ttl() else { return };
+    if !policy.is_negative(status) || body.len() > MAX_BODY_BYTES {
+        return;
+    }
+    if RESPONSES.len() >= MAX_ENTRIES {
+        RESPONSES.clear();
+    }
+    RESPONSES.insert(
+        key,
+        CachedResponse { status, headers: headers.clone(), body: body.clone(), expires_at: Instant::now() + ttl },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negative_cache() {
+        let policy = CachePolicy { negative_ttl_secs: Some(60), ..Default::default() };
+        let key = |path: &str, query: Option<&str>| key(&policy, "/neg", &Method::GET, path, query, &HeaderMap::new(), None);
+        let missing = key("/neg/missing", Some("a=1"));
+        assert!(lookup(&missing, &Method::GET).is_none());
+
+        store(&policy, missing.clone(), StatusCode::NOT_FOUND, &HeaderMap::new(), &Bytes::from_static(b"nope"));
+        let resp = lookup(&missing, &Method::GET).unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.headers()["x-cache"], "HIT");
+        // HEAD 命中同一条目，只返回响应头
+        let head_key = super::key(&policy, "/neg", &Method::HEAD, "/neg/missing", Some("a=1"), &HeaderMap::new(), None);
+        assert_eq!(head_key, missing);
+        let head = lookup(&head_key, &Method::HEAD).unwrap();
+        assert_eq!(head.status(), StatusCode::NOT_FOUND);
+        assert_eq!(axum::body::HttpBody::size_hint(head.body()).exact(), Some(0));
+
+        // 成功响应与未列出的状态码不缓存；其他查询串是不同的键
+        let ok = key("/neg/ok", None);
+        store(&policy, ok.clone(), StatusCode::OK, &HeaderMap::new(), &Bytes::new());
+        store(&policy, ok.clone(), StatusCode::INTERNAL_SERVER_ERROR, &HeaderMap::new(), &Bytes::new());
+        assert!(lookup(&ok, &Method::GET).is_none());
+        assert!(lookup(&key("/neg/missing", Some("a=2")), &Method::GET).is_none());
+
+        let custom = CachePolicy { negative_ttl_secs: Some(60), negative_statuses: Some(vec![503]), ..Default::default() };
+        store(&custom, ok.clone(), StatusCode::SERVICE_UNAVAILABLE, &HeaderMap::new(), &Bytes::new());
+        assert!(lookup(&ok, &Method::GET).is_some());
+    }
+
+    #[test]
+    fn test_custom_key() {
+        let policy = CachePolicy {
+            key_query_exclude: Some(vec!["_t".to_string()]),
+            key_headers: vec!["Accept-Language".to_string()],
+            key_claims: vec!["tenant_id".to_string()],
+            ..Default::default()
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert("accept-language", "zh-CN".parse().unwrap());
+        let acme = Claims { tenant_id: "acme".to_string(), ..Default::default() };
+        let other = Claims { tenant_id: "other".to_string(), ..Default::default() };
+        let key_for = |query, claims| key(&policy, "/r", &Method::GET, "/r/x", Some(query), &headers, Some(claims));
+
+        assert_eq!(key_for("b=2&_t=1&a=1", &acme), "/r GET /r/x?a=1&b=2 accept-language=zh-CN claim.tenant_id=acme");
+        assert_eq!(key_for("a=1&b=2&_t=9", &acme), key_for("b=2&a=1", &acme));
+        assert_ne!(key_for("a=1", &acme), key_for("a=1", &other));
+
+        let include = CachePolicy { key_query: Some(vec!["id".to_string()]), ..Default::default() };
+        let key = key(&include, "/r", &Method::GET, "/r/x", Some("page=2&id=7"), &HeaderMap::new(), None);
+        assert_eq!(key, "/r GET /r/x?id=7");
+    }
+
+    #[test]
+    fn test_validation() {
+        let policy = |ttl, statuses| CachePolicy { negative_ttl_secs: ttl, negative_statuses: statuses, ..Default::default() };
+        assert!(policy(Some(10), Some(vec![404, 503])).validation_errors().is_empty());
+        assert_eq!(policy(Some(0), None).validation_errors().len(), 1);
+        assert_eq!(policy(Some(10), Some(vec![200])).validation_errors().len(), 1);
+        assert_eq!(policy(None, Some(vec![404])).validation_errors().len(), 1);
+        let both = CachePolicy { key_query: Some(vec![]), key_query_exclude: Some(vec![]), ..Default::default() };
+        assert_eq!(both.validation_errors().len(), 1);
+    }
+}
@@ -0,0 +1,121 @@
+//! 路由级重试：上游连接失败或返回可重试的状态码（默认 502/503/504）时，按退避间隔换一个节点重发请求，
+//! 最多尝试 max_attempts 次（含首次）。配置后取代全局 upstream_retries（只对连接错误立即重试）。
+//!
+//! 退避从 backoff_ms 开始逐次翻倍、不超过 max_backoff_ms，实际等待时间在 [一半, 全部] 之间随机，避免多个客户端同时重试。
+//! 重试与退避计入路由的总时长，退避结束时会超过截止时间则不再重试。
+//! 默认只重试幂等方法（GET、HEAD、OPTIONS、PUT、DELETE、TRACE）；流式转发的请求体无法重发，不重试。
+use axum::http::{Method, StatusCode};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::config::RouteRule;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetryPolicy {
+    // 最多尝试的次数，含首次请求
+    pub max_attempts: u32,
+    #[serde(default = "default_backoff_ms")]
+    pub backoff_ms: u64,
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+    // 触发重试的上游状态码
+    #[serde(default = "default_statuses")]
+    pub statuses: Vec<u16>,
+    // 只重试幂等方法
+    #[serde(default = "default_idempotent_only")]
+    pub idempotent_only: bool,
+}
+
+fn default_backoff_ms() -> u64 {
+    100
+}
+
+fn default_max_backoff_ms() -> u64 {
+    2000
+}
+
+fn default_statuses() -> Vec<u16> {
+    vec![502, 503, 504]
+}
+
+fn default_idempotent_only() -> bool {
+    true
+}
+
+fn idempotent(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE | Method::TRACE)
+}
+
+impl RetryPolicy {
+    /// 该方法的请求允许重试的次数（不含首次）
+    pub fn retries(&self, method: &Method) -> u32 {
+        if self.idempotent_only && !idempotent(method) {
+            return 0;
+        }
+        self.max_attempts.saturating_sub(1)
+    }
+
+    pub fn retries_status(&self, status: StatusCode) -> bool {
+        self.statuses.contains(&status.as_u16())
+    }
+
+    /// 第 retry 次重试（从 1 开始）前的等待时间
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let ceiling = self.backoff_ms.saturating_mul(1 << retry.saturating_sub(1).min(16)).min(self.max_backoff_ms);
+        Duration::from_millis(rand::thread_rng().gen_range(ceiling / 2..=ceiling))
+    }
+}
+
+pub fn validation_errors(rule: &RouteRule) -> Vec<String> {
+    let Some(policy) = &rule.retry else {
+        return Vec::new();
+    };
+    let mut errors = Vec::new();
+    if policy.max_attempts == 0 {
+        errors.push("retry.max_attempts必须大于0".to_string());
+    }
+    if policy.backoff_ms > policy.max_backoff_ms {
+        errors.push("retry.backoff_ms不能大于max_backoff_ms".to_string());
+    }
+    for status in &policy.statuses {
+        if !(100..=599).contains(status) {
+            errors.push(format!("retry.statuses包含非法的状态码: {}", status));
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        serde_json::from_value(serde_json::json!({ "max_attempts": 3 })).unwrap()
+    }
+
+    #[test]
+    fn test_retries_and_statuses() {
+        let mut policy = policy();
+        assert_eq!(policy.retries(&Method::GET), 2);
+        assert_eq!(policy.retries(&Method::POST), 0);
+        assert!(policy.retries_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!policy.retries_status(StatusCode::INTERNAL_SERVER_ERROR));
+        policy.idempotent_only = false;
+        assert_eq!(policy.retries(&Method::POST), 2);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = policy();
+        for _ in 0..20 {
+            let first = policy.backoff(1);
+            assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100), "{:?}", first);
+            let third = policy.backoff(3);
+            assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400), "{:?}", third);
+        }
+        // 不超过 max_backoff_ms
+        assert!(policy.backoff(30) <= Duration::from_millis(2000));
+    }
+}
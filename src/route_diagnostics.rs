@@ -0,0 +1,221 @@
+use config::ConfigError;
+use std::collections::HashSet;
+
+use crate::config::{RouteRule, Settings};
+use crate::proxy::{route_label, route_score};
+use crate::whitelist::Entry;
+
+// 生成探测路径时替换通配符与路径变量的取值
+const SAMPLES: &[&str] = &["x", "1"];
+
+/// 诊断类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    // 两条路由得分相同且匹配同一路径，结果取决于声明顺序
+    Ambiguous,
+    // 路由的所有前缀都被先声明或得分更高的路由抢先匹配
+    Unreachable,
+    // 白名单项重复或不在路由前缀范围内
+    Whitelist,
+}
+
+impl Kind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Ambiguous => "ambiguous",
+            Kind::Unreachable => "unreachable",
+            Kind::Whitelist => "whitelist",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub kind: Kind,
+    pub message: String,
+}
+
+fn is_pattern(p: &str) -> bool {
+    p.contains('{') || p.contains('*') || p.contains('?')
+}
+
+// 把模式中的通配符与路径变量替换为 sample，得到一条具体路径
+fn fill(pattern: &str, sample: &str) -> String {
+    let mut out = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => {
+                while chars.peek() == Some(&'*') {
+                    chars.next();
+                }
+                out.push_str(sample);
+            }
+            '?' => out.push_str(&sample[..1]),
+            '{' => {
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        break;
+                    }
+                }
+                out.push_str(sample);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// 前缀的探测路径：普通前缀取自身与其下一级，模式取若干种替换结果
+fn probes(prefix: &str) -> Vec<String> {
+    let mut probes: Vec<String> = if is_pattern(prefix) {
+        SAMPLES.iter().map(|s| fill(prefix, s)).collect()
+    } else {
+        vec![prefix.to_string(), format!("{}/x", prefix.trim_end_matches('/'))]
+    };
+    probes.dedup();
+    probes
+}
+
+// 只在 Content-Type / Accept / 定时谓词相同的路由之间比较，谓词不同的路由按请求头或时段区分
+fn same_predicates(a: &RouteRule, b: &RouteRule) -> bool {
+    a.content_type == b.content_type && a.accept == b.accept && a.schedule == b.schedule && a.feature_flag == b.feature_flag
+}
+
+fn name(rules: &[RouteRule], i: usize) -> String {
+    format!("routes[{}]（{}）", i, route_label(&rules[i]))
+}
+
+/// 分析路由表：重叠且得分相同的路由、不可达的路由、无效的白名单项
+pub fn diagnose(rules: &[RouteRule]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let scores: Vec<i32> = rules.iter().map(route_score).collect();
+    let mut unreachable = HashSet::new();
+    let mut ambiguous = Vec::new();
+    let mut seen_pairs = HashSet::new();
+
+    for (i, rule) in rules.iter().enumerate() {
+        let own: Vec<String> = rule.prefix.iter().flat_map(|p| probes(p)).filter(|p| rule.matches(p)).collect();
+        let mut wins = false;
+        let mut winner = None;
+        for probe in &own {
+            // 与 find_best_match 一致：得分最高者胜出，得分相同取先声明的
+            let candidates: Vec<usize> = rules
+                .iter()
+                .enumerate()
+                .filter(|(_, other)| same_predicates(rule, other) && other.matches(probe))
+                .map(|(j, _)| j)
+                .collect();
+            let top = candidates.iter().map(|&j| scores[j]).max().unwrap_or(i32::MIN);
+            let tied: Vec<usize> = candidates.into_iter().filter(|&j| scores[j] == top).collect();
+            if tied.first() == Some(&i) {
+                wins = true;
+            } else if let Some(&first) = tied.first() {
+                winner = Some(first);
+            }
+            for &other in tied.iter().skip(1) {
+                if seen_pairs.insert((tied[0], other)) {
+                    ambiguous.push((tied[0], other, probe.clone()));
+                }
+            }
+        }
+        if !wins && let Some(w) = winner {
+            unreachable.insert(i);
+            diagnostics.push(Diagnostic {
+                kind: Kind::Unreachable,
+                message: format!("{}不会被匹配：其前缀均由{}处理（得分 {} / {}）", name(rules, i), name(rules, w), scores[w], scores[i]),
+            });
+        }
+    }
+
+    for (first, other, probe) in ambiguous {
+        if unreachable.contains(&other) {
+            continue;
+        }
+        diagnostics.push(Diagnostic {
+            kind: Kind::Ambiguous,
+            message: format!(
+                "{}与{}得分相同（{}）且都匹配 {}，按声明顺序由前者处理",
+                name(rules, first),
+                name(rules, other),
+                scores[first],
+                probe
+            ),
+        });
+    }
+
+    for (i, rule) in rules.iter().enumerate() {
+        let mut seen = HashSet::new();
+        for item in rule.whitelist.iter().flatten() {
+            let entry = Entry::parse(item);
+            if !seen.insert(entry.clone()) {
+                diagnostics.push(Diagnostic {
+                    kind: Kind::Whitelist,
+                    message: format!("{}的白名单项重复: {}", name(rules, i), item),
+                });
+            } else if entry.path.starts_with('/') && !probes(entry.path).iter().any(|p| rule.matches(p)) {
+                diagnostics.push(Diagnostic {
+                    kind: Kind::Whitelist,
+                    message: format!("{}的白名单项不在路由前缀范围内，不会生效: {}", name(rules, i), item),
+                });
+            }
+        }
+    }
+    diagnostics
+}
+
+/// 按 route_diagnostics 处理诊断结果：warn（默认）只输出告警，error 时拒绝加载，off 不检查
+pub fn enforce(settings: &Settings, rules: &[RouteRule]) -> Result<(), ConfigError> {
+    let mode = settings.route_diagnostics.as_deref().unwrap_or("warn");
+    if mode == "off" {
+        return Ok(());
+    }
+    let diagnostics = diagnose(rules);
+    for diagnostic in &diagnostics {
+        tracing::warn!(kind = diagnostic.kind.as_str(), "路由诊断: {}", diagnostic.message);
+    }
+    if mode == "error" && !diagnostics.is_empty() {
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        return Err(ConfigError::Message(format!("路由诊断未通过:\n  {}", messages.join("\n  "))));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(prefix: &str) -> RouteRule {
+        RouteRule { prefix: vec![prefix.to_string()], upstream: vec!["http://127.0.0.1:9001".to_string()], ..Default::default() }
+    }
+
+    fn kinds(rules: &[RouteRule]) -> Vec<Kind> {
+        diagnose(rules).into_iter().map(|d| d.kind).collect()
+    }
+
+    #[test]
+    fn test_fill_probes() {
+        assert_eq!(probes("/api/{id:[0-9]+}/**"), vec!["/api/x/x", "/api/1/1"]);
+        assert_eq!(probes("/static"), vec!["/static", "/static/x"]);
+    }
+
+    #[test]
+    fn test_diagnose_routes() {
+        // 更具体的模式得分更高，没有冲突
+        assert!(kinds(&[rule("/api/**"), rule("/api/user/{id}")]).is_empty());
+        // 同一前缀重复声明：后者不可达
+        assert_eq!(kinds(&[rule("/api"), rule("/api")]), vec![Kind::Unreachable]);
+        // 得分相同的不同模式匹配同一路径
+        assert_eq!(kinds(&[rule("/api/*/items"), rule("/api/{id}/**")]), vec![Kind::Ambiguous]);
+        // 谓词不同的路由按请求头区分，不视为冲突
+        let json = RouteRule { accept: Some(vec!["application/json".to_string()]), ..rule("/api") };
+        assert!(kinds(&[rule("/api"), json]).is_empty());
+
+        let mut guarded = rule("/api/**");
+        guarded.whitelist = Some(vec!["/api/health".to_string(), "GET /public/**".to_string(), "/api/health".to_string()]);
+        let messages: Vec<String> = diagnose(&[guarded]).into_iter().map(|d| d.message).collect();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].contains("不会生效: GET /public/**"));
+        assert!(messages[1].contains("重复: /api/health"));
+    }
+}
@@ -0,0 +1,281 @@
+//! 定时路由：路由的 schedule 是一组 cron 表达式（分 时 日 月 周），只在当前时间命中其中之一时才参与匹配。
+//! 同一前缀下，定时路由在生效时段内优先于不带定时的路由，可用于定时维护应答、闲时切换到批处理上游、只在工作时间开放灰度。
+//! 时区为固定的 UTC 偏移（如 +08:00），不处理夏令时。
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::{RouteRule, Settings};
+
+const DAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+// 全局默认时区（相对 UTC 的秒数）
+static DEFAULT_OFFSET: AtomicI32 = AtomicI32::new(0);
+
+/// 应用 schedule_timezone
+pub fn configure(settings: &Settings) {
+    let offset = settings.schedule_timezone.as_deref().and_then(parse_offset).unwrap_or(0);
+    DEFAULT_OFFSET.store(offset, Ordering::Relaxed);
+}
+
+/// 解析时区：UTC / Z，或 +08:00、-05:30、+8 形式的 UTC 偏移，返回秒数
+pub fn parse_offset(s: &str) -> Option<i32> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("utc") || s == "Z" {
+        return Some(0);
+    }
+    let sign = match s.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let (hours, minutes) = s[1..].split_once(':').unwrap_or((&s[1..], "0"));
+    let (hours, minutes): (i32, i32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    ((0..=14).contains(&hours) && (0..60).contains(&minutes)).then_some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// 某一时刻的本地时间
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Civil {
+    minute: u32,
+    hour: u32,
+    day: u32,
+    month: u32,
+    // 0 为周日
+    weekday: u32,
+}
+
+impl Civil {
+    fn at(unix_secs: i64, offset: i32) -> Self {
+        let local = unix_secs + offset as i64;
+        let days = local.div_euclid(86_400);
+        let secs = local.rem_euclid(86_400);
+        // 1970-01-01 是周四
+        let weekday = (days + 4).rem_euclid(7) as u32;
+        // 公历日期（Howard Hinnant 的 civil_from_days）
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        Self { minute: (secs / 60 % 60) as u32, hour: (secs / 3600) as u32, day, month, weekday }
+    }
+}
+
+/// 编译后的 cron 表达式，每个字段为允许取值的位集合
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    // 日与周是否为 *：两者都被限定时按标准 cron 取“或”
+    any_day: bool,
+    any_weekday: bool,
+}
+
+// 字段取值：数字，星期字段另支持 sun~sat
+fn parse_value(s: &str, weekday: bool) -> Option<u32> {
+    if weekday && let Some(i) = DAY_NAMES.iter().position(|d| s.eq_ignore_ascii_case(d)) {
+        return Some(i as u32);
+    }
+    s.parse().ok()
+}
+
+// 解析一个字段：*、a、a-b、a,b，以及 */n、a-b/n 步长
+fn parse_field(field: &str, name: &str, min: u32, max: u32, weekday: bool) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|s| *s > 0).ok_or(format!("{}的步长无效: {}", name, part))?),
+            None => (part, 1),
+        };
+        let invalid = || format!("{}的取值无效: {}", name, part);
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((a, b)) => (parse_value(a, weekday).ok_or_else(invalid)?, parse_value(b, weekday).ok_or_else(invalid)?),
+                None => {
+                    let value = parse_value(range, weekday).ok_or_else(invalid)?;
+                    (value, if step > 1 { max } else { value })
+                }
+            },
+        };
+        if start < min || end > max || start > end {
+            return Err(format!("{}须在{}到{}之间: {}", name, min, max, part));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+impl Cron {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("cron 表达式须为 5 个字段（分 时 日 月 周）: {}", expr));
+        };
+        let mut weekdays = parse_field(weekday, "周", 0, 7, true)?;
+        // 7 与 0 都表示周日
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: parse_field(minute, "分", 0, 59, false)?,
+            hours: parse_field(hour, "时", 0, 23, false)?,
+            days: parse_field(day, "日", 1, 31, false)?,
+            months: parse_field(month, "月", 1, 12, false)?,
+            weekdays,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+
+    fn matches(&self, t: &Civil) -> bool {
+        let has = |bits: u64, value: u32| bits & (1 << value) != 0;
+        let day = has(self.days, t.day);
+        let weekday = has(self.weekdays, t.weekday);
+        let date = match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        };
+        date && has(self.minutes, t.minute) && has(self.hours, t.hour) && has(self.months, t.month)
+    }
+}
+
+// 表达式 -> 编译结果，路由匹配时复用
+static COMPILED: Lazy<DashMap<String, Option<Cron>>> = Lazy::new(DashMap::new);
+
+fn compiled(expr: &str) -> Option<Cron> {
+    if let Some(cron) = COMPILED.get(expr) {
+        return cron.value().clone();
+    }
+    let cron = Cron::parse(expr).ok();
+    COMPILED.insert(expr.to_string(), cron.clone());
+    cron
+}
+
+/// 路由在给定时刻是否生效；未配置 schedule 的路由始终生效
+pub fn active_at(rule: &RouteRule, unix_secs: i64) -> bool {
+    let Some(schedule) = &rule.schedule else {
+        return true;
+    };
+    let offset = rule
+        .schedule_timezone
+        .as_deref()
+        .and_then(parse_offset)
+        .unwrap_or_else(|| DEFAULT_OFFSET.load(Ordering::Relaxed));
+    let now = Civil::at(unix_secs, offset);
+    schedule.iter().filter_map(|expr| compiled(expr)).any(|cron| cron.matches(&now))
+}
+
+/// 路由当前是否生效
+pub fn active(rule: &RouteRule) -> bool {
+    if rule.schedule.is_none() {
+        return true;
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+    active_at(rule, now)
+}
+
+pub fn validation_errors(rule: &RouteRule) -> Vec<String> {
+    let mut errors = Vec::new();
+    if let Some(schedule) = &rule.schedule {
+        if schedule.is_empty() {
+            errors.push("schedule不能为空".to_string());
+        }
+        errors.extend(schedule.iter().filter_map(|expr| Cron::parse(expr).err()).map(|e| format!("schedule: {}", e)));
+    }
+    if let Some(timezone) = &rule.schedule_timezone
+        && parse_offset(timezone).is_none()
+    {
+        errors.push(format!("schedule_timezone须为 UTC 或 +08:00 形式的 UTC 偏移: {}", timezone));
+    }
+    errors
+}
+
+pub fn settings_errors(settings: &Settings) -> Vec<String> {
+    match &settings.schedule_timezone {
+        Some(timezone) if parse_offset(timezone).is_none() => {
+            vec![format!("schedule_timezone须为 UTC 或 +08:00 形式的 UTC 偏移: {}", timezone)]
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_civil_time() {
+        // 2024-03-01 01:30:00 UTC，周五
+        let t = Civil::at(1_709_256_600, 0);
+        assert_eq!(t, Civil { minute: 30, hour: 1, day: 1, month: 3, weekday: 5 });
+        // 东八区已是 09:30；西五区仍是前一天（闰年的 2 月 29 日，周四）
+        assert_eq!(Civil::at(1_709_256_600, 8 * 3600).hour, 9);
+        assert_eq!(Civil::at(1_709_256_600, -5 * 3600), Civil { minute: 30, hour: 20, day: 29, month: 2, weekday: 4 });
+        assert_eq!(parse_offset("+05:30"), Some(19_800));
+        assert_eq!(parse_offset("-8"), Some(-28_800));
+        assert_eq!(parse_offset("Asia/Shanghai"), None);
+    }
+
+    #[test]
+    fn test_cron_matching() {
+        let business = Cron::parse("* 9-17 * * mon-fri").unwrap();
+        let at = |minute, hour, day, weekday| Civil { minute, hour, day, month: 3, weekday };
+        assert!(business.matches(&at(0, 9, 1, 5)));
+        assert!(business.matches(&at(59, 17, 1, 5)));
+        assert!(!business.matches(&at(0, 18, 1, 5)));
+        assert!(!business.matches(&at(0, 10, 2, 6)));
+
+        // 日与周同时限定时取“或”；7 表示周日
+        let cron = Cron::parse("*/15 0 1 * 7").unwrap();
+        assert!(cron.matches(&at(45, 0, 1, 3)));
+        assert!(cron.matches(&at(0, 0, 3, 0)));
+        assert!(!cron.matches(&at(10, 0, 3, 0)));
+
+        assert!(Cron::parse("* * * *").is_err());
+        assert!(Cron::parse("* 24 * * *").is_err());
+        assert!(Cron::parse("* * * * fri-mon").is_err());
+        assert!(Cron::parse("*/0 * * * *").is_err());
+    }
+
+    #[test]
+    fn test_route_schedule() {
+        let rule = RouteRule {
+            prefix: vec!["/batch".to_string()],
+            schedule: Some(vec!["* 0-5 * * *".to_string()]),
+            schedule_timezone: Some("+08:00".to_string()),
+            ..Default::default()
+        };
+        // 2024-03-01 01:30 UTC 为东八区 09:30，不在 0~5 点
+        assert!(!active_at(&rule, 1_709_256_600));
+        assert!(active_at(&rule, 1_709_256_600 - 6 * 3600));
+        assert!(active_at(&RouteRule::default(), 0));
+        assert!(validation_errors(&rule).is_empty());
+
+        // 生效时段内定时路由优先于同前缀的普通路由，时段外不参与匹配（2 月 31 日永不生效）
+        let plain = RouteRule { prefix: vec!["/batch".to_string()], upstream: vec!["http://online".to_string()], ..Default::default() };
+        let scheduled = |expr: &str| RouteRule {
+            upstream: vec!["http://batch".to_string()],
+            schedule: Some(vec![expr.to_string()]),
+            ..plain.clone()
+        };
+        let headers = axum::http::HeaderMap::new();
+        let rules = vec![plain.clone(), scheduled("* * * * *")];
+        assert_eq!(crate::proxy::find_best_match(&rules, "/batch/x", &headers).unwrap().upstream[0], "http://batch");
+        let rules = vec![plain.clone(), scheduled("* * 31 2 *")];
+        assert_eq!(crate::proxy::find_best_match(&rules, "/batch/x", &headers).unwrap().upstream[0], "http://online");
+
+        let invalid = RouteRule { schedule: Some(vec!["nope".to_string()]), schedule_timezone: Some("CST".to_string()), ..rule };
+        assert_eq!(validation_errors(&invalid).len(), 2);
+    }
+}
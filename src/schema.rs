@@ -0,0 +1,91 @@
+use axum::http::{header, HeaderMap};
+use dashmap::DashMap;
+use jsonschema::Validator;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// 单次响应最多列出的违规项
+const MAX_VIOLATIONS: usize = 20;
+
+// ===== 已编译的 Schema（按文件路径缓存，启动校验路由时即编译） =====
+static VALIDATORS: Lazy<DashMap<String, Arc<Validator>>> = Lazy::new(DashMap::new);
+
+/// 读取并编译 Schema 文件（相对于工作目录）
+pub fn load(path: &str) -> Result<Arc<Validator>, String> {
+    if let Some(validator) = VALIDATORS.get(path) {
+        return Ok(validator.clone());
+    }
+    let content = std::fs::read_to_string(path).map_err(|e| format!("读取 {} 失败: {}", path, e))?;
+    let schema: serde_json::Value = serde_json::from_str(&content).map_err(|e| format!("{} 不是合法的 JSON: {}", path, e))?;
+    let validator = jsonschema::validator_for(&schema).map_err(|e| format!("{} 无法编译: {}", path, e))?;
+    let validator = Arc::new(validator);
+    VALIDATORS.insert(path.to_string(), validator.clone());
+    Ok(validator)
+}
+
+#[derive(Debug, Serialize)]
+pub struct Violation {
+    // 违规位置（JSON Pointer），请求体整体问题为空
+    pub path: String,
+    pub message: String,
+}
+
+/// 校验请求的 Content-Type 与请求体，失败时返回违规列表
+pub fn validate_request(schema_path: &str, headers: &HeaderMap, body: &[u8]) -> Result<(), Vec<Violation>> {
+    let violation = |message: String| vec![Violation { path: String::new(), message }];
+
+    let content_type = headers.get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok()).unwrap_or_default();
+    if !is_json(content_type) {
+        return Err(violation(format!("Content-Type must be application/json, got '{}'", content_type)));
+    }
+
+    let validator = load(schema_path).map_err(violation)?;
+    let instance: serde_json::Value =
+        serde_json::from_slice(body).map_err(|e| violation(format!("Invalid JSON: {}", e)))?;
+
+    let violations: Vec<Violation> = validator
+        .iter_errors(&instance)
+        .take(MAX_VIOLATIONS)
+        .map(|e| Violation { path: e.instance_path().as_str().to_string(), message: e.to_string() })
+        .collect();
+    if violations.is_empty() { Ok(()) } else { Err(violations) }
+}
+
+// application/json 及 application/*+json
+fn is_json(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    mime == "application/json" || (mime.starts_with("application/") && mime.ends_with("+json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_request_lists_violations() {
+        let path = std::env::temp_dir().join(format!("helios-schema-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{"type":"object","required":["name","qty"],"properties":{"name":{"type":"string"},"qty":{"type":"integer","minimum":1}}}"#,
+        )
+        .unwrap();
+        let schema = path.to_str().unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, "application/json; charset=utf-8".parse().unwrap());
+        assert!(validate_request(schema, &headers, br#"{"name":"book","qty":2}"#).is_ok());
+
+        let violations = validate_request(schema, &headers, br#"{"name":1,"qty":0}"#).unwrap_err();
+        let paths: Vec<&str> = violations.iter().map(|v| v.path.as_str()).collect();
+        assert!(paths.contains(&"/name"));
+        assert!(paths.contains(&"/qty"));
+
+        assert!(validate_request(schema, &headers, b"not json").is_err());
+
+        headers.insert(header::CONTENT_TYPE, "text/plain".parse().unwrap());
+        assert!(validate_request(schema, &headers, br#"{"name":"book","qty":2}"#).is_err());
+
+        std::fs::remove_file(path).ok();
+    }
+}
@@ -0,0 +1,290 @@
+//! 受保护文件下发：配置了 sendfile 的路由收到带 X-Sendfile 或 X-Accel-Redirect 的上游响应时，网关丢弃上游的响应体，
+//! 直接把头中指向的文件（本地目录 root 下的路径，或 s3 中的对象键）发送给客户端。业务后端只负责鉴权并返回该头，
+//! 大文件的字节不再经过后端。
+//!
+//! 本地文件按块流式发送，支持单段 Range；头中的路径不能越出 root（含符号链接）。上游设置的 Content-Type、
+//! Content-Disposition、Cache-Control、Expires、Set-Cookie 沿用到文件响应上（与 nginx 一致）。
+use axum::{
+    body::{Body, Bytes},
+    extract::Request,
+    http::{header, HeaderMap, HeaderName, Method, Response, StatusCode},
+    response::IntoResponse,
+};
+use http_body::{Frame, SizeHint};
+use serde::{Deserialize, Serialize};
+use std::io::SeekFrom;
+use std::path::{Component, Path, PathBuf};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncSeekExt, ReadBuf};
+
+use crate::config::{RouteRule, Settings};
+use crate::problem::{Problem, ProblemType};
+
+/// 触发下发的响应头，按顺序查找
+pub const HEADERS: [&str; 2] = ["x-sendfile", crate::internal_redirect::HEADER];
+// 沿用到文件响应上的上游响应头
+const KEPT_HEADERS: [HeaderName; 5] =
+    [header::CONTENT_TYPE, header::CONTENT_DISPOSITION, header::CACHE_CONTROL, header::EXPIRES, header::SET_COOKIE];
+// 本地文件每次读取的块大小
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// 文件来源，root 与 s3 二选一
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SendfilePolicy {
+    // 本地文件根目录；头中的路径相对于该目录（X-Sendfile 也可以是该目录下的绝对路径）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+    // 对象存储；头中的路径去掉开头的 / 后拼接 key_prefix 作为对象键
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub s3: Option<crate::s3::S3Origin>,
+}
+
+pub fn validation_errors(rule: &RouteRule) -> Vec<String> {
+    let Some(policy) = &rule.sendfile else {
+        return Vec::new();
+    };
+    let mut errors = Vec::new();
+    match (&policy.root, &policy.s3) {
+        (Some(root), None) => {
+            if !Path::new(root).is_dir() {
+                errors.push(format!("sendfile.root不是目录: {}", root));
+            }
+        }
+        (None, Some(s3)) => errors.extend(s3.validation_errors().into_iter().map(|e| format!("sendfile.{}", e))),
+        _ => errors.push("sendfile须且只能配置 root 或 s3 之一".to_string()),
+    }
+    if crate::internal_redirect::enabled(rule) {
+        errors.push("sendfile与internal_redirect都处理 X-Accel-Redirect，不能同时开启".to_string());
+    }
+    errors
+}
+
+/// 下发文件所需的原请求信息
+pub struct Delivery {
+    policy: SendfilePolicy,
+    route: String,
+    method: Method,
+    headers: HeaderMap,
+    timeout: Duration,
+}
+
+impl Delivery {
+    /// 路由配置了 sendfile 时记录原请求；文件响应的方法除 HEAD 外均按 GET 处理
+    pub fn capture(rule: &RouteRule, req: &Request<Body>) -> Option<Self> {
+        let policy = rule.sendfile.clone()?;
+        let timeout = req.extensions().get::<Settings>().map(Settings::request_timeout).unwrap_or(Duration::from_secs(10));
+        let method = if req.method() == Method::HEAD { Method::HEAD } else { Method::GET };
+        Some(Self { policy, route: crate::proxy::route_label(rule), method, headers: req.headers().clone(), timeout })
+    }
+
+    /// 上游响应带下发头时返回文件响应，否则原样返回
+    pub async fn respond(self, resp: Response<Body>) -> Response<Body> {
+        let Some(target) = target(resp.headers()) else {
+            return resp;
+        };
+        let (source, mut delivered) = match (&self.policy.root, &self.policy.s3) {
+            (Some(root), _) => ("file", serve_file(Path::new(root), &target, &self.method, &self.headers).await),
+            (None, Some(origin)) => {
+                let key = origin.prefixed_key(&target);
+                ("s3", crate::s3::serve_object(origin, &key, &self.method, &self.headers, self.timeout).await)
+            }
+            (None, None) => return resp,
+        };
+        let result = match delivered.status() {
+            StatusCode::NOT_FOUND => "not_found",
+            status if status.is_server_error() => "error",
+            _ => "ok",
+        };
+        crate::metrics::SENDFILE_DELIVERIES.with_label_values(&[&self.route, source, result]).inc();
+
+        let (parts, _) = resp.into_parts();
+        if delivered.status().is_success() {
+            for name in &KEPT_HEADERS {
+                let values: Vec<_> = parts.headers.get_all(name).iter().cloned().collect();
+                if !values.is_empty() {
+                    delivered.headers_mut().remove(name);
+                }
+                for value in values {
+                    delivered.headers_mut().append(name.clone(), value);
+                }
+            }
+        }
+        // 上游响应的扩展（身份等）留给后续的指标与日志
+        delivered.extensions_mut().extend(parts.extensions);
+        delivered
+    }
+}
+
+// 下发头中的路径，去掉查询串
+fn target(headers: &HeaderMap) -> Option<String> {
+    let value = HEADERS.iter().find_map(|name| headers.get(*name))?.to_str().ok()?;
+    let path = value.split('?').next().unwrap_or_default().trim();
+    (!path.is_empty()).then(|| path.to_string())
+}
+
+// 把头中的路径拼接到 root 下；含 .. 时返回 None
+fn resolve(root: &Path, target: &str) -> Option<PathBuf> {
+    let path = Path::new(target);
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let mut resolved = root.to_path_buf();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::RootDir | Component::CurDir => {}
+            Component::ParentDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(resolved)
+}
+
+/// 解析单段 Range：None 表示不是单段字节范围（按完整文件返回），Some(None) 表示范围无法满足
+fn parse_range(value: &str, len: u64) -> Option<Option<(u64, u64)>> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (first, last) = spec.split_once('-')?;
+    let (first, last) = (first.trim(), last.trim());
+    let range = if first.is_empty() {
+        let suffix: u64 = last.parse().ok()?;
+        (suffix > 0 && len > 0).then(|| (len.saturating_sub(suffix), len - 1))
+    } else {
+        let start: u64 = first.parse().ok()?;
+        let end = match last {
+            "" => len.saturating_sub(1),
+            last => last.parse::<u64>().ok()?.min(len.saturating_sub(1)),
+        };
+        (start < len && start <= end).then_some((start, end))
+    };
+    Some(range)
+}
+
+fn not_found() -> Response<Body> {
+    Problem::new(StatusCode::NOT_FOUND, ProblemType::NotFound).detail("File not found").into_response()
+}
+
+async fn serve_file(root: &Path, target: &str, method: &Method, req_headers: &HeaderMap) -> Response<Body> {
+    let Some(path) = resolve(root, target) else {
+        tracing::warn!(target = %target, "sendfile 路径包含 ..，拒绝下发");
+        return not_found();
+    };
+    // 规范化后再比较，防止经符号链接越出 root
+    let (Ok(path), Ok(root)) = (tokio::fs::canonicalize(&path).await, tokio::fs::canonicalize(root).await) else {
+        return not_found();
+    };
+    if !path.starts_with(&root) {
+        tracing::warn!(target = %target, "sendfile 路径越出 root，拒绝下发");
+        return not_found();
+    }
+    let Ok(mut file) = tokio::fs::File::open(&path).await else {
+        return not_found();
+    };
+    let metadata = match file.metadata().await {
+        Ok(metadata) if metadata.is_file() => metadata,
+        _ => return not_found(),
+    };
+    let len = metadata.len();
+
+    let mut builder = Response::builder()
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .header(header::ACCEPT_RANGES, "bytes");
+    if let Ok(modified) = metadata.modified() {
+        builder = builder.header(header::LAST_MODIFIED, httpdate::fmt_http_date(modified));
+    }
+    let range = req_headers.get(header::RANGE).and_then(|v| v.to_str().ok()).and_then(|v| parse_range(v, len));
+    let (status, start, count) = match range {
+        None => (StatusCode::OK, 0, len),
+        Some(Some((start, end))) => {
+            builder = builder.header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, len));
+            (StatusCode::PARTIAL_CONTENT, start, end - start + 1)
+        }
+        Some(None) => {
+            return Problem::new(StatusCode::RANGE_NOT_SATISFIABLE, ProblemType::BadRequest)
+                .detail("Requested range not satisfiable")
+                .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+                .into_response();
+        }
+    };
+    let builder = builder.status(status).header(header::CONTENT_LENGTH, count);
+    if method == Method::HEAD || count == 0 {
+        return builder.body(Body::empty()).unwrap();
+    }
+    if start > 0 && file.seek(SeekFrom::Start(start)).await.is_err() {
+        return Problem::new(StatusCode::INTERNAL_SERVER_ERROR, ProblemType::Internal).detail("File read error").into_response();
+    }
+    builder.body(Body::new(FileBody { file, remaining: count })).unwrap()
+}
+
+/// 按块读取文件的响应体，只发送 remaining 字节
+struct FileBody {
+    file: tokio::fs::File,
+    remaining: u64,
+}
+
+impl http_body::Body for FileBody {
+    type Data = Bytes;
+    type Error = std::io::Error;
+
+    fn poll_frame(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, std::io::Error>>> {
+        if self.remaining == 0 {
+            return Poll::Ready(None);
+        }
+        let this = &mut *self;
+        let mut buf = vec![0; this.remaining.min(CHUNK_SIZE) as usize];
+        let mut read = ReadBuf::new(&mut buf);
+        match Pin::new(&mut this.file).poll_read(cx, &mut read) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Err(err)) => Poll::Ready(Some(Err(err))),
+            Poll::Ready(Ok(())) => {
+                let n = read.filled().len();
+                if n == 0 {
+                    let err = std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "文件在发送过程中被截断");
+                    return Poll::Ready(Some(Err(err)));
+                }
+                this.remaining -= n as u64;
+                buf.truncate(n);
+                Poll::Ready(Some(Ok(Frame::data(Bytes::from(buf)))))
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.remaining == 0
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::with_exact(self.remaining)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let root = Path::new("/srv/protected");
+        assert_eq!(resolve(root, "/reports/q1.pdf").unwrap(), Path::new("/srv/protected/reports/q1.pdf"));
+        assert_eq!(resolve(root, "/srv/protected/reports/q1.pdf").unwrap(), Path::new("/srv/protected/reports/q1.pdf"));
+        assert!(resolve(root, "/reports/../../etc/passwd").is_none());
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-accel-redirect", "/reports/q1.pdf?sig=1".parse().unwrap());
+        assert_eq!(target(&headers).as_deref(), Some("/reports/q1.pdf"));
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-9", 100), Some(Some((0, 9))));
+        assert_eq!(parse_range("bytes=90-", 100), Some(Some((90, 99))));
+        assert_eq!(parse_range("bytes=-10", 100), Some(Some((90, 99))));
+        assert_eq!(parse_range("bytes=50-500", 100), Some(Some((50, 99))));
+        assert_eq!(parse_range("bytes=100-", 100), Some(None));
+        // 多段范围与无法解析的值按完整文件返回
+        assert_eq!(parse_range("bytes=0-1,5-6", 100), None);
+        assert_eq!(parse_range("items=0-1", 100), None);
+    }
+}
@@ -0,0 +1,179 @@
+//! Server-Timing 响应头：把一次转发的耗时拆成 dns、connect、ttfb、total 返回给客户端，
+//! 前端无需查看网关日志即可在浏览器开发者工具中看到时间花在了哪里。
+//!
+//! 上游客户端的 DNS 解析与建连经由本模块的解析器与连接层完成，它们把耗时记到当前转发尝试的记录中（task-local）；
+//! 复用连接池中的连接时没有 dns 与 connect 两项。https 上游的 TLS 握手计入 connect（desc 为 tcp+tls）。
+use axum::{
+    body::Body,
+    http::{HeaderValue, Response},
+};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+pub const HEADER: &str = "server-timing";
+
+tokio::task_local! {
+    static CONNECTION: Arc<Mutex<Connection>>;
+}
+
+/// 新建连接的耗时；复用连接时为空
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Connection {
+    pub dns: Option<Duration>,
+    // TCP 建连（https 含 TLS 握手），不含 DNS 解析
+    pub connect: Option<Duration>,
+}
+
+fn record(update: impl FnOnce(&mut Connection)) {
+    let _ = CONNECTION.try_with(|connection| {
+        if let Ok(mut connection) = connection.lock() {
+            update(&mut connection);
+        }
+    });
+}
+
+/// 单次转发尝试的耗时，附在上游响应的扩展中
+#[derive(Debug, Clone, Copy)]
+pub struct Attempt {
+    pub connection: Connection,
+    // 发出请求到收到响应头
+    pub ttfb: Duration,
+}
+
+/// 执行一次转发尝试，返回结果与期间新建连接的耗时
+pub async fn measure<F: Future>(attempt: F) -> (F::Output, Connection) {
+    let connection = Arc::new(Mutex::new(Connection::default()));
+    let output = CONNECTION.scope(connection.clone(), attempt).await;
+    let connection = connection.lock().map(|c| *c).unwrap_or_default();
+    (output, connection)
+}
+
+/// 记录解析耗时的 DNS 解析器（系统 getaddrinfo，与默认解析器一致）
+pub struct TimedResolver;
+
+impl Resolve for TimedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let start = Instant::now();
+            let addrs: Vec<_> = tokio::net::lookup_host((name.as_str(), 0)).await?.collect();
+            record(|c| c.dns = Some(start.elapsed()));
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// 记录建连耗时的连接层
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectTiming;
+
+impl<S> tower::Layer<S> for ConnectTiming {
+    type Service = Timed<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Timed(inner)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Timed<S>(S);
+
+impl<S, R> tower::Service<R> for Timed<S>
+where
+    S: tower::Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        let connecting = self.0.call(req);
+        Box::pin(async move {
+            let start = Instant::now();
+            let result = connecting.await;
+            if result.is_ok() {
+                let elapsed = start.elapsed();
+                // 连接层的耗时包含其中的 DNS 解析
+                record(|c| c.connect = Some(elapsed.saturating_sub(c.dns.unwrap_or_default())));
+            }
+            result
+        })
+    }
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64() * 1000.0)
+}
+
+/// 组装 Server-Timing 的值；tls 表示 connect 中包含 TLS 握手
+pub fn value(attempt: &Attempt, tls: bool, total: Duration) -> String {
+    let mut metrics = Vec::with_capacity(4);
+    if let Some(dns) = attempt.connection.dns {
+        metrics.push(format!("dns;dur={}", millis(dns)));
+    }
+    if let Some(connect) = attempt.connection.connect {
+        let desc = if tls { "tcp+tls" } else { "tcp" };
+        metrics.push(format!("connect;dur={};desc=\"{}\"", millis(connect), desc));
+    }
+    metrics.push(format!("ttfb;dur={}", millis(attempt.ttfb)));
+    metrics.push(format!("total;dur={}", millis(total)));
+    metrics.join(", ")
+}
+
+/// 在响应上追加网关的 Server-Timing（保留上游自己的 Server-Timing）
+pub fn apply(resp: &mut Response<Body>, attempt: &Attempt, tls: bool, total: Duration) {
+    if let Ok(value) = HeaderValue::from_str(&value(attempt, tls, total)) {
+        resp.headers_mut().append(HEADER, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value() {
+        let attempt = Attempt {
+            connection: Connection { dns: Some(Duration::from_micros(1250)), connect: Some(Duration::from_millis(3)) },
+            ttfb: Duration::from_millis(20),
+        };
+        assert_eq!(
+            value(&attempt, true, Duration::from_micros(25_500)),
+            "dns;dur=1.250, connect;dur=3.000;desc=\"tcp+tls\", ttfb;dur=20.000, total;dur=25.500"
+        );
+        // 复用连接时只有 ttfb 与 total
+        let reused = Attempt { connection: Connection::default(), ttfb: Duration::from_millis(2) };
+        assert_eq!(value(&reused, false, Duration::from_millis(3)), "ttfb;dur=2.000, total;dur=3.000");
+    }
+
+    #[tokio::test]
+    async fn test_measure_records_new_connections() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let app = axum::Router::new().route("/", axum::routing::get(|| async { "ok" }));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let client = reqwest::Client::builder()
+            .dns_resolver(Arc::new(TimedResolver))
+            .connector_layer(ConnectTiming)
+            .build()
+            .unwrap();
+        let url = format!("http://localhost:{}/", port);
+        let (resp, connection) = measure(client.get(&url).send()).await;
+        assert_eq!(resp.unwrap().text().await.unwrap(), "ok");
+        assert!(connection.dns.is_some() && connection.connect.is_some(), "{:?}", connection);
+
+        // 连接池中的连接被复用，没有新的 DNS 解析与建连
+        let (resp, connection) = measure(client.get(&url).send()).await;
+        assert_eq!(resp.unwrap().text().await.unwrap(), "ok");
+        assert_eq!(connection, Connection::default());
+    }
+}
@@ -0,0 +1,325 @@
+use axum::{
+    async_trait,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Extension, Json, Router,
+};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use crate::auth::{decode_bearer, Claims};
+use crate::config::Settings;
+use crate::problem::{Problem, ProblemType};
+
+/// 默认会话 Cookie 名
+pub const DEFAULT_COOKIE: &str = "helios_session";
+
+/// Cookie 名称须为 RFC 6265 的 token：可见 ASCII 字符，不含分隔符
+pub fn valid_cookie_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|b| b.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?={}".contains(&b))
+}
+
+/// 服务端保存的会话：原始 token 及解析后的 Claims
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub token: String,
+    pub claims: Claims,
+}
+
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    async fn put(&self, id: &str, session: &Session, ttl: Duration) -> Result<(), String>;
+    async fn get(&self, id: &str) -> Result<Option<Session>, String>;
+    async fn delete(&self, id: &str) -> Result<(), String>;
+}
+
+// ===== 内存存储（单实例部署） =====
+#[derive(Default)]
+pub struct MemoryStore {
+    sessions: DashMap<String, (Session, Instant)>,
+}
+
+#[async_trait]
+impl SessionStore for MemoryStore {
+    async fn put(&self, id: &str, session: &Session, ttl: Duration) -> Result<(), String> {
+        // 写入时顺带清理过期会话
+        let now = Instant::now();
+        self.sessions.retain(|_, (_, expires)| *expires > now);
+        self.sessions.insert(id.to_string(), (session.clone(), now + ttl));
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<Session>, String> {
+        let expired = match self.sessions.get(id) {
+            Some(entry) if entry.1 > Instant::now() => return Ok(Some(entry.0.clone())),
+            Some(_) => true,
+            None => false,
+        };
+        if expired {
+            self.sessions.remove(id);
+        }
+        Ok(None)
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), String> {
+        self.sessions.remove(id);
+        Ok(())
+    }
+}
+
+// ===== Redis 存储（多实例共享，需启用 redis-session 特性） =====
+#[cfg(feature = "redis-session")]
+pub struct RedisStore {
+    client: redis::Client,
+    conn: tokio::sync::OnceCell<redis::aio::ConnectionManager>,
+}
+
+#[cfg(feature = "redis-session")]
+impl RedisStore {
+    const KEY_PREFIX: &'static str = "helios:session:";
+
+    pub fn new(url: &str) -> Result<Self, String> {
+        let client = redis::Client::open(url).map_err(|e| e.to_string())?;
+        Ok(Self { client, conn: tokio::sync::OnceCell::new() })
+    }
+
+    // 首次使用时建立连接，之后自动重连
+    async fn conn(&self) -> Result<redis::aio::ConnectionManager, String> {
+        self.conn
+            .get_or_try_init(|| redis::aio::ConnectionManager::new(self.client.clone()))
+            .await
+            .cloned()
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(feature = "redis-session")]
+#[async_trait]
+impl SessionStore for RedisStore {
+    async fn put(&self, id: &str, session: &Session, ttl: Duration) -> Result<(), String> {
+        let value = serde_json::to_string(session).map_err(|e| e.to_string())?;
+        let mut conn = self.conn().await?;
+        redis::AsyncCommands::set_ex::<_, _, ()>(&mut conn, format!("{}{}", Self::KEY_PREFIX, id), value, ttl.as_secs().max(1))
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<Session>, String> {
+        let mut conn = self.conn().await?;
+        let value: Option<String> = redis::AsyncCommands::get(&mut conn, format!("{}{}", Self::KEY_PREFIX, id))
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(value.and_then(|v| serde_json::from_str(&v).ok()))
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), String> {
+        let mut conn = self.conn().await?;
+        redis::AsyncCommands::del::<_, ()>(&mut conn, format!("{}{}", Self::KEY_PREFIX, id))
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+// ===== 会话配置 =====
+/// 启用会话时注入请求扩展
+pub struct Sessions {
+    pub store: Arc<dyn SessionStore>,
+    pub cookie: String,
+    pub ttl: Duration,
+}
+
+impl Sessions {
+    /// 未配置 session_store 时返回 None（不启用会话）
+    pub fn from_settings(settings: &Settings) -> Option<Arc<Self>> {
+        let store: Arc<dyn SessionStore> = match settings.session_store.as_deref()? {
+            "memory" => Arc::new(MemoryStore::default()),
+            #[cfg(feature = "redis-session")]
+            url => match RedisStore::new(url) {
+                Ok(store) => Arc::new(store),
+                Err(err) => {
+                    tracing::error!("会话存储初始化失败: {}", err);
+                    return None;
+                }
+            },
+            #[cfg(not(feature = "redis-session"))]
+            _ => {
+                tracing::error!("session_store使用 Redis 需要以 redis-session 特性编译，会话未启用");
+                return None;
+            }
+        };
+        let cookie = settings.session_cookie_name.clone().unwrap_or_else(|| DEFAULT_COOKIE.to_string());
+        if !valid_cookie_name(&cookie) {
+            tracing::error!(cookie = %cookie, "session_cookie_name不是合法的 Cookie 名称，会话未启用");
+            return None;
+        }
+        Some(Arc::new(Self {
+            store,
+            cookie,
+            ttl: Duration::from_secs(settings.session_ttl_secs.unwrap_or(3600)),
+        }))
+    }
+
+    /// 从 Cookie 头中取出会话 ID
+    pub fn session_id(&self, headers: &HeaderMap) -> Option<String> {
+        headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(';'))
+            .filter_map(|pair| pair.trim().split_once('='))
+            .find(|(name, _)| *name == self.cookie)
+            .map(|(_, value)| value.to_string())
+    }
+
+    /// 按 Cookie 查找会话（存储异常视为无会话）
+    pub async fn lookup(&self, headers: &HeaderMap) -> Option<Session> {
+        let id = self.session_id(headers)?;
+        match self.store.get(&id).await {
+            Ok(session) => session,
+            Err(err) => {
+                tracing::warn!("读取会话失败: {}", err);
+                None
+            }
+        }
+    }
+
+    // Cookie 名称在 from_settings 中已校验，会话 ID 为十六进制，正常不会失败
+    fn cookie_header(&self, value: &str, max_age: u64) -> Option<HeaderValue> {
+        HeaderValue::from_str(&format!(
+            "{}={}; Path=/; HttpOnly; Secure; SameSite=Lax; Max-Age={}",
+            self.cookie, value, max_age
+        ))
+        .ok()
+    }
+}
+
+fn new_session_id() -> String {
+    let bytes: [u8; 32] = rand::random();
+    hex::encode(bytes)
+}
+
+// ===== 会话端点 =====
+pub fn router() -> Router {
+    Router::new().route("/gateway/session", post(login).delete(logout))
+}
+
+fn error(status: StatusCode, kind: ProblemType, msg: &str) -> Response {
+    Problem::new(status, kind).detail(msg).into_response()
+}
+
+#[derive(Debug, Serialize)]
+struct SessionCreated {
+    sub: String,
+    expires_in: u64,
+}
+
+/// 用 Bearer token 换取 httpOnly 会话 Cookie
+async fn login(
+    sessions: Option<Extension<Arc<Sessions>>>,
+    Extension(settings): Extension<Settings>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(Extension(sessions)) = sessions else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let claims = match decode_bearer(&headers, &settings.jwt_decoding_key) {
+        Ok(claims) => claims,
+        Err(err) => return err.into_response(),
+    };
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+
+    // 会话不超过 token 本身的有效期
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let ttl = sessions.ttl.as_secs().min((claims.exp as u64).saturating_sub(now));
+    if ttl == 0 {
+        return error(StatusCode::UNAUTHORIZED, ProblemType::Unauthorized, "Token expired");
+    }
+
+    let id = new_session_id();
+    let Some(cookie) = sessions.cookie_header(&id, ttl) else {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, ProblemType::Internal, "Invalid session cookie");
+    };
+    let session = Session { token, claims };
+    if let Err(err) = sessions.store.put(&id, &session, Duration::from_secs(ttl)).await {
+        tracing::error!("写入会话失败: {}", err);
+        return error(StatusCode::SERVICE_UNAVAILABLE, ProblemType::ServiceUnavailable, "Session store unavailable");
+    }
+
+    let mut resp = Json(SessionCreated { sub: session.claims.sub, expires_in: ttl }).into_response();
+    resp.headers_mut().insert(header::SET_COOKIE, cookie);
+    resp
+}
+
+/// 删除服务端会话并清除 Cookie，立即失效
+async fn logout(sessions: Option<Extension<Arc<Sessions>>>, headers: HeaderMap) -> Response {
+    let Some(Extension(sessions)) = sessions else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if let Some(id) = sessions.session_id(&headers)
+        && let Err(err) = sessions.store.delete(&id).await
+    {
+        tracing::error!("删除会话失败: {}", err);
+        return error(StatusCode::SERVICE_UNAVAILABLE, ProblemType::ServiceUnavailable, "Session store unavailable");
+    }
+    let mut resp = StatusCode::NO_CONTENT.into_response();
+    if let Some(cookie) = sessions.cookie_header("", 0) {
+        resp.headers_mut().insert(header::SET_COOKIE, cookie);
+    }
+    resp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_store_expiry_and_delete() {
+        let store = MemoryStore::default();
+        let session = Session {
+            token: "t".to_string(),
+            claims: Claims { sub: "u1".to_string(), tenant_id: "t1".to_string(), ..Default::default() },
+        };
+        store.put("a", &session, Duration::from_secs(60)).await.unwrap();
+        store.put("b", &session, Duration::ZERO).await.unwrap();
+
+        assert_eq!(store.get("a").await.unwrap().unwrap().claims.sub, "u1");
+        assert!(store.get("b").await.unwrap().is_none());
+
+        store.delete("a").await.unwrap();
+        assert!(store.get("a").await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_session_cookie_parsing() {
+        let sessions = Sessions {
+            store: Arc::new(MemoryStore::default()),
+            cookie: DEFAULT_COOKIE.to_string(),
+            ttl: Duration::from_secs(60),
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, "theme=dark; helios_session=abc123".parse().unwrap());
+        assert_eq!(sessions.session_id(&headers).as_deref(), Some("abc123"));
+    }
+
+    #[test]
+    fn test_cookie_name_validation() {
+        assert!(valid_cookie_name(DEFAULT_COOKIE));
+        assert!(valid_cookie_name("__Host-sid"));
+        for name in ["", "my session", "sid;", "a=b", "sess\u{e9}", "\"sid\""] {
+            assert!(!valid_cookie_name(name), "{:?}", name);
+        }
+
+        // 非法名称不启用会话，而不是在签发 Cookie 时 panic
+        let settings = |name: &str| crate::testing::settings(serde_json::json!({ "session_store": "memory", "session_cookie_name": name }));
+        assert!(Sessions::from_settings(&settings("sid")).is_some());
+        assert!(Sessions::from_settings(&settings("bad name\n")).is_none());
+    }
+}
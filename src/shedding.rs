@@ -0,0 +1,219 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::config::{RouteRule, Settings};
+use crate::metrics::{EVENT_LOOP_LAG_MS, PROCESS_CPU_PERCENT, PROCESS_MEMORY_BYTES};
+
+// /proc/self/stat 中 CPU 时间的单位（USER_HZ），Linux 上固定为 100
+const USER_HZ: f64 = 100.0;
+
+/// 触发降载的资源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overload {
+    Cpu = 1,
+    Memory = 2,
+    EventLoopLag = 3,
+}
+
+impl Overload {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Overload::Cpu => "cpu",
+            Overload::Memory => "memory",
+            Overload::EventLoopLag => "event_loop_lag",
+        }
+    }
+
+    fn from_code(code: u8) -> Option<Self> {
+        match code {
+            1 => Some(Overload::Cpu),
+            2 => Some(Overload::Memory),
+            3 => Some(Overload::EventLoopLag),
+            _ => None,
+        }
+    }
+}
+
+/// 单次资源采样；非 Linux 平台无法读取 CPU 与内存时为 None
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sample {
+    pub cpu_percent: Option<f64>,
+    pub memory_bytes: Option<u64>,
+    pub event_loop_lag: Duration,
+}
+
+/// 降载阈值，未配置的项不参与判断
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Thresholds {
+    pub cpu_percent: Option<f64>,
+    pub memory_bytes: Option<u64>,
+    pub event_loop_lag: Option<Duration>,
+}
+
+impl Thresholds {
+    /// 均未配置时不启用降载
+    pub fn from_settings(settings: &Settings) -> Option<Self> {
+        let thresholds = Self {
+            cpu_percent: settings.load_shed_cpu_percent.map(f64::from),
+            memory_bytes: settings.load_shed_memory_mb.map(|mb| mb * 1024 * 1024),
+            event_loop_lag: settings.load_shed_event_loop_lag_ms.map(Duration::from_millis),
+        };
+        (thresholds.cpu_percent.is_some() || thresholds.memory_bytes.is_some() || thresholds.event_loop_lag.is_some())
+            .then_some(thresholds)
+    }
+
+    /// 第一个超过阈值的资源
+    pub fn exceeded(&self, sample: &Sample) -> Option<Overload> {
+        if let (Some(max), Some(cpu)) = (self.cpu_percent, sample.cpu_percent)
+            && cpu >= max
+        {
+            return Some(Overload::Cpu);
+        }
+        if let (Some(max), Some(memory)) = (self.memory_bytes, sample.memory_bytes)
+            && memory >= max
+        {
+            return Some(Overload::Memory);
+        }
+        if self.event_loop_lag.is_some_and(|max| sample.event_loop_lag >= max) {
+            return Some(Overload::EventLoopLag);
+        }
+        None
+    }
+}
+
+// 最近一次采样的结论：0 表示未过载，其余为 Overload 的取值
+static OVERLOAD: AtomicU8 = AtomicU8::new(0);
+
+/// 当前是否过载及原因
+pub fn overloaded() -> Option<Overload> {
+    Overload::from_code(OVERLOAD.load(Ordering::Relaxed))
+}
+
+/// 过载时可以被拒绝的低优先级路由
+pub fn is_low_priority(rule: &RouteRule) -> bool {
+    rule.priority.as_deref() == Some("low")
+}
+
+// ===== 资源采样 =====
+// /proc/self/stat 第 14、15 项为用户态与内核态 CPU 时间；进程名可能含空格，从最后一个 ')' 之后开始数
+fn parse_cpu_ticks(stat: &str) -> Option<u64> {
+    let mut fields = stat.rsplit_once(')')?.1.split_whitespace().skip(11);
+    let utime: u64 = fields.next()?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+    Some(utime + stime)
+}
+
+// /proc/self/status 中的 VmRSS 行，单位 kB
+fn parse_vm_rss(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+fn resident_memory_bytes() -> Option<u64> {
+    parse_vm_rss(&std::fs::read_to_string("/proc/self/status").ok()?)
+}
+
+// 按两次采样之间的 CPU 时间增量计算占用率，按核数归一化到 0~100
+struct CpuSampler {
+    last: Option<(Instant, u64)>,
+    cores: f64,
+}
+
+impl CpuSampler {
+    fn new() -> Self {
+        let cores = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1) as f64;
+        Self { last: None, cores }
+    }
+
+    fn sample(&mut self) -> Option<f64> {
+        let ticks = parse_cpu_ticks(&std::fs::read_to_string("/proc/self/stat").ok()?)?;
+        let now = Instant::now();
+        let previous = self.last.replace((now, ticks));
+        let (at, prev_ticks) = previous?;
+        let elapsed = now.duration_since(at).as_secs_f64();
+        if elapsed <= 0.0 {
+            return None;
+        }
+        let busy = ticks.saturating_sub(prev_ticks) as f64 / USER_HZ;
+        Some((busy / elapsed / self.cores * 100.0).min(100.0))
+    }
+}
+
+/// 配置了任一阈值时启动后台采样
+pub fn spawn_monitor(settings: &Settings) {
+    let Some(thresholds) = Thresholds::from_settings(settings) else {
+        return;
+    };
+    let interval = Duration::from_millis(settings.load_shed_sample_interval_ms.unwrap_or(500).max(10));
+    tracing::info!(interval_ms = interval.as_millis() as u64, "启动负载降载监控");
+    tokio::spawn(monitor_loop(thresholds, interval));
+}
+
+async fn monitor_loop(thresholds: Thresholds, interval: Duration) {
+    let mut cpu = CpuSampler::new();
+    loop {
+        // 事件循环延迟：定时器实际唤醒时间超出预定时间的部分
+        let start = Instant::now();
+        tokio::time::sleep(interval).await;
+        let sample = Sample {
+            event_loop_lag: start.elapsed().saturating_sub(interval),
+            cpu_percent: cpu.sample(),
+            memory_bytes: resident_memory_bytes(),
+        };
+        EVENT_LOOP_LAG_MS.set(sample.event_loop_lag.as_millis() as i64);
+        if let Some(percent) = sample.cpu_percent {
+            PROCESS_CPU_PERCENT.set(percent.round() as i64);
+        }
+        if let Some(bytes) = sample.memory_bytes {
+            PROCESS_MEMORY_BYTES.set(bytes as i64);
+        }
+
+        let state = thresholds.exceeded(&sample);
+        let previous = Overload::from_code(OVERLOAD.swap(state.map_or(0, |o| o as u8), Ordering::Relaxed));
+        match (previous, state) {
+            (None, Some(reason)) => tracing::warn!(
+                reason = reason.as_str(),
+                cpu_percent = sample.cpu_percent,
+                memory_bytes = sample.memory_bytes,
+                event_loop_lag_ms = sample.event_loop_lag.as_millis() as u64,
+                "网关过载，开始拒绝低优先级请求"
+            ),
+            (Some(_), None) => tracing::info!("网关负载恢复，停止降载"),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc() {
+        let stat = "4242 (helios worker) S 1 4242 4242 0 -1 4194560 100 0 0 0 250 50 0 0 20 0 8 0 12345";
+        assert_eq!(parse_cpu_ticks(stat), Some(300));
+        assert_eq!(parse_cpu_ticks("garbage"), None);
+
+        let status = "Name:\thelios\nVmPeak:\t  20000 kB\nVmRSS:\t   1536 kB\n";
+        assert_eq!(parse_vm_rss(status), Some(1536 * 1024));
+    }
+
+    #[test]
+    fn test_thresholds_exceeded() {
+        let thresholds = Thresholds {
+            cpu_percent: Some(80.0),
+            memory_bytes: None,
+            event_loop_lag: Some(Duration::from_millis(50)),
+        };
+        let calm = Sample { cpu_percent: Some(30.0), memory_bytes: Some(u64::MAX), event_loop_lag: Duration::from_millis(2) };
+        assert_eq!(thresholds.exceeded(&calm), None);
+
+        let busy = Sample { cpu_percent: Some(95.0), ..calm };
+        assert_eq!(thresholds.exceeded(&busy), Some(Overload::Cpu));
+
+        // 平台不支持 CPU 采样时只看已有的指标
+        let lagging = Sample { cpu_percent: None, event_loop_lag: Duration::from_millis(120), ..calm };
+        assert_eq!(thresholds.exceeded(&lagging), Some(Overload::EventLoopLag));
+    }
+}
@@ -0,0 +1,235 @@
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::metrics::{SLO_BURN_RATE, SLO_COMPLIANCE};
+
+/// 滚动窗口（分钟）及其名称；环形缓冲按分钟分桶，覆盖最长的窗口
+pub const WINDOWS: [(&str, u64); 3] = [("5m", 5), ("1h", 60), ("6h", 360)];
+const BUCKETS: usize = 360;
+
+/// 路由级 SLO：可用性（非 5xx 比例）与延迟达标比例，至少配置一项
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Slo {
+    // 如 0.999 表示 5xx 不超过 0.1%
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub availability: Option<f64>,
+    // 延迟阈值与达标比例：如 300 与 0.999 表示 99.9% 的请求在 300ms 内完成
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_target: Option<f64>,
+}
+
+impl Slo {
+    pub fn validation_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (field, target) in [("slo.availability", self.availability), ("slo.latency_target", self.latency_target)] {
+            if target.is_some_and(|t| !(t > 0.0 && t < 1.0)) {
+                errors.push(format!("{}必须在0到1之间（不含端点）", field));
+            }
+        }
+        if self.latency_ms.is_some() != self.latency_target.is_some() {
+            errors.push("slo.latency_ms与slo.latency_target必须同时配置".to_string());
+        }
+        if self.latency_ms == Some(0) {
+            errors.push("slo.latency_ms必须大于0".to_string());
+        }
+        if self.availability.is_none() && self.latency_target.is_none() {
+            errors.push("slo至少需要配置availability或latency_target".to_string());
+        }
+        errors
+    }
+
+    // (目标名称, 达标比例)
+    fn objectives(&self) -> impl Iterator<Item = (&'static str, f64)> {
+        let availability = self.availability.map(|t| ("availability", t));
+        let latency = self.latency_target.map(|t| ("latency", t));
+        availability.into_iter().chain(latency)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Bucket {
+    minute: u64,
+    total: u64,
+    errors: u64,
+    slow: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Counts {
+    total: u64,
+    errors: u64,
+    slow: u64,
+}
+
+// 按分钟分桶的环形缓冲，过期的桶在写入时覆盖
+struct Tracker {
+    slo: Slo,
+    buckets: Mutex<Vec<Bucket>>,
+}
+
+impl Tracker {
+    fn new(slo: Slo) -> Self {
+        Self { slo, buckets: Mutex::new(vec![Bucket::default(); BUCKETS]) }
+    }
+
+    fn record_at(&self, minute: u64, status: u16, elapsed: Duration) {
+        let slow = self.slo.latency_ms.is_some_and(|max| elapsed.as_millis() as u64 > max);
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = &mut buckets[(minute % BUCKETS as u64) as usize];
+        if bucket.minute != minute {
+            *bucket = Bucket { minute, ..Default::default() };
+        }
+        bucket.total += 1;
+        bucket.errors += u64::from(status >= 500);
+        bucket.slow += u64::from(slow);
+    }
+
+    fn window_at(&self, minute: u64, minutes: u64) -> Counts {
+        let buckets = self.buckets.lock().unwrap();
+        buckets
+            .iter()
+            .filter(|b| b.total > 0 && b.minute <= minute && minute - b.minute < minutes)
+            .fold(Counts::default(), |acc, b| Counts {
+                total: acc.total + b.total,
+                errors: acc.errors + b.errors,
+                slow: acc.slow + b.slow,
+            })
+    }
+}
+
+static TRACKERS: Lazy<DashMap<String, Tracker>> = Lazy::new(DashMap::new);
+
+fn current_minute() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() / 60).unwrap_or(0)
+}
+
+/// 记录配置了 SLO 的路由的一次请求
+pub fn record(route: &str, slo: &Slo, status: u16, elapsed: Duration) {
+    if !TRACKERS.contains_key(route) {
+        TRACKERS.entry(route.to_string()).or_insert_with(|| Tracker::new(slo.clone()));
+    }
+    if let Some(tracker) = TRACKERS.get(route) {
+        tracker.record_at(current_minute(), status, elapsed);
+    }
+}
+
+// ===== 达标情况 =====
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct WindowStatus {
+    pub window: &'static str,
+    pub total: u64,
+    pub bad: u64,
+    // 无请求时为 1
+    pub compliance: f64,
+    // 错误预算消耗速度：实际坏请求比例 / 允许的坏请求比例，1 表示恰好在窗口内耗尽预算
+    pub burn_rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ObjectiveStatus {
+    pub objective: &'static str,
+    pub target: f64,
+    // 最长窗口内剩余的错误预算比例，可为负
+    pub error_budget_remaining: f64,
+    pub windows: Vec<WindowStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RouteSlo {
+    pub route: String,
+    pub slo: Slo,
+    pub objectives: Vec<ObjectiveStatus>,
+}
+
+fn status_at(route: &str, tracker: &Tracker, minute: u64) -> RouteSlo {
+    let counts: Vec<(&'static str, Counts)> =
+        WINDOWS.iter().map(|(name, minutes)| (*name, tracker.window_at(minute, *minutes))).collect();
+    let objectives = tracker
+        .slo
+        .objectives()
+        .map(|(objective, target)| {
+            let allowed = 1.0 - target;
+            let windows: Vec<WindowStatus> = counts
+                .iter()
+                .map(|(window, c)| {
+                    let bad = if objective == "latency" { c.slow } else { c.errors };
+                    let bad_ratio = if c.total == 0 { 0.0 } else { bad as f64 / c.total as f64 };
+                    WindowStatus { window, total: c.total, bad, compliance: 1.0 - bad_ratio, burn_rate: bad_ratio / allowed }
+                })
+                .collect();
+            let longest = windows.last().map(|w| w.burn_rate).unwrap_or_default();
+            ObjectiveStatus { objective, target, error_budget_remaining: 1.0 - longest, windows }
+        })
+        .collect();
+    RouteSlo { route: route.to_string(), slo: tracker.slo.clone(), objectives }
+}
+
+/// 所有已产生流量的 SLO 路由的达标情况
+pub fn snapshot() -> Vec<RouteSlo> {
+    let minute = current_minute();
+    let mut routes: Vec<RouteSlo> = TRACKERS.iter().map(|entry| status_at(entry.key(), entry.value(), minute)).collect();
+    routes.sort_by(|a, b| a.route.cmp(&b.route));
+    routes
+}
+
+/// 抓取 /metrics 前刷新达标率与消耗速度指标
+pub fn refresh_gauges() {
+    for route in snapshot() {
+        for objective in &route.objectives {
+            for window in &objective.windows {
+                let labels = [route.route.as_str(), objective.objective, window.window];
+                SLO_COMPLIANCE.with_label_values(&labels).set(window.compliance);
+                SLO_BURN_RATE.with_label_values(&labels).set(window.burn_rate);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burn_rate_windows() {
+        let slo = Slo { availability: Some(0.99), latency_ms: Some(300), latency_target: Some(0.9) };
+        let tracker = Tracker::new(slo);
+        // 30 分钟前：100 个正常请求；当前分钟：10 个请求，其中 2 个 5xx、5 个超过延迟阈值
+        for _ in 0..100 {
+            tracker.record_at(1000 - 30, 200, Duration::from_millis(10));
+        }
+        for i in 0..10 {
+            let status = if i < 2 { 503 } else { 200 };
+            let elapsed = Duration::from_millis(if i < 5 { 500 } else { 10 });
+            tracker.record_at(1000, status, elapsed);
+        }
+
+        let status = status_at("orders", &tracker, 1000);
+        let availability = &status.objectives[0];
+        assert_eq!(availability.objective, "availability");
+        let five_min = &availability.windows[0];
+        assert_eq!((five_min.total, five_min.bad), (10, 2));
+        assert!((five_min.burn_rate - 20.0).abs() < 1e-9);
+        let hour = &availability.windows[1];
+        assert_eq!((hour.total, hour.bad), (110, 2));
+
+        let latency = &status.objectives[1];
+        assert!((latency.windows[0].compliance - 0.5).abs() < 1e-9);
+
+        // 桶过期后不再计入
+        assert_eq!(tracker.window_at(1000 + 400, 360), Counts::default());
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(Slo { availability: Some(0.999), ..Default::default() }.validation_errors().is_empty());
+        assert_eq!(Slo::default().validation_errors().len(), 1);
+        let bad = Slo { availability: Some(1.0), latency_ms: Some(300), latency_target: None };
+        assert_eq!(bad.validation_errors().len(), 2);
+    }
+}
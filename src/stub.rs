@@ -0,0 +1,253 @@
+//! 可配置的桩上游：固定延迟、按比例返回错误，并回显收到的请求（含请求头）。
+//! 供 stub_upstream 可执行文件与集成测试在进程内启动假后端，需启用 stub 特性
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json, Router,
+};
+use hyper::body::Incoming;
+use hyper_util::rt::TokioIo;
+use rand::Rng;
+use serde_json::{json, Map, Value};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tower::ServiceExt;
+
+/// 桩上游的行为配置
+#[derive(Debug, Clone)]
+pub struct StubUpstream {
+    name: String,
+    latency: Duration,
+    error_rate: f64,
+    error_status: StatusCode,
+    echo_headers: bool,
+    header_names: bool,
+}
+
+struct StubState {
+    options: StubUpstream,
+    requests: AtomicU64,
+}
+
+impl StubUpstream {
+    /// name 出现在每个响应的 service 字段与 X-Stub-Service 头中，用于区分负载均衡到的实例
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            latency: Duration::ZERO,
+            error_rate: 0.0,
+            error_status: StatusCode::INTERNAL_SERVER_ERROR,
+            echo_headers: true,
+            header_names: false,
+        }
+    }
+
+    /// 每个请求响应前固定等待的时间
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// 返回错误的比例（0~1），错误状态码见 error_status
+    pub fn error_rate(mut self, rate: f64) -> Self {
+        self.error_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// 错误响应的状态码，默认 500
+    pub fn error_status(mut self, status: StatusCode) -> Self {
+        self.error_status = status;
+        self
+    }
+
+    /// 是否在响应体中回显请求头，默认开启
+    pub fn echo_headers(mut self, echo: bool) -> Self {
+        self.echo_headers = echo;
+        self
+    }
+
+    /// 是否在响应体的 header_names 中按收到的顺序回显请求头的原始写法（大小写），默认关闭；
+    /// 开启后每个连接只处理一个请求
+    pub fn header_names(mut self, echo: bool) -> Self {
+        self.header_names = echo;
+        self
+    }
+
+    /// 在 127.0.0.1 的随机端口上启动，句柄释放时停止
+    pub async fn spawn(self) -> std::io::Result<StubHandle> {
+        self.spawn_on(TcpListener::bind("127.0.0.1:0").await?)
+    }
+
+    /// 在给定的监听器上启动
+    pub fn spawn_on(self, listener: TcpListener) -> std::io::Result<StubHandle> {
+        let addr = listener.local_addr()?;
+        let header_names = self.header_names;
+        let state = Arc::new(StubState { options: self, requests: AtomicU64::new(0) });
+        let app = Router::new().fallback(handle).with_state(state.clone());
+        let task = tokio::spawn(async move {
+            if header_names {
+                serve_with_header_names(listener, app).await;
+            } else if let Err(err) = axum::serve(listener, app).await {
+                tracing::error!("桩上游异常退出: {}", err);
+            }
+        });
+        Ok(StubHandle { addr, state, task })
+    }
+}
+
+/// 运行中的桩上游
+pub struct StubHandle {
+    addr: SocketAddr,
+    state: Arc<StubState>,
+    task: JoinHandle<()>,
+}
+
+impl StubHandle {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 可直接写入路由 upstream 的地址，如 http://127.0.0.1:40123
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// 已收到的请求数
+    pub fn requests(&self) -> u64 {
+        self.state.requests.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for StubHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+// 请求头的原始写法，由 serve_with_header_names 在 hyper 解析前从连接中读出
+#[derive(Clone)]
+struct HeaderNames(Vec<String>);
+
+// HeaderMap 只保存小写的请求头名称：先窥视（不消费）连接上的请求头字节，再交给 hyper 处理，
+// 关闭 keep-alive 使每个连接只有一个请求与窥视到的请求头对应
+async fn serve_with_header_names(listener: TcpListener, app: Router) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                tracing::warn!("桩上游接受连接失败: {}", err);
+                continue;
+            }
+        };
+        let app = app.clone();
+        tokio::spawn(async move {
+            let names = peek_header_names(&stream).await.unwrap_or_default();
+            let service = hyper::service::service_fn(move |mut req: hyper::Request<Incoming>| {
+                req.extensions_mut().insert(HeaderNames(names.clone()));
+                app.clone().oneshot(req.map(Body::new))
+            });
+            let conn = hyper::server::conn::http1::Builder::new()
+                .keep_alive(false)
+                .serve_connection(TokioIo::new(stream), service);
+            if let Err(err) = conn.await {
+                tracing::debug!("桩上游连接异常结束: {}", err);
+            }
+        });
+    }
+}
+
+async fn peek_header_names(stream: &TcpStream) -> std::io::Result<Vec<String>> {
+    let mut buf = vec![0u8; 16 * 1024];
+    loop {
+        let n = stream.peek(&mut buf).await?;
+        if let Some(end) = buf[..n].windows(4).position(|w| w == b"\r\n\r\n") {
+            let head = String::from_utf8_lossy(&buf[..end]);
+            return Ok(head.lines().skip(1).filter_map(|line| line.split_once(':')).map(|(name, _)| name.to_string()).collect());
+        }
+        // 连接已关闭或请求头超过缓冲区
+        if n == 0 || n == buf.len() {
+            return Ok(Vec::new());
+        }
+        // 请求头尚未收全，稍后再窥视
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+}
+
+async fn handle(State(state): State<Arc<StubState>>, header_names: Option<Extension<HeaderNames>>, req: Request) -> Response {
+    state.requests.fetch_add(1, Ordering::Relaxed);
+    let options = &state.options;
+    if !options.latency.is_zero() {
+        tokio::time::sleep(options.latency).await;
+    }
+    let status = if options.error_rate > 0.0 && rand::thread_rng().gen_bool(options.error_rate) {
+        options.error_status
+    } else {
+        StatusCode::OK
+    };
+
+    let mut body = json!({
+        "service": options.name,
+        "method": req.method().as_str(),
+        "path": req.uri().path(),
+        "query": req.uri().query(),
+    });
+    if options.echo_headers {
+        let mut headers = Map::new();
+        for (name, value) in req.headers() {
+            let value = Value::String(String::from_utf8_lossy(value.as_bytes()).into_owned());
+            match headers.get_mut(name.as_str()) {
+                Some(Value::Array(values)) => values.push(value),
+                Some(existing) => *existing = Value::Array(vec![existing.take(), value]),
+                None => {
+                    headers.insert(name.to_string(), value);
+                }
+            }
+        }
+        body["headers"] = Value::Object(headers);
+    }
+    if let Some(Extension(HeaderNames(names))) = header_names {
+        body["header_names"] = json!(names);
+    }
+    (status, [("x-stub-service", options.name.clone())], Json(body)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_echo_and_errors() {
+        let stub = StubUpstream::new("users").spawn().await.unwrap();
+        let resp = reqwest::Client::new()
+            .get(format!("{}/user/1?x=1", stub.url()))
+            .header("x-user-id", "42")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers()["x-stub-service"], "users");
+        let body: Value = resp.json().await.unwrap();
+        assert_eq!(body["path"], "/user/1");
+        assert_eq!(body["query"], "x=1");
+        assert_eq!(body["headers"]["x-user-id"], "42");
+        assert_eq!(stub.requests(), 1);
+
+        let failing = StubUpstream::new("flaky")
+            .error_rate(1.0)
+            .error_status(StatusCode::SERVICE_UNAVAILABLE)
+            .echo_headers(false)
+            .spawn()
+            .await
+            .unwrap();
+        let resp = reqwest::get(failing.url()).await.unwrap();
+        assert_eq!(resp.status(), 503);
+        let body: Value = resp.json().await.unwrap();
+        assert!(body.get("headers").is_none());
+    }
+}
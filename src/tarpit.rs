@@ -0,0 +1,109 @@
+use axum::{
+    body::Body,
+    http::{Response, StatusCode},
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::metrics::{GaugeGuard, TARPIT_HITS, TARPIT_IN_FLIGHT};
+use crate::problem::{Problem, ProblemType};
+
+const DEFAULT_DELAY_MS: u64 = 10_000;
+const DEFAULT_MAX_CONCURRENT: usize = 256;
+
+// 所有蜜罐路由中正在拖延的请求数
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+/// 蜜罐路由：命中的请求先拖延一段时间再拒绝，消耗扫描器的时间；不转发到任何上游
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tarpit {
+    // 拒绝前的延迟，默认 10000ms；另加 0~jitter_ms 的随机抖动，避免被识别出固定延迟
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jitter_ms: Option<u64>,
+    // 最终返回的状态码：404（默认）或 403
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    // 同时拖延的请求上限（按所有蜜罐路由合计），超出后立即拒绝，避免占满连接
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent: Option<usize>,
+}
+
+// 作用域内的拖延计数
+struct Slot;
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        IN_FLIGHT.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Tarpit {
+    fn delay(&self) -> Duration {
+        let jitter = match self.jitter_ms.unwrap_or(0) {
+            0 => 0,
+            max => rand::random::<u64>() % (max + 1),
+        };
+        Duration::from_millis(self.delay_ms.unwrap_or(DEFAULT_DELAY_MS) + jitter)
+    }
+
+    fn reject(&self) -> Response<Body> {
+        match self.status {
+            Some(403) => Problem::new(StatusCode::FORBIDDEN, ProblemType::Forbidden).into_response(),
+            _ => Problem::new(StatusCode::NOT_FOUND, ProblemType::NotFound).into_response(),
+        }
+    }
+
+    /// 拖延后拒绝；拖延中的请求已达上限时立即拒绝
+    pub async fn serve(&self, route: &str) -> Response<Body> {
+        let max = self.max_concurrent.unwrap_or(DEFAULT_MAX_CONCURRENT);
+        if IN_FLIGHT.fetch_add(1, Ordering::Relaxed) >= max {
+            IN_FLIGHT.fetch_sub(1, Ordering::Relaxed);
+            TARPIT_HITS.with_label_values(&[route, "overflow"]).inc();
+            return self.reject();
+        }
+        let _slot = Slot;
+        let _gauge = GaugeGuard::new(&TARPIT_IN_FLIGHT);
+        TARPIT_HITS.with_label_values(&[route, "delayed"]).inc();
+        tokio::time::sleep(self.delay()).await;
+        self.reject()
+    }
+
+    pub fn validation_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if let Some(status) = self.status
+            && !matches!(status, 403 | 404)
+        {
+            errors.push(format!("tarpit.status必须是403或404: {}", status));
+        }
+        if self.max_concurrent == Some(0) {
+            errors.push("tarpit.max_concurrent必须大于0".to_string());
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_delay_then_reject() {
+        let tarpit = Tarpit { delay_ms: Some(50), status: Some(403), max_concurrent: Some(1), ..Default::default() };
+        let start = std::time::Instant::now();
+        let (slow, fast) = tokio::join!(tarpit.serve("tarpit-test"), async {
+            tokio::task::yield_now().await;
+            tarpit.serve("tarpit-test").await
+        });
+        assert_eq!(slow.status(), StatusCode::FORBIDDEN);
+        assert_eq!(fast.status(), StatusCode::FORBIDDEN);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert_eq!(TARPIT_HITS.with_label_values(&["tarpit-test", "overflow"]).get(), 1);
+
+        assert_eq!(Tarpit { status: Some(500), max_concurrent: Some(0), ..Default::default() }.validation_errors().len(), 2);
+    }
+}
@@ -0,0 +1,121 @@
+//! 租户上游覆盖：路由的 tenant_upstreams 把指定租户（JWT 的 tenant_id）固定到专属的上游组，
+//! 在负载均衡之前查询，用于把大客户隔离到独立的后端池，或在故障期间单独迁移某个租户。
+//!
+//! 管理端可在运行时按（路由, 租户）覆盖配置：运行时的设置优先于配置文件，设置为空列表表示回到路由的默认上游，
+//! 删除运行时设置后恢复配置文件中的值。运行时设置只保存在本副本的内存中，重启后失效。
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+use crate::config::RouteRule;
+
+// (路由标识, 租户) -> 上游组；空列表表示不覆盖
+static RUNTIME: Lazy<DashMap<(String, String), Vec<String>>> = Lazy::new(DashMap::new);
+
+/// 租户在该路由上应使用的上游组；没有覆盖时为 None
+pub fn upstreams(rule: &RouteRule, route: &str, tenant: &str) -> Option<Vec<String>> {
+    if tenant.is_empty() {
+        return None;
+    }
+    let pool = match RUNTIME.get(&(route.to_string(), tenant.to_string())) {
+        Some(runtime) => runtime.clone(),
+        None => rule.tenant_upstreams.get(tenant)?.clone(),
+    };
+    (!pool.is_empty()).then_some(pool)
+}
+
+/// 设置运行时覆盖（Some，空列表表示回到默认上游）或删除运行时覆盖（None），返回此前的运行时设置
+pub fn set(route: &str, tenant: &str, upstreams: Option<Vec<String>>) -> Option<Vec<String>> {
+    let key = (route.to_string(), tenant.to_string());
+    match upstreams {
+        Some(upstreams) => RUNTIME.insert(key, upstreams),
+        None => RUNTIME.remove(&key).map(|(_, previous)| previous),
+    }
+}
+
+/// 生效中的一条覆盖
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Override {
+    pub route: String,
+    pub tenant: String,
+    // 空列表表示该租户被运行时设置回默认上游
+    pub upstream: Vec<String>,
+    // config 或 runtime
+    pub source: &'static str,
+}
+
+/// 给定路由上生效的全部覆盖（运行时设置优先），按路由与租户排序
+pub fn list(rules: &[RouteRule]) -> Vec<Override> {
+    let mut overrides = Vec::new();
+    for rule in rules {
+        let route = crate::proxy::route_label(rule);
+        for (tenant, upstream) in &rule.tenant_upstreams {
+            if !RUNTIME.contains_key(&(route.clone(), tenant.clone())) {
+                overrides.push(Override { route: route.clone(), tenant: tenant.clone(), upstream: upstream.clone(), source: "config" });
+            }
+        }
+        for entry in RUNTIME.iter().filter(|e| e.key().0 == route) {
+            let (route, tenant) = entry.key().clone();
+            overrides.push(Override { route, tenant, upstream: entry.value().clone(), source: "runtime" });
+        }
+    }
+    overrides.sort_by(|a, b| (&a.route, &a.tenant).cmp(&(&b.route, &b.tenant)));
+    overrides
+}
+
+pub fn validation_errors(rule: &RouteRule) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (tenant, upstreams) in &rule.tenant_upstreams {
+        if tenant.trim().is_empty() {
+            errors.push("tenant_upstreams的租户不能为空".to_string());
+        }
+        if upstreams.is_empty() {
+            errors.push(format!("tenant_upstreams.{}不能为空", tenant));
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_config_and_runtime_overrides() {
+        let rule = RouteRule {
+            id: Some("tenant-test".to_string()),
+            upstream: vec!["http://shared:8080".to_string()],
+            tenant_upstreams: BTreeMap::from([("acme".to_string(), vec!["http://acme-pool:8080".to_string()])]),
+            ..Default::default()
+        };
+        let route = "tenant-test";
+        assert_eq!(upstreams(&rule, route, "acme"), Some(vec!["http://acme-pool:8080".to_string()]));
+        assert_eq!(upstreams(&rule, route, "other"), None);
+        assert_eq!(upstreams(&rule, route, ""), None);
+
+        // 运行时迁移 acme、固定 globex；空列表让 acme 回到默认上游
+        set(route, "globex", Some(vec!["http://globex:8080".to_string()]));
+        assert_eq!(upstreams(&rule, route, "globex"), Some(vec!["http://globex:8080".to_string()]));
+        set(route, "acme", Some(Vec::new()));
+        assert_eq!(upstreams(&rule, route, "acme"), None);
+        let listed = list(std::slice::from_ref(&rule));
+        assert_eq!(listed.len(), 2);
+        assert_eq!((listed[0].tenant.as_str(), listed[0].source, listed[0].upstream.len()), ("acme", "runtime", 0));
+        assert_eq!((listed[1].tenant.as_str(), listed[1].source), ("globex", "runtime"));
+
+        // 删除运行时设置后恢复配置
+        assert_eq!(set(route, "acme", None), Some(Vec::new()));
+        assert_eq!(upstreams(&rule, route, "acme"), Some(vec!["http://acme-pool:8080".to_string()]));
+        assert_eq!(list(std::slice::from_ref(&rule))[0].source, "config");
+    }
+
+    #[test]
+    fn test_validation() {
+        let rule = RouteRule {
+            tenant_upstreams: BTreeMap::from([(" ".to_string(), vec!["http://a".to_string()]), ("acme".to_string(), Vec::new())]),
+            ..Default::default()
+        };
+        assert_eq!(validation_errors(&rule).len(), 2);
+    }
+}
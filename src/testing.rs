@@ -0,0 +1,927 @@
+//! 端到端测试工具：在随机端口上以内存配置启动完整网关，配合 stub 模块的桩上游与签名 JWT，
+//! 用真实 HTTP 请求覆盖路由、鉴权、限流与重试等功能。需启用 stub 特性
+use anyhow::Context;
+use jsonwebtoken::{encode, EncodingKey, Header};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+use crate::config::{RouteRule, Settings};
+use crate::gateway::GatewayBuilder;
+
+/// 测试网关使用的 JWT 密钥
+pub const TEST_JWT_SECRET: &str = "helios-test-secret";
+
+/// 测试用的基础设置，extra 中的键覆盖默认值（如 `json!({ "client_qps": 2 })`）
+pub fn settings(extra: Value) -> Settings {
+    let mut value = json!({
+        "gateway_bind": "127.0.0.1:0",
+        "jwt_decoding_key": TEST_JWT_SECRET,
+        "global_qps": 10000,
+        "client_qps": 10000,
+    });
+    if let (Some(base), Some(extra)) = (value.as_object_mut(), extra.as_object()) {
+        base.extend(extra.clone());
+    }
+    serde_json::from_value(value).expect("测试设置无效")
+}
+
+/// 用 HS256 签发 JWT；claims 未包含 exp 时设为一小时后过期
+pub fn jwt(secret: &str, mut claims: Value) -> String {
+    if let Some(claims) = claims.as_object_mut() {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        claims.entry("exp").or_insert(json!(now + 3600));
+    }
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_bytes())).expect("JWT 签发失败")
+}
+
+/// 运行中的测试网关，释放时停止
+pub struct TestGateway {
+    addr: SocketAddr,
+    secret: String,
+    task: JoinHandle<()>,
+}
+
+impl TestGateway {
+    /// 以默认测试设置启动
+    pub async fn start(routes: Vec<RouteRule>) -> anyhow::Result<Self> {
+        Self::start_with(settings(json!({})), routes).await
+    }
+
+    /// 以给定设置启动；监听 127.0.0.1 的随机端口，忽略 gateway_bind
+    pub async fn start_with(settings: Settings, routes: Vec<RouteRule>) -> anyhow::Result<Self> {
+        let secret = settings.jwt_decoding_key.clone();
+        // 与 Gateway::serve 一致：有路由需要保留请求头大小写时改用可记录原始写法的连接处理
+        let preserve_header_case = crate::header_case::enabled(&routes);
+        let gateway = GatewayBuilder::new(settings).routes(routes).build().context("测试网关配置无效")?;
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let app = gateway.app;
+        let task = tokio::spawn(async move {
+            if preserve_header_case {
+                let _ = crate::header_case::serve(listener, app, false).await;
+            } else {
+                let _ = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await;
+            }
+        });
+        Ok(Self { addr, secret, task })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 网关上某个路径的完整地址，如 url("/api/users")
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// 用网关的 jwt_decoding_key 签发的令牌
+    pub fn token(&self, sub: &str, tenant_id: &str) -> String {
+        jwt(&self.secret, json!({ "sub": sub, "tenant_id": tenant_id }))
+    }
+
+    /// 以指定身份发送 GET 请求
+    pub async fn get_as(&self, path: &str, sub: &str, tenant_id: &str) -> reqwest::Result<reqwest::Response> {
+        reqwest::Client::new().get(self.url(path)).bearer_auth(self.token(sub, tenant_id)).send().await
+    }
+}
+
+impl Drop for TestGateway {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub::StubUpstream;
+
+    fn route(prefix: &str, upstreams: Vec<String>) -> RouteRule {
+        RouteRule { prefix: vec![prefix.to_string()], upstream: upstreams, ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn test_routing_and_auth() {
+        let users = StubUpstream::new("users").spawn().await.unwrap();
+        let mut rule = route("/e2e-users/**", vec![users.url()]);
+        rule.whitelist = Some(vec!["/e2e-users/public".to_string()]);
+        let gateway = TestGateway::start(vec![rule]).await.unwrap();
+
+        let resp = reqwest::get(gateway.url("/e2e-users/1")).await.unwrap();
+        assert_eq!(resp.status(), 401);
+        let resp = reqwest::get(gateway.url("/e2e-users/public")).await.unwrap();
+        assert_eq!(resp.status(), 200);
+
+        let resp = gateway.get_as("/e2e-users/1?x=1", "u-1", "acme").await.unwrap();
+        assert_eq!(resp.status(), 200);
+        let body: Value = resp.json().await.unwrap();
+        assert_eq!(body["service"], "users");
+        assert_eq!(body["query"], "x=1");
+        // 身份信息透传给上游
+        assert_eq!(body["headers"]["uid"], "u-1");
+        assert_eq!(body["headers"]["tenant_id"], "acme");
+
+        let forged = jwt("wrong-secret", json!({ "sub": "u-1", "tenant_id": "acme" }));
+        let resp = reqwest::Client::new().get(gateway.url("/e2e-users/1")).bearer_auth(forged).send().await.unwrap();
+        assert_eq!(resp.status(), 401);
+        assert_eq!(users.requests(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_client_rate_limit() {
+        let stub = StubUpstream::new("limited").spawn().await.unwrap();
+        let gateway = TestGateway::start_with(settings(json!({ "client_qps": 2 })), vec![route("/e2e-limited/**", vec![stub.url()])])
+            .await
+            .unwrap();
+        let mut statuses = Vec::new();
+        for _ in 0..4 {
+            statuses.push(gateway.get_as("/e2e-limited/x", "u-1", "acme").await.unwrap().status().as_u16());
+        }
+        assert_eq!(&statuses[..2], &[200, 200]);
+        assert!(statuses[2..].contains(&429));
+    }
+
+    #[tokio::test]
+    async fn test_retry_on_dead_upstream() {
+        let stub = StubUpstream::new("alive").spawn().await.unwrap();
+        // 取一个空闲端口后立即释放，作为不可达的上游
+        let dead = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let rule = route("/e2e-retry/**", vec![format!("http://{}", dead), stub.url()]);
+        let gateway = TestGateway::start_with(settings(json!({ "upstream_retries": 1 })), vec![rule]).await.unwrap();
+        for _ in 0..4 {
+            assert_eq!(gateway.get_as("/e2e-retry/x", "u-1", "acme").await.unwrap().status(), 200);
+        }
+        assert_eq!(stub.requests(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_hedging_cancels_slow_upstream() {
+        let slow = StubUpstream::new("slow").latency(std::time::Duration::from_secs(3)).spawn().await.unwrap();
+        let fast = StubUpstream::new("fast").spawn().await.unwrap();
+        let mut rule = route("/e2e-hedge/**", vec![slow.url(), fast.url()]);
+        rule.hedge_after_ms = Some(50);
+        let gateway = TestGateway::start(vec![rule]).await.unwrap();
+        let started = std::time::Instant::now();
+        for _ in 0..4 {
+            let body: Value = gateway.get_as("/e2e-hedge/x", "u-1", "acme").await.unwrap().json().await.unwrap();
+            assert_eq!(body["service"], "fast");
+        }
+        assert!(started.elapsed() < std::time::Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_first_byte_timeout() {
+        let slow = StubUpstream::new("slow").latency(std::time::Duration::from_millis(1500)).spawn().await.unwrap();
+        let mut strict = route("/e2e-ttfb-strict/**", vec![slow.url()]);
+        strict.first_byte_timeout_ms = Some(200);
+        // 配置分阶段超时后 request_timeout_secs 不再限制总时长
+        let mut relaxed = route("/e2e-ttfb-relaxed/**", vec![slow.url()]);
+        relaxed.first_byte_timeout_ms = Some(3000);
+        let gateway = TestGateway::start_with(settings(json!({ "request_timeout_secs": 1 })), vec![strict, relaxed]).await.unwrap();
+
+        let resp = gateway.get_as("/e2e-ttfb-strict/x", "u-1", "acme").await.unwrap();
+        assert_eq!(resp.status(), 504);
+        let resp = gateway.get_as("/e2e-ttfb-relaxed/x", "u-1", "acme").await.unwrap();
+        assert_eq!(resp.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_namespace_admin_token() {
+        let mut orders = route("/e2e-ns-orders/**", vec!["http://orders.internal:8080".to_string()]);
+        orders.namespace = Some("orders".to_string());
+        let mut users = route("/e2e-ns-users/**", vec!["http://users.internal:8080".to_string()]);
+        users.namespace = Some("users".to_string());
+        let settings = settings(json!({ "admin_token": "root-token", "admin_namespace_tokens": { "orders": "orders-token" } }));
+        let gateway = TestGateway::start_with(settings, vec![orders, users]).await.unwrap();
+        let client = reqwest::Client::new();
+
+        let dump: Value = client.get(gateway.url("/admin/api/config")).bearer_auth("orders-token").send().await.unwrap().json().await.unwrap();
+        let routes = dump["routes"].as_array().unwrap();
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0]["namespace"], "orders");
+        assert!(dump["settings"].is_null());
+
+        // 其他命名空间的上游与不在范围内的端点
+        let resp = client
+            .post(gateway.url("/admin/api/upstreams/drain"))
+            .bearer_auth("orders-token")
+            .json(&json!({ "upstream": "http://users.internal:8080" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 404);
+        let resp = client.get(gateway.url("/admin/api/audit")).bearer_auth("orders-token").send().await.unwrap();
+        assert_eq!(resp.status(), 403);
+
+        let dump: Value = client.get(gateway.url("/admin/api/config")).bearer_auth("root-token").send().await.unwrap().json().await.unwrap();
+        assert_eq!(dump["routes"].as_array().unwrap().iter().filter(|r| r["namespace"].is_string()).count(), 2);
+        assert_eq!(dump["settings"]["admin_namespace_tokens"]["orders"], crate::config::REDACTED);
+    }
+
+    #[tokio::test]
+    async fn test_route_plugins() {
+        let stub = StubUpstream::new("plugins").spawn().await.unwrap();
+        let mut rule = route("/e2e-plugins/**", vec![stub.url()]);
+        rule.plugins = Some(
+            serde_json::from_value(json!([
+                { "name": "headers", "config": { "request_set": { "x-team": "orders" }, "response_set": { "x-plugin": "headers" } } },
+            ]))
+            .unwrap(),
+        );
+        let mut mock = route("/e2e-plugins-mock/**", vec![stub.url()]);
+        mock.plugins = Some(serde_json::from_value(json!([{ "name": "mock", "config": { "status": 503, "body": "maintenance" } }])).unwrap());
+        let gateway = TestGateway::start(vec![rule, mock]).await.unwrap();
+
+        let resp = gateway.get_as("/e2e-plugins/x", "u-1", "acme").await.unwrap();
+        assert_eq!(resp.headers()["x-plugin"], "headers");
+        let body: Value = resp.json().await.unwrap();
+        assert_eq!(body["headers"]["x-team"], "orders");
+
+        let resp = gateway.get_as("/e2e-plugins-mock/x", "u-1", "acme").await.unwrap();
+        assert_eq!(resp.status(), 503);
+        assert_eq!(resp.text().await.unwrap(), "maintenance");
+        assert_eq!(stub.requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_route_capture() {
+        // 接收 OTLP 日志的收集端
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<Value>();
+        let collector = axum::Router::new().route(
+            "/v1/logs",
+            axum::routing::post(move |axum::Json(payload): axum::Json<Value>| async move {
+                let _ = tx.send(payload);
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let collector_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, collector).await });
+
+        let stub = StubUpstream::new("capture").spawn().await.unwrap();
+        let mut rule = route("/e2e-capture/**", vec![stub.url()]);
+        rule.capture = Some(serde_json::from_value(json!({ "max_body_bytes": 16 })).unwrap());
+        let settings = settings(json!({ "otel_logs_endpoint": collector_url, "otel_service_name": "edge" }));
+        let gateway = TestGateway::start_with(settings, vec![rule]).await.unwrap();
+
+        let trace_id = "4bf92f3577b34da6a3ce929d0e0e4736";
+        let resp = reqwest::Client::new()
+            .post(gateway.url("/e2e-capture/orders?debug=1"))
+            .bearer_auth(gateway.token("u-1", "acme"))
+            .header("traceparent", format!("00-{}-00f067aa0ba902b7-01", trace_id))
+            .body("{\"order\":\"a-very-long-order-id\"}")
+            .send()
+            .await
+            .unwrap();
+        let body: Value = resp.json().await.unwrap();
+        // 上游收到同一 trace 下网关的新 span
+        let forwarded = body["headers"]["traceparent"].as_str().unwrap();
+        assert!(forwarded.starts_with(&format!("00-{}-", trace_id)));
+        assert!(!forwarded.contains("00f067aa0ba902b7"));
+
+        let payload = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        let resource = &payload["resourceLogs"][0];
+        assert_eq!(resource["resource"]["attributes"][0]["value"]["stringValue"], "edge");
+        let record = &resource["scopeLogs"][0]["logRecords"][0];
+        assert_eq!(record["traceId"], trace_id);
+        assert!(forwarded.contains(record["spanId"].as_str().unwrap()));
+        let attr = |key: &str| record["attributes"].as_array().unwrap().iter().find(|a| a["key"] == key).unwrap()["value"].clone();
+        assert_eq!(attr("http.request.method")["stringValue"], "POST");
+        assert_eq!(attr("url.query")["stringValue"], "debug=1");
+        assert_eq!(attr("http.request.header.authorization")["stringValue"], "[REDACTED]");
+        assert_eq!(attr("http.request.body")["stringValue"], "{\"order\":\"a-very");
+        assert_eq!(attr("http.request.body.truncated")["boolValue"], true);
+        assert_eq!(attr("http.response.status_code")["intValue"], "200");
+        assert_eq!(attr("http.response.body.complete")["boolValue"], true);
+    }
+
+    #[tokio::test]
+    async fn test_route_methods() {
+        let stub = StubUpstream::new("methods").spawn().await.unwrap();
+        let mut rule = route("/e2e-methods/**", vec![stub.url()]);
+        rule.methods = Some(vec!["GET".to_string(), "POST".to_string()]);
+        let gateway = TestGateway::start(vec![rule]).await.unwrap();
+        let client = reqwest::Client::new();
+
+        // OPTIONS 与未允许的方法无需鉴权，也不转发
+        let resp = client.request(reqwest::Method::OPTIONS, gateway.url("/e2e-methods/x")).send().await.unwrap();
+        assert_eq!(resp.status(), 204);
+        assert_eq!(resp.headers()["allow"], "GET, POST, HEAD, OPTIONS");
+        let resp = client.delete(gateway.url("/e2e-methods/x")).send().await.unwrap();
+        assert_eq!(resp.status(), 405);
+        assert_eq!(resp.headers()["allow"], "GET, POST, HEAD, OPTIONS");
+        assert_eq!(stub.requests(), 0);
+
+        let resp = gateway.get_as("/e2e-methods/x", "u-1", "acme").await.unwrap();
+        assert_eq!(resp.status(), 200);
+        assert_eq!(stub.requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_head_requests() {
+        // 记录收到的方法：/missing 返回 404，其余返回 1000 字节
+        let (tx, mut methods) = tokio::sync::mpsc::unbounded_channel::<String>();
+        let upstream = axum::Router::new().fallback(move |req: axum::extract::Request| {
+            let tx = tx.clone();
+            async move {
+                let _ = tx.send(req.method().to_string());
+                if req.uri().path().ends_with("/missing") {
+                    return (axum::http::StatusCode::NOT_FOUND, [("x-origin", "yes")], "nope".to_string());
+                }
+                (axum::http::StatusCode::OK, [("x-origin", "yes")], "a".repeat(1000))
+            }
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, upstream).await });
+
+        let mut rule = route("/e2e-head/**", vec![upstream_url]);
+        rule.cache = Some(serde_json::from_value(json!({ "negative_ttl_secs": 60 })).unwrap());
+        rule.max_response_body_bytes = Some(100);
+        let gateway = TestGateway::start(vec![rule]).await.unwrap();
+        let token = gateway.token("u-1", "acme");
+        let head = |path: &str| reqwest::Client::new().head(gateway.url(path)).bearer_auth(&token).send();
+
+        // 以 HEAD 转发，响应头原样返回，不受响应体缓冲上限约束
+        let resp = head("/e2e-head/x").await.unwrap();
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers()["x-origin"], "yes");
+        assert_eq!(resp.headers()["content-length"], "1000");
+        assert_eq!(methods.recv().await.unwrap(), "HEAD");
+
+        // HEAD 命中 GET 写入的缓存，只返回响应头，不再访问上游
+        let resp = gateway.get_as("/e2e-head/missing", "u-1", "acme").await.unwrap();
+        assert_eq!(resp.status(), 404);
+        assert_eq!(methods.recv().await.unwrap(), "GET");
+        let resp = head("/e2e-head/missing").await.unwrap();
+        assert_eq!(resp.status(), 404);
+        assert_eq!(resp.headers()["x-cache"], "HIT");
+        assert_eq!(resp.headers()["content-length"], "4");
+        assert!(methods.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_server_timing() {
+        let stub = StubUpstream::new("timing").spawn().await.unwrap();
+        let mut rule = route("/e2e-timing/**", vec![stub.url().replace("127.0.0.1", "localhost")]);
+        rule.server_timing = Some(true);
+        let plain = route("/e2e-no-timing/**", vec![stub.url()]);
+        let gateway = TestGateway::start(vec![rule, plain]).await.unwrap();
+
+        // 首次请求新建连接：包含 dns 与 connect
+        let resp = gateway.get_as("/e2e-timing/x", "u-1", "acme").await.unwrap();
+        let timing = resp.headers()["server-timing"].to_str().unwrap().to_string();
+        let names: Vec<&str> = timing.split(", ").map(|m| m.split(';').next().unwrap()).collect();
+        assert_eq!(names, vec!["dns", "connect", "ttfb", "total"], "{}", timing);
+        assert!(timing.contains("desc=\"tcp\""));
+        resp.bytes().await.unwrap();
+
+        // 复用连接：只有 ttfb 与 total
+        let resp = gateway.get_as("/e2e-timing/x", "u-1", "acme").await.unwrap();
+        let timing = resp.headers()["server-timing"].to_str().unwrap();
+        assert!(timing.starts_with("ttfb;dur="), "{}", timing);
+
+        let resp = gateway.get_as("/e2e-no-timing/x", "u-1", "acme").await.unwrap();
+        assert!(resp.headers().get("server-timing").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tenant_upstreams() {
+        let shared = StubUpstream::new("shared").spawn().await.unwrap();
+        let dedicated = StubUpstream::new("dedicated").spawn().await.unwrap();
+        let mut rule = route("/e2e-tenant/**", vec![shared.url()]);
+        rule.id = Some("e2e-tenant".to_string());
+        rule.tenant_upstreams.insert("big".to_string(), vec![dedicated.url()]);
+        let settings = settings(json!({ "admin_token": "root-token" }));
+        let gateway = TestGateway::start_with(settings, vec![rule]).await.unwrap();
+        let service = |resp: reqwest::Response| async move { resp.json::<Value>().await.unwrap()["service"].clone() };
+
+        assert_eq!(service(gateway.get_as("/e2e-tenant/x", "u-1", "big").await.unwrap()).await, "dedicated");
+        assert_eq!(service(gateway.get_as("/e2e-tenant/x", "u-2", "small").await.unwrap()).await, "shared");
+
+        // 运行时把 small 迁到专属池，big 回到默认上游
+        let client = reqwest::Client::new();
+        for (tenant, upstream) in [("small", vec![dedicated.url()]), ("big", Vec::new())] {
+            let resp = client
+                .put(gateway.url("/admin/api/tenant-upstreams"))
+                .bearer_auth("root-token")
+                .json(&json!({ "route": "e2e-tenant", "tenant": tenant, "upstream": upstream }))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), 200);
+        }
+        assert_eq!(service(gateway.get_as("/e2e-tenant/x", "u-1", "big").await.unwrap()).await, "shared");
+        assert_eq!(service(gateway.get_as("/e2e-tenant/x", "u-2", "small").await.unwrap()).await, "dedicated");
+
+        // 删除运行时设置后恢复配置
+        let resp = client
+            .delete(gateway.url("/admin/api/tenant-upstreams"))
+            .bearer_auth("root-token")
+            .json(&json!({ "route": "e2e-tenant", "tenant": "big" }))
+            .send()
+            .await
+            .unwrap();
+        let listed: Value = resp.json().await.unwrap();
+        assert_eq!(listed[0]["tenant"], "big");
+        assert_eq!(listed[0]["source"], "config");
+        assert_eq!(service(gateway.get_as("/e2e-tenant/x", "u-1", "big").await.unwrap()).await, "dedicated");
+    }
+
+    #[tokio::test]
+    async fn test_mirror_compare() {
+        let primary = StubUpstream::new("orders").spawn().await.unwrap();
+        let same = StubUpstream::new("orders").spawn().await.unwrap();
+        let rewrite = StubUpstream::new("orders-v2").spawn().await.unwrap();
+        let mirrored = |id: &str, shadow: String| RouteRule {
+            id: Some(id.to_string()),
+            mirror: Some(serde_json::from_value(json!({ "upstream": shadow, "compare": true, "ignore_fields": ["/headers"] })).unwrap()),
+            ..route(&format!("/{}/**", id), vec![primary.url()])
+        };
+        let routes = vec![mirrored("e2e-mirror-same", same.url()), mirrored("e2e-mirror-v2", rewrite.url())];
+        let gateway = TestGateway::start_with(settings(json!({ "admin_token": "root-token" })), routes).await.unwrap();
+
+        // 客户端只看到主上游的响应
+        let body: Value = gateway.get_as("/e2e-mirror-v2/1", "u-1", "acme").await.unwrap().json().await.unwrap();
+        assert_eq!(body["service"], "orders");
+        let body: Value = gateway.get_as("/e2e-mirror-same/1", "u-1", "acme").await.unwrap().json().await.unwrap();
+        assert_eq!(body["service"], "orders");
+
+        // 对比在后台完成
+        let client = reqwest::Client::new();
+        let diffs = |route: &str| {
+            let request = client.get(gateway.url(&format!("/admin/api/mirror-diffs?route={}", route))).bearer_auth("root-token");
+            async move { request.send().await.unwrap().json::<Value>().await.unwrap() }
+        };
+        let mut found = Value::Null;
+        for _ in 0..50 {
+            found = diffs("e2e-mirror-v2").await;
+            if found.as_array().is_some_and(|d| !d.is_empty()) {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(found[0]["path"], "/e2e-mirror-v2/1");
+        assert_eq!(found[0]["differences"], json!(["body/service: \"orders\" != \"orders-v2\""]));
+        assert_eq!((same.requests(), rewrite.requests()), (1, 1));
+        assert_eq!(diffs("e2e-mirror-same").await, json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_internal_redirect() {
+        use axum::response::IntoResponse;
+        // 鉴权后端：放行的请求以 X-Accel-Redirect 交给文件路由
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let authz_url = format!("http://{}", listener.local_addr().unwrap());
+        let app = axum::Router::new().fallback(|uri: axum::http::Uri| async move {
+            match uri.path().split_once("/allowed/").map(|(_, file)| file) {
+                Some(file) => ([("x-accel-redirect", format!("/e2e-files/{}", file))], "authorized").into_response(),
+                None if uri.path().ends_with("/escape") => ([("x-accel-redirect", "/e2e-files/../admin")], "authorized").into_response(),
+                None if uri.path().ends_with("/untidy") => ([("x-accel-redirect", "/e2e-files//./report.pdf")], "authorized").into_response(),
+                None => (axum::http::StatusCode::FORBIDDEN, "denied").into_response(),
+            }
+        });
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let files = StubUpstream::new("files").spawn().await.unwrap();
+
+        let mut authz = route("/e2e-downloads/**", vec![authz_url]);
+        authz.internal_redirect = Some(true);
+        let mut file_route = route("/e2e-files/**", vec![files.url()]);
+        file_route.internal = Some(true);
+        let gateway = TestGateway::start(vec![authz, file_route]).await.unwrap();
+
+        let resp = gateway.get_as("/e2e-downloads/allowed/report.pdf", "u-1", "acme").await.unwrap();
+        assert_eq!(resp.status(), 200);
+        assert!(resp.headers().get("x-accel-redirect").is_none());
+        let body: Value = resp.json().await.unwrap();
+        assert_eq!(body["service"], "files");
+        assert_eq!(body["path"], "/e2e-files/report.pdf");
+
+        let resp = gateway.get_as("/e2e-downloads/secret.pdf", "u-1", "acme").await.unwrap();
+        assert_eq!(resp.status(), 403);
+        // 文件路由不能直接访问
+        let resp = gateway.get_as("/e2e-files/report.pdf", "u-1", "acme").await.unwrap();
+        assert_eq!(resp.status(), 404);
+        assert_eq!(files.requests(), 1);
+
+        // 重定向目标同样经过路径检查与规范化
+        let resp = gateway.get_as("/e2e-downloads/escape", "u-1", "acme").await.unwrap();
+        assert_eq!(resp.status(), 400);
+        let resp = gateway.get_as("/e2e-downloads/untidy", "u-1", "acme").await.unwrap();
+        assert_eq!(resp.status(), 200);
+        let body: Value = resp.json().await.unwrap();
+        assert_eq!(body["path"], "/e2e-files/report.pdf");
+        assert_eq!(files.requests(), 2);
+
+        // 流量只按客户端请求的路由统计一次，重新分派的目标路由不再重复计数
+        let bytes = |route: &str| crate::metrics::ROUTE_BYTES.with_label_values(&[route, "download"]).get();
+        for _ in 0..50 {
+            if bytes("/e2e-downloads/**") > 0 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(bytes("/e2e-downloads/**") > 0);
+        assert_eq!(bytes("/e2e-files/**"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_route_auth_modes() {
+        let stub = StubUpstream::new("modes").spawn().await.unwrap();
+        let mut machine = route("/e2e-auth-key/**", vec![stub.url()]);
+        machine.auth = Some("api_key".to_string());
+        let mut public = route("/e2e-auth-none/**", vec![stub.url()]);
+        public.auth = Some("none".to_string());
+        let mut users = route("/e2e-auth-jwt/**", vec![stub.url()]);
+        users.auth = Some("jwt".to_string());
+        let settings = settings(json!({ "api_keys": [{ "key": "ak-1", "sub": "billing-sync", "tenant_id": "acme" }] }));
+        let gateway = TestGateway::start_with(settings, vec![machine, public, users]).await.unwrap();
+        let client = reqwest::Client::new();
+
+        let resp = client.get(gateway.url("/e2e-auth-key/x")).header("x-api-key", "ak-1").send().await.unwrap();
+        assert_eq!(resp.status(), 200);
+        let body: Value = resp.json().await.unwrap();
+        assert_eq!(body["headers"]["uid"], "billing-sync");
+        assert_eq!(body["headers"]["tenant_id"], "acme");
+        // api_key 路由不接受 JWT
+        assert_eq!(gateway.get_as("/e2e-auth-key/x", "u-1", "acme").await.unwrap().status(), 401);
+        let resp = client.get(gateway.url("/e2e-auth-key/x")).header("x-api-key", "ak-2").send().await.unwrap();
+        assert_eq!(resp.status(), 401);
+
+        // none 路由不鉴权，客户端伪造的身份头被去掉
+        let resp = client.get(gateway.url("/e2e-auth-none/x")).header("uid", "admin").send().await.unwrap();
+        assert_eq!(resp.status(), 200);
+        let body: Value = resp.json().await.unwrap();
+        assert!(body["headers"]["uid"].is_null());
+
+        assert_eq!(reqwest::get(gateway.url("/e2e-auth-jwt/x")).await.unwrap().status(), 401);
+        assert_eq!(gateway.get_as("/e2e-auth-jwt/x", "u-1", "acme").await.unwrap().status(), 200);
+        assert_eq!(stub.requests(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_sendfile() {
+        use axum::response::IntoResponse;
+        let root = std::env::temp_dir().join(format!("helios-sendfile-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("reports")).unwrap();
+        std::fs::write(root.join("reports/q1.csv"), "id,total\n1,42\n").unwrap();
+        // 应用后端只做鉴权，文件内容由网关发送
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let app_url = format!("http://{}", listener.local_addr().unwrap());
+        let app = axum::Router::new().fallback(|uri: axum::http::Uri| async move {
+            match uri.path().strip_prefix("/e2e-sendfile/") {
+                Some("public") => "no file".into_response(),
+                Some(file) => (
+                    [("x-sendfile", format!("/{}", file)), ("content-disposition", "attachment".to_string())],
+                    "should not reach the client",
+                )
+                    .into_response(),
+                None => axum::http::StatusCode::NOT_FOUND.into_response(),
+            }
+        });
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let mut rule = route("/e2e-sendfile/**", vec![app_url]);
+        rule.sendfile = Some(crate::sendfile::SendfilePolicy { root: Some(root.display().to_string()), s3: None });
+        let gateway = TestGateway::start(vec![rule]).await.unwrap();
+
+        let resp = gateway.get_as("/e2e-sendfile/reports/q1.csv", "u-1", "acme").await.unwrap();
+        assert_eq!(resp.status(), 200);
+        assert!(resp.headers().get("x-sendfile").is_none());
+        assert_eq!(resp.headers()["content-disposition"], "attachment");
+        assert_eq!(resp.text().await.unwrap(), "id,total\n1,42\n");
+
+        let resp = reqwest::Client::new()
+            .get(gateway.url("/e2e-sendfile/reports/q1.csv"))
+            .bearer_auth(gateway.token("u-1", "acme"))
+            .header("range", "bytes=9-")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 206);
+        assert_eq!(resp.headers()["content-range"], "bytes 9-13/14");
+        assert_eq!(resp.text().await.unwrap(), "1,42\n");
+
+        assert_eq!(gateway.get_as("/e2e-sendfile/reports/missing.csv", "u-1", "acme").await.unwrap().status(), 404);
+        // 没有下发头的响应原样返回
+        assert_eq!(gateway.get_as("/e2e-sendfile/public", "u-1", "acme").await.unwrap().text().await.unwrap(), "no file");
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_whitelist_limits() {
+        let slow = StubUpstream::new("public").latency(std::time::Duration::from_millis(500)).spawn().await.unwrap();
+        let mut rule = route("/e2e-public/**", vec![slow.url()]);
+        rule.whitelist = Some(vec!["/e2e-public/open".to_string()]);
+        rule.whitelist_limits = Some(crate::whitelist::WhitelistLimits {
+            timeout_ms: Some(200),
+            max_request_body_bytes: Some(16),
+            client_qps: Some(2),
+        });
+        let gateway = TestGateway::start(vec![rule]).await.unwrap();
+        let client = reqwest::Client::new();
+
+        // 免鉴权的请求：超时更短、请求体更小、按客户端限流
+        let resp = client.post(gateway.url("/e2e-public/open")).body("x".repeat(64)).send().await.unwrap();
+        assert_eq!(resp.status(), 413);
+        let resp = client.get(gateway.url("/e2e-public/open")).send().await.unwrap();
+        assert_eq!(resp.status(), 504);
+        let resp = client.get(gateway.url("/e2e-public/open")).send().await.unwrap();
+        assert_eq!(resp.status(), 429);
+        assert_eq!(resp.headers()["x-ratelimit-scope"], "whitelist");
+
+        // 同一路由上需要鉴权的路径不受影响
+        for _ in 0..3 {
+            let resp = client
+                .post(gateway.url("/e2e-public/orders"))
+                .bearer_auth(gateway.token("u-1", "acme"))
+                .body("x".repeat(64))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), 200);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff() {
+        use axum::http::StatusCode;
+        let failing = StubUpstream::new("failing").error_rate(1.0).error_status(StatusCode::SERVICE_UNAVAILABLE).spawn().await.unwrap();
+        let healthy = StubUpstream::new("healthy").spawn().await.unwrap();
+        let policy: crate::retry::RetryPolicy = serde_json::from_value(json!({ "max_attempts": 3, "backoff_ms": 10 })).unwrap();
+        let mut mixed = route("/e2e-retry-mixed/**", vec![failing.url(), healthy.url()]);
+        mixed.retry = Some(policy.clone());
+        let mut down = route("/e2e-retry-down/**", vec![failing.url()]);
+        down.retry = Some(policy);
+        let gateway = TestGateway::start(vec![mixed, down]).await.unwrap();
+
+        // 503 换到另一个节点重试
+        for _ in 0..4 {
+            assert_eq!(gateway.get_as("/e2e-retry-mixed/x", "u-1", "acme").await.unwrap().status(), 200);
+        }
+        assert_eq!(healthy.requests(), 4);
+
+        // 节点都试过后仍按 max_attempts 重试，最终返回上游的 503
+        let before = failing.requests();
+        assert_eq!(gateway.get_as("/e2e-retry-down/x", "u-1", "acme").await.unwrap().status(), 503);
+        assert_eq!(failing.requests() - before, 3);
+        // 非幂等方法不重试
+        let resp = reqwest::Client::new()
+            .post(gateway.url("/e2e-retry-down/x"))
+            .bearer_auth(gateway.token("u-1", "acme"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 503);
+        assert_eq!(failing.requests() - before, 4);
+    }
+
+    #[tokio::test]
+    async fn test_retry_within_deadline() {
+        use axum::http::StatusCode;
+        let failing = StubUpstream::new("failing").error_rate(1.0).error_status(StatusCode::SERVICE_UNAVAILABLE).spawn().await.unwrap();
+        let policy = json!({ "max_attempts": 10, "backoff_ms": 300, "max_backoff_ms": 300 });
+        let mut rule = route("/e2e-retry-deadline/**", vec![failing.url()]);
+        rule.retry = Some(serde_json::from_value(policy).unwrap());
+        rule.total_timeout_ms = Some(400);
+        let gateway = TestGateway::start(vec![rule]).await.unwrap();
+
+        // 退避计入总时长：每次等待 150~300ms，400ms 内至多再重试两次，不会按 max_attempts 等满 9 次退避
+        let start = std::time::Instant::now();
+        assert_eq!(gateway.get_as("/e2e-retry-deadline/x", "u-1", "acme").await.unwrap().status(), 503);
+        assert!(start.elapsed() < std::time::Duration::from_secs(1), "{:?}", start.elapsed());
+        assert!((2..=3).contains(&failing.requests()), "{}", failing.requests());
+    }
+
+    #[tokio::test]
+    async fn test_shadow_auth() {
+        use crate::metrics::AUTH_SHADOW_COUNTER;
+        let stub = StubUpstream::new("shadow").spawn().await.unwrap();
+        let mut rule = route("/e2e-shadow/**", vec![stub.url()]);
+        rule.id = Some("e2e-shadow".to_string());
+        rule.auth = Some("shadow".to_string());
+        let gateway = TestGateway::start(vec![rule]).await.unwrap();
+
+        // 校验失败的请求照常转发，但客户端伪造的身份头不会到达上游
+        let forged = jwt("wrong-secret", json!({ "sub": "u-1", "tenant_id": "acme" }));
+        let resp = reqwest::Client::new()
+            .get(gateway.url("/e2e-shadow/x"))
+            .bearer_auth(forged)
+            .header("uid", "admin")
+            .header("tenant_id", "root")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+        let body: Value = resp.json().await.unwrap();
+        assert!(body["headers"]["uid"].is_null());
+        assert!(body["headers"]["tenant_id"].is_null());
+        assert_eq!(AUTH_SHADOW_COUNTER.with_label_values(&["e2e-shadow", "deny", "decode_error"]).get(), 1);
+        reqwest::get(gateway.url("/e2e-shadow/x")).await.unwrap();
+        assert_eq!(AUTH_SHADOW_COUNTER.with_label_values(&["e2e-shadow", "deny", "missing_header"]).get(), 1);
+
+        // 校验通过的请求透传真实身份并计入 allow
+        let body: Value = gateway.get_as("/e2e-shadow/x", "u-2", "acme").await.unwrap().json().await.unwrap();
+        assert_eq!(body["headers"]["uid"], "u-2");
+        assert_eq!(AUTH_SHADOW_COUNTER.with_label_values(&["e2e-shadow", "allow", ""]).get(), 1);
+        assert_eq!(stub.requests(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_optional_auth() {
+        let stub = StubUpstream::new("optional").spawn().await.unwrap();
+        let mut rule = route("/e2e-optional/**", vec![stub.url()]);
+        rule.auth = Some("optional".to_string());
+        let gateway = TestGateway::start(vec![rule]).await.unwrap();
+        let client = reqwest::Client::new();
+
+        // 匿名请求放行，伪造的身份头被去掉
+        let resp = client
+            .get(gateway.url("/e2e-optional/x"))
+            .header("uid", "admin")
+            .header("tenant_id", "root")
+            .header("x-auth-status", "authenticated")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+        let body: Value = resp.json().await.unwrap();
+        assert!(body["headers"]["uid"].is_null());
+        assert!(body["headers"]["tenant_id"].is_null());
+        assert_eq!(body["headers"]["x-auth-status"], "anonymous");
+
+        // 无效的 token 同样按匿名放行，不返回 401
+        let forged = jwt("wrong-secret", json!({ "sub": "u-1", "tenant_id": "acme" }));
+        let resp = client
+            .get(gateway.url("/e2e-optional/x"))
+            .bearer_auth(forged)
+            .header("uid", "u-1")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+        let body: Value = resp.json().await.unwrap();
+        assert!(body["headers"]["uid"].is_null());
+        assert_eq!(body["headers"]["x-auth-status"], "anonymous");
+
+        // 有效的 token 透传身份
+        let body: Value = gateway.get_as("/e2e-optional/x", "u-2", "acme").await.unwrap().json().await.unwrap();
+        assert_eq!(body["headers"]["uid"], "u-2");
+        assert_eq!(body["headers"]["tenant_id"], "acme");
+        assert_eq!(body["headers"]["x-auth-status"], "authenticated");
+        assert_eq!(stub.requests(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_preserve_header_case() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let stub = StubUpstream::new("legacy").header_names(true).spawn().await.unwrap();
+        let mut rule = route("/e2e-header-case/**", vec![stub.url()]);
+        rule.preserve_header_case = Some(true);
+        let gateway = TestGateway::start(vec![rule]).await.unwrap();
+
+        // reqwest 会把请求头名称写成小写，这里直接写出原始请求
+        let mut conn = tokio::net::TcpStream::connect(gateway.addr()).await.unwrap();
+        let request = format!(
+            "GET /e2e-header-case/x HTTP/1.1\r\nHost: gateway\r\nAuthorization: Bearer {}\r\nX-Legacy-CamelCase: 1\r\nSOAPAction: ping\r\nConnection: close\r\n\r\n",
+            gateway.token("u-1", "acme")
+        );
+        conn.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        conn.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        let body: Value = serde_json::from_str(body).unwrap();
+        let names: Vec<&str> = body["header_names"].as_array().unwrap().iter().filter_map(Value::as_str).collect();
+        assert!(names.contains(&"X-Legacy-CamelCase"), "{:?}", names);
+        assert!(names.contains(&"SOAPAction"), "{:?}", names);
+        assert_eq!(body["headers"]["soapaction"], "ping");
+        assert_eq!(stub.requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_upstream_warm_up() {
+        let stub = StubUpstream::new("warm").spawn().await.unwrap();
+        // 预热失败的上游：端口已释放，连接被拒绝
+        let dead = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dead_url = format!("http://{}", dead.local_addr().unwrap());
+        drop(dead);
+        let settings = settings(json!({
+            "upstream_warmup_connections": 2,
+            "upstream_warmup_requests": 1,
+            "upstream_warmup_path": "/healthz",
+        }));
+        let routes = vec![route("/e2e-warm/**", vec![stub.url()]), route("/e2e-warm-dead/**", vec![dead_url.clone()])];
+        // 预热在后台进行，不阻塞启动
+        let gateway = TestGateway::start_with(settings, routes).await.unwrap();
+
+        let warmed = |url: &str| !crate::upstream::status(url).warming;
+        for _ in 0..100 {
+            if warmed(&stub.url()) && warmed(&dead_url) {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        // 启动时按配置向上游发出 2 个并发连接请求与 1 个串行请求
+        assert!(warmed(&stub.url()));
+        assert_eq!(stub.requests(), 3);
+        // 预热失败的上游同样结束预热并进入轮转
+        assert!(warmed(&dead_url));
+        assert_eq!(gateway.get_as("/e2e-warm/x", "u-1", "acme").await.unwrap().status(), 200);
+        assert_eq!(stub.requests(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_route_http_version() {
+        use crate::upstream::{status, Protocol};
+        let h1 = StubUpstream::new("h1").spawn().await.unwrap();
+        let h2 = StubUpstream::new("h2").spawn().await.unwrap();
+        let mut http1 = route("/e2e-http1/**", vec![h1.url()]);
+        http1.http_version = Some("http1".to_string());
+        http1.keep_alive = Some(false);
+        let mut http2 = route("/e2e-http2/**", vec![h2.url()]);
+        http2.http_version = Some("http2".to_string());
+        let gateway = TestGateway::start(vec![http1, http2]).await.unwrap();
+
+        // 各路由使用按自身偏好建立的客户端，上游看到的协议不同
+        assert_eq!(gateway.get_as("/e2e-http1/x", "u-1", "acme").await.unwrap().status(), 200);
+        assert_eq!(gateway.get_as("/e2e-http2/x", "u-1", "acme").await.unwrap().status(), 200);
+        assert_eq!(status(&h1.url()).protocol, Some(Protocol::Http1));
+        assert_eq!(status(&h2.url()).protocol, Some(Protocol::Http2));
+    }
+
+    #[tokio::test]
+    async fn test_route_test_endpoint() {
+        let mut wide = route("/e2e-rt/**", vec!["http://wide.internal:8080".to_string()]);
+        wide.id = Some("e2e-rt-wide".to_string());
+        let mut orders = route("/e2e-rt/orders/{id}", vec!["http://orders.internal:8080".to_string()]);
+        orders.id = Some("e2e-rt-orders".to_string());
+        orders.whitelist = Some(vec!["/e2e-rt/orders/public".to_string()]);
+        let gateway = TestGateway::start_with(settings(json!({ "admin_token": "root-token" })), vec![wide, orders]).await.unwrap();
+        let client = reqwest::Client::new();
+        let route_test = |input: Value| {
+            let request = client.post(gateway.url("/admin/api/route-test")).bearer_auth("root-token").json(&input);
+            async move { request.send().await.unwrap().json::<Value>().await.unwrap() }
+        };
+
+        // 更具体的路由胜出，并给出转发路径与鉴权判定；不向上游发送请求
+        let token = gateway.token("u-1", "acme");
+        let result = route_test(json!({
+            "path": "/proxy/e2e-rt/orders/42",
+            "headers": { "authorization": format!("Bearer {}", token) },
+        }))
+        .await;
+        assert_eq!(result["matched"]["rule"]["id"], "e2e-rt-orders");
+        assert_eq!(result["matched"]["variables"]["id"], "42");
+        assert_eq!(result["matched"]["forward_path"], "/e2e-rt/orders/42");
+        assert_eq!(result["middleware"]["auth_required"], true);
+        assert_eq!(result["middleware"]["token_valid"], true);
+
+        let result = route_test(json!({ "method": "post", "path": "/e2e-rt/orders/public" })).await;
+        assert_eq!(result["matched"]["rule"]["id"], "e2e-rt-orders");
+        assert_eq!(result["middleware"]["whitelisted"], true);
+        assert_eq!(result["middleware"]["auth_required"], false);
+        assert!(result["middleware"]["token_valid"].is_null());
+
+        let result = route_test(json!({ "path": "/e2e-rt/users/7" })).await;
+        assert_eq!(result["matched"]["rule"]["id"], "e2e-rt-wide");
+
+        // 没有匹配的路由
+        let result = route_test(json!({ "path": "/e2e-other/x" })).await;
+        assert!(result["matched"].is_null());
+        assert!(result["middleware"]["auth_mode"].is_null());
+        assert_eq!(result["middleware"]["auth_required"], false);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_strips_forged_identity() {
+        let stub = StubUpstream::new("pipeline").spawn().await.unwrap();
+        // 只鉴权、不透传身份，以及完全跳过各阶段的路由
+        let mut auth_only = route("/e2e-pipeline-auth/**", vec![stub.url()]);
+        auth_only.middleware = Some(vec!["auth".to_string()]);
+        let mut bare = route("/e2e-pipeline-bare/**", vec![stub.url()]);
+        bare.middleware = Some(Vec::new());
+        let gateway = TestGateway::start(vec![auth_only, bare]).await.unwrap();
+        let client = reqwest::Client::new();
+
+        for path in ["/e2e-pipeline-auth/x", "/e2e-pipeline-bare/x"] {
+            let resp = client
+                .get(gateway.url(path))
+                .bearer_auth(gateway.token("u-1", "acme"))
+                .header("uid", "admin")
+                .header("tenant_id", "root")
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), 200, "{}", path);
+            let body: Value = resp.json().await.unwrap();
+            assert!(body["headers"]["uid"].is_null(), "{}", path);
+            assert!(body["headers"]["tenant_id"].is_null(), "{}", path);
+        }
+        assert_eq!(stub.requests(), 2);
+    }
+}
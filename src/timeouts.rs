@@ -0,0 +1,107 @@
+//! 分阶段的上游超时：连接、首字节（收到响应头）、数据块间空闲与总时长。
+//! 路由未配置任何分阶段超时时沿用 request_timeout_secs 作为总时长，与此前的行为一致；
+//! 配置后 request_timeout_secs 只作为首字节超时的默认值，长时间下载不再受总时长限制，
+//! 而停止发送数据的上游仍会被空闲超时及时发现。
+use std::time::Duration;
+
+use crate::config::RouteRule;
+
+/// 默认的连接超时
+pub const DEFAULT_CONNECT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    pub connect: Duration,
+    // 发出请求到收到响应头
+    pub first_byte: Option<Duration>,
+    // 两次读取之间的最长间隔（等待响应头同样受此约束）
+    pub idle: Option<Duration>,
+    // 从发出请求到读完响应体
+    pub total: Option<Duration>,
+}
+
+fn millis(ms: Option<u64>) -> Option<Duration> {
+    ms.map(Duration::from_millis)
+}
+
+impl Timeouts {
+    /// 路由是否配置了分阶段超时
+    pub fn split(rule: &RouteRule) -> bool {
+        [rule.connect_timeout_ms, rule.first_byte_timeout_ms, rule.idle_timeout_ms, rule.total_timeout_ms]
+            .iter()
+            .any(Option::is_some)
+    }
+
+    pub fn for_route(rule: &RouteRule, request_timeout: Duration) -> Self {
+        if !Self::split(rule) {
+            return Self { connect: DEFAULT_CONNECT, first_byte: None, idle: None, total: Some(request_timeout) };
+        }
+        Self {
+            connect: millis(rule.connect_timeout_ms).unwrap_or(DEFAULT_CONNECT),
+            first_byte: Some(millis(rule.first_byte_timeout_ms).unwrap_or(request_timeout)),
+            idle: millis(rule.idle_timeout_ms),
+            total: millis(rule.total_timeout_ms),
+        }
+    }
+
+    /// 首字节与总时长都不超过 limit
+    pub fn cap(self, limit: Duration) -> Self {
+        let capped = |timeout: Option<Duration>| Some(timeout.map_or(limit, |t| t.min(limit)));
+        Self { first_byte: capped(self.first_byte), total: capped(self.total), ..self }
+    }
+}
+
+/// 校验分阶段超时
+pub fn validation_errors(rule: &RouteRule) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (field, value) in [
+        ("connect_timeout_ms", rule.connect_timeout_ms),
+        ("first_byte_timeout_ms", rule.first_byte_timeout_ms),
+        ("idle_timeout_ms", rule.idle_timeout_ms),
+        ("total_timeout_ms", rule.total_timeout_ms),
+    ] {
+        if value == Some(0) {
+            errors.push(format!("{}必须大于0", field));
+        }
+    }
+    if let (Some(first_byte), Some(total)) = (rule.first_byte_timeout_ms, rule.total_timeout_ms)
+        && first_byte > total
+    {
+        errors.push("first_byte_timeout_ms不能大于total_timeout_ms".to_string());
+    }
+    // 保留请求头大小写的路由使用独立的 HTTP/1.1 客户端，只支持首字节超时
+    if rule.preserve_header_case == Some(true)
+        && (rule.connect_timeout_ms.is_some() || rule.idle_timeout_ms.is_some() || rule.total_timeout_ms.is_some())
+    {
+        errors.push("preserve_header_case只支持 first_byte_timeout_ms，不能配置连接、空闲与总时长超时".to_string());
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeouts_for_route() {
+        let request_timeout = Duration::from_secs(10);
+        let mut rule = RouteRule { prefix: vec!["/download".to_string()], ..Default::default() };
+        assert_eq!(Timeouts::for_route(&rule, request_timeout).total, Some(request_timeout));
+
+        rule.idle_timeout_ms = Some(2000);
+        let timeouts = Timeouts::for_route(&rule, request_timeout);
+        assert_eq!(timeouts.first_byte, Some(request_timeout));
+        assert_eq!(timeouts.idle, Some(Duration::from_secs(2)));
+        assert_eq!(timeouts.total, None);
+        assert_eq!(timeouts.connect, DEFAULT_CONNECT);
+
+        // 白名单请求的超时上限同时约束首字节与总时长
+        let capped = timeouts.cap(Duration::from_secs(3));
+        assert_eq!((capped.first_byte, capped.total), (Some(Duration::from_secs(3)), Some(Duration::from_secs(3))));
+        assert_eq!(capped.idle, Some(Duration::from_secs(2)));
+
+        rule.first_byte_timeout_ms = Some(5000);
+        rule.total_timeout_ms = Some(1000);
+        assert_eq!(validation_errors(&rule), vec!["first_byte_timeout_ms不能大于total_timeout_ms"]);
+    }
+}
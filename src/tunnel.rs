@@ -0,0 +1,144 @@
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, Method, Response, StatusCode},
+    response::IntoResponse,
+};
+use hyper::upgrade::OnUpgrade;
+use hyper_util::rt::TokioIo;
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use std::time::Duration;
+
+use crate::metrics::{GaugeGuard, TUNNELS_ACTIVE};
+use crate::problem::{Problem, ProblemType};
+use crate::websocket::WebSocketLimits;
+
+// 协议升级只能在 HTTP/1.1 上进行；隧道是长连接，不设置整体超时、不复用连接
+static TUNNEL_CLIENT: Lazy<Client> = Lazy::new(|| {
+    Client::builder()
+        .http1_only()
+        .pool_max_idle_per_host(0)
+        .connect_timeout(Duration::from_secs(5))
+        .tcp_nodelay(true)
+        .build()
+        .expect("Failed to build tunnel client")
+});
+
+/// 请求的升级协议：需同时带 Connection: upgrade 与 Upgrade 头
+pub fn requested_protocol(headers: &HeaderMap) -> Option<&str> {
+    let connection_upgrade = headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|token| token.trim().eq_ignore_ascii_case("upgrade"));
+    if !connection_upgrade {
+        return None;
+    }
+    headers.get(header::UPGRADE).and_then(|v| v.to_str().ok()).map(str::trim)
+}
+
+/// 路由是否允许该协议（按协议名比较，忽略版本号与大小写，* 表示任意协议）
+pub fn allows(allowed: &[String], protocol: &str) -> bool {
+    let name = |p: &str| p.split('/').next().unwrap_or_default().trim().to_ascii_lowercase();
+    let requested: Vec<String> = protocol.split(',').map(name).collect();
+    allowed
+        .iter()
+        .any(|a| a == "*" || requested.iter().any(|r| *r == name(a)))
+}
+
+fn error_response(message: String) -> Response<Body> {
+    Problem::new(StatusCode::BAD_GATEWAY, ProblemType::UpstreamError).detail(message).into_response()
+}
+
+// ===== 转发升级请求，上游返回 101 后在两端之间双向拷贝原始字节（配置了 WebSocket 限制时按帧转发） =====
+pub async fn tunnel(
+    on_upgrade: OnUpgrade,
+    url: String,
+    method: Method,
+    headers: &HeaderMap,
+    route: String,
+    tenant: Option<String>,
+    websocket: Option<WebSocketLimits>,
+) -> Response<Body> {
+    let mut rb = TUNNEL_CLIENT.request(method, &url);
+    for (name, value) in headers {
+        if name == header::HOST || name == crate::debug::DEBUG_HEADER {
+            continue;
+        }
+        rb = rb.header(name, value);
+    }
+
+    let resp = match rb.send().await {
+        Ok(resp) => resp,
+        Err(err) => return error_response(format!("Proxy error: {}", err)),
+    };
+
+    let status = resp.status();
+    let mut builder = Response::builder().status(status);
+    for (name, value) in resp.headers() {
+        builder = builder.header(name, value);
+    }
+
+    // 上游拒绝升级：按普通响应返回
+    if status != StatusCode::SWITCHING_PROTOCOLS {
+        return match resp.bytes().await {
+            Ok(body) => builder.body(Body::from(body)).unwrap(),
+            Err(err) => error_response(format!("Response body error: {}", err)),
+        };
+    }
+
+    let mut upstream_io = match resp.upgrade().await {
+        Ok(io) => io,
+        Err(err) => return error_response(format!("Upstream upgrade failed: {}", err)),
+    };
+
+    tokio::spawn(async move {
+        let _active = GaugeGuard::new(&TUNNELS_ACTIVE);
+        let mut client_io = match on_upgrade.await {
+            Ok(io) => TokioIo::new(io),
+            Err(err) => {
+                tracing::warn!(route = %route, "客户端协议升级失败: {}", err);
+                return;
+            }
+        };
+        if let Some(limits) = websocket {
+            let (sent, received) = crate::websocket::relay(client_io, upstream_io, &limits, &route).await;
+            crate::bandwidth::record(&route, tenant.as_deref(), sent, received);
+            return;
+        }
+        match tokio::io::copy_bidirectional(&mut client_io, &mut upstream_io).await {
+            Ok((sent, received)) => {
+                tracing::debug!(route = %route, sent, received, "隧道关闭");
+                crate::bandwidth::record(&route, tenant.as_deref(), sent, received);
+            }
+            Err(err) => tracing::debug!(route = %route, "隧道异常关闭: {}", err),
+        }
+    });
+
+    builder.body(Body::empty()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requested_protocol() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::UPGRADE, "h2c".parse().unwrap());
+        assert_eq!(requested_protocol(&headers), None);
+
+        headers.insert(header::CONNECTION, "keep-alive, Upgrade".parse().unwrap());
+        assert_eq!(requested_protocol(&headers), Some("h2c"));
+    }
+
+    #[test]
+    fn test_allows() {
+        let allowed = vec!["websocket".to_string(), "h2c".to_string()];
+        assert!(allows(&allowed, "WebSocket"));
+        assert!(allows(&allowed, "foo/1, h2c"));
+        assert!(!allows(&allowed, "custom-proto/2"));
+        assert!(allows(&["*".to_string()], "custom-proto/2"));
+    }
+}
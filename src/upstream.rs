@@ -0,0 +1,442 @@
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU32, AtomicU64, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+use crate::config::Settings;
+
+/// 单个上游节点的运行时状态，被所有引用该节点的负载均衡器共享
+#[derive(Debug, Default)]
+pub struct UpstreamState {
+    draining: AtomicBool,
+    // 预热中：仅在没有其他可用节点时才分配请求
+    warming: AtomicBool,
+    in_flight: AtomicI64,
+    // 并发上限：在途请求达到该值时不再分配新请求，0 表示沿用默认上限
+    max_in_flight: AtomicU32,
+    // 熔断：连续失败次数，以及熔断截止时间（相对 EPOCH 的毫秒数，0 表示未熔断）
+    consecutive_failures: AtomicU32,
+    open_until_ms: AtomicU64,
+    // 延迟探测结果（EWMA，微秒）：0 表示尚未探测，PROBE_FAILED 表示最近一次探测失败
+    probe_latency_us: AtomicU64,
+    // 累计转发次数与失败次数（连接错误或 5xx）
+    requests: AtomicU64,
+    failures: AtomicU64,
+    // 最近一次响应使用的 HTTP 协议（https 上游经 ALPN 协商），0 表示尚未转发过
+    protocol: AtomicU8,
+}
+
+const PROBE_FAILED: u64 = u64::MAX;
+
+/// 延迟探测结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProbeLatency {
+    Measured(Duration),
+    Unknown,
+    Failed,
+}
+
+impl UpstreamState {
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    pub fn is_warming(&self) -> bool {
+        self.warming.load(Ordering::Relaxed)
+    }
+
+    pub fn in_flight(&self) -> i64 {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// 生效的并发上限，None 表示不限制
+    pub fn max_in_flight(&self) -> Option<u32> {
+        match self.max_in_flight.load(Ordering::Relaxed) {
+            0 => Some(DEFAULT_MAX_IN_FLIGHT.load(Ordering::Relaxed)).filter(|max| *max > 0),
+            max => Some(max),
+        }
+    }
+
+    /// 在途请求已达并发上限
+    pub fn is_saturated(&self) -> bool {
+        self.max_in_flight().is_some_and(|max| self.in_flight() >= max as i64)
+    }
+
+    pub fn probe_latency(&self) -> ProbeLatency {
+        match self.probe_latency_us.load(Ordering::Relaxed) {
+            0 => ProbeLatency::Unknown,
+            PROBE_FAILED => ProbeLatency::Failed,
+            us => ProbeLatency::Measured(Duration::from_micros(us)),
+        }
+    }
+
+    /// 记录一次探测结果，成功时按 EWMA（新样本权重 1/4）平滑
+    pub fn record_probe(&self, latency: Option<Duration>) {
+        let value = match latency {
+            None => PROBE_FAILED,
+            Some(latency) => {
+                let sample = (latency.as_micros() as u64).max(1);
+                match self.probe_latency() {
+                    ProbeLatency::Measured(prev) => (prev.as_micros() as u64 * 3 + sample) / 4,
+                    _ => sample,
+                }
+            }
+        };
+        self.probe_latency_us.store(value, Ordering::Relaxed);
+    }
+
+    /// 熔断中（到期后进入半开状态，允许请求探测）
+    pub fn is_ejected(&self) -> bool {
+        let until = self.open_until_ms.load(Ordering::Relaxed);
+        until != 0 && now_ms() < until
+    }
+}
+
+static EPOCH: Lazy<Instant> = Lazy::new(Instant::now);
+
+fn now_ms() -> u64 {
+    EPOCH.elapsed().as_millis() as u64
+}
+
+// ===== 全局上游状态表（按 URL） =====
+static UPSTREAMS: Lazy<DashMap<String, Arc<UpstreamState>>> = Lazy::new(DashMap::new);
+
+pub fn state(url: &str) -> Arc<UpstreamState> {
+    if let Some(state) = UPSTREAMS.get(url) {
+        return state.clone();
+    }
+    UPSTREAMS.entry(url.to_string()).or_default().clone()
+}
+
+// 未单独配置的上游使用的并发上限，0 表示不限制
+static DEFAULT_MAX_IN_FLIGHT: AtomicU32 = AtomicU32::new(0);
+
+/// 对应 upstream_max_in_flight 中表示默认上限的键
+pub const ANY_UPSTREAM: &str = "*";
+
+/// 应用各上游的并发上限（URL -> 上限，* 为默认值）
+pub fn configure_max_in_flight(limits: &BTreeMap<String, u32>) {
+    DEFAULT_MAX_IN_FLIGHT.store(limits.get(ANY_UPSTREAM).copied().unwrap_or(0), Ordering::Relaxed);
+    for (url, max) in limits {
+        if url != ANY_UPSTREAM {
+            state(url).max_in_flight.store(*max, Ordering::Relaxed);
+        }
+    }
+}
+
+/// 是否还能接收新请求（未达并发上限）
+pub fn has_capacity(url: &str) -> bool {
+    UPSTREAMS.get(url).is_none_or(|s| !s.is_saturated())
+}
+
+/// 是否处于完整轮转中（未摘流、已完成预热且未熔断）
+pub fn is_available(url: &str) -> bool {
+    UPSTREAMS.get(url).is_none_or(|s| !s.is_draining() && !s.is_warming() && !s.is_ejected())
+}
+
+/// 是否可以分配新请求（忽略预热状态），在没有完整可用节点时兜底
+pub fn is_routable(url: &str) -> bool {
+    UPSTREAMS.get(url).is_none_or(|s| !s.is_draining())
+}
+
+/// 设置摘流状态，返回之前的状态
+pub fn set_draining(url: &str, draining: bool) -> bool {
+    state(url).draining.swap(draining, Ordering::Relaxed)
+}
+
+/// 与上游实际使用的 HTTP 协议
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Protocol {
+    #[serde(rename = "http/1.1")]
+    Http1,
+    #[serde(rename = "h2")]
+    Http2,
+}
+
+impl Protocol {
+    pub fn from_version(version: reqwest::Version) -> Option<Self> {
+        match version {
+            reqwest::Version::HTTP_2 => Some(Protocol::Http2),
+            reqwest::Version::HTTP_10 | reqwest::Version::HTTP_11 => Some(Protocol::Http1),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Protocol::Http1 => "http/1.1",
+            Protocol::Http2 => "h2",
+        }
+    }
+
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Protocol::Http1),
+            2 => Some(Protocol::Http2),
+            _ => None,
+        }
+    }
+}
+
+/// 记住上游本次响应使用的协议，返回此前记住的协议
+pub fn record_protocol(url: &str, protocol: Protocol) -> Option<Protocol> {
+    let value = match protocol {
+        Protocol::Http1 => 1,
+        Protocol::Http2 => 2,
+    };
+    Protocol::from_u8(state(url).protocol.swap(value, Ordering::Relaxed))
+}
+
+/// 计入一次转发结果（不论是否启用熔断）
+pub fn record_result(url: &str, failed: bool) {
+    let state = state(url);
+    state.requests.fetch_add(1, Ordering::Relaxed);
+    if failed {
+        state.failures.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// 记录一次失败；连续失败达到阈值时熔断 open_for，返回本次是否触发熔断
+pub fn record_failure(url: &str, threshold: u32, open_for: Duration) -> bool {
+    let state = state(url);
+    if state.is_ejected() {
+        return false;
+    }
+    // 熔断到期后的半开探测失败，立即重新熔断
+    let half_open = state.open_until_ms.load(Ordering::Relaxed) != 0;
+    let failures = state.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+    if failures < threshold && !half_open {
+        return false;
+    }
+    state.consecutive_failures.store(0, Ordering::Relaxed);
+    state.open_until_ms.store(now_ms() + open_for.as_millis() as u64, Ordering::Relaxed);
+    crate::coordination::publish(crate::coordination::Kind::Breaker, url, Some(open_for), "连续失败");
+    true
+}
+
+/// 记录一次成功；若此前处于熔断（半开探测成功）则关闭熔断并返回 true
+pub fn record_success(url: &str) -> bool {
+    let Some(state) = UPSTREAMS.get(url).map(|s| s.clone()) else {
+        return false;
+    };
+    state.consecutive_failures.store(0, Ordering::Relaxed);
+    let closed = state.open_until_ms.swap(0, Ordering::Relaxed) != 0;
+    if closed {
+        crate::coordination::withdraw(crate::coordination::Kind::Breaker, url);
+    }
+    closed
+}
+
+/// 应用其他副本共享的熔断；本地已熔断到更晚时保留本地的
+pub fn eject_shared(url: &str, remaining: Duration) {
+    let until = now_ms() + remaining.as_millis() as u64;
+    state(url).open_until_ms.fetch_max(until, Ordering::Relaxed);
+}
+
+/// 撤销其他副本已关闭的熔断
+pub fn close_shared(url: &str) {
+    if let Some(state) = UPSTREAMS.get(url) {
+        state.consecutive_failures.store(0, Ordering::Relaxed);
+        state.open_until_ms.store(0, Ordering::Relaxed);
+    }
+}
+
+/// 作用域内的上游在途请求计数
+pub struct InFlightGuard(Arc<UpstreamState>);
+
+impl InFlightGuard {
+    pub fn new(url: &str) -> Self {
+        let state = state(url);
+        state.in_flight.fetch_add(1, Ordering::Relaxed);
+        Self(state)
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpstreamStatus {
+    pub url: String,
+    pub draining: bool,
+    pub warming: bool,
+    pub ejected: bool,
+    pub in_flight: i64,
+    pub max_in_flight: Option<u32>,
+    // 区域延迟探测结果（毫秒），未探测或探测失败为 None
+    pub probe_latency_ms: Option<f64>,
+    pub probe_failed: bool,
+    pub requests: u64,
+    pub failures: u64,
+    // 最近一次协商出的 HTTP 协议，尚未转发过为 None
+    pub protocol: Option<Protocol>,
+}
+
+pub fn status(url: &str) -> UpstreamStatus {
+    let state = state(url);
+    UpstreamStatus {
+        url: url.to_string(),
+        draining: state.is_draining(),
+        warming: state.is_warming(),
+        ejected: state.is_ejected(),
+        in_flight: state.in_flight(),
+        max_in_flight: state.max_in_flight(),
+        probe_latency_ms: match state.probe_latency() {
+            ProbeLatency::Measured(d) => Some(d.as_secs_f64() * 1000.0),
+            _ => None,
+        },
+        probe_failed: state.probe_latency() == ProbeLatency::Failed,
+        requests: state.requests.load(Ordering::Relaxed),
+        failures: state.failures.load(Ordering::Relaxed),
+        protocol: Protocol::from_u8(state.protocol.load(Ordering::Relaxed)),
+    }
+}
+
+// ===== 连接预热 =====
+/// 预热参数：并发建立的空闲连接数、之后串行发送的预热请求数
+#[derive(Debug, Clone)]
+pub struct WarmUp {
+    pub connections: usize,
+    pub requests: usize,
+    pub path: String,
+}
+
+impl WarmUp {
+    /// 两项均为 0（默认）时不预热
+    pub fn from_settings(settings: &Settings) -> Option<Self> {
+        let connections = settings.upstream_warmup_connections.unwrap_or(0);
+        let requests = settings.upstream_warmup_requests.unwrap_or(0);
+        (connections > 0 || requests > 0).then(|| Self {
+            connections,
+            requests,
+            path: settings.upstream_warmup_path.clone().unwrap_or_else(|| "/".to_string()),
+        })
+    }
+}
+
+/// 后台预热一组上游，预热期间节点不进入完整轮转
+pub fn spawn_warm_up<I>(settings: &Settings, urls: I)
+where
+    I: IntoIterator<Item = String>,
+{
+    let Some(warm_up) = WarmUp::from_settings(settings) else {
+        return;
+    };
+    for url in urls {
+        state(&url).warming.store(true, Ordering::Relaxed);
+        tokio::spawn(run_warm_up(url, warm_up.clone()));
+    }
+}
+
+async fn run_warm_up(url: String, warm_up: WarmUp) {
+    let start = Instant::now();
+    let target = format!("{}{}", url, warm_up.path);
+
+    // 并发请求会各自占用一条连接，完成后留在连接池中成为空闲连接
+    let mut tasks = JoinSet::new();
+    for _ in 0..warm_up.connections {
+        tasks.spawn(warm_up_request(target.clone()));
+    }
+    let mut failed = 0;
+    while let Some(result) = tasks.join_next().await {
+        if !matches!(result, Ok(true)) {
+            failed += 1;
+        }
+    }
+    for _ in 0..warm_up.requests {
+        if !warm_up_request(target.clone()).await {
+            failed += 1;
+        }
+    }
+
+    state(&url).warming.store(false, Ordering::Relaxed);
+    if failed > 0 {
+        tracing::warn!(upstream = %url, failed, "上游预热存在失败请求，仍进入轮转");
+    }
+    tracing::info!(upstream = %url, elapsed_ms = start.elapsed().as_millis() as u64, "上游预热完成");
+}
+
+// 读完响应体，连接才会归还到连接池
+async fn warm_up_request(target: String) -> bool {
+    match crate::proxy::HTTP_CLIENT.get(&target).send().await {
+        Ok(resp) => resp.bytes().await.is_ok(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drain_and_in_flight() {
+        let url = "http://drain-test:8080";
+        assert!(is_available(url));
+
+        let guard = InFlightGuard::new(url);
+        assert!(!set_draining(url, true));
+        assert!(!is_available(url));
+        // 摘流不影响已在途的请求
+        assert_eq!(status(url).in_flight, 1);
+        drop(guard);
+        assert_eq!(status(url).in_flight, 0);
+
+        assert!(set_draining(url, false));
+        assert!(is_available(url));
+    }
+
+    #[test]
+    fn test_warm_up_from_settings() {
+        use crate::testing::settings;
+        assert!(WarmUp::from_settings(&settings(serde_json::json!({}))).is_none());
+        let warm_up = WarmUp::from_settings(&settings(serde_json::json!({ "upstream_warmup_requests": 3 }))).unwrap();
+        assert_eq!((warm_up.connections, warm_up.requests, warm_up.path.as_str()), (0, 3, "/"));
+    }
+
+    #[test]
+    fn test_max_in_flight() {
+        let url = "http://capped-test:8080";
+        configure_max_in_flight(&BTreeMap::from([(url.to_string(), 2)]));
+        let first = InFlightGuard::new(url);
+        assert!(has_capacity(url));
+        let second = InFlightGuard::new(url);
+        assert!(!has_capacity(url));
+        // 饱和不影响可用性判断，只影响新请求的分配
+        assert!(is_available(url));
+        drop(first);
+        assert!(has_capacity(url));
+        drop(second);
+        assert_eq!(status(url).max_in_flight, Some(2));
+    }
+
+    #[test]
+    fn test_record_protocol() {
+        let url = "https://alpn-test:8443";
+        assert_eq!(status(url).protocol, None);
+        assert_eq!(record_protocol(url, Protocol::Http2), None);
+        assert_eq!(record_protocol(url, Protocol::Http1), Some(Protocol::Http2));
+        assert_eq!(status(url).protocol, Some(Protocol::Http1));
+        assert_eq!(serde_json::to_value(status(url)).unwrap()["protocol"], "http/1.1");
+        assert_eq!(Protocol::from_version(reqwest::Version::HTTP_2), Some(Protocol::Http2));
+    }
+
+    #[test]
+    fn test_circuit_breaker_trips_and_closes() {
+        let url = "http://breaker-test:8080";
+        assert!(!record_failure(url, 3, Duration::from_secs(30)));
+        assert!(!record_failure(url, 3, Duration::from_secs(30)));
+        assert!(record_failure(url, 3, Duration::from_secs(30)));
+        assert!(!is_available(url));
+        assert!(is_routable(url));
+
+        assert!(record_success(url));
+        assert!(is_available(url));
+        assert!(!record_success(url));
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+use crate::auth::Claims;
+use crate::config::RouteRule;
+
+// 引用 JWT 声明的占位符前缀，如 {claim.tenant_id}
+const CLAIM_PREFIX: &str = "claim.";
+// 替换值的长度上限（DNS 标签上限）
+const MAX_VALUE_LEN: usize = 63;
+// 校验模板时依次尝试的替换值，分别适配主机名、端口与协议位置
+const SAMPLES: &[&str] = &["x", "1", "http"];
+
+/// 渲染失败的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    // 路径变量或声明缺失
+    Missing(String),
+    // 取值含有不允许的字符
+    Invalid(String),
+    // 渲染结果不是合法的上游地址
+    BadUrl(String),
+    // 主机不在 upstream_hosts 中
+    HostNotAllowed(String),
+}
+
+impl std::fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TemplateError::Missing(name) => write!(f, "missing value for {{{}}}", name),
+            TemplateError::Invalid(name) => write!(f, "invalid value for {{{}}}", name),
+            TemplateError::BadUrl(url) => write!(f, "rendered upstream is not a valid URL: {}", url),
+            TemplateError::HostNotAllowed(host) => write!(f, "upstream host not allowed: {}", host),
+        }
+    }
+}
+
+/// 上游地址是否包含占位符
+pub fn is_template(url: &str) -> bool {
+    url.contains('{')
+}
+
+/// 路由的上游是否需要按请求渲染
+pub fn is_templated(rule: &RouteRule) -> bool {
+    rule.upstream.iter().any(|u| is_template(u))
+}
+
+// 模板中的占位符名称，未闭合的 { 返回 None
+fn placeholders(template: &str) -> Option<Vec<&str>> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}')? + start;
+        names.push(rest[start + 1..end].trim());
+        rest = &rest[end + 1..];
+    }
+    Some(names)
+}
+
+// 逐个替换占位符
+fn substitute(template: &str, mut value: impl FnMut(&str) -> Result<String, TemplateError>) -> Result<String, TemplateError> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|e| e + start) else {
+            return Err(TemplateError::BadUrl(template.to_string()));
+        };
+        out.push_str(&rest[..start]);
+        out.push_str(&value(rest[start + 1..end].trim())?);
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+// 替换值只允许字母数字、- 与 _，以及不构成 .. 的 .，避免改写主机、端口之外的 URL 结构
+fn safe_value(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= MAX_VALUE_LEN
+        && !value.contains("..")
+        && !value.starts_with('.')
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// 主机是否命中 upstream_hosts，支持 *.example.com 匹配任意子域
+pub fn host_allowed(patterns: &[String], host: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.to_ascii_lowercase();
+        match pattern.strip_prefix("*.") {
+            Some(domain) => host.strip_suffix(domain).is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+            None => host == pattern,
+        }
+    })
+}
+
+/// 用路径变量与 JWT 声明渲染一个上游地址，并校验结果的协议、主机与端口
+pub fn render(
+    template: &str,
+    allowed_hosts: &[String],
+    variables: &HashMap<String, String>,
+    claims: Option<&Claims>,
+) -> Result<String, TemplateError> {
+    let url = substitute(template, |name| {
+        let value = match name.strip_prefix(CLAIM_PREFIX) {
+            Some(claim) => claims.and_then(|c| c.get(claim)),
+            None => variables.get(name).cloned(),
+        }
+        .ok_or_else(|| TemplateError::Missing(name.to_string()))?;
+        if safe_value(&value) { Ok(value) } else { Err(TemplateError::Invalid(name.to_string())) }
+    })?;
+    let parsed = crate::upstream_url::parse(&url).map_err(|_| TemplateError::BadUrl(url.clone()))?;
+    let host = parsed.host_str().unwrap_or_default();
+    if !host_allowed(allowed_hosts, host) {
+        return Err(TemplateError::HostNotAllowed(host.to_string()));
+    }
+    Ok(url)
+}
+
+/// 渲染路由的全部上游（已渲染的地址原样保留）
+pub fn render_upstreams(
+    rule: &RouteRule,
+    variables: &HashMap<String, String>,
+    claims: Option<&Claims>,
+) -> Result<Vec<String>, TemplateError> {
+    let allowed = rule.upstream_hosts.as_deref().unwrap_or_default();
+    rule.upstream
+        .iter()
+        .map(|u| if is_template(u) { render(u, allowed, variables, claims) } else { Ok(u.clone()) })
+        .collect()
+}
+
+/// 以示例值渲染模板，得到一个可解析的地址，用于加载时校验；无法得到合法地址时返回 None
+pub fn sample(template: &str) -> Option<String> {
+    SAMPLES.iter().find_map(|s| {
+        let url = substitute(template, |_| Ok(s.to_string())).ok()?;
+        crate::upstream_url::parse(&url).is_ok().then_some(url)
+    })
+}
+
+/// 校验上游模板：占位符须为路由前缀中的路径变量或 claim.<name>，且须配置 upstream_hosts
+pub fn validation_errors(rule: &RouteRule) -> Vec<String> {
+    let mut errors = Vec::new();
+    for template in rule.upstream.iter().filter(|u| is_template(u)) {
+        let Some(names) = placeholders(template) else {
+            errors.push(format!("上游模板缺少 }}: {}", template));
+            continue;
+        };
+        for name in names {
+            let known = match name.strip_prefix(CLAIM_PREFIX) {
+                Some(claim) => !claim.is_empty(),
+                None => rule.prefix.iter().any(|p| p.contains(&format!("{{{}}}", name)) || p.contains(&format!("{{{}:", name))),
+            };
+            if !known {
+                errors.push(format!("上游模板 {} 引用了未定义的变量: {}", template, name));
+            }
+        }
+        if sample(template).is_none() {
+            errors.push(format!("上游模板无法渲染为合法地址: {}", template));
+        }
+    }
+    if is_templated(rule) && rule.upstream_hosts.as_ref().is_none_or(|hosts| hosts.is_empty()) {
+        errors.push("上游包含模板时必须配置upstream_hosts".to_string());
+    }
+    for host in rule.upstream_hosts.iter().flatten() {
+        if host.trim().is_empty() || host.contains(['/', ':', '{']) {
+            errors.push(format!("upstream_hosts包含非法主机名: {}", host));
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(prefix: &str, upstream: &str, hosts: &[&str]) -> RouteRule {
+        RouteRule {
+            prefix: vec![prefix.to_string()],
+            upstream: vec![upstream.to_string()],
+            upstream_hosts: Some(hosts.iter().map(|h| h.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_render_with_variables_and_claims() {
+        let hosts = vec!["*.internal".to_string()];
+        let vars = HashMap::from([("version".to_string(), "v2".to_string())]);
+        let claims = Claims { tenant_id: "acme".to_string(), ..Default::default() };
+        assert_eq!(
+            render("http://svc-{version}.internal:8080", &hosts, &vars, None).unwrap(),
+            "http://svc-v2.internal:8080"
+        );
+        assert_eq!(
+            render("https://{claim.tenant_id}.internal", &hosts, &vars, Some(&claims)).unwrap(),
+            "https://acme.internal"
+        );
+        assert_eq!(
+            render("http://{claim.tenant_id}.internal", &hosts, &vars, None),
+            Err(TemplateError::Missing("claim.tenant_id".to_string()))
+        );
+
+        // 取值不能改写 URL 结构，渲染结果的主机须在 upstream_hosts 中
+        for bad in ["evil.com/x", "a@evil.com", "..", "x:99"] {
+            let vars = HashMap::from([("version".to_string(), bad.to_string())]);
+            assert_eq!(
+                render("http://svc-{version}.internal", &hosts, &vars, None),
+                Err(TemplateError::Invalid("version".to_string())),
+                "{}",
+                bad
+            );
+        }
+        assert_eq!(
+            render("http://{version}", &hosts, &vars, None),
+            Err(TemplateError::HostNotAllowed("v2".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_template_validation() {
+        assert!(rule("/api/{version}/**", "http://svc-{version}.internal:8080", &["*.internal"]).validation_errors().is_empty());
+        assert!(rule("/api/**", "http://{claim.tenant_id}.internal:{claim.port}", &["*.internal"]).validation_errors().is_empty());
+        assert!(!rule("/api/**", "http://svc-{version}.internal", &["*.internal"]).validation_errors().is_empty());
+        assert!(!rule("/api/{version}/**", "http://svc-{version}.internal", &[]).validation_errors().is_empty());
+        assert!(!rule("/api/{version}/**", "http://svc-{version.internal", &["*.internal"]).validation_errors().is_empty());
+    }
+}
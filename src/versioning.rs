@@ -0,0 +1,207 @@
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 默认的版本请求头
+pub const DEFAULT_VERSION_HEADER: &str = "x-api-version";
+
+/// 版本号的来源
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionSource {
+    // 路径中的 /v{n} 段
+    #[default]
+    Path,
+    // 请求头，默认 X-Api-Version
+    Header,
+    // Accept 媒体类型参数，如 application/json; version=2
+    Accept,
+}
+
+/// 路由级 API 版本配置：按版本号转发到不同的上游组
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Versioning {
+    #[serde(default)]
+    pub source: VersionSource,
+    // source = "header" 时读取的请求头
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    // 请求未携带版本时使用的版本；不配置则转发到路由本身的 upstream
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    // 版本号（不带 v 前缀）-> 版本配置
+    #[serde(default)]
+    pub versions: BTreeMap<String, ApiVersion>,
+}
+
+/// 单个版本的上游与弃用信息
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiVersion {
+    // 该版本的上游组；为空则使用路由本身的 upstream
+    #[serde(default)]
+    pub upstream: Vec<String>,
+    // 已弃用：响应附带 Deprecation: true
+    #[serde(default)]
+    pub deprecated: bool,
+    // 下线时间（HTTP-date），响应附带 Sunset 头
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sunset: Option<String>,
+    // 迁移说明链接，响应附带 Link: <...>; rel="deprecation"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+}
+
+impl Versioning {
+    /// 从请求中提取版本号（去掉 v 前缀），未携带则为 None
+    pub fn extract(&self, path: &str, headers: &HeaderMap) -> Option<String> {
+        let raw = match self.source {
+            VersionSource::Path => path.split('/').find(|seg| is_path_version(seg)).map(str::to_string),
+            VersionSource::Header => {
+                let name = self.header.as_deref().unwrap_or(DEFAULT_VERSION_HEADER);
+                headers.get(name).and_then(|v| v.to_str().ok()).map(|v| v.trim().to_string())
+            }
+            VersionSource::Accept => headers
+                .get_all(header::ACCEPT)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(','))
+                .find_map(accept_version),
+        }?;
+        let version = raw.strip_prefix(['v', 'V']).unwrap_or(&raw);
+        (!version.is_empty()).then(|| version.to_string())
+    }
+
+    /// 解析请求对应的版本：Ok(None) 表示未携带版本且无默认版本，Err 为未配置的版本号
+    pub fn resolve(&self, path: &str, headers: &HeaderMap) -> Result<Option<(String, &ApiVersion)>, String> {
+        let Some(version) = self.extract(path, headers).or_else(|| self.default.clone()) else {
+            return Ok(None);
+        };
+        match self.versions.get(&version) {
+            Some(config) => Ok(Some((version, config))),
+            None => Err(version),
+        }
+    }
+
+    /// 校验错误
+    pub fn validation_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.versions.is_empty() {
+            errors.push("versioning.versions不能为空".to_string());
+        }
+        if let Some(name) = &self.header
+            && HeaderName::try_from(name.as_str()).is_err()
+        {
+            errors.push(format!("versioning.header不是合法的请求头: {}", name));
+        }
+        if let Some(default) = &self.default
+            && !self.versions.contains_key(default)
+        {
+            errors.push(format!("versioning.default未在versions中配置: {}", default));
+        }
+        for (name, version) in &self.versions {
+            if name.is_empty() || name.starts_with(['v', 'V']) {
+                errors.push(format!("versioning.versions的版本号不能为空或带 v 前缀: {}", name));
+            }
+            if version.upstream.iter().any(|u| u.trim().is_empty()) {
+                errors.push(format!("versioning.versions.{}.upstream不能包含空地址", name));
+            }
+            if let Some(sunset) = &version.sunset
+                && httpdate::parse_http_date(sunset).is_err()
+            {
+                errors.push(format!("versioning.versions.{}.sunset须为 HTTP-date 格式: {}", name, sunset));
+            }
+        }
+        errors
+    }
+}
+
+impl ApiVersion {
+    /// 为响应附加 Deprecation / Sunset / Link 头
+    pub fn apply_headers(&self, headers: &mut HeaderMap) {
+        if self.deprecated {
+            headers.insert(HeaderName::from_static("deprecation"), HeaderValue::from_static("true"));
+        }
+        if let Some(sunset) = self.sunset.as_deref().and_then(|s| HeaderValue::from_str(s).ok()) {
+            headers.insert(HeaderName::from_static("sunset"), sunset);
+        }
+        if let Some(link) = &self.link
+            && let Ok(value) = HeaderValue::from_str(&format!("<{}>; rel=\"deprecation\"", link))
+        {
+            headers.append(header::LINK, value);
+        }
+    }
+}
+
+// 形如 v1、v2、v1.1 的路径段
+fn is_path_version(seg: &str) -> bool {
+    seg.strip_prefix('v')
+        .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()) && rest.chars().all(|c| c.is_ascii_digit() || c == '.'))
+}
+
+// 媒体类型中的 version 参数
+fn accept_version(media: &str) -> Option<String> {
+    media.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("version")
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versioning(source: VersionSource) -> Versioning {
+        Versioning {
+            source,
+            versions: BTreeMap::from([
+                ("1".to_string(), ApiVersion { deprecated: true, ..Default::default() }),
+                ("2".to_string(), ApiVersion::default()),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_extract_from_path_header_and_accept() {
+        let headers = HeaderMap::new();
+        let path = versioning(VersionSource::Path);
+        assert_eq!(path.extract("/api/v2/users", &headers).as_deref(), Some("2"));
+        assert_eq!(path.extract("/api/users/video", &headers), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-version", "v1".parse().unwrap());
+        headers.insert(header::ACCEPT, "text/html, application/json; version=\"2\"".parse().unwrap());
+        assert_eq!(versioning(VersionSource::Header).extract("/", &headers).as_deref(), Some("1"));
+        assert_eq!(versioning(VersionSource::Accept).extract("/", &headers).as_deref(), Some("2"));
+    }
+
+    #[test]
+    fn test_resolve_and_deprecation_headers() {
+        let mut config = versioning(VersionSource::Path);
+        let headers = HeaderMap::new();
+        assert!(matches!(config.resolve("/api/users", &headers), Ok(None)));
+        assert_eq!(config.resolve("/api/v3/users", &headers).unwrap_err(), "3");
+
+        config.default = Some("1".to_string());
+        let (version, api) = config.resolve("/api/users", &headers).unwrap().unwrap();
+        assert_eq!(version, "1");
+
+        let mut response = HeaderMap::new();
+        ApiVersion { sunset: Some("Wed, 31 Dec 2025 23:59:59 GMT".to_string()), ..api.clone() }.apply_headers(&mut response);
+        assert_eq!(response["deprecation"], "true");
+        assert_eq!(response["sunset"], "Wed, 31 Dec 2025 23:59:59 GMT");
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let mut config = versioning(VersionSource::Header);
+        assert!(config.validation_errors().is_empty());
+        config.default = Some("9".to_string());
+        config.versions.get_mut("1").unwrap().sunset = Some("next year".to_string());
+        assert_eq!(config.validation_errors().len(), 2);
+    }
+}
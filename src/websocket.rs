@@ -0,0 +1,359 @@
+use governor::{
+    Quota, RateLimiter,
+    clock::DefaultClock,
+    state::{InMemoryState, NotKeyed},
+};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::num::NonZeroU32;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+
+use crate::metrics::WEBSOCKET_CLOSED;
+
+/// 路由级 WebSocket 限制：按帧解析客户端发往上游的数据，超限时以 Close 帧关闭连接
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebSocketLimits {
+    // 单帧负载上限（字节）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_frame_bytes: Option<u64>,
+    // 单条消息（含分片）负载上限（字节）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_message_bytes: Option<u64>,
+    // 每个连接每秒允许客户端发送的消息数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub messages_per_sec: Option<u32>,
+    // 双向都没有数据的最长时间，超过后关闭连接
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_timeout_secs: Option<u64>,
+}
+
+impl WebSocketLimits {
+    pub fn validation_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (field, value) in [
+            ("websocket.max_frame_bytes", self.max_frame_bytes),
+            ("websocket.max_message_bytes", self.max_message_bytes),
+            ("websocket.messages_per_sec", self.messages_per_sec.map(u64::from)),
+            ("websocket.idle_timeout_secs", self.idle_timeout_secs),
+        ] {
+            if value == Some(0) {
+                errors.push(format!("{}必须大于0", field));
+            }
+        }
+        errors
+    }
+}
+
+/// 关闭连接的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    FrameTooLarge,
+    MessageTooLarge,
+    RateLimited,
+    IdleTimeout,
+}
+
+impl Violation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Violation::FrameTooLarge => "frame_too_large",
+            Violation::MessageTooLarge => "message_too_large",
+            Violation::RateLimited => "rate_limited",
+            Violation::IdleTimeout => "idle_timeout",
+        }
+    }
+
+    // RFC 6455 关闭码：1009 消息过大，1008 违反策略，1001 离开
+    fn close_code(&self) -> u16 {
+        match self {
+            Violation::FrameTooLarge | Violation::MessageTooLarge => 1009,
+            Violation::RateLimited => 1008,
+            Violation::IdleTimeout => 1001,
+        }
+    }
+}
+
+// ===== 帧头解析与限制检查 =====
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FrameHeader {
+    fin: bool,
+    opcode: u8,
+    len: u64,
+}
+
+/// 帧头的长度：前两个字节之后还需读取的扩展长度与掩码字节数
+fn header_extra(second: u8) -> usize {
+    let ext = match second & 0x7f {
+        126 => 2,
+        127 => 8,
+        _ => 0,
+    };
+    ext + if second & 0x80 != 0 { 4 } else { 0 }
+}
+
+fn parse_header(head: &[u8]) -> FrameHeader {
+    let len = match head[1] & 0x7f {
+        126 => u16::from_be_bytes([head[2], head[3]]) as u64,
+        127 => u64::from_be_bytes(head[2..10].try_into().unwrap_or_default()),
+        len => len as u64,
+    };
+    FrameHeader { fin: head[0] & 0x80 != 0, opcode: head[0] & 0x0f, len }
+}
+
+type DirectLimiter = RateLimiter<NotKeyed, InMemoryState, DefaultClock>;
+
+struct Inspector<'a> {
+    limits: &'a WebSocketLimits,
+    limiter: Option<DirectLimiter>,
+    // 当前消息已收到的负载字节数
+    message_bytes: u64,
+}
+
+impl<'a> Inspector<'a> {
+    fn new(limits: &'a WebSocketLimits) -> Self {
+        let limiter = limits
+            .messages_per_sec
+            .and_then(NonZeroU32::new)
+            .map(|n| RateLimiter::direct(Quota::per_second(n)));
+        Self { limits, limiter, message_bytes: 0 }
+    }
+
+    fn inspect(&mut self, frame: FrameHeader) -> Result<(), Violation> {
+        if self.limits.max_frame_bytes.is_some_and(|max| frame.len > max) {
+            return Err(Violation::FrameTooLarge);
+        }
+        // 控制帧（opcode >= 8）不属于消息，也不打断分片
+        if frame.opcode >= 8 {
+            return Ok(());
+        }
+        if frame.opcode != 0 {
+            self.message_bytes = 0;
+        }
+        self.message_bytes += frame.len;
+        if self.limits.max_message_bytes.is_some_and(|max| self.message_bytes > max) {
+            return Err(Violation::MessageTooLarge);
+        }
+        if frame.fin && self.limiter.as_ref().is_some_and(|l| l.check().is_err()) {
+            return Err(Violation::RateLimited);
+        }
+        Ok(())
+    }
+}
+
+/// Close 帧：发往上游的帧必须带掩码
+fn close_frame(code: u16, reason: &str, masked: bool) -> Vec<u8> {
+    let mut payload = code.to_be_bytes().to_vec();
+    payload.extend_from_slice(&reason.as_bytes()[..reason.len().min(123)]);
+    let mut frame = vec![0x88, payload.len() as u8];
+    if masked {
+        let mask: [u8; 4] = rand::random();
+        frame[1] |= 0x80;
+        frame.extend_from_slice(&mask);
+        payload.iter_mut().enumerate().for_each(|(i, b)| *b ^= mask[i % 4]);
+    }
+    frame.extend_from_slice(&payload);
+    frame
+}
+
+// 最近一次收发数据的时间与双向字节数
+struct Activity {
+    start: Instant,
+    last_ms: AtomicU64,
+    upload: AtomicU64,
+    download: AtomicU64,
+}
+
+impl Activity {
+    fn touch(&self) {
+        self.last_ms.store(self.start.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.start.elapsed().saturating_sub(Duration::from_millis(self.last_ms.load(Ordering::Relaxed)))
+    }
+}
+
+enum Stop {
+    Closed,
+    Io(io::Error),
+    Violation(Violation),
+}
+
+impl From<io::Error> for Stop {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => Stop::Closed,
+            _ => Stop::Io(err),
+        }
+    }
+}
+
+// 客户端 -> 上游：逐帧检查帧头，负载原样转发
+async fn inspect_frames<R, W>(reader: &mut R, writer: &mut W, limits: &WebSocketLimits, activity: &Activity) -> Stop
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut inspector = Inspector::new(limits);
+    let mut head = [0u8; 14];
+    loop {
+        let result: Result<(), Stop> = async {
+            reader.read_exact(&mut head[..2]).await?;
+            let size = 2 + header_extra(head[1]);
+            reader.read_exact(&mut head[2..size]).await?;
+            activity.touch();
+            let frame = parse_header(&head);
+            inspector.inspect(frame).map_err(Stop::Violation)?;
+            writer.write_all(&head[..size]).await?;
+            let copied = tokio::io::copy(&mut (&mut *reader).take(frame.len), writer).await?;
+            activity.upload.fetch_add(size as u64 + copied, Ordering::Relaxed);
+            if copied < frame.len {
+                return Err(Stop::Closed);
+            }
+            activity.touch();
+            Ok(())
+        }
+        .await;
+        if let Err(stop) = result {
+            return stop;
+        }
+    }
+}
+
+// 上游 -> 客户端：原样拷贝
+async fn pipe<R, W>(reader: &mut R, writer: &mut W, activity: &Activity) -> Stop
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = vec![0u8; 16 * 1024];
+    loop {
+        let n = match reader.read(&mut buf).await {
+            Ok(0) => return Stop::Closed,
+            Ok(n) => n,
+            Err(err) => return Stop::Io(err),
+        };
+        if let Err(err) = writer.write_all(&buf[..n]).await {
+            return Stop::Io(err);
+        }
+        activity.download.fetch_add(n as u64, Ordering::Relaxed);
+        activity.touch();
+    }
+}
+
+async fn idle_timeout(activity: &Activity, timeout: Option<Duration>) -> Stop {
+    let Some(timeout) = timeout else {
+        return std::future::pending().await;
+    };
+    loop {
+        let idle = activity.idle_for();
+        if idle >= timeout {
+            return Stop::Violation(Violation::IdleTimeout);
+        }
+        tokio::time::sleep(timeout - idle).await;
+    }
+}
+
+/// 在客户端与上游之间转发 WebSocket 连接并执行限制；超限时向两端发送 Close 帧后断开。
+/// 返回 (客户端发往上游, 上游发往客户端) 的字节数
+pub async fn relay<C, U>(client: C, upstream: U, limits: &WebSocketLimits, route: &str) -> (u64, u64)
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+    U: AsyncRead + AsyncWrite + Unpin,
+{
+    let (client_read, mut client_write) = tokio::io::split(client);
+    let (mut upstream_read, mut upstream_write) = tokio::io::split(upstream);
+    let mut client_read = BufReader::new(client_read);
+    let activity = Activity {
+        start: Instant::now(),
+        last_ms: AtomicU64::new(0),
+        upload: AtomicU64::new(0),
+        download: AtomicU64::new(0),
+    };
+
+    let stop = tokio::select! {
+        stop = inspect_frames(&mut client_read, &mut upstream_write, limits, &activity) => stop,
+        stop = pipe(&mut upstream_read, &mut client_write, &activity) => stop,
+        stop = idle_timeout(&activity, limits.idle_timeout_secs.map(Duration::from_secs)) => stop,
+    };
+
+    match stop {
+        Stop::Violation(violation) => {
+            WEBSOCKET_CLOSED.with_label_values(&[route, violation.as_str()]).inc();
+            tracing::info!(route = %route, reason = violation.as_str(), "WebSocket 连接超出限制，已关闭");
+            let code = violation.close_code();
+            let _ = client_write.write_all(&close_frame(code, violation.as_str(), false)).await;
+            let _ = upstream_write.write_all(&close_frame(code, violation.as_str(), true)).await;
+        }
+        Stop::Io(err) => tracing::debug!(route = %route, "WebSocket 连接异常关闭: {}", err),
+        Stop::Closed => tracing::debug!(route = %route, "WebSocket 连接关闭"),
+    }
+    let _ = client_write.shutdown().await;
+    let _ = upstream_write.shutdown().await;
+    (activity.upload.load(Ordering::Relaxed), activity.download.load(Ordering::Relaxed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 客户端发出的带掩码数据帧
+    fn client_frame(fin: bool, opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![(if fin { 0x80 } else { 0 }) | opcode];
+        match payload.len() {
+            len @ 0..=125 => frame.push(0x80 | len as u8),
+            len => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(&[0; 4]);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn test_inspect_limits() {
+        let limits = WebSocketLimits { max_frame_bytes: Some(100), max_message_bytes: Some(150), messages_per_sec: Some(2), ..Default::default() };
+        let mut inspector = Inspector::new(&limits);
+        let frame = |fin, opcode, len| FrameHeader { fin, opcode, len };
+
+        assert_eq!(inspector.inspect(frame(true, 1, 101)), Err(Violation::FrameTooLarge));
+        // 分片消息累计负载
+        assert_eq!(inspector.inspect(frame(false, 2, 100)), Ok(()));
+        assert_eq!(inspector.inspect(frame(false, 9, 10)), Ok(()));
+        assert_eq!(inspector.inspect(frame(true, 0, 60)), Err(Violation::MessageTooLarge));
+        // 每秒 2 条消息
+        assert_eq!(inspector.inspect(frame(true, 1, 10)), Ok(()));
+        assert_eq!(inspector.inspect(frame(true, 1, 10)), Ok(()));
+        assert_eq!(inspector.inspect(frame(true, 1, 10)), Err(Violation::RateLimited));
+
+        let head = client_frame(true, 2, &[7; 300]);
+        assert_eq!(header_extra(head[1]), 6);
+        assert_eq!(parse_header(&head), FrameHeader { fin: true, opcode: 2, len: 300 });
+        assert_eq!(WebSocketLimits { idle_timeout_secs: Some(0), ..Default::default() }.validation_errors().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_relay_closes_on_oversized_frame() {
+        let (client, mut client_peer) = tokio::io::duplex(4096);
+        let (upstream, mut upstream_peer) = tokio::io::duplex(4096);
+        let limits = WebSocketLimits { max_frame_bytes: Some(8), ..Default::default() };
+        let relay = tokio::spawn(async move { relay(client, upstream, &limits, "ws-test").await });
+
+        client_peer.write_all(&client_frame(true, 1, b"hello")).await.unwrap();
+        let mut forwarded = vec![0u8; 11];
+        upstream_peer.read_exact(&mut forwarded).await.unwrap();
+        assert_eq!(forwarded, client_frame(true, 1, b"hello"));
+
+        client_peer.write_all(&client_frame(true, 1, b"too large!")).await.unwrap();
+        let mut closed = Vec::new();
+        client_peer.read_to_end(&mut closed).await.unwrap();
+        assert_eq!(&closed[..4], &[0x88, 17, 0x03, 0xf1]);
+        assert_eq!(relay.await.unwrap(), (11, 0));
+        assert_eq!(WEBSOCKET_CLOSED.with_label_values(&["ws-test", "frame_too_large"]).get(), 1);
+    }
+}
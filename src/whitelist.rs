@@ -0,0 +1,136 @@
+use axum::http::Method;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::config::RouteRule;
+use crate::path_matcher::RoutePattern;
+
+/// 白名单项：`/path` 对任意方法生效，`GET /path` 或 `GET,HEAD /path` 只对所列方法生效
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Entry<'a> {
+    // None 表示任意方法
+    pub methods: Option<Vec<&'a str>>,
+    pub path: &'a str,
+}
+
+impl<'a> Entry<'a> {
+    pub fn parse(item: &'a str) -> Self {
+        let item = item.trim();
+        match item.split_once(char::is_whitespace) {
+            Some((methods, path)) if !methods.starts_with('/') => {
+                let methods: Vec<&str> = methods.split(',').map(str::trim).filter(|m| !m.is_empty()).collect();
+                let any = methods.contains(&"*");
+                Entry { methods: (!any).then_some(methods), path: path.trim() }
+            }
+            _ => Entry { methods: None, path: item },
+        }
+    }
+
+    fn matches_method(&self, method: &Method) -> bool {
+        self.methods
+            .as_ref()
+            .is_none_or(|methods| methods.iter().any(|m| m.eq_ignore_ascii_case(method.as_str())))
+    }
+
+    fn matches_path(&self, match_path: &str) -> bool {
+        let p = self.path;
+        if p.contains('{') || p.contains('*') || p.contains('?') {
+            RoutePattern::from_pattern(p).map(|rp| rp.matches(match_path)).unwrap_or(false)
+        } else {
+            match_path == p || match_path.starts_with(&format!("{}/", p))
+        }
+    }
+}
+
+/// 请求是否命中路由的白名单（任意一项的方法与路径同时命中即可）
+pub fn hit(rule: &RouteRule, method: &Method, match_path: &str) -> bool {
+    rule.whitelist
+        .iter()
+        .flatten()
+        .map(|item| Entry::parse(item))
+        .any(|entry| entry.matches_method(method) && entry.matches_path(match_path))
+}
+
+/// 经白名单免鉴权的请求使用的更严格限制，未配置的项沿用路由本身的设置
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct WhitelistLimits {
+    // 上游首字节与总时长超时（毫秒），取与路由自身超时中较小的值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    // 请求体大小上限，取与 max_request_body_bytes 中较小的值；配置后请求体总是缓冲读取
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_request_body_bytes: Option<usize>,
+    // 每个客户端 IP 在该路由白名单路径上的每秒请求数，在全局限流之外额外计算
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_qps: Option<u32>,
+}
+
+impl WhitelistLimits {
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout_ms.map(Duration::from_millis)
+    }
+
+    pub fn validation_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (field, value) in [
+            ("timeout_ms", self.timeout_ms),
+            ("max_request_body_bytes", self.max_request_body_bytes.map(|v| v as u64)),
+            ("client_qps", self.client_qps.map(u64::from)),
+        ] {
+            if value == Some(0) {
+                errors.push(format!("whitelist_limits.{}必须大于0", field));
+            }
+        }
+        errors
+    }
+}
+
+/// 校验白名单项的方法名与路径模式
+pub fn validation_errors(whitelist: &[String]) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (i, item) in whitelist.iter().enumerate() {
+        let entry = Entry::parse(item);
+        if !entry.path.starts_with('/') {
+            errors.push(format!("whitelist[{}]的路径必须以 / 开头: {}", i, item));
+        } else if (entry.path.contains('{') || entry.path.contains('*') || entry.path.contains('?'))
+            && let Err(err) = RoutePattern::from_pattern(entry.path)
+        {
+            errors.push(format!("whitelist[{}]模式无法编译: {}", i, err));
+        }
+        for method in entry.methods.iter().flatten() {
+            if !method.bytes().all(|b| b.is_ascii_alphabetic()) {
+                errors.push(format!("whitelist[{}]包含非法的方法名: {}", i, method));
+            }
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entry() {
+        assert_eq!(Entry::parse("/health"), Entry { methods: None, path: "/health" });
+        assert_eq!(Entry::parse("GET,HEAD  /public/**"), Entry { methods: Some(vec!["GET", "HEAD"]), path: "/public/**" });
+        assert_eq!(Entry::parse("* /docs"), Entry { methods: None, path: "/docs" });
+    }
+
+    #[test]
+    fn test_method_aware_hit() {
+        let rule = RouteRule {
+            prefix: vec!["/public/**".to_string()],
+            whitelist: Some(vec!["get /public/**".to_string(), "/public/ping".to_string()]),
+            ..Default::default()
+        };
+        assert!(hit(&rule, &Method::GET, "/public/articles/1"));
+        assert!(!hit(&rule, &Method::POST, "/public/articles"));
+        assert!(hit(&rule, &Method::POST, "/public/ping"));
+        assert!(!hit(&RouteRule::default(), &Method::GET, "/public/articles"));
+
+        let bad = vec!["public".to_string(), "G3T /x".to_string(), "/ok".to_string()];
+        assert_eq!(validation_errors(&bad).len(), 2);
+    }
+}